@@ -0,0 +1,109 @@
+//! Criterion benchmarks for the hot paths of the ray tracer
+//!
+//! Run with `cargo bench --features bench`. Scene and mesh paths are relative to
+//! the crate root, which is where cargo runs benchmarks from.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rtrace::ray::{Intersectable, MeshObject, Ray};
+use rtrace::scene::{Color, Point, Vec3};
+use rtrace::{AntiAliasingMode, Mesh, Renderer, Scene};
+
+const TRIANGLE_STL: &str = "solid t\nfacet normal 0 0 1\nouter loop\nvertex -1 -1 0\nvertex 1 -1 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+
+/// Rays fired from above the mesh through an evenly spaced grid over its bounds
+fn grid_rays(mesh: &Mesh, count: usize) -> Vec<Ray> {
+    let (min, max) = mesh.bounds();
+    let z = max.z + 1.0;
+    let mut rays = Vec::with_capacity(count * count);
+    for j in 0..count {
+        for i in 0..count {
+            let x = min.x + (max.x - min.x) * (i as f64 + 0.5) / count as f64;
+            let y = min.y + (max.y - min.y) * (j as f64 + 0.5) / count as f64;
+            rays.push(Ray::new(Point::new(x, y, z), Vec3::new(0.0, 0.0, -1.0)));
+        }
+    }
+    rays
+}
+
+fn triangle_intersection(c: &mut Criterion) {
+    let mesh = Mesh::from_stl_bytes(TRIANGLE_STL.as_bytes()).unwrap();
+    let object = MeshObject::new_brute_force(mesh, Color::new(1.0, 1.0, 1.0), 0);
+    let hit_ray = Ray::new(Point::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let miss_ray = Ray::new(Point::new(5.0, 5.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+
+    let mut group = c.benchmark_group("triangle intersection");
+    group.bench_function("hit", |b| {
+        b.iter(|| object.hit(black_box(&hit_ray), 0.001, f64::INFINITY))
+    });
+    group.bench_function("miss", |b| {
+        b.iter(|| object.hit(black_box(&miss_ray), 0.001, f64::INFINITY))
+    });
+    group.finish();
+}
+
+fn mesh_traversal(c: &mut Criterion) {
+    let mesh = Mesh::from_stl_file("examples/plus.stl").unwrap();
+    let rays = grid_rays(&mesh, 16);
+    let color = Color::new(1.0, 1.0, 1.0);
+    let kdtree = MeshObject::new(mesh.clone(), color, 0);
+    let brute_force = MeshObject::new_brute_force(mesh, color, 0);
+
+    let mut group = c.benchmark_group("mesh traversal");
+    for (name, object) in [("kd-tree", &kdtree), ("brute force", &brute_force)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                rays.iter()
+                    .filter(|ray| object.hit(ray, 0.001, f64::INFINITY).is_some())
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn sampling_modes(c: &mut Criterion) {
+    let scene = Scene::from_json_file("doc/scenes/sampling-antialiasing.json").unwrap();
+
+    let mut group = c.benchmark_group("sampling");
+    group.sample_size(10);
+    for (name, mode, samples) in [
+        ("quincunx", AntiAliasingMode::Quincunx, 1),
+        ("stochastic x4", AntiAliasingMode::Stochastic, 4),
+        ("no-jitter", AntiAliasingMode::NoJitter, 1),
+    ] {
+        let mut renderer = Renderer::new(64, 64);
+        renderer.anti_aliasing_mode = mode;
+        renderer.samples = samples;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(name),
+            &renderer,
+            |b, renderer| b.iter(|| renderer.render(&scene).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn full_render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("full render");
+    group.sample_size(10);
+    for path in [
+        "doc/scenes/camera-perspective.json",
+        "doc/scenes/object-mesh.json",
+    ] {
+        let scene = Scene::from_json_file(path).unwrap();
+        let renderer = Renderer::new(160, 120);
+        group.bench_with_input(BenchmarkId::from_parameter(path), &scene, |b, scene| {
+            b.iter(|| renderer.render(scene).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    triangle_intersection,
+    mesh_traversal,
+    sampling_modes,
+    full_render
+);
+criterion_main!(benches);
@@ -0,0 +1,10 @@
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+        .expect("cbindgen.toml should be valid");
+    cbindgen::generate_with_config(&crate_dir, config)
+        .expect("C header should generate from src/lib.rs")
+        .write_to_file(format!("{}/include/rtrace.h", crate_dir));
+}
@@ -0,0 +1,122 @@
+#ifndef RTRACE_H
+#define RTRACE_H
+
+/* Generated by cbindgen from bindings/c/src/lib.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Outcome of a call
+typedef enum RtraceStatus {
+  RTRACE_STATUS_OK = 0,
+  // A pointer was null, a string wasn't UTF-8, or the scene or mesh didn't parse
+  RTRACE_STATUS_INVALID_ARGUMENT = 1,
+  // The scene loaded but couldn't be rendered or encoded
+  RTRACE_STATUS_RENDER_FAILED = 2,
+  // The renderer panicked, which is a bug in rtrace
+  RTRACE_STATUS_PANIC = 3,
+} RtraceStatus;
+
+// Packed 8-bit RGB pixels, row by row from the top left
+typedef struct RtraceImage {
+  uint8_t *pixels;
+  // Bytes in `pixels`: width × height × 3
+  size_t len;
+  uint32_t width;
+  uint32_t height;
+} RtraceImage;
+
+// Bytes of an encoded image file
+typedef struct RtraceBuffer {
+  uint8_t *data;
+  size_t len;
+} RtraceBuffer;
+
+// Triangles of a parsed mesh
+typedef struct RtraceMesh {
+  // Nine coordinates per triangle: x, y and z of each of its three vertices
+  double *vertices;
+  // Three coordinates per triangle: its unit normal
+  double *normals;
+  size_t triangle_count;
+} RtraceMesh;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message of the last call on this thread that failed, or NULL if none has
+//
+// The string is owned by rtrace and stays valid until another call on this thread
+// fails.
+const char *rtrace_last_error(void);
+
+// Version of the library, as a static string
+const char *rtrace_version(void);
+
+// Render a scene from JSON into raw RGB pixels
+//
+// `size` is the image diagonal in pixels, split between width and height by the
+// camera's aspect ratio. Relative mesh paths are found from the current directory.
+// Sampling follows the scene's `render_settings`.
+//
+// # Safety
+//
+// `scene_json` must be a NUL-terminated string and `image` must point to writable
+// memory for an `RtraceImage`. On failure `*image` is left empty.
+enum RtraceStatus rtrace_render_rgb(const char *scene_json,
+                                    uint32_t size,
+                                    struct RtraceImage *image);
+
+// Render a scene from JSON into an encoded image file held in memory
+//
+// `format` is a file extension such as "png", "jpg" or "exr", or NULL for PNG.
+// Otherwise as `rtrace_render_rgb`.
+//
+// # Safety
+//
+// `scene_json` must be a NUL-terminated string, `format` NULL or a NUL-terminated
+// string, and `buffer` must point to writable memory for an `RtraceBuffer`. On
+// failure `*buffer` is left empty.
+enum RtraceStatus rtrace_render_encoded(const char *scene_json,
+                                        uint32_t size,
+                                        const char *format,
+                                        struct RtraceBuffer *buffer);
+
+// Parse the bytes of an ASCII or binary STL file into triangles
+//
+// # Safety
+//
+// `data` must point to `len` readable bytes and `mesh` to writable memory for an
+// `RtraceMesh`. On failure `*mesh` is left empty.
+enum RtraceStatus rtrace_parse_stl(const uint8_t *data, size_t len, struct RtraceMesh *mesh);
+
+// Free the pixels of an image from `rtrace_render_rgb` and empty it
+//
+// # Safety
+//
+// `image` must be NULL or point to an image filled in by rtrace and not yet freed.
+void rtrace_image_free(struct RtraceImage *image);
+
+// Free the bytes of a buffer from `rtrace_render_encoded` and empty it
+//
+// # Safety
+//
+// `buffer` must be NULL or point to a buffer filled in by rtrace and not yet freed.
+void rtrace_buffer_free(struct RtraceBuffer *buffer);
+
+// Free the arrays of a mesh from `rtrace_parse_stl` and empty it
+//
+// # Safety
+//
+// `mesh` must be NULL or point to a mesh filled in by rtrace and not yet freed.
+void rtrace_mesh_free(struct RtraceMesh *mesh);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* RTRACE_H */
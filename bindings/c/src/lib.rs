@@ -0,0 +1,428 @@
+//! C ABI for rtrace, for embedding the renderer in C, C++ and C# applications
+//!
+//! Every function returns an `RtraceStatus`, and on failure `rtrace_last_error`
+//! describes what went wrong. Images, buffers and meshes handed out belong to the
+//! caller until passed to their `_free` function. `include/rtrace.h` is generated from
+//! this file by the build script.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// Outcome of a call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtraceStatus {
+    Ok = 0,
+    /// A pointer was null, a string wasn't UTF-8, or the scene or mesh didn't parse
+    InvalidArgument = 1,
+    /// The scene loaded but couldn't be rendered or encoded
+    RenderFailed = 2,
+    /// The renderer panicked, which is a bug in rtrace
+    Panic = 3,
+}
+
+/// Packed 8-bit RGB pixels, row by row from the top left
+#[repr(C)]
+#[derive(Debug)]
+pub struct RtraceImage {
+    pub pixels: *mut u8,
+    /// Bytes in `pixels`: width × height × 3
+    pub len: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Bytes of an encoded image file
+#[repr(C)]
+#[derive(Debug)]
+pub struct RtraceBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Triangles of a parsed mesh
+#[repr(C)]
+#[derive(Debug)]
+pub struct RtraceMesh {
+    /// Nine coordinates per triangle: x, y and z of each of its three vertices
+    pub vertices: *mut f64,
+    /// Three coordinates per triangle: its unit normal
+    pub normals: *mut f64,
+    pub triangle_count: usize,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Message of the last call on this thread that failed, or NULL if none has
+///
+/// The string is owned by rtrace and stays valid until another call on this thread
+/// fails.
+#[no_mangle]
+pub extern "C" fn rtrace_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Version of the library, as a static string
+#[no_mangle]
+pub extern "C" fn rtrace_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Render a scene from JSON into raw RGB pixels
+///
+/// `size` is the image diagonal in pixels, split between width and height by the
+/// camera's aspect ratio. Relative mesh paths are found from the current directory.
+/// Sampling follows the scene's `render_settings`.
+///
+/// # Safety
+///
+/// `scene_json` must be a NUL-terminated string and `image` must point to writable
+/// memory for an `RtraceImage`. On failure `*image` is left empty.
+#[no_mangle]
+pub unsafe extern "C" fn rtrace_render_rgb(
+    scene_json: *const c_char,
+    size: u32,
+    image: *mut RtraceImage,
+) -> RtraceStatus {
+    let Some(image) = image.as_mut() else {
+        return fail(RtraceStatus::InvalidArgument, "image is NULL".to_string());
+    };
+    *image = RtraceImage {
+        pixels: ptr::null_mut(),
+        len: 0,
+        width: 0,
+        height: 0,
+    };
+    guard(|| {
+        let (scene, renderer) = prepare(str_arg(scene_json, "scene_json")?, size)?;
+        let pixels = (renderer.render_rgb(&scene))
+            .map_err(|e| (RtraceStatus::RenderFailed, e.to_string()))?;
+        let (pixels, len) = into_raw(pixels);
+        *image = RtraceImage {
+            pixels,
+            len,
+            width: renderer.width,
+            height: renderer.height,
+        };
+        Ok(())
+    })
+}
+
+/// Render a scene from JSON into an encoded image file held in memory
+///
+/// `format` is a file extension such as "png", "jpg" or "exr", or NULL for PNG.
+/// Otherwise as `rtrace_render_rgb`.
+///
+/// # Safety
+///
+/// `scene_json` must be a NUL-terminated string, `format` NULL or a NUL-terminated
+/// string, and `buffer` must point to writable memory for an `RtraceBuffer`. On
+/// failure `*buffer` is left empty.
+#[no_mangle]
+pub unsafe extern "C" fn rtrace_render_encoded(
+    scene_json: *const c_char,
+    size: u32,
+    format: *const c_char,
+    buffer: *mut RtraceBuffer,
+) -> RtraceStatus {
+    let Some(buffer) = buffer.as_mut() else {
+        return fail(RtraceStatus::InvalidArgument, "buffer is NULL".to_string());
+    };
+    *buffer = RtraceBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+    guard(|| {
+        let format = match format.is_null() {
+            true => "png",
+            false => str_arg(format, "format")?,
+        };
+        let mut sink = rtrace::WriterSink::for_extension(Vec::new(), format)
+            .map_err(|e| (RtraceStatus::InvalidArgument, e))?;
+        let (scene, renderer) = prepare(str_arg(scene_json, "scene_json")?, size)?;
+        (renderer.render_to_sink(&scene, &mut sink))
+            .map_err(|e| (RtraceStatus::RenderFailed, e.to_string()))?;
+        let (data, len) = into_raw(sink.into_inner());
+        *buffer = RtraceBuffer { data, len };
+        Ok(())
+    })
+}
+
+/// Parse the bytes of an ASCII or binary STL file into triangles
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes and `mesh` to writable memory for an
+/// `RtraceMesh`. On failure `*mesh` is left empty.
+#[no_mangle]
+pub unsafe extern "C" fn rtrace_parse_stl(
+    data: *const u8,
+    len: usize,
+    mesh: *mut RtraceMesh,
+) -> RtraceStatus {
+    let Some(mesh) = mesh.as_mut() else {
+        return fail(RtraceStatus::InvalidArgument, "mesh is NULL".to_string());
+    };
+    *mesh = RtraceMesh {
+        vertices: ptr::null_mut(),
+        normals: ptr::null_mut(),
+        triangle_count: 0,
+    };
+    guard(|| {
+        if data.is_null() {
+            return Err((RtraceStatus::InvalidArgument, "data is NULL".to_string()));
+        }
+        let bytes = std::slice::from_raw_parts(data, len);
+        let parsed = rtrace::mesh::Mesh::from_stl_bytes(bytes)
+            .map_err(|e| (RtraceStatus::InvalidArgument, e.to_string()))?;
+        let vertices = (parsed.triangles.iter())
+            .flat_map(|triangle| triangle.vertices.iter().flat_map(|v| v.coords.iter()))
+            .copied()
+            .collect();
+        let normals = (parsed.triangles.iter())
+            .flat_map(|triangle| triangle.normal.iter().copied())
+            .collect();
+        *mesh = RtraceMesh {
+            vertices: into_raw(vertices).0,
+            normals: into_raw(normals).0,
+            triangle_count: parsed.triangles.len(),
+        };
+        Ok(())
+    })
+}
+
+/// Free the pixels of an image from `rtrace_render_rgb` and empty it
+///
+/// # Safety
+///
+/// `image` must be NULL or point to an image filled in by rtrace and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rtrace_image_free(image: *mut RtraceImage) {
+    if let Some(image) = image.as_mut() {
+        free_raw(image.pixels, image.len);
+        image.pixels = ptr::null_mut();
+        image.len = 0;
+    }
+}
+
+/// Free the bytes of a buffer from `rtrace_render_encoded` and empty it
+///
+/// # Safety
+///
+/// `buffer` must be NULL or point to a buffer filled in by rtrace and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rtrace_buffer_free(buffer: *mut RtraceBuffer) {
+    if let Some(buffer) = buffer.as_mut() {
+        free_raw(buffer.data, buffer.len);
+        buffer.data = ptr::null_mut();
+        buffer.len = 0;
+    }
+}
+
+/// Free the arrays of a mesh from `rtrace_parse_stl` and empty it
+///
+/// # Safety
+///
+/// `mesh` must be NULL or point to a mesh filled in by rtrace and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn rtrace_mesh_free(mesh: *mut RtraceMesh) {
+    if let Some(mesh) = mesh.as_mut() {
+        free_raw(mesh.vertices, mesh.triangle_count * 9);
+        free_raw(mesh.normals, mesh.triangle_count * 3);
+        mesh.vertices = ptr::null_mut();
+        mesh.normals = ptr::null_mut();
+        mesh.triangle_count = 0;
+    }
+}
+
+type Failure = (RtraceStatus, String);
+
+/// Run `f`, recording its error or panic for `rtrace_last_error`
+fn guard(f: impl FnOnce() -> Result<(), Failure>) -> RtraceStatus {
+    let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = (payload.downcast_ref::<&str>().map(|s| s.to_string()))
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err((RtraceStatus::Panic, format!("rtrace panicked: {}", message)))
+    });
+    match result {
+        Ok(()) => RtraceStatus::Ok,
+        Err((status, message)) => fail(status, message),
+    }
+}
+
+fn fail(status: RtraceStatus, message: String) -> RtraceStatus {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+    status
+}
+
+/// The UTF-8 string at `pointer`, an argument called `name`
+///
+/// # Safety
+///
+/// `pointer` must be NULL or a NUL-terminated string that outlives `'a`.
+unsafe fn str_arg<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if pointer.is_null() {
+        return Err((RtraceStatus::InvalidArgument, format!("{} is NULL", name)));
+    }
+    (CStr::from_ptr(pointer).to_str()).map_err(|_| {
+        (
+            RtraceStatus::InvalidArgument,
+            format!("{} is not UTF-8", name),
+        )
+    })
+}
+
+/// Scene parsed from `scene_json` and a renderer for an image `size` pixels across
+/// its diagonal, built by `RenderOptions` from the scene's settings
+fn prepare(scene_json: &str, size: u32) -> Result<(rtrace::Scene, rtrace::Renderer), Failure> {
+    let scene = rtrace::Scene::from_json_str(scene_json).map_err(|e| {
+        let message = format!("Failed to parse scene JSON: {}", e);
+        (RtraceStatus::InvalidArgument, message)
+    })?;
+    let options = rtrace::RenderOptions {
+        size: Some(size),
+        ..Default::default()
+    };
+    let renderer = (options.renderer(&scene)).map_err(|e| (RtraceStatus::InvalidArgument, e))?;
+    Ok((scene, renderer))
+}
+
+/// Hand `values` over to C as a pointer and length
+fn into_raw<T>(values: Vec<T>) -> (*mut T, usize) {
+    let len = values.len();
+    (Box::into_raw(values.into_boxed_slice()).cast(), len)
+}
+
+/// Free `len` values handed over by `into_raw`
+///
+/// # Safety
+///
+/// `pointer` must be NULL or have come from `into_raw` with the same `len`.
+unsafe fn free_raw<T>(pointer: *mut T, len: usize) {
+    if !pointer.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(pointer, len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r##"{
+        "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 2},
+        "objects": [
+            {"kind": "sphere", "center": [0, 0, 0], "radius": 0.8,
+             "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}
+        ],
+        "lights": [{"position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1.0}],
+        "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+    }"##;
+
+    fn last_error() -> String {
+        let message = rtrace_last_error();
+        assert!(!message.is_null());
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_render_rgb_and_encoded() {
+        let json = CString::new(SCENE).unwrap();
+        let mut image = RtraceImage {
+            pixels: ptr::null_mut(),
+            len: 0,
+            width: 0,
+            height: 0,
+        };
+        let status = unsafe { rtrace_render_rgb(json.as_ptr(), 45, &mut image) };
+        assert_eq!(status, RtraceStatus::Ok);
+        assert_eq!((image.width, image.height), (40, 20));
+        assert_eq!(image.len, 40 * 20 * 3);
+        // The sphere fills the middle of the image
+        let middle = unsafe { std::slice::from_raw_parts(image.pixels, image.len) };
+        let middle = &middle[(10 * 40 + 20) * 3..][..3];
+        assert!(middle[0] > 0 && middle[1] == 0 && middle[2] == 0);
+        unsafe { rtrace_image_free(&mut image) };
+        assert!(image.pixels.is_null() && image.len == 0);
+
+        let mut buffer = RtraceBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        let status = unsafe { rtrace_render_encoded(json.as_ptr(), 45, ptr::null(), &mut buffer) };
+        assert_eq!(status, RtraceStatus::Ok);
+        let png = unsafe { std::slice::from_raw_parts(buffer.data, buffer.len) };
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        unsafe { rtrace_buffer_free(&mut buffer) };
+        unsafe { rtrace_buffer_free(&mut buffer) };
+        assert!(buffer.data.is_null());
+
+        let format = CString::new("gif89").unwrap();
+        let status =
+            unsafe { rtrace_render_encoded(json.as_ptr(), 45, format.as_ptr(), &mut buffer) };
+        assert_eq!(status, RtraceStatus::InvalidArgument);
+        assert!(last_error().contains("gif89"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut image = RtraceImage {
+            pixels: ptr::null_mut(),
+            len: 0,
+            width: 0,
+            height: 0,
+        };
+        let status = unsafe { rtrace_render_rgb(ptr::null(), 45, &mut image) };
+        assert_eq!(status, RtraceStatus::InvalidArgument);
+        assert_eq!(last_error(), "scene_json is NULL");
+
+        let json = CString::new("{\"camera\": 1}").unwrap();
+        let status = unsafe { rtrace_render_rgb(json.as_ptr(), 45, &mut image) };
+        assert_eq!(status, RtraceStatus::InvalidArgument);
+        assert!(last_error().starts_with("Failed to parse scene JSON"));
+        assert!(image.pixels.is_null());
+
+        // Errors are kept per thread
+        let other = std::thread::spawn(|| rtrace_last_error().is_null());
+        assert!(other.join().unwrap());
+    }
+
+    #[test]
+    fn test_parse_stl() {
+        let stl = b"solid t
+facet normal 0 0 1
+outer loop
+vertex 0 0 0
+vertex 1 0 0
+vertex 0 1 0
+endloop
+endfacet
+endsolid t
+";
+        let mut mesh = RtraceMesh {
+            vertices: ptr::null_mut(),
+            normals: ptr::null_mut(),
+            triangle_count: 0,
+        };
+        let status = unsafe { rtrace_parse_stl(stl.as_ptr(), stl.len(), &mut mesh) };
+        assert_eq!(status, RtraceStatus::Ok);
+        assert_eq!(mesh.triangle_count, 1);
+        let vertices = unsafe { std::slice::from_raw_parts(mesh.vertices, 9) };
+        assert_eq!(vertices, [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        let normal = unsafe { std::slice::from_raw_parts(mesh.normals, 3) };
+        assert_eq!(normal, [0.0, 0.0, 1.0]);
+        unsafe { rtrace_mesh_free(&mut mesh) };
+        assert_eq!(mesh.triangle_count, 0);
+
+        let status = unsafe { rtrace_parse_stl(b"solid".as_ptr(), 3, &mut mesh) };
+        assert_eq!(status, RtraceStatus::InvalidArgument);
+        assert!(mesh.vertices.is_null());
+    }
+}
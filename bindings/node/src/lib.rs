@@ -1,5 +1,11 @@
-use napi::{Error, Result, Status};
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Env, Error, JsFunction, JsObject, JsUnknown, Result, Status, Task, ValueType};
 use napi_derive::napi;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 
 /// Returns a hello world message (Node.js binding)
 #[napi]
@@ -13,63 +19,327 @@ pub fn greet_with_name(name: String) -> String {
     format!("{}, {}", rtrace::hello_world(), name)
 }
 
-/// Render a scene from JSON string directly
-#[napi]
-pub fn render_scene(
-    scene_json: String,
-    output_path: String,
-    size: Option<u32>,
-) -> Result<String> {
-    let diagonal_size = size.unwrap_or(1000);
+/// Anti-aliasing sampling mode
+#[napi(string_enum)]
+pub enum AntiAliasing {
+    #[napi(value = "quincunx")]
+    Quincunx,
+    #[napi(value = "stochastic")]
+    Stochastic,
+    #[napi(value = "no-jitter")]
+    NoJitter,
+}
 
-    // Parse the JSON scene
-    let scene = rtrace::Scene::from_json_str(&scene_json).map_err(|e| {
-        Error::new(
+impl From<AntiAliasing> for rtrace::AntiAliasingMode {
+    fn from(mode: AntiAliasing) -> Self {
+        match mode {
+            AntiAliasing::Quincunx => rtrace::AntiAliasingMode::Quincunx,
+            AntiAliasing::Stochastic => rtrace::AntiAliasingMode::Stochastic,
+            AntiAliasing::NoJitter => rtrace::AntiAliasingMode::NoJitter,
+        }
+    }
+}
+
+/// Sampling options; unset fields fall back to the scene's `render_settings`
+#[napi(object)]
+pub struct SamplingOptions {
+    pub anti_aliasing: Option<AntiAliasing>,
+    pub samples: Option<u32>,
+}
+
+/// Library options for the functions taking an image diagonal and sampling options
+fn sized_options(size: Option<u32>, sampling: Option<SamplingOptions>) -> rtrace::RenderOptions {
+    let (anti_aliasing, samples) = match sampling {
+        Some(sampling) => (sampling.anti_aliasing.map(Into::into), sampling.samples),
+        None => (None, None),
+    };
+    rtrace::RenderOptions {
+        size,
+        anti_aliasing,
+        samples,
+        ..Default::default()
+    }
+}
+
+/// Caps applied to every render, for services rendering untrusted scenes
+///
+/// Unset fields are unlimited. Renders over a limit fail with an error naming it.
+#[napi(object)]
+#[derive(Clone, Copy)]
+pub struct RenderLimits {
+    /// Maximum width × height of the output image
+    pub max_pixels: Option<i64>,
+    /// Maximum number of scene objects
+    pub max_objects: Option<u32>,
+    /// Maximum number of triangles across all meshes
+    pub max_triangles: Option<u32>,
+    /// Maximum ray recursion depth (renders use at most this many bounces)
+    pub max_depth: Option<u32>,
+}
+
+static RENDER_LIMITS: Mutex<Option<RenderLimits>> = Mutex::new(None);
+
+/// Set the limits enforced by every later render in this process (`null` removes them)
+#[napi]
+pub fn set_render_limits(limits: Option<RenderLimits>) -> Result<()> {
+    if limits.and_then(|l| l.max_pixels).is_some_and(|max| max < 0) {
+        return Err(Error::new(
             Status::InvalidArg,
-            format!("Failed to parse scene JSON: {}", e),
-        )
-    })?;
+            "maxPixels must not be negative".to_string(),
+        ));
+    }
+    *RENDER_LIMITS.lock().unwrap_or_else(|e| e.into_inner()) = limits;
+    Ok(())
+}
 
-    // Compute pixel dimensions from diagonal size and camera aspect ratio
-    let camera_aspect_ratio = scene.camera.width / scene.camera.height;
-    let diagonal = diagonal_size as f64;
-    
-    // Using diagonal D and aspect ratio R = W/H:
-    // H = D / sqrt(R² + 1)  
-    // W = R * H
-    let height_f64 = diagonal / (camera_aspect_ratio * camera_aspect_ratio + 1.0).sqrt();
-    let width_f64 = camera_aspect_ratio * height_f64;
-    
-    let width = width_f64.round() as u32;
-    let height = height_f64.round() as u32;
-
-    // Create renderer with k-d tree enabled and multi-threading
-    let renderer = rtrace::Renderer::new(width, height);
-
-    // Render and save
-    renderer.render_to_file(&scene, &output_path).map_err(|e| {
-        Error::new(
-            Status::GenericFailure,
-            format!("Failed to render scene: {}", e),
-        )
-    })?;
+/// Renderer for `scene` built from `options`, within the limits from `setRenderLimits`
+fn renderer(scene: &rtrace::Scene, options: &rtrace::RenderOptions) -> Result<rtrace::Renderer> {
+    let renderer = options
+        .renderer(scene)
+        .map_err(|e| Error::new(Status::InvalidArg, e))?;
+    Ok(limited(renderer))
+}
 
-    Ok(format!(
-        "Successfully rendered {}×{} image (diagonal {}) to '{}' (multi-threaded)",
-        width, height, diagonal_size, output_path
-    ))
+/// Apply the limits from `setRenderLimits`, lowering the recursion depth to fit
+fn limited(mut renderer: rtrace::Renderer) -> rtrace::Renderer {
+    let Some(limits) = *RENDER_LIMITS.lock().unwrap_or_else(|e| e.into_inner()) else {
+        return renderer;
+    };
+    let limits = rtrace::RendererLimits {
+        max_pixels: limits.max_pixels.map(|max| max as u64),
+        max_objects: limits.max_objects.map(|max| max as usize),
+        max_triangles: limits.max_triangles.map(|max| max as usize),
+        max_depth: limits.max_depth.map(|max| max.min(i32::MAX as u32) as i32),
+    };
+    if let Some(max_depth) = limits.max_depth {
+        renderer.max_depth = renderer.max_depth.min(max_depth);
+    }
+    renderer.with_limits(limits)
+}
+
+/// Progress as the `{ phase, percent, completedPixels, totalPixels, elapsedMs, etaMs }`
+/// object that progress callbacks receive
+fn progress_event(env: &Env, progress: &rtrace::Progress) -> Result<JsObject> {
+    let phase = match progress.phase {
+        rtrace::RenderPhase::Setup => "setup",
+        rtrace::RenderPhase::Tracing => "tracing",
+        rtrace::RenderPhase::PostProcessing => "postProcessing",
+        rtrace::RenderPhase::Finished => "finished",
+    };
+    let mut event = env.create_object()?;
+    event.set_named_property("phase", phase)?;
+    event.set_named_property("percent", progress.percent())?;
+    event.set_named_property("completedPixels", progress.completed_pixels as u32)?;
+    event.set_named_property("totalPixels", progress.total_pixels as u32)?;
+    event.set_named_property("elapsedMs", progress.elapsed.as_secs_f64() * 1000.0)?;
+    match progress.eta {
+        Some(eta) => event.set_named_property("etaMs", eta.as_secs_f64() * 1000.0)?,
+        None => event.set_named_property("etaMs", env.get_null()?)?,
+    }
+    Ok(event)
+}
+
+/// Outline settings for `render`, in place of the scene's `outline` block
+///
+/// Unset fields take the same defaults as in a scene file; colors are hex strings.
+#[napi(object)]
+pub struct OutlineOptions {
+    /// `false` draws no outlines, even where the scene has them (default `true`)
+    pub enabled: Option<bool>,
+    pub color: Option<String>,
+    pub thickness: Option<f64>,
+    pub depth_weight: Option<f64>,
+    pub normal_weight: Option<f64>,
+    pub object_weight: Option<f64>,
+    pub threshold: Option<f64>,
+    pub use_8_neighbors: Option<bool>,
+    pub crease_angle: Option<f64>,
+    pub supersample: Option<u32>,
 }
 
-/// Render a scene from JSON string with specific thread count
+impl From<OutlineOptions> for rtrace::OutlineSettings {
+    fn from(options: OutlineOptions) -> Self {
+        let defaults = rtrace::OutlineSettings::default();
+        rtrace::OutlineSettings {
+            enabled: options.enabled.unwrap_or(true),
+            color: options.color.unwrap_or(defaults.color),
+            thickness: options.thickness.unwrap_or(defaults.thickness),
+            depth_weight: options.depth_weight.unwrap_or(defaults.depth_weight),
+            normal_weight: options.normal_weight.unwrap_or(defaults.normal_weight),
+            object_weight: options.object_weight.unwrap_or(defaults.object_weight),
+            threshold: options.threshold.unwrap_or(defaults.threshold),
+            use_8_neighbors: options.use_8_neighbors.unwrap_or(defaults.use_8_neighbors),
+            crease_angle: options.crease_angle,
+            objects: defaults.objects,
+            supersample: options.supersample.unwrap_or(defaults.supersample),
+        }
+    }
+}
+
+/// Where `render` writes the image and how it renders it
+///
+/// Unset fields fall back to the scene's `render_settings` and `outline`, then to the
+/// CLI's defaults: a 1000 pixel diagonal, all cores, k-d trees, quincunx sampling and
+/// seed 0. Give either `size` or `width` and `height`; one of those alone takes the
+/// other from the camera's aspect ratio.
+#[napi(object)]
+pub struct RenderOptions {
+    /// Image file to write; its extension picks the format (`.exr` and `.hdr` keep
+    /// linear float radiance)
+    pub output: String,
+    /// Image diagonal in pixels, the aspect ratio following the camera
+    pub size: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Worker threads (all cores if unset)
+    pub threads: Option<u32>,
+    /// `false` intersects meshes by brute force instead of through k-d trees
+    pub kdtree: Option<bool>,
+    pub anti_aliasing: Option<AntiAliasing>,
+    pub samples: Option<u32>,
+    pub seed: Option<i64>,
+    pub outline: Option<OutlineOptions>,
+    /// Maximum ray bounces for reflections and refraction
+    pub max_depth: Option<u32>,
+}
+
+impl RenderOptions {
+    /// The library's options, rejecting values JS numbers allow but renders don't
+    fn into_rtrace(self) -> Result<(String, rtrace::RenderOptions)> {
+        let seed = self
+            .seed
+            .map(|seed| {
+                u64::try_from(seed).map_err(|_| {
+                    Error::new(Status::InvalidArg, "seed must not be negative".to_string())
+                })
+            })
+            .transpose()?;
+        let options = rtrace::RenderOptions {
+            size: self.size,
+            width: self.width,
+            height: self.height,
+            threads: self.threads.map(|threads| threads as usize),
+            kdtree: self.kdtree,
+            anti_aliasing: self.anti_aliasing.map(Into::into),
+            samples: self.samples,
+            seed,
+            outline: self.outline.map(Into::into),
+            max_depth: self.max_depth.map(|max| max.min(i32::MAX as u32) as i32),
+        };
+        Ok((self.output, options))
+    }
+}
+
+/// Load a scene from JSON text, or from the file at `source` when it isn't an object
+fn load_scene(source: &str) -> Result<rtrace::Scene> {
+    if source.trim_start().starts_with('{') {
+        rtrace::Scene::from_json_str(source).map_err(|e| {
+            Error::new(
+                Status::InvalidArg,
+                format!("Failed to parse scene JSON: {}", e),
+            )
+        })
+    } else {
+        // Relative mesh and image paths resolve against the scene file
+        rtrace::Scene::from_json_file(source).map_err(|e| {
+            Error::new(
+                Status::InvalidArg,
+                format!("Failed to load scene file: {}", e),
+            )
+        })
+    }
+}
+
+/// Render a scene on the libuv thread pool, resolving with a summary
+///
+/// `sceneSource` is either scene JSON or the path of a scene file. The JS thread stays
+/// free while the scene loads and renders. `onProgress` receives the same events as
+/// `renderSceneWithProgress`'s callback, but can't cancel the render; an exception it
+/// throws is uncaught rather than failing the render.
 #[napi]
-pub fn render_scene_threaded(
-    scene_json: String,
+pub fn render(
+    scene_source: String,
+    options: RenderOptions,
+    on_progress: Option<JsFunction>,
+) -> Result<AsyncTask<RenderTask>> {
+    let (output_path, options) = options.into_rtrace()?;
+    let progress = on_progress
+        .map(|callback| {
+            callback.create_threadsafe_function(0, |ctx: ThreadSafeCallContext<_>| {
+                Ok(vec![progress_event(&ctx.env, &ctx.value)?])
+            })
+        })
+        .transpose()?;
+    Ok(AsyncTask::new(RenderTask {
+        scene_source,
+        output_path,
+        options,
+        progress,
+    }))
+}
+
+/// A `render` call, run off the JS thread
+pub struct RenderTask {
+    scene_source: String,
     output_path: String,
-    size: Option<u32>,
-    thread_count: Option<u32>,
-) -> Result<String> {
-    let diagonal_size = size.unwrap_or(1000);
+    options: rtrace::RenderOptions,
+    progress: Option<ThreadsafeFunction<rtrace::Progress, ErrorStrategy::Fatal>>,
+}
+
+impl Task for RenderTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<String> {
+        let scene = load_scene(&self.scene_source)?;
+        let mut renderer = renderer(&scene, &self.options)?;
+        if let Some(progress) = self.progress.take() {
+            // Queued for the JS thread, so the render never waits on the callback
+            renderer = renderer.with_progress(Arc::new(move |update: &rtrace::Progress| {
+                progress.call(update.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+            }));
+        }
+
+        // Render and save
+        let output_path = &self.output_path;
+        renderer.render_to_file(&scene, output_path).map_err(|e| {
+            Error::new(
+                Status::GenericFailure,
+                format!("Failed to render scene: {}", e),
+            )
+        })?;
+
+        let thread_info = match self.options.threads {
+            Some(threads) => format!("{} threads", threads),
+            None => "all available threads".to_string(),
+        };
+        let intersection = match renderer.use_kdtree {
+            true => "k-d tree",
+            false => "brute force",
+        };
+        Ok(format!(
+            "Successfully rendered {}×{} image to '{}' ({}, {})",
+            renderer.width, renderer.height, output_path, thread_info, intersection
+        ))
+    }
+
+    fn resolve(&mut self, _env: Env, summary: String) -> Result<String> {
+        Ok(summary)
+    }
+}
 
+/// Render a scene from JSON string into an encoded image in memory
+///
+/// `format` is a file extension such as `"png"` (the default), `"jpg"` or `"exr"`.
+/// Nothing touches the filesystem, so a server can send the Buffer straight back. For
+/// unencoded pixels, use `renderSceneToRgba`.
+#[napi]
+pub fn render_scene_to_buffer(
+    scene_json: String,
+    size: Option<u32>,
+    format: Option<String>,
+    options: Option<SamplingOptions>,
+) -> Result<Buffer> {
     // Parse the JSON scene
     let scene = rtrace::Scene::from_json_str(&scene_json).map_err(|e| {
         Error::new(
@@ -78,55 +348,40 @@ pub fn render_scene_threaded(
         )
     })?;
 
-    // Compute pixel dimensions from diagonal size and camera aspect ratio
-    let camera_aspect_ratio = scene.camera.width / scene.camera.height;
-    let diagonal = diagonal_size as f64;
-    
-    // Using diagonal D and aspect ratio R = W/H:
-    // H = D / sqrt(R² + 1)  
-    // W = R * H
-    let height_f64 = diagonal / (camera_aspect_ratio * camera_aspect_ratio + 1.0).sqrt();
-    let width_f64 = camera_aspect_ratio * height_f64;
-    
-    let width = width_f64.round() as u32;
-    let height = height_f64.round() as u32;
-
-    // Create renderer with specific thread count
-    let renderer = if let Some(threads) = thread_count {
-        rtrace::Renderer::new_with_threads(width, height, threads as usize)
-    } else {
-        rtrace::Renderer::new(width, height)
-    };
+    let format = format.as_deref().unwrap_or("png");
+    let mut sink = rtrace::WriterSink::for_extension(Vec::new(), format)
+        .map_err(|e| Error::new(Status::InvalidArg, e))?;
 
-    // Render and save
-    renderer.render_to_file(&scene, &output_path).map_err(|e| {
+    let renderer = renderer(&scene, &sized_options(size, options))?;
+    renderer.render_to_sink(&scene, &mut sink).map_err(|e| {
         Error::new(
             Status::GenericFailure,
             format!("Failed to render scene: {}", e),
         )
     })?;
 
-    let thread_info = if let Some(threads) = thread_count {
-        format!(" with {} threads", threads)
-    } else {
-        " with all available threads".to_string()
-    };
+    Ok(sink.into_inner().into())
+}
 
-    Ok(format!(
-        "Successfully rendered {}×{} image (diagonal {}) to '{}'{}",
-        width, height, diagonal_size, output_path, thread_info
-    ))
+/// Raw pixels of a render
+#[napi(object)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    /// Width × height × 4 bytes, row by row from the top left; alpha is always 255
+    pub pixels: Buffer,
 }
 
-/// Render a scene from JSON string with brute force (no k-d tree)
+/// Render a scene from JSON string into raw RGBA pixels in memory
+///
+/// For servers that process the image further (or hand it to a canvas) rather than
+/// send an encoded file; `renderSceneToBuffer` returns a PNG.
 #[napi]
-pub fn render_scene_brute_force(
+pub fn render_scene_to_rgba(
     scene_json: String,
-    output_path: String,
     size: Option<u32>,
-) -> Result<String> {
-    let diagonal_size = size.unwrap_or(1000);
-
+    options: Option<SamplingOptions>,
+) -> Result<RgbaImage> {
     // Parse the JSON scene
     let scene = rtrace::Scene::from_json_str(&scene_json).map_err(|e| {
         Error::new(
@@ -135,176 +390,351 @@ pub fn render_scene_brute_force(
         )
     })?;
 
-    // Compute pixel dimensions from diagonal size and camera aspect ratio
-    let camera_aspect_ratio = scene.camera.width / scene.camera.height;
-    let diagonal = diagonal_size as f64;
-    
-    // Using diagonal D and aspect ratio R = W/H:
-    // H = D / sqrt(R² + 1)  
-    // W = R * H
-    let height_f64 = diagonal / (camera_aspect_ratio * camera_aspect_ratio + 1.0).sqrt();
-    let width_f64 = camera_aspect_ratio * height_f64;
-    
-    let width = width_f64.round() as u32;
-    let height = height_f64.round() as u32;
-
-    // Create renderer with k-d tree disabled (brute force)
-    let renderer = rtrace::Renderer::new_brute_force(width, height);
-
-    // Render and save
-    renderer.render_to_file(&scene, &output_path).map_err(|e| {
+    // Rendered through a sink, which frames the image as a saved file would be
+    let renderer = renderer(&scene, &sized_options(size, options))?;
+    let mut sink = rtrace::MemorySink::new();
+    renderer.render_to_sink(&scene, &mut sink).map_err(|e| {
         Error::new(
             Status::GenericFailure,
             format!("Failed to render scene: {}", e),
         )
     })?;
+    let Some(rtrace::RenderedImage::Rgb8(image)) = sink.image() else {
+        return Err(Error::new(
+            Status::GenericFailure,
+            "Render produced no 8-bit image".to_string(),
+        ));
+    };
 
-    Ok(format!(
-        "Successfully rendered {}×{} image (diagonal {}) to '{}' (brute force)",
-        width, height, diagonal_size, output_path
-    ))
+    // Framing can crop the image, so report the size actually rendered
+    let (width, height) = image.dimensions();
+    let pixels: Vec<u8> = (image.pixels())
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    Ok(RgbaImage {
+        width,
+        height,
+        pixels: pixels.into(),
+    })
 }
 
-/// Render a scene from JSON file directly (handles relative paths correctly)
-#[napi]
-pub fn render_scene_from_file(
-    scene_file_path: String,
-    output_path: String,
-    size: Option<u32>,
-) -> Result<String> {
-    let diagonal_size = size.unwrap_or(1000);
+/// Camera to render a `PreparedScene` through: the scene's own, or its camera `name`,
+/// with any of the other fields replaced
+#[napi(object)]
+pub struct CameraOptions {
+    pub name: Option<String>,
+    pub position: Option<Vec<f64>>,
+    pub target: Option<Vec<f64>>,
+    pub up: Option<Vec<f64>>,
+    /// Field of view in degrees, for perspective and fisheye cameras
+    pub fov: Option<f64>,
+}
 
-    // Load scene from file (handles relative paths)
-    let scene = rtrace::Scene::from_json_file(&scene_file_path).map_err(|e| {
-        Error::new(
-            Status::InvalidArg,
-            format!("Failed to load scene file: {}", e),
-        )
-    })?;
+impl CameraOptions {
+    /// The library's camera, rejecting vectors without exactly three components
+    fn into_rtrace(self) -> Result<rtrace::CameraOverride> {
+        let vector = |field: &str, value: Option<Vec<f64>>| {
+            value
+                .map(|value| {
+                    <[f64; 3]>::try_from(value).map_err(|_| {
+                        Error::new(
+                            Status::InvalidArg,
+                            format!("{} must have three components", field),
+                        )
+                    })
+                })
+                .transpose()
+        };
+        Ok(rtrace::CameraOverride {
+            name: self.name,
+            position: vector("position", self.position)?,
+            target: vector("target", self.target)?,
+            up: vector("up", self.up)?,
+            fov: self.fov,
+        })
+    }
+}
 
-    // Compute pixel dimensions from diagonal size and camera aspect ratio
-    let camera_aspect_ratio = scene.camera.width / scene.camera.height;
-    let diagonal = diagonal_size as f64;
-    
-    // Using diagonal D and aspect ratio R = W/H:
-    // H = D / sqrt(R² + 1)  
-    // W = R * H
-    let height_f64 = diagonal / (camera_aspect_ratio * camera_aspect_ratio + 1.0).sqrt();
-    let width_f64 = camera_aspect_ratio * height_f64;
-    
-    let width = width_f64.round() as u32;
-    let height = height_f64.round() as u32;
-
-    // Create renderer with k-d tree enabled and multi-threading
-    let renderer = rtrace::Renderer::new(width, height);
-
-    // Render and save
-    renderer.render_to_file(&scene, &output_path).map_err(|e| {
-        Error::new(
-            Status::GenericFailure,
-            format!("Failed to render scene: {}", e),
-        )
-    })?;
+/// A scene whose world is built once, then rendered through any camera and edited in
+/// place, for viewers and tools that move parts and re-render
+///
+/// Edits build only the objects they change, and moving objects refits the world's
+/// tree of object boxes rather than building it again.
+#[napi]
+pub struct PreparedScene {
+    renderer: rtrace::Renderer,
+    prepared: rtrace::PreparedScene,
+    source_path: Option<String>, // Scene file that added meshes are found next to
+}
 
-    Ok(format!(
-        "Successfully rendered {}×{} image (diagonal {}) to '{}' (multi-threaded)",
-        width, height, diagonal_size, output_path
-    ))
+#[napi]
+impl PreparedScene {
+    /// Build the world of `sceneSource`, scene JSON or the path of a scene file, for
+    /// images `size` pixels across the diagonal
+    #[napi(constructor)]
+    pub fn new(
+        scene_source: String,
+        size: Option<u32>,
+        options: Option<SamplingOptions>,
+    ) -> Result<Self> {
+        let scene = load_scene(&scene_source)?;
+        let renderer = renderer(&scene, &sized_options(size, options))?;
+        let prepared = renderer.prepare(scene).map_err(|e| {
+            Error::new(
+                Status::GenericFailure,
+                format!("Failed to prepare scene: {}", e),
+            )
+        })?;
+        let source_path = match scene_source.trim_start().starts_with('{') {
+            true => None,
+            false => Some(scene_source),
+        };
+        Ok(Self {
+            renderer,
+            prepared,
+            source_path,
+        })
+    }
+
+    /// Render through the scene's camera, or `camera`, into raw RGBA pixels
+    ///
+    /// The image is the camera's whole view; the scene's cropping and padding are left
+    /// to the caller.
+    #[napi]
+    pub fn render(&self, camera: Option<CameraOptions>) -> Result<RgbaImage> {
+        let camera = match camera {
+            Some(camera) => camera.into_rtrace()?,
+            None => rtrace::CameraOverride::default(),
+        };
+        let image = (self.renderer)
+            .render_prepared(&self.prepared, &camera)
+            .map_err(|e| {
+                Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to render scene: {}", e),
+                )
+            })?;
+        let (width, height) = image.dimensions();
+        let pixels: Vec<u8> = (image.pixels())
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect();
+        Ok(RgbaImage {
+            width,
+            height,
+            pixels: pixels.into(),
+        })
+    }
+
+    /// Give every object called `name` the `transform` strings in place of its own
+    /// (`null` removes it)
+    #[napi]
+    pub fn move_object(&mut self, name: String, transform: Option<Vec<String>>) -> Result<()> {
+        self.edit(rtrace::SceneEdit::Transform { name, transform })
+    }
+
+    /// Add the object in `objectJson` after the others
+    ///
+    /// Meshes are found like the scene's. A group adds its children one by one,
+    /// stopping at the first that fails.
+    #[napi]
+    pub fn add_object(&mut self, object_json: String) -> Result<()> {
+        let object: rtrace::Object = serde_json::from_str(&object_json).map_err(|e| {
+            Error::new(
+                Status::InvalidArg,
+                format!("Failed to parse object JSON: {}", e),
+            )
+        })?;
+        let mut loaded = rtrace::Scene {
+            objects: vec![object],
+            ..Default::default()
+        };
+        loaded
+            .load_mesh_data(self.source_path.as_deref())
+            .map_err(|e| Error::new(Status::InvalidArg, format!("Failed to load object: {}", e)))?;
+        for object in loaded.objects {
+            self.edit(rtrace::SceneEdit::Add(Box::new(object)))?;
+        }
+        Ok(())
+    }
+
+    /// Remove every object called `name`
+    #[napi]
+    pub fn remove_object(&mut self, name: String) -> Result<()> {
+        self.edit(rtrace::SceneEdit::Remove { name })
+    }
+
+    fn edit(&mut self, edit: rtrace::SceneEdit) -> Result<()> {
+        (self.renderer)
+            .edit_prepared(&mut self.prepared, edit)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))
+    }
 }
 
-/// Render a scene from JSON file with specific thread count
+/// Render a scene from JSON string progressively, reporting a snapshot after each pass
+///
+/// `callback` receives `{ pass, passes, width, height, pixels }` where `pixels` is a
+/// Buffer of packed RGB bytes holding the running average so far. Returning `false`
+/// from the callback (or throwing) cancels the render. The final image is written to
+/// `output_path`.
 #[napi]
-pub fn render_scene_from_file_threaded(
-    scene_file_path: String,
+pub fn render_scene_progressive(
+    env: Env,
+    scene_json: String,
     output_path: String,
     size: Option<u32>,
-    thread_count: Option<u32>,
+    passes: Option<u32>,
+    callback: JsFunction,
 ) -> Result<String> {
-    let diagonal_size = size.unwrap_or(1000);
+    let passes = passes.unwrap_or(16);
 
-    // Load scene from file (handles relative paths)
-    let scene = rtrace::Scene::from_json_file(&scene_file_path).map_err(|e| {
+    // Parse the JSON scene
+    let scene = rtrace::Scene::from_json_str(&scene_json).map_err(|e| {
         Error::new(
             Status::InvalidArg,
-            format!("Failed to load scene file: {}", e),
+            format!("Failed to parse scene JSON: {}", e),
         )
     })?;
 
-    // Compute pixel dimensions from diagonal size and camera aspect ratio
-    let camera_aspect_ratio = scene.camera.width / scene.camera.height;
-    let diagonal = diagonal_size as f64;
-    
-    // Using diagonal D and aspect ratio R = W/H:
-    // H = D / sqrt(R² + 1)  
-    // W = R * H
-    let height_f64 = diagonal / (camera_aspect_ratio * camera_aspect_ratio + 1.0).sqrt();
-    let width_f64 = camera_aspect_ratio * height_f64;
-    
-    let width = width_f64.round() as u32;
-    let height = height_f64.round() as u32;
-
-    // Create renderer with specific thread count
-    let renderer = if let Some(threads) = thread_count {
-        rtrace::Renderer::new_with_threads(width, height, threads as usize)
-    } else {
-        rtrace::Renderer::new(width, height)
+    // One stochastic sample per pixel per pass; the callback can cancel between passes
+    let options = rtrace::RenderOptions {
+        size,
+        anti_aliasing: Some(rtrace::AntiAliasingMode::Stochastic),
+        samples: Some(passes),
+        ..Default::default()
     };
-
-    // Render and save
-    renderer.render_to_file(&scene, &output_path).map_err(|e| {
+    let cancellation = rtrace::CancellationToken::new();
+    let renderer = renderer(&scene, &options)?.with_cancellation(cancellation.clone());
+
+    let mut callback_error: Option<Error> = None;
+    let result = renderer.render_progressive(&scene, |pass, total, snapshot| {
+        if callback_error.is_some() || cancellation.is_cancelled() {
+            return;
+        }
+        let report = || -> Result<bool> {
+            let mut event = env.create_object()?;
+            event.set_named_property("pass", pass)?;
+            event.set_named_property("passes", total)?;
+            event.set_named_property("width", snapshot.width())?;
+            event.set_named_property("height", snapshot.height())?;
+            let pixels = env.create_buffer_with_data(snapshot.as_raw().clone())?;
+            event.set_named_property("pixels", pixels.into_raw())?;
+            let keep_going: JsUnknown = callback.call(None, &[event])?;
+            if keep_going.get_type()? == ValueType::Boolean {
+                return keep_going.coerce_to_bool()?.get_value();
+            }
+            Ok(true)
+        };
+        match report() {
+            Ok(true) => {}
+            Ok(false) => cancellation.cancel(),
+            Err(e) => {
+                cancellation.cancel();
+                callback_error = Some(e);
+            }
+        }
+    });
+
+    if let Some(e) = callback_error {
+        return Err(e);
+    }
+    let image = result.map_err(|e| {
         Error::new(
             Status::GenericFailure,
             format!("Failed to render scene: {}", e),
         )
     })?;
 
-    let thread_info = if let Some(threads) = thread_count {
-        format!(" with {} threads", threads)
-    } else {
-        " with all available threads".to_string()
-    };
+    image.save(&output_path).map_err(|e| {
+        Error::new(
+            Status::GenericFailure,
+            format!("Failed to save image: {}", e),
+        )
+    })?;
 
     Ok(format!(
-        "Successfully rendered {}×{} image (diagonal {}) to '{}'{}",
-        width, height, diagonal_size, output_path, thread_info
+        "Successfully rendered {}×{} image (diagonal {}) to '{}' in {} passes",
+        renderer.width,
+        renderer.height,
+        size.unwrap_or(rtrace::RenderOptions::DEFAULT_SIZE),
+        output_path,
+        passes
     ))
 }
 
-/// Render a scene from JSON file with brute force (no k-d tree)
+/// Render a scene from JSON string, reporting progress to a JS callback
+///
+/// `callback` receives `{ phase, percent, completedPixels, totalPixels, elapsedMs, etaMs }`
+/// where `phase` is one of `"setup"`, `"tracing"`, `"postProcessing"` or `"finished"` and
+/// `etaMs` is `null` until an estimate is available. Returning `false` from the callback
+/// (or throwing) cancels the render.
 #[napi]
-pub fn render_scene_from_file_brute_force(
-    scene_file_path: String,
+pub fn render_scene_with_progress(
+    env: Env,
+    scene_json: String,
     output_path: String,
     size: Option<u32>,
+    callback: JsFunction,
+    options: Option<SamplingOptions>,
 ) -> Result<String> {
-    let diagonal_size = size.unwrap_or(1000);
-
-    // Load scene from file (handles relative paths)
-    let scene = rtrace::Scene::from_json_file(&scene_file_path).map_err(|e| {
+    // Parse the JSON scene
+    let scene = rtrace::Scene::from_json_str(&scene_json).map_err(|e| {
         Error::new(
             Status::InvalidArg,
-            format!("Failed to load scene file: {}", e),
+            format!("Failed to parse scene JSON: {}", e),
         )
     })?;
 
-    // Compute pixel dimensions from diagonal size and camera aspect ratio
-    let camera_aspect_ratio = scene.camera.width / scene.camera.height;
-    let diagonal = diagonal_size as f64;
-    
-    // Using diagonal D and aspect ratio R = W/H:
-    // H = D / sqrt(R² + 1)  
-    // W = R * H
-    let height_f64 = diagonal / (camera_aspect_ratio * camera_aspect_ratio + 1.0).sqrt();
-    let width_f64 = camera_aspect_ratio * height_f64;
-    
-    let width = width_f64.round() as u32;
-    let height = height_f64.round() as u32;
-
-    // Create renderer with k-d tree disabled (brute force)
-    let renderer = rtrace::Renderer::new_brute_force(width, height);
-
-    // Render and save
-    renderer.render_to_file(&scene, &output_path).map_err(|e| {
+    // Progress arrives on rayon worker threads, so render on a separate thread and
+    // forward updates to this (JS) thread over a channel
+    let (sender, receiver) = mpsc::channel::<rtrace::Progress>();
+    let sender = Mutex::new(sender);
+    let cancellation = rtrace::CancellationToken::new();
+    let renderer = renderer(&scene, &sized_options(size, options))?;
+    let (width, height) = (renderer.width, renderer.height);
+    let renderer = renderer
+        .with_cancellation(cancellation.clone())
+        .with_progress(Arc::new(move |progress: &rtrace::Progress| {
+            if let Ok(sender) = sender.lock() {
+                let _ = sender.send(progress.clone());
+            }
+        }));
+    let output = output_path.clone();
+    let render_thread = thread::spawn(move || {
+        renderer
+            .render_to_file(&scene, &output)
+            .map_err(|e| e.to_string())
+    });
+
+    let mut callback_error: Option<Error> = None;
+    for progress in receiver {
+        if callback_error.is_some() || cancellation.is_cancelled() {
+            continue;
+        }
+        let report = || -> Result<bool> {
+            let event = progress_event(&env, &progress)?;
+            let keep_going: JsUnknown = callback.call(None, &[event])?;
+            if keep_going.get_type()? == ValueType::Boolean {
+                return keep_going.coerce_to_bool()?.get_value();
+            }
+            Ok(true)
+        };
+        match report() {
+            Ok(true) => {}
+            Ok(false) => cancellation.cancel(),
+            Err(e) => {
+                cancellation.cancel();
+                callback_error = Some(e);
+            }
+        }
+    }
+
+    let result = render_thread
+        .join()
+        .map_err(|_| Error::new(Status::GenericFailure, "Render thread panicked".to_string()))?;
+    if let Some(e) = callback_error {
+        return Err(e);
+    }
+    result.map_err(|e| {
         Error::new(
             Status::GenericFailure,
             format!("Failed to render scene: {}", e),
@@ -312,7 +742,10 @@ pub fn render_scene_from_file_brute_force(
     })?;
 
     Ok(format!(
-        "Successfully rendered {}×{} image (diagonal {}) to '{}' (brute force)",
-        width, height, diagonal_size, output_path
+        "Successfully rendered {}×{} image (diagonal {}) to '{}'",
+        width,
+        height,
+        size.unwrap_or(rtrace::RenderOptions::DEFAULT_SIZE),
+        output_path
     ))
 }
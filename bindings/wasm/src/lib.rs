@@ -0,0 +1,112 @@
+//! WebAssembly bindings for rtrace, for rendering previews in the browser
+//!
+//! Renders run on the calling thread, since the library is built without its `parallel`
+//! feature, unless the `threads` feature puts them on a pool of Web Workers. Meshes can't
+//! be read from files in the browser, so their STL bytes are passed in alongside the scene.
+
+use rtrace::AssetOptions;
+use wasm_bindgen::prelude::*;
+
+/// Start rayon's pool on `threadCount` Web Workers, resolving once renders can use it
+/// (threaded builds in the browser only)
+#[cfg(all(feature = "threads", target_arch = "wasm32", target_os = "unknown"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+/// Returns a hello world message (WASM binding)
+#[wasm_bindgen(js_name = helloWorld)]
+pub fn hello_world() -> String {
+    rtrace::hello_world()
+}
+
+/// Render a scene from JSON into raw RGBA pixels, `width` × `height` × 4 bytes row by
+/// row from the top left, ready for an `ImageData`
+///
+/// Sampling follows the scene's `render_settings`. Alpha is always 255.
+#[wasm_bindgen(js_name = renderScene)]
+pub fn render_scene(scene_json: &str, width: u32, height: u32) -> Result<Vec<u8>, JsError> {
+    render(scene_json, width, height, AssetOptions::default()).map_err(|e| JsError::new(&e))
+}
+
+/// Render a scene from JSON as `renderScene` does, with the STL file `stl_bytes` standing
+/// in for every mesh whose `filename` is `stl_name`
+#[wasm_bindgen(js_name = renderSceneWithStl)]
+pub fn render_scene_with_stl(
+    scene_json: &str,
+    width: u32,
+    height: u32,
+    stl_name: &str,
+    stl_bytes: &[u8],
+) -> Result<Vec<u8>, JsError> {
+    let mut options = AssetOptions::default();
+    (options.preloaded_meshes).insert(stl_name.to_string(), stl_bytes.to_vec());
+    render(scene_json, width, height, options).map_err(|e| JsError::new(&e))
+}
+
+fn render(
+    scene_json: &str,
+    width: u32,
+    height: u32,
+    options: AssetOptions,
+) -> Result<Vec<u8>, String> {
+    let scene = rtrace::Scene::from_json_str_with_assets(scene_json, &options)
+        .map_err(|e| format!("Failed to parse scene JSON: {}", e))?;
+    let options = rtrace::RenderOptions {
+        width: Some(width),
+        height: Some(height),
+        ..Default::default()
+    };
+    let renderer = options.renderer(&scene)?;
+    // Threaded builds render on rayon's global pool, started by `initThreadPool`
+    let rgb =
+        (renderer.render_rgb(&scene)).map_err(|e| format!("Failed to render scene: {}", e))?;
+    Ok((rgb.chunks_exact(3))
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r##"{
+        "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 2},
+        "objects": [
+            {"kind": "mesh", "filename": "part.stl",
+             "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}
+        ],
+        "lights": [{"position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1.0}],
+        "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+    }"##;
+
+    // A triangle facing the camera, filling the middle of the view
+    const STL: &[u8] = b"solid part
+facet normal 0 -1 0
+outer loop
+vertex -1 0 -1
+vertex 1 0 -1
+vertex 0 0 1
+endloop
+endfacet
+endsolid part
+";
+
+    #[test]
+    fn test_render_with_stl() {
+        let mut options = AssetOptions::default();
+        (options.preloaded_meshes).insert("part.stl".to_string(), STL.to_vec());
+        let pixels = render(SCENE, 40, 20, options).unwrap();
+        assert_eq!(pixels.len(), 40 * 20 * 4);
+        let pixel = |x: usize, y: usize| &pixels[(y * 40 + x) * 4..][..4];
+        let middle = pixel(20, 10);
+        assert!(middle[0] > 0 && middle[1] == 0 && middle[2] == 0 && middle[3] == 255);
+        assert_eq!(pixel(1, 1)[3], 255);
+
+        // Without the bytes there's no file to fall back on
+        let error = render(SCENE, 40, 20, AssetOptions::default()).unwrap_err();
+        assert!(error.contains("part.stl"), "{}", error);
+        let mut options = AssetOptions::default();
+        (options.preloaded_meshes).insert("part.stl".to_string(), STL.to_vec());
+        let error = render(SCENE, 0, 20, options).unwrap_err();
+        assert!(error.contains("0×20"), "{}", error);
+    }
+}
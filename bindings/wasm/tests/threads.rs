@@ -0,0 +1,31 @@
+//! Browser test of the `threads` feature, run with a nightly atomics build:
+//! `RUSTFLAGS='-C target-feature=+atomics,+bulk-memory' wasm-pack test --chrome --headless
+//! -- --features threads -Z build-std=panic_abort,std`
+#![cfg(all(feature = "threads", target_arch = "wasm32", target_os = "unknown"))]
+
+use rtrace_wasm::{init_thread_pool, render_scene};
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const SCENE: &str = r##"{
+    "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 2},
+    "objects": [
+        {"kind": "sphere", "center": [0, 0, 0], "radius": 0.8,
+         "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}
+    ],
+    "lights": [{"position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1.0}],
+    "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+}"##;
+
+#[wasm_bindgen_test]
+async fn test_render_on_thread_pool() {
+    JsFuture::from(init_thread_pool(2)).await.unwrap();
+    let rgba = render_scene(SCENE, 20, 10).unwrap();
+    assert_eq!(rgba.len(), 20 * 10 * 4);
+    // The sphere fills the middle of the image over a black background
+    let middle = (5 * 20 + 10) * 4;
+    assert!(rgba[middle] > rgba[middle + 1]);
+    assert_eq!(&rgba[..4], &[0, 0, 0, 255]);
+}
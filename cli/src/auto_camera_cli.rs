@@ -1,5 +1,5 @@
 use clap::Parser;
-use rtrace::{AutoCamera, Scene};
+use rtrace::{AssetOptions, AutoCamera, Scene};
 use std::path::Path;
 
 /// Auto Camera Bounds CLI - generates 4 camera views for a scene
@@ -13,6 +13,10 @@ struct Args {
     /// Output JSON file for camera configurations
     #[arg(short, long)]
     output: String,
+
+    /// Allow downloading http(s) mesh URLs referenced by the scene
+    #[arg(long)]
+    allow_network: bool,
 }
 
 fn main() {
@@ -25,7 +29,12 @@ fn main() {
     }
 
     // Load scene from JSON
-    let scene = match Scene::from_json_file(&args.input) {
+    let asset_options = AssetOptions {
+        allow_network: args.allow_network,
+        cache_dir: None,
+        ..Default::default()
+    };
+    let scene = match Scene::from_json_file_with_assets(&args.input, &asset_options) {
         Ok(scene) => scene,
         Err(e) => {
             eprintln!("Error loading scene from '{}': {}", args.input, e);
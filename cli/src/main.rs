@@ -1,72 +1,578 @@
-use clap::Parser;
-use rtrace::{AntiAliasingMode, Renderer, Scene};
+use clap::{Parser, Subcommand};
+use rtrace::{
+    Aov, AntiAliasingMode, AssetOptions, AutoCamera, CancellationToken, DatasetConfig, FalseColor,
+    MissingAssetPolicy, NonFinitePixel, NonFinitePixels, ObjectStat, ObjectStats, Overlay,
+    Overrides, PreviewSession, PreviewUpdate, RenderCache, RenderMode, RenderOptions, Renderer,
+    Scene, SequenceFormat, StdoutProgress, Stereo, Target, TemporalConfig, TileFailures,
+    ToneMapping, TurntableAxis, XRayConfig, DEFAULT_OVERLAP_TOLERANCE,
+};
+use std::collections::BTreeMap;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 /// Ray tracer CLI - renders 3D scenes from JSON descriptions
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+/// Settings for rendering a scene, shared by `animate`, `turntable` and `stereo`
+#[derive(clap::Args, Debug)]
 struct Args {
     /// Input JSON scene file
-    #[arg(short, long)]
-    input: String,
+    #[arg(short, long, required = true)]
+    input: Option<String>,
 
-    /// Output PNG image file
-    #[arg(short, long)]
-    output: String,
+    /// Output image file; .exr and .hdr keep linear float radiance. For animate and
+    /// turntable, .gif and .apng write one animation and .mp4, .mov, .mkv and .webm a
+    /// video through ffmpeg; other formats get each frame's number before the extension
+    /// (out.png becomes out.0000.png, ...)
+    #[arg(short, long, required = true)]
+    output: Option<String>,
 
     /// Image diagonal size in pixels (aspect ratio computed from camera settings)
     #[arg(short, long, default_value_t = 1000)]
     size: u32,
 
+    /// Resolution preset: 1080p, 4k or square-2048 (replaces --size and --aspect)
+    #[arg(long, conflicts_with_all = ["size", "aspect"])]
+    preset: Option<String>,
+
+    /// Image aspect ratio as W:H (e.g. 16:9) instead of the camera's width/height
+    #[arg(long)]
+    aspect: Option<String>,
+
+    /// Center and size the ortho camera's viewport on the scene's finite bounds, with a
+    /// small margin (same as "fit": true on the camera)
+    #[arg(long)]
+    fit: bool,
+
+    /// Render the scene's named cameras instead of its camera: a comma-separated list
+    /// (front,top) or "all", each to its own file (out.png becomes out.front.png, ...)
+    #[arg(long)]
+    camera: Option<String>,
+
     /// Maximum ray bounces for reflections
     #[arg(long, default_value_t = 10)]
     max_depth: i32,
 
-    /// Number of samples per pixel
+    /// Number of samples per pixel (overrides the scene's render_settings)
     #[arg(long)]
     samples: Option<u32>,
 
-    /// Anti-aliasing mode: quincunx (default), stochastic, or no-jitter
-    #[arg(long, default_value = "quincunx")]
-    anti_aliasing: String,
+    /// Anti-aliasing mode: quincunx (default), stochastic, or no-jitter (overrides the scene)
+    #[arg(long)]
+    anti_aliasing: Option<String>,
+
+    /// Render mode: shaded (default), ao (ambient occlusion clay preview), shadows
+    /// (shadow factor only), lighting (direct light on matte white surfaces) or preview
+    /// (one ray per pixel, hard shadows, no reflections; outlines still drawn)
+    #[arg(long, default_value = "shaded")]
+    mode: String,
+
+    /// Tone mapping for 8-bit output: linear, srgb, reinhard or aces (overrides the scene)
+    #[arg(long)]
+    tonemap: Option<String>,
+
+    /// Exposure adjustment in stops before tone mapping (overrides the scene)
+    #[arg(long, allow_hyphen_values = true)]
+    exposure: Option<f64>,
+
+    /// Pick the exposure from a quick low-resolution pre-pass so the brightest parts
+    /// of the scene land on white; the scene's or --exposure's value is added on top
+    #[arg(long)]
+    auto_exposure: bool,
+
+    /// Extra passes to write next to the output, comma separated:
+    /// depth, normal, object_id, albedo, direct, indirect, alpha, outline
+    #[arg(long, value_delimiter = ',')]
+    aov: Vec<String>,
+
+    /// Show the depth, alpha and outline passes through a color map in 8-bit images:
+    /// viridis, plasma or coolwarm
+    #[arg(long)]
+    aov_color_map: Option<String>,
+
+    /// Values at the ends of --aov-color-map, as MIN,MAX (default: the nearest and
+    /// farthest hits for depth, 0 to 1 otherwise)
+    #[arg(long, value_delimiter = ',', num_args = 2, allow_hyphen_values = true)]
+    aov_range: Option<Vec<f64>>,
+
+    /// Also write a normalized depth map here (nearest white, 16-bit gray; PNG or TIFF)
+    #[arg(long)]
+    output_depth: Option<String>,
+
+    /// Also write a normal map here (world-space x, y, z as red, green, blue)
+    #[arg(long)]
+    output_normals: Option<String>,
+
+    /// Also write one pass per light group (lights' "group" field) plus an ambient pass
+    #[arg(long)]
+    light_groups: bool,
+
+    /// Debug lines to draw over the image, comma separated: bounds (object bounding
+    /// boxes), axes (XYZ gizmo at the origin)
+    #[arg(long, value_delimiter = ',')]
+    overlay: Vec<String>,
+
+    /// Crop the saved image (and its passes) to the rendered geometry (overrides the scene)
+    #[arg(long)]
+    crop_to_content: bool,
+
+    /// Margin around the saved image, or its content with --crop-to-content, as a
+    /// percentage of its larger side, filled with the background (overrides the scene)
+    #[arg(long)]
+    padding_percent: Option<f64>,
+
+    /// Number of occlusion rays per hit in ao mode
+    #[arg(long, default_value_t = 16)]
+    ao_samples: u32,
+
+    /// Maximum occluder distance in ao mode (scene units)
+    #[arg(long, default_value_t = 1.0)]
+    ao_radius: f64,
+
+    /// Points sampled on each area light's disk, for lights without their own
+    /// shadow_samples (more give smoother soft shadows at proportional cost)
+    #[arg(long)]
+    shadow_samples: Option<u32>,
+
+    /// Shade at half resolution and upsample along full-resolution hits, for quicker
+    /// renders of soft lighting (mirrors, glass and silhouettes stay full resolution)
+    #[arg(long)]
+    half_res_shading: bool,
+
+    /// Draw every mesh from its own file, ignoring the lower-detail lods it would be
+    /// swapped for when small on screen
+    #[arg(long)]
+    full_detail: bool,
+
+    /// Trace the quincunx corner grid in a separate pass, each corner exactly once, so
+    /// the work done doesn't depend on the tile layout (pixels are identical either way)
+    #[arg(long)]
+    strict_determinism: bool,
+
+    /// X-ray view: draw every surface partly see-through, with comma-separated
+    /// opacities for the nearest surfaces first, the last repeating (e.g. 0.3,1)
+    #[arg(long)]
+    xray: Option<String>,
+
+    /// Allow downloading http(s) mesh and backplate URLs referenced by the scene
+    #[arg(long)]
+    allow_network: bool,
+
+    /// Directory for downloaded assets (defaults to rtrace-cache in the system temp directory)
+    #[arg(long)]
+    asset_cache: Option<String>,
+
+    /// Directory searched for relative mesh and image paths not found next to the scene
+    /// or in its asset_paths
+    #[arg(long)]
+    asset_root: Option<String>,
+
+    /// When a mesh or background image can't be loaded: error (default), placeholder
+    /// (magenta unit box in the mesh's place) or skip
+    #[arg(long, default_value = "error")]
+    missing_asset: String,
+
+    /// JSON file patching materials and lights by name after the scene loads, leaving
+    /// the scene file untouched (re-read on change with --watch)
+    #[arg(long)]
+    overrides: Option<String>,
+
+    /// Warn about materials that can blow out to white or have out-of-range settings
+    #[arg(long)]
+    check_materials: bool,
+
+    /// Fix the materials --check-materials warns about before rendering, reporting each
+    #[arg(long)]
+    normalize_materials: bool,
+
+    /// Seed for the scene's jitter block in place of its own, to render another variation
+    #[arg(long)]
+    jitter_seed: Option<u64>,
+
+    /// Directory of previously rendered images; skips rendering when inputs are unchanged
+    #[arg(long)]
+    render_cache: Option<String>,
+
+    /// Keep running and re-render whenever the input file changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Write a Chrome tracing profile of the render phases to this file
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// After rendering, list each object's intersection tests and share of the time
+    /// spent in them, slowest first (renders somewhat slower while counting)
+    #[arg(long)]
+    stats_by_object: bool,
+
+    /// Paint NaN and infinite pixels magenta and list them with the object each one shows
+    #[arg(long)]
+    check_nan: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render randomized variations of a scene with masks, bounding boxes and depth
+    Dataset {
+        /// Dataset JSON config naming the scene, image count and variation ranges
+        #[arg(short, long)]
+        config: String,
+
+        /// Directory for the images and annotations.json (overrides the config's output_dir)
+        #[arg(long)]
+        output_dir: Option<String>,
+
+        /// Allow downloading http(s) mesh and backplate URLs referenced by the scene
+        #[arg(long)]
+        allow_network: bool,
+    },
+    /// List pairs of objects whose geometry intersects, e.g. parts of an assembly that
+    /// collide; exits with status 1 if any overlap
+    CheckOverlaps {
+        /// Input JSON scene file
+        #[arg(short, long)]
+        input: String,
+
+        /// Overlaps no deeper than this, in scene units, count as touching
+        #[arg(long, default_value_t = DEFAULT_OVERLAP_TOLERANCE)]
+        tolerance: f64,
+
+        /// Allow downloading http(s) mesh URLs referenced by the scene
+        #[arg(long)]
+        allow_network: bool,
+    },
+    /// Print how close two objects or points come, in scene units, and how far the
+    /// first can move along each axis before it meets the second
+    Measure {
+        /// Input JSON scene file
+        #[arg(short, long)]
+        input: String,
+
+        /// Object name, or a point as x,y,z
+        #[arg(long)]
+        from: String,
+
+        /// Object name, or a point as x,y,z
+        #[arg(long)]
+        to: String,
+
+        /// Allow downloading http(s) mesh URLs referenced by the scene
+        #[arg(long)]
+        allow_network: bool,
+    },
+    /// Render every frame of the scene's animation block to numbered images, a GIF/APNG
+    /// or a video
+    Animate(Box<AnimateArgs>),
+    /// Orbit a camera around the scene's bounds, rendering numbered images, a GIF/APNG
+    /// or a video
+    Turntable(Box<TurntableArgs>),
+    /// Render left and right eye views of a perspective camera, side by side or as
+    /// separate images
+    Stereo(Box<StereoArgs>),
+}
+
+/// How `animate` and `turntable` save their frames
+#[derive(clap::Args, Debug)]
+struct SequenceArgs {
+    /// Save frames as: frames (numbered images), gif, apng or ffmpeg (piped to ffmpeg,
+    /// which encodes by the output extension); chosen from the output extension by default
+    #[arg(long)]
+    format: Option<String>,
+
+    /// ffmpeg executable for the ffmpeg format
+    #[arg(long)]
+    ffmpeg: Option<String>,
+
+    /// ffmpeg output options, split on spaces, in place of the default
+    /// "-pix_fmt yuv420p -vf pad=ceil(iw/2)*2:ceil(ih/2)*2"
+    #[arg(long, allow_hyphen_values = true)]
+    ffmpeg_args: Option<String>,
+
+    /// Blend each frame with the frames before it where they saw the same surfaces,
+    /// averaging area light and ambient occlusion noise over the sequence (lighting must
+    /// stay still); BLEND is the least weight of a frame's own samples
+    #[arg(long, num_args = 0..=1, default_missing_value = "0.1", value_name = "BLEND")]
+    temporal: Option<f64>,
+}
+
+impl SequenceArgs {
+    /// The format named by --format or the output extension, with any ffmpeg settings
+    fn format(&self, output: &str) -> Result<SequenceFormat, String> {
+        let mut format = match &self.format {
+            Some(name) => name.parse()?,
+            None => SequenceFormat::from_path(output),
+        };
+        match &mut format {
+            SequenceFormat::Ffmpeg { program, args } => {
+                if let Some(ffmpeg) = &self.ffmpeg {
+                    *program = ffmpeg.clone();
+                }
+                if let Some(ffmpeg_args) = &self.ffmpeg_args {
+                    *args = ffmpeg_args.split_whitespace().map(String::from).collect();
+                }
+            }
+            _ if self.ffmpeg.is_some() || self.ffmpeg_args.is_some() => {
+                return Err(format!(
+                    "--ffmpeg and --ffmpeg-args need the ffmpeg format, not {}",
+                    format.name()
+                ));
+            }
+            _ => {}
+        }
+        Ok(format)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct AnimateArgs {
+    #[command(flatten)]
+    render: Args,
+
+    #[command(flatten)]
+    sequence: SequenceArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct TurntableArgs {
+    #[command(flatten)]
+    render: Args,
+
+    #[command(flatten)]
+    sequence: SequenceArgs,
+
+    /// Number of frames in one full orbit
+    #[arg(long, default_value_t = 120)]
+    frames: u32,
+
+    /// Axis to orbit about, through the center of the scene's bounds: x, y or z
+    #[arg(long, default_value = "z")]
+    axis: String,
+
+    /// Frames per second of .gif, .apng and video output
+    #[arg(long, default_value_t = 30.0)]
+    fps: f64,
+}
+
+#[derive(clap::Args, Debug)]
+struct StereoArgs {
+    #[command(flatten)]
+    render: Args,
+
+    /// Distance between the eyes in scene units (default: 1/30 of the distance from the
+    /// camera to its target)
+    #[arg(long)]
+    ipd: Option<f64>,
+
+    /// sbs (one image twice as wide, left eye on the left) or separate (out.left.png
+    /// and out.right.png)
+    #[arg(long, default_value = "sbs")]
+    layout: String,
+}
+
+/// Frames rendered in place of a single image
+enum Sequence {
+    Animation,
+    Turntable {
+        frames: u32,
+        axis: TurntableAxis,
+        fps: f64,
+    },
+}
+
+impl Args {
+    fn input(&self) -> &str {
+        self.input.as_deref().expect("clap requires --input")
+    }
+
+    fn output(&self) -> &str {
+        self.output.as_deref().expect("clap requires --output")
+    }
 }
 
 fn main() {
-    let args = Args::parse();
+    let cli = Cli::parse();
+
+    let (args, sequence, sequence_args, stereo) = match cli.command {
+        Some(Command::Dataset {
+            config,
+            output_dir,
+            allow_network,
+        }) => {
+            dataset(&config, output_dir.as_deref(), allow_network);
+            return;
+        }
+        Some(Command::CheckOverlaps {
+            input,
+            tolerance,
+            allow_network,
+        }) => {
+            check_overlaps(&input, tolerance, allow_network);
+            return;
+        }
+        Some(Command::Measure {
+            input,
+            from,
+            to,
+            allow_network,
+        }) => {
+            measure(&input, &from, &to, allow_network);
+            return;
+        }
+        Some(Command::Animate(animate)) => {
+            let animate = *animate;
+            (
+                animate.render,
+                Some(Sequence::Animation),
+                Some(animate.sequence),
+                None,
+            )
+        }
+        Some(Command::Turntable(turntable)) => {
+            let axis = match turntable.axis.parse() {
+                Ok(axis) => axis,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let sequence = Sequence::Turntable {
+                frames: turntable.frames,
+                axis,
+                fps: turntable.fps,
+            };
+            (
+                turntable.render,
+                Some(sequence),
+                Some(turntable.sequence),
+                None,
+            )
+        }
+        Some(Command::Stereo(stereo)) => {
+            let layout = match stereo.layout.parse() {
+                Ok(layout) => layout,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let settings = Stereo {
+                interpupillary_distance: stereo.ipd,
+                layout,
+            };
+            (stereo.render, None, None, Some(settings))
+        }
+        None => (cli.args, None, None, None),
+    };
+    let temporal = sequence_args
+        .as_ref()
+        .and_then(|sequence| sequence.temporal);
+    let sequence_format = match sequence_args.map(|sequence| sequence.format(args.output())) {
+        Some(Ok(format)) => Some(format),
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        None => None,
+    };
+    if (sequence.is_some() || stereo.is_some()) && (args.watch || args.render_cache.is_some()) {
+        eprintln!(
+            "Error: animate, turntable and stereo can't be used with --watch or --render-cache"
+        );
+        std::process::exit(1);
+    }
+    let batch = sequence.is_some() || stereo.is_some() || args.watch || args.render_cache.is_some();
+    if args.camera.is_some() && batch {
+        eprintln!(
+            "Error: --camera can't be used with animate, turntable, stereo, --watch or --render-cache"
+        );
+        std::process::exit(1);
+    }
+    let maps = args.output_depth.is_some() || args.output_normals.is_some();
+    if maps && (sequence.is_some() || stereo.is_some() || args.watch || args.camera.is_some()) {
+        eprintln!(
+            "Error: --output-depth and --output-normals can't be used with animate, turntable, stereo, --watch or --camera"
+        );
+        std::process::exit(1);
+    }
 
     // Validate input file exists
-    if !Path::new(&args.input).exists() {
-        eprintln!("Error: Input file '{}' does not exist", args.input);
+    if !Path::new(args.input()).exists() {
+        eprintln!("Error: Input file '{}' does not exist", args.input());
         std::process::exit(1);
     }
 
     // Parse anti-aliasing mode
-    let anti_aliasing_mode = match args.anti_aliasing.as_str() {
-        "quincunx" => AntiAliasingMode::Quincunx,
-        "stochastic" => AntiAliasingMode::Stochastic,
-        "no-jitter" => AntiAliasingMode::NoJitter,
-        _ => {
-            eprintln!("Error: Invalid anti-aliasing mode '{}'. Valid options are: quincunx, stochastic, no-jitter", args.anti_aliasing);
+    let anti_aliasing_mode: Option<AntiAliasingMode> = match args.anti_aliasing.as_deref() {
+        Some(name) => match name.parse() {
+            Ok(mode) => Some(mode),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Parse render mode
+    let render_mode: RenderMode = match args.mode.parse() {
+        Ok(mode) => mode,
+        Err(e) => {
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
     };
 
-    // Determine sample count based on mode and user input
-    let samples = args.samples.unwrap_or(1); // Default to 1 sample for all modes
-
-    // Validate samples parameter
-    if samples == 0 {
-        eprintln!("Error: Samples must be greater than 0");
-        std::process::exit(1);
+    if args.profile.is_some() {
+        rtrace::profile::enable();
     }
 
+    let missing_asset = match args.missing_asset.parse() {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Load scene from JSON
-    let scene = match Scene::from_json_file(&args.input) {
+    let asset_options = AssetOptions {
+        allow_network: args.allow_network,
+        cache_dir: args.asset_cache.as_ref().map(Into::into),
+        missing_asset,
+        asset_root: args.asset_root.as_ref().map(Into::into),
+        jitter_seed: args.jitter_seed,
+        ..Default::default()
+    };
+    let scene = match load_scene(&args, &asset_options) {
         Ok(scene) => scene,
         Err(e) => {
-            eprintln!("Error loading scene from '{}': {}", args.input, e);
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    warn_missing_assets(&scene, missing_asset);
+    let camera_names = match args
+        .camera
+        .as_deref()
+        .map(|names| scene.camera_names(names))
+    {
+        Some(Ok(names)) => names,
+        Some(Err(e)) => {
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
+        None => Vec::new(),
     };
 
     println!(
@@ -74,71 +580,735 @@ fn main() {
         scene.objects.len(),
         scene.lights.len()
     );
+    let (frames, cameras) = match &sequence {
+        None => (0, Vec::new()),
+        Some(Sequence::Animation) => match &scene.animation {
+            Some(animation) => (animation.frames, Vec::new()),
+            None => {
+                eprintln!("Error: Scene '{}' has no animation block", args.input());
+                std::process::exit(1);
+            }
+        },
+        Some(Sequence::Turntable { frames, axis, .. }) => {
+            match AutoCamera::turntable(&scene, *frames, *axis) {
+                Ok(cameras) => (*frames, cameras),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
 
-    // Compute pixel dimensions from diagonal size and camera aspect ratio
-    let camera_aspect_ratio = scene.camera.width / scene.camera.height;
-    let diagonal = args.size as f64;
-    
-    // Using diagonal D and aspect ratio R = W/H:
-    // H = D / sqrt(R² + 1)
-    // W = R * H
-    let height_f64 = diagonal / (camera_aspect_ratio * camera_aspect_ratio + 1.0).sqrt();
-    let width_f64 = camera_aspect_ratio * height_f64;
-    
-    let width = width_f64.round() as u32;
-    let height = height_f64.round() as u32;
+    let (width, height) = match image_dimensions(&args, &scene) {
+        Ok(dimensions) => dimensions,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Command-line flags take precedence over the scene's render_settings and outline
+    let options = RenderOptions {
+        width: Some(width),
+        height: Some(height),
+        anti_aliasing: anti_aliasing_mode,
+        samples: args.samples,
+        seed: Some(0), // Always use deterministic seed 0
+        max_depth: Some(args.max_depth),
+        ..Default::default()
+    };
+    let mut renderer = match options.renderer(&scene) {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if renderer.outline_config.is_some() {
+        println!("Outline detection enabled from scene configuration");
+    }
+    if args.crop_to_content {
+        renderer.framing.crop_to_content = true;
+    }
+    if let Some(padding_percent) = args.padding_percent {
+        renderer.framing.padding_percent = padding_percent;
+    }
+    renderer = renderer.with_progress(Arc::new(StdoutProgress::new()));
+    match render_mode {
+        RenderMode::AmbientOcclusion => {
+            renderer = renderer.with_ambient_occlusion(args.ao_samples, args.ao_radius);
+            println!(
+                "Ambient occlusion mode ({} samples, radius {})",
+                args.ao_samples, args.ao_radius
+            );
+        }
+        RenderMode::Preview => {
+            renderer = renderer.with_preview();
+            println!("Preview mode (1 ray per pixel, hard shadows, no reflections)");
+        }
+        RenderMode::Shaded => {}
+        mode => {
+            println!("Render mode: {}", mode.name());
+            renderer.render_mode = mode;
+        }
+    }
+    if let Some(samples) = args.shadow_samples {
+        renderer = renderer.with_shadow_samples(samples);
+    }
+    if args.half_res_shading {
+        renderer = renderer.with_half_res_shading();
+    }
+    if args.full_detail {
+        renderer = renderer.with_full_detail();
+    }
+    if args.strict_determinism {
+        renderer = renderer.with_strict_determinism();
+    }
+    if let Some(opacities) = &args.xray {
+        let xray: XRayConfig = match opacities.parse() {
+            Ok(xray) => xray,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+        println!("X-ray view (layer opacities {})", opacities);
+        renderer = renderer.with_xray(xray);
+    }
+
+    if let Some(tilt_shift) = scene.get_tilt_shift_config() {
+        if let Err(e) = tilt_shift.validate() {
+            eprintln!("Error: Invalid tilt-shift settings in scene: {}", e);
+            std::process::exit(1);
+        }
+        println!(
+            "Tilt-shift blur enabled (focus {}, range {}, blur {})",
+            tilt_shift.focus, tilt_shift.range, tilt_shift.blur
+        );
+        renderer = renderer.with_tilt_shift(tilt_shift);
+    }
+
+    // Scene tone mapping, with --tonemap and --exposure taking precedence
+    let mut tone_mapping = match scene.get_tone_mapping() {
+        Ok(tone_mapping) => tone_mapping.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Error: Invalid tone mapping in scene: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(operator) = &args.tonemap {
+        tone_mapping.operator = match operator.parse() {
+            Ok(operator) => operator,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        };
+    }
+    if let Some(exposure) = args.exposure {
+        tone_mapping.exposure = exposure;
+    }
+    // --auto-exposure adds its measurement once the render cache has been checked
+    renderer = renderer.with_tone_mapping(tone_mapping);
+
+    let aovs: Result<Vec<Aov>, String> = args.aov.iter().map(|name| name.parse()).collect();
+    match aovs {
+        Ok(aovs) => renderer = renderer.with_aovs(aovs),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(name) = &args.aov_color_map {
+        match name.parse() {
+            Ok(map) => {
+                let range = args.aov_range.as_ref().map(|range| [range[0], range[1]]);
+                renderer = renderer.with_aov_false_color(FalseColor { map, range });
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if args.aov_range.is_some() {
+        eprintln!("Error: --aov-range needs --aov-color-map");
+        std::process::exit(1);
+    }
+    if args.light_groups {
+        renderer = renderer.with_light_groups();
+    }
+    let overlays: Result<Vec<Overlay>, String> =
+        args.overlay.iter().map(|name| name.parse()).collect();
+    match overlays {
+        Ok(overlays) => renderer = renderer.with_overlays(overlays),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
 
     println!(
-        "Using camera aspect ratio {:.3} to compute {}×{} pixels from diagonal {}",
-        camera_aspect_ratio, width, height, args.size
+        "Rendering {}×{} image with {} anti-aliasing ({} samples)...",
+        width,
+        height,
+        renderer.anti_aliasing_mode.name(),
+        renderer.samples
     );
 
-    // Create renderer
-    let mut renderer = Renderer::new(width, height);
-    renderer.max_depth = args.max_depth;
-    renderer.samples = samples;
-    renderer.seed = Some(0); // Always use deterministic seed 0
-    
-    // Configure outline detection from scene settings
-    match scene.get_outline_config() {
-        Ok(Some(outline_config)) => {
-            renderer = renderer.with_outline_detection(outline_config);
-            println!("Outline detection enabled from scene configuration");
-            
-            // Check if current anti-aliasing mode is compatible with outline detection
-            if anti_aliasing_mode == AntiAliasingMode::Quincunx {
-                println!("Warning: Quincunx anti-aliasing is not compatible with outline detection. Switching to no-jitter mode.");
-                renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+    if args.watch {
+        let renderer = settle_exposure(&args, renderer, &scene);
+        watch(&args, &asset_options, renderer, scene);
+    }
+
+    // Reuse an identical earlier render if a render cache is configured; the cache
+    // only holds the main image, so AOV and light group renders always run, as do
+    // renders standing in for missing assets, with normalized materials, collecting
+    // per-object statistics or checking for non-finite pixels
+    let render_cache = args
+        .render_cache
+        .as_ref()
+        .filter(|_| {
+            renderer.aovs.is_empty()
+                && !maps
+                && !renderer.light_groups
+                && scene.missing_assets.is_empty()
+                && !args.normalize_materials
+                && !args.stats_by_object
+                && !args.check_nan
+        })
+        .map(RenderCache::new);
+    let cache_key = match &render_cache {
+        Some(_) => match RenderCache::key(
+            args.input(),
+            args.overrides.as_deref(),
+            &scene,
+            &renderer,
+            args.auto_exposure,
+            &asset_options,
+        ) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                eprintln!("Error computing render cache key: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    if let (Some(cache), Some(key)) = (&render_cache, &cache_key) {
+        if let Some(cached) = cache.lookup(key, Path::new(args.output())) {
+            if let Err(e) = std::fs::copy(&cached, args.output()) {
+                eprintln!("Error copying cached render: {}", e);
+                std::process::exit(1);
+            }
+            println!("Render cache hit ({}), copied to '{}'", key, args.output());
+            return;
+        }
+    }
+    let renderer = settle_exposure(&args, renderer, &scene);
+
+    // First Ctrl-C stops the render after the tiles in flight; a second one exits immediately
+    let cancellation = CancellationToken::new();
+    let handler_token = cancellation.clone();
+    let handler = ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            std::process::exit(130);
+        }
+        eprintln!("\nCancelling render (press Ctrl-C again to exit immediately)...");
+        handler_token.cancel();
+    });
+    if let Err(e) = handler {
+        eprintln!("Warning: could not install Ctrl-C handler: {}", e);
+    }
+    let mut renderer = renderer.with_cancellation(cancellation.clone());
+    let object_stats = ObjectStats::new();
+    if args.stats_by_object {
+        renderer = renderer.with_object_stats(object_stats.clone());
+    }
+    let tile_failures = TileFailures::new();
+    renderer = renderer.with_tile_failures(tile_failures.clone());
+    if let Some(format) = sequence_format {
+        renderer = renderer.with_sequence_format(format);
+    }
+    if let Some(blend) = temporal {
+        let temporal = TemporalConfig {
+            blend,
+            ..TemporalConfig::default()
+        };
+        if let Err(e) = temporal.validate() {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        println!("Temporal accumulation enabled (blend {})", blend);
+        renderer = renderer.with_temporal_accumulation(temporal);
+    }
+    let non_finite = NonFinitePixels::new();
+    if args.check_nan {
+        renderer = renderer.with_non_finite_check(non_finite.clone());
+    }
+
+    // Render and save
+    let on_frame = |frame: u32, path: &str| {
+        println!("Frame {}/{} rendered to '{}'", frame + 1, frames, path);
+        for failure in tile_failures.failures() {
+            eprintln!("Warning: frame {} {}", frame, failure);
+        }
+        tile_failures.clear();
+        print_non_finite(&non_finite.pixels());
+        non_finite.clear();
+    };
+    let result = match &sequence {
+        None => match &stereo {
+            Some(stereo) => renderer.render_stereo_to_file(&scene, stereo, args.output()),
+            None if !camera_names.is_empty() => {
+                renderer.render_cameras_to_file(&scene, &camera_names, args.output())
+            }
+            None => renderer.render_to_file(&scene, args.output()),
+        },
+        Some(Sequence::Animation) => renderer.render_sequence(&scene, args.output(), on_frame),
+        Some(Sequence::Turntable { fps, .. }) => {
+            let scene_at = |frame: u32| {
+                Ok(Scene {
+                    camera: cameras[frame as usize].clone(),
+                    ..scene.clone()
+                })
+            };
+            renderer.render_frames(frames, scene_at, args.output(), *fps, on_frame)
+        }
+    };
+    if let Err(e) = result {
+        if cancellation.is_cancelled() {
+            if sequence.is_some() {
+                eprintln!("Render cancelled; frames already saved were kept");
+            } else if !camera_names.is_empty() {
+                eprintln!("Render cancelled; cameras already saved were kept");
             } else {
-                renderer.anti_aliasing_mode = anti_aliasing_mode;
+                eprintln!("Render cancelled; '{}' was not written", args.output());
             }
+            std::process::exit(130);
+        }
+        eprintln!("Error rendering image: {}", e);
+        std::process::exit(1);
+    }
+    let failures = tile_failures.failures();
+    for failure in &failures {
+        eprintln!("Warning: {}", failure);
+    }
+    print_non_finite(&non_finite.pixels());
+
+    // Images with tiles left black aren't kept for next time
+    let complete = failures.iter().all(|failure| failure.recovered);
+    if let (true, Some(cache), Some(key)) = (complete, &render_cache, &cache_key) {
+        // A failed cache write only costs a re-render next time
+        if let Err(e) = cache.store(key, Path::new(args.output())) {
+            eprintln!("Warning: could not update render cache: {}", e);
+        }
+    }
+
+    if sequence.is_some() {
+        println!("Successfully rendered {} frames", frames);
+    } else if !camera_names.is_empty() {
+        for name in &camera_names {
+            let path = Renderer::camera_output_path(args.output(), name);
+            println!(
+                "Successfully rendered camera '{}' to '{}'",
+                name,
+                path.display()
+            );
+        }
+    } else {
+        println!("Successfully rendered to '{}'", args.output());
+    }
+    if maps {
+        save_depth_normal_maps(&renderer, &scene, &args);
+    }
+
+    if args.stats_by_object {
+        print_object_stats(&object_stats.report(&scene));
+    }
+
+    if let Some(profile_path) = &args.profile {
+        match rtrace::profile::write_chrome_trace(profile_path) {
+            Ok(()) => println!("Profile written to '{}'", profile_path),
+            Err(e) => eprintln!("Warning: could not write profile: {}", e),
+        }
+    }
+}
+
+/// Write the depth and normal maps asked for by --output-depth and --output-normals
+fn save_depth_normal_maps(renderer: &Renderer, scene: &Scene, args: &Args) {
+    let (depth, normals) = match renderer.render_depth_normal_maps(scene) {
+        Ok(maps) => maps,
+        Err(e) => {
+            eprintln!("Error rendering depth and normal maps: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(path) = &args.output_depth {
+        if let Err(e) = depth.save(path) {
+            eprintln!("Error saving depth map '{}': {}", path, e);
+            std::process::exit(1);
+        }
+        println!("Depth map saved to '{}'", path);
+    }
+    if let Some(path) = &args.output_normals {
+        if let Err(e) = normals.save(path) {
+            eprintln!("Error saving normal map '{}': {}", path, e);
+            std::process::exit(1);
+        }
+        println!("Normal map saved to '{}'", path);
+    }
+}
+
+/// Table of per-object intersection work, slowest object first
+fn print_object_stats(stats: &[ObjectStat]) {
+    println!("Intersection tests by object (CPU time summed over threads):");
+    println!(
+        "{:>7} {:>10} {:>14} {:>12}  object",
+        "share", "time", "tests", "hits"
+    );
+    for stat in stats {
+        println!(
+            "{:>6.1}% {:>9.3}s {:>14} {:>12}  {}",
+            stat.share * 100.0,
+            stat.time.as_secs_f64(),
+            stat.tests,
+            stat.hits,
+            stat.label
+        );
+    }
+}
+
+/// Warning listing NaN and infinite pixels, grouped by the object they show
+fn print_non_finite(pixels: &[NonFinitePixel]) {
+    if pixels.is_empty() {
+        return;
+    }
+    eprintln!(
+        "Warning: {} pixels came out NaN or infinite and were painted magenta:",
+        pixels.len()
+    );
+    let mut by_object: BTreeMap<&str, Vec<&NonFinitePixel>> = BTreeMap::new();
+    for pixel in pixels {
+        let object = pixel.object.as_deref().unwrap_or("background");
+        by_object.entry(object).or_default().push(pixel);
+    }
+    for (object, pixels) in by_object {
+        let [r, g, b] = pixels[0].color;
+        let (x, y) = (pixels[0].x, pixels[0].y);
+        eprintln!(
+            "  {}: {} pixels, first ({}, {}) = ({}, {}, {})",
+            object,
+            pixels.len(),
+            x,
+            y,
+            r,
+            g,
+            b
+        );
+    }
+}
+
+/// Render every variation described by a dataset config
+/// Report the objects of a scene that overlap, exiting with status 1 if any do
+fn check_overlaps(input: &str, tolerance: f64, allow_network: bool) {
+    let asset_options = AssetOptions {
+        allow_network,
+        ..Default::default()
+    };
+    let scene = match Scene::from_json_file_with_assets(input, &asset_options) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Error loading scene from '{}': {}", input, e);
+            std::process::exit(1);
+        }
+    };
+    let overlaps = match rtrace::find_overlaps(&scene, tolerance) {
+        Ok(overlaps) => overlaps,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
         }
-        Ok(None) => {
-            // No outline detection configured - use original anti-aliasing mode
-            renderer.anti_aliasing_mode = anti_aliasing_mode;
+    };
+    if overlaps.is_empty() {
+        println!(
+            "No overlaps between the {} objects of '{}' (tolerance {})",
+            scene.objects.len(),
+            input,
+            tolerance
+        );
+        return;
+    }
+    for overlap in &overlaps {
+        println!("{}", overlap.describe(&scene));
+    }
+    match overlaps.len() {
+        1 => println!("1 overlapping pair"),
+        count => println!("{} overlapping pairs", count),
+    }
+    std::process::exit(1);
+}
+
+fn measure(input: &str, from: &str, to: &str, allow_network: bool) {
+    let asset_options = AssetOptions {
+        allow_network,
+        ..Default::default()
+    };
+    let scene = match Scene::from_json_file_with_assets(input, &asset_options) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Error loading scene from '{}': {}", input, e);
+            std::process::exit(1);
         }
+    };
+    let measurement = Target::parse(&scene, from)
+        .and_then(|from| Ok((from, Target::parse(&scene, to)?)))
+        .and_then(|(from, to)| rtrace::measure(&scene, &from, &to));
+    match measurement {
+        Ok(measurement) => println!("{}", measurement.describe(&scene)),
         Err(e) => {
-            eprintln!("Error: Invalid outline color in scene: {}", e);
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
     }
+}
 
-    let final_anti_aliasing_name = match renderer.anti_aliasing_mode {
-        AntiAliasingMode::Quincunx => "quincunx",
-        AntiAliasingMode::Stochastic => "stochastic",
-        AntiAliasingMode::NoJitter => "no-jitter",
+fn dataset(config_path: &str, output_dir: Option<&str>, allow_network: bool) {
+    let mut config = match DatasetConfig::from_json_file(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading dataset config from '{}': {}", config_path, e);
+            std::process::exit(1);
+        }
     };
+    if let Some(output_dir) = output_dir {
+        config.output_dir = output_dir.into();
+    }
 
+    let asset_options = AssetOptions {
+        allow_network,
+        ..Default::default()
+    };
     println!(
-        "Rendering {}×{} image (diagonal {}) with {} anti-aliasing ({} samples)...",
-        width, height, args.size, final_anti_aliasing_name, samples
+        "Rendering {} variations of '{}' into '{}'",
+        config.count,
+        config.scene.display(),
+        config.output_dir.display()
     );
-
-    // Render and save
-    if let Err(e) = renderer.render_to_file(&scene, &args.output) {
-        eprintln!("Error rendering image: {}", e);
+    let mut rendered = 0;
+    let result = config.generate(&asset_options, |image| {
+        rendered += 1;
+        println!(
+            "[{}/{}] {} (seed {}, {} objects visible)",
+            rendered,
+            config.count,
+            image.image,
+            image.seed,
+            image.objects.len()
+        );
+    });
+    if let Err(e) = result {
+        eprintln!("Error generating dataset: {}", e);
         std::process::exit(1);
     }
+    println!(
+        "Annotations written to '{}'",
+        config.output_dir.join("annotations.json").display()
+    );
+}
 
-    println!("Successfully rendered to '{}'", args.output);
+/// Pick the output resolution from --preset, or from --size and the aspect ratio
+///
+/// The camera viewport is widened or heightened to match, so a different aspect
+/// ratio shows more of the scene rather than distorting it.
+fn image_dimensions(args: &Args, scene: &Scene) -> Result<(u32, u32), String> {
+    if let Some(preset) = &args.preset {
+        let (width, height) = match preset.as_str() {
+            "1080p" => (1920, 1080),
+            "4k" => (3840, 2160),
+            "square-2048" => (2048, 2048),
+            _ => {
+                return Err(format!(
+                    "Invalid preset '{}'. Valid options are: 1080p, 4k, square-2048",
+                    preset
+                ))
+            }
+        };
+        println!("Using preset {} ({}×{} pixels)", preset, width, height);
+        return Ok((width, height));
+    }
+
+    let (aspect_ratio, source) = match &args.aspect {
+        Some(aspect) => (parse_aspect(aspect)?, "requested"),
+        None => (scene.camera.width / scene.camera.height, "camera"),
+    };
+
+    // Using diagonal D and aspect ratio R = W/H:
+    // H = D / sqrt(R² + 1)
+    // W = R * H
+    let diagonal = args.size as f64;
+    let height_f64 = diagonal / (aspect_ratio * aspect_ratio + 1.0).sqrt();
+    let width_f64 = aspect_ratio * height_f64;
+
+    let width = (width_f64.round() as u32).max(1);
+    let height = (height_f64.round() as u32).max(1);
+
+    println!(
+        "Using {} aspect ratio {:.3} to compute {}×{} pixels from diagonal {}",
+        source, aspect_ratio, width, height, args.size
+    );
+    Ok((width, height))
+}
+
+/// Parse an aspect ratio written as `W:H` (e.g. `16:9`) or as a single number
+fn parse_aspect(aspect: &str) -> Result<f64, String> {
+    let invalid = || format!("Invalid aspect ratio '{}'. Use W:H, e.g. 16:9", aspect);
+    let ratio = match aspect.split_once(':') {
+        Some((w, h)) => {
+            let w: f64 = w.trim().parse().map_err(|_| invalid())?;
+            let h: f64 = h.trim().parse().map_err(|_| invalid())?;
+            w / h
+        }
+        None => aspect.trim().parse().map_err(|_| invalid())?,
+    };
+    if ratio.is_finite() && ratio > 0.0 {
+        Ok(ratio)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Report each asset that was replaced by a placeholder or skipped
+fn warn_missing_assets(scene: &Scene, policy: MissingAssetPolicy) {
+    let action = match policy {
+        MissingAssetPolicy::Placeholder => "using a placeholder",
+        _ => "skipping it",
+    };
+    for asset in &scene.missing_assets {
+        eprintln!(
+            "Warning: could not load '{}' ({}); {}",
+            asset.reference, asset.error, action
+        );
+    }
+}
+
+/// Load the input scene, apply the --overrides file, if any, and check or normalize
+/// its materials as asked
+fn load_scene(args: &Args, asset_options: &AssetOptions) -> Result<Scene, String> {
+    let mut scene = Scene::from_json_file_with_assets(args.input(), asset_options)
+        .map_err(|e| format!("Error loading scene from '{}': {}", args.input(), e))?;
+    if let Some(path) = &args.overrides {
+        Overrides::from_json_file(path)
+            .map_err(|e| e.to_string())
+            .and_then(|overrides| overrides.apply(&mut scene))
+            .map_err(|e| format!("Error applying overrides from '{}': {}", path, e))?;
+    }
+    if args.normalize_materials {
+        for warning in scene.normalize_materials() {
+            eprintln!("Normalized {}", warning);
+        }
+    } else if args.check_materials {
+        for warning in scene.material_warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+    for inferred in &scene.inferred_units {
+        eprintln!("Warning: {}", inferred);
+    }
+    if args.fit {
+        scene.camera.fit = Some(true);
+        for camera in scene.cameras.values_mut() {
+            camera.fit = Some(true);
+        }
+    }
+    // Fitted here as well as by the renderer so the image takes the fitted aspect ratio
+    scene.camera = scene
+        .fitted_camera()
+        .map_err(|e| format!("Error fitting camera: {}", e))?;
+    Ok(scene)
+}
+
+/// Render, then re-render every time the input file is modified
+///
+/// Edits that leave the camera and geometry alone (materials, lights, fog) reuse the
+/// previous frame's primary hits, so only shading is recomputed.
+/// Add the exposure `--auto-exposure` measures to `renderer`'s tone mapping, and report
+/// the tone mapping used
+fn settle_exposure(args: &Args, renderer: Renderer, scene: &Scene) -> Renderer {
+    let mut tone_mapping = renderer.tone_mapping;
+    if args.auto_exposure {
+        match renderer.auto_exposure(scene) {
+            Ok(exposure) => {
+                println!("Auto exposure: {:+.2} stops", exposure);
+                tone_mapping.exposure += exposure;
+            }
+            Err(e) => {
+                eprintln!("Error measuring exposure: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if tone_mapping != ToneMapping::default() {
+        println!(
+            "Tone mapping: {:?}, exposure {:+.2} stops",
+            tone_mapping.operator, tone_mapping.exposure
+        );
+    }
+    renderer.with_tone_mapping(tone_mapping)
+}
+
+fn watch(args: &Args, asset_options: &AssetOptions, renderer: Renderer, scene: Scene) -> ! {
+    let modified = |path: &str| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+    let mut session = PreviewSession::new(renderer);
+    let mut scene = Some(scene);
+    // Either file changing reloads the scene; overrides-only edits usually just reshade
+    let inputs_modified = || {
+        (
+            modified(args.input()),
+            args.overrides.as_deref().and_then(modified),
+        )
+    };
+    let mut last_modified: (Option<SystemTime>, Option<SystemTime>) = inputs_modified();
+
+    loop {
+        if let Some(scene) = scene.take() {
+            let start = Instant::now();
+            match session.render(&scene) {
+                Ok((image, update)) => match image.save(args.output()) {
+                    Ok(()) => {
+                        let kind = match update {
+                            PreviewUpdate::Full => "full render",
+                            PreviewUpdate::ShadingOnly => "shading only",
+                        };
+                        println!(
+                            "Rendered '{}' ({}, {:.2}s); watching '{}' for changes...",
+                            args.output(),
+                            kind,
+                            start.elapsed().as_secs_f64(),
+                            args.input()
+                        );
+                    }
+                    Err(e) => eprintln!("Error saving image: {}", e),
+                },
+                Err(e) => eprintln!("Error rendering image: {}", e),
+            }
+        }
+
+        std::thread::sleep(Duration::from_millis(250));
+
+        let current = inputs_modified();
+        if current != last_modified {
+            last_modified = current;
+            // Keep watching through invalid intermediate edits
+            match load_scene(args, asset_options) {
+                Ok(updated) => {
+                    warn_missing_assets(&updated, asset_options.missing_asset);
+                    scene = Some(updated);
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
 }
@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SCENE: &str = r##"{
+    "camera": {"kind": "ortho", "position": [0, -10, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 8, "height": 8},
+    "objects": [{"kind": "sphere", "center": [1, 0, 0], "radius": 1,
+        "material": {"color": "#FFFFFF", "ambient": 0.2, "diffuse": 0.8, "specular": 0, "shininess": 1}}],
+    "lights": [{"position": [0, -10, 5], "color": "#FFFFFF", "intensity": 1}],
+    "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}, "background_color": "#000000"}
+}"##;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rtrace-cli-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run the CLI on `scene` with the render cache in `cache`, plus `extra` arguments
+fn render(scene: &Path, output: &Path, cache: &Path, extra: &[&str]) -> String {
+    let result = Command::new(env!("CARGO_BIN_EXE_rtrace"))
+        .arg("-i")
+        .arg(scene)
+        .arg("-o")
+        .arg(output)
+        .args(["-s", "64", "--render-cache"])
+        .arg(cache)
+        .args(extra)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&result.stdout).to_string();
+    assert!(
+        result.status.success(),
+        "{}{}",
+        stdout,
+        String::from_utf8_lossy(&result.stderr)
+    );
+    stdout
+}
+
+#[test]
+fn test_fit_misses_the_render_cache() {
+    let dir = temp_dir("fit");
+    let scene = dir.join("scene.json");
+    std::fs::write(&scene, SCENE).unwrap();
+    let cache = dir.join("cache");
+    let (plain, fitted) = (dir.join("plain.png"), dir.join("fitted.png"));
+
+    render(&scene, &plain, &cache, &[]);
+    let stdout = render(&scene, &fitted, &cache, &["--fit"]);
+    assert!(!stdout.contains("Render cache hit"), "{}", stdout);
+    assert_ne!(
+        std::fs::read(&plain).unwrap(),
+        std::fs::read(&fitted).unwrap()
+    );
+
+    // Each is still found again on its own
+    let again = dir.join("again.png");
+    assert!(render(&scene, &again, &cache, &["--fit"]).contains("Render cache hit"));
+    assert_eq!(
+        std::fs::read(&again).unwrap(),
+        std::fs::read(&fitted).unwrap()
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_auto_exposure_hits_the_render_cache_before_measuring() {
+    let dir = temp_dir("auto-exposure");
+    let scene = dir.join("scene.json");
+    std::fs::write(&scene, SCENE).unwrap();
+    let cache = dir.join("cache");
+    let (plain, exposed) = (dir.join("plain.png"), dir.join("exposed.png"));
+
+    render(&scene, &plain, &cache, &[]);
+    let stdout = render(&scene, &exposed, &cache, &["--auto-exposure"]);
+    assert!(!stdout.contains("Render cache hit"), "{}", stdout);
+    assert!(stdout.contains("Auto exposure"), "{}", stdout);
+
+    // A repeat is found without the pre-pass
+    let again = dir.join("again.png");
+    let stdout = render(&scene, &again, &cache, &["--auto-exposure"]);
+    assert!(stdout.contains("Render cache hit"), "{}", stdout);
+    assert!(!stdout.contains("Auto exposure"), "{}", stdout);
+    assert_eq!(
+        std::fs::read(&again).unwrap(),
+        std::fs::read(&exposed).unwrap()
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(scene) = rtrace::Scene::from_json_str(json) else {
+        return;
+    };
+
+    // Exercise the parsing that happens lazily when a scene is rendered
+    let _ = scene.get_outline_config();
+    let _ = scene.compute_finite_bounds();
+    let _ = rtrace::Renderer::new(4, 4).render(&scene);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Covers both the ASCII and binary STL parsers via format auto-detection
+fuzz_target!(|data: &[u8]| {
+    let _ = rtrace::Mesh::from_stl_bytes(data);
+});
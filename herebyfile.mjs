@@ -287,7 +287,7 @@ for (const file of docSceneFiles) {
     ).join('')}`;
     
     // Build command with base parameters
-    let command = `./target/release/rtrace -i doc/scenes/${file} -o doc/images/${baseName}.png -s 500`;
+    let command = `./target/release/rtrace -i doc/scenes/${file} -o doc/images/${baseName}.png -s 500 --render-cache target/render-cache`;
     
     // Add metadata-based parameters if available
     const metadata = docSceneMetadata[file];
@@ -312,7 +312,7 @@ for (const special of docSpecialScenes) {
         word.charAt(0).toUpperCase() + word.slice(1)
     ).join('')}`;
     
-    const command = `./target/release/rtrace -i doc/scenes/${special.scene} -o doc/images/${special.name}.png -s 500 ${special.params}`;
+    const command = `./target/release/rtrace -i doc/scenes/${special.scene} -o doc/images/${special.name}.png -s 500 --render-cache target/render-cache ${special.params}`;
     
     docRenderTasks[taskName] = task({
         name: `render:doc:${special.name}`,
@@ -341,10 +341,10 @@ for (const outlineDemo of outlineDemoScenes) {
                 delete scene.scene_settings.outline;
             }
             fs.writeFileSync('/tmp/${outlineDemo.name}.json', JSON.stringify(scene, null, 2));
-        " && ./target/release/rtrace -i /tmp/${outlineDemo.name}.json -o doc/images/${outlineDemo.name}.png -s 500 ${outlineDemo.params}`;
+        " && ./target/release/rtrace -i /tmp/${outlineDemo.name}.json -o doc/images/${outlineDemo.name}.png -s 500 --render-cache target/render-cache ${outlineDemo.params}`;
     } else {
         const sceneBasePath = outlineDemo.scene.includes('/') ? outlineDemo.scene : `examples/${outlineDemo.scene}`;
-        command = `./target/release/rtrace -i ${sceneBasePath} -o doc/images/${outlineDemo.name}.png -s 500 ${outlineDemo.params}`;
+        command = `./target/release/rtrace -i ${sceneBasePath} -o doc/images/${outlineDemo.name}.png -s 500 --render-cache target/render-cache ${outlineDemo.params}`;
     }
     
     docRenderTasks[taskName] = task({
@@ -364,7 +364,7 @@ for (const scene of docMultiFileScenes) {
     
     const commands = scene.files.map(file => {
         const outputName = basename(file, '.json');
-        return `./target/release/rtrace -i doc/scenes/${file} -o doc/images/${outputName}.png -s 500`;
+        return `./target/release/rtrace -i doc/scenes/${file} -o doc/images/${outputName}.png -s 500 --render-cache target/render-cache`;
     }).join(' && ');
 
     docRenderTasks[taskName] = task({
@@ -7,15 +7,88 @@
 export declare function helloWorld(): string
 /** Advanced function that takes parameters (demonstration) */
 export declare function greetWithName(name: string): string
-/** Render a scene from JSON string directly */
-export declare function renderScene(sceneJson: string, outputPath: string, size?: number | undefined | null): string
-/** Render a scene from JSON string with specific thread count */
-export declare function renderSceneThreaded(sceneJson: string, outputPath: string, size?: number | undefined | null, threadCount?: number | undefined | null): string
-/** Render a scene from JSON string with brute force (no k-d tree) */
-export declare function renderSceneBruteForce(sceneJson: string, outputPath: string, size?: number | undefined | null): string
-/** Render a scene from JSON file directly (handles relative paths correctly) */
-export declare function renderSceneFromFile(sceneFilePath: string, outputPath: string, size?: number | undefined | null): string
-/** Render a scene from JSON file with specific thread count */
-export declare function renderSceneFromFileThreaded(sceneFilePath: string, outputPath: string, size?: number | undefined | null, threadCount?: number | undefined | null): string
-/** Render a scene from JSON file with brute force (no k-d tree) */
-export declare function renderSceneFromFileBruteForce(sceneFilePath: string, outputPath: string, size?: number | undefined | null): string
+/** Anti-aliasing sampling mode */
+export const enum AntiAliasing {
+  Quincunx = 'quincunx',
+  Stochastic = 'stochastic',
+  NoJitter = 'no-jitter'
+}
+/** Sampling options; unset fields fall back to the scene's `render_settings` */
+export interface SamplingOptions {
+  antiAliasing?: AntiAliasing
+  samples?: number
+}
+/**
+ * Outline settings for `render`, in place of the scene's `outline` block
+ *
+ * Unset fields take the same defaults as in a scene file; colors are hex strings.
+ */
+export interface OutlineOptions {
+  /** `false` draws no outlines, even where the scene has them (default `true`) */
+  enabled?: boolean
+  color?: string
+  thickness?: number
+  depthWeight?: number
+  normalWeight?: number
+  objectWeight?: number
+  threshold?: number
+  use8Neighbors?: boolean
+  creaseAngle?: number
+  supersample?: number
+}
+/**
+ * Where `render` writes the image and how it renders it
+ *
+ * Unset fields fall back to the scene's `render_settings` and `outline`, then to the
+ * CLI's defaults: a 1000 pixel diagonal, all cores, k-d trees, quincunx sampling and
+ * seed 0. Give either `size` or `width` and `height`; one of those alone takes the
+ * other from the camera's aspect ratio.
+ */
+export interface RenderOptions {
+  /**
+   * Image file to write; its extension picks the format (`.exr` and `.hdr` keep
+   * linear float radiance)
+   */
+  output: string
+  /** Image diagonal in pixels, the aspect ratio following the camera */
+  size?: number
+  width?: number
+  height?: number
+  /** Worker threads (all cores if unset) */
+  threads?: number
+  /** `false` intersects meshes by brute force instead of through k-d trees */
+  kdtree?: boolean
+  antiAliasing?: AntiAliasing
+  samples?: number
+  seed?: number
+  outline?: OutlineOptions
+  /** Maximum ray bounces for reflections and refraction */
+  maxDepth?: number
+}
+/**
+ * Render a scene on the libuv thread pool, resolving with a summary
+ *
+ * `sceneSource` is either scene JSON or the path of a scene file. The JS thread stays
+ * free while the scene loads and renders. `onProgress` receives the same events as
+ * `renderSceneWithProgress`'s callback, but can't cancel the render; an exception it
+ * throws is uncaught rather than failing the render.
+ */
+export declare function render(sceneSource: string, options: RenderOptions, onProgress?: (...args: any[]) => any | undefined | null): Promise<string>
+/**
+ * Render a scene from JSON string progressively, reporting a snapshot after each pass
+ *
+ * `callback` receives `{ pass, passes, width, height, pixels }` where `pixels` is a
+ * Buffer of packed RGB bytes holding the running average so far. Returning `false`
+ * from the callback (or throwing) cancels the render. The final image is written to
+ * `output_path`.
+ */
+export declare function renderSceneProgressive(sceneJson: string, outputPath: string, size: number | undefined | null, passes: number | undefined | null, callback: (...args: any[]) => any): string
+/**
+ * Render a scene from JSON string, reporting progress to a JS callback
+ *
+ * `callback` receives `{ phase, percent, completedPixels, totalPixels, elapsedMs, etaMs }`
+ * where `phase` is one of `"setup"`, `"tracing"`, `"postProcessing"` or `"finished"` and
+ * `etaMs` is `null` until an estimate is available. Returning `false` from the callback
+ * (or throwing) cancels the render.
+ */
+export declare function renderSceneWithProgress(sceneJson: string, outputPath: string, size: number | undefined | null, callback: (...args: any[]) => any, options?: SamplingOptions | undefined | null): string
@@ -31,14 +31,12 @@ async function demonstrateMultiThreading() {
         console.time(test.name);
         
         try {
-            let result;
-            if (test.threads === null) {
-                // Use default multi-threading (all cores)
-                result = rtrace.renderSceneFromFile(sceneFile, `./demo_${test.name.toLowerCase().replace(' ', '_')}.png`, 500);
-            } else {
-                // Use specific thread count
-                result = rtrace.renderSceneFromFileThreaded(sceneFile, `./demo_${test.name.toLowerCase().replace(' ', '_')}.png`, 500, test.threads);
-            }
+            // Without a thread count, all cores are used
+            const result = await rtrace.render(sceneFile, {
+                output: `./demo_${test.name.toLowerCase().replace(' ', '_')}.png`,
+                size: 500,
+                threads: test.threads,
+            });
             console.timeEnd(test.name);
             console.log(`   ✓ ${result}\n`);
         } catch (error) {
@@ -54,9 +52,9 @@ async function demonstrateMultiThreading() {
     console.log('   • Seamless integration with existing API');
     
     console.log('\n📝 API Usage:');
-    console.log('   rtrace.renderScene(json, output, size)              // Auto multi-threading');
-    console.log('   rtrace.renderSceneThreaded(json, output, size, threads) // Custom threads');
-    console.log('   rtrace.renderSceneFromFile(file, output, size)      // File-based rendering');
+    console.log('   await rtrace.render(json, { output, size })          // Auto multi-threading, async');
+    console.log('   await rtrace.render(json, { output, size, threads }) // Custom threads');
+    console.log('   await rtrace.render(file, { output, size })          // File-based rendering');
     
     // Clean up demo files
     setTimeout(() => {
@@ -143,19 +143,21 @@ console.log('Scene JSON created, rendering image...\n');
 // Define output path in examples directory
 const outputPath = path.join(__dirname, '..', 'examples', 'radial_spheres_1000.png');
 
-try {
-    // Render the scene using the Node.js binding
-    const result = rtrace.renderScene(sceneJson, outputPath, 1000);
-    console.log('✅ Render successful!');
-    console.log(result);
-    console.log(`\nOutput saved to: ${outputPath}`);
+(async () => {
+    try {
+        // Render the scene using the Node.js binding
+        const result = await rtrace.render(sceneJson, { output: outputPath, size: 1000 });
+        console.log('✅ Render successful!');
+        console.log(result);
+        console.log(`\nOutput saved to: ${outputPath}`);
     
-    // Also save the scene JSON for reference
-    const jsonPath = path.join(__dirname, '..', 'examples', 'radial_spheres.json');
-    require('fs').writeFileSync(jsonPath, sceneJson);
-    console.log(`Scene JSON saved to: ${jsonPath}`);
+        // Also save the scene JSON for reference
+        const jsonPath = path.join(__dirname, '..', 'examples', 'radial_spheres.json');
+        require('fs').writeFileSync(jsonPath, sceneJson);
+        console.log(`Scene JSON saved to: ${jsonPath}`);
     
-} catch (error) {
-    console.error('❌ Render failed:', error);
-    process.exit(1);
-}
\ No newline at end of file
+    } catch (error) {
+        console.error('❌ Render failed:', error);
+        process.exit(1);
+    }
+})();
\ No newline at end of file
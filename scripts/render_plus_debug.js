@@ -9,13 +9,13 @@ async function renderScene(sceneFile, outputPrefix, size = 1000) {
   // K-d tree version
   console.log("Rendering WITH k-d tree...");
   const kdtreeOutput = `../examples/${outputPrefix}_kdtree_${size}.png`;
-  const kdtreeResult = rtrace.renderScene(sceneJson, kdtreeOutput, size);
+  const kdtreeResult = await rtrace.render(sceneJson, { output: kdtreeOutput, size });
   console.log("✓", kdtreeResult);
   
   // Brute force version  
   console.log("Rendering WITHOUT k-d tree (brute force)...");
   const bruteOutput = `../examples/${outputPrefix}_brute_force_${size}.png`;
-  const bruteResult = rtrace.renderSceneBruteForce(sceneJson, bruteOutput, size);
+  const bruteResult = await rtrace.render(sceneJson, { output: bruteOutput, size, kdtree: false });
   console.log("✓", bruteResult);
 }
 
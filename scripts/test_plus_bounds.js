@@ -1,25 +1,27 @@
 const rtrace = require('../rtrace.node');
 const fs = require('fs');
 
-try {
-  console.log("Loading plus.stl scene...");
-  const sceneJson = fs.readFileSync('.../examples/plus_front.json', 'utf8');
+(async () => {
+  try {
+    console.log("Loading plus.stl scene...");
+    const sceneJson = fs.readFileSync('.../examples/plus_front.json', 'utf8');
   
-  // Render with k-d tree
-  console.log("\nRendering plus.stl WITH k-d tree acceleration...");
-  const resultKdTree = rtrace.renderScene(sceneJson, '.../examples/plus_kdtree_500.png', 500);
-  console.log("K-d tree result:", resultKdTree);
+    // Render with k-d tree
+    console.log("\nRendering plus.stl WITH k-d tree acceleration...");
+    const resultKdTree = await rtrace.render(sceneJson, { output: '.../examples/plus_kdtree_500.png', size: 500 });
+    console.log("K-d tree result:", resultKdTree);
   
-  // Render without k-d tree (brute force)
-  console.log("\nRendering plus.stl WITHOUT k-d tree (brute force)...");
-  const resultBruteForce = rtrace.renderSceneBruteForce(sceneJson, '.../examples/plus_brute_force_500.png', 500);
-  console.log("Brute force result:", resultBruteForce);
+    // Render without k-d tree (brute force)
+    console.log("\nRendering plus.stl WITHOUT k-d tree (brute force)...");
+    const resultBruteForce = await rtrace.render(sceneJson, { output: '.../examples/plus_brute_force_500.png', size: 500, kdtree: false });
+    console.log("Brute force result:", resultBruteForce);
   
-  console.log("\n✅ Both renders completed successfully!");
-  console.log("Compare these images:");
-  console.log("- K-d tree:     ../examples/plus_kdtree_500.png");
-  console.log("- Brute force:  ../examples/plus_brute_force_500.png");
+    console.log("\n✅ Both renders completed successfully!");
+    console.log("Compare these images:");
+    console.log("- K-d tree:     ../examples/plus_kdtree_500.png");
+    console.log("- Brute force:  ../examples/plus_brute_force_500.png");
   
-} catch (error) {
-  console.error("Error:", error);
-}
\ No newline at end of file
+  } catch (error) {
+    console.error("Error:", error);
+  }
+})();
\ No newline at end of file
@@ -0,0 +1,517 @@
+//! Keyframed camera and object transforms, rendered as numbered frames
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scene::{center_into_transform, Object, Scene, Vec3};
+
+/// Frames per second when an animation doesn't set `fps`
+pub const DEFAULT_FPS: f64 = 24.0;
+
+/// Camera and object keyframes over a sequence of frames
+///
+/// Each keyframed value is interpolated linearly between the keyframes that set it
+/// and held before the first and after the last. Frames are numbered from 0, and
+/// keyframes may sit between or beyond them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Animation {
+    /// Number of frames to render
+    pub frames: u32,
+    /// Frames per second of GIF, APNG and video output, and of `camera_path` timing
+    /// (default 24)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f64>,
+    /// Camera keyframes, in frame order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera: Vec<CameraKeyframe>,
+    /// Waypoints the camera walks through from frame 0, in place of position and
+    /// target keyframes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera_path: Vec<Waypoint>,
+    /// Object keyframes keyed by object `name`; groups move as a whole
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub objects: BTreeMap<String, ObjectTrack>,
+    /// Indices each name covers once groups are expanded, found when the scene loads
+    #[serde(skip)]
+    targets: Option<BTreeMap<String, Vec<usize>>>,
+}
+
+/// Camera settings at one frame; unset values follow the other keyframes
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraKeyframe {
+    pub frame: f64,
+    pub position: Option<[f64; 3]>,
+    pub target: Option<[f64; 3]>,
+    pub up: Option<[f64; 3]>,
+    /// Field of view in degrees, for perspective and fisheye cameras
+    pub fov: Option<f64>,
+}
+
+/// One stop on a camera path
+///
+/// The camera passes through each waypoint's position on a smooth curve, starting
+/// and ending at rest, and turns smoothly between their targets.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Waypoint {
+    pub position: [f64; 3],
+    /// Point looked at from here; None looks along the path
+    pub target: Option<[f64; 3]>,
+    /// Seconds taken to reach the next waypoint; unused on the last
+    pub duration: Option<f64>,
+}
+
+/// Keyframes for one named object
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectTrack {
+    /// Point the object turns and scales about, in scene coordinates
+    #[serde(default)]
+    pub pivot: [f64; 3],
+    /// Keyframes, in frame order
+    pub keyframes: Vec<ObjectKeyframe>,
+}
+
+/// Move of an object at one frame, applied after its own and its groups' transforms
+///
+/// The object is scaled, then turned about the pivot, then moved. Unset values
+/// follow the other keyframes, or leave the object alone if none set them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectKeyframe {
+    pub frame: f64,
+    /// Move along each axis, in scene units
+    pub translate: Option<[f64; 3]>,
+    /// Turn about each axis in degrees, as in `rotate` transforms; 360 is a full turn
+    pub rotate: Option<[f64; 3]>,
+    /// Uniform scale
+    pub scale: Option<f64>,
+}
+
+impl Animation {
+    fn validate(&self) -> Result<(), String> {
+        if self.frames == 0 {
+            return Err("Animation frames must be greater than 0".to_string());
+        }
+        let in_order = |frames: &[f64]| {
+            frames.iter().all(|frame| frame.is_finite())
+                && frames.windows(2).all(|pair| pair[0] < pair[1])
+        };
+        let camera_frames: Vec<f64> = self.camera.iter().map(|key| key.frame).collect();
+        if !in_order(&camera_frames) {
+            return Err("Camera keyframes must be in increasing frame order".to_string());
+        }
+        for (name, track) in &self.objects {
+            let frames: Vec<f64> = track.keyframes.iter().map(|key| key.frame).collect();
+            if !in_order(&frames) {
+                return Err(format!(
+                    "Keyframes for '{}' must be in increasing frame order",
+                    name
+                ));
+            }
+        }
+
+        if !self.camera_path.is_empty() {
+            if (self.camera.iter()).any(|key| key.position.is_some() || key.target.is_some()) {
+                return Err(
+                    "Camera keyframes can't set position or target alongside a camera path"
+                        .to_string(),
+                );
+            }
+            let fps = self.fps.unwrap_or(DEFAULT_FPS);
+            if !(fps.is_finite() && fps > 0.0) {
+                return Err(format!("Frame rate must be greater than 0, got {}", fps));
+            }
+            let leading = &self.camera_path[..self.camera_path.len() - 1];
+            for (i, waypoint) in leading.iter().enumerate() {
+                if !waypoint.duration.is_some_and(|duration| duration > 0.0) {
+                    return Err(format!(
+                        "Camera path waypoint {} needs a duration greater than 0 to reach the next",
+                        i
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Find the objects each name covers, in a scene whose groups are not yet expanded
+    ///
+    /// Called by `Scene::expand_groups`, as group names don't survive expansion. Names
+    /// that match nothing in the scene are an error, as in jitter blocks.
+    pub(crate) fn bind(&mut self, objects: &[Object]) -> Result<(), String> {
+        self.validate()?;
+        if self.targets.is_none() {
+            self.targets = Some(self.find_targets(objects)?);
+        }
+        Ok(())
+    }
+
+    fn find_targets(&self, objects: &[Object]) -> Result<BTreeMap<String, Vec<usize>>, String> {
+        let mut targets = BTreeMap::new();
+        for name in self.objects.keys() {
+            let mut indices = Vec::new();
+            find_named(objects, name, &mut 0, &mut indices);
+            if indices.is_empty() {
+                return Err(format!("Animation for unknown object '{}'", name));
+            }
+            targets.insert(name.clone(), indices);
+        }
+        Ok(targets)
+    }
+
+    /// Move the camera and objects of a loaded scene to where they are at `frame`
+    pub fn apply(&self, scene: &mut Scene, frame: u32) -> Result<(), String> {
+        self.validate()?;
+        let frame = frame as f64;
+
+        let camera = &mut scene.camera;
+        let keys = &self.camera;
+        if let Some(position) = sample(keys.iter().map(|k| (k.frame, k.position)), frame) {
+            camera.position = position;
+        }
+        if let Some(target) = sample(keys.iter().map(|k| (k.frame, k.target)), frame) {
+            camera.target = target;
+        }
+        if let Some(up) = sample(keys.iter().map(|k| (k.frame, k.up)), frame) {
+            camera.up = up;
+        }
+        let fovs = keys.iter().map(|k| (k.frame, k.fov.map(|fov| [fov])));
+        if let Some([fov]) = sample(fovs, frame) {
+            camera.fov = Some(fov);
+        }
+        if !self.camera_path.is_empty() {
+            let seconds = frame / self.fps.unwrap_or(DEFAULT_FPS);
+            let (position, target) = walk(&self.camera_path, seconds);
+            camera.position = position.into();
+            if let Some(target) = target {
+                camera.target = target.into();
+            }
+        }
+
+        // Scenes that were never expanded have no groups, so names are found as they are
+        let found;
+        let targets = match &self.targets {
+            Some(targets) => targets,
+            None => {
+                found = self.find_targets(&scene.objects)?;
+                &found
+            }
+        };
+
+        for (name, track) in &self.objects {
+            let transform = track.transform(frame);
+            if transform.is_empty() {
+                continue;
+            }
+            for &index in &targets[name] {
+                let object = scene.objects.get_mut(index).ok_or_else(|| {
+                    format!("Animation for '{}' doesn't match the scene's objects", name)
+                })?;
+                center_into_transform(object);
+                object
+                    .transform_mut()
+                    .get_or_insert_with(Vec::new)
+                    .extend(transform.iter().cloned());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ObjectTrack {
+    /// Transforms that move the object to where it is at `frame`
+    fn transform(&self, frame: f64) -> Vec<String> {
+        let keys = &self.keyframes;
+        let translate = sample(keys.iter().map(|k| (k.frame, k.translate)), frame);
+        let rotate = sample(keys.iter().map(|k| (k.frame, k.rotate)), frame);
+        let scale = sample(keys.iter().map(|k| (k.frame, k.scale.map(|s| [s]))), frame);
+
+        let mut transform = Vec::new();
+        let [px, py, pz] = self.pivot;
+        let pivoted = self.pivot != [0.0; 3] && (rotate.is_some() || scale.is_some());
+        if pivoted {
+            transform.push(format!("translate({}, {}, {})", -px, -py, -pz));
+        }
+        if let Some([scale]) = scale {
+            transform.push(format!("scale({}, {}, {})", scale, scale, scale));
+        }
+        if let Some([x, y, z]) = rotate {
+            transform.push(format!("rotate({}, {}, {})", x, y, z));
+        }
+        let [dx, dy, dz] = translate.unwrap_or_default();
+        if pivoted {
+            transform.push(format!("translate({}, {}, {})", px + dx, py + dy, pz + dz));
+        } else if translate.is_some() {
+            transform.push(format!("translate({}, {}, {})", dx, dy, dz));
+        }
+        transform
+    }
+}
+
+/// Value at `frame` of a property set by some keyframes, interpolated linearly between
+/// them and held beyond the first and last; None if no keyframe sets it
+fn sample<const N: usize>(
+    keys: impl Iterator<Item = (f64, Option<[f64; N]>)>,
+    frame: f64,
+) -> Option<[f64; N]> {
+    let mut before: Option<(f64, [f64; N])> = None;
+    for (key_frame, value) in keys {
+        let Some(value) = value else {
+            continue;
+        };
+        if key_frame >= frame {
+            return Some(match before {
+                Some((before_frame, before_value)) => {
+                    let t = (frame - before_frame) / (key_frame - before_frame);
+                    std::array::from_fn(|i| before_value[i] + (value[i] - before_value[i]) * t)
+                }
+                None => value,
+            });
+        }
+        before = Some((key_frame, value));
+    }
+    before.map(|(_, value)| value)
+}
+
+/// Camera position and target `seconds` along a path of waypoints, held at either end
+///
+/// Both follow a cubic Hermite curve through the waypoints, with the velocity at each
+/// inner waypoint set by its neighbours so speed changes smoothly, and zero at the
+/// ends. Waypoints without a target look one unit ahead along the path, which makes
+/// the view direction follow the turns. The target is None if a waypoint without one
+/// has nowhere to look, such as a path of one waypoint.
+fn walk(waypoints: &[Waypoint], seconds: f64) -> (Vec3, Option<Vec3>) {
+    let positions: Vec<Vec3> = waypoints.iter().map(|w| Vec3::from(w.position)).collect();
+    let last = positions.len() - 1;
+    let ahead = |i: usize| {
+        let direction = positions[(i + 1).min(last)] - positions[i.saturating_sub(1)];
+        (direction.norm() > 0.0).then(|| positions[i] + direction.normalize())
+    };
+    let targets: Vec<Option<Vec3>> = (waypoints.iter().enumerate())
+        .map(|(i, waypoint)| waypoint.target.map(Vec3::from).or_else(|| ahead(i)))
+        .collect();
+    let times: Vec<f64> = std::iter::once(0.0)
+        .chain(waypoints[..last].iter().scan(0.0, |time, waypoint| {
+            *time += waypoint.duration.unwrap_or_default();
+            Some(*time)
+        }))
+        .collect();
+
+    let segment = (times[1..].iter())
+        .position(|&time| seconds < time)
+        .unwrap_or(last);
+    if segment == last {
+        return (positions[last], targets[last]);
+    }
+    let span = times[segment + 1] - times[segment];
+    let s = ((seconds - times[segment]) / span).max(0.0);
+    let hermite = |points: &[Vec3]| {
+        let velocity = |i: usize| {
+            if i == 0 || i == last {
+                return Vec3::zeros();
+            }
+            (points[i + 1] - points[i - 1]) / (times[i + 1] - times[i - 1])
+        };
+        let (s2, s3) = (s * s, s * s * s);
+        points[segment] * (2.0 * s3 - 3.0 * s2 + 1.0)
+            + velocity(segment) * span * (s3 - 2.0 * s2 + s)
+            + points[segment + 1] * (3.0 * s2 - 2.0 * s3)
+            + velocity(segment + 1) * span * (s3 - s2)
+    };
+    let targets: Option<Vec<Vec3>> = targets.into_iter().collect();
+    let target = targets.map(|targets| hermite(&targets));
+    (hermite(&positions), target)
+}
+
+/// Push the indices that objects named `name` get once groups are expanded, counting
+/// from `next`
+fn find_named(objects: &[Object], name: &str, next: &mut usize, found: &mut Vec<usize>) {
+    for object in objects {
+        let start = *next;
+        match object {
+            Object::Group { objects, .. } => find_named(objects, name, next, found),
+            _ => *next += 1,
+        }
+        if object.name() == Some(name) {
+            found.extend(start..*next);
+        }
+    }
+}
+
+/// File for one frame of a sequence: the frame number, zero-padded to four digits,
+/// goes before the extension, so `turntable.png` becomes `turntable.0007.png`
+pub fn frame_path(path: &str, frame: u32) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{:04}.{}", stem, frame, extension.to_string_lossy()),
+        None => format!("{}.{:04}", stem, frame),
+    };
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::parse_transforms;
+    use nalgebra::Point3;
+
+    const SCENE: &str = r##"{
+        "camera": {"kind": "perspective", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4, "fov": 40},
+        "objects": [
+            {"kind": "sphere", "name": "ball", "center": [2, 0, 0], "radius": 1,
+             "material": {"color": "#808080", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}},
+            {"kind": "group", "name": "stack", "transform": ["translate(0, 0, 1)"], "objects": [
+                {"kind": "cube", "center": [1, 0, 0], "size": [1, 1, 1],
+                 "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}},
+                {"kind": "sphere", "center": [0, 1, 0], "radius": 0.5,
+                 "material": {"color": "#00FF00", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}
+            ]}
+        ],
+        "lights": [],
+        "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}, "background_color": "#000000"},
+        "animation": {
+            "frames": 5,
+            "camera": [
+                {"frame": 0, "position": [0, -5, 0]},
+                {"frame": 4, "position": [0, -9, 0], "fov": 60}
+            ],
+            "objects": {
+                "stack": {"keyframes": [
+                    {"frame": 0, "rotate": [0, 0, 0]},
+                    {"frame": 4, "rotate": [0, 0, 360]}
+                ]},
+                "ball": {"pivot": [2, 0, 0], "keyframes": [
+                    {"frame": 2, "translate": [0, 0, 0], "scale": 1},
+                    {"frame": 3, "translate": [0, 0, 2], "scale": 2}
+                ]}
+            }
+        }
+    }"##;
+
+    #[test]
+    fn test_sample() {
+        let keys = [(0.0, Some([0.0])), (1.0, None), (4.0, Some([8.0]))];
+        assert_eq!(sample(keys.into_iter(), -1.0), Some([0.0]));
+        assert_eq!(sample(keys.into_iter(), 1.0), Some([2.0]));
+        assert_eq!(sample(keys.into_iter(), 4.0), Some([8.0]));
+        assert_eq!(sample(keys.into_iter(), 9.0), Some([8.0]));
+        assert_eq!(sample([(0.0, None::<[f64; 1]>)].into_iter(), 0.0), None);
+    }
+
+    #[test]
+    fn test_frames() {
+        let scene = Scene::from_json_str(SCENE).unwrap();
+        assert_eq!(scene.animation.as_ref().unwrap().frames, 5);
+        let center = |scene: &Scene, index: usize, local: [f64; 3]| {
+            let transform = scene.objects[index].clone().transform_mut().take();
+            parse_transforms(&transform.unwrap_or_default())
+                .unwrap()
+                .transform_point(&Point3::from(local))
+        };
+
+        // Halfway through, the camera has moved halfway; the fov is held from its one keyframe
+        let frame = scene.frame(2).unwrap();
+        assert!(frame.animation.is_none());
+        assert_eq!(frame.camera.position, [0.0, -7.0, 0.0]);
+        assert_eq!(frame.camera.fov, Some(60.0));
+
+        // Both members of the group turned half way round the origin
+        let cube = center(&frame, 1, [0.0; 3]);
+        assert!((cube - Point3::new(-1.0, 0.0, 1.0)).norm() < 1e-9);
+        let sphere = center(&frame, 2, [0.0, 1.0, 0.0]);
+        assert!((sphere - Point3::new(0.0, -1.0, 1.0)).norm() < 1e-9);
+
+        // The ball holds still until its first keyframe, then grows about its pivot
+        assert_eq!(center(&scene.frame(0).unwrap(), 0, [2.0, 0.0, 0.0]).x, 2.0);
+        let frame = scene.frame(3).unwrap();
+        let ball = center(&frame, 0, [2.0, 0.0, 0.0]);
+        assert!((ball - Point3::new(2.0, 0.0, 2.0)).norm() < 1e-9);
+        let top = center(&frame, 0, [2.0, 0.0, 1.0]);
+        assert!((top - Point3::new(2.0, 0.0, 4.0)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn test_camera_path() {
+        let mut scene: serde_json::Value = serde_json::from_str(SCENE).unwrap();
+        scene["animation"] = serde_json::json!({
+            "frames": 30,
+            "fps": 10,
+            "camera": [{"frame": 0, "fov": 70}],
+            "camera_path": [
+                {"position": [0, 0, 0], "duration": 1},
+                {"position": [10, 0, 0], "duration": 1},
+                {"position": [10, 10, 0], "target": [10, 20, 0]}
+            ]
+        });
+        let scene = Scene::from_json_str(&scene.to_string()).unwrap();
+        let camera = |frame: u32| {
+            let camera = scene.frame(frame).unwrap().camera;
+            (Vec3::from(camera.position), Vec3::from(camera.target))
+        };
+        let near = |a: Vec3, b: [f64; 3]| (a - Vec3::from(b)).norm() < 1e-9;
+
+        // Starts looking along the path, and passes the corner looking across it
+        let (position, target) = camera(0);
+        assert!(near(position, [0.0, 0.0, 0.0]) && near(target, [1.0, 0.0, 0.0]));
+        let (position, target) = camera(10);
+        let diagonal = 0.5f64.sqrt();
+        assert!(near(position, [10.0, 0.0, 0.0]));
+        assert!(near(target, [10.0 + diagonal, diagonal, 0.0]));
+        assert_eq!(scene.frame(10).unwrap().camera.fov, Some(70.0));
+
+        // Setting off from rest, it rounds the corner rather than running straight into it
+        let (position, _) = camera(5);
+        assert!(near(position, [4.375, -0.625, 0.0]), "{:?}", position);
+
+        // Ends at the last waypoint and stays there
+        let (position, target) = camera(29);
+        assert!(near(position, [10.0, 10.0, 0.0]) && near(target, [10.0, 20.0, 0.0]));
+
+        let load = |animation: serde_json::Value| {
+            let mut scene: serde_json::Value = serde_json::from_str(SCENE).unwrap();
+            scene["animation"] = animation;
+            Scene::from_json_str(&scene.to_string())
+        };
+        let path = serde_json::json!([{"position": [0, 0, 0]}, {"position": [1, 0, 0]}]);
+        let error = load(serde_json::json!({"frames": 2, "camera_path": path}))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("waypoint 0 needs a duration"), "{}", error);
+        let keyframes = serde_json::json!([{"frame": 0, "target": [0, 0, 0]}]);
+        let path = serde_json::json!([{"position": [0, 0, 0]}]);
+        let animation = serde_json::json!({"frames": 2, "camera": keyframes, "camera_path": path});
+        assert!(load(animation).is_err());
+    }
+
+    #[test]
+    fn test_invalid_animation() {
+        let load = |animation: serde_json::Value| {
+            let mut scene: serde_json::Value = serde_json::from_str(SCENE).unwrap();
+            scene["animation"] = animation;
+            Scene::from_json_str(&scene.to_string())
+        };
+        let animation = serde_json::json!({"frames": 2, "objects": {"nothing": {"keyframes": []}}});
+        let error = load(animation).unwrap_err().to_string();
+        assert!(error.contains("unknown object 'nothing'"), "{}", error);
+
+        let keyframes = serde_json::json!([{"frame": 1, "fov": 30}, {"frame": 0, "fov": 40}]);
+        let error = load(serde_json::json!({"frames": 2, "camera": keyframes}))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("increasing frame order"), "{}", error);
+    }
+
+    #[test]
+    fn test_frame_path() {
+        assert_eq!(frame_path("out/turntable.png", 7), "out/turntable.0007.png");
+        assert_eq!(frame_path("frames", 12345), "frames.12345");
+    }
+}
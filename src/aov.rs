@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+
+use crate::colormap::ColorMap;
+
+/// Auxiliary output variable: a per-pixel data pass rendered alongside the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// Distance from the camera to the first hit (0 for background)
+    Depth,
+    /// World-space surface normal of the first hit (zero for background)
+    Normal,
+    /// Index of the hit object in the scene's `objects` list plus one (0 for background)
+    ObjectId,
+    /// Unlit surface color, including textures
+    Albedo,
+    /// Locally lit surface color, or the background
+    Direct,
+    /// Light arriving through reflections; `direct + indirect` is the shaded color
+    Indirect,
+    /// Opacity: 1 for objects, 0 for background, the shadow's strength on shadow catchers
+    Alpha,
+    /// Strength of the outline drawn by outline detection, 0 to 1, with or without it enabled
+    Outline,
+}
+
+impl Aov {
+    /// Every AOV, in the order they are documented
+    pub const ALL: [Aov; 8] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::ObjectId,
+        Aov::Albedo,
+        Aov::Direct,
+        Aov::Indirect,
+        Aov::Alpha,
+        Aov::Outline,
+    ];
+
+    /// Name used on the command line and in output file names
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::ObjectId => "object_id",
+            Aov::Albedo => "albedo",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+            Aov::Alpha => "alpha",
+            Aov::Outline => "outline",
+        }
+    }
+
+    /// Path for this AOV next to the main output: `render.png` becomes `render.depth.png`
+    pub fn output_path(&self, output_path: &str) -> PathBuf {
+        pass_output_path(output_path, self.name())
+    }
+}
+
+/// Insert `pass` before the extension of `output_path`, keeping the format
+pub(crate) fn pass_output_path(output_path: &str, pass: &str) -> PathBuf {
+    let path = Path::new(output_path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, pass, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, pass),
+    };
+    path.with_file_name(file_name)
+}
+
+impl std::str::FromStr for Aov {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Aov::ALL
+            .into_iter()
+            .find(|aov| aov.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Aov::ALL.iter().map(Aov::name).collect();
+                format!("Invalid AOV '{}'. Valid options are: {}", name, names.join(", "))
+            })
+    }
+}
+
+/// False-color view of the single-valued AOVs (depth, alpha and outline) in 8-bit images
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FalseColor {
+    pub map: ColorMap,
+    /// Values at the low and high ends of the map; by default the nearest and farthest
+    /// hits for depth, and 0 to 1 otherwise
+    pub range: Option<[f64; 2]>,
+}
+
+/// One rendered AOV: raw linear values, three channels per pixel, row by row
+#[derive(Debug, Clone)]
+pub struct AovBuffer {
+    pub aov: Aov,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 3]>,
+}
+
+impl AovBuffer {
+    pub fn new(aov: Aov, width: u32, height: u32) -> Self {
+        Self {
+            aov,
+            width,
+            height,
+            pixels: vec![[0.0; 3]; (width * height) as usize],
+        }
+    }
+
+    /// The raw values as a float image, for OpenEXR and Radiance HDR output
+    #[cfg(feature = "image")]
+    pub fn to_rgb32f(&self) -> image::Rgb32FImage {
+        let data = self.pixels.iter().flatten().copied().collect();
+        image::Rgb32FImage::from_raw(self.width, self.height, data)
+            .expect("AOV buffer matches its dimensions")
+    }
+
+    /// An 8-bit visualization for formats that cannot hold the raw values
+    ///
+    /// Depth is normalized so the nearest hit is white and the farthest is dark,
+    /// normals map from -1..1 to 0..1, and each object ID gets a distinct color.
+    #[cfg(feature = "image")]
+    pub fn to_rgb8(&self) -> image::RgbImage {
+        self.to_rgb8_with(None)
+    }
+
+    /// An 8-bit visualization as `to_rgb8` makes, with depth, alpha and outline shown
+    /// through `false_color`'s map if given; the background stays black
+    #[cfg(feature = "image")]
+    pub fn to_rgb8_with(&self, false_color: Option<&FalseColor>) -> image::RgbImage {
+        let max_depth = self.pixels.iter().map(|p| p[0]).fold(0.0f32, f32::max);
+        let min_depth = self
+            .pixels
+            .iter()
+            .map(|p| p[0])
+            .filter(|&d| d > 0.0)
+            .fold(max_depth, f32::min);
+
+        let false_color = false_color.map(|false_color| {
+            let range = match (false_color.range, self.aov) {
+                (Some(range), _) => range,
+                (None, Aov::Depth) => [min_depth as f64, max_depth as f64],
+                (None, _) => [0.0, 1.0],
+            };
+            crate::colormap::ColorScale {
+                stops: false_color.map.stops(),
+                range,
+            }
+        });
+
+        let data = self
+            .pixels
+            .iter()
+            .flat_map(|&[r, g, b]| match (self.aov, &false_color) {
+                (Aov::Depth, _) if r <= 0.0 => [0.0; 3],
+                (Aov::Depth | Aov::Alpha | Aov::Outline, Some(scale)) => {
+                    let color = scale.color(r as f64);
+                    [color.x as f32, color.y as f32, color.z as f32]
+                }
+                (Aov::Depth, None) => {
+                    let range = (max_depth - min_depth).max(f32::EPSILON);
+                    [1.0 - 0.9 * (r - min_depth) / range; 3]
+                }
+                (Aov::Normal, _) if [r, g, b] == [0.0; 3] => [0.0; 3],
+                (Aov::Normal, _) => [r * 0.5 + 0.5, g * 0.5 + 0.5, b * 0.5 + 0.5],
+                (Aov::ObjectId, _) => id_color(r as u32),
+                (Aov::Albedo | Aov::Direct | Aov::Indirect | Aov::Alpha | Aov::Outline, _) => {
+                    [r, g, b]
+                }
+            })
+            .map(|value| (value.clamp(0.0, 1.0) * 255.0) as u8)
+            .collect();
+        image::RgbImage::from_raw(self.width, self.height, data)
+            .expect("AOV buffer matches its dimensions")
+    }
+}
+
+/// Distinct, stable color for an object ID (black for background)
+#[cfg(feature = "image")]
+fn id_color(id: u32) -> [f32; 3] {
+    if id == 0 {
+        return [0.0; 3];
+    }
+    // Golden-ratio hue steps keep neighboring IDs far apart
+    let hue = (id as f32 * 0.618_034).fract() * 6.0;
+    let x = 1.0 - ((hue % 2.0) - 1.0).abs();
+    match hue as u32 {
+        0 => [1.0, x, 0.2],
+        1 => [x, 1.0, 0.2],
+        2 => [0.2, 1.0, x],
+        3 => [0.2, x, 1.0],
+        4 => [x, 0.2, 1.0],
+        _ => [1.0, 0.2, x],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aov_names_and_paths() {
+        for aov in Aov::ALL {
+            assert_eq!(aov.name().parse(), Ok(aov));
+        }
+        assert!("beauty".parse::<Aov>().is_err());
+
+        assert_eq!(
+            Aov::Depth.output_path("out/render.exr"),
+            PathBuf::from("out/render.depth.exr")
+        );
+        assert_eq!(
+            Aov::ObjectId.output_path("render"),
+            PathBuf::from("render.object_id")
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_false_color() {
+        let mut buffer = AovBuffer::new(Aov::Depth, 3, 1);
+        buffer.pixels = vec![[0.0; 3], [2.0; 3], [4.0; 3]];
+        let viridis = FalseColor {
+            map: ColorMap::Viridis,
+            range: None,
+        };
+        let image = buffer.to_rgb8_with(Some(&viridis));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(1, 0).0, [0x44, 0x01, 0x54]);
+        assert_eq!(image.get_pixel(2, 0).0, [0xFD, 0xE7, 0x25]);
+
+        // A fixed range puts the same distances elsewhere along the map
+        let fixed = FalseColor {
+            range: Some([0.0, 4.0]),
+            ..viridis
+        };
+        let image = buffer.to_rgb8_with(Some(&fixed));
+        assert_eq!(image.get_pixel(1, 0).0, [0x21, 0x90, 0x8C]);
+        assert_eq!(buffer.to_rgb8().get_pixel(1, 0).0, [255; 3]);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::mesh::Mesh;
+use crate::scene::{Point, Vec3};
+use crate::tube::{add_facing, perpendicular};
+
+/// Most triangles `arrow_mesh` makes for each side: the base, two for the shaft, two for
+/// the shoulder where the head widens and one for the head
+pub const ARROW_TRIANGLES_PER_SIDE: usize = 6;
+
+/// Mesh of an arrow from `from` to `to` with `sides` facets around it: a round shaft of
+/// `shaft_radius`, capped at the tail, ending in a cone `head_size` long and across
+///
+/// A head longer than the arrow takes up all of it, leaving no shaft.
+pub fn arrow_mesh(
+    from: &Point,
+    to: &Point,
+    shaft_radius: f64,
+    head_size: f64,
+    sides: u32,
+) -> Result<Mesh, String> {
+    if !(shaft_radius.is_finite() && shaft_radius > 0.0) {
+        return Err("Arrow shaft_radius must be greater than 0".to_string());
+    }
+    if !(head_size.is_finite() && head_size > 0.0) {
+        return Err("Arrow head_size must be greater than 0".to_string());
+    }
+    if sides < 3 {
+        return Err("Arrow sides must be at least 3".to_string());
+    }
+    let axis = to - from;
+    let length = axis.norm();
+    if !(length.is_finite() && length > 1e-12) {
+        return Err("Arrow from and to are the same point".to_string());
+    }
+    let direction = axis / length;
+    let normal = perpendicular(&direction);
+    let binormal = direction.cross(&normal);
+    let ring = |center: &Point, radius: f64| -> Vec<Point> {
+        (0..sides)
+            .map(|j| {
+                let angle = std::f64::consts::TAU * j as f64 / sides as f64;
+                center + (normal * angle.cos() + binormal * angle.sin()) * radius
+            })
+            .collect()
+    };
+
+    let head_radius = head_size / 2.0;
+    let neck = to - direction * head_size.min(length);
+    let head = ring(&neck, head_radius);
+    let sides = sides as usize;
+    let mut mesh = Mesh::new();
+    let fan = |mesh: &mut Mesh, center: &Point, ring: &[Point], outward: &Vec3| {
+        for j in 0..sides {
+            add_facing(mesh, [*center, ring[j], ring[(j + 1) % sides]], outward);
+        }
+    };
+    if head_size < length {
+        let tail = ring(from, shaft_radius);
+        let shaft = ring(&neck, shaft_radius);
+        fan(&mut mesh, from, &tail, &-direction);
+        for j in 0..sides {
+            let next = (j + 1) % sides;
+            let outward = (tail[j] - from) + (tail[next] - from);
+            add_facing(&mut mesh, [tail[j], shaft[j], shaft[next]], &outward);
+            add_facing(&mut mesh, [tail[j], shaft[next], tail[next]], &outward);
+        }
+        // The shoulder faces back along the shaft, or forward if the head is the narrower
+        let shoulder = direction * (shaft_radius - head_radius).signum();
+        for j in 0..sides {
+            let next = (j + 1) % sides;
+            add_facing(&mut mesh, [shaft[j], head[j], head[next]], &shoulder);
+            add_facing(&mut mesh, [shaft[j], head[next], shaft[next]], &shoulder);
+        }
+    } else {
+        fan(&mut mesh, &neck, &head, &-direction);
+    }
+    for j in 0..sides {
+        let next = (j + 1) % sides;
+        let outward = (head[j] - neck) + (head[next] - neck);
+        add_facing(&mut mesh, [head[j], head[next], *to], &outward);
+    }
+    mesh.build_kdtree();
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrow_mesh() {
+        let from = Point::new(1.0, 0.0, 0.0);
+        let to = Point::new(1.0, 0.0, 4.0);
+        let mesh = arrow_mesh(&from, &to, 0.1, 1.0, 8).unwrap();
+        assert_eq!(mesh.triangle_count(), ARROW_TRIANGLES_PER_SIDE * 8);
+        let (min, max) = mesh.bounds();
+        assert!((min.z, max.z) == (0.0, 4.0));
+        assert!((max.x - 1.5).abs() < 1e-9 && (min.x - 0.5).abs() < 1e-9);
+
+        // The shaft and head face away from the axis; the tail and shoulder face back
+        for triangle in &mesh.triangles {
+            let centroid = triangle.vertices.iter().map(|v| v.coords).sum::<Vec3>() / 3.0;
+            let radial = Vec3::new(centroid.x - 1.0, centroid.y, 0.0);
+            match triangle.normal.z.abs() > 0.99 {
+                true => assert!(triangle.normal.z < 0.0, "{:?}", triangle),
+                false => assert!(triangle.normal.dot(&radial) > 0.0, "{:?}", triangle),
+            }
+        }
+
+        // A head longer than the arrow is all there is
+        let short = arrow_mesh(&from, &Point::new(1.0, 0.0, 0.5), 0.1, 1.0, 8).unwrap();
+        assert_eq!(short.triangle_count(), 2 * 8);
+        assert!(arrow_mesh(&from, &from, 0.1, 1.0, 8).is_err());
+        assert!(arrow_mesh(&from, &to, 0.0, 1.0, 8).is_err());
+        assert!(arrow_mesh(&from, &to, 0.1, f64::NAN, 8).is_err());
+    }
+}
@@ -0,0 +1,598 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::backplate::Backplate;
+use crate::bump::HeightMap;
+use crate::core::volume::DensityGrid;
+use crate::glyphs::DataTable;
+use crate::mesh::{Mesh, MeshLimits};
+
+/// Options controlling how a scene and the external assets it references are loaded
+#[derive(Debug, Clone, Default)]
+pub struct AssetOptions {
+    /// Allow fetching `http://` and `https://` asset URLs
+    pub allow_network: bool,
+    /// Directory for downloaded assets (defaults to `rtrace-cache` in the temp directory)
+    pub cache_dir: Option<PathBuf>,
+    /// Size limits applied to every mesh the scene loads
+    pub mesh_limits: MeshLimits,
+    /// What to do when a mesh, image or density file can't be loaded
+    pub missing_asset: MissingAssetPolicy,
+    /// Directory searched for relative asset paths after the scene's own search paths
+    pub asset_root: Option<PathBuf>,
+    /// Seed for the scene's `jitter` block in place of the one in the file
+    pub jitter_seed: Option<u64>,
+    /// STL bytes of meshes held in memory, by the file name the scene gives them; used
+    /// in place of files and URLs, as where there is no file system
+    pub preloaded_meshes: HashMap<String, Vec<u8>>,
+}
+
+/// How scene loading treats an asset that can't be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingAssetPolicy {
+    /// Fail loading the scene
+    #[default]
+    Error,
+    /// Stand in a magenta box for a mesh; drop a background image
+    Placeholder,
+    /// Leave the mesh out of the render; drop a background image
+    Skip,
+}
+
+impl MissingAssetPolicy {
+    /// Name used by the CLI
+    pub fn name(&self) -> &'static str {
+        match self {
+            MissingAssetPolicy::Error => "error",
+            MissingAssetPolicy::Placeholder => "placeholder",
+            MissingAssetPolicy::Skip => "skip",
+        }
+    }
+}
+
+impl std::str::FromStr for MissingAssetPolicy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "error" => Ok(MissingAssetPolicy::Error),
+            "placeholder" => Ok(MissingAssetPolicy::Placeholder),
+            "skip" => Ok(MissingAssetPolicy::Skip),
+            _ => Err(format!(
+                "Invalid missing asset policy '{}'. Valid options are: placeholder, skip, error",
+                name
+            )),
+        }
+    }
+}
+
+/// Asset that failed to load and was replaced or skipped under a `MissingAssetPolicy`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingAsset {
+    /// File name or URL as written in the scene
+    pub reference: String,
+    /// Why loading failed
+    pub error: String,
+}
+
+/// Material color of placeholder meshes
+pub const PLACEHOLDER_COLOR: &str = "#FF00FF";
+
+impl AssetOptions {
+    /// Directory downloaded assets are stored in
+    pub fn cache_dir(&self) -> PathBuf {
+        self.cache_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("rtrace-cache"))
+    }
+}
+
+/// Check whether an asset reference is an http(s) URL rather than a file path
+pub fn is_remote_url(reference: &str) -> bool {
+    let lower = reference.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// First of `search_dirs` containing the local asset `reference`
+pub fn find_asset_dir<'a>(reference: &str, search_dirs: &'a [PathBuf]) -> Result<&'a Path, String> {
+    search_dirs
+        .iter()
+        .find(|dir| dir.join(reference).is_file())
+        .map(PathBuf::as_path)
+        .ok_or_else(|| {
+            let searched: Vec<String> = search_dirs
+                .iter()
+                .map(|dir| format!("'{}'", dir.display()))
+                .collect();
+            format!(
+                "Asset '{}' not found (searched {})",
+                reference,
+                searched.join(", ")
+            )
+        })
+}
+
+/// Load an STL mesh from the preloaded meshes, a remote URL or a local path, found in
+/// the first of `search_dirs` holding it
+#[cfg(feature = "stl")]
+pub fn load_mesh(
+    reference: &str,
+    search_dirs: &[PathBuf],
+    options: &AssetOptions,
+) -> Result<Mesh, Box<dyn std::error::Error>> {
+    if let Some(bytes) = options.preloaded_meshes.get(reference) {
+        Mesh::from_stl_bytes_with_limits(bytes, &options.mesh_limits)
+    } else if is_remote_url(reference) {
+        let bytes = fetch_cached(reference, options)?;
+        Mesh::from_stl_bytes_with_limits(&bytes, &options.mesh_limits)
+    } else {
+        let dir = find_asset_dir(reference, search_dirs)?;
+        Mesh::from_stl_file_with_limits(dir.join(reference), &options.mesh_limits)
+    }
+}
+
+/// Without the `stl` feature there is no mesh loader, so mesh objects are an error
+#[cfg(not(feature = "stl"))]
+pub fn load_mesh(
+    reference: &str,
+    _search_dirs: &[PathBuf],
+    _options: &AssetOptions,
+) -> Result<Mesh, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Cannot load mesh '{}': rtrace was built without the `stl` feature",
+        reference
+    )
+    .into())
+}
+
+/// Load a backplate image from a remote URL or a local path, found like meshes
+#[cfg(feature = "image")]
+pub fn load_backplate(
+    reference: &str,
+    search_dirs: &[PathBuf],
+    options: &AssetOptions,
+) -> Result<Backplate, Box<dyn std::error::Error>> {
+    Backplate::from_bytes(&read_image(reference, search_dirs, options, "background")?)
+}
+
+/// Load a bump height map from a remote URL or a local path, found like meshes
+#[cfg(feature = "image")]
+pub fn load_height_map(
+    reference: &str,
+    search_dirs: &[PathBuf],
+    options: &AssetOptions,
+) -> Result<HeightMap, Box<dyn std::error::Error>> {
+    HeightMap::from_bytes(&read_image(reference, search_dirs, options, "bump")?)
+}
+
+/// Load a volume's text density grid from a remote URL or a local path, found like meshes
+pub fn load_density_grid(
+    reference: &str,
+    search_dirs: &[PathBuf],
+    options: &AssetOptions,
+) -> Result<DensityGrid, Box<dyn std::error::Error>> {
+    let bytes = if is_remote_url(reference) {
+        fetch_cached(reference, options)?
+    } else {
+        let path = find_asset_dir(reference, search_dirs)?.join(reference);
+        std::fs::read(&path)
+            .map_err(|e| format!("Failed to read density file '{}': {}", path.display(), e))?
+    };
+    let text = String::from_utf8(bytes)
+        .map_err(|_| format!("Density file '{}' is not a text file", reference))?;
+    Ok(DensityGrid::parse(&text).map_err(|e| format!("Density file '{}': {}", reference, e))?)
+}
+
+/// Load a glyph field's CSV or NumPy data file from a remote URL or a local path, found
+/// like meshes
+pub fn load_data_table(
+    reference: &str,
+    search_dirs: &[PathBuf],
+    options: &AssetOptions,
+) -> Result<DataTable, Box<dyn std::error::Error>> {
+    let bytes = if is_remote_url(reference) {
+        fetch_cached(reference, options)?
+    } else {
+        let path = find_asset_dir(reference, search_dirs)?.join(reference);
+        std::fs::read(&path)
+            .map_err(|e| format!("Failed to read data file '{}': {}", path.display(), e))?
+    };
+    Ok(DataTable::from_bytes(reference, &bytes)
+        .map_err(|e| format!("Data file '{}': {}", reference, e))?)
+}
+
+/// Bytes of a remote or local image; `kind` names the image in errors
+#[cfg(feature = "image")]
+fn read_image(
+    reference: &str,
+    search_dirs: &[PathBuf],
+    options: &AssetOptions,
+    kind: &str,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if is_remote_url(reference) {
+        return fetch_cached(reference, options);
+    }
+    let path = find_asset_dir(reference, search_dirs)?.join(reference);
+    let bytes = std::fs::read(&path).map_err(|e| {
+        format!(
+            "Failed to read {} image '{}': {}",
+            kind,
+            path.display(),
+            e
+        )
+    })?;
+    Ok(bytes)
+}
+
+/// Without the `image` feature there is no image decoder, so backplates are an error
+#[cfg(not(feature = "image"))]
+pub fn load_backplate(
+    reference: &str,
+    _search_dirs: &[PathBuf],
+    _options: &AssetOptions,
+) -> Result<Backplate, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Cannot load background image '{}': rtrace was built without the `image` feature",
+        reference
+    )
+    .into())
+}
+
+/// Without the `image` feature there is no image decoder, so bump images are an error
+#[cfg(not(feature = "image"))]
+pub fn load_height_map(
+    reference: &str,
+    _search_dirs: &[PathBuf],
+    _options: &AssetOptions,
+) -> Result<HeightMap, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Cannot load bump image '{}': rtrace was built without the `image` feature",
+        reference
+    )
+    .into())
+}
+
+/// Fetch a remote asset, reusing a previously downloaded copy when available
+pub fn fetch_cached(
+    url: &str,
+    options: &AssetOptions,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if !options.allow_network {
+        return Err(format!(
+            "Asset '{}' is a remote URL but network access is disabled (use --allow-network)",
+            url
+        )
+        .into());
+    }
+
+    let path = cache_path(url, &options.cache_dir());
+    if let Ok(bytes) = std::fs::read(&path) {
+        return Ok(bytes);
+    }
+
+    let bytes = download(url)?;
+
+    // Write through a temporary file so a partial download is never mistaken for a cache hit
+    std::fs::create_dir_all(path.parent().unwrap_or_else(|| Path::new(".")))?;
+    let tmp_path = path.with_extension("part");
+    std::fs::write(&tmp_path, &bytes)?;
+    std::fs::rename(&tmp_path, &path)?;
+
+    Ok(bytes)
+}
+
+/// Location of the cached copy of `url` inside `cache_dir`
+///
+/// The file name is a stable hash of the URL, keeping the original extension
+/// so cached files remain recognizable.
+pub fn cache_path(url: &str, cache_dir: &Path) -> PathBuf {
+    let mut hasher = StableHasher::new();
+    hasher.update(url.as_bytes());
+    let hash = hasher.finish();
+
+    let path_part = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = path_part
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext)
+        .filter(|ext| {
+            !ext.is_empty() && ext.len() <= 8 && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+
+    match extension {
+        Some(ext) => cache_dir.join(format!("{:016x}.{}", hash, ext.to_ascii_lowercase())),
+        None => cache_dir.join(format!("{:016x}", hash)),
+    }
+}
+
+/// 64-bit FNV-1a hasher for on-disk cache keys
+///
+/// Unlike `DefaultHasher`, the output is stable across runs and Rust versions.
+pub(crate) struct StableHasher(u64);
+
+impl StableHasher {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(feature = "remote")]
+fn download(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    use std::io::Read;
+
+    /// Largest remote asset we are willing to download (256 MiB)
+    const MAX_REMOTE_ASSET_BYTES: u64 = 256 * 1024 * 1024;
+
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| format!("Failed to fetch '{}': {}", url, e))?;
+
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_REMOTE_ASSET_BYTES + 1)
+        .read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 > MAX_REMOTE_ASSET_BYTES {
+        return Err(format!(
+            "Remote asset '{}' exceeds the {} MiB size limit",
+            url,
+            MAX_REMOTE_ASSET_BYTES / (1024 * 1024)
+        )
+        .into());
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "remote"))]
+fn download(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Err(format!(
+        "Cannot fetch '{}': rtrace was built without the `remote` feature",
+        url
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_remote_url() {
+        assert!(is_remote_url("https://example.com/part.stl"));
+        assert!(is_remote_url("HTTP://example.com/part.stl"));
+        assert!(!is_remote_url("models/part.stl"));
+        assert!(!is_remote_url("/abs/path/part.stl"));
+    }
+
+    #[test]
+    fn test_cache_path() {
+        let dir = Path::new("/cache");
+        let a = cache_path("https://example.com/models/Part.STL?v=2", dir);
+        let b = cache_path("https://example.com/models/Part.STL?v=2", dir);
+        let c = cache_path("https://example.com/models/other.stl", dir);
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(a.extension().unwrap(), "stl");
+        assert!(cache_path("https://example.com/", dir)
+            .extension()
+            .is_none());
+    }
+
+    #[test]
+    fn test_remote_mesh_requires_opt_in() {
+        let result = load_mesh(
+            "https://example.com/part.stl",
+            &[PathBuf::from(".")],
+            &AssetOptions::default(),
+        );
+        assert!(result.unwrap_err().to_string().contains("--allow-network"));
+    }
+
+    #[test]
+    fn test_asset_search_paths() {
+        use crate::scene::{Material, Object, Scene};
+
+        let dir = std::env::temp_dir().join(format!("rtrace-search-test-{}", std::process::id()));
+        let stl = "solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        for subdir in ["scenes", "models", "library"] {
+            std::fs::create_dir_all(dir.join(subdir)).unwrap();
+        }
+        std::fs::write(dir.join("models/part.stl"), stl).unwrap();
+        std::fs::write(dir.join("library/bolt.stl"), stl).unwrap();
+        let scene_path = dir.join("scenes/scene.json");
+        let scene_path = scene_path.to_str().unwrap();
+
+        let mut scene = Scene::default();
+        for filename in ["part.stl", "bolt.stl"] {
+            scene.objects.push(Object::Mesh {
+                name: None,
+                filename: filename.to_string(),
+                material: Material::default(),
+                face_materials: Vec::new(),
+                orient: None,
+                unit: None,
+                lods: Vec::new(),
+                transform: None,
+                motion: None,
+                ignore_clip: None,
+                mesh_data: None,
+            });
+        }
+        scene.asset_paths.push("../models".to_string());
+
+        // The scene's own paths find part.stl, but bolt.stl needs the asset root
+        let error = scene
+            .load_mesh_data_with_assets(Some(scene_path), &AssetOptions::default())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Asset 'bolt.stl' not found (searched '"),
+            "{}",
+            error
+        );
+
+        let options = AssetOptions {
+            asset_root: Some(dir.join("library")),
+            ..Default::default()
+        };
+        assert_eq!(scene.asset_search_dirs(Some(scene_path), &options).len(), 3);
+        scene
+            .load_mesh_data_with_assets(Some(scene_path), &options)
+            .unwrap();
+        assert!(scene.objects.iter().all(|object| matches!(
+            object,
+            Object::Mesh {
+                mesh_data: Some(_),
+                ..
+            }
+        )));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_asset_policy() {
+        use crate::scene::{Material, Object, Scene};
+
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Mesh {
+            name: None,
+            filename: "missing-asset-test.stl".to_string(),
+            material: Material::default(),
+            face_materials: Vec::new(),
+            orient: None,
+            unit: None,
+            lods: Vec::new(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+            mesh_data: None,
+        });
+        let load = |scene: &mut Scene, policy: &str| {
+            let options = AssetOptions {
+                missing_asset: policy.parse().unwrap(),
+                ..Default::default()
+            };
+            scene.load_mesh_data_with_assets(None, &options)
+        };
+
+        assert!(load(&mut scene, "error").is_err());
+
+        load(&mut scene, "skip").unwrap();
+        assert_eq!(scene.missing_assets.len(), 1);
+        assert_eq!(scene.missing_assets[0].reference, "missing-asset-test.stl");
+        assert!(matches!(
+            &scene.objects[0],
+            Object::Mesh {
+                mesh_data: None,
+                ..
+            }
+        ));
+
+        load(&mut scene, "placeholder").unwrap();
+        assert_eq!(scene.missing_assets.len(), 1);
+        match &scene.objects[0] {
+            Object::Mesh {
+                mesh_data: Some(mesh),
+                material,
+                ..
+            } => {
+                assert_eq!(mesh.triangle_count(), 12);
+                assert_eq!(material.color, PLACEHOLDER_COLOR);
+            }
+            _ => panic!("Expected a placeholder mesh"),
+        }
+
+        assert!("retry".parse::<MissingAssetPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_density_file() {
+        use crate::scene::{Object, Scene};
+
+        let dir = std::env::temp_dir().join(format!("rtrace-density-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("puff.txt"), "# 2x1x1 puff\n2 1 1\n0.0 2.0\n").unwrap();
+        let scene_path = dir.join("scene.json");
+        let scene_path = scene_path.to_str().unwrap();
+
+        let mut scene = Scene::default();
+        scene.objects.push(
+            serde_json::from_str(
+                r#"{"kind": "volume", "center": [0, 0, 0], "size": [2, 2, 2],
+                    "density": 0.5, "density_file": "puff.txt"}"#,
+            )
+            .unwrap(),
+        );
+        scene
+            .load_mesh_data_with_assets(Some(scene_path), &AssetOptions::default())
+            .unwrap();
+        let volume = scene.objects[0].volume().unwrap().unwrap();
+        assert_eq!(volume.grid.as_ref().unwrap().resolution, [2, 1, 1]);
+        assert_eq!(
+            volume.density_at(&crate::scene::Point::new(0.5, 0.0, 0.0)),
+            1.0
+        );
+
+        // A missing file leaves the constant density unless missing assets are an error
+        if let Object::Volume { density_file, .. } = &mut scene.objects[0] {
+            *density_file = Some("gone.txt".to_string());
+        }
+        assert!(scene
+            .load_mesh_data_with_assets(Some(scene_path), &AssetOptions::default())
+            .is_err());
+        let options = AssetOptions {
+            missing_asset: MissingAssetPolicy::Skip,
+            ..Default::default()
+        };
+        scene
+            .load_mesh_data_with_assets(Some(scene_path), &options)
+            .unwrap();
+        assert_eq!(scene.missing_assets[0].reference, "gone.txt");
+        let volume = scene.objects[0].volume().unwrap().unwrap();
+        assert!(volume.grid.is_none());
+        assert_eq!(
+            volume.density_at(&crate::scene::Point::new(0.5, 0.0, 0.0)),
+            0.5
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remote_mesh_served_from_cache() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("rtrace-assets-test-{}", std::process::id()));
+        let url = "https://example.com/cached-triangle.stl";
+
+        let stl = "solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        let path = cache_path(url, &cache_dir);
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        std::fs::write(&path, stl).unwrap();
+
+        let options = AssetOptions {
+            allow_network: true,
+            cache_dir: Some(cache_dir.clone()),
+            ..Default::default()
+        };
+        let mesh = load_mesh(url, &[PathBuf::from(".")], &options).unwrap();
+        assert_eq!(mesh.triangles.len(), 1);
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+}
@@ -1,5 +1,50 @@
 use crate::scene::{Camera, Point, Scene, Vec3};
 
+/// Field of view of the perspective and turntable cameras, in degrees
+const PERSPECTIVE_FOV: f64 = 50.0;
+
+/// Angle the perspective and turntable cameras look down at, in degrees
+const DOWN_ANGLE: f64 = 35.0;
+
+/// Margin `AutoCamera::fit` leaves on each side, as a share of the bounds' extent
+const FIT_MARGIN: f64 = 0.05;
+
+/// Axis a turntable camera orbits about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurntableAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl TurntableAxis {
+    /// The axis and two directions across it, in the order that turns counterclockwise
+    /// seen from the axis's positive end
+    fn basis(self) -> (Vec3, Vec3, Vec3) {
+        match self {
+            TurntableAxis::X => (Vec3::x(), Vec3::y(), Vec3::z()),
+            TurntableAxis::Y => (Vec3::y(), Vec3::z(), Vec3::x()),
+            TurntableAxis::Z => (Vec3::z(), Vec3::x(), Vec3::y()),
+        }
+    }
+}
+
+impl std::str::FromStr for TurntableAxis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "x" => Ok(TurntableAxis::X),
+            "y" => Ok(TurntableAxis::Y),
+            "z" => Ok(TurntableAxis::Z),
+            _ => Err(format!(
+                "Invalid turntable axis '{}'. Valid options are: x, y, z",
+                s
+            )),
+        }
+    }
+}
+
 /// Auto camera bounds functionality
 /// Generates 4 camera views for a given scene: left, front, top, and perspective
 pub struct AutoCamera;
@@ -32,6 +77,96 @@ impl AutoCamera {
         })
     }
 
+    /// Cameras for `frames` evenly spaced frames of one orbit around the scene's
+    /// finite bounds, about an axis through their center
+    ///
+    /// Each camera frames the scene as the perspective camera does, and the first one
+    /// matches it for the Z axis. The orbit runs clockwise seen from the axis's positive
+    /// end, so the scene appears to turn counterclockwise, as a positive `rotate` would
+    /// turn it. Width and height are copied from `scene.camera` to keep its aspect ratio.
+    pub fn turntable(
+        scene: &Scene,
+        frames: u32,
+        axis: TurntableAxis,
+    ) -> Result<Vec<Camera>, String> {
+        if frames == 0 {
+            return Err("Turntable frames must be greater than 0".to_string());
+        }
+        let (min, max) = scene
+            .compute_finite_bounds()
+            .ok_or("Scene has no finite objects to compute bounds")?;
+        let center = Point::from((min.coords + max.coords) / 2.0);
+        let size = max - min;
+
+        (0..frames)
+            .map(|frame| {
+                let turn = 360.0 * frame as f64 / frames as f64;
+                let mut camera = Self::orbit_camera(center, &size, 1.15, axis, 45.0 - turn);
+                camera.width = scene.camera.width;
+                camera.height = scene.camera.height;
+                Ok(camera)
+            })
+            .collect()
+    }
+
+    /// `camera`, which must be orthographic, moved and resized to frame `bounds` tightly
+    ///
+    /// The view direction and up vector are kept. The camera slides across its view to
+    /// center the bounds, backs away if part of them is behind it, and its width and
+    /// height become the extent of the bounds across the view plus a 5% margin on each
+    /// side. Fitting a fitted camera changes nothing.
+    pub fn fit(camera: &Camera, (min, max): (Point, Point)) -> Result<Camera, String> {
+        if camera.kind != "ortho" {
+            return Err(format!(
+                "Camera fit needs an ortho camera, not '{}'",
+                camera.kind
+            ));
+        }
+        // Camera basis as `camera::Camera::from_config` computes it
+        let position = Point::from(camera.position);
+        let back = position - Point::from(camera.target);
+        let right = Vec3::from(camera.up).cross(&back);
+        if back.norm() == 0.0 || right.norm() == 0.0 {
+            return Err(
+                "Camera fit needs a target away from the position and an up vector across the view"
+                    .to_string(),
+            );
+        }
+        let back = back.normalize();
+        let right = right.normalize();
+        let up = back.cross(&right);
+
+        // Extent of the bounds' corners across the view and along it
+        let (mut low, mut high) = (Vec3::repeat(f64::INFINITY), Vec3::repeat(f64::NEG_INFINITY));
+        for corner in 0..8 {
+            let point = Point::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            );
+            let offset = point - position;
+            let local = Vec3::new(right.dot(&offset), up.dot(&offset), -back.dot(&offset));
+            low = low.inf(&local);
+            high = high.sup(&local);
+        }
+        let extent = high - low;
+        let size = extent.x.max(extent.y);
+        if size == 0.0 {
+            return Ok(camera.clone());
+        }
+
+        let shift = right * (low.x + high.x) / 2.0 + up * (low.y + high.y) / 2.0;
+        // Ortho rays start in the camera's plane, so everything must be in front of it
+        let backup = (size * FIT_MARGIN - low.z).max(0.0);
+        let mut fitted = camera.clone();
+        fitted.position = (position + shift + back * backup).into();
+        fitted.target = (Point::from(camera.target) + shift).into();
+        // Flat bounds seen edge-on still get a sliver of viewport
+        fitted.width = extent.x.max(size * 0.01) * (1.0 + 2.0 * FIT_MARGIN);
+        fitted.height = extent.y.max(size * 0.01) * (1.0 + 2.0 * FIT_MARGIN);
+        Ok(fitted)
+    }
+
     /// Generate left camera (side view from negative Y direction, looking toward +Y)
     fn generate_left_camera(center: Point, viewport_width: f64, viewport_height: f64) -> Camera {
         // Position camera in negative Y direction to position camera far enough
@@ -48,6 +183,10 @@ impl AutoCamera {
             grid_pitch: None,
             grid_color: None,
             grid_thickness: None,
+            grid_reflections: None,
+            shutter_open: None,
+            shutter_close: None,
+            fit: None,
         }
     }
 
@@ -67,6 +206,10 @@ impl AutoCamera {
             grid_pitch: None,
             grid_color: None,
             grid_thickness: None,
+            grid_reflections: None,
+            shutter_open: None,
+            shutter_close: None,
+            fit: None,
         }
     }
 
@@ -86,6 +229,10 @@ impl AutoCamera {
             grid_pitch: None,
             grid_color: None,
             grid_thickness: None,
+            grid_reflections: None,
+            shutter_open: None,
+            shutter_close: None,
+            fit: None,
         }
     }
 
@@ -95,38 +242,58 @@ impl AutoCamera {
         size: &Vec3,
         margin_factor: f64,
     ) -> Result<Camera, String> {
-        let fov: f64 = 50.0; // Field of view in degrees
-        let down_angle: f64 = 35.0; // Angle looking down from horizontal
+        // Position camera along 45-degree X-Y axis line in positive octant
+        Ok(Self::orbit_camera(
+            center,
+            size,
+            margin_factor,
+            TurntableAxis::Z,
+            45.0,
+        ))
+    }
 
+    /// Perspective camera looking down at `center` from `angle` degrees around `axis`
+    fn orbit_camera(
+        center: Point,
+        size: &Vec3,
+        margin_factor: f64,
+        axis: TurntableAxis,
+        angle: f64,
+    ) -> Camera {
         // Calculate maximum scene dimension for camera distance calculation
         let max_dimension = size.x.max(size.y).max(size.z) * margin_factor;
 
         // Calculate camera distance based on FOV to ensure entire scene is visible
-        let fov_rad = fov.to_radians();
+        let fov_rad = PERSPECTIVE_FOV.to_radians();
         let distance_for_fov = max_dimension / (fov_rad / 2.0).tan();
 
-        // Position camera along 45-degree X-Y axis line in positive octant
-        let xy_angle = 45.0_f64.to_radians(); // 45 degrees in X-Y plane
-        let down_angle_rad = down_angle.to_radians();
+        let angle_rad = angle.to_radians();
+        let down_angle_rad = DOWN_ANGLE.to_radians();
 
         // Calculate position components
+        let (up, across_u, across_v) = axis.basis();
         let horizontal_distance = distance_for_fov * down_angle_rad.cos();
-        let x = center.x + horizontal_distance * xy_angle.cos();
-        let y = center.y + horizontal_distance * xy_angle.sin();
-        let z = center.z + distance_for_fov * down_angle_rad.sin();
+        let position = center
+            + across_u * (horizontal_distance * angle_rad.cos())
+            + across_v * (horizontal_distance * angle_rad.sin())
+            + up * (distance_for_fov * down_angle_rad.sin());
 
-        Ok(Camera {
+        Camera {
             kind: "perspective".to_string(),
-            position: [x, y, z],
+            position: position.into(),
             target: [center.x, center.y, center.z],
-            up: [0.0, 0.0, 1.0],
+            up: up.into(),
             width: 1.0,  // Not used for perspective cameras
             height: 1.0, // Not used for perspective cameras
-            fov: Some(fov),
+            fov: Some(PERSPECTIVE_FOV),
             grid_pitch: None,
             grid_color: None,
             grid_thickness: None,
-        })
+            grid_reflections: None,
+            shutter_open: None,
+            shutter_close: None,
+            fit: None,
+        }
     }
 }
 
@@ -155,26 +322,44 @@ impl AutoCameraResult {
 mod tests {
     use super::*;
     use crate::scene::{Light, Material, Object, SceneSettings};
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_auto_camera_with_sphere() {
         let sphere = Object::Sphere {
+            name: None,
             center: [0.0, 0.0, 0.0],
             radius: 1.0,
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         };
 
         let scene = Scene {
             camera: Camera::default(), // Will be ignored
+            cameras: BTreeMap::new(),
             objects: vec![sphere],
             lights: vec![Light {
                 position: [2.0, 2.0, 2.0],
                 color: "#FFFFFF".to_string(),
                 intensity: 1.0,
                 diameter: None,
+                shadow_samples: None,
+                attenuation: None,
+                emitter: None,
+                caustics: None,
+                group: None,
+                name: None,
             }],
             scene_settings: SceneSettings::default(),
+            render_settings: None,
+            asset_paths: Vec::new(),
+            meshes: BTreeMap::new(),
+            jitter: None,
+            animation: None,
+            missing_assets: Vec::new(),
+            inferred_units: Vec::new(),
         };
 
         let result = AutoCamera::generate_cameras(&scene).unwrap();
@@ -202,17 +387,28 @@ mod tests {
     #[test]
     fn test_auto_camera_with_cube() {
         let cube = Object::Cube {
+            name: None,
             center: [1.0, 1.0, 1.0],
             size: [2.0, 2.0, 2.0],
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         };
 
         let scene = Scene {
             camera: Camera::default(),
+            cameras: BTreeMap::new(),
             objects: vec![cube],
             lights: vec![],
             scene_settings: SceneSettings::default(),
+            render_settings: None,
+            asset_paths: Vec::new(),
+            meshes: BTreeMap::new(),
+            jitter: None,
+            animation: None,
+            missing_assets: Vec::new(),
+            inferred_units: Vec::new(),
         };
 
         let result = AutoCamera::generate_cameras(&scene).unwrap();
@@ -228,9 +424,17 @@ mod tests {
     fn test_auto_camera_empty_scene() {
         let scene = Scene {
             camera: Camera::default(),
+            cameras: BTreeMap::new(),
             objects: vec![], // Empty
             lights: vec![],
             scene_settings: SceneSettings::default(),
+            render_settings: None,
+            asset_paths: Vec::new(),
+            meshes: BTreeMap::new(),
+            jitter: None,
+            animation: None,
+            missing_assets: Vec::new(),
+            inferred_units: Vec::new(),
         };
 
         let result = AutoCamera::generate_cameras(&scene);
@@ -241,21 +445,113 @@ mod tests {
     #[test]
     fn test_auto_camera_only_planes() {
         let plane = Object::Plane {
+            name: None,
             point: [0.0, 0.0, 0.0],
             normal: [0.0, 0.0, 1.0],
+            width: None,
+            height: None,
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         };
 
         let scene = Scene {
             camera: Camera::default(),
+            cameras: BTreeMap::new(),
             objects: vec![plane], // Only planes (infinite bounds)
             lights: vec![],
             scene_settings: SceneSettings::default(),
+            render_settings: None,
+            asset_paths: Vec::new(),
+            meshes: BTreeMap::new(),
+            jitter: None,
+            animation: None,
+            missing_assets: Vec::new(),
+            inferred_units: Vec::new(),
         };
 
         let result = AutoCamera::generate_cameras(&scene);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("no finite objects"));
     }
+
+    #[test]
+    fn test_fit() {
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Cube {
+            name: None,
+            center: [2.0, 3.0, 1.0],
+            size: [2.0, 4.0, 1.0],
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        // Looking down from above, off to one side and inside the cube's height
+        scene.camera = Camera {
+            position: [0.0, 0.0, 1.2],
+            target: [0.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            fit: Some(true),
+            ..Camera::default()
+        };
+
+        let fitted = scene.fitted_camera().unwrap();
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(fitted.width, 2.0 * 1.1) && close(fitted.height, 4.0 * 1.1));
+        assert!(close(fitted.target[0], 2.0) && close(fitted.target[1], 3.0));
+        // Backed off to clear the top of the cube by the margin
+        assert!(close(fitted.position[2], 1.5 + 4.0 * FIT_MARGIN));
+        scene.camera = fitted.clone();
+        let refitted = scene.fitted_camera().unwrap();
+        assert!(
+            close(refitted.width, fitted.width) && close(refitted.position[2], fitted.position[2])
+        );
+
+        scene.camera.kind = "perspective".to_string();
+        assert!(scene.fitted_camera().is_err());
+        scene.camera.fit = None;
+        assert!(scene.fitted_camera().is_ok());
+    }
+
+    #[test]
+    fn test_turntable() {
+        let mut scene = Scene::default();
+        scene.camera.width = 16.0;
+        scene.camera.height = 9.0;
+        scene.objects.push(Object::Cube {
+            name: None,
+            center: [1.0, 1.0, 1.0],
+            size: [2.0, 2.0, 2.0],
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // The first frame is the perspective view, with the scene camera's aspect ratio
+        let cameras = AutoCamera::turntable(&scene, 4, TurntableAxis::Z).unwrap();
+        let perspective = AutoCamera::generate_cameras(&scene).unwrap().perspective;
+        assert_eq!(cameras.len(), 4);
+        assert_eq!(cameras[0].position, perspective.position);
+        assert_eq!((cameras[0].width, cameras[0].height), (16.0, 9.0));
+
+        // Each frame is a quarter turn clockwise seen from above, at the same height
+        let offset = |camera: &Camera| Vec3::from(camera.position) - Vec3::new(1.0, 1.0, 1.0);
+        let (first, second) = (offset(&cameras[0]), offset(&cameras[1]));
+        assert!((second - Vec3::new(first.y, -first.x, first.z)).norm() < 1e-9);
+        assert!(cameras
+            .iter()
+            .all(|camera| camera.target == [1.0, 1.0, 1.0]));
+
+        // Other axes point the camera's up along them
+        let cameras = AutoCamera::turntable(&scene, 2, TurntableAxis::X).unwrap();
+        assert_eq!(cameras[1].up, [1.0, 0.0, 0.0]);
+        assert!((offset(&cameras[0]).x - offset(&cameras[1]).x).abs() < 1e-9);
+
+        assert!(AutoCamera::turntable(&scene, 0, TurntableAxis::Z).is_err());
+        assert_eq!("Y".parse(), Ok(TurntableAxis::Y));
+        assert!("w".parse::<TurntableAxis>().is_err());
+    }
 }
@@ -0,0 +1,116 @@
+use crate::core::tonemap::srgb_to_linear;
+use crate::scene::Color;
+
+/// Image shown behind the scene wherever a camera ray misses every object
+///
+/// The image is stretched over the frame in screen space, so a backplate with the
+/// same resolution as the render maps one texel to each pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backplate {
+    pub width: u32,
+    pub height: u32,
+    /// Colors, row by row from the top
+    pub pixels: Vec<Color>,
+    /// Whether the pixels are sRGB-encoded (8 and 16-bit images) rather than linear
+    pub srgb: bool,
+}
+
+impl Backplate {
+    pub fn new(width: u32, height: u32, pixels: Vec<Color>) -> Result<Self, String> {
+        if width == 0 || height == 0 || pixels.len() != (width * height) as usize {
+            return Err(format!(
+                "Backplate needs {}x{} pixels, got {}",
+                width,
+                height,
+                pixels.len()
+            ));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+            srgb: false,
+        })
+    }
+
+    /// Decode a PNG, JPEG, OpenEXR or Radiance HDR image
+    ///
+    /// Integer values are scaled to 0-1 without gamma decoding, so the photo comes
+    /// through unchanged with the default linear tone mapping; they are marked `srgb`
+    /// for scenes in the sRGB color space to decode.
+    #[cfg(feature = "image")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let image = image::load_from_memory(bytes)?;
+        let srgb = !matches!(
+            image,
+            image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_)
+        );
+        let image = image.to_rgb32f();
+        let pixels = image
+            .pixels()
+            .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+        Ok(Self {
+            srgb,
+            ..Self::new(image.width(), image.height(), pixels)?
+        })
+    }
+
+    /// Copy with sRGB-encoded pixels decoded to linear values
+    pub fn to_linear(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels: if self.srgb {
+                self.pixels.iter().map(|c| c.map(srgb_to_linear)).collect()
+            } else {
+                self.pixels.clone()
+            },
+            srgb: false,
+        }
+    }
+
+    /// Bilinearly filtered color at screen position (u, v), with v = 1 at the top
+    pub fn sample(&self, u: f64, v: f64) -> Color {
+        let x = (u * self.width as f64 - 0.5).clamp(0.0, (self.width - 1) as f64);
+        let y = ((1.0 - v) * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+
+        let texel = |x: u32, y: u32| self.pixels[(y * self.width + x) as usize];
+        let top = texel(x0, y0) * (1.0 - fx) + texel(x1, y0) * fx;
+        let bottom = texel(x0, y1) * (1.0 - fx) + texel(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backplate_sampling() {
+        // 2x2: red, green on top; blue, white below
+        let backplate = Backplate::new(
+            2,
+            2,
+            vec![
+                Color::new(1.0, 0.0, 0.0),
+                Color::new(0.0, 1.0, 0.0),
+                Color::new(0.0, 0.0, 1.0),
+                Color::new(1.0, 1.0, 1.0),
+            ],
+        )
+        .unwrap();
+
+        // Pixel centers of a matching render hit texels exactly
+        assert_eq!(backplate.sample(0.25, 0.75), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(backplate.sample(0.75, 0.25), Color::new(1.0, 1.0, 1.0));
+        // Halfway between texels blends them; edges clamp
+        assert_eq!(backplate.sample(0.5, 0.75), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(backplate.sample(0.0, 0.0), Color::new(0.0, 0.0, 1.0));
+
+        assert!(Backplate::new(2, 2, vec![Color::zeros(); 3]).is_err());
+    }
+}
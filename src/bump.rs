@@ -0,0 +1,292 @@
+use nalgebra::Unit;
+
+use crate::core::shading::value_noise;
+use crate::ray::HitRecord;
+use crate::scene::{Bump, Point, Vec3};
+
+/// Grayscale height image for `Bump::Image`, repeating in both directions
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightMap {
+    pub width: u32,
+    pub height: u32,
+    /// Heights from 0 (black) to 1 (white), row by row from the top
+    pub heights: Vec<f64>,
+}
+
+impl HeightMap {
+    pub fn new(width: u32, height: u32, heights: Vec<f64>) -> Result<Self, String> {
+        if width == 0 || height == 0 || heights.len() != (width * height) as usize {
+            return Err(format!(
+                "Height map needs {}x{} pixels, got {}",
+                width,
+                height,
+                heights.len()
+            ));
+        }
+        Ok(Self {
+            width,
+            height,
+            heights,
+        })
+    }
+
+    /// Decode an image, keeping only its brightness
+    #[cfg(feature = "image")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let image = image::load_from_memory(bytes)?.to_luma32f();
+        let heights = image.pixels().map(|p| p[0] as f64).collect();
+        Ok(Self::new(image.width(), image.height(), heights)?)
+    }
+
+    /// Bilinearly filtered height at (u, v), with one repeat of the image per unit
+    pub fn sample(&self, u: f64, v: f64) -> f64 {
+        let x = (u * self.width as f64 - 0.5).rem_euclid(self.width as f64);
+        let y = ((1.0 - v) * self.height as f64 - 0.5).rem_euclid(self.height as f64);
+        let (x0, y0) = (x as u32 % self.width, y as u32 % self.height);
+        let (x1, y1) = ((x0 + 1) % self.width, (y0 + 1) % self.height);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+
+        let texel = |x: u32, y: u32| self.heights[(y * self.width + x) as usize];
+        let top = texel(x0, y0) * (1.0 - fx) + texel(x1, y0) * fx;
+        let bottom = texel(x0, y1) * (1.0 - fx) + texel(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+impl Bump {
+    pub fn validate(&self) -> Result<(), String> {
+        let (size, depth) = match self {
+            Bump::Noise { scale, depth } => (*scale, *depth),
+            Bump::Image { size, depth, .. } => (*size, *depth),
+        };
+        if !(size > 0.0 && depth.is_finite()) {
+            return Err("Bump needs a positive size and a finite depth".to_string());
+        }
+        Ok(())
+    }
+
+    /// Height in scene units at `point` on a surface facing `normal`
+    ///
+    /// Images are projected along all three axes and blended by how squarely the
+    /// surface faces each one, so they need no texture coordinates. An image that
+    /// has not been loaded is flat.
+    fn height(&self, point: &Point, normal: &Vec3) -> f64 {
+        match self {
+            Bump::Noise { scale, depth } => {
+                // Three octaves, each half the size and half the height of the last
+                let (mut total, mut amplitude, mut frequency) = (0.0, 4.0 / 7.0, 1.0 / scale);
+                for _ in 0..3 {
+                    total += amplitude * value_noise(&(point * frequency));
+                    amplitude /= 2.0;
+                    frequency *= 2.0;
+                }
+                total * depth
+            }
+            Bump::Image {
+                size,
+                depth,
+                height_map,
+                ..
+            } => {
+                let Some(height_map) = height_map else {
+                    return 0.0;
+                };
+                let p = point / *size;
+                let weights = normal.map(|n| n.powi(4));
+                let projections = [(p.y, p.z), (p.x, p.z), (p.x, p.y)];
+                let total: f64 = weights
+                    .iter()
+                    .zip(projections)
+                    .map(|(weight, (u, v))| weight * height_map.sample(u, v))
+                    .sum();
+                total / weights.sum() * depth
+            }
+        }
+    }
+
+    /// Distance between the points used to measure the slope of the height field
+    fn step(&self) -> f64 {
+        match self {
+            Bump::Noise { scale, .. } => scale * 0.01,
+            Bump::Image {
+                size, height_map, ..
+            } => {
+                let texels = height_map
+                    .as_ref()
+                    .map_or(1, |map| map.width.max(map.height));
+                size / texels as f64 * 0.5
+            }
+        }
+    }
+
+    /// Shading normal at a hit, tilted away from the slope of the height field
+    pub fn perturb_normal(&self, hit: &HitRecord) -> Unit<Vec3> {
+        let normal = hit.normal;
+        let helper = if normal.x.abs() < 0.9 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        let tangent = Unit::new_normalize(helper.cross(&normal));
+        let bitangent = normal.cross(&tangent);
+
+        let step = self.step();
+        let slope = |direction: &Vec3| {
+            let ahead = self.height(&(hit.point + direction * step), &normal);
+            let behind = self.height(&(hit.point - direction * step), &normal);
+            (ahead - behind) / (2.0 * step)
+        };
+        Unit::new_normalize(
+            normal.as_ref() - slope(&tangent) * tangent.as_ref() - slope(&bitangent) * bitangent,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::scene::Color;
+
+    fn floor_hit(x: f64) -> HitRecord {
+        let ray = Ray::new(Point::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        HitRecord::new(
+            Point::new(x, 0.0, 0.0),
+            Vec3::z(),
+            5.0,
+            &ray,
+            Color::new(1.0, 1.0, 1.0),
+            0,
+        )
+    }
+
+    #[test]
+    fn test_image_bump_tilts_normal_down_the_slope() {
+        // Heights rise from left to right across a 4-pixel ramp
+        let ramp = HeightMap::new(4, 1, vec![0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0]).unwrap();
+        assert!((ramp.sample(0.5, 0.5) - 0.5).abs() < 1e-9);
+        // Repeats past the edges
+        assert!((ramp.sample(1.5, 0.5) - ramp.sample(0.5, 0.5)).abs() < 1e-9);
+
+        let bump = Bump::Image {
+            image: "ramp.png".to_string(),
+            size: 4.0,
+            depth: 1.0,
+            height_map: Some(std::sync::Arc::new(ramp)),
+        };
+        // Across the middle of the ramp the height climbs 1/3 per scene unit along +X
+        let normal = bump.perturb_normal(&floor_hit(2.0));
+        let expected = Vec3::new(-1.0 / 3.0, 0.0, 1.0).normalize();
+        assert!(
+            (normal.as_ref() - expected).magnitude() < 1e-6,
+            "{:?}",
+            normal
+        );
+
+        // Without its image the surface stays flat
+        let unloaded = Bump::Image {
+            image: "ramp.png".to_string(),
+            size: 4.0,
+            depth: 1.0,
+            height_map: None,
+        };
+        let normal = unloaded.perturb_normal(&floor_hit(2.0));
+        assert!((normal.as_ref() - Vec3::z()).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn test_noise_bump() {
+        let bump = Bump::Noise {
+            scale: 0.5,
+            depth: 0.2,
+        };
+        assert!(bump.validate().is_ok());
+        let tilted = (0..20)
+            .map(|i| bump.perturb_normal(&floor_hit(i as f64 * 0.37)))
+            .filter(|normal| normal.z < 0.999)
+            .count();
+        assert!(tilted > 10);
+
+        let flat = Bump::Noise {
+            scale: 0.0,
+            depth: 0.2,
+        };
+        assert!(flat.validate().is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_height_images_load_with_scene() {
+        use crate::assets::{AssetOptions, MissingAssetPolicy};
+        use crate::scene::{Object, Scene};
+
+        let dir = std::env::temp_dir().join(format!("rtrace-bump-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        image::GrayImage::from_fn(2, 2, |x, _| image::Luma([x as u8 * 255]))
+            .save(dir.join("bumps.png"))
+            .unwrap();
+        let sphere = |image: &str| {
+            format!(
+                r##"{{"kind": "sphere", "name": "ball", "center": [0, 0, 0], "radius": 1,
+                "material": {{"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2,
+                    "shininess": 10, "bump": {{"type": "image", "image": "{}", "size": 1, "depth": 0.1}}}}}}"##,
+                image
+            )
+        };
+        let scene_path = dir.join("scene.json");
+        std::fs::write(
+            &scene_path,
+            format!(
+                r##"{{"camera": {{"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4}}, "lights": [], "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 0.1}}}},
+                "objects": [{}, {}, {}]}}"##,
+                sphere("bumps.png"),
+                sphere("bumps.png"),
+                sphere("missing.png")
+            ),
+        )
+        .unwrap();
+
+        let options = AssetOptions {
+            missing_asset: MissingAssetPolicy::Skip,
+            ..Default::default()
+        };
+        let mut scene =
+            Scene::from_json_file_with_assets(scene_path.to_str().unwrap(), &options).unwrap();
+        let height_map = |object: &Object| match object {
+            Object::Sphere { material, .. } => match &material.bump {
+                Some(Bump::Image { height_map, .. }) => height_map.clone(),
+                _ => panic!("expected an image bump"),
+            },
+            _ => panic!("expected a sphere"),
+        };
+
+        // Both spheres share one copy of the image; the missing one stays flat
+        let first = height_map(&scene.objects[0]).unwrap();
+        assert_eq!((first.width, first.height), (2, 2));
+        assert!(std::sync::Arc::ptr_eq(
+            &first,
+            &height_map(&scene.objects[1]).unwrap()
+        ));
+        assert!(height_map(&scene.objects[2]).is_none());
+        assert_eq!(scene.missing_assets.len(), 1);
+
+        // Overrides keep the loaded images, but can't bring in new ones
+        let overrides: crate::Overrides = serde_json::from_str(
+            r#"{"objects": {"ball": {"material": {"bump": {"depth": 0.3}}}}}"#,
+        )
+        .unwrap();
+        overrides.apply(&mut scene).unwrap();
+        assert!(std::sync::Arc::ptr_eq(
+            &first,
+            &height_map(&scene.objects[0]).unwrap()
+        ));
+        let overrides: crate::Overrides = serde_json::from_str(
+            r#"{"objects": {"ball": {"material": {"bump": {"image": "other.png"}}}}}"#,
+        )
+        .unwrap();
+        let error = overrides.apply(&mut scene).unwrap_err();
+        assert!(error.contains("'other.png' is not loaded"), "{}", error);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,25 +1,48 @@
-use crate::ray::Ray;
-use crate::scene::{Camera as CameraConfig, Point, Vec3};
+use crate::core::shading::grid_line_coverage;
+use crate::ray::{HitRecord, Ray, RayDifferentials};
+use crate::scene::{Camera as CameraConfig, Color, Point, Vec3};
 use nalgebra::Unit;
 
-/// Camera implementation supporting both orthographic and perspective projection
-#[derive(Debug)]
+/// How screen coordinates are mapped to ray directions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Parallel rays through a rectangular viewport
+    Orthographic,
+    /// Pinhole projection onto a flat viewport
+    Perspective,
+    /// Equidistant fisheye: angle from the view axis grows linearly with distance
+    /// from the image center. `fov` (radians) spans the image width, the diameter of
+    /// the image circle; corners outside the circle see nothing.
+    Fisheye { fov: f64 },
+    /// Full 360°×180° latitude/longitude panorama
+    Equirectangular,
+}
+
+/// Camera implementation supporting orthographic, perspective and spherical projections
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub origin: Point,
+    // For spherical projections these are the unit right and up vectors
     pub horizontal: Vec3,
     pub vertical: Vec3,
     pub lower_left_corner: Point,
     pub view_direction: Unit<Vec3>,
-    pub is_perspective: bool,
+    pub projection: Projection,
+    pub aspect_ratio: f64,
     pub focal_length: f64,
     // Grid background fields for orthographic cameras
     pub grid_pitch: Option<f64>,
     pub grid_color: Option<crate::scene::Color>,
     pub grid_thickness: Option<f64>,
+    pub grid_reflections: bool, // Reflected rays that miss everything show the grid too
+    // Size of a pixel in (u, v); when set, rays carry differentials for texture filtering
+    pub pixel_spread: Option<(f64, f64)>,
+    // Open and close times rays are spread over; None when nothing moves
+    pub shutter: Option<(f64, f64)>,
 }
 
 impl Camera {
-    /// Create a new camera from configuration (supports both ortho and perspective)
+    /// Create a new camera from configuration (ortho, perspective, fisheye or equirect)
     pub fn from_config(config: &CameraConfig, aspect_ratio: f64) -> Result<Self, String> {
         let origin = Point::new(config.position[0], config.position[1], config.position[2]);
         let target = Point::new(config.target[0], config.target[1], config.target[2]);
@@ -31,6 +54,11 @@ impl Camera {
         let v = w.cross(&u); // Up vector
         let view_direction = Unit::new_normalize(-*w.as_ref());
 
+        let (shutter_open, shutter_close) = config.shutter();
+        if shutter_close < shutter_open {
+            return Err("Camera shutter_close must not come before shutter_open".to_string());
+        }
+
         // Parse grid color if provided
         let grid_color = if let Some(color_str) = &config.grid_color {
             Some(crate::scene::hex_to_color(color_str)?)
@@ -59,11 +87,39 @@ impl Camera {
                 aspect_ratio,
                 grid_color,
             ),
+            "fisheye" => {
+                // Fisheye lenses default to a 180° (hemispherical) field of view
+                let fov = config.fov.unwrap_or(180.0);
+                if fov <= 0.0 || fov > 360.0 {
+                    return Err(
+                        "Fisheye field of view must be between 0 and 360 degrees".to_string()
+                    );
+                }
+                Ok(Self::create_spherical(
+                    origin,
+                    u,
+                    v,
+                    view_direction,
+                    Projection::Fisheye {
+                        fov: fov.to_radians(),
+                    },
+                    aspect_ratio,
+                ))
+            }
+            "equirect" => Ok(Self::create_spherical(
+                origin,
+                u,
+                v,
+                view_direction,
+                Projection::Equirectangular,
+                aspect_ratio,
+            )),
             _ => Err(format!("Unsupported camera type: {}", config.kind)),
         }
     }
 
     /// Create orthographic camera
+    #[allow(clippy::too_many_arguments)]
     fn create_orthographic(
         origin: Point,
         u: Unit<Vec3>,
@@ -74,9 +130,10 @@ impl Camera {
         aspect_ratio: f64,
         grid_color: Option<crate::scene::Color>,
     ) -> Result<Self, String> {
-        // Calculate viewport dimensions
-        let viewport_height = config.height;
-        let viewport_width = config.width.max(viewport_height * aspect_ratio);
+        // Grow the configured viewport along one axis to match the image aspect
+        // ratio, so the requested area stays visible and is never stretched
+        let viewport_width = config.width.max(config.height * aspect_ratio);
+        let viewport_height = viewport_width / aspect_ratio;
 
         // Calculate the horizontal and vertical vectors for the viewport
         let horizontal = viewport_width * u.as_ref();
@@ -91,15 +148,20 @@ impl Camera {
             vertical,
             lower_left_corner,
             view_direction,
-            is_perspective: false,
+            projection: Projection::Orthographic,
+            aspect_ratio,
             focal_length: 0.0, // Not used for orthographic
             grid_pitch: config.grid_pitch,
             grid_color,
             grid_thickness: config.grid_thickness,
+            grid_reflections: config.grid_reflections.unwrap_or(false),
+            pixel_spread: None,
+            shutter: None,
         })
     }
 
     /// Create perspective camera
+    #[allow(clippy::too_many_arguments)]
     fn create_perspective(
         origin: Point,
         u: Unit<Vec3>,
@@ -143,34 +205,217 @@ impl Camera {
             vertical,
             lower_left_corner,
             view_direction,
-            is_perspective: true,
+            projection: Projection::Perspective,
+            aspect_ratio,
             focal_length,
             grid_pitch: config.grid_pitch,
             grid_color,
             grid_thickness: config.grid_thickness,
+            grid_reflections: config.grid_reflections.unwrap_or(false),
+            pixel_spread: None,
+            shutter: None,
         })
     }
 
+    /// Create a fisheye or equirectangular camera
+    ///
+    /// Rays leave the camera origin; the projection is evaluated per ray in `get_ray`.
+    fn create_spherical(
+        origin: Point,
+        u: Unit<Vec3>,
+        v: Vec3,
+        view_direction: Unit<Vec3>,
+        projection: Projection,
+        aspect_ratio: f64,
+    ) -> Self {
+        Self {
+            origin,
+            horizontal: *u.as_ref(),
+            vertical: v,
+            lower_left_corner: origin,
+            view_direction,
+            projection,
+            aspect_ratio,
+            focal_length: 0.0, // Not used for spherical projections
+            grid_pitch: None,
+            grid_color: None,
+            grid_thickness: None,
+            grid_reflections: false,
+            pixel_spread: None,
+            shutter: None,
+        }
+    }
+
+    /// Whether rays diverge from a single origin (everything except orthographic)
+    pub fn is_perspective(&self) -> bool {
+        self.projection != Projection::Orthographic
+    }
+
+    /// Whether screen coordinates (u, v) see the scene, which they all do except
+    /// outside a fisheye's image circle
+    pub fn in_image(&self, u: f64, v: f64) -> bool {
+        match self.projection {
+            Projection::Fisheye { .. } => {
+                let (x, y) = self.fisheye_offset(u, v);
+                x * x + y * y <= 1.0
+            }
+            _ => true,
+        }
+    }
+
+    /// Offset of (u, v) from the image center, normalized so the image width spans
+    /// [-1, 1]
+    fn fisheye_offset(&self, u: f64, v: f64) -> (f64, f64) {
+        (2.0 * u - 1.0, (2.0 * v - 1.0) / self.aspect_ratio)
+    }
+
     /// Generate a ray for the given screen coordinates (u, v are in [0, 1])
+    ///
+    /// With `pixel_spread` set, the ray carries its differentials to the rays one
+    /// pixel over in u and v.
     pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        if self.is_perspective {
-            // For perspective projection, rays diverge from the camera origin
-            let viewport_point = self.lower_left_corner + u * self.horizontal + v * self.vertical;
-            let ray_direction = Unit::new_normalize(viewport_point - self.origin);
-            Ray::new(self.origin, *ray_direction.as_ref())
-        } else {
-            // For orthographic projection, all rays are parallel to the view direction
-            // The ray origin should be on the viewport plane, not at the camera position
-            let viewport_point = self.lower_left_corner + u * self.horizontal + v * self.vertical;
-            Ray::new(viewport_point, *self.view_direction.as_ref())
+        let ray = self.base_ray(u, v);
+        let Some((du, dv)) = self.pixel_spread else {
+            return ray;
+        };
+        let (ray_u, ray_v) = (self.base_ray(u + du, v), self.base_ray(u, v + dv));
+        let differentials = RayDifferentials {
+            origin_dx: ray_u.origin - ray.origin,
+            origin_dy: ray_v.origin - ray.origin,
+            direction_dx: ray_u.direction.as_ref() - ray.direction.as_ref(),
+            direction_dy: ray_v.direction.as_ref() - ray.direction.as_ref(),
+        };
+        ray.with_differentials(differentials)
+    }
+
+    /// Time of a ray taken `fraction` of the way from shutter open to close, or 0
+    /// when nothing moves
+    pub fn shutter_time(&self, fraction: f64) -> f64 {
+        self.shutter
+            .map_or(0.0, |(open, close)| open + fraction * (close - open))
+    }
+
+    fn base_ray(&self, u: f64, v: f64) -> Ray {
+        match self.projection {
+            Projection::Perspective => {
+                // For perspective projection, rays diverge from the camera origin
+                let viewport_point =
+                    self.lower_left_corner + u * self.horizontal + v * self.vertical;
+                let ray_direction = Unit::new_normalize(viewport_point - self.origin);
+                Ray::new(self.origin, *ray_direction.as_ref())
+            }
+            Projection::Orthographic => {
+                // For orthographic projection, all rays are parallel to the view direction
+                // The ray origin should be on the viewport plane, not at the camera position
+                let viewport_point =
+                    self.lower_left_corner + u * self.horizontal + v * self.vertical;
+                Ray::new(viewport_point, *self.view_direction.as_ref())
+            }
+            Projection::Fisheye { fov } => {
+                let (x, y) = self.fisheye_offset(u, v);
+                let r = (x * x + y * y).sqrt();
+
+                // Equidistant mapping: angle from the view axis is proportional to radius
+                let theta = r * fov / 2.0;
+                let (sin_theta, cos_theta) = theta.sin_cos();
+                let radial = if r > 1e-12 {
+                    (x / r) * self.horizontal + (y / r) * self.vertical
+                } else {
+                    Vec3::zeros()
+                };
+
+                let direction = cos_theta * self.view_direction.as_ref() + sin_theta * radial;
+                Ray::new(self.origin, direction)
+            }
+            Projection::Equirectangular => {
+                // Longitude spans the image width, latitude the image height
+                let longitude = (u - 0.5) * 2.0 * std::f64::consts::PI;
+                let latitude = (v - 0.5) * std::f64::consts::PI;
+
+                let (sin_lon, cos_lon) = longitude.sin_cos();
+                let (sin_lat, cos_lat) = latitude.sin_cos();
+
+                let direction = cos_lat
+                    * (cos_lon * self.view_direction.as_ref() + sin_lon * self.horizontal)
+                    + sin_lat * self.vertical;
+                Ray::new(self.origin, direction)
+            }
         }
     }
 
+    /// Screen coordinates (u, v) of a world point, the inverse of `get_ray`
+    ///
+    /// Returns None for points behind a perspective camera and for fisheye and
+    /// equirectangular cameras, whose projections bend straight lines.
+    pub fn project(&self, point: &Point) -> Option<(f64, f64)> {
+        let on_viewport = match self.projection {
+            Projection::Orthographic => *point,
+            Projection::Perspective => {
+                let offset = point - self.origin;
+                let distance = offset.dot(self.view_direction.as_ref());
+                if distance <= 1e-9 {
+                    return None;
+                }
+                self.origin + offset * (self.focal_length / distance)
+            }
+            Projection::Fisheye { .. } | Projection::Equirectangular => return None,
+        };
+        // The viewport vectors are perpendicular to each other and to the view direction
+        let offset = on_viewport - self.lower_left_corner;
+        Some((
+            offset.dot(&self.horizontal) / self.horizontal.norm_squared(),
+            offset.dot(&self.vertical) / self.vertical.norm_squared(),
+        ))
+    }
+
+    /// World size at `point` of one pixel of an image `width` pixels across
+    ///
+    /// Measured across the view for flat projections; for fisheye and equirectangular
+    /// cameras it is the arc a pixel spans at the point's distance.
+    pub fn pixel_size(&self, point: &Point, width: u32) -> f64 {
+        let offset = point - self.origin;
+        let across = match self.projection {
+            Projection::Orthographic => self.horizontal.norm(),
+            Projection::Perspective => {
+                let depth = offset.dot(self.view_direction.as_ref()).abs();
+                self.horizontal.norm() * depth / self.focal_length
+            }
+            Projection::Fisheye { fov } => fov * offset.norm(),
+            Projection::Equirectangular => std::f64::consts::TAU * offset.norm(),
+        };
+        across / width as f64
+    }
+
     /// Check if an orthographic camera ray intersects with grid lines
     /// Returns the grid color if the ray hits a grid line, None otherwise
-    pub fn get_grid_color(&self, ray: &Ray) -> Option<crate::scene::Color> {
+    pub fn get_grid_color(&self, ray: &Ray) -> Option<Color> {
+        let point_sample = Ray {
+            differentials: None,
+            ..ray.clone()
+        };
+        match self.grid_coverage(&point_sample)? {
+            (grid_color, coverage) if coverage > 0.0 => Some(grid_color),
+            _ => None,
+        }
+    }
+
+    /// What a ray that missed everything shows: `background`, blended towards the grid
+    /// color by how much of the ray's pixel falls on grid lines
+    pub fn grid_background(&self, ray: &Ray, background: Color) -> Color {
+        match self.grid_coverage(ray) {
+            Some((grid_color, coverage)) => background + (grid_color - background) * coverage,
+            None => background,
+        }
+    }
+
+    /// Grid color and the share of the ray's pixel footprint on grid lines, or None if
+    /// there is no grid
+    ///
+    /// The footprint comes from the ray's differentials, and the share is exactly 0 or
+    /// 1 for rays without them.
+    fn grid_coverage(&self, ray: &Ray) -> Option<(Color, f64)> {
         // Only orthographic cameras support grid backgrounds
-        if self.is_perspective {
+        if self.is_perspective() {
             return None;
         }
 
@@ -183,77 +428,43 @@ impl Camera {
                 _ => return None,
             };
 
-        let half_thickness = grid_thickness / 2.0;
-
-        // For orthographic rays, we need to find intersections with the origin planes
-        // and check if we're close to grid lines
-
-        // Check intersection with XY plane (z = 0)
-        if ray.direction.z.abs() > 1e-10 {
-            let t = -ray.origin.z / ray.direction.z;
-            if t > 0.0 {
-                let intersection_point = ray.origin + t * ray.direction.as_ref();
-                let x = intersection_point.x;
-                let y = intersection_point.y;
-
-                // Check if we're on a grid line
-                let x_mod = (x / grid_pitch).fract().abs();
-                let y_mod = (y / grid_pitch).fract().abs();
-
-                let x_grid_dist = (x_mod * grid_pitch).min((1.0 - x_mod) * grid_pitch);
-                let y_grid_dist = (y_mod * grid_pitch).min((1.0 - y_mod) * grid_pitch);
-
-                if x_grid_dist <= half_thickness || y_grid_dist <= half_thickness {
-                    return Some(*grid_color);
-                }
+        // The grid lies on the XY, XZ and YZ planes through the origin; a ray is on
+        // a grid line if it crosses one on any of them
+        let mut uncovered = 1.0;
+        for (axis, (a, b)) in [(2, (0, 1)), (1, (0, 2)), (0, (1, 2))] {
+            if ray.direction[axis].abs() <= 1e-10 {
+                continue;
             }
-        }
-
-        // Check intersection with XZ plane (y = 0)
-        if ray.direction.y.abs() > 1e-10 {
-            let t = -ray.origin.y / ray.direction.y;
-            if t > 0.0 {
-                let intersection_point = ray.origin + t * ray.direction.as_ref();
-                let x = intersection_point.x;
-                let z = intersection_point.z;
-
-                let x_mod = (x / grid_pitch).fract().abs();
-                let z_mod = (z / grid_pitch).fract().abs();
-
-                let x_grid_dist = (x_mod * grid_pitch).min((1.0 - x_mod) * grid_pitch);
-                let z_grid_dist = (z_mod * grid_pitch).min((1.0 - z_mod) * grid_pitch);
-
-                if x_grid_dist <= half_thickness || z_grid_dist <= half_thickness {
-                    return Some(*grid_color);
-                }
+            let t = -ray.origin[axis] / ray.direction[axis];
+            if t <= 0.0 {
+                continue;
             }
-        }
-
-        // Check intersection with YZ plane (x = 0)
-        if ray.direction.x.abs() > 1e-10 {
-            let t = -ray.origin.x / ray.direction.x;
-            if t > 0.0 {
-                let intersection_point = ray.origin + t * ray.direction.as_ref();
-                let y = intersection_point.y;
-                let z = intersection_point.z;
-
-                let y_mod = (y / grid_pitch).fract().abs();
-                let z_mod = (z / grid_pitch).fract().abs();
-
-                let y_grid_dist = (y_mod * grid_pitch).min((1.0 - y_mod) * grid_pitch);
-                let z_grid_dist = (z_mod * grid_pitch).min((1.0 - z_mod) * grid_pitch);
-
-                if y_grid_dist <= half_thickness || z_grid_dist <= half_thickness {
-                    return Some(*grid_color);
+            let point = ray.at(t);
+            let footprint = match &ray.differentials {
+                Some(differentials) => {
+                    let mut normal = Vec3::zeros();
+                    normal[axis] = 1.0;
+                    let hit = HitRecord::new(point, normal, t, ray, Color::zeros(), 0);
+                    let (dx, dy) = differentials.surface_offsets(ray, &hit);
+                    (dx[a].abs().max(dy[a].abs()), dx[b].abs().max(dy[b].abs()))
                 }
-            }
+                None => (0.0, 0.0),
+            };
+            let coverage = grid_line_coverage(
+                point[a],
+                point[b],
+                grid_pitch,
+                grid_thickness / grid_pitch,
+                footprint,
+            );
+            uncovered *= 1.0 - coverage;
         }
-
-        None
+        Some((*grid_color, 1.0 - uncovered))
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod tests {
     use super::*;
     use crate::scene::Camera as CameraConfig;
@@ -266,7 +477,24 @@ mod tests {
         // Test that we can generate rays
         let ray = camera.get_ray(0.5, 0.5);
         assert_eq!(ray.origin, Point::new(0.0, -5.0, 2.0));
-        assert!(!camera.is_perspective);
+        assert!(!camera.is_perspective());
+    }
+
+    #[test]
+    fn test_orthographic_viewport_follows_image_aspect() {
+        let mut config = CameraConfig::default();
+        config.width = 16.0;
+        config.height = 9.0;
+
+        // Wider image: the viewport widens and keeps the configured height
+        let wide = Camera::from_config(&config, 2.0).unwrap();
+        assert!((wide.horizontal.magnitude() - 18.0).abs() < 1e-9);
+        assert!((wide.vertical.magnitude() - 9.0).abs() < 1e-9);
+
+        // Taller image: the viewport grows vertically instead of squashing
+        let square = Camera::from_config(&config, 1.0).unwrap();
+        assert!((square.horizontal.magnitude() - 16.0).abs() < 1e-9);
+        assert!((square.vertical.magnitude() - 16.0).abs() < 1e-9);
     }
 
     #[test]
@@ -276,7 +504,7 @@ mod tests {
         config.fov = Some(45.0);
 
         let camera = Camera::from_config(&config, 1.0).unwrap();
-        assert!(camera.is_perspective);
+        assert_eq!(camera.projection, Projection::Perspective);
         assert_eq!(camera.focal_length, 1.0);
 
         // Test that we can generate rays
@@ -296,7 +524,7 @@ mod tests {
         // Don't specify fov, should default to 45 degrees
 
         let camera = Camera::from_config(&config, 1.0).unwrap();
-        assert!(camera.is_perspective);
+        assert_eq!(camera.projection, Projection::Perspective);
     }
 
     #[test]
@@ -319,13 +547,95 @@ mod tests {
     #[test]
     fn test_unsupported_camera_type() {
         let mut config = CameraConfig::default();
-        config.kind = "fisheye".to_string();
+        config.kind = "cylindrical".to_string();
 
         let result = Camera::from_config(&config, 1.0);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Unsupported camera type"));
     }
 
+    #[test]
+    fn test_fisheye_camera() {
+        let mut config = CameraConfig::default();
+        config.kind = "fisheye".to_string();
+        config.position = [0.0, 0.0, 0.0];
+        config.target = [0.0, 1.0, 0.0];
+
+        let camera = Camera::from_config(&config, 1.0).unwrap();
+        assert_eq!(
+            camera.projection,
+            Projection::Fisheye {
+                fov: 180.0_f64.to_radians()
+            }
+        );
+
+        // Center looks straight ahead
+        let center = camera.get_ray(0.5, 0.5);
+        assert_eq!(center.origin, camera.origin);
+        assert!((center.direction.as_ref() - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+
+        // Edges of a 180° fisheye look 90° sideways and straight up
+        let right = camera.get_ray(1.0, 0.5);
+        assert!((right.direction.as_ref() - Vec3::new(1.0, 0.0, 0.0)).magnitude() < 1e-9);
+        let top = camera.get_ray(0.5, 1.0);
+        assert!((top.direction.as_ref() - Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+
+        // Corners lie outside the image circle, edge midpoints on it
+        assert!(camera.in_image(0.5, 0.5) && camera.in_image(1.0, 0.5));
+        assert!(!camera.in_image(1.0, 1.0) && !camera.in_image(0.0, 0.0));
+        let wide = Camera::from_config(&config, 2.0).unwrap();
+        assert!(wide.in_image(0.5, 1.0) && !wide.in_image(0.95, 1.0));
+
+        config.fov = Some(400.0);
+        assert!(Camera::from_config(&config, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_equirect_camera() {
+        let mut config = CameraConfig::default();
+        config.kind = "equirect".to_string();
+        config.position = [0.0, 0.0, 0.0];
+        config.target = [0.0, 1.0, 0.0];
+
+        let camera = Camera::from_config(&config, 2.0).unwrap();
+        assert_eq!(camera.projection, Projection::Equirectangular);
+
+        let forward = camera.get_ray(0.5, 0.5);
+        assert!((forward.direction.as_ref() - Vec3::new(0.0, 1.0, 0.0)).magnitude() < 1e-9);
+
+        // A quarter of the way across is 90° to the left
+        let left = camera.get_ray(0.25, 0.5);
+        assert!((left.direction.as_ref() - Vec3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-9);
+
+        // Both horizontal edges look straight backwards
+        let back = camera.get_ray(0.0, 0.5);
+        assert!((back.direction.as_ref() - Vec3::new(0.0, -1.0, 0.0)).magnitude() < 1e-9);
+
+        // Top row looks straight up (Z-up)
+        let up = camera.get_ray(0.3, 1.0);
+        assert!((up.direction.as_ref() - Vec3::new(0.0, 0.0, 1.0)).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn test_project_inverts_get_ray() {
+        let mut config = CameraConfig::default();
+        for kind in ["ortho", "perspective"] {
+            config.kind = kind.to_string();
+            let camera = Camera::from_config(&config, 1.5).unwrap();
+            let ray = camera.get_ray(0.2, 0.7);
+            let point = ray.origin + 3.0 * ray.direction.as_ref();
+            let (u, v) = camera.project(&point).unwrap();
+            assert!((u - 0.2).abs() < 1e-9 && (v - 0.7).abs() < 1e-9, "{}", kind);
+        }
+
+        // Nothing behind a perspective camera, and no flat projection for spherical ones
+        let camera = Camera::from_config(&config, 1.5).unwrap();
+        assert!(camera.project(&Point::new(0.0, -10.0, 2.0)).is_none());
+        config.kind = "fisheye".to_string();
+        let camera = Camera::from_config(&config, 1.5).unwrap();
+        assert!(camera.project(&Point::origin()).is_none());
+    }
+
     #[test]
     fn test_perspective_ray_divergence() {
         let mut config = CameraConfig::default();
@@ -393,6 +703,38 @@ mod tests {
         assert!(grid_color_miss.is_none(), "Ray should miss grid lines");
     }
 
+    #[test]
+    fn test_grid_background_filtering() {
+        let mut config = CameraConfig::default();
+        config.grid_pitch = Some(1.0);
+        config.grid_color = Some("#FF0000".to_string());
+        config.grid_thickness = Some(0.1);
+        let mut camera = Camera::from_config(&config, 1.0).unwrap();
+        let black = Color::zeros();
+
+        let ray = Ray::new(Point::new(0.3, 0.3, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(camera.grid_background(&ray, black), black);
+
+        // A pixel many cells wide sees the lines' share of the plane, 0.1 across each axis
+        let wide = ray.clone().with_differentials(RayDifferentials {
+            origin_dx: Vec3::new(40.0, 0.0, 0.0),
+            origin_dy: Vec3::new(0.0, 40.0, 0.0),
+            direction_dx: Vec3::zeros(),
+            direction_dy: Vec3::zeros(),
+        });
+        let color = camera.grid_background(&wide, black);
+        assert!((color.x - 0.19).abs() < 0.01 && color.y == 0.0);
+        // Point sampling ignores the footprint
+        assert!(camera.get_grid_color(&wide).is_none());
+
+        // Rays from a camera with a pixel spread carry their differentials
+        camera.pixel_spread = Some((0.01, 0.02));
+        let differentials = camera.get_ray(0.5, 0.5).differentials.unwrap();
+        assert!((differentials.origin_dx - 0.01 * camera.horizontal).norm() < 1e-12);
+        assert!((differentials.origin_dy - 0.02 * camera.vertical).norm() < 1e-12);
+        assert_eq!(differentials.direction_dx, Vec3::zeros());
+    }
+
     #[test]
     fn test_perspective_camera_no_grid() {
         let mut config = CameraConfig::default();
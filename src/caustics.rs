@@ -0,0 +1,389 @@
+//! Caustics from photon mapping
+//!
+//! Before rendering, each light shoots photons at the transparent and reflective
+//! objects. Photons are followed through refractions and mirror bounces and left
+//! where they land on diffuse surfaces; shading then adds up the photons near a point
+//! as extra light from the light that shot them. Only light that got there by way of
+//! glass or a mirror is stored, since shadow rays already account for direct light.
+
+use std::f64::consts::PI;
+
+use nalgebra::Unit;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::core::shading::reflect;
+use crate::lighting::{effective_material, refracted_ray, sample_disk_light_point, shading_hit};
+use crate::ray::{Ray, World};
+use crate::scene::{
+    hex_to_color, Caustics, Color, Light, Material, Object, Point, Scene, Texture, Vec3,
+};
+use crate::SeededRng;
+
+/// Photons traced from one seed; batches run in parallel and are kept in order
+const PHOTON_BATCH: u32 = 4096;
+
+/// Light arriving at a diffuse surface after passing through glass or off a mirror
+#[derive(Debug, Clone)]
+struct Photon {
+    position: Point,
+    power: Color,
+    direction: Vec3, // direction of travel when it landed
+}
+
+/// Photons one light left on diffuse surfaces, bucketed by cells the size of the
+/// gather radius
+#[derive(Debug)]
+pub struct PhotonMap {
+    radius: f64,
+    photons: Vec<Photon>,          // ordered by cell
+    cells: Vec<([i64; 3], usize)>, // each occupied cell, sorted, with its first photon
+}
+
+impl PhotonMap {
+    fn new(mut photons: Vec<Photon>, radius: f64) -> Self {
+        // A stable sort keeps the photons of each cell in the order they were traced
+        photons.sort_by_key(|photon| cell_of(&photon.position, radius));
+        let mut cells: Vec<([i64; 3], usize)> = Vec::new();
+        for (index, photon) in photons.iter().enumerate() {
+            let cell = cell_of(&photon.position, radius);
+            if cells.last().is_none_or(|(last, _)| *last != cell) {
+                cells.push((cell, index));
+            }
+        }
+        Self {
+            radius,
+            photons,
+            cells,
+        }
+    }
+
+    /// Number of photons stored
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Caustic light arriving at `point` on a surface facing `normal`, per unit area
+    ///
+    /// Photons within the radius count most at the center and fade out towards its
+    /// edge, which smooths the estimate without shifting it.
+    pub fn irradiance(&self, point: &Point, normal: &Unit<Vec3>) -> Color {
+        let radius_squared = self.radius * self.radius;
+        let center = cell_of(point, self.radius);
+        let mut total = Color::zeros();
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let cell = [center[0] + dx, center[1] + dy, center[2] + dz];
+                    let Ok(found) = self.cells.binary_search_by(|(key, _)| key.cmp(&cell)) else {
+                        continue;
+                    };
+                    let start = self.cells[found].1;
+                    let end = self
+                        .cells
+                        .get(found + 1)
+                        .map_or(self.photons.len(), |(_, next)| *next);
+                    for photon in &self.photons[start..end] {
+                        // Photons arriving from behind light the other side of the surface
+                        if photon.direction.dot(normal) >= 0.0 {
+                            continue;
+                        }
+                        let distance_squared = (photon.position - point).norm_squared();
+                        if distance_squared < radius_squared {
+                            total += photon.power * (1.0 - distance_squared / radius_squared);
+                        }
+                    }
+                }
+            }
+        }
+        // The weights integrate to half the disk's area
+        total * (2.0 / (PI * radius_squared))
+    }
+}
+
+fn cell_of(point: &Point, size: f64) -> [i64; 3] {
+    [0, 1, 2].map(|axis| (point[axis] / size).floor() as i64)
+}
+
+/// Directions from a light that can reach one target's bounding sphere
+struct Cone {
+    axis: Unit<Vec3>,
+    across: (Vec3, Vec3), // perpendicular to the axis and each other
+    cos_max: f64,
+    solid_angle: f64,
+}
+
+impl Cone {
+    fn new(apex: &Point, center: &Point, radius: f64) -> Self {
+        let offset = center - apex;
+        let distance = offset.norm();
+        // From inside the sphere every direction leads to it
+        let cos_max = if distance <= radius {
+            -1.0
+        } else {
+            (1.0 - (radius / distance).powi(2)).sqrt()
+        };
+        let axis = if distance > 0.0 {
+            Unit::new_normalize(offset)
+        } else {
+            Vec3::z_axis()
+        };
+        let helper = if axis.x.abs() < 0.9 {
+            Vec3::x()
+        } else {
+            Vec3::y()
+        };
+        let u = axis.cross(&helper).normalize();
+        let v = axis.cross(&u);
+        Self {
+            axis,
+            across: (u, v),
+            cos_max,
+            solid_angle: 2.0 * PI * (1.0 - cos_max),
+        }
+    }
+
+    /// Direction evenly distributed over the cone for uniform `r1` and `r2` in [0, 1)
+    fn sample(&self, r1: f64, r2: f64) -> Vec3 {
+        let cos_theta = 1.0 - r1 * (1.0 - self.cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * r2;
+        let (u, v) = self.across;
+        (u * phi.cos() + v * phi.sin()) * sin_theta + self.axis.as_ref() * cos_theta
+    }
+
+    fn contains(&self, direction: &Vec3) -> bool {
+        direction.dot(&self.axis) >= self.cos_max
+    }
+}
+
+/// Bounding spheres, as center and radius, of the finite objects with a transparent or
+/// reflective material (or mesh faces with one), which photons are aimed at
+pub fn caustic_targets(scene: &Scene) -> Vec<(Point, f64)> {
+    scene
+        .objects
+        .iter()
+        .filter(|object| {
+            let faces = match object {
+                Object::Mesh { face_materials, .. } => face_materials.as_slice(),
+                _ => &[],
+            };
+            object.material().is_some_and(focuses_light)
+                || faces.iter().any(|face| focuses_light(&face.material))
+        })
+        .filter_map(|object| scene.object_bounds(object))
+        .map(|(min, max)| (nalgebra::center(&min, &max), (max - min).norm() / 2.0))
+        .collect()
+}
+
+/// Whether light can pass through or bounce off any part of `material`
+fn focuses_light(material: &Material) -> bool {
+    let checker = match &material.texture {
+        Some(Texture::Checkerboard { material_b }) => focuses_light(material_b),
+        _ => false,
+    };
+    material.transparency.is_some_and(|share| share > 0.0)
+        || material.reflectivity.is_some_and(|share| share > 0.0)
+        || checker
+}
+
+/// Photons `light` focuses onto diffuse surfaces, or None if it has none to give
+///
+/// `targets` are bounding spheres, as center and radius, of the transparent and
+/// reflective objects. Photons are only shot towards them, evenly over the directions
+/// that can reach one, so each carries the light's intensity times its share of those
+/// directions. Lights given off by emissive surfaces cast no caustics.
+#[allow(clippy::too_many_arguments)]
+pub fn trace_photons(
+    settings: &Caustics,
+    light: &Light,
+    light_index: usize,
+    targets: &[(Point, f64)],
+    world: &World,
+    materials: &[Material],
+    seed: u64,
+) -> Option<PhotonMap> {
+    if light.emitter.is_some() || targets.is_empty() {
+        return None;
+    }
+    let position = Point::from(light.position);
+    let light_radius = light.diameter.unwrap_or(0.0) / 2.0;
+    // Area lights shoot from anywhere on their disk, so the cones widen to match
+    let cones: Vec<Cone> = targets
+        .iter()
+        .map(|(center, radius)| Cone::new(&position, center, radius + light_radius))
+        .collect();
+    let solid_angle: f64 = cones.iter().map(|cone| cone.solid_angle).sum();
+    let light_color = hex_to_color(&light.color).unwrap_or(Color::new(1.0, 1.0, 1.0));
+    let photon_power = light_color * (light.intensity * solid_angle / settings.photons as f64);
+
+    let trace_batch = |batch: u32| {
+        let mut rng = SeededRng::seed_from_u64(batch_seed(seed, light_index, batch));
+        let count = PHOTON_BATCH.min(settings.photons - batch * PHOTON_BATCH);
+        let mut photons = Vec::new();
+        for _ in 0..count {
+            // Pick a cone by its share of the directions, then a direction within it
+            let mut pick = rng.gen::<f64>() * solid_angle;
+            let cone = cones
+                .iter()
+                .find(|cone| {
+                    pick -= cone.solid_angle;
+                    pick < 0.0
+                })
+                .unwrap_or(&cones[cones.len() - 1]);
+            let direction = cone.sample(rng.gen(), rng.gen());
+            // Directions where cones overlap are drawn that many times as often
+            let overlap = cones
+                .iter()
+                .filter(|cone| cone.contains(&direction))
+                .count();
+            let origin = match light.diameter {
+                Some(diameter) => sample_disk_light_point(
+                    &mut rng,
+                    &position,
+                    &(position - direction),
+                    diameter,
+                    0,
+                    1,
+                ),
+                None => position,
+            };
+            let ray = Ray::new(origin, direction);
+            let power = photon_power / overlap.max(1) as f64;
+            follow_photon(
+                ray,
+                power,
+                light,
+                settings,
+                world,
+                materials,
+                &mut rng,
+                &mut photons,
+            );
+        }
+        photons
+    };
+
+    let batch_count = settings.photons.div_ceil(PHOTON_BATCH);
+    #[cfg(feature = "parallel")]
+    let batches = (0..batch_count).into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let batches = 0..batch_count;
+    let photons: Vec<Photon> = batches
+        .map(trace_batch)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flatten()
+        .collect();
+
+    (!photons.is_empty()).then(|| PhotonMap::new(photons, settings.radius))
+}
+
+/// Follow one photon through refractions and reflections, storing it on each diffuse
+/// surface it reaches after the first
+#[allow(clippy::too_many_arguments)]
+fn follow_photon(
+    mut ray: Ray,
+    mut power: Color,
+    light: &Light,
+    settings: &Caustics,
+    world: &World,
+    materials: &[Material],
+    rng: &mut SeededRng,
+    photons: &mut Vec<Photon>,
+) {
+    let mut traveled = 0.0;
+    for bounce in 0..=settings.max_bounces {
+        let Some(hit) = world.hit(&ray, 0.001, f64::INFINITY) else {
+            return;
+        };
+        traveled += hit.t;
+        let material = materials
+            .get(hit.material_key())
+            .cloned()
+            .unwrap_or_default();
+        let surface = effective_material(&hit, &material);
+        if bounce > 0 && surface.diffuse > 0.0 {
+            // Stored to match how direct light dims: photons thin out with the square of
+            // the distance, so that is undone for lights without physical falloff
+            let spread = traveled * traveled * light.falloff(traveled);
+            photons.push(Photon {
+                position: hit.point,
+                power: power * spread,
+                direction: *ray.direction,
+            });
+        }
+
+        // Carry on through the surface or off it in proportion to how it is shaded
+        let transparency = surface.transparency.unwrap_or(0.0).clamp(0.0, 1.0);
+        let reflectivity = surface.reflectivity.unwrap_or(0.0).clamp(0.0, 1.0);
+        let normal = shading_hit(&hit, &material).normal;
+        let choice: f64 = rng.gen();
+        if choice < transparency {
+            let tint = hex_to_color(&surface.color).unwrap_or(Color::new(1.0, 1.0, 1.0));
+            ray = refracted_ray(&ray, &hit, &normal, surface.refractive_index());
+            power = power.component_mul(&tint);
+        } else if choice < transparency + (1.0 - transparency) * reflectivity {
+            let direction = reflect(ray.direction.as_ref(), &normal);
+            ray = Ray::new(hit.point + 0.001 * hit.normal.as_ref(), *direction);
+        } else {
+            return;
+        }
+    }
+}
+
+fn batch_seed(seed: u64, light_index: usize, batch: u32) -> u64 {
+    seed.wrapping_mul(0x9E3779B97F4A7C15_u64)
+        .wrapping_add((light_index as u64).wrapping_mul(0x85EBCA6B))
+        .wrapping_add((batch as u64).wrapping_mul(0xC2B2AE35))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_photon_map_irradiance() {
+        let up = Unit::new_normalize(Vec3::z());
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        let photon = |x: f64, direction: Vec3| Photon {
+            position: Point::new(x, 0.0, 0.0),
+            power: Color::new(1.0, 0.5, 0.0),
+            direction,
+        };
+        let map = PhotonMap::new(
+            vec![photon(0.0, down), photon(0.35, down), photon(0.0, -down)],
+            0.5,
+        );
+        assert_eq!(map.len(), 3);
+
+        // The photon at the center counts fully, the one 0.35 away about half, and the
+        // one arriving from below not at all
+        let irradiance = map.irradiance(&Point::origin(), &up);
+        let expected = (1.0 + (1.0 - 0.49)) * 2.0 / (PI * 0.25);
+        assert!((irradiance.x - expected).abs() < 1e-12);
+        assert!((irradiance.y - expected / 2.0).abs() < 1e-12);
+        assert_eq!(
+            map.irradiance(&Point::new(2.0, 0.0, 0.0), &up),
+            Color::zeros()
+        );
+    }
+
+    #[test]
+    fn test_cone() {
+        let cone = Cone::new(&Point::origin(), &Point::new(0.0, 0.0, 10.0), 5.0);
+        // A sphere half as wide as it is far away spans 30 degrees either side
+        assert!((cone.cos_max - 0.75f64.sqrt()).abs() < 1e-12);
+        for (r1, r2) in [(0.0, 0.0), (0.5, 0.25), (0.999, 0.9)] {
+            let direction = cone.sample(r1, r2);
+            assert!((direction.norm() - 1.0).abs() < 1e-12);
+            assert!(cone.contains(&direction));
+        }
+        assert!(!cone.contains(&Vec3::x()));
+    }
+}
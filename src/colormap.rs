@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::scene::{hex_to_color, Color};
+
+/// Built-in scientific color map for data values, from low to high
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMap {
+    /// Dark purple through teal to yellow, evenly bright steps that read in grayscale
+    #[default]
+    Viridis,
+    /// Dark blue through magenta to yellow, evenly bright steps like viridis
+    Plasma,
+    /// Blue through light gray to red, for values either side of a midpoint
+    Coolwarm,
+}
+
+impl ColorMap {
+    /// Every built-in color map
+    pub const ALL: [ColorMap; 3] = [ColorMap::Viridis, ColorMap::Plasma, ColorMap::Coolwarm];
+
+    /// Name used in scene files and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorMap::Viridis => "viridis",
+            ColorMap::Plasma => "plasma",
+            ColorMap::Coolwarm => "coolwarm",
+        }
+    }
+
+    /// Color `share` of the way from the low end of the map to the high end
+    pub fn sample(&self, share: f64) -> Color {
+        sample_stops(&self.stops(), share)
+    }
+
+    /// The map's colors at nine even steps from low to high
+    pub fn stops(&self) -> Vec<Color> {
+        let hex: [u32; 9] = match self {
+            ColorMap::Viridis => [
+                0x440154, 0x472D7B, 0x3B528B, 0x2C728E, 0x21908C, 0x27AD81, 0x5DC863, 0xAADC32,
+                0xFDE725,
+            ],
+            ColorMap::Plasma => [
+                0x0D0887, 0x4C02A1, 0x7E03A8, 0xA92395, 0xCC4678, 0xE56B5D, 0xF89441, 0xFDC328,
+                0xF0F921,
+            ],
+            ColorMap::Coolwarm => [
+                0x3B4CC0, 0x6282EA, 0x8DB0FE, 0xB8D0F9, 0xDDDDDD, 0xF5C4AC, 0xF49A7B, 0xDE604D,
+                0xB40426,
+            ],
+        };
+        let channel = |value: u32, shift: u32| ((value >> shift) & 0xFF) as f64 / 255.0;
+        (hex.iter())
+            .map(|&value| Color::new(channel(value, 16), channel(value, 8), channel(value, 0)))
+            .collect()
+    }
+}
+
+impl std::str::FromStr for ColorMap {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ColorMap::ALL
+            .into_iter()
+            .find(|map| map.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = ColorMap::ALL.iter().map(ColorMap::name).collect();
+                format!(
+                    "Invalid color map '{}'. Valid options are: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Colors for data values in a scene file: a built-in map's name, or hex colors spread
+/// evenly from low to high
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ColorMapSpec {
+    Named(ColorMap),
+    Colors(Vec<String>),
+}
+
+impl Default for ColorMapSpec {
+    fn default() -> Self {
+        ColorMapSpec::Named(ColorMap::default())
+    }
+}
+
+impl ColorMapSpec {
+    /// Colors at even steps from low to high, for `sample_stops`
+    pub fn stops(&self) -> Result<Vec<Color>, String> {
+        match self {
+            ColorMapSpec::Named(map) => Ok(map.stops()),
+            ColorMapSpec::Colors(colors) if colors.is_empty() => {
+                Err("A color map needs at least one color".to_string())
+            }
+            ColorMapSpec::Colors(colors) => colors.iter().map(|hex| hex_to_color(hex)).collect(),
+        }
+    }
+}
+
+/// Color map stops with the values at either end of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorScale {
+    /// Colors at even steps from the low end to the high end
+    pub stops: Vec<Color>,
+    /// Values at the low and high ends
+    pub range: [f64; 2],
+}
+
+impl ColorScale {
+    /// Color for `value`, or the end color beyond either end
+    pub fn color(&self, value: f64) -> Color {
+        sample_stops(&self.stops, normalize(value, self.range))
+    }
+}
+
+/// Color `share` of the way along the evenly spaced `stops`, clamped to the ends
+pub fn sample_stops(stops: &[Color], share: f64) -> Color {
+    if stops.len() == 1 {
+        return stops[0];
+    }
+    let position = share.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let index = (position.floor() as usize).min(stops.len() - 2);
+    stops[index].lerp(&stops[index + 1], position - index as f64)
+}
+
+/// Share of the way `value` lies from `range[0]` to `range[1]`, clamped to 0..1; the
+/// middle when the range is empty
+pub fn normalize(value: f64, range: [f64; 2]) -> f64 {
+    let [low, high] = range;
+    match high > low {
+        true => ((value - low) / (high - low)).clamp(0.0, 1.0),
+        false => 0.5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_maps() {
+        for map in ColorMap::ALL {
+            assert_eq!(map.name().parse(), Ok(map));
+        }
+        assert!("jet".parse::<ColorMap>().is_err());
+
+        let hex = |color: Color| {
+            let channel = |value: f64| (value * 255.0).round() as u8;
+            format!(
+                "#{:02X}{:02X}{:02X}",
+                channel(color.x),
+                channel(color.y),
+                channel(color.z)
+            )
+        };
+        assert_eq!(hex(ColorMap::Viridis.sample(0.0)), "#440154");
+        assert_eq!(hex(ColorMap::Plasma.sample(1.0)), "#F0F921");
+        assert_eq!(hex(ColorMap::Coolwarm.sample(0.5)), "#DDDDDD");
+        assert_eq!(hex(ColorMap::Coolwarm.sample(2.0)), "#B40426");
+
+        // Scene files name a map or list their own colors
+        let spec: ColorMapSpec = serde_json::from_str(r#""plasma""#).unwrap();
+        assert_eq!(spec, ColorMapSpec::Named(ColorMap::Plasma));
+        let spec: ColorMapSpec = serde_json::from_str(r##"["#000000", "#FFFFFF"]"##).unwrap();
+        let stops = spec.stops().unwrap();
+        assert_eq!(sample_stops(&stops, 0.25), Color::repeat(0.25));
+        assert!(ColorMapSpec::Colors(Vec::new()).stops().is_err());
+        assert!(serde_json::from_str::<ColorMapSpec>(r#""jet""#).is_err());
+
+        assert_eq!(normalize(15.0, [10.0, 20.0]), 0.5);
+        assert_eq!(normalize(-5.0, [10.0, 20.0]), 0.0);
+        assert_eq!(normalize(3.0, [3.0, 3.0]), 0.5);
+    }
+}
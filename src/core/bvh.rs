@@ -0,0 +1,305 @@
+//! Bounding volume hierarchy over the objects of a world
+//!
+//! Objects keep their own acceleration, like the k-d trees of meshes; this top-level
+//! tree lets a ray skip every object whose box it misses. When objects move without
+//! being added or removed, `refit` reshapes the boxes of the same tree around them
+//! rather than building it again.
+
+use super::ray::{ray_hits_aabb, Intersectable, Ray};
+use super::{Point, Vec3};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Most objects a leaf holds before it is split
+const LEAF_SIZE: usize = 4;
+
+/// Deepest tree traversal can walk; median splits of any world that fits in memory
+/// stay far shallower
+const MAX_DEPTH: usize = 64;
+
+type Object = Box<dyn Intersectable + Send + Sync>;
+
+#[derive(Debug, Clone)]
+struct Node {
+    min: Point,
+    max: Point,
+    start: usize, // Leaves: first of their objects in `order`; inner nodes: second child
+    count: usize, // Objects of a leaf, 0 for inner nodes, whose first child follows them
+    axis: usize,  // Axis an inner node's children were split along
+}
+
+/// Tree of boxes around a world's bounded objects, found by index into its objects
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    order: Vec<usize>,     // Bounded objects, leaf by leaf
+    unbounded: Vec<usize>, // Objects without a box, tested against every ray
+    object_count: usize,
+}
+
+impl Bvh {
+    /// Build the tree over `objects`
+    pub fn new(objects: &[Object]) -> Self {
+        let boxes: Vec<_> = objects.iter().map(|object| object_box(&**object)).collect();
+        let (mut order, unbounded): (Vec<usize>, Vec<usize>) =
+            (0..objects.len()).partition(|&index| boxes[index].is_some());
+        let mut nodes = Vec::new();
+        if !order.is_empty() {
+            build_node(&mut nodes, &boxes, &mut order, 0);
+        }
+        Self {
+            nodes,
+            order,
+            unbounded,
+            object_count: objects.len(),
+        }
+    }
+
+    /// Number of objects the tree was built over
+    pub fn object_count(&self) -> usize {
+        self.object_count
+    }
+
+    /// Fit the boxes of the tree to `objects`, the objects it was built over after some
+    /// have moved, keeping its shape
+    ///
+    /// False, leaving the tree as it was, if the objects no longer fit it: there are
+    /// more or fewer of them, or one has gained or lost its box.
+    pub fn refit(&mut self, objects: &[Object]) -> bool {
+        if objects.len() != self.object_count {
+            return false;
+        }
+        let boxes: Vec<_> = objects.iter().map(|object| object_box(&**object)).collect();
+        if self.unbounded.iter().any(|&index| boxes[index].is_some())
+            || self.order.iter().any(|&index| boxes[index].is_none())
+        {
+            return false;
+        }
+        // Children follow their parents, so walking back fits them first
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            let (min, max) = match node.count {
+                0 => {
+                    let (first, second) = (&self.nodes[index + 1], &self.nodes[node.start]);
+                    (first.min.inf(&second.min), first.max.sup(&second.max))
+                }
+                count => enclose(
+                    self.order[node.start..node.start + count]
+                        .iter()
+                        .filter_map(|&object| boxes[object]),
+                ),
+            };
+            self.nodes[index].min = min;
+            self.nodes[index].max = max;
+        }
+        true
+    }
+
+    /// Call `visit` with the index of every object `ray` may hit within
+    /// [t_min, t_max], unbounded ones first, then the rest near to far
+    ///
+    /// `visit` returns the distance of the closest hit found so far, and boxes beyond
+    /// it are skipped.
+    pub fn traverse(&self, ray: &Ray, t_min: f64, t_max: f64, mut visit: impl FnMut(usize) -> f64) {
+        let mut t_max = t_max;
+        for &index in &self.unbounded {
+            t_max = visit(index);
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = [0usize; MAX_DEPTH];
+        let mut depth = 1;
+        while depth > 0 {
+            depth -= 1;
+            let index = stack[depth];
+            let node = &self.nodes[index];
+            if !ray_hits_aabb(ray, &node.min, &node.max, t_min, t_max) {
+                continue;
+            }
+            if node.count > 0 {
+                for &object in &self.order[node.start..node.start + node.count] {
+                    t_max = visit(object);
+                }
+                continue;
+            }
+            // The first child holds the lower half along the split axis
+            let (near, far) = match ray.direction[node.axis] < 0.0 {
+                true => (node.start, index + 1),
+                false => (index + 1, node.start),
+            };
+            stack[depth] = far;
+            stack[depth + 1] = near;
+            depth += 2;
+        }
+    }
+}
+
+/// Box of `object`, padded a little so rays grazing flat objects still reach them, or
+/// None if it has no usable box
+fn object_box(object: &dyn Intersectable) -> Option<(Point, Point)> {
+    let (min, max) = object.bounding_box()?;
+    let finite = min.iter().chain(max.iter()).all(|value| value.is_finite());
+    if !finite || (0..3).any(|axis| min[axis] > max[axis]) {
+        return None;
+    }
+    let pad = Vec3::repeat(1e-9 * (max - min).norm().max(1.0));
+    Some((min - pad, max + pad))
+}
+
+/// Box around all of `boxes`
+fn enclose(boxes: impl IntoIterator<Item = (Point, Point)>) -> (Point, Point) {
+    let infinity = Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    boxes
+        .into_iter()
+        .fold((infinity, -infinity), |(min, max), (low, high)| {
+            (min.inf(&low), max.sup(&high))
+        })
+}
+
+/// Add the node over `items`, which start at `start` in the tree's order, and the
+/// nodes below it, splitting at the median center along the axis the centers
+/// spread most
+fn build_node(
+    nodes: &mut Vec<Node>,
+    boxes: &[Option<(Point, Point)>],
+    items: &mut [usize],
+    start: usize,
+) {
+    let item_box = |index: usize| boxes[index].expect("tree items have boxes");
+    let (min, max) = enclose(items.iter().map(|&index| item_box(index)));
+    let index = nodes.len();
+    nodes.push(Node {
+        min,
+        max,
+        start,
+        count: items.len(),
+        axis: 0,
+    });
+    if items.len() <= LEAF_SIZE {
+        return;
+    }
+
+    let center = |index: usize| {
+        let (low, high) = item_box(index);
+        nalgebra::center(&low, &high)
+    };
+    let (low, high) = enclose(items.iter().map(|&index| (center(index), center(index))));
+    let axis = (high - low).imax();
+    let middle = items.len() / 2;
+    items.select_nth_unstable_by(middle, |&a, &b| center(a)[axis].total_cmp(&center(b)[axis]));
+    let (first, second) = items.split_at_mut(middle);
+    build_node(nodes, boxes, first, start);
+    let second_index = nodes.len();
+    build_node(nodes, boxes, second, start + middle);
+    nodes[index].start = second_index;
+    nodes[index].count = 0;
+    nodes[index].axis = axis;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ray::{Plane, Sphere, World};
+    use crate::core::Color;
+
+    /// Spheres along a helix, `offset` along x, and an infinite floor
+    fn helix(offset: f64) -> World {
+        let mut world = World::new();
+        for index in 0..40 {
+            let angle = index as f64 * 0.5;
+            world.add(Box::new(Sphere {
+                center: Point::new(offset + 3.0 * angle.cos(), 3.0 * angle.sin(), angle),
+                radius: 0.6,
+                material_color: Color::new(1.0, 1.0, 1.0),
+                material_index: index,
+            }));
+        }
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let floor = Plane::new(Point::new(0.0, 0.0, -1.0), normal, Color::zeros(), 40);
+        world.add(Box::new(floor));
+        world
+    }
+
+    /// Object and distance each of a fan of rays hits in `world`
+    fn hits(world: &World) -> Vec<Option<(usize, f64)>> {
+        let origin = Point::new(-12.0, -15.0, 25.0);
+        (0..400)
+            .map(|ray| {
+                let target = Point::new((ray % 20) as f64 - 10.0, (ray / 20) as f64 - 10.0, 8.0);
+                let ray = Ray::new(origin, target - origin);
+                (world.hit(&ray, 0.001, f64::INFINITY)).map(|hit| (hit.material_index, hit.t))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bvh_finds_the_same_hits() {
+        let mut world = helix(0.0);
+        let linear = hits(&world);
+        assert!(
+            linear
+                .iter()
+                .filter(|hit| hit.is_some_and(|(index, _)| index < 40))
+                .count()
+                > 40
+        );
+        assert!(linear
+            .iter()
+            .any(|hit| hit.is_some_and(|(index, _)| index == 40)));
+
+        world.build_bvh();
+        let bvh = world.bvh().unwrap();
+        assert_eq!(bvh.object_count(), 41);
+        assert_eq!(bvh.unbounded, [40]);
+        assert!(bvh.nodes.len() > 1);
+        assert_eq!(hits(&world), linear);
+
+        // A tree built before more objects were added is left unused
+        world.add(Box::new(Sphere {
+            center: Point::new(0.0, 0.0, 4.0),
+            radius: 5.0,
+            material_color: Color::new(1.0, 1.0, 1.0),
+            material_index: 41,
+        }));
+        assert!(world.bvh().is_none());
+        assert_ne!(hits(&world), linear);
+    }
+
+    #[test]
+    fn test_refit_follows_moved_objects() {
+        let mut world = helix(0.0);
+        world.build_bvh();
+        let bvh = world.bvh().unwrap().clone();
+
+        // Moved objects refit the old tree to the hits of a linear search
+        let mut moved = helix(4.0);
+        let linear = hits(&moved);
+        assert!(moved.refit_bvh(&bvh));
+        let refit = moved.bvh().unwrap();
+        assert_eq!(refit.order, bvh.order);
+        assert_ne!(refit.nodes[0].min, bvh.nodes[0].min);
+        assert_eq!(hits(&moved), linear);
+        assert_ne!(linear, hits(&world));
+
+        // Objects that no longer fit it need a new tree
+        let mut more = helix(0.0);
+        more.add(Box::new(Plane::new(
+            Point::origin(),
+            Vec3::new(1.0, 0.0, 0.0),
+            Color::zeros(),
+            41,
+        )));
+        assert!(!more.refit_bvh(&bvh));
+        assert!(more.bvh().is_none());
+        let mut unbounded = helix(0.0);
+        unbounded.objects[0] = Box::new(Plane::new(
+            Point::origin(),
+            Vec3::new(1.0, 0.0, 0.0),
+            Color::zeros(),
+            0,
+        ));
+        assert!(!unbounded.refit_bvh(&bvh));
+    }
+}
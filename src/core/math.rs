@@ -0,0 +1,63 @@
+//! Float functions missing from `core`
+//!
+//! With `std` these forward to the inherent `f64` methods, so std builds produce
+//! exactly the same numbers as before; without it they come from `libm`.
+
+macro_rules! unary {
+    ($($name:ident),*) => {
+        $(
+            #[inline]
+            pub fn $name(x: f64) -> f64 {
+                #[cfg(feature = "std")]
+                {
+                    x.$name()
+                }
+                #[cfg(not(feature = "std"))]
+                {
+                    libm::$name(x)
+                }
+            }
+        )*
+    };
+}
+
+unary!(sqrt, exp, sin, cos, floor, asin, round, log2);
+
+/// `x` raised to the floating point power `y`
+#[inline]
+pub fn powf(x: f64, y: f64) -> f64 {
+    #[cfg(feature = "std")]
+    {
+        x.powf(y)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        libm::pow(x, y)
+    }
+}
+
+/// Angle of the point (x, y) from the positive x axis, in -π..=π
+#[inline]
+pub fn atan2(y: f64, x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    {
+        y.atan2(x)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        libm::atan2(y, x)
+    }
+}
+
+/// Fractional part of `x`, with the sign of `x`
+#[inline]
+pub fn fract(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    {
+        x.fract()
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        x - libm::trunc(x)
+    }
+}
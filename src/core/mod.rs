@@ -0,0 +1,24 @@
+//! Ray, intersection and shading math that builds without std
+//!
+//! Everything under `core` depends only on `core`, `alloc` and `nalgebra`, so it
+//! compiles with `--no-default-features --features libm` for embedded and lean WASM
+//! targets. Scene parsing, meshes with k-d trees, image output and the parallel
+//! renderer live in the std-only modules and build on these types.
+
+pub mod bvh;
+pub mod math;
+pub mod ray;
+pub mod shading;
+pub mod tonemap;
+pub mod volume;
+
+use nalgebra::{Point3, Vector3};
+
+/// Color representation as RGB values (0.0-1.0)
+pub type Color = Vector3<f64>;
+
+/// 3D point
+pub type Point = Point3<f64>;
+
+/// 3D vector
+pub type Vec3 = Vector3<f64>;
@@ -0,0 +1,1516 @@
+use super::bvh::Bvh;
+use super::math;
+use super::volume::Volume;
+use super::{Color, Point, Vec3};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use nalgebra::Unit;
+
+/// A ray in 3D space
+#[derive(Debug, Clone)]
+pub struct Ray {
+    pub origin: Point,
+    pub direction: Unit<Vec3>,
+    pub differentials: Option<RayDifferentials>, // spread to neighboring pixels, for texture filtering
+    pub time: f64, // moment within the shutter, 0 to 1, that moving objects are placed at
+}
+
+impl Ray {
+    pub fn new(origin: Point, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: Unit::new_normalize(direction),
+            differentials: None,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Get a point along the ray at parameter t
+    pub fn at(&self, t: f64) -> Point {
+        self.origin + t * self.direction.as_ref()
+    }
+}
+
+/// Change in a ray's origin and (unit) direction from one pixel to the next in x and y
+///
+/// Carried from the camera through reflections, these give the size of a pixel's
+/// footprint where the ray lands, so textures can be averaged over it instead of
+/// point sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayDifferentials {
+    pub origin_dx: Vec3,
+    pub origin_dy: Vec3,
+    pub direction_dx: Vec3,
+    pub direction_dy: Vec3,
+}
+
+impl RayDifferentials {
+    /// Offsets from the hit to where the neighboring pixels' rays meet the plane
+    /// tangent to the surface there, in x and y
+    pub fn surface_offsets(&self, ray: &Ray, hit: &HitRecord) -> (Vec3, Vec3) {
+        let normal = hit.normal.as_ref();
+        let facing = ray.direction.dot(normal);
+        let offset = |origin_d: Vec3, direction_d: Vec3| {
+            let spread = origin_d + hit.t * direction_d;
+            if facing.abs() < 1e-12 {
+                return spread;
+            }
+            // Slide along the ray until back on the tangent plane
+            spread - spread.dot(normal) / facing * ray.direction.as_ref()
+        };
+        (
+            offset(self.origin_dx, self.direction_dx),
+            offset(self.origin_dy, self.direction_dy),
+        )
+    }
+
+    /// Differentials of the ray mirrored at `hit`, treating the surface as flat there
+    pub fn reflect(&self, ray: &Ray, hit: &HitRecord) -> Self {
+        let normal = hit.normal.as_ref();
+        let (origin_dx, origin_dy) = self.surface_offsets(ray, hit);
+        let mirror = |direction_d: Vec3| direction_d - 2.0 * direction_d.dot(normal) * normal;
+        Self {
+            origin_dx,
+            origin_dy,
+            direction_dx: mirror(self.direction_dx),
+            direction_dy: mirror(self.direction_dy),
+        }
+    }
+
+    /// Differentials of the ray refracted at `hit` with index ratio `eta`, bending the
+    /// neighboring rays by the same ratio as the ray itself
+    pub fn refract(&self, ray: &Ray, hit: &HitRecord, eta: f64) -> Self {
+        let (origin_dx, origin_dy) = self.surface_offsets(ray, hit);
+        Self {
+            origin_dx,
+            origin_dy,
+            direction_dx: self.direction_dx * eta,
+            direction_dy: self.direction_dy * eta,
+        }
+    }
+
+    /// Widths in texture (u, v) units of the pixel footprint at `hit`, or None if
+    /// the hit has no texture gradients
+    pub fn texture_footprint(&self, ray: &Ray, hit: &HitRecord) -> Option<(f64, f64)> {
+        let (gradient_u, gradient_v) = hit.texture_gradients?;
+        let (dx, dy) = self.surface_offsets(ray, hit);
+        let width = |gradient: Vec3| gradient.dot(&dx).abs().max(gradient.dot(&dy).abs());
+        Some((width(gradient_u), width(gradient_v)))
+    }
+}
+
+/// Result of a ray-object intersection
+#[derive(Debug, Clone)]
+pub struct HitRecord {
+    pub point: Point,
+    pub normal: Unit<Vec3>,
+    pub t: f64,
+    pub front_face: bool,
+    pub material_color: Color,
+    pub material_index: usize,
+    pub texture_coords: Option<(f64, f64)>, // u, v coordinates for texturing
+    pub texture_gradients: Option<(Vec3, Vec3)>, // change in u and v per unit of world-space movement
+    pub texture_footprint: Option<(f64, f64)>,   // u and v widths of the pixel seen here, if known
+    pub face_material: Option<usize>, // material of the mesh face hit, in place of the object's
+    pub time: f64,                    // time of the ray that found the hit
+}
+
+impl HitRecord {
+    pub fn new(
+        point: Point,
+        outward_normal: Vec3,
+        t: f64,
+        ray: &Ray,
+        material_color: Color,
+        material_index: usize,
+    ) -> Self {
+        let front_face = ray.direction.dot(&outward_normal) < 0.0;
+        let normal = if front_face {
+            Unit::new_normalize(outward_normal)
+        } else {
+            Unit::new_normalize(-outward_normal)
+        };
+
+        Self {
+            point,
+            normal,
+            t,
+            front_face,
+            material_color,
+            material_index,
+            texture_coords: None,
+            texture_gradients: None,
+            texture_footprint: None,
+            face_material: None,
+            time: ray.time,
+        }
+    }
+
+    /// Key of the hit's material in the renderer's material table
+    ///
+    /// This is the object's own index unless the mesh face hit has a material of its
+    /// own; `material_index` always identifies the object.
+    pub fn material_key(&self) -> usize {
+        self.face_material.unwrap_or(self.material_index)
+    }
+}
+
+/// Trait for objects that can be intersected by rays
+pub trait Intersectable {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn material_index(&self) -> usize;
+
+    /// World-space box around every hit the object can give, or None when it is
+    /// unbounded, like an infinite plane, so the world tests it against every ray
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        None
+    }
+}
+
+/// Sphere primitive
+pub struct Sphere {
+    pub center: Point,
+    pub radius: f64,
+    pub material_color: Color,
+    pub material_index: usize,
+}
+
+impl Sphere {
+    /// Get the bounding box of the sphere
+    pub fn bounds(&self) -> (Point, Point) {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        (self.center - r, self.center + r)
+    }
+}
+
+impl Intersectable for Sphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let root = sphere_root(
+            self.center,
+            self.radius,
+            ray.origin,
+            ray.direction.as_ref(),
+            t_min,
+            t_max,
+        )?;
+        let point = ray.at(root);
+        let outward_normal = (point - self.center) / self.radius;
+
+        let mut hit_record = HitRecord::new(
+            point,
+            outward_normal,
+            root,
+            ray,
+            self.material_color,
+            self.material_index,
+        );
+        hit_record.texture_coords = Some(sphere_uv(&outward_normal, self.radius));
+        hit_record.texture_gradients = Some(sphere_uv_gradients(&outward_normal, self.radius));
+        Some(hit_record)
+    }
+
+    fn material_index(&self) -> usize {
+        self.material_index
+    }
+
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        Some(self.bounds())
+    }
+}
+
+/// Nearest `t` in `[t_min, t_max]` where `origin + t * direction` meets the sphere
+fn sphere_root(
+    center: Point,
+    radius: f64,
+    origin: Point,
+    direction: &Vec3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<f64> {
+    let oc = origin - center;
+    let a = direction.dot(direction);
+    let half_b = oc.dot(direction);
+    let c = oc.dot(&oc) - radius * radius;
+
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let sqrtd = math::sqrt(discriminant);
+    let mut root = (-half_b - sqrtd) / a;
+    if root < t_min || t_max < root {
+        root = (-half_b + sqrtd) / a;
+        if root < t_min || t_max < root {
+            return None;
+        }
+    }
+    Some(root)
+}
+
+/// Texture coordinates on a sphere, from the unit offset of the hit from its center
+///
+/// v is the distance north of the equator along the surface (Z-up). u runs east around
+/// the sphere, scaled to an even number of units per turn so checkerboards meet at the seam.
+fn sphere_uv(unit_offset: &Vec3, radius: f64) -> (f64, f64) {
+    let longitude = math::atan2(unit_offset.y, unit_offset.x);
+    let latitude = math::asin(unit_offset.z.clamp(-1.0, 1.0));
+    let u = (longitude / core::f64::consts::TAU + 0.5) * sphere_squares_around(radius);
+    (u, latitude * radius)
+}
+
+/// Whole squares around the equator, so the texture closes up seamlessly
+fn sphere_squares_around(radius: f64) -> f64 {
+    (2.0 * math::round(core::f64::consts::PI * radius)).max(2.0)
+}
+
+/// Gradients of `sphere_uv` for movement on the sphere: east for u, north for v
+fn sphere_uv_gradients(unit_offset: &Vec3, radius: f64) -> (Vec3, Vec3) {
+    let (x, y, z) = (unit_offset.x, unit_offset.y, unit_offset.z);
+    // Distance from the axis; u lines bunch up toward the poles
+    let axis_distance = math::sqrt(x * x + y * y).max(1e-9);
+    let east = Vec3::new(-y, x, 0.0) / axis_distance;
+    let north = Vec3::new(
+        -z * x / axis_distance,
+        -z * y / axis_distance,
+        axis_distance,
+    );
+    let radians_per_u = core::f64::consts::TAU / sphere_squares_around(radius);
+    (east / (radius * axis_distance * radians_per_u), north)
+}
+
+/// Sphere under an affine transform: an ellipsoid, possibly sheared
+///
+/// Rays are moved into the sphere's own space rather than the sphere into world space,
+/// so nonuniform scales and shears give the exact shape.
+pub struct Ellipsoid {
+    pub center: Point, // sphere center before the transform
+    pub radius: f64,
+    pub object_to_world: nalgebra::Matrix4<f64>,
+    pub world_to_object: nalgebra::Matrix4<f64>,
+    pub material_color: Color,
+    pub material_index: usize,
+}
+
+impl Ellipsoid {
+    /// Sphere with `transform` applied; `None` if the transform can't be inverted
+    pub fn new(
+        center: Point,
+        radius: f64,
+        transform: nalgebra::Matrix4<f64>,
+        material_color: Color,
+        material_index: usize,
+    ) -> Option<Self> {
+        Some(Self {
+            center,
+            radius,
+            object_to_world: transform,
+            world_to_object: transform.try_inverse()?,
+            material_color,
+            material_index,
+        })
+    }
+
+    /// Get the tight axis-aligned bounding box of the ellipsoid in world space
+    pub fn bounds(&self) -> (Point, Point) {
+        let center = self.object_to_world.transform_point(&self.center);
+        let linear = self.object_to_world.fixed_view::<3, 3>(0, 0);
+        // Extent along each world axis is the radius times the length of that row
+        let half_extent = Vec3::from_fn(|axis, _| self.radius * linear.row(axis).norm());
+        (center - half_extent, center + half_extent)
+    }
+}
+
+impl Intersectable for Ellipsoid {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // The object-space direction stays unnormalized so t is the same along both rays
+        let origin = self.world_to_object.transform_point(&ray.origin);
+        let direction = self
+            .world_to_object
+            .transform_vector(ray.direction.as_ref());
+        let root = sphere_root(self.center, self.radius, origin, &direction, t_min, t_max)?;
+
+        // Normals take the transpose of the world-to-object part
+        let local_normal = (origin + direction * root - self.center) / self.radius;
+        let outward_normal =
+            self.world_to_object.fixed_view::<3, 3>(0, 0).transpose() * local_normal;
+
+        // Textures follow the sphere through the transform
+        let mut hit_record = HitRecord::new(
+            ray.at(root),
+            outward_normal,
+            root,
+            ray,
+            self.material_color,
+            self.material_index,
+        );
+        hit_record.texture_coords = Some(sphere_uv(&local_normal, self.radius));
+        // Gradients take the transpose of the world-to-object part, like normals
+        let (gradient_u, gradient_v) = sphere_uv_gradients(&local_normal, self.radius);
+        let transpose = self.world_to_object.fixed_view::<3, 3>(0, 0).transpose();
+        hit_record.texture_gradients = Some((transpose * gradient_u, transpose * gradient_v));
+        Some(hit_record)
+    }
+
+    fn material_index(&self) -> usize {
+        self.material_index
+    }
+
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        Some(self.bounds())
+    }
+}
+
+/// Plane primitive, infinite unless given a size
+pub struct Plane {
+    pub point: Point,
+    pub normal: Unit<Vec3>,
+    pub u_axis: Vec3,    // texture u is the dot product with the offset from `point`
+    pub v_axis: Vec3,    // texture v likewise
+    pub half_width: f64, // largest |v| on the plane; infinite if unbounded
+    pub half_height: f64, // largest |u| on the plane; infinite if unbounded
+    pub material_color: Color,
+    pub material_index: usize,
+}
+
+impl Plane {
+    /// Infinite plane, with texture axes picked from the normal
+    pub fn new(point: Point, normal: Vec3, material_color: Color, material_index: usize) -> Self {
+        let normal = Unit::new_normalize(normal);
+        let u_axis = if normal.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let u_axis = u_axis.cross(&normal).normalize();
+        let v_axis = normal.cross(&u_axis).normalize();
+        Self {
+            point,
+            normal,
+            u_axis,
+            v_axis,
+            half_width: f64::INFINITY,
+            half_height: f64::INFINITY,
+            material_color,
+            material_index,
+        }
+    }
+
+    /// The plane `new` gives, moved by `transform`
+    ///
+    /// Texture axes follow the transform, so turning the plane turns its texture and
+    /// scaling it scales the texture and size. `None` if the transform can't be inverted.
+    pub fn new_with_transform(
+        point: Point,
+        normal: Vec3,
+        transform: &nalgebra::Matrix4<f64>,
+        material_color: Color,
+        material_index: usize,
+    ) -> Option<Self> {
+        let plane = Self::new(point, normal, material_color, material_index);
+        // Covectors such as the normal and texture axes take the inverse transpose
+        let inverse_transpose = transform
+            .fixed_view::<3, 3>(0, 0)
+            .try_inverse()?
+            .transpose();
+        Some(Self {
+            point: transform.transform_point(&point),
+            normal: Unit::new_normalize(inverse_transpose * plane.normal.into_inner()),
+            u_axis: inverse_transpose * plane.u_axis,
+            v_axis: inverse_transpose * plane.v_axis,
+            ..plane
+        })
+    }
+
+    /// Limit the plane to a rectangle centered on `point`, `width` along its v axis and
+    /// `height` along its u axis
+    pub fn with_size(mut self, width: Option<f64>, height: Option<f64>) -> Self {
+        self.half_width = width.map_or(f64::INFINITY, |width| width / 2.0);
+        self.half_height = height.map_or(f64::INFINITY, |height| height / 2.0);
+        self
+    }
+
+    /// Get the bounding box of a plane limited in both directions
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        if !(self.half_width.is_finite() && self.half_height.is_finite()) {
+            return None;
+        }
+        // Edge directions are the in-plane offsets that move u or v alone by one
+        let axes = nalgebra::Matrix3::from_rows(&[
+            self.u_axis.transpose(),
+            self.v_axis.transpose(),
+            self.normal.transpose(),
+        ]);
+        let edges = axes.try_inverse()?;
+        let along_u = edges.column(0) * self.half_height;
+        let along_v = edges.column(1) * self.half_width;
+
+        let mut min = self.point;
+        let mut max = self.point;
+        for corner in [
+            self.point + along_u + along_v,
+            self.point + along_u - along_v,
+            self.point - along_u + along_v,
+            self.point - along_u - along_v,
+        ] {
+            min.coords = min.coords.inf(&corner.coords);
+            max.coords = max.coords.sup(&corner.coords);
+        }
+        Some((min, max))
+    }
+}
+
+impl Intersectable for Plane {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = self.normal.dot(&ray.direction);
+
+        // Ray is parallel to plane
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.point - ray.origin).dot(&self.normal) / denom;
+
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let relative_pos = point - self.point;
+        let u = relative_pos.dot(&self.u_axis);
+        let v = relative_pos.dot(&self.v_axis);
+        if u.abs() > self.half_height || v.abs() > self.half_width {
+            return None;
+        }
+
+        let mut hit_record = HitRecord::new(
+            point,
+            *self.normal.as_ref(),
+            t,
+            ray,
+            self.material_color,
+            self.material_index,
+        );
+        hit_record.texture_coords = Some((u, v));
+        hit_record.texture_gradients = Some((self.u_axis, self.v_axis));
+
+        Some(hit_record)
+    }
+
+    fn material_index(&self) -> usize {
+        self.material_index
+    }
+
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        self.bounds()
+    }
+}
+
+/// Flat circular disc primitive, visible from both sides
+pub struct Disc {
+    pub center: Point,
+    pub normal: Unit<Vec3>,
+    pub radius: f64,
+    pub material_color: Color,
+    pub material_index: usize,
+}
+
+impl Disc {
+    /// Get the bounding box of the disc
+    pub fn bounds(&self) -> (Point, Point) {
+        // Along each axis the rim extends radius * sin(angle between axis and normal)
+        let extent = self
+            .normal
+            .map(|n| self.radius * math::sqrt((1.0 - n * n).max(0.0)));
+        (self.center - extent, self.center + extent)
+    }
+}
+
+impl Intersectable for Disc {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = self.normal.dot(&ray.direction);
+
+        // Ray is parallel to the disc
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.center - ray.origin).dot(&self.normal) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let point = ray.at(t);
+        let relative_pos = point - self.center;
+        if relative_pos.magnitude_squared() > self.radius * self.radius {
+            return None;
+        }
+
+        let mut hit_record = HitRecord::new(
+            point,
+            *self.normal.as_ref(),
+            t,
+            ray,
+            self.material_color,
+            self.material_index,
+        );
+
+        // Texture coordinates measured from the center, oriented like a plane's
+        let u_axis = if self.normal.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let u_axis = Unit::new_normalize(u_axis.cross(&self.normal));
+        let v_axis = Unit::new_normalize(self.normal.cross(&u_axis));
+        hit_record.texture_coords = Some((relative_pos.dot(&u_axis), relative_pos.dot(&v_axis)));
+        hit_record.texture_gradients = Some((*u_axis.as_ref(), *v_axis.as_ref()));
+
+        Some(hit_record)
+    }
+
+    fn material_index(&self) -> usize {
+        self.material_index
+    }
+
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        Some(self.bounds())
+    }
+}
+
+/// Polyline drawn as a flat strip that turns to face each ray, for lines and fibers
+///
+/// The strip is as wide at each point as twice its entry in `half_widths`, blending
+/// linearly along each segment; its normal always points back along the ray.
+pub struct Ribbon {
+    pub points: Vec<Point>,
+    pub half_widths: Vec<f64>,
+    pub material_color: Color,
+    pub material_index: usize,
+    bounds: (Point, Point),
+}
+
+impl Ribbon {
+    pub fn new(
+        points: Vec<Point>,
+        half_widths: Vec<f64>,
+        material_color: Color,
+        material_index: usize,
+    ) -> Self {
+        let widest = Vec3::repeat(half_widths.iter().fold(0.0, |a: f64, b| a.max(*b)));
+        let (min, max) = points.iter().fold(
+            (Vec3::repeat(f64::INFINITY), Vec3::repeat(f64::NEG_INFINITY)),
+            |(min, max), point| (min.inf(&point.coords), max.sup(&point.coords)),
+        );
+        Self {
+            points,
+            half_widths,
+            material_color,
+            material_index,
+            bounds: (Point::from(min - widest), Point::from(max + widest)),
+        }
+    }
+
+    /// Get the bounding box of the ribbon
+    pub fn bounds(&self) -> (Point, Point) {
+        self.bounds
+    }
+}
+
+impl Intersectable for Ribbon {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if !ray_hits_aabb(ray, &self.bounds.0, &self.bounds.1, t_min, t_max) {
+            return None;
+        }
+        let direction = ray.direction.as_ref();
+        let mut nearest = None;
+        let mut t_max = t_max;
+        for (i, pair) in self.points.windows(2).enumerate() {
+            // Closest approach of the ray to the segment, whose offset from the ray
+            // lies in the strip where it faces the ray
+            let axis = pair[1] - pair[0];
+            let from_start = ray.origin - pair[0];
+            let along = direction.dot(&axis);
+            let toward = direction.dot(&from_start);
+            let denominator = axis.norm_squared() - along * along;
+            let s = if denominator > 1e-12 * axis.norm_squared() {
+                ((axis.dot(&from_start) - along * toward) / denominator).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let t = s * along - toward;
+            if t <= t_min || t >= t_max {
+                continue;
+            }
+            let half_width =
+                self.half_widths[i] + s * (self.half_widths[i + 1] - self.half_widths[i]);
+            let offset = ray.at(t) - (pair[0] + s * axis);
+            if offset.norm_squared() <= half_width * half_width {
+                t_max = t;
+                nearest = Some(t);
+            }
+        }
+        let t = nearest?;
+        Some(HitRecord::new(
+            ray.at(t),
+            -direction,
+            t,
+            ray,
+            self.material_color,
+            self.material_index,
+        ))
+    }
+
+    fn material_index(&self) -> usize {
+        self.material_index
+    }
+
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        Some(self.bounds())
+    }
+}
+
+/// Oriented box (cube) primitive - supports rotation
+pub struct Cube {
+    pub center: Point,
+    pub half_size: Vec3,
+    pub transform: nalgebra::Matrix4<f64>, // World to local transform
+    pub inverse_transform: nalgebra::Matrix4<f64>, // Local to world transform
+    pub material_color: Color,
+    pub material_index: usize,
+}
+
+impl Cube {
+    pub fn new(center: Point, size: Vec3, material_color: Color, material_index: usize) -> Self {
+        let half_size = size / 2.0;
+        let transform = nalgebra::Matrix4::identity();
+        Self {
+            center,
+            half_size,
+            transform,
+            inverse_transform: transform,
+            material_color,
+            material_index,
+        }
+    }
+
+    /// Create a new oriented cube with a transform matrix
+    pub fn new_with_transform(
+        center: Point,
+        size: Vec3,
+        transform_matrix: nalgebra::Matrix4<f64>,
+        material_color: Color,
+        material_index: usize,
+    ) -> Self {
+        let half_size = size / 2.0;
+        let inverse = transform_matrix.try_inverse().unwrap_or_else(nalgebra::Matrix4::identity);
+        Self {
+            center,
+            half_size,
+            transform: inverse, // Store world-to-local transform
+            inverse_transform: transform_matrix, // Store local-to-world transform
+            material_color,
+            material_index,
+        }
+    }
+
+    /// Get the axis-aligned bounding box of the oriented cube in world space
+    pub fn bounds(&self) -> (Point, Point) {
+        // If no rotation, use simple AABB
+        if self.transform == nalgebra::Matrix4::identity() {
+            return (self.center - self.half_size, self.center + self.half_size);
+        }
+
+        // For oriented cubes, we need to transform all 8 corners and find the AABB
+        let corners = [
+            Point::new(-self.half_size.x, -self.half_size.y, -self.half_size.z),
+            Point::new(-self.half_size.x, -self.half_size.y,  self.half_size.z),
+            Point::new(-self.half_size.x,  self.half_size.y, -self.half_size.z),
+            Point::new(-self.half_size.x,  self.half_size.y,  self.half_size.z),
+            Point::new( self.half_size.x, -self.half_size.y, -self.half_size.z),
+            Point::new( self.half_size.x, -self.half_size.y,  self.half_size.z),
+            Point::new( self.half_size.x,  self.half_size.y, -self.half_size.z),
+            Point::new( self.half_size.x,  self.half_size.y,  self.half_size.z),
+        ];
+
+        // Transform corners to world space and find AABB
+        let mut min = Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+
+        for corner in &corners {
+            // Transform corner to world space: center + rotation * corner
+            let world_corner = self.center + (self.inverse_transform * corner.to_homogeneous()).xyz();
+            min.x = min.x.min(world_corner.x);
+            min.y = min.y.min(world_corner.y);
+            min.z = min.z.min(world_corner.z);
+            max.x = max.x.max(world_corner.x);
+            max.y = max.y.max(world_corner.y);
+            max.z = max.z.max(world_corner.z);
+        }
+
+        (min, max)
+    }
+}
+
+impl Intersectable for Cube {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // Transform ray to cube's local coordinate space
+        let local_origin = Point::from((self.transform * Point::from(ray.origin - self.center).to_homogeneous()).xyz());
+        let local_direction = (self.transform * ray.direction.to_homogeneous()).xyz();
+        
+        // Handle degenerate direction (shouldn't happen with normalized rays, but be safe)
+        if local_direction.magnitude() < 1e-8 {
+            return None;
+        }
+        
+        // Perform intersection against axis-aligned box in local space. The local direction
+        // stays unnormalized so t is the same along the local and world rays.
+        // The exit starts unbounded rather than at t_max, so a ray from inside that
+        // leaves beyond t_max misses instead of stopping at t_max without a face
+        let mut t_min_hit = t_min;
+        let mut t_max_hit = f64::INFINITY;
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        let mut exit_normal = Vec3::new(0.0, 0.0, 0.0);
+        let mut _hit_front = true;
+
+        // Check intersection with each pair of parallel planes (in local space)
+        for axis in 0..3 {
+            let inv_dir = 1.0 / local_direction[axis];
+            let mut t0 = (-self.half_size[axis] - local_origin[axis]) * inv_dir;
+            let mut t1 = (self.half_size[axis] - local_origin[axis]) * inv_dir;
+
+            let mut axis_normal = Vec3::new(0.0, 0.0, 0.0);
+            axis_normal[axis] = if inv_dir < 0.0 { 1.0 } else { -1.0 };
+
+            if inv_dir < 0.0 {
+                core::mem::swap(&mut t0, &mut t1);
+                axis_normal[axis] = -axis_normal[axis];
+            }
+
+            if t0 > t_min_hit {
+                t_min_hit = t0;
+                normal = axis_normal;
+                _hit_front = true;
+            }
+
+            if t1 < t_max_hit {
+                t_max_hit = t1;
+                exit_normal = -axis_normal;
+            }
+
+            if t_min_hit > t_max_hit {
+                return None;
+            }
+        }
+
+        // From inside the box, the ray hits the face where it leaves
+        let (t, normal) = if t_min_hit > t_min {
+            (t_min_hit, normal)
+        } else {
+            (t_max_hit, exit_normal)
+        };
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        // Calculate hit point in local space
+        let local_hit_point = local_origin + local_direction * t;
+        
+        // Transform hit point back to world space
+        let world_hit_point = self.center + (self.inverse_transform * local_hit_point.to_homogeneous()).xyz();
+        
+        // Transform normal back to world space (use inverse transpose for normals)
+        let world_normal = if self.transform == nalgebra::Matrix4::identity() {
+            normal
+        } else {
+            // Normals take the inverse transpose of the local-to-world part, which is the
+            // transpose of the stored world-to-local part
+            let normal_transform = self.transform.fixed_view::<3, 3>(0, 0).transpose();
+            normal_transform * normal
+        };
+        
+        // Texture coordinates across the hit face, from the cube's corner in local units
+        let face = (0..3).find(|&axis| normal[axis] != 0.0).unwrap_or(2);
+        let (a, b) = ((face + 1) % 3, (face + 2) % 3);
+        let corner = local_hit_point.coords + self.half_size;
+
+        let mut hit_record = HitRecord::new(
+            world_hit_point,
+            world_normal,
+            t,
+            ray,
+            self.material_color,
+            self.material_index,
+        );
+        hit_record.texture_coords = Some((corner[a], corner[b]));
+        let to_local = self.transform.fixed_view::<3, 3>(0, 0);
+        hit_record.texture_gradients =
+            Some((to_local.row(a).transpose(), to_local.row(b).transpose()));
+        Some(hit_record)
+    }
+
+    fn material_index(&self) -> usize {
+        self.material_index
+    }
+
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        Some(self.bounds())
+    }
+}
+
+/// Triangle defined by three vertices and a normal
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub vertices: [Point; 3],
+    pub normal: Vec3,
+}
+
+impl Triangle {
+    /// Get the center point of the triangle
+    pub fn center(&self) -> Point {
+        (self.vertices[0] + self.vertices[1].coords + self.vertices[2].coords) / 3.0
+    }
+
+    /// Get the bounding box of the triangle
+    pub fn bounds(&self) -> (Point, Point) {
+        let mut min = self.vertices[0];
+        let mut max = self.vertices[0];
+
+        for vertex in &self.vertices[1..] {
+            min.coords = min.coords.inf(&vertex.coords);
+            max.coords = max.coords.sup(&vertex.coords);
+        }
+
+        (min, max)
+    }
+
+    /// Gradients of the barycentric (u, v) returned by `intersect`, within the triangle's plane
+    pub fn barycentric_gradients(&self) -> (Vec3, Vec3) {
+        let edge1 = self.vertices[1] - self.vertices[0];
+        let edge2 = self.vertices[2] - self.vertices[0];
+        let normal = edge1.cross(&edge2);
+        let across_u = edge2.cross(&normal);
+        let across_v = normal.cross(&edge1);
+        (
+            across_u / edge1.dot(&across_u),
+            across_v / edge2.dot(&across_v),
+        )
+    }
+
+    /// Ray-triangle intersection using Möller-Trumbore algorithm
+    ///
+    /// Returns the ray parameter, the geometric normal (facing the side the vertex
+    /// winding implies) and the barycentric (u, v) coordinates of the hit.
+    pub fn intersect(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, Vec3, (f64, f64))> {
+        let edge1 = self.vertices[1] - self.vertices[0];
+        let edge2 = self.vertices[2] - self.vertices[0];
+        let h = ray.direction.cross(&edge2);
+        let a = edge1.dot(&h);
+
+        if a > -1e-8 && a < 1e-8 {
+            return None; // Ray is parallel to triangle
+        }
+
+        let f = 1.0 / a;
+        let s = ray.origin - self.vertices[0];
+        let u = f * s.dot(&h);
+
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = f * ray.direction.dot(&q);
+
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = f * edge2.dot(&q);
+
+        if t > t_min && t < t_max {
+            // Compute normal from vertex geometry, considering vertex winding order
+            let mut normal = edge1.cross(&edge2);
+
+            // Ensure normal is not zero (degenerate triangle)
+            if normal.magnitude() < 1e-8 {
+                return None;
+            }
+
+            // The sign of 'a' tells us about vertex winding:
+            // - If a > 0: vertices are counter-clockwise, normal points toward ray
+            // - If a < 0: vertices are clockwise, normal points away from ray
+            // We want the normal to point toward the "outside" of the mesh
+            if a < 0.0 {
+                normal = -normal;
+            }
+
+            normal = normal.normalize();
+
+            Some((t, normal, (u, v)))
+        } else {
+            None
+        }
+    }
+
+}
+
+/// Slab test: does `ray` pass through the axis-aligned box within [t_min, t_max]?
+pub fn ray_hits_aabb(
+    ray: &Ray,
+    bounds_min: &Point,
+    bounds_max: &Point,
+    t_min: f64,
+    t_max: f64,
+) -> bool {
+    let mut t_min_bound = t_min;
+    let mut t_max_bound = t_max;
+
+    for axis in 0..3 {
+        let inv_dir = 1.0 / ray.direction[axis];
+        let mut t0 = (bounds_min[axis] - ray.origin[axis]) * inv_dir;
+        let mut t1 = (bounds_max[axis] - ray.origin[axis]) * inv_dir;
+
+        if inv_dir < 0.0 {
+            core::mem::swap(&mut t0, &mut t1);
+        }
+
+        t_min_bound = t_min_bound.max(t0);
+        t_max_bound = t_max_bound.min(t1);
+
+        if t_min_bound > t_max_bound {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Collection of intersectable objects
+#[derive(Default)]
+pub struct World {
+    pub objects: Vec<Box<dyn Intersectable + Send + Sync>>,
+    pub volumes: Vec<Volume>, // smoke and steam, seen through rather than hit
+    pub opacities: Vec<f64>,  // opacity by `HitRecord::material_key`, for shadows; solid if missing
+    pub clip_planes: Vec<(Point, Vec3)>, // point and normal; surfaces on the normal's side are cut away
+    pub clip_exempt: Vec<bool>, // by `HitRecord::material_index`, objects the clip planes leave whole
+    bvh: Option<Bvh>,           // over `objects`, once they are all added
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            volumes: Vec::new(),
+            opacities: Vec::new(),
+            clip_planes: Vec::new(),
+            clip_exempt: Vec::new(),
+            bvh: None,
+        }
+    }
+
+    pub fn add(&mut self, object: Box<dyn Intersectable + Send + Sync>) {
+        self.objects.push(object);
+    }
+
+    /// Build the tree that lets rays skip the objects they can't hit, once every object
+    /// is added; a world without one, or with more or fewer objects since, tests them all
+    pub fn build_bvh(&mut self) {
+        self.bvh = Some(Bvh::new(&self.objects));
+    }
+
+    /// Take `bvh`, the tree of a world whose objects these are after some moved, refit
+    /// around them in place of building one; false if they no longer fit it
+    pub fn refit_bvh(&mut self, bvh: &Bvh) -> bool {
+        let mut bvh = bvh.clone();
+        let fits = bvh.refit(&self.objects);
+        if fits {
+            self.bvh = Some(bvh);
+        }
+        fits
+    }
+
+    /// The world's tree, if it has one for its current objects
+    pub fn bvh(&self) -> Option<&Bvh> {
+        (self.bvh.as_ref()).filter(|bvh| bvh.object_count() == self.objects.len())
+    }
+
+    pub fn add_volume(&mut self, volume: Volume) {
+        self.volumes.push(volume);
+    }
+
+    /// How much of the light reaching the surface at `hit` it blocks, from 0 to 1
+    pub fn opacity(&self, hit: &HitRecord) -> f64 {
+        self.opacities
+            .get(hit.material_key())
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Share of light getting through every volume between `from` and `to`
+    pub fn volume_transmittance(&self, from: &Point, to: &Point) -> f64 {
+        self.volumes
+            .iter()
+            .map(|volume| volume.transmittance(from, to))
+            .product()
+    }
+
+    /// Whether a clip plane cuts away the surface at `hit`
+    pub fn clipped(&self, hit: &HitRecord) -> bool {
+        !self.clip_planes.is_empty()
+            && !self
+                .clip_exempt
+                .get(hit.material_index)
+                .copied()
+                .unwrap_or(false)
+            && (self.clip_planes.iter())
+                .any(|(point, normal)| (hit.point - point).dot(normal) > 0.0)
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest: Option<(usize, HitRecord)> = None;
+        let mut closest_so_far = t_max;
+        let mut test = |index: usize| {
+            let object = &*self.objects[index];
+            if let Some(hit) = self.hit_object(object, ray, t_min, closest_so_far) {
+                // Of hits at the same distance the later object's wins, in any order
+                let nearer = match &closest {
+                    Some((closest_index, closest_hit)) => {
+                        hit.t < closest_hit.t || index > *closest_index
+                    }
+                    None => true,
+                };
+                if nearer {
+                    closest_so_far = hit.t;
+                    closest = Some((index, hit));
+                }
+            }
+            closest_so_far
+        };
+        match self.bvh() {
+            Some(bvh) => bvh.traverse(ray, t_min, t_max, test),
+            None => (0..self.objects.len()).for_each(|index| {
+                test(index);
+            }),
+        }
+
+        let mut closest_hit = closest.map(|(_, hit)| hit);
+        if let (Some(hit), Some(differentials)) = (&mut closest_hit, &ray.differentials) {
+            hit.texture_footprint = differentials.texture_footprint(ray, hit);
+        }
+        closest_hit
+    }
+
+    /// Nearest hit on `object` within [t_min, t_max] that no clip plane cuts away
+    fn hit_object(
+        &self,
+        object: &dyn Intersectable,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<HitRecord> {
+        let mut t_from = t_min;
+        while let Some(hit) = object.hit(ray, t_from, t_max) {
+            if !self.clipped(&hit) {
+                return Some(hit);
+            }
+            // Look again just past the cut-away surface, for the object's far side
+            let next = hit.t + 1e-9 * hit.t.abs().max(1.0);
+            if !next.is_finite() || next <= t_from {
+                break;
+            }
+            t_from = next;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sphere_bounds() {
+        let sphere = Sphere {
+            center: Point::new(1.0, 2.0, 3.0),
+            radius: 1.5,
+            material_color: Color::new(1.0, 0.0, 0.0),
+            material_index: 0,
+        };
+
+        let (min, max) = sphere.bounds();
+        assert_eq!(min, Point::new(-0.5, 0.5, 1.5));
+        assert_eq!(max, Point::new(2.5, 3.5, 4.5));
+    }
+
+    #[test]
+    fn test_disc_hit_and_bounds() {
+        let disc = Disc {
+            center: Point::new(0.0, 0.0, 1.0),
+            normal: Unit::new_normalize(Vec3::new(0.0, 0.0, 1.0)),
+            radius: 2.0,
+            material_color: Color::new(1.0, 1.0, 1.0),
+            material_index: 0,
+        };
+
+        // Hit from either side inside the rim, miss outside it
+        let down = Ray::new(Point::new(1.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = disc.hit(&down, 0.001, 1000.0).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-10);
+        assert!((hit.normal.z - 1.0).abs() < 1e-10);
+        let up = Ray::new(Point::new(0.0, 1.5, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!((disc.hit(&up, 0.001, 1000.0).unwrap().normal.z + 1.0).abs() < 1e-10);
+        let outside = Ray::new(Point::new(1.5, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(disc.hit(&outside, 0.001, 1000.0).is_none());
+
+        let (min, max) = disc.bounds();
+        assert_eq!(min, Point::new(-2.0, -2.0, 1.0));
+        assert_eq!(max, Point::new(2.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn test_ribbon_faces_every_ray() {
+        // An L along x then y, 0.4 wide at the corner and narrowing to 0.2 at both ends
+        let ribbon = Ribbon::new(
+            vec![
+                Point::new(-2.0, 0.0, 0.0),
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(0.0, 2.0, 0.0),
+            ],
+            vec![0.1, 0.2, 0.1],
+            Color::new(1.0, 1.0, 1.0),
+            0,
+        );
+        let (min, max) = ribbon.bounds();
+        assert_eq!(min, Point::new(-2.2, -0.2, -0.2));
+        assert_eq!(max, Point::new(0.2, 2.2, 0.2));
+
+        // Seen from above and from the side it is equally wide, facing the ray
+        let down = |x: f64, y: f64| Ray::new(Point::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = ribbon.hit(&down(-1.0, 0.14), 0.001, 1000.0).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-10);
+        assert!((hit.normal.z - 1.0).abs() < 1e-10);
+        assert!(ribbon.hit(&down(-1.0, 0.16), 0.001, 1000.0).is_none());
+        let across = Ray::new(Point::new(-1.0, -5.0, 0.14), Vec3::new(0.0, 1.0, 0.0));
+        let hit = ribbon.hit(&across, 0.001, 1000.0).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-10);
+        assert!((hit.normal.y + 1.0).abs() < 1e-10);
+
+        // The nearer segment is found, and a ray along the line sees its end
+        let along = Ray::new(Point::new(-0.05, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!((ribbon.hit(&along, 0.001, 1000.0).unwrap().t - 5.0).abs() < 1e-10);
+        let end_on = Ray::new(Point::new(-5.0, 0.0, 0.05), Vec3::new(1.0, 0.0, 0.0));
+        assert!((ribbon.hit(&end_on, 0.001, 1000.0).unwrap().t - 3.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_plane_size_and_texture_axes() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let down = |x: f64, y: f64| Ray::new(Point::new(x, y, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // A floor is `width` along X and `height` along Y
+        let floor = Plane::new(Point::origin(), Vec3::new(0.0, 0.0, 1.0), white, 0);
+        assert!(floor.bounds().is_none());
+        let floor = floor.with_size(Some(4.0), Some(2.0));
+        assert!(floor.hit(&down(1.9, 0.9), 0.001, 1000.0).is_some());
+        assert!(floor.hit(&down(2.1, 0.0), 0.001, 1000.0).is_none());
+        assert!(floor.hit(&down(0.0, 1.1), 0.001, 1000.0).is_none());
+        assert_eq!(
+            floor.bounds(),
+            Some((Point::new(-2.0, -1.0, 0.0), Point::new(2.0, 1.0, 0.0)))
+        );
+
+        // Turning and scaling the plane turns and scales its texture and size with it
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let transform =
+            nalgebra::Matrix4::from_euler_angles(0.0, 0.0, core::f64::consts::FRAC_PI_2)
+                * nalgebra::Matrix4::new_scaling(2.0);
+        let turned = Plane::new_with_transform(Point::origin(), up, &transform, white, 0)
+            .unwrap()
+            .with_size(Some(4.0), Some(2.0));
+        let floor_hit = floor.hit(&down(1.0, 0.5), 0.001, 1000.0).unwrap();
+        let (u, v) = floor_hit.texture_coords.unwrap();
+        let turned_hit = turned.hit(&down(-1.0, 2.0), 0.001, 1000.0).unwrap();
+        let (turned_u, turned_v) = turned_hit.texture_coords.unwrap();
+        assert!((turned_u - u).abs() < 1e-10 && (turned_v - v).abs() < 1e-10);
+        let (min, max) = turned.bounds().unwrap();
+        assert!((min - Point::new(-2.0, -4.0, 0.0)).norm() < 1e-10);
+        assert!((max - Point::new(2.0, 4.0, 0.0)).norm() < 1e-10);
+    }
+
+    #[test]
+    fn test_sphere_and_cube_texture_coords() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let toward = |from: Vec3| Ray::new(Point::from(from), -from);
+
+        // On a sphere, v is the distance north of the equator along the surface and
+        // u turns an even number of units around, meeting itself at the seam
+        let sphere = Sphere {
+            center: Point::origin(),
+            radius: 2.0,
+            material_color: white,
+            material_index: 0,
+        };
+        let uv = |from: Vec3| {
+            sphere
+                .hit(&toward(from), 0.001, 100.0)
+                .unwrap()
+                .texture_coords
+        };
+        let (_, v) = uv(Vec3::new(0.0, 0.0, 10.0)).unwrap();
+        assert!((v - core::f64::consts::PI).abs() < 1e-9);
+        let (east, equator) = uv(Vec3::new(10.0, 0.0, 0.0)).unwrap();
+        assert!((east - 6.0).abs() < 1e-9 && equator.abs() < 1e-9);
+        let (west, _) = uv(Vec3::new(-10.0, -1e-9, 0.0)).unwrap();
+        assert!(west.abs() < 1e-6);
+
+        // Cube faces are measured from the corner, so whole squares meet at the edges
+        let cube = Cube::new(
+            Point::new(5.0, 0.0, 0.0),
+            Vec3::new(2.0, 2.0, 2.0),
+            white,
+            0,
+        );
+        let down = Ray::new(Point::new(5.5, 0.25, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let (u, v) = cube
+            .hit(&down, 0.001, 100.0)
+            .unwrap()
+            .texture_coords
+            .unwrap();
+        assert!((u - 1.5).abs() < 1e-9 && (v - 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ellipsoid_hit_and_bounds() {
+        // Unit sphere scaled to semi-axes 1, 2 and 3, then moved up 5
+        let transform = nalgebra::Matrix4::new_translation(&Vec3::new(0.0, 0.0, 5.0))
+            * nalgebra::Matrix4::new_nonuniform_scaling(&Vec3::new(1.0, 2.0, 3.0));
+        let white = Color::new(1.0, 1.0, 1.0);
+        let ellipsoid = Ellipsoid::new(Point::origin(), 1.0, transform, white, 0).unwrap();
+
+        // t stays in world units along every axis
+        let down = Ray::new(Point::new(0.0, 0.0, 20.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = ellipsoid.hit(&down, 0.001, 1000.0).unwrap();
+        assert!((hit.t - 12.0).abs() < 1e-10);
+        assert!((hit.normal.z - 1.0).abs() < 1e-10);
+        let side = Ray::new(Point::new(0.0, -10.0, 5.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!((ellipsoid.hit(&side, 0.001, 1000.0).unwrap().t - 8.0).abs() < 1e-10);
+
+        // Off-axis normals follow the stretched surface: x^2 + y^2/4 = 1 has normal (x, y/4)
+        let across = Ray::new(Point::new(5.0, 1.0, 5.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = ellipsoid.hit(&across, 0.001, 1000.0).unwrap();
+        let x = 0.75_f64.sqrt();
+        assert!((hit.point.x - x).abs() < 1e-10);
+        let expected = Vec3::new(x, 0.25, 0.0).normalize();
+        assert!((hit.normal.into_inner() - expected).norm() < 1e-10);
+        let outside = Ray::new(Point::new(5.0, 2.5, 5.0), Vec3::new(-1.0, 0.0, 0.0));
+        assert!(ellipsoid.hit(&outside, 0.001, 1000.0).is_none());
+
+        let (min, max) = ellipsoid.bounds();
+        assert!((min - Point::new(-1.0, -2.0, 2.0)).norm() < 1e-10);
+        assert!((max - Point::new(1.0, 2.0, 8.0)).norm() < 1e-10);
+
+        // Shear leans the shape, and the bounds follow it
+        let mut shear = nalgebra::Matrix4::identity();
+        shear[(0, 2)] = 1.0; // x += z
+        let sheared = Ellipsoid::new(Point::origin(), 1.0, shear, white, 0).unwrap();
+        let (min, max) = sheared.bounds();
+        assert!((max.x - 2.0_f64.sqrt()).abs() < 1e-10 && (min.x + max.x).abs() < 1e-10);
+        let high = Ray::new(Point::new(0.9, -5.0, 0.9), Vec3::new(0.0, 1.0, 0.0));
+        assert!(sheared.hit(&high, 0.001, 1000.0).is_some());
+        let low = Ray::new(Point::new(0.9, -5.0, -0.9), Vec3::new(0.0, 1.0, 0.0));
+        assert!(sheared.hit(&low, 0.001, 1000.0).is_none());
+
+        let flat = nalgebra::Matrix4::new_nonuniform_scaling(&Vec3::new(1.0, 0.0, 1.0));
+        assert!(Ellipsoid::new(Point::origin(), 1.0, flat, white, 0).is_none());
+    }
+
+    #[test]
+    fn test_cube_bounds() {
+        let cube = Cube::new(
+            Point::new(1.0, 2.0, 3.0),
+            Vec3::new(2.0, 4.0, 6.0),
+            Color::new(0.0, 1.0, 0.0),
+            0,
+        );
+
+        let (min, max) = cube.bounds();
+        assert_eq!(min, Point::new(0.0, 0.0, 0.0));
+        assert_eq!(max, Point::new(2.0, 4.0, 6.0));
+
+        // From inside, the ray hits the face it leaves through, seen from its back
+        let up = Ray::new(Point::new(1.0, 2.0, 3.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = cube.hit(&up, 0.001, 1000.0).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-10);
+        assert!(!hit.front_face);
+        assert!((hit.normal.z + 1.0).abs() < 1e-10);
+        assert!(cube.hit(&up, 0.001, 2.0).is_none());
+    }
+
+    #[test]
+    fn test_cube_rotation_z() {
+        use nalgebra::Matrix4;
+        
+        // Create a 45-degree rotation around Z-axis
+        let rotation_matrix = Matrix4::from_euler_angles(0.0, 0.0, 45.0_f64.to_radians());
+        
+        let cube = Cube::new_with_transform(
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 2.0, 2.0),
+            rotation_matrix,
+            Color::new(1.0, 0.0, 0.0),
+            0,
+        );
+
+        // Test ray intersection from above should still work
+        let ray = Ray::new(Point::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = cube.hit(&ray, 0.001, 1000.0);
+        
+        assert!(hit.is_some(), "Ray should intersect rotated cube");
+        
+        let hit_record = hit.unwrap();
+        assert!((hit_record.point.z - 1.0).abs() < 1e-10, "Hit should be at z=1 (top face)");
+        assert!(hit_record.point.x.abs() < 1e-10, "Hit x should be near 0");
+        assert!(hit_record.point.y.abs() < 1e-10, "Hit y should be near 0");
+
+        // Side faces turn with the cube
+        let ray = Ray::new(Point::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit_record = cube.hit(&ray, 0.001, 1000.0).unwrap();
+        let expected = Vec3::new(1.0, 1.0, 0.0).normalize();
+        assert!((hit_record.normal.into_inner() - expected).norm() < 1e-10, "Normal should follow the rotation");
+    }
+
+    #[test]
+    fn test_cube_translation_transform() {
+        use nalgebra::Matrix4;
+
+        // A translation in the transform moves the cube away from its center
+        let translation = Matrix4::new_translation(&Vec3::new(3.0, 0.0, 0.0));
+        let cube = Cube::new_with_transform(
+            Point::new(0.0, 0.0, 1.0),
+            Vec3::new(2.0, 2.0, 2.0),
+            translation,
+            Color::new(1.0, 0.0, 0.0),
+            0,
+        );
+
+        let ray = Ray::new(Point::new(3.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = cube.hit(&ray, 0.001, 1000.0).expect("Ray should hit the moved cube");
+        assert!((hit_record.point - Point::new(3.0, 0.0, 2.0)).norm() < 1e-10, "Hit should be on the moved top face");
+
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cube.hit(&ray, 0.001, 1000.0).is_none(), "Ray should miss the cube's original position");
+
+        // Scaled cubes report the world distance to the hit
+        let cube = Cube::new_with_transform(
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 2.0, 2.0),
+            Matrix4::new_scaling(0.5),
+            Color::new(1.0, 0.0, 0.0),
+            0,
+        );
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = cube.hit(&ray, 0.001, 1000.0).unwrap();
+        assert!((hit_record.t - 4.5).abs() < 1e-10, "Hit distance should be 4.5, got {}", hit_record.t);
+    }
+
+    #[test]
+    fn test_cube_rotation_bounds() {
+        use nalgebra::Matrix4;
+        
+        // Test that rotating a cube around Z-axis expands its bounding box correctly
+        let rotation_matrix = Matrix4::from_euler_angles(0.0, 0.0, 45.0_f64.to_radians());
+        
+        let cube = Cube::new_with_transform(
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 2.0, 2.0), // 2x2x2 cube
+            rotation_matrix,
+            Color::new(1.0, 0.0, 0.0),
+            0,
+        );
+
+        let (min, max) = cube.bounds();
+        
+        // When a 2x2 square is rotated 45 degrees, its diagonal becomes the new width/height
+        // Diagonal = sqrt(2^2 + 2^2) = sqrt(8) = 2*sqrt(2) ≈ 2.828
+        let expected_half_diagonal = 2.0_f64.sqrt();
+        
+        assert!((min.x - (-expected_half_diagonal)).abs() < 1e-10, "Min X should be expanded");
+        assert!((max.x - expected_half_diagonal).abs() < 1e-10, "Max X should be expanded");
+        assert!((min.y - (-expected_half_diagonal)).abs() < 1e-10, "Min Y should be expanded");
+        assert!((max.y - expected_half_diagonal).abs() < 1e-10, "Max Y should be expanded");
+        
+        // Z bounds should remain unchanged
+        assert!((min.z - (-1.0)).abs() < 1e-10, "Min Z should be -1");
+        assert!((max.z - 1.0).abs() < 1e-10, "Max Z should be 1");
+    }
+
+    #[test]
+    fn test_cube_no_transform_identity() {
+        // Test that cubes without transforms behave identically to before
+        let cube = Cube::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 2.0, 2.0),
+            Color::new(1.0, 0.0, 0.0),
+            0,
+        );
+
+        // Test ray intersection
+        let ray = Ray::new(Point::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = cube.hit(&ray, 0.001, 1000.0);
+        
+        assert!(hit.is_some(), "Ray should intersect unrotated cube");
+        
+        let hit_record = hit.unwrap();
+        assert!((hit_record.point.z - 1.0).abs() < 1e-10, "Hit should be at z=1");
+        
+        // Test bounds
+        let (min, max) = cube.bounds();
+        assert_eq!(min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(max, Point::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_cube_positioning() {
+        // Test that cubes are positioned correctly at non-origin locations
+        let cube = Cube::new(
+            Point::new(5.0, 3.0, 2.0), // Cube center at (5, 3, 2)
+            Vec3::new(2.0, 2.0, 2.0),  // 2x2x2 size
+            Color::new(1.0, 0.0, 0.0),
+            0,
+        );
+
+        // Test ray intersection from above the cube
+        let ray = Ray::new(Point::new(5.0, 3.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = cube.hit(&ray, 0.001, 1000.0);
+        
+        assert!(hit.is_some(), "Ray should intersect cube at correct position");
+        
+        let hit_record = hit.unwrap();
+        // Should hit the top face at z = center.z + half_size.z = 2 + 1 = 3
+        assert!((hit_record.point.z - 3.0).abs() < 1e-10, "Hit should be at z=3 (top face of cube at center z=2)");
+        assert!((hit_record.point.x - 5.0).abs() < 1e-10, "Hit x should be at cube center x=5");
+        assert!((hit_record.point.y - 3.0).abs() < 1e-10, "Hit y should be at cube center y=3");
+        
+        // Test bounds - should be centered around (5, 3, 2)
+        let (min, max) = cube.bounds();
+        assert_eq!(min, Point::new(4.0, 2.0, 1.0)); // center - half_size
+        assert_eq!(max, Point::new(6.0, 4.0, 3.0)); // center + half_size
+    }
+}
+
@@ -0,0 +1,378 @@
+use super::math;
+use super::{Point, Vec3};
+use nalgebra::Unit;
+
+/// Reflect a vector around a normal
+pub fn reflect(incident: &Vec3, normal: &Unit<Vec3>) -> Unit<Vec3> {
+    let reflected = incident - 2.0 * incident.dot(normal) * normal.as_ref();
+    Unit::new_normalize(reflected)
+}
+
+/// Bend a unit vector crossing a surface, or None if it is totally internally reflected
+///
+/// `normal` faces the side `incident` comes from, and `eta` is the ratio of the
+/// refractive indices, the one it leaves over the one it enters.
+pub fn refract(incident: &Unit<Vec3>, normal: &Unit<Vec3>, eta: f64) -> Option<Unit<Vec3>> {
+    let cos_in = -incident.dot(normal);
+    let sin_out_squared = eta * eta * (1.0 - cos_in * cos_in);
+    if sin_out_squared > 1.0 {
+        return None;
+    }
+    let cos_out = math::sqrt(1.0 - sin_out_squared);
+    let refracted = eta * incident.as_ref() + (eta * cos_in - cos_out) * normal.as_ref();
+    Some(Unit::new_normalize(refracted))
+}
+
+/// Diffuse and specular (Phong) strengths for one light direction at a surface point
+///
+/// Both are in 0.0-1.0 and still need scaling by the material coefficients and
+/// the light color and intensity. Specular is zero for surfaces facing away from
+/// the light.
+pub fn phong_strengths(
+    point: &Point,
+    normal: &Unit<Vec3>,
+    light_dir: &Unit<Vec3>,
+    camera_pos: &Point,
+    shininess: f64,
+) -> (f64, f64) {
+    let diffuse_strength = normal.dot(light_dir).max(0.0);
+    let spec_strength = if diffuse_strength > 0.0 {
+        let view_dir = Unit::new_normalize(*camera_pos - *point);
+        let reflect_dir = reflect(&(-light_dir.as_ref()), normal);
+        math::powf(view_dir.dot(&reflect_dir).max(0.0), shininess)
+    } else {
+        0.0
+    };
+    (diffuse_strength, spec_strength)
+}
+
+/// Fraction of the fog color (0.0-1.0) to blend in at `distance`
+///
+/// Linear falloff between `start` and `end`, shaped by an exponential `density`.
+pub fn fog_factor(distance: f64, start: f64, end: f64, density: f64) -> f64 {
+    let linear = if distance <= start {
+        0.0
+    } else if distance >= end {
+        1.0
+    } else {
+        (distance - start) / (end - start)
+    };
+
+    let fog_factor = 1.0 - math::exp(-density * linear);
+    fog_factor.clamp(0.0, 1.0)
+}
+
+/// Henyey-Greenstein phase function, scaled so that isotropic scattering (`g` = 0) is 1
+///
+/// `cos_theta` is the cosine between the light's direction of travel and the scattered
+/// direction; `g` above 0 favors scattering onward, below 0 back towards the light.
+pub fn henyey_greenstein(g: f64, cos_theta: f64) -> f64 {
+    let denominator = (1.0 + g * g - 2.0 * g * cos_theta).max(f64::EPSILON);
+    (1.0 - g * g) / (denominator * math::sqrt(denominator))
+}
+
+/// Map two uniform random numbers in [0, 1) to a cosine-weighted direction on the
+/// hemisphere around `normal`
+pub fn cosine_hemisphere(normal: &Unit<Vec3>, r1: f64, r2: f64) -> Vec3 {
+    let phi = 2.0 * core::f64::consts::PI * r1;
+    let radius = math::sqrt(r2);
+
+    // Build an orthonormal basis around the normal
+    let helper = if normal.x.abs() < 0.9 {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    let tangent = Unit::new_normalize(helper.cross(normal.as_ref()));
+    let bitangent = normal.cross(tangent.as_ref());
+
+    radius * math::cos(phi) * tangent.as_ref()
+        + radius * math::sin(phi) * bitangent
+        + math::sqrt(1.0 - r2) * normal.as_ref()
+}
+
+/// Map two uniform random numbers in [0, 1) into the `index`th of `count` equal-area
+/// cells of the unit square
+///
+/// Cells are laid out in about √count rows, the first rows taking one extra cell
+/// when `count` is not a square, so any sample count covers the square evenly.
+pub fn stratified_square(index: u32, count: u32, r1: f64, r2: f64) -> (f64, f64) {
+    let rows = (math::sqrt(count as f64) as u32).max(1);
+    let (base, extra) = (count / rows, count % rows);
+    let (mut first, mut top) = (0, 0.0);
+    for row in 0..rows {
+        let columns = base + u32::from(row < extra);
+        // Rows are as tall as their share of the cells, keeping every cell's area 1/count
+        let height = columns as f64 / count as f64;
+        if index < first + columns {
+            let column = index - first;
+            return ((column as f64 + r1) / columns as f64, top + r2 * height);
+        }
+        first += columns;
+        top += height;
+    }
+    // Out of range indices get the whole square
+    (r1, r2)
+}
+
+/// Map a point of the unit square to the unit disk, preserving relative areas
+///
+/// Shirley and Chiu's concentric mapping, which keeps strata compact instead of
+/// stretching them into thin wedges like a polar mapping would.
+pub fn concentric_disk(u: f64, v: f64) -> (f64, f64) {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let quarter = core::f64::consts::FRAC_PI_4;
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, quarter * (b / a))
+    } else {
+        (b, 2.0 * quarter - quarter * (a / b))
+    };
+    (radius * math::cos(angle), radius * math::sin(angle))
+}
+
+/// Whether texture coordinates (u, v) fall on a grid line
+pub fn on_grid_line(u: f64, v: f64, cell_size: f64, line_width: f64) -> bool {
+    let half_width = line_width / 2.0;
+    let u_mod = math::fract(u / cell_size).abs();
+    let v_mod = math::fract(v / cell_size).abs();
+
+    let on_u_line = u_mod <= half_width || u_mod >= (1.0 - half_width);
+    let on_v_line = v_mod <= half_width || v_mod >= (1.0 - half_width);
+    on_u_line || on_v_line
+}
+
+/// Whether texture coordinates (u, v) fall on an even square of a 1×1 checkerboard
+pub fn checker_is_even(u: f64, v: f64) -> bool {
+    let checker_u = math::floor(u) as i32;
+    let checker_v = math::floor(v) as i32;
+    (checker_u + checker_v) % 2 == 0
+}
+
+/// Share of `x ± filter_width / 2` covered by lines `line_width` wide, centered on every
+/// multiple of `period` (all in the same units)
+///
+/// This box-filters the lines over a pixel's footprint, so lines thinner than a pixel
+/// fade out instead of flickering. A zero width gives the 0 or 1 of a point sample.
+pub fn line_coverage(x: f64, period: f64, line_width: f64, filter_width: f64) -> f64 {
+    let duty = (line_width / period).clamp(0.0, 1.0);
+    let width = filter_width / period;
+    if width > 1e-9 {
+        // Line coverage summed from 0, with the lines shifted to start at each integer
+        let covered = |s: f64| math::floor(s) * duty + (s - math::floor(s)).min(duty);
+        let s = x / period + duty / 2.0;
+        return ((covered(s + width / 2.0) - covered(s - width / 2.0)) / width).clamp(0.0, 1.0);
+    }
+    let offset = math::fract(x / period).abs();
+    if offset <= duty / 2.0 || offset >= 1.0 - duty / 2.0 {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Share of a pixel footprint `(width in u, width in v)` covered by the lines that
+/// `on_grid_line` tests for
+pub fn grid_line_coverage(
+    u: f64,
+    v: f64,
+    cell_size: f64,
+    line_width: f64,
+    footprint: (f64, f64),
+) -> f64 {
+    let across_u = line_coverage(u, cell_size, line_width * cell_size, footprint.0);
+    let across_v = line_coverage(v, cell_size, line_width * cell_size, footprint.1);
+    1.0 - (1.0 - across_u) * (1.0 - across_v)
+}
+
+/// Strength of a pattern `distance` from its center that fades out by `fade_distance`,
+/// easing from 1 at the center to 0
+pub fn distance_fade(distance: f64, fade_distance: f64) -> f64 {
+    let x = (distance / fade_distance).clamp(0.0, 1.0);
+    1.0 - x * x * (3.0 - 2.0 * x)
+}
+
+/// Share of a pixel footprint `(width in u, width in v)` on the odd squares of the
+/// checkerboard that `checker_is_even` tests for
+pub fn checker_odd_coverage(u: f64, v: f64, footprint: (f64, f64)) -> f64 {
+    // Share of x ± width / 2 where floor(x) is odd
+    let odd = |x: f64, width: f64| {
+        let total = |x: f64| {
+            let half = x / 2.0;
+            math::floor(half) + 2.0 * (half - math::floor(half) - 0.5).max(0.0)
+        };
+        if width > 1e-9 {
+            ((total(x + width / 2.0) - total(x - width / 2.0)) / width).clamp(0.0, 1.0)
+        } else if x / 2.0 - math::floor(x / 2.0) >= 0.5 {
+            1.0
+        } else {
+            0.0
+        }
+    };
+    // Odd squares are odd along exactly one axis
+    let (odd_u, odd_v) = (odd(u, footprint.0), odd(v, footprint.1));
+    odd_u + odd_v - 2.0 * odd_u * odd_v
+}
+
+/// Smooth 3D value noise in 0.0-1.0 with features about one unit across
+///
+/// Random values on the integer lattice are blended with a quintic fade, so the
+/// noise and its gradient are continuous everywhere.
+pub fn value_noise(point: &Point) -> f64 {
+    let cell = [
+        math::floor(point.x),
+        math::floor(point.y),
+        math::floor(point.z),
+    ];
+    let fade = |t: f64| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let [fx, fy, fz] = [
+        fade(point.x - cell[0]),
+        fade(point.y - cell[1]),
+        fade(point.z - cell[2]),
+    ];
+    let lattice = |dx: i64, dy: i64, dz: i64| {
+        let mut hash = (cell[0] as i64 + dx) as u64;
+        hash = hash.wrapping_mul(0x9E3779B97F4A7C15) ^ (cell[1] as i64 + dy) as u64;
+        hash = hash.wrapping_mul(0xC2B2AE3D27D4EB4F) ^ (cell[2] as i64 + dz) as u64;
+        hash = (hash ^ (hash >> 31)).wrapping_mul(0x165667B19E3779F9);
+        (hash >> 11) as f64 / (1u64 << 53) as f64
+    };
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+
+    let along_x = |dy, dz| lerp(lattice(0, dy, dz), lattice(1, dy, dz), fx);
+    let near = lerp(along_x(0, 0), along_x(1, 0), fy);
+    let far = lerp(along_x(0, 1), along_x(1, 1), fy);
+    lerp(near, far, fz)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fog_factor() {
+        assert_eq!(fog_factor(5.0, 10.0, 20.0, 1.0), 0.0);
+        let mid = fog_factor(15.0, 10.0, 20.0, 1.0);
+        assert!((mid - (1.0 - (-0.5f64).exp())).abs() < 1e-12);
+        assert!(fog_factor(30.0, 10.0, 20.0, 100.0) <= 1.0);
+    }
+
+    #[test]
+    fn test_refract() {
+        let normal = Unit::new_normalize(Vec3::new(0.0, 0.0, 1.0));
+        // Straight on, nothing bends
+        let down = Unit::new_normalize(Vec3::new(0.0, 0.0, -1.0));
+        assert!((refract(&down, &normal, 1.0 / 1.5).unwrap().into_inner() - *down).norm() < 1e-12);
+
+        // Snell's law at 45 degrees into glass
+        let slanted = Unit::new_normalize(Vec3::new(1.0, 0.0, -1.0));
+        let bent = refract(&slanted, &normal, 1.0 / 1.5).unwrap();
+        assert!((bent.x - 0.5f64.sqrt() / 1.5).abs() < 1e-12);
+        assert!(bent.z < 0.0);
+
+        // Leaving glass that steeply reflects it all back
+        assert!(refract(&slanted, &normal, 1.5).is_none());
+    }
+
+    #[test]
+    fn test_line_coverage() {
+        // Point samples: lines 0.2 wide around multiples of 2
+        assert_eq!(line_coverage(-0.1, 2.0, 0.2, 0.0), 1.0);
+        assert_eq!(line_coverage(4.05, 2.0, 0.2, 0.0), 1.0);
+        assert_eq!(line_coverage(1.0, 2.0, 0.2, 0.0), 0.0);
+
+        // Footprints average the lines, approaching their share of the period
+        assert!((line_coverage(0.0, 2.0, 0.2, 0.4) - 0.5).abs() < 1e-12);
+        assert!((line_coverage(1.0, 2.0, 0.2, 1.0)).abs() < 1e-12);
+        assert!((line_coverage(0.3, 2.0, 0.2, 200.0) - 0.1).abs() < 1e-3);
+        assert!((grid_line_coverage(0.3, 0.7, 2.0, 0.1, (200.0, 200.0)) - 0.19).abs() < 1e-3);
+
+        // Checker squares do the same
+        assert_eq!(checker_odd_coverage(1.5, 0.5, (0.0, 0.0)), 1.0);
+        assert_eq!(checker_odd_coverage(-0.5, -0.5, (0.0, 0.0)), 0.0);
+        assert!((checker_odd_coverage(1.0, 0.5, (0.5, 0.0)) - 0.5).abs() < 1e-12);
+        assert!((checker_odd_coverage(0.3, 0.6, (50.0, 50.0)) - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_distance_fade() {
+        assert_eq!(distance_fade(0.0, 10.0), 1.0);
+        assert_eq!(distance_fade(5.0, 10.0), 0.5);
+        assert_eq!(distance_fade(10.0, 10.0), 0.0);
+        assert_eq!(distance_fade(25.0, 10.0), 0.0);
+        assert!(distance_fade(2.0, 10.0) > distance_fade(3.0, 10.0));
+    }
+
+    #[test]
+    fn test_henyey_greenstein() {
+        assert_eq!(henyey_greenstein(0.0, 0.3), 1.0);
+        // Forward scattering is brightest looking into the light
+        assert!(henyey_greenstein(0.6, 1.0) > 10.0 * henyey_greenstein(0.6, -1.0));
+        // Averaged over the sphere (uniform in cos theta), energy is kept
+        let n = 100000;
+        let mean = (0..n)
+            .map(|i| henyey_greenstein(0.6, -1.0 + 2.0 * (i as f64 + 0.5) / n as f64))
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_value_noise() {
+        let mut previous = value_noise(&Point::new(-3.0, 0.5, 2.25));
+        for i in 1..=1000 {
+            let value = value_noise(&Point::new(-3.0 + i as f64 * 0.001, 0.5, 2.25));
+            assert!((0.0..=1.0).contains(&value));
+            // Continuous across lattice cells, with no jumps between close points
+            assert!((value - previous).abs() < 0.01);
+            previous = value;
+        }
+        assert_ne!(
+            value_noise(&Point::new(0.5, 0.5, 0.5)),
+            value_noise(&Point::new(1.5, 0.5, 0.5))
+        );
+    }
+
+    #[test]
+    fn test_stratified_disk() {
+        for count in [1, 5, 16, 17] {
+            let mut cells = alloc::vec::Vec::new();
+            for index in 0..count {
+                let (u0, v0) = stratified_square(index, count, 0.0, 0.0);
+                let (u1, v1) = stratified_square(index, count, 0.999_999, 0.999_999);
+                // Every cell is inside the square with the same area
+                assert!(u0 >= 0.0 && v0 >= 0.0 && u1 <= 1.0 && v1 <= 1.0);
+                assert!(((u1 - u0) * (v1 - v0) * count as f64 - 1.0).abs() < 1e-4);
+                cells.push((u0, v0));
+            }
+            cells.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            cells.dedup();
+            assert_eq!(cells.len(), count as usize);
+        }
+
+        for i in 0..=16 {
+            for j in 0..=16 {
+                let (x, y) = concentric_disk(i as f64 / 16.0, j as f64 / 16.0);
+                assert!(x * x + y * y <= 1.0 + 1e-12);
+            }
+        }
+        // Square corners land on the rim, the center at the center
+        let (x, y) = concentric_disk(1.0, 1.0);
+        assert!((x * x + y * y - 1.0).abs() < 1e-12);
+        assert_eq!(concentric_disk(0.5, 0.5), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_cosine_hemisphere_stays_above_surface() {
+        let normal = Unit::new_normalize(Vec3::new(0.3, -0.2, 1.0));
+        for i in 0..16 {
+            for j in 0..16 {
+                let dir = cosine_hemisphere(&normal, i as f64 / 16.0, j as f64 / 16.0);
+                assert!((dir.magnitude() - 1.0).abs() < 1e-9);
+                assert!(dir.dot(&normal) >= -1e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use super::math;
+use super::Color;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Curve that maps linear radiance to display values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapOperator {
+    /// Clamp to 0-1 with no gamma encoding (the historical output)
+    Linear,
+    /// Clamp to 0-1, then encode with the sRGB transfer function
+    Srgb,
+    /// Reinhard `x / (1 + x)`, then sRGB; rolls off highlights smoothly
+    Reinhard,
+    /// ACES filmic approximation (Narkowicz), then sRGB; adds contrast and saturation
+    Aces,
+}
+
+impl core::str::FromStr for ToneMapOperator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "linear" => Ok(Self::Linear),
+            "srgb" => Ok(Self::Srgb),
+            "reinhard" => Ok(Self::Reinhard),
+            "aces" => Ok(Self::Aces),
+            _ => Err(format!(
+                "Invalid tone mapping operator '{}'. Valid options are: linear, srgb, reinhard, aces",
+                name
+            )),
+        }
+    }
+}
+
+/// Post-processing applied to each pixel before it is quantized to 8 bits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+    /// Exposure adjustment in stops; each stop doubles the radiance
+    pub exposure: f64,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            operator: ToneMapOperator::Linear,
+            exposure: 0.0,
+        }
+    }
+}
+
+impl ToneMapping {
+    /// Map linear radiance to a display color with every channel in 0.0-1.0
+    pub fn apply(&self, color: Color) -> Color {
+        let scale = if self.exposure == 0.0 {
+            1.0
+        } else {
+            math::powf(2.0, self.exposure)
+        };
+        color.map(|channel| self.map_channel(channel * scale))
+    }
+
+    fn map_channel(&self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        match self.operator {
+            ToneMapOperator::Linear => x.min(1.0),
+            ToneMapOperator::Srgb => linear_to_srgb(x.min(1.0)),
+            ToneMapOperator::Reinhard => linear_to_srgb(x / (1.0 + x)),
+            ToneMapOperator::Aces => linear_to_srgb(aces_filmic(x)),
+        }
+    }
+}
+
+/// Share of pixels `auto_exposure` keeps at or below white
+pub const AUTO_EXPOSURE_PERCENTILE: f64 = 0.99;
+
+/// Largest correction `auto_exposure` picks, in stops either way
+pub const MAX_AUTO_EXPOSURE: f64 = 16.0;
+
+/// Exposure in stops that brings the brightest pixels of a linear image to white
+///
+/// The luminance at `AUTO_EXPOSURE_PERCENTILE` is mapped to 1.0, so small highlights
+/// and visible lights may clip while everything else keeps its detail. When fewer
+/// pixels than that are lit, the average luminance of the lit pixels is used instead.
+/// A black image gets 0.
+pub fn auto_exposure(colors: impl IntoIterator<Item = Color>) -> f64 {
+    let mut luminances: Vec<f64> = colors
+        .into_iter()
+        .map(|c| (0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z).max(0.0))
+        .filter(|l| l.is_finite())
+        .collect();
+    if luminances.is_empty() {
+        return 0.0;
+    }
+    luminances.sort_by(f64::total_cmp);
+
+    let index = ((luminances.len() - 1) as f64 * AUTO_EXPOSURE_PERCENTILE) as usize;
+    let mut white = luminances[index];
+    if white <= 0.0 {
+        let lit: Vec<f64> = luminances.into_iter().filter(|&l| l > 0.0).collect();
+        if lit.is_empty() {
+            return 0.0;
+        }
+        white = lit.iter().sum::<f64>() / lit.len() as f64;
+    }
+    (-math::log2(white)).clamp(-MAX_AUTO_EXPOSURE, MAX_AUTO_EXPOSURE)
+}
+
+/// ACES filmic curve fit by Krzysztof Narkowicz, clamped to 0.0-1.0
+pub fn aces_filmic(x: f64) -> f64 {
+    let mapped = (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14);
+    mapped.clamp(0.0, 1.0)
+}
+
+/// sRGB transfer function (gamma encoding) for a linear value in 0.0-1.0
+pub fn linear_to_srgb(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * math::powf(x, 1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse sRGB transfer function: the linear value of an encoded value in 0.0-1.0
+pub fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        math::powf((x + 0.055) / 1.055, 2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_linear_clamp() {
+        let mapping = ToneMapping::default();
+        let color = mapping.apply(Color::new(-0.5, 0.25, 3.0));
+        assert_eq!(color, Color::new(0.0, 0.25, 1.0));
+    }
+
+    #[test]
+    fn test_operators_stay_in_range() {
+        for operator in [
+            ToneMapOperator::Srgb,
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::Aces,
+        ] {
+            let mapping = ToneMapping {
+                operator,
+                exposure: 1.0,
+            };
+            let mut previous = -1.0;
+            for i in 0..100 {
+                // Monotonic and bounded across a wide range of radiance
+                let value = mapping.apply(Color::repeat(i as f64 * 0.25)).x;
+                assert!((0.0..=1.0).contains(&value));
+                assert!(value >= previous);
+                previous = value;
+            }
+        }
+
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-12);
+        assert!((linear_to_srgb(0.5) - 0.7353569830524495).abs() < 1e-9);
+        for i in 0..=255 {
+            let encoded = i as f64 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(encoded)) - encoded).abs() < 1e-9);
+        }
+        assert!((srgb_to_linear(0.5) - 0.21404114048223255).abs() < 1e-9);
+        assert_eq!("aces".parse(), Ok(ToneMapOperator::Aces));
+        assert!("gamma".parse::<ToneMapOperator>().is_err());
+    }
+
+    #[test]
+    fn test_auto_exposure() {
+        // The same image lit 1000 times brighter needs 10 fewer stops
+        let image: Vec<Color> = (0..100).map(|i| Color::repeat(i as f64 / 50.0)).collect();
+        let normal = auto_exposure(image.iter().copied());
+        let bright = auto_exposure(image.iter().map(|c| c * 1024.0));
+        assert!((normal - bright - 10.0).abs() < 1e-9);
+        // The 99th percentile pixel lands on white
+        let mapping = ToneMapping {
+            operator: ToneMapOperator::Linear,
+            exposure: normal,
+        };
+        assert!((mapping.apply(image[98]).x - 1.0).abs() < 1e-9);
+        assert!(mapping.apply(image[97]).x < 1.0);
+
+        // A single lit pixel is averaged rather than ignored; black images are left alone
+        let mut dark = vec![Color::zeros(); 1000];
+        dark[0] = Color::repeat(0.25);
+        assert!((auto_exposure(dark.iter().copied()) - 2.0).abs() < 1e-9);
+        assert_eq!(auto_exposure(vec![Color::zeros(); 10]), 0.0);
+        assert_eq!(auto_exposure(Vec::new()), 0.0);
+        assert_eq!(auto_exposure([Color::repeat(1e-30)]), MAX_AUTO_EXPOSURE);
+    }
+}
@@ -0,0 +1,256 @@
+use super::math;
+use super::ray::Ray;
+use super::{Color, Point, Vec3};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use nalgebra::Matrix4;
+
+/// Densities sampled on a regular grid spanning a volume's box
+///
+/// Values sit at cell centers, `x` varying fastest, and are interpolated trilinearly
+/// between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityGrid {
+    pub resolution: [usize; 3],
+    pub values: Vec<f32>,
+}
+
+impl DensityGrid {
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> Result<Self, String> {
+        let count = resolution
+            .iter()
+            .try_fold(1usize, |count, size| count.checked_mul(*size))
+            .ok_or("Density grid resolution is too large")?;
+        if count == 0 {
+            return Err("Density grid resolution must be at least 1 in every axis".to_string());
+        }
+        if values.len() != count {
+            return Err(format!(
+                "Density grid of {}x{}x{} needs {} values, found {}",
+                resolution[0],
+                resolution[1],
+                resolution[2],
+                count,
+                values.len()
+            ));
+        }
+        if values
+            .iter()
+            .any(|value| !(value.is_finite() && *value >= 0.0))
+        {
+            return Err("Density grid values must be finite and at least 0".to_string());
+        }
+        Ok(Self { resolution, values })
+    }
+
+    /// Parse the text voxel format: the resolution `nx ny nz`, then `nx * ny * nz`
+    /// densities with `x` varying fastest, all separated by whitespace
+    ///
+    /// Lines starting with `#` are comments.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut numbers = text
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace);
+        let mut resolution = [0; 3];
+        for size in &mut resolution {
+            let word = numbers
+                .next()
+                .ok_or("Density grid is missing its resolution")?;
+            *size = word
+                .parse()
+                .map_err(|_| format!("Invalid density grid resolution '{}'", word))?;
+        }
+        let values = numbers
+            .map(|word| {
+                word.parse()
+                    .map_err(|_| format!("Invalid density grid value '{}'", word))
+            })
+            .collect::<Result<Vec<f32>, String>>()?;
+        Self::new(resolution, values)
+    }
+
+    /// Density at `local`, with each coordinate from 0 to 1 across the grid
+    pub fn sample(&self, local: &Point) -> f64 {
+        let mut lower = [0; 3];
+        let mut weight = [0.0; 3];
+        for axis in 0..3 {
+            let size = self.resolution[axis];
+            let position = (local[axis] * size as f64 - 0.5).clamp(0.0, (size - 1) as f64);
+            let floor = math::floor(position);
+            lower[axis] = (floor as usize).min(size.saturating_sub(2));
+            weight[axis] = position - lower[axis] as f64;
+        }
+        let value = |x: usize, y: usize, z: usize| {
+            let [nx, ny, _] = self.resolution;
+            self.values[(z * ny + y) * nx + x] as f64
+        };
+        let upper = |axis: usize| (lower[axis] + 1).min(self.resolution[axis] - 1);
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let pick = |axis: usize| corner >> axis & 1 == 1;
+            let mut share = 1.0;
+            let mut index = [0; 3];
+            for axis in 0..3 {
+                if pick(axis) {
+                    share *= weight[axis];
+                    index[axis] = upper(axis);
+                } else {
+                    share *= 1.0 - weight[axis];
+                    index[axis] = lower[axis];
+                }
+            }
+            if share > 0.0 {
+                density += share * value(index[0], index[1], index[2]);
+            }
+        }
+        density
+    }
+}
+
+/// Box of participating medium such as smoke or steam, rendered by ray marching
+///
+/// Light traveling through it is absorbed and scattered in proportion to the density;
+/// the scattered share, tinted by `color`, is what makes the volume visible.
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub center: Point,
+    pub size: Vec3,
+    pub world_to_local: Matrix4<f64>, // inverse of the transform about the center
+    pub density: f64,                 // constant density, or the grid's multiplier
+    pub grid: Option<Arc<DensityGrid>>,
+    pub absorption: f64, // per unit density and length
+    pub scattering: f64, // per unit density and length
+    pub color: Color,    // share of scattered light of each channel
+    pub anisotropy: f64, // Henyey-Greenstein asymmetry of the scattering
+    pub steps: u32,      // points sampled along each ray through the box
+}
+
+impl Volume {
+    /// Coordinates of `point` in the box, 0 to 1 along each side inside it
+    fn local(&self, point: &Point) -> Point {
+        // Like a cube's, the transform works about the center, translations included
+        let offset = Point::from(point - self.center).to_homogeneous();
+        let local = (self.world_to_local * offset).xyz();
+        Point::from(local.component_div(&self.size)) + Vec3::repeat(0.5)
+    }
+
+    /// Span of `ray` inside the box within `t_min..t_max`, if any
+    pub fn segment(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        // The local direction stays unnormalized so t is the same in both spaces
+        let origin = self.local(&ray.origin);
+        let direction = (self.world_to_local * ray.direction.to_homogeneous())
+            .xyz()
+            .component_div(&self.size);
+        let (mut enter, mut leave) = (t_min, t_max);
+        for axis in 0..3 {
+            if direction[axis].abs() < 1e-12 {
+                if !(0.0..=1.0).contains(&origin[axis]) {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = -origin[axis] / direction[axis];
+            let t1 = (1.0 - origin[axis]) / direction[axis];
+            enter = enter.max(t0.min(t1));
+            leave = leave.min(t0.max(t1));
+        }
+        (enter < leave).then_some((enter, leave))
+    }
+
+    /// Density at a world-space point, 0 outside the box
+    pub fn density_at(&self, point: &Point) -> f64 {
+        let local = self.local(point);
+        if (0..3).any(|axis| !(0.0..=1.0).contains(&local[axis])) {
+            return 0.0;
+        }
+        match &self.grid {
+            Some(grid) => self.density * grid.sample(&local),
+            None => self.density,
+        }
+    }
+
+    /// Light lost per unit length at `point`, to absorption and scattering together
+    pub fn extinction_at(&self, point: &Point) -> f64 {
+        (self.absorption + self.scattering) * self.density_at(point)
+    }
+
+    /// Share of light getting through the box between `from` and `to`, from a
+    /// midpoint march with a quarter of the volume's steps
+    pub fn transmittance(&self, from: &Point, to: &Point) -> f64 {
+        let offset = to - from;
+        let length = offset.magnitude();
+        if length <= 0.0 {
+            return 1.0;
+        }
+        let ray = Ray::new(*from, offset);
+        let Some((enter, leave)) = self.segment(&ray, 0.0, length) else {
+            return 1.0;
+        };
+        let steps = (self.steps / 4).max(1);
+        let step = (leave - enter) / steps as f64;
+        let depth: f64 = (0..steps)
+            .map(|index| self.extinction_at(&ray.at(enter + (index as f64 + 0.5) * step)))
+            .sum::<f64>()
+            * step;
+        math::exp(-depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steam(grid: Option<DensityGrid>) -> Volume {
+        Volume {
+            center: Point::new(0.0, 0.0, 1.0),
+            size: Vec3::new(2.0, 2.0, 2.0),
+            world_to_local: Matrix4::identity(),
+            density: 0.5,
+            grid: grid.map(Arc::new),
+            absorption: 0.25,
+            scattering: 0.75,
+            color: Color::new(1.0, 1.0, 1.0),
+            anisotropy: 0.0,
+            steps: 64,
+        }
+    }
+
+    #[test]
+    fn test_density_grid() {
+        let grid = DensityGrid::parse("# two cells along x\n2 1 1\n0 4\n").unwrap();
+        assert_eq!(grid.resolution, [2, 1, 1]);
+        // Cell centers hold the values exactly, and between them they blend
+        assert_eq!(grid.sample(&Point::new(0.25, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.sample(&Point::new(0.75, 0.5, 0.5)), 4.0);
+        assert_eq!(grid.sample(&Point::new(0.5, 0.5, 0.5)), 2.0);
+        assert_eq!(grid.sample(&Point::new(1.0, 0.0, 1.0)), 4.0);
+
+        assert!(DensityGrid::parse("2 2 1\n1 2 3").is_err());
+        assert!(DensityGrid::parse("1 1 1\n-1").is_err());
+        assert!(DensityGrid::parse("1 x 1\n1").is_err());
+    }
+
+    #[test]
+    fn test_volume_segment_and_transmittance() {
+        let volume = steam(None);
+        let ray = Ray::new(Point::new(-5.0, 0.0, 1.0), Vec3::x());
+        let (enter, leave) = volume.segment(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((enter - 4.0).abs() < 1e-12 && (leave - 6.0).abs() < 1e-12);
+        assert!(volume.segment(&ray, 0.0, 3.0).is_none());
+        let above = Ray::new(Point::new(-5.0, 0.0, 2.5), Vec3::x());
+        assert!(volume.segment(&above, 0.0, f64::INFINITY).is_none());
+
+        // Beer-Lambert falloff over the 2 units inside: exp(-(0.25 + 0.75) * 0.5 * 2)
+        let through = volume.transmittance(&Point::new(-5.0, 0.0, 1.0), &Point::new(5.0, 0.0, 1.0));
+        assert!((through - (-1.0f64).exp()).abs() < 1e-9);
+        assert_eq!(volume.density_at(&Point::new(0.0, 0.0, 3.0)), 0.0);
+
+        // A grid scales the density through the box
+        let volume = steam(Some(DensityGrid::new([1, 1, 2], vec![0.0, 2.0]).unwrap()));
+        assert_eq!(volume.density_at(&Point::new(0.0, 0.0, 0.5)), 0.0);
+        assert_eq!(volume.density_at(&Point::new(0.0, 0.0, 1.5)), 1.0);
+    }
+}
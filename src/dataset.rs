@@ -0,0 +1,413 @@
+use std::path::{Path, PathBuf};
+
+use nalgebra::{Rotation3, Unit, Vector3};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::aov::{Aov, AovBuffer};
+use crate::assets::AssetOptions;
+use crate::renderer::{AntiAliasingMode, Renderer};
+use crate::scene::Scene;
+use crate::SeededRng;
+
+/// Settings for rendering many randomized variations of one scene as training data
+///
+/// Variation `i` uses seed `seed + i`: it replaces the seed of the scene's `jitter`
+/// block, which moves named objects, and drives the camera and light ranges here.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatasetConfig {
+    /// Scene file; relative paths start at the config file's directory
+    pub scene: PathBuf,
+    /// Number of images to render
+    pub count: u32,
+    /// Seed of the first variation
+    #[serde(default)]
+    pub seed: u64,
+    /// Directory for images and `annotations.json`, relative like `scene`
+    #[serde(default = "default_output_dir")]
+    pub output_dir: PathBuf,
+    /// Image diagonal in pixels; the aspect ratio comes from the camera
+    #[serde(default = "default_size")]
+    pub size: u32,
+    /// Samples per pixel in place of the scene's render_settings
+    pub samples: Option<u32>,
+    #[serde(default)]
+    pub camera: CameraVariation,
+    #[serde(default)]
+    pub lights: LightVariation,
+}
+
+fn default_output_dir() -> PathBuf {
+    PathBuf::from("dataset")
+}
+
+fn default_size() -> u32 {
+    512
+}
+
+/// Ranges for moving the camera around its target
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraVariation {
+    /// Largest turn about the camera's up axis through the target, in degrees
+    #[serde(default)]
+    pub orbit: f64,
+    /// Largest change in height angle above the target, in degrees
+    #[serde(default)]
+    pub elevation: f64,
+    /// Largest change in distance from the target, as a fraction of it
+    #[serde(default)]
+    pub distance: f64,
+}
+
+/// Ranges applied to every light
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightVariation {
+    /// Largest move along each axis, in scene units
+    #[serde(default)]
+    pub position: f64,
+    /// Largest change in intensity, as a fraction of it
+    #[serde(default)]
+    pub intensity: f64,
+}
+
+/// One rendered variation, as listed in `annotations.json`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DatasetImage {
+    pub seed: u64,
+    /// File names within the output directory
+    pub image: String,
+    pub mask: String,
+    pub depth: String,
+    pub width: u32,
+    pub height: u32,
+    pub camera_position: [f64; 3],
+    pub camera_target: [f64; 3],
+    /// Visible objects, by ID
+    pub objects: Vec<ObjectAnnotation>,
+}
+
+/// Where one object appears in an image
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ObjectAnnotation {
+    /// Value of the object's pixels in the mask: its index in the loaded scene's
+    /// `objects` list plus one, with groups expanded
+    pub id: u32,
+    pub name: Option<String>,
+    /// Pixel bounding box as `[x, y, width, height]`
+    pub bbox: [u32; 4],
+    /// Number of pixels showing the object
+    pub pixels: u32,
+}
+
+/// Layout of `annotations.json`
+#[derive(Serialize)]
+struct Annotations<'a> {
+    images: &'a [DatasetImage],
+}
+
+impl DatasetConfig {
+    /// Load a config, resolving `scene` and `output_dir` against the file's directory
+    pub fn from_json_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let mut config: DatasetConfig = serde_json::from_str(&content)?;
+        config.validate()?;
+        let base = Path::new(path).parent().unwrap_or(Path::new(""));
+        config.scene = base.join(&config.scene);
+        config.output_dir = base.join(&config.output_dir);
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.count == 0 || self.size == 0 || self.samples == Some(0) {
+            return Err("count, size and samples must be greater than 0".to_string());
+        }
+        let (camera, lights) = (&self.camera, &self.lights);
+        let ranges = [
+            camera.orbit,
+            camera.elevation,
+            camera.distance,
+            lights.position,
+            lights.intensity,
+        ];
+        if !ranges.iter().all(|range| *range >= 0.0) {
+            return Err("ranges must not be negative".to_string());
+        }
+        if camera.distance >= 1.0 || lights.intensity > 1.0 {
+            return Err(
+                "camera distance must be below 1 and light intensity at most 1".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Move the camera and lights of a loaded scene by amounts drawn from `seed`
+    pub fn vary(&self, scene: &mut Scene, seed: u64) {
+        // Complemented so the draws don't repeat the jitter block's, which uses the seed as is
+        let mut rng = SeededRng::seed_from_u64(!seed);
+        let mut draw = |range: f64| rng.gen_range(-range..=range);
+
+        let camera = &mut scene.camera;
+        let target = Vector3::from(camera.target);
+        let up = Unit::new_normalize(Vector3::from(camera.up));
+        let mut offset = Vector3::from(camera.position) - target;
+
+        let orbit = draw(self.camera.orbit).to_radians();
+        offset = Rotation3::from_axis_angle(&up, orbit) * offset;
+
+        // Stay short of straight up or down, where the view would flip
+        let elevation = draw(self.camera.elevation).to_radians();
+        let limit = 89f64.to_radians();
+        let current = (offset.dot(&up) / offset.norm()).clamp(-1.0, 1.0).asin();
+        let turn = (current + elevation).clamp(-limit, limit) - current;
+        if let Some(axis) = Unit::try_new(offset.cross(&up), 1e-12) {
+            offset = Rotation3::from_axis_angle(&axis, turn) * offset;
+        }
+
+        // Orthographic cameras zoom by their viewport rather than their distance
+        let scale = 1.0 + draw(self.camera.distance);
+        offset *= scale;
+        if camera.kind == "ortho" {
+            camera.width *= scale;
+            camera.height *= scale;
+        }
+        camera.position = (target + offset).into();
+
+        for light in &mut scene.lights {
+            for coordinate in &mut light.position {
+                *coordinate += draw(self.lights.position);
+            }
+            light.intensity *= 1.0 + draw(self.lights.intensity);
+        }
+    }
+
+    /// Render every variation into `output_dir`, then write `annotations.json`
+    ///
+    /// Each variation writes `NNNNN.png`, a 16-bit grayscale `NNNNN.mask.png` holding
+    /// object IDs (0 for background) and a `NNNNN.depth.exr` of camera distances.
+    /// `on_image` is called after each one.
+    pub fn generate(
+        &self,
+        asset_options: &AssetOptions,
+        mut on_image: impl FnMut(&DatasetImage),
+    ) -> Result<Vec<DatasetImage>, Box<dyn std::error::Error>> {
+        self.validate()?;
+        std::fs::create_dir_all(&self.output_dir)?;
+        let scene_path = self.scene.to_string_lossy();
+
+        let mut images = Vec::with_capacity(self.count as usize);
+        for index in 0..self.count {
+            let seed = self.seed.wrapping_add(index as u64);
+            let options = AssetOptions {
+                jitter_seed: Some(seed),
+                ..asset_options.clone()
+            };
+            let mut scene = Scene::from_json_file_with_assets(&scene_path, &options)?;
+            self.vary(&mut scene, seed);
+
+            let renderer = self.renderer(&scene)?;
+            let stem = format!("{:05}", index);
+            let image = format!("{}.png", stem);
+            let mask = format!("{}.mask.png", stem);
+            let depth = format!("{}.depth.exr", stem);
+
+            renderer
+                .render(&scene)?
+                .save(self.output_dir.join(&image))?;
+            let aovs = renderer.render_aovs(&scene, &[Aov::ObjectId, Aov::Depth])?;
+            let ids: Vec<u16> = aovs[0].pixels.iter().map(|p| p[0] as u16).collect();
+            image::ImageBuffer::<image::Luma<u16>, _>::from_raw(
+                renderer.width,
+                renderer.height,
+                ids,
+            )
+            .expect("mask matches the image dimensions")
+            .save(self.output_dir.join(&mask))?;
+            aovs[1].to_rgb32f().save(self.output_dir.join(&depth))?;
+
+            let annotation = DatasetImage {
+                seed,
+                image,
+                mask,
+                depth,
+                width: renderer.width,
+                height: renderer.height,
+                camera_position: scene.camera.position,
+                camera_target: scene.camera.target,
+                objects: annotate(&aovs[0], &scene),
+            };
+            on_image(&annotation);
+            images.push(annotation);
+        }
+
+        let file = std::fs::File::create(self.output_dir.join("annotations.json"))?;
+        let annotations = Annotations { images: &images };
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), &annotations)?;
+        Ok(images)
+    }
+
+    /// Renderer for one variation, configured from the scene as the CLI does
+    fn renderer(&self, scene: &Scene) -> Result<Renderer, Box<dyn std::error::Error>> {
+        let aspect_ratio = scene.camera.width / scene.camera.height;
+        let height = self.size as f64 / (aspect_ratio * aspect_ratio + 1.0).sqrt();
+        let width = ((aspect_ratio * height).round() as u32).max(1);
+        let height = (height.round() as u32).max(1);
+
+        let mut settings = scene.render_settings.clone().unwrap_or_default();
+        settings.samples = self.samples.or(settings.samples);
+        let mut renderer = Renderer::new(width, height).with_render_settings(&settings);
+        renderer.seed = Some(0);
+        if let Some(outline) = scene.get_outline_config()? {
+            renderer = renderer.with_outline_detection(outline);
+            if renderer.anti_aliasing_mode == AntiAliasingMode::Quincunx {
+                renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+            }
+        }
+        if let Some(tilt_shift) = scene.get_tilt_shift_config() {
+            tilt_shift.validate()?;
+            renderer = renderer.with_tilt_shift(tilt_shift);
+        }
+        if let Some(tone_mapping) = scene.get_tone_mapping()? {
+            renderer = renderer.with_tone_mapping(tone_mapping);
+        }
+        Ok(renderer)
+    }
+}
+
+/// Bounding box and pixel count of every object visible in an object ID pass
+pub fn annotate(object_ids: &AovBuffer, scene: &Scene) -> Vec<ObjectAnnotation> {
+    let mut annotations: Vec<ObjectAnnotation> = Vec::new();
+    for (index, pixel) in object_ids.pixels.iter().enumerate() {
+        let id = pixel[0] as u32;
+        if id == 0 {
+            continue;
+        }
+        let x = index as u32 % object_ids.width;
+        let y = index as u32 / object_ids.width;
+        let position = annotations.binary_search_by_key(&id, |annotation| annotation.id);
+        let annotation = match position {
+            Ok(position) => &mut annotations[position],
+            Err(position) => {
+                let name = scene.objects.get(id as usize - 1).and_then(|o| o.name());
+                annotations.insert(
+                    position,
+                    ObjectAnnotation {
+                        id,
+                        name: name.map(str::to_string),
+                        bbox: [x, y, 1, 1],
+                        pixels: 0,
+                    },
+                );
+                &mut annotations[position]
+            }
+        };
+        let [left, top, width, height] = annotation.bbox;
+        let (right, bottom) = ((left + width).max(x + 1), (top + height).max(y + 1));
+        let (left, top) = (left.min(x), top.min(y));
+        annotation.bbox = [left, top, right - left, bottom - top];
+        annotation.pixels += 1;
+    }
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r##"{
+        "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+        "objects": [
+            {"kind": "sphere", "name": "ball", "center": [1, 0, 0], "radius": 0.5,
+             "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}},
+            {"kind": "cube", "center": [-1, 0, 0], "size": [1, 1, 1],
+             "material": {"color": "#00FF00", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}
+        ],
+        "lights": [{"position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1}],
+        "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}},
+        "jitter": {"objects": {"ball": {"translate": [0.2, 0, 0.2]}}}
+    }"##;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rtrace-dataset-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_vary_keeps_camera_on_target() {
+        let config: DatasetConfig = serde_json::from_str(
+            r#"{"scene": "scene.json", "count": 1,
+                "camera": {"orbit": 180, "elevation": 30, "distance": 0.5},
+                "lights": {"position": 1, "intensity": 0.5}}"#,
+        )
+        .unwrap();
+        let mut scene = Scene::from_json_str(SCENE).unwrap();
+        config.vary(&mut scene, 7);
+
+        // Distance and viewport scale together; elevation stays within its range
+        let offset = Vector3::from(scene.camera.position);
+        let scale = offset.norm() / 5.0;
+        assert!((0.5..=1.5).contains(&scale));
+        assert!((scene.camera.width - 4.0 * scale).abs() < 1e-9);
+        assert!(offset.z.abs() <= scale * 5.0 * 30f64.to_radians().sin() + 1e-9);
+        let light = &scene.lights[0];
+        assert!((0.5..=1.5).contains(&light.intensity));
+        assert!(light.position[0].abs() <= 1.0);
+
+        // The same seed moves the camera the same way
+        let mut again = Scene::from_json_str(SCENE).unwrap();
+        config.vary(&mut again, 7);
+        assert_eq!(again.camera.position, scene.camera.position);
+    }
+
+    #[test]
+    fn test_generate_writes_annotations() {
+        let dir = temp_dir("generate");
+        std::fs::write(dir.join("scene.json"), SCENE).unwrap();
+        let config_path = dir.join("dataset.json");
+        std::fs::write(
+            &config_path,
+            r#"{"scene": "scene.json", "count": 2, "seed": 5, "size": 32, "output_dir": "out"}"#,
+        )
+        .unwrap();
+
+        let config = DatasetConfig::from_json_file(config_path.to_str().unwrap()).unwrap();
+        let mut reported = 0;
+        let images = config
+            .generate(&AssetOptions::default(), |_| reported += 1)
+            .unwrap();
+        assert_eq!(reported, 2);
+        assert_eq!(images[1].seed, 6);
+
+        // Both objects are visible, left to right in ID order, inside the image
+        let objects = &images[0].objects;
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[0].name.as_deref(), Some("ball"));
+        assert!(objects[0].bbox[0] > objects[1].bbox[0]);
+        for object in objects {
+            let [x, y, w, h] = object.bbox;
+            assert!(x + w <= images[0].width && y + h <= images[0].height);
+            assert!(object.pixels > 0 && object.pixels <= w * h);
+        }
+
+        let out = dir.join("out");
+        let mask = image::open(out.join("00001.mask.png"))
+            .unwrap()
+            .into_luma16();
+        assert!(mask.pixels().any(|p| p[0] == 1) && mask.pixels().any(|p| p[0] == 2));
+        assert!(out.join("00001.depth.exr").is_file());
+        let annotations: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(out.join("annotations.json")).unwrap()).unwrap();
+        assert_eq!(annotations["images"][1]["image"], "00001.png");
+
+        let error = DatasetConfig::from_json_file(dir.join("missing.json").to_str().unwrap());
+        assert!(error.is_err());
+        std::fs::write(&config_path, r#"{"scene": "scene.json", "count": 0}"#).unwrap();
+        assert!(DatasetConfig::from_json_file(config_path.to_str().unwrap()).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
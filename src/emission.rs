@@ -0,0 +1,214 @@
+use std::sync::Arc;
+
+use nalgebra::Unit;
+
+use crate::scene::{hex_to_color, Emission, Light, Material, Point, Vec3};
+
+/// World-space triangles of a glowing surface, sampled by area when it lights the scene
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    /// Material key of the surface: its object index, or the key of a mesh face material
+    pub key: usize,
+    triangles: Vec<[Point; 3]>,
+    /// Running total of the triangle areas
+    cumulative_areas: Vec<f64>,
+}
+
+impl Emitter {
+    pub fn new(key: usize, triangles: Vec<[Point; 3]>) -> Self {
+        let mut total = 0.0;
+        let cumulative_areas = triangles
+            .iter()
+            .map(|[a, b, c]| {
+                total += (b - a).cross(&(c - a)).magnitude() / 2.0;
+                total
+            })
+            .collect();
+        Self {
+            key,
+            triangles,
+            cumulative_areas,
+        }
+    }
+
+    /// Total area; 0 for surfaces that glow without lighting anything
+    pub fn area(&self) -> f64 {
+        self.cumulative_areas.last().copied().unwrap_or(0.0)
+    }
+
+    /// Area-weighted center of the surface
+    pub fn center(&self) -> Point {
+        let mut previous = 0.0;
+        let mut sum = Vec3::zeros();
+        for ([a, b, c], cumulative) in self.triangles.iter().zip(&self.cumulative_areas) {
+            sum += (a.coords + b.coords + c.coords) / 3.0 * (cumulative - previous);
+            previous = *cumulative;
+        }
+        Point::from(sum / self.area().max(f64::EPSILON))
+    }
+
+    /// Point on the surface for `(u, v)` in the unit square, with its triangle's normal
+    ///
+    /// Evenly spread `(u, v)` give points spread evenly by area, so stratified samples
+    /// of the square stay stratified on the surface. None if the surface has no area.
+    pub fn sample(&self, u: f64, v: f64) -> Option<(Point, Unit<Vec3>)> {
+        let total = self.area();
+        if total <= 0.0 {
+            return None;
+        }
+        let target = u * total;
+        let index = self
+            .cumulative_areas
+            .partition_point(|&cumulative| cumulative <= target)
+            .min(self.triangles.len() - 1);
+        let start = index
+            .checked_sub(1)
+            .map_or(0.0, |i| self.cumulative_areas[i]);
+        let span = self.cumulative_areas[index] - start;
+        // Reuse what is left of u within the chosen triangle
+        let u = ((target - start) / span).clamp(0.0, 1.0);
+
+        let [a, b, c] = self.triangles[index];
+        let s = u.sqrt();
+        let point = a + s * (1.0 - v) * (b - a) + s * v * (c - a);
+        Some((point, Unit::new_normalize((b - a).cross(&(c - a)))))
+    }
+}
+
+impl Emission {
+    pub fn validate(&self) -> Result<(), String> {
+        hex_to_color(&self.color)?;
+        if !(self.strength.is_finite() && self.strength >= 0.0) {
+            return Err("Emission strength must be a finite number of at least 0".to_string());
+        }
+        if let Some(attenuation) = &self.attenuation {
+            attenuation.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Light standing in for `emitter`, with this emission's color, strength and falloff
+    pub fn light(&self, emitter: Emitter) -> Light {
+        let center = emitter.center();
+        Light {
+            position: [center.x, center.y, center.z],
+            color: self.color.clone(),
+            intensity: self.strength,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: self.attenuation,
+            group: self.group.clone(),
+            name: None,
+            emitter: Some(Arc::new(emitter)),
+            caustics: None,
+        }
+    }
+}
+
+/// Light for the surface with material `key`, if the material glows
+///
+/// `triangles` is only called for emissive materials; objects that aren't made of
+/// triangles pass none, and glow without lighting anything.
+pub(crate) fn emissive_light(
+    material: &Material,
+    key: usize,
+    triangles: impl FnOnce() -> Vec<[Point; 3]>,
+) -> Result<Option<Light>, String> {
+    let Some(emission) = &material.emission else {
+        return Ok(None);
+    };
+    emission.validate()?;
+    Ok(Some(emission.light(Emitter::new(key, triangles()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emitter_samples_by_area() {
+        // A 2x1 rectangle split into a large and a small triangle at x = 1.5
+        let emitter = Emitter::new(
+            3,
+            vec![
+                [
+                    Point::new(0.0, 0.0, 0.0),
+                    Point::new(1.5, 0.0, 0.0),
+                    Point::new(1.5, 0.0, 1.0),
+                ],
+                [
+                    Point::new(0.0, 0.0, 0.0),
+                    Point::new(1.5, 0.0, 1.0),
+                    Point::new(0.0, 0.0, 1.0),
+                ],
+                [
+                    Point::new(1.5, 0.0, 0.0),
+                    Point::new(2.0, 0.0, 0.0),
+                    Point::new(2.0, 0.0, 1.0),
+                ],
+                [
+                    Point::new(1.5, 0.0, 0.0),
+                    Point::new(2.0, 0.0, 1.0),
+                    Point::new(1.5, 0.0, 1.0),
+                ],
+            ],
+        );
+        assert!((emitter.area() - 2.0).abs() < 1e-12);
+        assert!((emitter.center() - Point::new(1.0, 0.0, 0.5)).magnitude() < 1e-12);
+
+        // A grid of (u, v) covers the rectangle evenly
+        let n = 40;
+        let points: Vec<Point> = (0..n * n)
+            .map(|i| {
+                let u = ((i / n) as f64 + 0.5) / n as f64;
+                let v = ((i % n) as f64 + 0.5) / n as f64;
+                let (point, normal) = emitter.sample(u, v).unwrap();
+                assert!((normal.y.abs() - 1.0).abs() < 1e-12);
+                point
+            })
+            .collect();
+        let right = points.iter().filter(|p| p.x > 1.5).count() as f64;
+        let top = points.iter().filter(|p| p.z > 0.5).count() as f64;
+        assert!((right / points.len() as f64 - 0.25).abs() < 0.02);
+        assert!((top / points.len() as f64 - 0.5).abs() < 0.02);
+
+        let glow_only = Emitter::new(0, Vec::new());
+        assert_eq!(glow_only.area(), 0.0);
+        assert!(glow_only.sample(0.5, 0.5).is_none());
+    }
+
+    #[test]
+    fn test_emissive_light() {
+        let mut material = Material::default();
+        let quad = || {
+            crate::mesh::Mesh::from_polygon(&[
+                Point::new(0.0, 0.0, 2.0),
+                Point::new(1.0, 0.0, 2.0),
+                Point::new(1.0, 1.0, 2.0),
+                Point::new(0.0, 1.0, 2.0),
+            ])
+            .triangles
+            .iter()
+            .map(|triangle| triangle.vertices)
+            .collect()
+        };
+        assert!(emissive_light(&material, 0, quad).unwrap().is_none());
+
+        material.emission = Some(Emission {
+            color: "#FFEECC".to_string(),
+            strength: 3.0,
+            attenuation: None,
+            group: Some("panels".to_string()),
+        });
+        let light = emissive_light(&material, 5, quad).unwrap().unwrap();
+        assert_eq!(light.position, [0.5, 0.5, 2.0]);
+        assert_eq!(
+            (light.intensity, light.group.as_deref()),
+            (3.0, Some("panels"))
+        );
+        assert_eq!(light.emitter.as_ref().unwrap().key, 5);
+
+        material.emission.as_mut().unwrap().strength = -1.0;
+        assert!(emissive_light(&material, 5, quad).is_err());
+    }
+}
@@ -0,0 +1,412 @@
+use std::collections::BTreeMap;
+
+use serde_json::{Number, Value};
+
+/// Evaluate an arithmetic expression such as `2*sin(0.5)+1`
+///
+/// Supports `+ - * / %`, `^` for powers, parentheses, the constants `pi` and `e`,
+/// and the functions `sin cos tan asin acos atan atan2 sqrt abs exp ln log10 floor
+/// ceil round min max pow`. Angles are in radians. Other names are looked up in
+/// `parameters`.
+pub fn evaluate(expression: &str, parameters: &BTreeMap<String, f64>) -> Result<f64, String> {
+    let mut lookup = |name: &str| {
+        parameters
+            .get(name)
+            .copied()
+            .ok_or_else(|| format!("unknown name '{}'", name))
+    };
+    evaluate_with(expression, &mut lookup)
+}
+
+fn evaluate_with(
+    expression: &str,
+    lookup: &mut dyn FnMut(&str) -> Result<f64, String>,
+) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().collect(),
+        position: 0,
+        depth: 0,
+        lookup,
+    };
+    let value = parser.expression()?;
+    parser.skip_whitespace();
+    if let Some(c) = parser.peek() {
+        return Err(format!("unexpected '{}'", c));
+    }
+    if !value.is_finite() {
+        return Err(format!("result {} is not a finite number", value));
+    }
+    Ok(value)
+}
+
+/// Replace every `"=..."` string in a scene's JSON with the number it evaluates to
+///
+/// Names in the expressions refer to the top-level `parameters` table, whose values
+/// may themselves be expressions using other parameters. The table is removed once
+/// resolved. Returns whether the scene had any parameters or expressions.
+pub(crate) fn evaluate_scene_expressions(scene: &mut Value) -> Result<bool, String> {
+    let declared = match scene.as_object_mut().and_then(|s| s.remove("parameters")) {
+        None => serde_json::Map::new(),
+        Some(Value::Object(declared)) => declared,
+        Some(_) => return Err("Scene parameters must be an object of names to values".into()),
+    };
+    let mut resolved = BTreeMap::new();
+    for name in declared.keys() {
+        resolve_parameter(name, &declared, &mut resolved, &mut Vec::new())?;
+    }
+    let evaluated = replace_expressions(scene, &resolved, &mut String::new())?;
+    Ok(evaluated || !declared.is_empty())
+}
+
+fn resolve_parameter(
+    name: &str,
+    declared: &serde_json::Map<String, Value>,
+    resolved: &mut BTreeMap<String, f64>,
+    resolving: &mut Vec<String>,
+) -> Result<f64, String> {
+    if let Some(value) = resolved.get(name) {
+        return Ok(*value);
+    }
+    if resolving.iter().any(|pending| pending == name) {
+        return Err(format!("Parameter '{}' depends on itself", name));
+    }
+    let value = match declared.get(name) {
+        Some(Value::Number(number)) => number.as_f64().unwrap_or(f64::NAN),
+        Some(Value::String(text)) if text.starts_with('=') => {
+            resolving.push(name.to_string());
+            let mut lookup = |other: &str| match declared.contains_key(other) {
+                true => resolve_parameter(other, declared, resolved, resolving),
+                false => Err(format!("unknown name '{}'", other)),
+            };
+            let value = evaluate_with(&text[1..], &mut lookup)
+                .map_err(|e| format!("Parameter '{}' = '{}': {}", name, &text[1..], e))?;
+            resolving.pop();
+            value
+        }
+        _ => {
+            return Err(format!(
+                "Parameter '{}' must be a number or an '=' expression",
+                name
+            ))
+        }
+    };
+    resolved.insert(name.to_string(), value);
+    Ok(value)
+}
+
+/// Replace expressions below `value`, tracking the JSON path for error messages
+fn replace_expressions(
+    value: &mut Value,
+    parameters: &BTreeMap<String, f64>,
+    path: &mut String,
+) -> Result<bool, String> {
+    let mut evaluated = false;
+    match value {
+        Value::String(text) if text.starts_with('=') => {
+            let result = evaluate(&text[1..], parameters)
+                .map_err(|e| format!("Expression '{}' at {}: {}", &text[1..], path, e))?;
+            *value = json_number(result);
+            evaluated = true;
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                let length = path.len();
+                path.push_str(&format!("[{}]", index));
+                evaluated |= replace_expressions(item, parameters, path)?;
+                path.truncate(length);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                let length = path.len();
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                evaluated |= replace_expressions(field, parameters, path)?;
+                path.truncate(length);
+            }
+        }
+        _ => {}
+    }
+    Ok(evaluated)
+}
+
+/// Whole results become JSON integers so they also fill integer fields such as `samples`
+fn json_number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < 9.0e15 {
+        Value::Number(Number::from(value as i64))
+    } else {
+        Number::from_f64(value).map_or(Value::Null, Value::Number)
+    }
+}
+
+/// Deepest nesting of parentheses, calls and signs an expression may have, keeping
+/// the recursive descent well clear of the stack's limit
+const MAX_DEPTH: usize = 256;
+
+/// Recursive descent parser that evaluates as it goes
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize, // nesting of the unary being parsed
+    lookup: &'a mut dyn FnMut(&str) -> Result<f64, String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Consume `c` if it is the next non-whitespace character
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            match self.peek() {
+                Some(found) => Err(format!("expected '{}', found '{}'", c, found)),
+                None => Err(format!("expected '{}' at end of expression", c)),
+            }
+        }
+    }
+
+    /// Sum: `term (('+' | '-') term)*`
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Product: `unary (('*' | '/' | '%') unary)*`
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.unary()?;
+            } else if self.eat('/') {
+                value /= self.unary()?;
+            } else if self.eat('%') {
+                value %= self.unary()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Sign, binding looser than `^` so `-2^2` is -4
+    ///
+    /// Every nested parse passes through here, so this is where the depth is limited.
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("nested more than {} deep", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let value = if self.eat('-') {
+            self.unary().map(|value| -value)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        };
+        self.depth -= 1;
+        value
+    }
+
+    /// Right-associative power: `atom ('^' unary)?`
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            Ok(base.powf(self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let value = self.expression()?;
+                self.expect(')')?;
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let start = self.position;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    self.position += 1;
+                }
+                let name: String = self.chars[start..self.position].iter().collect();
+                if self.eat('(') {
+                    let mut arguments = vec![self.expression()?];
+                    while self.eat(',') {
+                        arguments.push(self.expression()?);
+                    }
+                    self.expect(')')?;
+                    call(&name, &arguments)
+                } else {
+                    match name.as_str() {
+                        "pi" => Ok(std::f64::consts::PI),
+                        "e" => Ok(std::f64::consts::E),
+                        _ => (self.lookup)(&name),
+                    }
+                }
+            }
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("unexpected end of expression".into()),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.position += 1;
+        }
+        // Exponent, as in 1e-3
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let mark = self.position;
+            self.position += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.position += 1;
+            }
+            if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.position = mark;
+            }
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.position += 1;
+            }
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse()
+            .map_err(|_| format!("invalid number '{}'", text))
+    }
+}
+
+fn call(name: &str, arguments: &[f64]) -> Result<f64, String> {
+    let count = arguments.len();
+    let unary = |f: fn(f64) -> f64| match arguments {
+        [x] => Ok(f(*x)),
+        _ => Err(format!("{}() takes 1 argument, got {}", name, count)),
+    };
+    let binary = |f: fn(f64, f64) -> f64| match arguments {
+        [x, y] => Ok(f(*x, *y)),
+        _ => Err(format!("{}() takes 2 arguments, got {}", name, count)),
+    };
+    match name {
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "asin" => unary(f64::asin),
+        "acos" => unary(f64::acos),
+        "atan" => unary(f64::atan),
+        "sqrt" => unary(f64::sqrt),
+        "abs" => unary(f64::abs),
+        "exp" => unary(f64::exp),
+        "ln" => unary(f64::ln),
+        "log10" => unary(f64::log10),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "atan2" => binary(f64::atan2),
+        "pow" => binary(f64::powf),
+        "min" => binary(f64::min),
+        "max" => binary(f64::max),
+        _ => Err(format!("unknown function '{}'", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let parameters = BTreeMap::from([("r".to_string(), 2.0)]);
+        let eval = |expression: &str| evaluate(expression, &parameters);
+
+        assert_eq!(eval("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9.0));
+        assert_eq!(eval("2^3^2"), Ok(512.0));
+        assert_eq!(eval("-2^2"), Ok(-4.0));
+        assert_eq!(eval("7 % 4 - 1e1"), Ok(-7.0));
+        assert_eq!(eval("2*sin(0.5)+1"), Ok(2.0 * 0.5f64.sin() + 1.0));
+        assert_eq!(eval("max(r, 3) * pi"), Ok(3.0 * std::f64::consts::PI));
+
+        assert!(eval("1 +").is_err());
+        assert!(eval("(1").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("width").unwrap_err().contains("'width'"));
+        assert!(eval("sin(1, 2)").is_err());
+        assert!(eval("1 / 0").is_err());
+
+        // Deep nesting fails cleanly rather than overflowing the stack
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(200)), Ok(1.0));
+        assert!(eval(&nested(100_000)).unwrap_err().contains("nested"));
+        assert!(eval(&"-".repeat(100_000)).unwrap_err().contains("nested"));
+        assert!(eval(&format!("{}1", "sqrt(".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn test_scene_expressions() {
+        let mut scene: Value = serde_json::from_str(
+            r##"{
+                "parameters": {"height": "=base * 2", "base": 1.5},
+                "radius": "=height + 1",
+                "samples": "=2 * 2",
+                "center": [0, "=-base", 0],
+                "color": "#FFFFFF"
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(evaluate_scene_expressions(&mut scene), Ok(true));
+        assert_eq!(
+            scene,
+            serde_json::json!({
+                "radius": 4,
+                "samples": 4,
+                "center": [0, -1.5, 0],
+                "color": "#FFFFFF"
+            })
+        );
+
+        let mut plain = serde_json::json!({"radius": 1});
+        assert_eq!(evaluate_scene_expressions(&mut plain), Ok(false));
+
+        let mut cyclic = serde_json::json!({"parameters": {"a": "=b", "b": "=a + 1"}});
+        assert!(evaluate_scene_expressions(&mut cyclic)
+            .unwrap_err()
+            .contains("depends on itself"));
+
+        let mut broken = serde_json::json!({"objects": [{"radius": "=2 *"}]});
+        let error = evaluate_scene_expressions(&mut broken).unwrap_err();
+        assert!(error.contains("objects[0].radius"), "{}", error);
+    }
+}
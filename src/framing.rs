@@ -0,0 +1,166 @@
+//! Cropping saved images to the rendered geometry and padding them with a margin
+
+/// Crop and margin applied to images saved by `Renderer::render_to_sink`
+///
+/// Content is every pixel whose center ray hits an object, the same pixels with a
+/// nonzero alpha; shadow catchers count only where a shadow falls. The image, or the bounding box of its content when cropping,
+/// gets `padding_percent` of its larger side added on every edge, filled with the
+/// background color.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Framing {
+    /// Trim the image to the bounding box of its content (kept whole if nothing is hit)
+    pub crop_to_content: bool,
+    /// Margin on each edge, as a percentage of the larger side of the (cropped) image
+    pub padding_percent: f64,
+}
+
+impl Framing {
+    /// Whether saved images come out exactly as rendered
+    pub fn is_identity(&self) -> bool {
+        !self.crop_to_content && self.padding_percent == 0.0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&self.padding_percent) {
+            return Err(format!(
+                "Padding percent must be between 0 and 100, got {}",
+                self.padding_percent
+            ));
+        }
+        Ok(())
+    }
+
+    /// Part of a `width`×`height` image that is kept, and the margin put around it
+    ///
+    /// `covered` holds whether each pixel shows content, row by row; it is only read
+    /// when cropping.
+    pub fn window(&self, width: u32, height: u32, covered: &[bool]) -> FramingWindow {
+        let mut window = FramingWindow {
+            x0: 0,
+            y0: 0,
+            width,
+            height,
+            padding: 0,
+        };
+        if self.crop_to_content {
+            let mut bounds: Option<(u32, u32, u32, u32)> = None;
+            for (index, _) in covered.iter().enumerate().filter(|(_, &hit)| hit) {
+                let (x, y) = (index as u32 % width, index as u32 / width);
+                bounds = Some(match bounds {
+                    Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                    None => (x, y, x, y),
+                });
+            }
+            if let Some((x0, y0, x1, y1)) = bounds {
+                window.x0 = x0;
+                window.y0 = y0;
+                window.width = x1 - x0 + 1;
+                window.height = y1 - y0 + 1;
+            }
+        }
+        let larger_side = window.width.max(window.height) as f64;
+        window.padding = (larger_side * self.padding_percent / 100.0).round() as u32;
+        window
+    }
+}
+
+/// Region kept from a rendered image and the margin added on each of its edges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramingWindow {
+    pub x0: u32,
+    pub y0: u32,
+    pub width: u32,
+    pub height: u32,
+    pub padding: u32,
+}
+
+impl FramingWindow {
+    /// Size of the framed image
+    pub fn output_size(&self) -> (u32, u32) {
+        (
+            self.width + 2 * self.padding,
+            self.height + 2 * self.padding,
+        )
+    }
+
+    /// Copy the kept region of `image` into a new image, with the margin set to `fill`
+    #[cfg(feature = "image")]
+    pub fn apply<P>(
+        &self,
+        image: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+        fill: P,
+    ) -> image::ImageBuffer<P, Vec<P::Subpixel>>
+    where
+        P: image::Pixel,
+    {
+        use image::GenericImageView;
+
+        let (width, height) = self.output_size();
+        let mut framed = image::ImageBuffer::from_pixel(width, height, fill);
+        let kept = image.view(self.x0, self.y0, self.width, self.height);
+        image::imageops::replace(
+            &mut framed,
+            &*kept,
+            self.padding as i64,
+            self.padding as i64,
+        );
+        framed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        // 6x4 image with content covering x 2..=3, y 1..=2
+        let covered: Vec<bool> = (0..24)
+            .map(|i| (2..=3).contains(&(i % 6)) && (1..=2).contains(&(i / 6)))
+            .collect();
+        let crop = Framing {
+            crop_to_content: true,
+            padding_percent: 50.0,
+        };
+        let window = crop.window(6, 4, &covered);
+        assert_eq!(
+            (window.x0, window.y0, window.width, window.height),
+            (2, 1, 2, 2)
+        );
+        assert_eq!((window.padding, window.output_size()), (1, (4, 4)));
+
+        // Without content nothing is cropped, and padding alone keeps the whole image
+        let window = crop.window(6, 4, &[false; 24]);
+        assert_eq!((window.x0, window.width, window.padding), (0, 6, 3));
+        let pad = Framing {
+            crop_to_content: false,
+            padding_percent: 10.0,
+        };
+        assert_eq!(pad.window(20, 10, &[]).output_size(), (24, 14));
+        assert!(Framing::default().is_identity());
+        assert!(Framing {
+            padding_percent: -1.0,
+            ..pad
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_apply() {
+        let image = image::RgbImage::from_fn(4, 4, |x, y| image::Rgb([x as u8, y as u8, 9]));
+        let window = FramingWindow {
+            x0: 1,
+            y0: 2,
+            width: 2,
+            height: 1,
+            padding: 1,
+        };
+        let framed = window.apply(&image, image::Rgb([0, 0, 0]));
+        assert_eq!(framed.dimensions(), (4, 3));
+        assert_eq!(framed.get_pixel(1, 1), &image::Rgb([1, 2, 9]));
+        assert_eq!(framed.get_pixel(2, 1), &image::Rgb([2, 2, 9]));
+        assert_eq!(framed.get_pixel(0, 0), &image::Rgb([0, 0, 0]));
+    }
+}
@@ -0,0 +1,446 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::colormap::{normalize, sample_stops, ColorScale};
+use crate::mesh::{Mesh, Triangle};
+use crate::scene::{FaceMaterial, GlyphShape, Material, Object, Point, Vec3};
+use crate::tube::add_facing;
+
+/// Steps the color map is split into; glyphs take the color of the nearest step
+pub const COLOR_LEVELS: usize = 64;
+
+/// Columns of numbers read from a CSV or NumPy file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataTable {
+    /// Column names: the CSV header, or "0", "1"... for the columns of a NumPy array
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+}
+
+impl DataTable {
+    /// Table from the bytes of the file `reference`: a NumPy array if its name ends in
+    /// `.npy`, otherwise CSV
+    pub fn from_bytes(reference: &str, bytes: &[u8]) -> Result<Self, String> {
+        if reference.to_ascii_lowercase().ends_with(".npy") {
+            Self::parse_npy(bytes)
+        } else {
+            let text = std::str::from_utf8(bytes).map_err(|_| "CSV file is not UTF-8")?;
+            Self::parse_csv(text)
+        }
+    }
+
+    /// Table from CSV text with a header row naming the columns
+    ///
+    /// Blank lines and lines starting with `#` are skipped, and empty cells read as NaN.
+    pub fn parse_csv(text: &str) -> Result<Self, String> {
+        let mut lines = (text.lines().enumerate())
+            .map(|(number, line)| (number + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let (_, header) = lines.next().ok_or("CSV file has no header row")?;
+        let columns: Vec<String> = (header.split(','))
+            .map(|name| name.trim().trim_matches('"').to_string())
+            .collect();
+        let mut rows = Vec::new();
+        for (number, line) in lines {
+            let row = (line.split(','))
+                .map(|cell| match cell.trim() {
+                    "" => Ok(f64::NAN),
+                    cell => (cell.parse::<f64>())
+                        .map_err(|_| format!("CSV line {}: '{}' is not a number", number, cell)),
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+            if row.len() != columns.len() {
+                return Err(format!(
+                    "CSV line {} has {} values for {} columns",
+                    number,
+                    row.len(),
+                    columns.len()
+                ));
+            }
+            rows.push(row);
+        }
+        Ok(Self { columns, rows })
+    }
+
+    /// Table from a NumPy `.npy` file of a 1 or 2 dimensional array of little-endian
+    /// floats or integers, one row per point
+    pub fn parse_npy(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 10 || &bytes[..6] != b"\x93NUMPY" {
+            return Err("Not a NumPy .npy file".to_string());
+        }
+        let (header_len, start) = match bytes[6] {
+            1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+            _ if bytes.len() >= 12 => {
+                let len = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+                (len as usize, 12)
+            }
+            _ => return Err("NumPy file is truncated".to_string()),
+        };
+        let header = (bytes.get(start..start + header_len))
+            .and_then(|header| std::str::from_utf8(header).ok())
+            .ok_or("NumPy file has a broken header")?;
+        let field = |key: &str| {
+            let after = &header[header.find(&format!("'{}'", key))? + key.len() + 2..];
+            Some(after.trim_start().strip_prefix(':')?.trim_start())
+        };
+
+        let descr = field("descr")
+            .and_then(|value| value.strip_prefix('\'')?.split('\'').next())
+            .ok_or("NumPy header has no 'descr'")?;
+        let (size, read): (usize, fn(&[u8]) -> f64) = match descr {
+            "<f8" => (8, |b| f64::from_le_bytes(b.try_into().unwrap())),
+            "<f4" => (4, |b| f32::from_le_bytes(b.try_into().unwrap()) as f64),
+            "<i8" => (8, |b| i64::from_le_bytes(b.try_into().unwrap()) as f64),
+            "<i4" => (4, |b| i32::from_le_bytes(b.try_into().unwrap()) as f64),
+            _ => {
+                return Err(format!(
+                    "NumPy arrays of '{}' are not supported; use little-endian floats or integers",
+                    descr
+                ))
+            }
+        };
+        let fortran = field("fortran_order").is_some_and(|value| value.starts_with("True"));
+        let shape: Vec<usize> = field("shape")
+            .and_then(|value| value.strip_prefix('(')?.split(')').next())
+            .ok_or("NumPy header has no 'shape'")?
+            .split(',')
+            .map(str::trim)
+            .filter(|dimension| !dimension.is_empty())
+            .map(|dimension| dimension.parse().map_err(|_| "NumPy shape is not a number"))
+            .collect::<Result<_, _>>()?;
+        let (count, width) = match shape[..] {
+            [count] => (count, 1),
+            [count, width] => (count, width),
+            _ => {
+                return Err(format!(
+                    "NumPy array has {} dimensions, not 1 or 2",
+                    shape.len()
+                ))
+            }
+        };
+
+        let data = &bytes[start + header_len..];
+        let values = count.checked_mul(width).ok_or("NumPy array is too large")?;
+        // The data bounds the rows and columns only when they hold values at all
+        if values == 0 {
+            return Err(format!("NumPy array of shape {:?} is empty", shape));
+        }
+        if data.len() < values.saturating_mul(size) {
+            return Err(format!(
+                "NumPy file holds {} bytes for {} values",
+                data.len(),
+                values
+            ));
+        }
+        let value = |index: usize| read(&data[index * size..(index + 1) * size]);
+        let rows = (0..count)
+            .map(|row| {
+                (0..width)
+                    .map(|column| match fortran {
+                        true => value(column * count + row),
+                        false => value(row * width + column),
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            columns: (0..width).map(|column| column.to_string()).collect(),
+            rows,
+        })
+    }
+
+    /// Index of the column called `name`
+    pub fn column(&self, name: &str) -> Result<usize, String> {
+        (self.columns.iter().position(|column| column == name)).ok_or_else(|| {
+            format!(
+                "Data has no column '{}' (columns: {})",
+                name,
+                self.columns.join(", ")
+            )
+        })
+    }
+}
+
+/// Mesh of every glyph of a glyph field object, before its transform, and the face
+/// materials coloring them by their scalar; None for other objects, or without data
+///
+/// Rows whose position isn't finite are left out, as are arrows of zero length. Glyphs
+/// whose scalar isn't finite keep the object's own material.
+pub fn glyph_field(object: &Object) -> Result<Option<(Mesh, Vec<FaceMaterial>)>, String> {
+    let Object::Glyphs {
+        glyph,
+        size,
+        position,
+        vector,
+        vector_scale,
+        scalar,
+        sides,
+        material,
+        table: Some(table),
+        ..
+    } = object
+    else {
+        return Ok(None);
+    };
+    if !(size.is_finite() && *size > 0.0) {
+        return Err("Glyph size must be greater than 0".to_string());
+    }
+    if *sides < 3 {
+        return Err("Glyph sides must be at least 3".to_string());
+    }
+    if !vector_scale.is_finite() {
+        return Err("Glyph vector_scale must be a finite number".to_string());
+    }
+    let columns = |names: &[String; 3]| -> Result<[usize; 3], String> {
+        Ok([
+            table.column(&names[0])?,
+            table.column(&names[1])?,
+            table.column(&names[2])?,
+        ])
+    };
+    let position = columns(position)?;
+    let vector = vector.as_ref().map(columns).transpose()?;
+    let scalar = scalar
+        .as_deref()
+        .map(|name| table.column(name))
+        .transpose()?;
+    if *glyph == GlyphShape::Arrow && vector.is_none() {
+        return Err("Arrow glyphs need 'vector' columns".to_string());
+    }
+
+    let scale = glyph_color_scale(object)?;
+    let level = |value: f64| -> Option<u16> {
+        let scale = scale.as_ref()?;
+        if !value.is_finite() {
+            return None;
+        }
+        Some((normalize(value, scale.range) * (COLOR_LEVELS - 1) as f64).round() as u16)
+    };
+
+    let template = match glyph {
+        GlyphShape::Sphere => Some(unit_sphere(*sides)),
+        GlyphShape::Cube => Some(Mesh::placeholder_box()),
+        GlyphShape::Arrow => None,
+    };
+    let mut mesh = Mesh::new();
+    let mut used = [false; COLOR_LEVELS];
+    for row in &table.rows {
+        let point = Point::new(row[position[0]], row[position[1]], row[position[2]]);
+        if !point.coords.iter().all(|c| c.is_finite()) {
+            continue;
+        }
+        let start = mesh.triangles.len();
+        match (&template, vector) {
+            (Some(template), _) => {
+                for triangle in &template.triangles {
+                    mesh.add_triangle(Triangle {
+                        vertices: triangle.vertices.map(|v| point + v.coords * *size),
+                        normal: triangle.normal,
+                    });
+                }
+            }
+            (None, Some(vector)) => {
+                let offset = Vec3::new(row[vector[0]], row[vector[1]], row[vector[2]]);
+                let tip = point + offset * *vector_scale;
+                if offset.iter().all(|c| c.is_finite()) && (tip - point).norm() > 1e-12 {
+                    let arrow = crate::arrow::arrow_mesh(&point, &tip, size / 6.0, *size, *sides)?;
+                    for triangle in arrow.triangles {
+                        mesh.add_triangle(triangle);
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+        if let Some(column) = scalar {
+            let level = level(row[column]);
+            if let Some(level) = level {
+                used[level as usize] = true;
+            }
+            let added = mesh.triangles.len() - start;
+            (mesh.attributes).extend(std::iter::repeat_n(level.unwrap_or(u16::MAX), added));
+        }
+    }
+    mesh.build_kdtree();
+
+    let stops = scale.map(|scale| scale.stops).unwrap_or_default();
+    let face_materials = (0..COLOR_LEVELS)
+        .filter(|level| used[*level])
+        .map(|level| {
+            let color = sample_stops(&stops, level as f64 / (COLOR_LEVELS - 1) as f64);
+            let channel = |value: f64| (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+            FaceMaterial {
+                attribute: Some(level as u16),
+                faces: None,
+                material: Material {
+                    color: format!(
+                        "#{:04X}{:04X}{:04X}",
+                        channel(color.x),
+                        channel(color.y),
+                        channel(color.z)
+                    ),
+                    ..material.clone()
+                },
+            }
+        })
+        .collect();
+    Ok(Some((mesh, face_materials)))
+}
+
+/// Most triangles a glyph of `shape` with `sides` facets around it is made of
+pub fn glyph_triangle_count(shape: GlyphShape, sides: u32) -> usize {
+    match shape {
+        GlyphShape::Sphere => 2 * sides as usize * (sides as usize / 2).max(2),
+        GlyphShape::Cube => 12,
+        GlyphShape::Arrow => crate::arrow::ARROW_TRIANGLES_PER_SIDE * sides as usize,
+    }
+}
+
+/// Color map stops and the scalars at either end of them for a glyph field colored by a
+/// `scalar` column; None for other objects, or without a scalar or data
+///
+/// Without a `scalar_range` the range runs from the lowest finite scalar to the highest.
+pub fn glyph_color_scale(object: &Object) -> Result<Option<ColorScale>, String> {
+    let Object::Glyphs {
+        scalar: Some(scalar),
+        color_map,
+        scalar_range,
+        table: Some(table),
+        ..
+    } = object
+    else {
+        return Ok(None);
+    };
+    let column = table.column(scalar)?;
+    let stops = (color_map.clone().unwrap_or_default().stops())
+        .map_err(|e| format!("Glyph color_map: {}", e))?;
+    let range = match scalar_range {
+        Some(range) => *range,
+        None => (table.rows.iter())
+            .map(|row| row[column])
+            .filter(|value| value.is_finite())
+            .fold([f64::INFINITY, f64::NEG_INFINITY], |[low, high], value| {
+                [low.min(value), high.max(value)]
+            }),
+    };
+    Ok(Some(ColorScale { stops, range }))
+}
+
+/// Sphere 1 across centered on the origin, with `sides` facets around its equator and
+/// half as many bands from pole to pole
+fn unit_sphere(sides: u32) -> Mesh {
+    let bands = (sides / 2).max(2);
+    let vertex = |band: u32, side: u32| {
+        let (polar, around) = (
+            PI * band as f64 / bands as f64,
+            TAU * side as f64 / sides as f64,
+        );
+        Point::new(
+            polar.sin() * around.cos() / 2.0,
+            polar.sin() * around.sin() / 2.0,
+            polar.cos() / 2.0,
+        )
+    };
+    let mut mesh = Mesh::new();
+    for band in 0..bands {
+        for side in 0..sides {
+            let quad = [
+                vertex(band, side),
+                vertex(band + 1, side),
+                vertex(band + 1, side + 1),
+                vertex(band, side + 1),
+            ];
+            let outward = quad.iter().map(|v| v.coords).sum::<Vec3>();
+            // Triangles at the poles with no area are left out
+            add_facing(&mut mesh, [quad[0], quad[1], quad[2]], &outward);
+            add_facing(&mut mesh, [quad[0], quad[2], quad[3]], &outward);
+        }
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_and_npy() {
+        let table = DataTable::parse_csv("# field\nx, y,z,\"speed\"\n\n0,1,2,3\n4,5,6,\n").unwrap();
+        assert_eq!(table.columns, ["x", "y", "z", "speed"]);
+        assert_eq!(table.rows[0], [0.0, 1.0, 2.0, 3.0]);
+        assert!(table.rows[1][3].is_nan());
+        assert_eq!(table.column("speed"), Ok(3));
+        assert!(table.column("w").unwrap_err().contains("x, y, z, speed"));
+        let error = DataTable::parse_csv("x,y\n1,2\n3\n").unwrap_err();
+        assert!(error.contains("line 3"), "{}", error);
+
+        // A 2 by 3 array of doubles, stored column by column
+        let header = "{'descr': '<f8', 'fortran_order': True, 'shape': (2, 3), }";
+        let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+        npy.extend((header.len() as u16).to_le_bytes());
+        npy.extend(header.bytes());
+        for value in [1.0f64, 4.0, 2.0, 5.0, 3.0, 6.0] {
+            npy.extend(value.to_le_bytes());
+        }
+        let table = DataTable::from_bytes("points.NPY", &npy).unwrap();
+        assert_eq!(table.columns, ["0", "1", "2"]);
+        assert_eq!(table.rows, [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        npy.truncate(npy.len() - 8);
+        assert!(DataTable::parse_npy(&npy).is_err());
+
+        // Empty arrays are refused, whatever size their other dimension claims
+        for shape in ["(1000000000000, 0)", "(0, 1000000000000)"] {
+            let header = format!(
+                "{{'descr': '<f8', 'fortran_order': False, 'shape': {}, }}",
+                shape
+            );
+            let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+            npy.extend((header.len() as u16).to_le_bytes());
+            npy.extend(header.bytes());
+            let error = DataTable::parse_npy(&npy).unwrap_err();
+            assert!(error.contains("empty"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_glyph_field() {
+        let table =
+            DataTable::parse_csv("x,y,z,u,v,w,t\n0,0,0,1,0,0,0\n5,0,0,0,0,0,10\n9,0,0,0,2,0,\n")
+                .unwrap();
+        let mut object: Object = serde_json::from_str(
+            r##"{"kind": "glyphs", "filename": "field.csv", "glyph": "cube", "size": 0.5, "scalar": "t",
+                "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}"##,
+        )
+        .unwrap();
+        assert!(glyph_field(&object).unwrap().is_none());
+        let Object::Glyphs { table: loaded, .. } = &mut object else {
+            unreachable!()
+        };
+        *loaded = Some(std::sync::Arc::new(table));
+
+        let (mesh, faces) = glyph_field(&object).unwrap().unwrap();
+        assert_eq!(mesh.triangle_count(), 3 * 12);
+        let (min, max) = mesh.bounds();
+        assert!((min - Point::new(-0.25, -0.25, -0.25)).norm() < 1e-12);
+        assert!((max - Point::new(9.25, 0.25, 0.25)).norm() < 1e-12);
+        // The ends of the default map, and the last glyph with no scalar left plain
+        assert_eq!(faces.len(), 2);
+        assert_eq!(faces[0].material.color, "#444401015454");
+        assert_eq!(faces[1].material.color, "#FDFDE7E72525");
+        assert_eq!(mesh.attributes[..12], [0; 12]);
+        assert_eq!(mesh.attributes[24..], [u16::MAX; 12]);
+
+        // Arrows start at each point, leaving out the one of zero length
+        if let Object::Glyphs { glyph, vector, .. } = &mut object {
+            *glyph = GlyphShape::Arrow;
+            *vector = Some(["u", "v", "w"].map(String::from));
+        }
+        let (mesh, _) = glyph_field(&object).unwrap().unwrap();
+        assert_eq!(mesh.triangle_count(), 2 * 6 * 16);
+        let (min, max) = mesh.bounds();
+        assert!((min.x - 0.0).abs() < 1e-12 && (max.y - 2.0).abs() < 1e-12);
+
+        if let Object::Glyphs { vector, .. } = &mut object {
+            *vector = None;
+        }
+        assert!(glyph_field(&object).unwrap_err().contains("vector"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Shading traced at half resolution and upsampled along full-resolution hits
+//!
+//! Every other pixel center in each direction is shaded, and the result is divided
+//! by the surface color so textures stay sharp. Full-resolution pixels then blend
+//! the four nearest texels that lie on their own surface, and are shaded directly
+//! where none do, such as along silhouettes.
+
+use crate::scene::{Color, Point, Vec3};
+
+/// Added to the surface color before shading is divided by it, so black surfaces
+/// keep their highlights
+pub const ALBEDO_EPSILON: f64 = 0.01;
+
+/// Largest distance of a texel from a pixel's tangent plane, as a share of the
+/// pixel's distance from the camera, before it stops counting
+const PLANE_TOLERANCE: f64 = 0.01;
+
+/// Share of the bilinear weight left after rejecting texels, below which the pixel
+/// is shaded at full resolution instead
+const MIN_WEIGHT: f64 = 0.1;
+
+/// Shading of one half-resolution texel
+#[derive(Debug, Clone, Copy)]
+pub struct Texel {
+    pub lighting: Color, // shaded color divided by the surface color plus ALBEDO_EPSILON
+    pub point: Point,
+    pub normal: Vec3,
+}
+
+/// Half-resolution shading, row by row; None where the texel isn't upsampled
+/// (misses, mirrors and glass)
+#[derive(Debug, Clone)]
+pub struct HalfResShading {
+    pub width: u32,
+    pub height: u32,
+    pub texels: Vec<Option<Texel>>,
+}
+
+impl HalfResShading {
+    /// Upsampled lighting at screen position (u, v), with v = 1 at the top, for a
+    /// surface at `point` facing `normal`, `depth` away from the camera
+    ///
+    /// The four nearest texels are blended bilinearly, each weighted down as it
+    /// leaves the surface's tangent plane or turns away from its normal. None when
+    /// too little weight is left to trust the blend.
+    pub fn lighting(
+        &self,
+        uv: (f64, f64),
+        point: &Point,
+        normal: &Vec3,
+        depth: f64,
+    ) -> Option<Color> {
+        let x = (uv.0 * self.width as f64 - 0.5).clamp(0.0, (self.width - 1) as f64);
+        let y = ((1.0 - uv.1) * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+
+        let mut total = Color::zeros();
+        let mut total_weight = 0.0;
+        for (tx, ty, bilinear) in [
+            (x0, y0, (1.0 - fx) * (1.0 - fy)),
+            (x1, y0, fx * (1.0 - fy)),
+            (x0, y1, (1.0 - fx) * fy),
+            (x1, y1, fx * fy),
+        ] {
+            let Some(texel) = self.texels[(ty * self.width + tx) as usize] else {
+                continue;
+            };
+            let offset = normal.dot(&(texel.point - point)).abs() / (PLANE_TOLERANCE * depth);
+            let facing = normal.dot(&texel.normal).max(0.0).powi(8);
+            let weight = bilinear * facing * (-offset * offset).exp();
+            total += texel.lighting * weight;
+            total_weight += weight;
+        }
+
+        (total_weight >= MIN_WEIGHT).then(|| total / total_weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lighting_follows_surfaces() {
+        // 2x1 texels: a floor facing up on the left, a wall facing the camera on the right
+        let floor = Texel {
+            lighting: Color::new(1.0, 0.0, 0.0),
+            point: Point::new(0.0, 0.0, 0.0),
+            normal: Vec3::z(),
+        };
+        let wall = Texel {
+            lighting: Color::new(0.0, 0.0, 1.0),
+            point: Point::new(1.0, 0.0, 1.0),
+            normal: -Vec3::y(),
+        };
+        let shading = HalfResShading {
+            width: 2,
+            height: 1,
+            texels: vec![Some(floor), Some(wall)],
+        };
+
+        // Halfway between the texels each surface only takes its own lighting
+        let floor_point = Point::new(0.5, 0.0, 0.0);
+        let lighting = shading.lighting((0.5, 0.5), &floor_point, &Vec3::z(), 10.0);
+        assert_eq!(lighting, Some(floor.lighting));
+        let wall_point = Point::new(0.5, 0.0, 1.0);
+        let lighting = shading.lighting((0.5, 0.5), &wall_point, &-Vec3::y(), 10.0);
+        assert_eq!(lighting, Some(wall.lighting));
+
+        // A surface neither texel lies on is left to be shaded directly
+        let lighting = shading.lighting((0.5, 0.5), &Point::new(0.0, 0.0, 5.0), &Vec3::z(), 10.0);
+        assert_eq!(lighting, None);
+
+        // Texels on the same surface blend bilinearly
+        let far_floor = Texel {
+            lighting: wall.lighting,
+            point: Point::new(1.0, 0.0, 0.0),
+            normal: Vec3::z(),
+        };
+        let shading = HalfResShading {
+            texels: vec![Some(floor), Some(far_floor)],
+            ..shading
+        };
+        let lighting = shading
+            .lighting((0.5, 0.5), &floor_point, &Vec3::z(), 10.0)
+            .unwrap();
+        assert!((lighting - Color::new(0.5, 0.0, 0.5)).norm() < 1e-12);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+
+use nalgebra::Point3;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::scene::{hex_to_color, parse_transforms, Object, Scene};
+use crate::SeededRng;
+
+/// Seeded random changes to named objects, for rendering many variations of one scene
+///
+/// Each range is symmetric about the scene's own value. Offsets are drawn in name order,
+/// so the same seed and scene always give the same variation.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Jitter {
+    /// Seed for the offsets; `AssetOptions::jitter_seed` replaces it
+    #[serde(default)]
+    pub seed: u64,
+    /// Ranges keyed by object `name`; groups are jittered as a whole
+    pub objects: BTreeMap<String, ObjectJitter>,
+}
+
+/// Ranges for one named object
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectJitter {
+    /// Largest move along each axis, in scene units
+    pub translate: Option<[f64; 3]>,
+    /// Largest turn about each axis through the object's center, in degrees
+    pub rotate: Option<[f64; 3]>,
+    /// Smallest and largest uniform scale about the object's center
+    pub scale: Option<[f64; 2]>,
+    /// Largest change to each channel of the material color, from 0 to 1
+    pub color: Option<f64>,
+}
+
+impl ObjectJitter {
+    fn validate(&self) -> Result<(), String> {
+        let ranges = self.translate.iter().chain(&self.rotate).flatten();
+        if !ranges.chain(&self.color).all(|range| *range >= 0.0) {
+            return Err("ranges must not be negative".to_string());
+        }
+        if let Some([min, max]) = self.scale {
+            if !(min > 0.0 && min <= max) {
+                return Err("scale needs 0 < min <= max".to_string());
+            }
+        }
+        if self.color.is_some_and(|color| color > 1.0) {
+            return Err("color range must be at most 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Draw offsets and apply them to `object`
+    fn apply(&self, object: &mut Object, rng: &mut SeededRng) -> Result<(), String> {
+        let mut draw = |range: f64| rng.gen_range(-range..=range);
+        let translate = self.translate.map(|range| range.map(&mut draw));
+        let rotate = self.rotate.map(|range| range.map(&mut draw));
+        let color = self.color.map(|range| [range; 3].map(&mut draw));
+        let scale = self.scale.map(|[min, max]| rng.gen_range(min..=max));
+
+        let local_center = local_center(object);
+        let transform = object.transform_mut().get_or_insert_with(Vec::new);
+        let [dx, dy, dz] = translate.unwrap_or_default();
+        if rotate.is_some() || scale.is_some() {
+            // Turn and scale about the object's center in place of the scene origin
+            let center = parse_transforms(transform)?.transform_point(&Point3::from(local_center));
+            transform.push(format!(
+                "translate({}, {}, {})",
+                -center.x, -center.y, -center.z
+            ));
+            if let Some(scale) = scale {
+                transform.push(format!("scale({}, {}, {})", scale, scale, scale));
+            }
+            if let Some([x, y, z]) = rotate {
+                transform.push(format!("rotate({}, {}, {})", x, y, z));
+            }
+            transform.push(format!(
+                "translate({}, {}, {})",
+                center.x + dx,
+                center.y + dy,
+                center.z + dz
+            ));
+        } else if translate.is_some() {
+            transform.push(format!("translate({}, {}, {})", dx, dy, dz));
+        }
+
+        if let Some(offset) = color {
+            for_each_material(object, &mut |material| {
+                let base = hex_to_color(&material.color)?;
+                let channel = |value: f64, offset: f64| {
+                    ((value + offset).clamp(0.0, 1.0) * 255.0).round() as u8
+                };
+                material.color = format!(
+                    "#{:02X}{:02X}{:02X}",
+                    channel(base.x, offset[0]),
+                    channel(base.y, offset[1]),
+                    channel(base.z, offset[2])
+                );
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Jitter {
+    /// Apply the jitter to a scene whose groups are not yet expanded
+    ///
+    /// Names that match nothing in the scene are an error, as in overrides files.
+    pub fn apply(&self, scene: &mut Scene, seed: u64) -> Result<(), String> {
+        let mut rng = SeededRng::seed_from_u64(seed);
+        for (name, object_jitter) in &self.objects {
+            object_jitter
+                .validate()
+                .map_err(|e| format!("Jitter for '{}': {}", name, e))?;
+            let mut found = false;
+            for_each_named(&mut scene.objects, name, &mut |object| {
+                found = true;
+                object_jitter.apply(object, &mut rng)
+            })
+            .map_err(|e| format!("Jitter for '{}': {}", name, e))?;
+            if !found {
+                return Err(format!("Jitter for unknown object '{}'", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Point the object's transform is applied to that marks its center
+fn local_center(object: &Object) -> [f64; 3] {
+    let centroid = |vertices: &[[f64; 3]]| {
+        let sum = vertices.iter().fold([0.0; 3], |sum, v| {
+            [sum[0] + v[0], sum[1] + v[1], sum[2] + v[2]]
+        });
+        sum.map(|total| total / vertices.len() as f64)
+    };
+    match object {
+        Object::Sphere { center, .. } | Object::Disc { center, .. } => *center,
+        Object::Plane { point, .. } | Object::FloorGrid { point, .. } => *point,
+        Object::Triangle { vertices, .. } => centroid(vertices),
+        Object::Quad { vertices, .. } => centroid(vertices),
+        Object::Tube { points, .. } | Object::Line { points, .. } => centroid(points),
+        Object::Arrow { from, to, .. } => centroid(&[*from, *to]),
+        // Cube and volume transforms already work about the box's center; meshes,
+        // instances, glyph fields and groups are placed by their transforms
+        Object::Cube { .. }
+        | Object::Volume { .. }
+        | Object::Mesh { .. }
+        | Object::Instance { .. }
+        | Object::Glyphs { .. }
+        | Object::Group { .. } => [0.0; 3],
+    }
+}
+
+/// Call `f` on every object named `name`, looking inside groups
+fn for_each_named(
+    objects: &mut [Object],
+    name: &str,
+    f: &mut impl FnMut(&mut Object) -> Result<(), String>,
+) -> Result<(), String> {
+    for object in objects {
+        if object.name() == Some(name) {
+            f(object)?;
+        }
+        if let Object::Group { objects, .. } = object {
+            for_each_named(objects, name, f)?;
+        }
+    }
+    Ok(())
+}
+
+/// Call `f` on the object's material, or on every material in a group
+fn for_each_material(
+    object: &mut Object,
+    f: &mut impl FnMut(&mut crate::scene::Material) -> Result<(), String>,
+) -> Result<(), String> {
+    if let Object::Group { objects, .. } = object {
+        return objects
+            .iter_mut()
+            .try_for_each(|child| for_each_material(child, f));
+    }
+    match object.material_mut() {
+        Some(material) => f(material),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r##"{
+        "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+        "objects": [
+            {"kind": "sphere", "name": "ball", "center": [2, 0, 0], "radius": 1,
+             "material": {"color": "#808080", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}},
+            {"kind": "group", "name": "stack", "transform": ["translate(0, 0, 1)"], "objects": [
+                {"kind": "cube", "center": [0, 0, 0], "size": [1, 1, 1],
+                 "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}
+            ]}
+        ],
+        "lights": [],
+        "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}},
+        "jitter": JITTER
+    }"##;
+
+    const JITTER: &str = r#"{"seed": 3, "objects": {
+        "ball": {"translate": [0.5, 0.5, 0], "rotate": [0, 0, 90], "scale": [0.5, 1.5], "color": 0.25},
+        "stack": {"rotate": [0, 0, 45], "color": 0.5}
+    }}"#;
+
+    fn scene_json(jitter: &str) -> String {
+        SCENE.replace("JITTER", jitter)
+    }
+
+    fn sphere(scene: &Scene) -> ([f64; 3], &str) {
+        let Object::Sphere {
+            center,
+            material,
+            transform,
+            ..
+        } = &scene.objects[0]
+        else {
+            panic!("expected a sphere");
+        };
+        let matrix = parse_transforms(transform.as_deref().unwrap()).unwrap();
+        let center = matrix.transform_point(&Point3::from(*center));
+        ([center.x, center.y, center.z], &material.color)
+    }
+
+    #[test]
+    fn test_jitter_named_objects() {
+        let scene = Scene::from_json_str(&scene_json(JITTER)).unwrap();
+        assert!(scene.jitter.is_none());
+
+        // Turning and scaling happen about the sphere's own center, so only the
+        // translate range moves it
+        let (center, color) = sphere(&scene);
+        assert!((center[0] - 2.0).abs() <= 0.5 && center[1].abs() <= 0.5);
+        assert!(center[2].abs() < 1e-9);
+        let gray = hex_to_color(color).unwrap();
+        assert!(gray.iter().all(|channel| (channel - 0.5).abs() <= 0.26));
+
+        // The group turns about its own origin, keeping the cube in place
+        let Object::Cube { transform, .. } = &scene.objects[1] else {
+            panic!("expected a cube");
+        };
+        let matrix = parse_transforms(transform.as_deref().unwrap()).unwrap();
+        let origin = matrix.transform_point(&Point3::origin());
+        assert!((origin - Point3::new(0.0, 0.0, 1.0)).norm() < 1e-9);
+
+        // The same seed gives the same scene; another seed a different one
+        let again = Scene::from_json_str(&scene_json(JITTER)).unwrap();
+        assert_eq!(sphere(&again), sphere(&scene));
+        let options = crate::assets::AssetOptions {
+            jitter_seed: Some(4),
+            ..Default::default()
+        };
+        let mut reseeded: Scene = serde_json::from_str(&scene_json(JITTER)).unwrap();
+        reseeded.load_mesh_data_with_assets(None, &options).unwrap();
+        assert_ne!(sphere(&reseeded), sphere(&scene));
+    }
+
+    #[test]
+    fn test_jitter_errors() {
+        let error = Scene::from_json_str(&scene_json(r#"{"objects": {"bal": {}}}"#))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(error, "Jitter for unknown object 'bal'");
+        let error = Scene::from_json_str(&scene_json(
+            r#"{"objects": {"ball": {"translate": [-1, 0, 0]}}}"#,
+        ))
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with("Jitter for 'ball'"), "{}", error);
+    }
+}
@@ -1,29 +1,279 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("rtrace needs the `std` feature, or `libm` for no_std builds");
+
+pub mod core;
+
+#[cfg(feature = "std")]
+pub mod animation;
+#[cfg(feature = "std")]
+pub mod aov;
+#[cfg(feature = "std")]
+pub mod arrow;
+#[cfg(feature = "std")]
+pub mod assets;
+#[cfg(feature = "std")]
+pub mod backplate;
+#[cfg(feature = "std")]
 pub mod auto_camera;
+#[cfg(feature = "std")]
+pub mod bump;
+#[cfg(feature = "std")]
 pub mod camera;
+#[cfg(feature = "std")]
+pub mod caustics;
+#[cfg(feature = "std")]
+pub mod colormap;
+#[cfg(feature = "image")]
+pub mod dataset;
+#[cfg(feature = "std")]
+pub mod emission;
+#[cfg(feature = "std")]
+pub mod expr;
+#[cfg(feature = "std")]
+pub mod framing;
+#[cfg(feature = "std")]
+pub mod glyphs;
+#[cfg(feature = "std")]
+mod half_res;
+#[cfg(feature = "std")]
+pub mod jitter;
+#[cfg(feature = "std")]
+pub mod light_groups;
+#[cfg(feature = "std")]
 pub mod lighting;
+#[cfg(feature = "std")]
+pub mod lod;
+#[cfg(feature = "std")]
+pub mod material_check;
+#[cfg(feature = "std")]
+pub mod measure;
+#[cfg(feature = "std")]
 pub mod mesh;
+#[cfg(feature = "std")]
+pub mod non_finite;
+#[cfg(feature = "std")]
+pub mod object_stats;
+#[cfg(feature = "std")]
 pub mod outline;
+#[cfg(feature = "std")]
+pub mod overlap;
+#[cfg(feature = "std")]
+pub mod overlay;
+#[cfg(feature = "std")]
+pub mod overrides;
+#[cfg(feature = "cli")]
+pub mod preview;
+#[cfg(feature = "std")]
+pub mod profile;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
 pub mod ray;
+#[cfg(feature = "cli")]
+pub mod render_cache;
+#[cfg(feature = "std")]
+pub mod render_options;
+#[cfg(feature = "std")]
 pub mod renderer;
+#[cfg(feature = "stl")]
+mod stl;
+#[cfg(feature = "std")]
+pub mod temporal;
+#[cfg(feature = "std")]
+pub mod tile_failures;
+#[cfg(feature = "std")]
+pub mod tilt_shift;
+#[cfg(feature = "std")]
+pub mod tube;
+#[cfg(feature = "std")]
+pub mod xray;
 /// Ray tracing library for rtrace
 ///
 /// This library provides a complete ray tracer with support for:
 /// - Orthographic and perspective camera projection
+/// - Zoom-to-fit orthographic cameras framing the scene's finite bounds
+/// - Named cameras rendered in one batch that builds the world once
+/// - Prepared scenes rendered again and again through changing cameras
+/// - Edits that move, add and remove objects of prepared scenes, rebuilding only those objects
+///   and refitting the bounding volume hierarchy over the world's objects
 /// - Basic geometric primitives (sphere, plane, cube)
+/// - Tubes swept along polylines or Bezier curves, tessellated into meshes
+/// - Arrow glyphs for vector fields and forces, tessellated into meshes
+/// - Glyph fields plotting the rows of CSV or NumPy files as spheres, cubes or arrows
+/// - Viridis, plasma and coolwarm color maps for data, false-color AOVs and color bars
+/// - Camera-facing lines for hair, fibers and annotations, with widths in pixels or scene units
 /// - Phong lighting model with ambient lighting
-/// - Atmospheric fog
+/// - Light falloff with distance (inverse-square or quadratic)
+/// - Ambient occlusion render mode for clay previews
+/// - Half-resolution shading upsampled along full-resolution hits
+/// - Preview render mode: one ray per pixel, hard shadows and outlines, for framing
+/// - Outline detection from depth, normal, object and crease edges, also as a post process
+/// - Anti-aliased outlines from supersampled depth, normal and object buffers
+/// - Per-object outline colors and opt-outs
+/// - X-ray views blending every surface along each ray, for ghosted technical drawings
+/// - Clip planes for cutaway views, with objects exempted to stay whole
+/// - Atmospheric fog, optionally lit by the scene's lights for light shafts
+/// - Smoke and steam volumes with constant or voxel-grid density, ray marched
+/// - Backplate images behind the scene
+/// - Automatic floor under the scene, optionally a shadow catcher
+/// - Optional sRGB color management: colors and images decoded, lit linearly, encoded on output
 /// - Texture support (grid patterns)
-/// - JSON scene description format
+/// - Floor grids: ground planes with fading grid lines
+/// - Glass-like transparent materials with refraction, and photon-mapped caustics
+/// - See-through reference geometry with a plain `opacity`, unbent
+/// - Shadow-catcher materials showing only received shadows, with an alpha pass
+/// - Bump mapping from noise or height images
+/// - Emissive materials, with glowing triangles and meshes sampled as area lights
+/// - Material sanity checks with optional energy normalization
+/// - JSON scene description format, with `=` expressions and named parameters
+/// - Mesh instancing with triangles and k-d trees shared between instances
+/// - Per-face mesh materials from STL attribute words or triangle ranges
+/// - Automatic lay-flat orientation of STL meshes onto the ground plane
+/// - Mesh units (millimeters, inches, or guessed from the model's size)
+/// - Mesh levels of detail picked by their size on screen
+/// - Object groups with shared, nestable transforms
+/// - Motion blur from per-object motion transforms sampled over the camera shutter
+/// - Keyframed camera and object animation rendered as numbered frame sequences
+/// - Walkthrough camera paths through timed waypoints
+/// - Turntable camera orbits and looping GIF/APNG output for frame sequences
+/// - Frame sequences piped to ffmpeg for MP4 and other video output
+/// - Temporal accumulation reprojecting earlier frames of a sequence to average out noise
+/// - Seeded jitter of named objects for generating scene variations
+/// - Synthetic dataset generation with object masks, bounding boxes and depth
+/// - Remote (http/https) mesh assets with an on-disk cache
+/// - Content-hash render cache for skipping unchanged renders
+/// - Failed tiles recorded and re-rendered on a single thread instead of aborting
+/// - NaN/Inf pixel checks that report the object hit and mark the pixel magenta
+/// - Render sinks for saving to files, memory, writers or per-tile callbacks
+/// - Saved images cropped to the rendered geometry and padded with a margin
+/// - Stereo pairs from a perspective camera, side by side or one image per eye
+/// - Overlap checks between objects' geometry, for validating assemblies
+/// - Closest-approach distances and axis clearances between objects and points
+/// - Preview sessions that reshade cached primary hits after material/light edits
+/// - Chrome-tracing profiles of render phases
+/// - Per-object intersection test counts and time, for finding slow meshes
+/// - Pluggable progress reporting (silent unless a reporter is attached)
+/// - Progressive rendering with a snapshot after each sampling pass
+/// - AOV passes (depth, normals, object ID, albedo, direct/indirect light, alpha)
+/// - Normalized depth maps and RGB normal maps as standalone images
+/// - Light groups with one output pass per group
+/// - Overrides files that patch materials and lights by name
+/// - Tilt-shift depth blur for a miniature look
+/// - Bounding box and axis overlays for checking transforms and framing
 /// - Auto camera bounds functionality
+/// - A `core` ray/intersection/shading module that builds without std
+#[cfg(feature = "std")]
 pub mod scene;
+#[cfg(feature = "image")]
+mod sequence;
+#[cfg(feature = "image")]
+pub mod sink;
+#[cfg(feature = "std")]
+pub mod stereo;
 
-pub use auto_camera::{AutoCamera, AutoCameraResult};
-pub use mesh::{Mesh, Triangle};
+pub use crate::core::tonemap::{ToneMapOperator, ToneMapping};
+pub use crate::core::volume::{DensityGrid, Volume};
+#[cfg(feature = "std")]
+pub use animation::{Animation, CameraKeyframe, ObjectKeyframe, ObjectTrack, Waypoint};
+#[cfg(feature = "std")]
+pub use aov::{Aov, AovBuffer, FalseColor};
+#[cfg(feature = "std")]
+pub use assets::{AssetOptions, MissingAsset, MissingAssetPolicy};
+#[cfg(feature = "std")]
+pub use backplate::Backplate;
+#[cfg(feature = "std")]
+pub use bump::HeightMap;
+#[cfg(feature = "std")]
+pub use colormap::{ColorMap, ColorMapSpec, ColorScale};
+#[cfg(feature = "image")]
+pub use dataset::{DatasetConfig, DatasetImage, ObjectAnnotation};
+#[cfg(feature = "std")]
+pub use framing::{Framing, FramingWindow};
+#[cfg(feature = "std")]
+pub use jitter::{Jitter, ObjectJitter};
+#[cfg(feature = "std")]
+pub use auto_camera::{AutoCamera, AutoCameraResult, TurntableAxis};
+#[cfg(feature = "image")]
+pub use light_groups::LightGroupPass;
+#[cfg(feature = "std")]
+pub use material_check::MaterialWarning;
+#[cfg(feature = "std")]
+pub use measure::{measure, Measurement, Target, CLEARANCE_DIRECTIONS};
+#[cfg(feature = "std")]
+pub use mesh::{Mesh, MeshLimits, Triangle};
+#[cfg(feature = "std")]
+pub use non_finite::{NonFinitePixel, NonFinitePixels, NON_FINITE_MARKER};
+#[cfg(feature = "std")]
+pub use object_stats::{ObjectStat, ObjectStats};
+#[cfg(feature = "image")]
+pub use outline::DepthMap;
+#[cfg(feature = "std")]
 pub use outline::{OutlineBuffers, OutlineConfig};
-pub use renderer::{AntiAliasingMode, Renderer};
+#[cfg(feature = "std")]
+pub use overlap::{find_overlaps, Overlap, DEFAULT_OVERLAP_TOLERANCE};
+#[cfg(feature = "std")]
+pub use overlay::Overlay;
+#[cfg(feature = "std")]
+pub use overrides::{ObjectOverride, Overrides};
+#[cfg(feature = "cli")]
+pub use preview::{PreviewSession, PreviewUpdate};
+#[cfg(feature = "std")]
+pub use progress::{Progress, ProgressReporter, RenderPhase, StdoutProgress};
+#[cfg(feature = "cli")]
+pub use render_cache::RenderCache;
+#[cfg(feature = "std")]
+pub use render_options::RenderOptions;
+#[cfg(feature = "std")]
+pub use renderer::{
+    AntiAliasingMode, CameraOverride, CancellationToken, RenderMode, Renderer, RendererLimits,
+};
+#[cfg(feature = "image")]
+pub use renderer::{PreparedScene, SceneEdit};
+#[cfg(feature = "std")]
 pub use scene::{
-    AmbientIllumination, Camera, Fog, Light, Material, Object, Scene, SceneSettings, Texture,
+    AmbientIllumination, AutoFloor, Bump, Camera, ClipPlane, ColorSpace, Emission, FaceMaterial,
+    Fog, GlyphShape, InferredUnit, Light, LineWidthUnit, Material, MeshLod, MeshOrient,
+    MeshPrototype, MeshUnit, Object, OutlineSettings, RenderSettings, Scene, SceneSettings,
+    Texture, TubeCurve, VolumetricFog,
 };
+#[cfg(feature = "image")]
+pub use sequence::SequenceFormat;
+#[cfg(feature = "image")]
+pub use sink::{
+    CallbackSink, FileSink, MemorySink, RenderSink, RenderedImage, SinkPass, TilePixels, WriterSink,
+};
+#[cfg(feature = "std")]
+pub use stereo::{Eye, Stereo, StereoLayout};
+#[cfg(feature = "std")]
+pub use temporal::TemporalConfig;
+#[cfg(feature = "std")]
+pub use tile_failures::{TileFailure, TileFailures};
+#[cfg(feature = "std")]
+pub use tilt_shift::TiltShiftConfig;
+#[cfg(feature = "std")]
+pub use xray::XRayConfig;
+
+/// Generator behind every seeded random draw: jitter, sampling and area light samples
+///
+/// `rand::rngs::StdRng` may switch algorithms between rand releases, so the algorithm
+/// is named here to keep seeded output the same from build to build. ChaCha12 is what
+/// `StdRng` uses today, so renders match those from before.
+#[cfg(feature = "std")]
+pub(crate) type SeededRng = rand_chacha::ChaCha12Rng;
+
+/// Clock for render timings and progress: std's, except in the browser, which has none
+/// and is timed through `performance.now()`
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub(crate) use std::time;
+#[cfg(all(feature = "std", target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) use web_time as time;
 
 /// Returns a greeting message
 ///
@@ -35,6 +285,7 @@ pub use scene::{
 /// let message = hello_world();
 /// assert_eq!(message, "hello world");
 /// ```
+#[cfg(feature = "std")]
 pub fn hello_world() -> String {
     "hello world".to_string()
 }
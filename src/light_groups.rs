@@ -0,0 +1,92 @@
+use crate::scene::Light;
+
+/// Group of lights without a `group` name
+pub const DEFAULT_GROUP: &str = "default";
+
+/// Pass holding everything not lit by a light: ambient, background and fog
+pub const AMBIENT_PASS: &str = "ambient";
+
+/// One pass of a light group render, in linear radiance
+///
+/// The passes of a render sum to its image before outline detection, tilt-shift
+/// and tone mapping, so lighting can be rebalanced by scaling them in post.
+#[cfg(feature = "image")]
+#[derive(Debug, Clone)]
+pub struct LightGroupPass {
+    /// Light group name, or `AMBIENT_PASS`
+    pub name: String,
+    pub image: image::Rgb32FImage,
+}
+
+#[cfg(feature = "image")]
+impl LightGroupPass {
+    /// Path for this pass next to the main output: `render.png` becomes `render.light_key.png`
+    pub fn output_path(&self, output_path: &str) -> std::path::PathBuf {
+        crate::aov::pass_output_path(output_path, &format!("light_{}", self.name))
+    }
+}
+
+/// Group a light belongs to
+pub fn group_of(light: &Light) -> &str {
+    light.group.as_deref().unwrap_or(DEFAULT_GROUP)
+}
+
+/// Distinct light group names in the order they first appear
+///
+/// Names end up in file names, so they are limited to letters, digits, `-` and `_`.
+pub fn group_names(lights: &[Light]) -> Result<Vec<String>, String> {
+    let mut names: Vec<String> = Vec::new();
+    for light in lights {
+        let name = group_of(light);
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid light group '{}': use letters, digits, '-' and '_'",
+                name
+            ));
+        }
+        if name == AMBIENT_PASS {
+            return Err(format!(
+                "Light group name '{}' is reserved for the ambient pass",
+                name
+            ));
+        }
+        if !names.iter().any(|existing| existing == name) {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(group: Option<&str>) -> Light {
+        Light {
+            position: [0.0, 0.0, 5.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: group.map(str::to_string),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_group_names() {
+        let lights = [light(Some("key")), light(None), light(Some("rim")), light(Some("key"))];
+        assert_eq!(group_names(&lights).unwrap(), ["key", "default", "rim"]);
+
+        assert!(group_names(&[light(Some("ambient"))]).is_err());
+        assert!(group_names(&[light(Some("../key"))]).is_err());
+        assert!(group_names(&[light(Some(""))]).is_err());
+    }
+}
@@ -1,3 +1,10 @@
+use crate::core::shading::{
+    checker_odd_coverage, concentric_disk, cosine_hemisphere, distance_fade, fog_factor,
+    grid_line_coverage, henyey_greenstein, phong_strengths, reflect, refract, stratified_square,
+};
+use crate::core::volume::Volume;
+use crate::emission::Emitter;
+use crate::profile::{time_rays, RayKind};
 use crate::ray::{HitRecord, Ray, World};
 use crate::scene::{
     hex_to_color, AmbientIllumination, Color, Fog, Light, Material, Point, Texture, Vec3,
@@ -6,71 +13,182 @@ use nalgebra::Unit;
 use rand::{Rng, SeedableRng};
 
 /// Apply texture pattern and return the appropriate material properties
-fn apply_texture(texture: &Texture, u: f64, v: f64, base_material: &Material) -> Material {
+///
+/// `footprint` is the width of the pixel in (u, v) at this point; patterns are averaged
+/// over it, and a zero footprint samples them at the exact point.
+fn apply_texture(
+    texture: &Texture,
+    u: f64,
+    v: f64,
+    footprint: (f64, f64),
+    base_material: &Material,
+) -> Material {
     match texture {
         Texture::Grid {
             line_color,
             line_width,
             cell_size,
+            fade_distance,
         } => {
-            let grid_color = hex_to_color(line_color).unwrap_or(Color::new(0.0, 0.0, 0.0));
-            let half_width = line_width / 2.0;
-
-            // Check if we're on a grid line
-            let u_mod = (u / cell_size).fract().abs();
-            let v_mod = (v / cell_size).fract().abs();
-
-            let on_u_line = u_mod <= half_width || u_mod >= (1.0 - half_width);
-            let on_v_line = v_mod <= half_width || v_mod >= (1.0 - half_width);
-
-            if on_u_line || on_v_line {
-                // Create a new material with grid color but same properties
-                Material {
-                    color: format!(
-                        "#{:02X}{:02X}{:02X}",
-                        (grid_color.x * 255.0) as u8,
-                        (grid_color.y * 255.0) as u8,
-                        (grid_color.z * 255.0) as u8
-                    ),
-                    ..base_material.clone()
-                }
-            } else {
-                base_material.clone()
+            let mut coverage = grid_line_coverage(u, v, *cell_size, *line_width, footprint);
+            if let Some(fade_distance) = fade_distance {
+                coverage *= distance_fade(u.hypot(v), *fade_distance);
+            }
+            if coverage <= 0.0 {
+                return base_material.clone();
             }
+            // Create a new material with grid color but same properties
+            let grid_color = hex_to_color(line_color).unwrap_or(Color::new(0.0, 0.0, 0.0));
+            let line_material = Material {
+                color: color_to_hex(grid_color),
+                ..base_material.clone()
+            };
+            blend_materials(base_material, &line_material, coverage)
         }
         Texture::Checkerboard { material_b } => {
-            // Use 1x1 world units for checkerboard pattern
-            let checker_u = u.floor() as i32;
-            let checker_v = v.floor() as i32;
-
-            // Use base material for primary squares (even), material_b for alternate squares (odd)
-            if (checker_u + checker_v) % 2 == 0 {
-                base_material.clone()
-            } else {
-                *material_b.clone()
+            // Use 1x1 world units for checkerboard pattern; base material for primary
+            // squares (even), material_b for alternate squares (odd), which may be
+            // textured itself
+            let odd = checker_odd_coverage(u, v, footprint);
+            if odd <= 0.0 {
+                return base_material.clone();
             }
+            let material_b = match &material_b.texture {
+                Some(texture) => apply_texture(texture, u, v, footprint, material_b),
+                None => *material_b.clone(),
+            };
+            blend_materials(base_material, &material_b, odd)
         }
     }
 }
 
-/// Sample a random point on a disk of given radius, centered at origin in local coordinates
-fn sample_disk_point<R: Rng>(rng: &mut R, radius: f64) -> (f64, f64) {
-    // Use rejection sampling to get uniform distribution on disk
-    loop {
-        let x = rng.gen_range(-radius..radius);
-        let y = rng.gen_range(-radius..radius);
-        if x * x + y * y <= radius * radius {
-            return (x, y);
+/// Mix of two materials, `weight` of the way from `a` to `b`
+///
+/// Colors and coefficients are interpolated; the texture, bump and emission come from
+/// whichever material has the larger share.
+fn blend_materials(a: &Material, b: &Material, weight: f64) -> Material {
+    if weight >= 1.0 {
+        return b.clone();
+    }
+    let lerp = |x: f64, y: f64| x + (y - x) * weight;
+    let color_a = hex_to_color(&a.color).unwrap_or(Color::new(1.0, 1.0, 1.0));
+    let color_b = hex_to_color(&b.color).unwrap_or(Color::new(0.0, 0.0, 0.0));
+    let majority = if weight < 0.5 { a } else { b };
+    Material {
+        color: color_to_hex(color_a + (color_b - color_a) * weight),
+        ambient: lerp(a.ambient, b.ambient),
+        diffuse: lerp(a.diffuse, b.diffuse),
+        specular: lerp(a.specular, b.specular),
+        shininess: lerp(a.shininess, b.shininess),
+        reflectivity: match (a.reflectivity, b.reflectivity) {
+            (None, None) => None,
+            (x, y) => Some(lerp(x.unwrap_or(0.0), y.unwrap_or(0.0))),
+        },
+        transparency: match (a.transparency, b.transparency) {
+            (None, None) => None,
+            (x, y) => Some(lerp(x.unwrap_or(0.0), y.unwrap_or(0.0))),
+        },
+        ..majority.clone()
+    }
+}
+
+fn color_to_hex(color: Color) -> String {
+    format!(
+        "#{:02X}{:02X}{:02X}",
+        (color.x * 255.0) as u8,
+        (color.y * 255.0) as u8,
+        (color.z * 255.0) as u8
+    )
+}
+
+/// The material at a hit point, after applying any texture at its coordinates
+pub(crate) fn effective_material(hit_record: &HitRecord, material: &Material) -> Material {
+    match (&material.texture, hit_record.texture_coords) {
+        (Some(texture), Some((u, v))) => apply_texture(
+            texture,
+            u,
+            v,
+            hit_record.texture_footprint.unwrap_or((0.0, 0.0)),
+            material,
+        ),
+        _ => material.clone(),
+    }
+}
+
+/// The hit as lighting sees it, with the normal tilted by the material's bump
+pub(crate) fn shading_hit(hit_record: &HitRecord, material: &Material) -> HitRecord {
+    let mut shading = hit_record.clone();
+    if let Some(bump) = &effective_material(hit_record, material).bump {
+        shading.normal = bump.perturb_normal(hit_record);
+    }
+    shading
+}
+
+/// Ray carrying on through a transparent surface at `hit`, bent by its refractive
+/// index, or mirrored where it can't get out (total internal reflection)
+pub(crate) fn refracted_ray(
+    ray: &Ray,
+    hit: &HitRecord,
+    normal: &Unit<Vec3>,
+    refractive_index: f64,
+) -> Ray {
+    // Hitting the front the ray goes into the material; hitting the back, out of it
+    let eta = if hit.front_face {
+        1.0 / refractive_index
+    } else {
+        refractive_index
+    };
+    match refract(&ray.direction, normal, eta) {
+        Some(direction) => {
+            let mut refracted =
+                Ray::new(hit.point - 0.001 * hit.normal.as_ref(), *direction).with_time(ray.time);
+            refracted.differentials = ray.differentials.map(|d| d.refract(ray, hit, eta));
+            refracted
+        }
+        None => {
+            let direction = reflect(ray.direction.as_ref(), normal);
+            let mut reflected =
+                Ray::new(hit.point + 0.001 * hit.normal.as_ref(), *direction).with_time(ray.time);
+            reflected.differentials = ray.differentials.map(|d| d.reflect(ray, hit));
+            reflected
         }
     }
 }
 
-/// Generate a random point on a disk perpendicular to the light direction
-fn sample_disk_light_point<R: Rng>(
+/// Ray carrying on unbent past a shadow catcher at `hit`
+pub(crate) fn ray_past(ray: &Ray, hit: &HitRecord) -> Ray {
+    let mut past = Ray::new(
+        hit.point + 0.001 * ray.direction.as_ref(),
+        *ray.direction.as_ref(),
+    )
+    .with_time(ray.time);
+    past.differentials = ray.differentials.map(|d| d.refract(ray, hit, 1.0));
+    past
+}
+
+/// Unlit surface color at a hit point, including textures
+pub fn surface_albedo(hit_record: &HitRecord, material: &Material) -> Color {
+    let effective_material = effective_material(hit_record, material);
+    hex_to_color(&effective_material.color).unwrap_or(Color::new(1.0, 1.0, 1.0))
+}
+
+/// Sample a random point in the `index`th of `count` equal-area strata of a disk of
+/// given radius, centered at origin in local coordinates
+fn sample_disk_point<R: Rng>(rng: &mut R, radius: f64, index: u32, count: u32) -> (f64, f64) {
+    let (u, v) = stratified_square(index, count, rng.gen(), rng.gen());
+    let (x, y) = concentric_disk(u, v);
+    (x * radius, y * radius)
+}
+
+/// Generate a random point on a disk perpendicular to the light direction, in the
+/// `index`th of `count` strata
+pub(crate) fn sample_disk_light_point<R: Rng>(
     rng: &mut R,
     light_center: &Point,
     hit_point: &Point,
     diameter: f64,
+    index: u32,
+    count: u32,
 ) -> Point {
     let radius = diameter / 2.0;
 
@@ -90,132 +208,238 @@ fn sample_disk_light_point<R: Rng>(
     let v = Unit::new_normalize(light_dir.cross(u.as_ref()));
 
     // Sample random point on disk
-    let (disk_u, disk_v) = sample_disk_point(rng, radius);
+    let (disk_u, disk_v) = sample_disk_point(rng, radius, index, count);
 
     // Convert to world coordinates
     light_center + disk_u * u.as_ref() + disk_v * v.as_ref()
 }
 
+/// Derive a deterministic RNG seed from a global seed and a hit point
+fn hit_point_seed(seed: u64, point: &Point) -> u64 {
+    seed.wrapping_mul(0x9E3779B97F4A7C15_u64)
+        .wrapping_add(((point.x * 1000.0) as u64).wrapping_mul(0x85EBCA6B))
+        .wrapping_add(((point.y * 1000.0) as u64).wrapping_mul(0xC2B2AE35))
+        .wrapping_add(((point.z * 1000.0) as u64).wrapping_mul(0x6C8E9CF5))
+}
+
+/// Number of points sampled on the disk of a diffuse (area) light without its own
+/// `shadow_samples`
+pub const DEFAULT_SHADOW_SAMPLES: u32 = 16;
+
+/// One point in each of `count` strata of a diffuse light's disk, as seen from the hit
+///
+/// The jitter within each stratum is seeded by the hit point, so shading and the
+/// shadow-only mode draw the same points.
+fn disk_light_samples(
+    light_center: Point,
+    hit_point: Point,
+    diameter: f64,
+    count: u32,
+    seed: u64,
+) -> impl Iterator<Item = Point> {
+    let mut rng = crate::SeededRng::seed_from_u64(hit_point_seed(seed, &hit_point));
+    (0..count).map(move |index| {
+        sample_disk_light_point(&mut rng, &light_center, &hit_point, diameter, index, count)
+    })
+}
+
+/// Share of the light from `target` reaching the hit point: 1 with nothing in the way,
+/// 0 behind anything solid, and in between behind see-through (`opacity`) surfaces
+fn light_through(hit_record: &HitRecord, target: &Point, world: &World) -> f64 {
+    let mut shadow_ray = Ray::new(
+        hit_record.point + 0.001 * hit_record.normal.as_ref(),
+        *target - hit_record.point,
+    )
+    .with_time(hit_record.time);
+    let mut distance = (*target - hit_record.point).magnitude();
+    let mut through = 1.0;
+    while let Some(hit) = time_rays(RayKind::Shadow, || world.hit(&shadow_ray, 0.001, distance)) {
+        // Broken hits count as solid rather than stepping nowhere forever
+        let opacity = world.opacity(&hit);
+        if opacity >= 1.0 || !hit.t.is_finite() {
+            return 0.0;
+        }
+        through *= 1.0 - opacity;
+        distance -= hit.t;
+        shadow_ray = Ray::new(hit.point, *shadow_ray.direction.as_ref()).with_time(hit.time);
+    }
+    through
+}
+
 /// Calculate light contribution from a point light source
+#[allow(clippy::too_many_arguments)]
 fn calculate_point_light_contribution(
     hit_record: &HitRecord,
     material: &Material,
+    light: &Light,
     light_pos: &Point,
     light_color: &Color,
-    light_intensity: f64,
     camera_pos: &Point,
     world: &World,
     material_color: &Color,
 ) -> Color {
-    let light_dir = Unit::new_normalize(*light_pos - hit_record.point);
-
-    // Check for shadows - cast ray from hit point to light
-    let shadow_ray = Ray::new(
-        hit_record.point + 0.001 * hit_record.normal.as_ref(),
-        *light_dir.as_ref(),
-    );
-    let light_distance = (*light_pos - hit_record.point).magnitude();
-
     // If there's an object between the hit point and the light, we're in shadow
-    if world.hit(&shadow_ray, 0.001, light_distance).is_some() {
+    let through = light_through(hit_record, light_pos, world);
+    if through <= 0.0 {
         return Color::new(0.0, 0.0, 0.0);
     }
+    let to_light = *light_pos - hit_record.point;
+    let light_intensity = light.intensity * light.falloff(to_light.magnitude()) * through;
+    let light_dir = Unit::new_normalize(to_light);
+
+    phong_contribution(
+        hit_record,
+        material,
+        &light_dir,
+        light_intensity,
+        light_color,
+        camera_pos,
+        material_color,
+    )
+}
 
-    // Diffuse component
-    let diffuse_strength = hit_record.normal.dot(&light_dir).max(0.0);
+/// Diffuse and specular (Phong model) light from one direction at the given intensity
+fn phong_contribution(
+    hit_record: &HitRecord,
+    material: &Material,
+    light_dir: &Unit<Vec3>,
+    light_intensity: f64,
+    light_color: &Color,
+    camera_pos: &Point,
+    material_color: &Color,
+) -> Color {
+    let (diffuse_strength, spec_strength) = phong_strengths(
+        &hit_record.point,
+        &hit_record.normal,
+        light_dir,
+        camera_pos,
+        material.shininess,
+    );
     let diffuse = material.diffuse
         * diffuse_strength
         * light_intensity
         * light_color.component_mul(material_color);
-
-    // Specular component (Phong model)
-    let specular = if diffuse_strength > 0.0 {
-        let view_dir = Unit::new_normalize(*camera_pos - hit_record.point);
-        let reflect_dir = reflect(&(-light_dir.as_ref()), &hit_record.normal);
-        let spec_strength = view_dir.dot(&reflect_dir).max(0.0).powf(material.shininess);
-        material.specular * spec_strength * light_intensity * light_color
-    } else {
-        Color::new(0.0, 0.0, 0.0)
-    };
+    let specular = material.specular * spec_strength * light_intensity * light_color;
 
     diffuse + specular
 }
 
 /// Calculate light contribution from a diffuse (area) light source
+#[allow(clippy::too_many_arguments)]
 fn calculate_diffuse_light_contribution(
     hit_record: &HitRecord,
     material: &Material,
+    light: &Light,
     light_center: &Point,
     light_color: &Color,
-    light_intensity: f64,
     diameter: f64,
     camera_pos: &Point,
     world: &World,
     material_color: &Color,
     seed: u64,
 ) -> Color {
-    // Number of samples to take on the light disk
-    const SAMPLES: u32 = 16;
-
-    // Create deterministic RNG seeded by hit point coordinates and global seed
-    let light_seed = seed
-        .wrapping_mul(0x9E3779B97F4A7C15_u64)
-        .wrapping_add(((hit_record.point.x * 1000.0) as u64).wrapping_mul(0x85EBCA6B))
-        .wrapping_add(((hit_record.point.y * 1000.0) as u64).wrapping_mul(0xC2B2AE35))
-        .wrapping_add(((hit_record.point.z * 1000.0) as u64).wrapping_mul(0x6C8E9CF5));
-    let mut rng = rand::rngs::StdRng::seed_from_u64(light_seed);
+    let samples = light.shadow_samples.unwrap_or(DEFAULT_SHADOW_SAMPLES);
     let mut total_contribution = Color::new(0.0, 0.0, 0.0);
-    let mut visible_samples = 0;
-
-    for _ in 0..SAMPLES {
-        // Sample a random point on the light disk
-        let sample_point =
-            sample_disk_light_point(&mut rng, light_center, &hit_record.point, diameter);
-
-        let light_dir = Unit::new_normalize(sample_point - hit_record.point);
-        let light_distance = (sample_point - hit_record.point).magnitude();
-
-        // Check for shadows - cast ray from hit point to sampled light point
-        let shadow_ray = Ray::new(
-            hit_record.point + 0.001 * hit_record.normal.as_ref(),
-            *light_dir.as_ref(),
-        );
+    let mut visible_samples = 0.0;
 
+    for sample_point in disk_light_samples(*light_center, hit_record.point, diameter, samples, seed)
+    {
         // If there's an object between the hit point and the light sample, skip this sample
-        if world.hit(&shadow_ray, 0.001, light_distance).is_some() {
+        let through = light_through(hit_record, &sample_point, world);
+        if through <= 0.0 {
             continue;
         }
-
-        visible_samples += 1;
-
-        // Diffuse component
-        let diffuse_strength = hit_record.normal.dot(&light_dir).max(0.0);
-        let diffuse = material.diffuse
-            * diffuse_strength
-            * light_intensity
-            * light_color.component_mul(material_color);
-
-        // Specular component (Phong model)
-        let specular = if diffuse_strength > 0.0 {
-            let view_dir = Unit::new_normalize(*camera_pos - hit_record.point);
-            let reflect_dir = reflect(&(-light_dir.as_ref()), &hit_record.normal);
-            let spec_strength = view_dir.dot(&reflect_dir).max(0.0).powf(material.shininess);
-            material.specular * spec_strength * light_intensity * light_color
-        } else {
-            Color::new(0.0, 0.0, 0.0)
-        };
-
-        total_contribution += diffuse + specular;
+        let to_light = sample_point - hit_record.point;
+        let light_intensity = light.intensity * light.falloff(to_light.magnitude()) * through;
+        let light_dir = Unit::new_normalize(to_light);
+
+        visible_samples += through;
+
+        total_contribution += phong_contribution(
+            hit_record,
+            material,
+            &light_dir,
+            light_intensity,
+            light_color,
+            camera_pos,
+            material_color,
+        );
     }
 
     // Scale the contributions based on visibility - more visible samples means more light received
-    if SAMPLES > 0 {
-        (total_contribution / SAMPLES as f64) * (visible_samples as f64 / SAMPLES as f64)
+    if samples > 0 {
+        (total_contribution / samples as f64) * (visible_samples / samples as f64)
     } else {
         Color::new(0.0, 0.0, 0.0)
     }
 }
 
+/// One point in each of `count` strata of an emissive surface, as seen from the hit
+///
+/// Each point comes with the cosine between the surface normal and the way to the hit,
+/// on whichever side the hit is, and is lifted off the surface toward the hit so the
+/// surface doesn't shadow its own samples. Seeded like `disk_light_samples`.
+fn emitter_samples(
+    emitter: &Emitter,
+    hit_point: Point,
+    count: u32,
+    seed: u64,
+) -> impl Iterator<Item = (Point, f64)> + '_ {
+    let mut rng = crate::SeededRng::seed_from_u64(hit_point_seed(seed, &hit_point));
+    (0..count).filter_map(move |index| {
+        let (u, v) = stratified_square(index, count, rng.gen(), rng.gen());
+        let (point, normal) = emitter.sample(u, v)?;
+        let to_hit = hit_point - point;
+        let facing = normal.dot(&to_hit) / to_hit.magnitude().max(f64::EPSILON);
+        Some((
+            point + 0.001 * facing.signum() * normal.as_ref(),
+            facing.abs(),
+        ))
+    })
+}
+
+/// Calculate light contribution from an emissive surface, including its own glow
+/// when the hit is on it
+#[allow(clippy::too_many_arguments)]
+fn calculate_emitter_contribution(
+    hit_record: &HitRecord,
+    material: &Material,
+    light: &Light,
+    emitter: &Emitter,
+    light_color: &Color,
+    camera_pos: &Point,
+    world: &World,
+    material_color: &Color,
+    seed: u64,
+) -> Color {
+    let mut total_contribution = Color::new(0.0, 0.0, 0.0);
+    if hit_record.material_key() == emitter.key {
+        total_contribution += light.intensity * light_color;
+    }
+
+    // Each sample stands for an equal share of the surface, dimmed as it turns away
+    let samples = light.shadow_samples.unwrap_or(DEFAULT_SHADOW_SAMPLES);
+    for (sample_point, facing) in emitter_samples(emitter, hit_record.point, samples, seed) {
+        let through = light_through(hit_record, &sample_point, world);
+        if through <= 0.0 {
+            continue;
+        }
+        let to_light = sample_point - hit_record.point;
+        let light_intensity =
+            light.intensity * facing * light.falloff(to_light.magnitude()) * through;
+        total_contribution += phong_contribution(
+            hit_record,
+            material,
+            &Unit::new_normalize(to_light),
+            light_intensity / samples as f64,
+            light_color,
+            camera_pos,
+            material_color,
+        );
+    }
+    total_contribution
+}
+
 /// Phong lighting calculation
 pub fn phong_lighting(
     hit_record: &HitRecord,
@@ -227,15 +451,7 @@ pub fn phong_lighting(
     seed: u64,
 ) -> Color {
     // Determine the effective material (possibly modified by texture)
-    let effective_material = if let Some(texture) = &material.texture {
-        if let Some((u, v)) = hit_record.texture_coords {
-            apply_texture(texture, u, v, material)
-        } else {
-            material.clone()
-        }
-    } else {
-        material.clone()
-    };
+    let effective_material = effective_material(hit_record, material);
 
     // Get effective material color
     let material_color =
@@ -252,15 +468,27 @@ pub fn phong_lighting(
         let light_pos = Point::new(light.position[0], light.position[1], light.position[2]);
         let light_color = hex_to_color(&light.color).unwrap_or(Color::new(1.0, 1.0, 1.0));
 
-        // Handle diffuse (area) lights vs point lights
-        let light_contribution = if let Some(diameter) = light.diameter {
+        // Handle emissive surfaces, diffuse (area) lights and point lights
+        let light_contribution = if let Some(emitter) = &light.emitter {
+            calculate_emitter_contribution(
+                hit_record,
+                &effective_material,
+                light,
+                emitter,
+                &light_color,
+                camera_pos,
+                world,
+                &material_color,
+                seed,
+            )
+        } else if let Some(diameter) = light.diameter {
             // Diffuse light - sample multiple points on the disk
             calculate_diffuse_light_contribution(
                 hit_record,
                 &effective_material,
+                light,
                 &light_pos,
                 &light_color,
-                light.intensity,
                 diameter,
                 camera_pos,
                 world,
@@ -272,25 +500,251 @@ pub fn phong_lighting(
             calculate_point_light_contribution(
                 hit_record,
                 &effective_material,
+                light,
                 &light_pos,
                 &light_color,
-                light.intensity,
                 camera_pos,
                 world,
                 &material_color,
             )
         };
 
-        color += light_contribution;
+        // Light it focused onto the surface through glass and mirrors
+        let caustic = match &light.caustics {
+            Some(caustics) => {
+                caustics
+                    .irradiance(&hit_record.point, &hit_record.normal)
+                    .component_mul(&material_color)
+                    * effective_material.diffuse
+            }
+            None => Color::zeros(),
+        };
+
+        // Smoke and steam between the surface and the light dim it
+        color += (light_contribution + caustic)
+            * world.volume_transmittance(&hit_record.point, &light_pos);
     }
 
     color
 }
 
-/// Reflect a vector around a normal
-fn reflect(incident: &Vec3, normal: &Unit<Vec3>) -> Unit<Vec3> {
-    let reflected = incident - 2.0 * incident.dot(normal) * normal.as_ref();
-    Unit::new_normalize(reflected)
+/// Sample a cosine-weighted direction on the hemisphere around a normal
+fn sample_cosine_hemisphere<R: Rng>(rng: &mut R, normal: &Unit<Vec3>) -> Vec3 {
+    let r1: f64 = rng.gen();
+    let r2: f64 = rng.gen();
+    cosine_hemisphere(normal, r1, r2)
+}
+
+/// Estimate ambient occlusion at a hit point
+///
+/// Casts `samples` cosine-distributed rays into the hemisphere around the surface
+/// normal and returns the unoccluded fraction (1.0 = fully open, 0.0 = fully occluded).
+/// Only occluders closer than `radius` count.
+pub fn ambient_occlusion(
+    hit_record: &HitRecord,
+    world: &World,
+    samples: u32,
+    radius: f64,
+    seed: u64,
+) -> f64 {
+    if samples == 0 {
+        return 1.0;
+    }
+
+    let mut rng = crate::SeededRng::seed_from_u64(hit_point_seed(seed, &hit_record.point));
+    let origin = hit_record.point + 0.001 * hit_record.normal.as_ref();
+    let mut occluded = 0;
+
+    for _ in 0..samples {
+        let direction = sample_cosine_hemisphere(&mut rng, &hit_record.normal);
+        let occlusion_ray = Ray::new(origin, direction).with_time(hit_record.time);
+        if time_rays(RayKind::Shadow, || world.hit(&occlusion_ray, 0.001, radius)).is_some() {
+            occluded += 1;
+        }
+    }
+
+    1.0 - occluded as f64 / samples as f64
+}
+
+/// Ray color calculation for ambient occlusion mode
+///
+/// Produces a uniform "clay" shade scaled by hemisphere visibility. Returns the same
+/// depth and normal data as `ray_color_with_data` so outlines still work.
+pub fn ambient_occlusion_color(
+    ray: &Ray,
+    world: &World,
+    camera_pos: &Point,
+    background_color: Color,
+    samples: u32,
+    radius: f64,
+    seed: u64,
+) -> (Color, Option<f64>, Option<Vec3>) {
+    let hit = world.hit(ray, 0.001, f64::INFINITY);
+    ambient_occlusion_shade(
+        hit.as_ref(),
+        world,
+        camera_pos,
+        background_color,
+        samples,
+        radius,
+        seed,
+    )
+}
+
+/// Ambient occlusion shading for an already-traced primary hit
+pub fn ambient_occlusion_shade(
+    hit: Option<&HitRecord>,
+    world: &World,
+    camera_pos: &Point,
+    background_color: Color,
+    samples: u32,
+    radius: f64,
+    seed: u64,
+) -> (Color, Option<f64>, Option<Vec3>) {
+    if let Some(hit) = hit {
+        let visibility = ambient_occlusion(hit, world, samples, radius, seed);
+        let depth = (hit.point - *camera_pos).magnitude();
+        (
+            Color::new(visibility, visibility, visibility),
+            Some(depth),
+            Some(*hit.normal.as_ref()),
+        )
+    } else {
+        (background_color, None, None)
+    }
+}
+
+/// Fraction of the scene's light reaching a hit point, with shadows as the only loss
+///
+/// Each light counts by its intensity; for diffuse lights and emissive surfaces this is
+/// the share of samples that are unblocked, drawn exactly as in shading. 1.0 means
+/// fully lit.
+pub fn shadow_factor(hit_record: &HitRecord, lights: &[Light], world: &World, seed: u64) -> f64 {
+    // Emissive objects that aren't made of triangles glow without lighting anything
+    let casts_light = |light: &&Light| {
+        light
+            .emitter
+            .as_ref()
+            .is_none_or(|emitter| emitter.area() > 0.0)
+    };
+    let total_intensity: f64 = lights
+        .iter()
+        .filter(casts_light)
+        .map(|light| light.intensity)
+        .sum();
+    if total_intensity <= 0.0 {
+        return 1.0;
+    }
+
+    let lit: f64 = lights
+        .iter()
+        .filter(casts_light)
+        .map(|light| {
+            let light_pos = Point::new(light.position[0], light.position[1], light.position[2]);
+            let visibility = match (&light.emitter, light.diameter) {
+                (Some(emitter), _) => {
+                    let samples = light.shadow_samples.unwrap_or(DEFAULT_SHADOW_SAMPLES);
+                    let visible: f64 = emitter_samples(emitter, hit_record.point, samples, seed)
+                        .map(|(sample_point, _)| light_through(hit_record, &sample_point, world))
+                        .sum();
+                    visible / samples.max(1) as f64
+                }
+                (None, Some(diameter)) => {
+                    let samples = light.shadow_samples.unwrap_or(DEFAULT_SHADOW_SAMPLES);
+                    let visible: f64 =
+                        disk_light_samples(light_pos, hit_record.point, diameter, samples, seed)
+                            .map(|sample_point| light_through(hit_record, &sample_point, world))
+                            .sum();
+                    visible / samples.max(1) as f64
+                }
+                (None, None) => light_through(hit_record, &light_pos, world),
+            };
+            light.intensity * visibility
+        })
+        .sum();
+
+    lit / total_intensity
+}
+
+/// Shadow-only shading for an already-traced primary hit: white where lit, black in shadow
+pub fn shadow_shade(
+    hit: Option<&HitRecord>,
+    world: &World,
+    lights: &[Light],
+    camera_pos: &Point,
+    background_color: Color,
+    seed: u64,
+) -> (Color, Option<f64>, Option<Vec3>) {
+    if let Some(hit) = hit {
+        let lit = shadow_factor(hit, lights, world, seed);
+        let depth = (hit.point - *camera_pos).magnitude();
+        (
+            Color::new(lit, lit, lit),
+            Some(depth),
+            Some(*hit.normal.as_ref()),
+        )
+    } else {
+        (background_color, None, None)
+    }
+}
+
+/// Direct lighting for an already-traced primary hit, as if every surface were matte white
+///
+/// Materials are replaced by a plain diffuse white that takes in all ambient light, so
+/// only the light arriving at the surface shows. Reflections and fog are left out.
+#[allow(clippy::too_many_arguments)]
+pub fn lighting_shade(
+    hit: Option<&HitRecord>,
+    world: &World,
+    lights: &[Light],
+    ambient: &AmbientIllumination,
+    camera_pos: &Point,
+    background_color: Color,
+    seed: u64,
+) -> (Color, Option<f64>, Option<Vec3>) {
+    if let Some(hit) = hit {
+        let white = Material {
+            ambient: 1.0,
+            diffuse: 1.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let color = phong_lighting(hit, &white, lights, ambient, camera_pos, world, seed);
+        let depth = (hit.point - *camera_pos).magnitude();
+        (color, Some(depth), Some(*hit.normal.as_ref()))
+    } else {
+        (background_color, None, None)
+    }
+}
+
+/// Preview shading for an already-traced primary hit: the surface's own material lit
+/// directly, without reflections, refraction or fog
+///
+/// Soft shadows are left to the caller, which gives previews lights that cast a single
+/// shadow ray. See-through surfaces and shadow catchers are drawn as plain surfaces.
+#[allow(clippy::too_many_arguments)]
+pub fn preview_shade(
+    hit: Option<&HitRecord>,
+    world: &World,
+    lights: &[Light],
+    ambient: &AmbientIllumination,
+    camera_pos: &Point,
+    background_color: Color,
+    materials: &[Material],
+    seed: u64,
+) -> (Color, Option<f64>, Option<Vec3>) {
+    if let Some(hit) = hit {
+        let material = materials
+            .get(hit.material_key())
+            .cloned()
+            .unwrap_or_default();
+        let bumped = shading_hit(hit, &material);
+        let color = phong_lighting(&bumped, &material, lights, ambient, camera_pos, world, seed);
+        let depth = (hit.point - *camera_pos).magnitude();
+        (color, Some(depth), Some(*hit.normal.as_ref()))
+    } else {
+        (background_color, None, None)
+    }
 }
 
 /// Apply atmospheric fog to a color based on distance
@@ -298,18 +752,12 @@ pub fn apply_fog(color: Color, fog: &Option<Fog>, distance: f64) -> Color {
     if let Some(fog_settings) = fog {
         let fog_color = hex_to_color(&fog_settings.color).unwrap_or(Color::new(0.5, 0.5, 0.5));
 
-        // Linear fog falloff
-        let fog_factor = if distance <= fog_settings.start {
-            0.0
-        } else if distance >= fog_settings.end {
-            1.0
-        } else {
-            (distance - fog_settings.start) / (fog_settings.end - fog_settings.start)
-        };
-
-        // Apply fog density
-        let fog_factor = 1.0 - (-fog_settings.density * fog_factor).exp();
-        let fog_factor = fog_factor.clamp(0.0, 1.0);
+        let fog_factor = fog_factor(
+            distance,
+            fog_settings.start,
+            fog_settings.end,
+            fog_settings.density,
+        );
 
         // Blend original color with fog color
         color * (1.0 - fog_factor) + fog_color * fog_factor
@@ -318,6 +766,229 @@ pub fn apply_fog(color: Color, fog: &Option<Fog>, distance: f64) -> Color {
     }
 }
 
+/// Volumetric fog over the first `distance` of `ray` (infinite for rays that miss), as
+/// the light it scatters towards the ray's origin and the share of the light from
+/// beyond that gets through; None unless the fog is volumetric
+///
+/// The fog fills the same shell around the camera as the flat fog, from `start` to
+/// `end`, with its density spread evenly so that light fades as the flat fog does.
+/// Each of the `steps` points marched along the ray is lit by the ambient light and by
+/// every light it can see, and scatters the fog color's share of it.
+#[allow(clippy::too_many_arguments)]
+fn volumetric_fog(
+    fog: &Option<Fog>,
+    ray: &Ray,
+    distance: f64,
+    world: &World,
+    lights: &[Light],
+    ambient: &AmbientIllumination,
+    camera_pos: &Point,
+    seed: u64,
+) -> Option<(Color, f64)> {
+    let fog = fog.as_ref()?;
+    let volumetric = fog.volumetric.as_ref()?;
+    let nothing = (Color::new(0.0, 0.0, 0.0), 1.0);
+    if fog.end <= fog.start || fog.density <= 0.0 {
+        return Some(nothing);
+    }
+    let extinction = fog.density / (fog.end - fog.start);
+
+    // Where the ray is within `radius` of the camera, as (enter, leave)
+    let from_camera = ray.origin - *camera_pos;
+    let along = from_camera.dot(&ray.direction);
+    let within = |radius: f64| {
+        let discriminant = along * along - (from_camera.norm_squared() - radius * radius);
+        (discriminant >= 0.0).then(|| {
+            let root = discriminant.sqrt();
+            (-along - root, -along + root)
+        })
+    };
+    let Some((_, leave_fog)) = within(fog.end) else {
+        return Some(nothing);
+    };
+    // Skip the clear space around the camera when the ray starts in it
+    let enter_fog = match within(fog.start) {
+        Some((enter, leave)) if enter <= 0.0 => leave.max(0.0),
+        _ => 0.0,
+    };
+    let end = distance.min(leave_fog);
+    if end <= enter_fog {
+        return Some(nothing);
+    }
+
+    let fog_color = hex_to_color(&fog.color).unwrap_or(Color::new(0.5, 0.5, 0.5));
+    let ambient_light =
+        hex_to_color(&ambient.color).unwrap_or(Color::new(1.0, 1.0, 1.0)) * ambient.intensity;
+    let light_colors: Vec<Color> = lights
+        .iter()
+        .map(|light| hex_to_color(&light.color).unwrap_or(Color::new(1.0, 1.0, 1.0)))
+        .collect();
+    let mut rng = crate::SeededRng::seed_from_u64(hit_point_seed(
+        seed,
+        &(ray.origin + ray.direction.as_ref()),
+    ));
+
+    // One jitter for the whole ray keeps the steps evenly spaced without banding. Every
+    // light sees the same draws, so light group passes still add up to the image.
+    let step = (end - enter_fog) / volumetric.steps as f64;
+    let (jitter, jitter_v): (f64, f64) = (rng.gen(), rng.gen());
+    let mut scattered = Color::new(0.0, 0.0, 0.0);
+    let mut transmittance = 1.0;
+    for index in 0..volumetric.steps {
+        let point = ray.at(enter_fog + (index as f64 + jitter) * step);
+        let camera_distance = (point - *camera_pos).magnitude();
+        if camera_distance < fog.start || camera_distance > fog.end {
+            continue;
+        }
+
+        let light_in = ambient_light
+            + in_scattered_light(
+                &point,
+                ray,
+                volumetric.anisotropy,
+                stratified_square(index, volumetric.steps, jitter, jitter_v),
+                world,
+                lights,
+                &light_colors,
+            );
+
+        // Light scattered within this step, dimmed by the fog in front of it
+        let step_transmittance = (-extinction * step).exp();
+        scattered +=
+            fog_color.component_mul(&light_in) * (transmittance * (1.0 - step_transmittance));
+        transmittance *= step_transmittance;
+    }
+    Some((scattered, transmittance))
+}
+
+/// Light from every light that `point` in a medium can see, weighted by the
+/// Henyey-Greenstein phase function for scattering back along `ray`
+///
+/// Emissive surfaces are sampled at the point `sample` of the unit square, so marching
+/// with stratified samples spreads the steps over the surface. Volumes between the point
+/// and a light dim it as they dim surfaces.
+fn in_scattered_light(
+    point: &Point,
+    ray: &Ray,
+    anisotropy: f64,
+    (u, v): (f64, f64),
+    world: &World,
+    lights: &[Light],
+    light_colors: &[Color],
+) -> Color {
+    let mut light_in = Color::new(0.0, 0.0, 0.0);
+    for (light, light_color) in lights.iter().zip(light_colors) {
+        let (target, facing) = match &light.emitter {
+            Some(emitter) => {
+                let Some((sample, normal)) = emitter.sample(u, v) else {
+                    continue;
+                };
+                let to_point = point - sample;
+                let facing = normal.dot(&to_point) / to_point.magnitude().max(f64::EPSILON);
+                (
+                    sample + 0.001 * facing.signum() * normal.as_ref(),
+                    facing.abs(),
+                )
+            }
+            None => (Point::from(light.position), 1.0),
+        };
+        let to_light = target - point;
+        let light_distance = to_light.magnitude();
+        let shadow_ray = Ray::new(*point, to_light).with_time(ray.time);
+        let blocked = time_rays(RayKind::Shadow, || {
+            world.hit(&shadow_ray, 0.001, light_distance - 0.001)
+        })
+        .is_some();
+        if blocked {
+            continue;
+        }
+        let cos_theta = to_light.dot(&ray.direction) / light_distance.max(f64::EPSILON);
+        light_in += light_color
+            * (light.intensity
+                * facing
+                * light.falloff(light_distance)
+                * world.volume_transmittance(point, &target)
+                * henyey_greenstein(anisotropy, cos_theta));
+    }
+    light_in
+}
+
+/// Light scattered towards the ray's origin by the volumes it passes through before
+/// `distance`, and the share of light from beyond that gets through them
+///
+/// None when the scene has no volumes. Each volume the ray crosses is marched with its
+/// own `steps`, lit like volumetric fog, and the volumes are composited front to back;
+/// where volumes overlap, the nearer one is treated as being in front.
+fn volume_scattering(
+    ray: &Ray,
+    distance: f64,
+    world: &World,
+    lights: &[Light],
+    ambient: &AmbientIllumination,
+    seed: u64,
+) -> Option<(Color, f64)> {
+    if world.volumes.is_empty() {
+        return None;
+    }
+    let mut segments: Vec<(&Volume, f64, f64)> = world
+        .volumes
+        .iter()
+        .filter_map(|volume| {
+            let (enter, leave) = volume.segment(ray, 0.0, distance)?;
+            Some((volume, enter, leave))
+        })
+        .collect();
+    let mut scattered = Color::new(0.0, 0.0, 0.0);
+    let mut transmittance = 1.0;
+    if segments.is_empty() {
+        return Some((scattered, transmittance));
+    }
+    segments.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let ambient_light =
+        hex_to_color(&ambient.color).unwrap_or(Color::new(1.0, 1.0, 1.0)) * ambient.intensity;
+    let light_colors: Vec<Color> = lights
+        .iter()
+        .map(|light| hex_to_color(&light.color).unwrap_or(Color::new(1.0, 1.0, 1.0)))
+        .collect();
+    let mut rng = crate::SeededRng::seed_from_u64(hit_point_seed(
+        seed,
+        &(ray.origin + ray.direction.as_ref()),
+    ));
+    // As in volumetric fog, one jitter per ray spaces the steps evenly without banding
+    let (jitter, jitter_v): (f64, f64) = (rng.gen(), rng.gen());
+
+    for (volume, enter, leave) in segments {
+        let step = (leave - enter) / volume.steps as f64;
+        let extinction = volume.absorption + volume.scattering;
+        for index in 0..volume.steps {
+            let point = ray.at(enter + (index as f64 + jitter) * step);
+            let density = volume.density_at(&point);
+            if density <= 0.0 || extinction <= 0.0 {
+                continue;
+            }
+            let light_in = ambient_light
+                + in_scattered_light(
+                    &point,
+                    ray,
+                    volume.anisotropy,
+                    stratified_square(index, volume.steps, jitter, jitter_v),
+                    world,
+                    lights,
+                    &light_colors,
+                );
+
+            // Of the light lost within this step, the scattered share glows
+            let step_transmittance = (-extinction * density * step).exp();
+            let albedo = volume.color * (volume.scattering / extinction);
+            scattered +=
+                albedo.component_mul(&light_in) * (transmittance * (1.0 - step_transmittance));
+            transmittance *= step_transmittance;
+        }
+    }
+    Some((scattered, transmittance))
+}
+
 /// Main ray color calculation
 #[allow(clippy::too_many_arguments)]
 pub fn ray_color(
@@ -328,7 +999,7 @@ pub fn ray_color(
     fog: &Option<Fog>,
     camera_pos: &Point,
     background_color: Color,
-    materials: &std::collections::HashMap<usize, Material>,
+    materials: &[Material],
     max_depth: i32,
     seed: u64,
 ) -> Color {
@@ -357,7 +1028,7 @@ pub fn ray_color_with_data(
     fog: &Option<Fog>,
     camera_pos: &Point,
     background_color: Color,
-    materials: &std::collections::HashMap<usize, Material>,
+    materials: &[Material],
     max_depth: i32,
     camera: Option<&crate::camera::Camera>,
     seed: u64,
@@ -366,69 +1037,245 @@ pub fn ray_color_with_data(
         return (Color::new(0.0, 0.0, 0.0), None, None);
     }
 
-    if let Some(hit) = world.hit(ray, 0.001, f64::INFINITY) {
+    let hit = world.hit(ray, 0.001, f64::INFINITY);
+    shade_hit_with_data(
+        ray,
+        hit.as_ref(),
+        world,
+        lights,
+        ambient,
+        fog,
+        camera_pos,
+        background_color,
+        materials,
+        max_depth,
+        camera,
+        seed,
+    )
+}
+
+/// Shade an already-traced hit (or miss) for `ray`, capturing depth and normal data
+///
+/// Split from `ray_color_with_data` so callers that cache primary hits can skip
+/// the intersection test and only recompute shading.
+#[allow(clippy::too_many_arguments)]
+pub fn shade_hit_with_data(
+    ray: &Ray,
+    hit: Option<&HitRecord>,
+    world: &World,
+    lights: &[Light],
+    ambient: &AmbientIllumination,
+    fog: &Option<Fog>,
+    camera_pos: &Point,
+    background_color: Color,
+    materials: &[Material],
+    max_depth: i32,
+    camera: Option<&crate::camera::Camera>,
+    seed: u64,
+) -> (Color, Option<f64>, Option<Vec3>) {
+    let (direct, indirect, depth, normal) = shade_hit_components(
+        ray,
+        hit,
+        world,
+        lights,
+        ambient,
+        fog,
+        camera_pos,
+        background_color,
+        materials,
+        max_depth,
+        camera,
+        seed,
+    );
+    (direct + indirect, depth, normal)
+}
+
+/// Like `shade_hit_with_data`, but keeps the direct and reflected light apart
+///
+/// Returns `(direct, indirect, depth, normal)`. Direct is the locally lit surface
+/// (or background), indirect is the reflected contribution; they sum to the shaded color.
+#[allow(clippy::too_many_arguments)]
+pub fn shade_hit_components(
+    ray: &Ray,
+    hit: Option<&HitRecord>,
+    world: &World,
+    lights: &[Light],
+    ambient: &AmbientIllumination,
+    fog: &Option<Fog>,
+    camera_pos: &Point,
+    background_color: Color,
+    materials: &[Material],
+    max_depth: i32,
+    camera: Option<&crate::camera::Camera>,
+    seed: u64,
+) -> (Color, Color, Option<f64>, Option<Vec3>) {
+    let black = Color::new(0.0, 0.0, 0.0);
+    if max_depth <= 0 {
+        return (black, black, None, None);
+    }
+
+    if let Some(hit) = hit {
         // Calculate camera-space depth
         let camera_space_depth = (hit.point - *camera_pos).magnitude();
         
         // Get the surface normal in world space
         let world_normal = *hit.normal.as_ref();
         
-        // Get material for this object using the material index from the hit record
+        // Get the material for this hit, which a mesh face can have of its own
         let material = materials
-            .get(&hit.material_index)
+            .get(hit.material_key())
             .cloned()
             .unwrap_or_else(Material::default);
 
+        // Shadow catchers show what is behind them, darkened by the shadows they receive
+        if material.is_shadow_catcher() {
+            let (behind, _, _) = ray_color_with_data(
+                &ray_past(ray, hit),
+                world,
+                lights,
+                ambient,
+                fog,
+                camera_pos,
+                background_color,
+                materials,
+                max_depth,
+                camera,
+                seed,
+            );
+            let lit = shadow_factor(hit, lights, world, seed);
+            let depth = Some(camera_space_depth);
+            return (behind * lit, black, depth, Some(world_normal));
+        }
+
         // Calculate lighting (reuse existing lighting logic)
-        let mut color = phong_lighting(&hit, &material, lights, ambient, camera_pos, world, seed);
+        let shading = shading_hit(hit, &material);
+        let mut direct = phong_lighting(
+            &shading, &material, lights, ambient, camera_pos, world, seed,
+        );
+        let mut indirect = black;
 
-        // Apply fog based on distance from camera
-        let distance = (hit.point - *camera_pos).magnitude();
-        color = apply_fog(color, fog, distance);
+        // Apply fog based on distance from camera, or march through volumetric fog
+        let volume = volumetric_fog(fog, ray, hit.t, world, lights, ambient, camera_pos, seed);
+        if volume.is_none() {
+            let distance = (hit.point - *camera_pos).magnitude();
+            direct = apply_fog(direct, fog, distance);
+        }
 
-        // Handle reflections if material has reflectivity
-        if let Some(reflectivity) = material.reflectivity {
+        // Handle reflections if the material (or its texture at this point) has reflectivity
+        if let Some(reflectivity) = effective_material(hit, &material).reflectivity {
             if reflectivity > 0.0 && max_depth > 1 {
-                let view_dir = Unit::new_normalize(*camera_pos - hit.point);
-                let reflect_dir = reflect(&(-view_dir.as_ref()), &hit.normal);
-                let reflect_ray = Ray::new(
+                // Mirror the incoming ray, which for orthographic cameras and deeper
+                // bounces doesn't come from the camera position
+                let reflect_dir = reflect(ray.direction.as_ref(), &shading.normal);
+                let mut reflect_ray = Ray::new(
                     hit.point + 0.001 * hit.normal.as_ref(),
                     *reflect_dir.as_ref(),
-                );
+                )
+                .with_time(ray.time);
+                reflect_ray.differentials = ray.differentials.map(|d| d.reflect(ray, hit));
 
                 // For reflected rays, we only care about color, not depth/normal data
-                let (reflected_color, _, _) = ray_color_with_data(
-                    &reflect_ray,
-                    world,
-                    lights,
-                    ambient,
-                    fog,
-                    camera_pos,
-                    background_color,
-                    materials,
-                    max_depth - 1,
-                    camera,
-                    seed,
-                );
+                let (reflected_color, _, _) = time_rays(RayKind::Reflection, || {
+                    ray_color_with_data(
+                        &reflect_ray,
+                        world,
+                        lights,
+                        ambient,
+                        fog,
+                        camera_pos,
+                        background_color,
+                        materials,
+                        max_depth - 1,
+                        camera.filter(|camera| camera.grid_reflections),
+                        seed,
+                    )
+                });
+
+                direct *= 1.0 - reflectivity;
+                indirect = reflected_color * reflectivity;
+            }
+        }
 
-                color = color * (1.0 - reflectivity) + reflected_color * reflectivity;
+        // Transparent materials show what is behind them, bent and tinted by their color
+        let surface = effective_material(hit, &material);
+        if let Some(transparency) = surface.transparency {
+            if transparency > 0.0 && max_depth > 1 {
+                let refract_ray =
+                    refracted_ray(ray, hit, &shading.normal, surface.refractive_index());
+                let (refracted_color, _, _) = time_rays(RayKind::Reflection, || {
+                    ray_color_with_data(
+                        &refract_ray,
+                        world,
+                        lights,
+                        ambient,
+                        fog,
+                        camera_pos,
+                        background_color,
+                        materials,
+                        max_depth - 1,
+                        camera.filter(|camera| camera.grid_reflections),
+                        seed,
+                    )
+                });
+                let tint = hex_to_color(&surface.color).unwrap_or(Color::new(1.0, 1.0, 1.0));
+
+                direct *= 1.0 - transparency;
+                indirect = indirect * (1.0 - transparency)
+                    + refracted_color.component_mul(&tint) * transparency;
             }
         }
 
-        (color, Some(camera_space_depth), Some(world_normal))
+        // See-through materials cover only part of what is straight behind them
+        let opacity = material.opacity();
+        if opacity < 1.0 && max_depth > 1 {
+            let (behind, _, _) = ray_color_with_data(
+                &ray_past(ray, hit),
+                world,
+                lights,
+                ambient,
+                fog,
+                camera_pos,
+                background_color,
+                materials,
+                max_depth - 1,
+                camera,
+                seed,
+            );
+            direct = direct * opacity + behind * (1.0 - opacity);
+            indirect *= opacity;
+        }
+
+        // Volumes in front of the surface, then volumetric fog, dim the surface and its
+        // reflection alike, and add their glow
+        let volumes = volume_scattering(ray, hit.t, world, lights, ambient, seed);
+        for (scattered, transmittance) in volumes.into_iter().chain(volume) {
+            direct = direct * transmittance + scattered;
+            indirect *= transmittance;
+        }
+
+        (direct, indirect, Some(camera_space_depth), Some(world_normal))
     } else {
         // Background pixel - check for grid background
-        let background = if let Some(camera) = camera {
-            if let Some(grid_color) = camera.get_grid_color(ray) {
-                grid_color
-            } else {
-                background_color
-            }
-        } else {
-            background_color
+        let mut background = match camera {
+            Some(camera) => camera.grid_background(ray, background_color),
+            None => background_color,
         };
+        let volumes = volume_scattering(ray, f64::INFINITY, world, lights, ambient, seed);
+        let fog = volumetric_fog(
+            fog,
+            ray,
+            f64::INFINITY,
+            world,
+            lights,
+            ambient,
+            camera_pos,
+            seed,
+        );
+        for (scattered, transmittance) in volumes.into_iter().chain(fog) {
+            background = background * transmittance + scattered;
+        }
         
-        (background, None, None)
+        (background, black, None, None)
     }
 }
 
@@ -442,7 +1289,7 @@ pub fn ray_color_with_camera(
     fog: &Option<Fog>,
     camera_pos: &Point,
     background_color: Color,
-    materials: &std::collections::HashMap<usize, Material>,
+    materials: &[Material],
     max_depth: i32,
     camera: Option<&crate::camera::Camera>,
     seed: u64,
@@ -452,56 +1299,154 @@ pub fn ray_color_with_camera(
     }
 
     if let Some(hit) = world.hit(ray, 0.001, f64::INFINITY) {
-        // Get material for this object using the material index from the hit record
+        // Get the material for this hit, which a mesh face can have of its own
         let material = materials
-            .get(&hit.material_index)
+            .get(hit.material_key())
             .cloned()
             .unwrap_or_else(Material::default);
 
+        // Shadow catchers show what is behind them, darkened by the shadows they receive
+        if material.is_shadow_catcher() {
+            let behind = ray_color_with_camera(
+                &ray_past(ray, &hit),
+                world,
+                lights,
+                ambient,
+                fog,
+                camera_pos,
+                background_color,
+                materials,
+                max_depth,
+                camera,
+                seed,
+            );
+            return behind * shadow_factor(&hit, lights, world, seed);
+        }
+
         // Calculate lighting
-        let mut color = phong_lighting(&hit, &material, lights, ambient, camera_pos, world, seed);
+        let shading = shading_hit(&hit, &material);
+        let mut color = phong_lighting(
+            &shading, &material, lights, ambient, camera_pos, world, seed,
+        );
 
-        // Apply fog based on distance from camera
-        let distance = (hit.point - *camera_pos).magnitude();
-        color = apply_fog(color, fog, distance);
+        // Apply fog based on distance from camera, or march through volumetric fog
+        let volume = volumetric_fog(fog, ray, hit.t, world, lights, ambient, camera_pos, seed);
+        if volume.is_none() {
+            let distance = (hit.point - *camera_pos).magnitude();
+            color = apply_fog(color, fog, distance);
+        }
 
-        // Handle reflections if material has reflectivity
-        if let Some(reflectivity) = material.reflectivity {
+        // Handle reflections if the material (or its texture at this point) has reflectivity
+        if let Some(reflectivity) = effective_material(&hit, &material).reflectivity {
             if reflectivity > 0.0 && max_depth > 1 {
-                let view_dir = Unit::new_normalize(*camera_pos - hit.point);
-                let reflect_dir = reflect(&(-view_dir.as_ref()), &hit.normal);
-                let reflect_ray = Ray::new(
+                // Mirror the incoming ray, which for orthographic cameras and deeper
+                // bounces doesn't come from the camera position
+                let reflect_dir = reflect(ray.direction.as_ref(), &shading.normal);
+                let mut reflect_ray = Ray::new(
                     hit.point + 0.001 * hit.normal.as_ref(),
                     *reflect_dir.as_ref(),
-                );
-
-                let reflected_color = ray_color_with_camera(
-                    &reflect_ray,
-                    world,
-                    lights,
-                    ambient,
-                    fog,
-                    camera_pos,
-                    background_color,
-                    materials,
-                    max_depth - 1,
-                    camera,
-                    seed,
-                );
+                )
+                .with_time(ray.time);
+                reflect_ray.differentials = ray.differentials.map(|d| d.reflect(ray, &hit));
+
+                let reflected_color = time_rays(RayKind::Reflection, || {
+                    ray_color_with_camera(
+                        &reflect_ray,
+                        world,
+                        lights,
+                        ambient,
+                        fog,
+                        camera_pos,
+                        background_color,
+                        materials,
+                        max_depth - 1,
+                        camera.filter(|camera| camera.grid_reflections),
+                        seed,
+                    )
+                });
 
                 color = color * (1.0 - reflectivity) + reflected_color * reflectivity;
             }
         }
 
-        color
-    } else {
-        // Ray missed all objects - check for grid background if camera is orthographic
-        if let Some(camera) = camera {
-            if let Some(grid_color) = camera.get_grid_color(ray) {
-                return grid_color;
+        // Transparent materials show what is behind them, bent and tinted by their color
+        let surface = effective_material(&hit, &material);
+        if let Some(transparency) = surface.transparency {
+            if transparency > 0.0 && max_depth > 1 {
+                let refract_ray =
+                    refracted_ray(ray, &hit, &shading.normal, surface.refractive_index());
+                let refracted_color = time_rays(RayKind::Reflection, || {
+                    ray_color_with_camera(
+                        &refract_ray,
+                        world,
+                        lights,
+                        ambient,
+                        fog,
+                        camera_pos,
+                        background_color,
+                        materials,
+                        max_depth - 1,
+                        camera.filter(|camera| camera.grid_reflections),
+                        seed,
+                    )
+                });
+                let tint = hex_to_color(&surface.color).unwrap_or(Color::new(1.0, 1.0, 1.0));
+
+                color = color * (1.0 - transparency)
+                    + refracted_color.component_mul(&tint) * transparency;
             }
         }
-        background_color
+
+        // See-through materials cover only part of what is straight behind them
+        let opacity = material.opacity();
+        if opacity < 1.0 && max_depth > 1 {
+            let behind = ray_color_with_camera(
+                &ray_past(ray, &hit),
+                world,
+                lights,
+                ambient,
+                fog,
+                camera_pos,
+                background_color,
+                materials,
+                max_depth - 1,
+                camera,
+                seed,
+            );
+            color = color * opacity + behind * (1.0 - opacity);
+        }
+
+        // Volumes in front of the surface, then volumetric fog
+        let volumes = volume_scattering(ray, hit.t, world, lights, ambient, seed);
+        volumes
+            .into_iter()
+            .chain(volume)
+            .fold(color, |color, (scattered, transmittance)| {
+                color * transmittance + scattered
+            })
+    } else {
+        // Ray missed all objects - check for grid background if camera is orthographic
+        let background = match camera {
+            Some(camera) => camera.grid_background(ray, background_color),
+            None => background_color,
+        };
+        let volumes = volume_scattering(ray, f64::INFINITY, world, lights, ambient, seed);
+        let fog = volumetric_fog(
+            fog,
+            ray,
+            f64::INFINITY,
+            world,
+            lights,
+            ambient,
+            camera_pos,
+            seed,
+        );
+        volumes
+            .into_iter()
+            .chain(fog)
+            .fold(background, |background, (scattered, transmittance)| {
+                background * transmittance + scattered
+            })
     }
 }
 
@@ -516,6 +1461,14 @@ mod tests {
         assert!((color.x - 1.0).abs() < 1e-6);
         assert!((color.y - 0.0).abs() < 1e-6);
         assert!((color.z - 0.0).abs() < 1e-6);
+
+        // 16 bits per channel, as decoded sRGB colors are stored
+        let color = hex_to_color("#FFFF80000000").unwrap();
+        assert!((color.y - 0x8000 as f64 / 65535.0).abs() < 1e-12);
+        let mut decoded = "#808080".to_string();
+        crate::scene::decode_srgb_hex(&mut decoded).unwrap();
+        assert_eq!(decoded, "#374237423742");
+        assert!(hex_to_color("#FFF").is_err());
     }
 
     #[test]
@@ -537,12 +1490,12 @@ mod tests {
 
     #[test]
     fn test_sample_disk_point() {
-        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let mut rng = crate::SeededRng::seed_from_u64(42);
         let radius = 2.0;
 
         // Sample multiple points and verify they're within the disk
-        for _ in 0..100 {
-            let (x, y) = sample_disk_point(&mut rng, radius);
+        for index in 0..100 {
+            let (x, y) = sample_disk_point(&mut rng, radius, index, 100);
             let distance_from_center = (x * x + y * y).sqrt();
             assert!(
                 distance_from_center <= radius,
@@ -556,15 +1509,15 @@ mod tests {
 
     #[test]
     fn test_sample_disk_light_point() {
-        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let mut rng = crate::SeededRng::seed_from_u64(42);
         let light_center = Point::new(0.0, 5.0, 0.0);
         let hit_point = Point::new(0.0, 0.0, 0.0);
         let diameter = 2.0;
 
         // Sample multiple points on the light disk
-        for _ in 0..100 {
+        for index in 0..100 {
             let sample_point =
-                sample_disk_light_point(&mut rng, &light_center, &hit_point, diameter);
+                sample_disk_light_point(&mut rng, &light_center, &hit_point, diameter, index, 100);
 
             // The sampled point should be roughly the same distance from hit point as the light center
             let center_distance = (light_center - hit_point).magnitude();
@@ -581,6 +1534,295 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sample_cosine_hemisphere() {
+        let mut rng = crate::SeededRng::seed_from_u64(7);
+        let normal = Unit::new_normalize(Vec3::new(0.0, 0.0, 1.0));
+
+        for _ in 0..100 {
+            let direction = sample_cosine_hemisphere(&mut rng, &normal);
+            assert!((direction.magnitude() - 1.0).abs() < 1e-9);
+            assert!(direction.dot(&normal) >= 0.0, "Sample must lie in the hemisphere");
+        }
+    }
+
+    #[test]
+    fn test_ambient_occlusion() {
+        use crate::ray::{Plane, Sphere};
+
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+        // An open floor is fully visible
+        let mut world = World::new();
+        world.add(Box::new(Plane::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+            0,
+        )));
+        let hit = world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(ambient_occlusion(&hit, &world, 32, 1.0, 0), 1.0);
+
+        // A floor hit inside a sphere is fully occluded
+        world.add(Box::new(Sphere {
+            center: Point::new(0.0, 0.0, 0.0),
+            radius: 0.5,
+            material_color: Color::new(1.0, 1.0, 1.0),
+            material_index: 1,
+        }));
+        let inside_ray = Ray::new(Point::new(0.0, 0.0, 0.1), Vec3::new(0.0, 0.0, -1.0));
+        let hit = world.hit(&inside_ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(ambient_occlusion(&hit, &world, 32, 1.0, 0), 0.0);
+
+        // Occluders beyond the radius are ignored
+        assert_eq!(ambient_occlusion(&hit, &world, 32, 0.01, 0), 1.0);
+    }
+
+    #[test]
+    fn test_stratified_shadow_samples() {
+        use crate::ray::{Cube, Plane};
+
+        // A slab over x < 0 hides exactly half of the light's disk from the origin
+        let mut world = World::new();
+        world.add(Box::new(Plane::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+            0,
+        )));
+        world.add(Box::new(Cube::new(
+            Point::new(-50.0, 0.0, 5.0),
+            Vec3::new(100.0, 100.0, 0.1),
+            Color::new(1.0, 1.0, 1.0),
+            1,
+        )));
+        let ray = Ray::new(Point::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let mut light = Light {
+            position: [0.0, 0.0, 10.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: Some(2.0),
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        };
+
+        // Each stratum lands on one side of the edge, so every seed sees exactly half
+        for seed in 0..20 {
+            assert_eq!(shadow_factor(&hit, &[light.clone()], &world, seed), 0.5);
+        }
+        // Counts that don't split evenly still average out to half
+        light.shadow_samples = Some(7);
+        let mean = (0..200)
+            .map(|seed| shadow_factor(&hit, &[light.clone()], &world, seed))
+            .sum::<f64>()
+            / 200.0;
+        assert!((mean - 0.5).abs() < 0.03, "{}", mean);
+    }
+
+    #[test]
+    fn test_light_attenuation() {
+        use crate::ray::Plane;
+        use crate::scene::Attenuation;
+
+        let mut world = World::new();
+        world.add(Box::new(Plane::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+            0,
+        )));
+        let ray = Ray::new(Point::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let material = Material {
+            ambient: 0.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let camera_pos = Point::new(0.0, 0.0, 1.0);
+        let brightness = |light: &Light| {
+            phong_lighting(
+                &hit,
+                &material,
+                std::slice::from_ref(light),
+                &AmbientIllumination::default(),
+                &camera_pos,
+                &world,
+                0,
+            )
+            .x
+        };
+
+        let mut light: Light = serde_json::from_str(
+            r##"{"position": [0, 0, 2], "color": "#FFFFFF", "intensity": 1.0, "diameter": null,
+                "attenuation": {"type": "inverse_square"}}"##,
+        )
+        .unwrap();
+        let near = brightness(&light);
+        light.position[2] = 4.0;
+        assert!((near / brightness(&light) - 4.0).abs() < 1e-9);
+        // Area lights fall off per sample, about as much as a point light at their center
+        light.diameter = Some(0.5);
+        let far_disk = brightness(&light);
+        light.position[2] = 2.0;
+        assert!((brightness(&light) / far_disk - 4.0).abs() < 0.2);
+
+        // Unset coefficients default to 1 / (1 + 0·d + 0·d²)
+        light.diameter = None;
+        light.attenuation =
+            serde_json::from_str(r#"{"type": "quadratic", "quadratic": 0.25}"#).unwrap();
+        assert_eq!(
+            light.attenuation,
+            Some(Attenuation::Quadratic {
+                constant: 1.0,
+                linear: 0.0,
+                quadratic: 0.25
+            })
+        );
+        assert!((light.falloff(2.0) - 0.5).abs() < 1e-12);
+        assert!((brightness(&light) * 2.0 - near * 4.0).abs() < 1e-9);
+        light.attenuation = Some(Attenuation::None);
+        assert!((brightness(&light) - near * 4.0).abs() < 1e-9);
+
+        for invalid in [
+            r#"{"type": "quadratic", "constant": 0}"#,
+            r#"{"type": "quadratic", "linear": -1}"#,
+        ] {
+            let attenuation: Attenuation = serde_json::from_str(invalid).unwrap();
+            assert!(attenuation.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_volumetric_fog() {
+        use crate::ray::Sphere;
+
+        // A ball above the +x axis shades it from a light overhead
+        let mut world = World::new();
+        world.add(Box::new(Sphere {
+            center: Point::new(3.0, 0.0, 5.0),
+            radius: 1.5,
+            material_color: Color::new(1.0, 1.0, 1.0),
+            material_index: 0,
+        }));
+        let light: Light = serde_json::from_str(
+            r##"{"position": [0, 0, 10], "color": "#FFFFFF", "intensity": 1.0, "diameter": null}"##,
+        )
+        .unwrap();
+        let mut fog: Fog = serde_json::from_str(
+            r##"{"color": "#FFFFFF", "density": 1.0, "start": 0, "end": 10}"##,
+        )
+        .unwrap();
+        let camera_pos = Point::origin();
+        let dark = AmbientIllumination {
+            intensity: 0.0,
+            ..AmbientIllumination::default()
+        };
+        let march = |fog: &Option<Fog>, direction: Vec3, distance: f64| {
+            let ray = Ray::new(camera_pos, direction);
+            volumetric_fog(
+                fog,
+                &ray,
+                distance,
+                &world,
+                std::slice::from_ref(&light),
+                &dark,
+                &camera_pos,
+                7,
+            )
+        };
+        assert!(march(&Some(fog.clone()), Vec3::x(), 5.0).is_none());
+
+        fog.volumetric = Some(serde_json::from_str("{}").unwrap());
+        let fog = Some(fog);
+        // Light fades through volumetric fog as through the flat fog
+        let (_, transmittance) = march(&fog, Vec3::x(), 5.0).unwrap();
+        assert!((transmittance - (-0.5f64).exp()).abs() < 1e-9);
+        let (_, transmittance) = march(&fog, Vec3::x(), f64::INFINITY).unwrap();
+        assert!((transmittance - (-1.0f64).exp()).abs() < 1e-9);
+
+        // The shaded side scatters less light than the open one
+        let (shaded, _) = march(&fog, Vec3::x(), f64::INFINITY).unwrap();
+        let (open, _) = march(&fog, -Vec3::x(), f64::INFINITY).unwrap();
+        assert!(shaded.x > 0.0 && shaded.x < 0.8 * open.x);
+
+        let invalid: Fog = serde_json::from_str(
+            r##"{"color": "#FFFFFF", "density": 1.0, "start": 0, "end": 10,
+                "volumetric": {"anisotropy": 1.0}}"##,
+        )
+        .unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_volume_scattering() {
+        use crate::scene::Object;
+
+        let volume = |json: &str| {
+            serde_json::from_str::<Object>(json)
+                .unwrap()
+                .volume()
+                .map(Option::unwrap)
+        };
+        let light: Light = serde_json::from_str(
+            r##"{"position": [5, 0, 10], "color": "#FFFFFF", "intensity": 1.0, "diameter": null}"##,
+        )
+        .unwrap();
+        let dark = AmbientIllumination {
+            intensity: 0.0,
+            ..AmbientIllumination::default()
+        };
+        let march = |world: &World, direction: Vec3| {
+            let ray = Ray::new(Point::origin(), direction);
+            let lights = std::slice::from_ref(&light);
+            volume_scattering(&ray, f64::INFINITY, world, lights, &dark, 3)
+        };
+
+        let mut world = World::new();
+        assert!(march(&world, Vec3::x()).is_none());
+
+        // A 2-unit box of steam ahead dims what is behind it and glows in the light
+        world.add_volume(
+            volume(
+                r#"{"kind": "volume", "center": [5, 0, 0], "size": [2, 2, 2],
+                    "density": 0.5, "absorption": 0.5, "scattering": 0.5}"#,
+            )
+            .unwrap(),
+        );
+        let (glow, transmittance) = march(&world, Vec3::x()).unwrap();
+        assert!((transmittance - (-1.0f64).exp()).abs() < 1e-9);
+        assert!(glow.x > 0.0 && glow.x == glow.z);
+        assert_eq!(march(&world, Vec3::y()).unwrap(), (Color::zeros(), 1.0));
+        // Surfaces below it are lit through it
+        let below =
+            world.volume_transmittance(&Point::new(5.0, 0.0, -5.0), &Point::new(5.0, 0.0, 10.0));
+        assert!((below - (-1.0f64).exp()).abs() < 1e-9);
+
+        // Purely absorbing smoke only darkens
+        world.volumes[0].scattering = 0.0;
+        world.volumes[0].absorption = 1.0;
+        let (glow, transmittance) = march(&world, Vec3::x()).unwrap();
+        assert!((transmittance - (-1.0f64).exp()).abs() < 1e-9);
+        assert_eq!(glow, Color::zeros());
+
+        let invalid = |field: &str| {
+            volume(&format!(
+                r#"{{"kind": "volume", "center": [0, 0, 0], "size": [1, 1, 1], {}}}"#,
+                field
+            ))
+            .is_err()
+        };
+        assert!(invalid(r#""density": -1"#));
+        assert!(invalid(r#""anisotropy": 1"#));
+        assert!(invalid(r#""steps": 0"#));
+        assert!(invalid(r#""color": "white""#));
+        assert!(!invalid(r#""scattering": 2"#));
+    }
+
     #[test]
     fn test_checkerboard_texture() {
         // Create a secondary material with different properties
@@ -592,6 +1834,12 @@ mod tests {
             shininess: 16.0,
             reflectivity: None,
             texture: None,
+            bump: None,
+            emission: None,
+            transparency: None,
+            refractive_index: None,
+            shadow_catcher: None,
+            opacity: None,
         };
 
         let texture = Texture::Checkerboard {
@@ -606,41 +1854,120 @@ mod tests {
             shininess: 32.0,
             reflectivity: None,
             texture: None,
+            bump: None,
+            emission: None,
+            transparency: None,
+            refractive_index: None,
+            shadow_catcher: None,
+            opacity: None,
         };
 
         // Test checkerboard pattern - should alternate between base_material and material_b
         // At (0.0, 0.0): floor(0) + floor(0) = 0, 0 % 2 = 0 -> base_material (red)
-        let result = apply_texture(&texture, 0.0, 0.0, &base_material);
+        let result = apply_texture(&texture, 0.0, 0.0, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#FF0000");
         assert_eq!(result.shininess, 32.0); // Should use base material properties
         assert_eq!(result.ambient, 0.1);
         assert_eq!(result.diffuse, 0.8);
 
         // At (1.0, 0.0): floor(1) + floor(0) = 1, 1 % 2 = 1 -> material_b (blue)
-        let result = apply_texture(&texture, 1.0, 0.0, &base_material);
+        let result = apply_texture(&texture, 1.0, 0.0, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#0000FF");
         assert_eq!(result.shininess, 16.0); // Should use material_b properties
         assert_eq!(result.ambient, 0.2);
         assert_eq!(result.diffuse, 0.6);
 
         // At (0.0, 1.0): floor(0) + floor(1) = 1, 1 % 2 = 1 -> material_b (blue)
-        let result = apply_texture(&texture, 0.0, 1.0, &base_material);
+        let result = apply_texture(&texture, 0.0, 1.0, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#0000FF");
         assert_eq!(result.shininess, 16.0);
 
         // At (1.0, 1.0): floor(1) + floor(1) = 2, 2 % 2 = 0 -> base_material (red)
-        let result = apply_texture(&texture, 1.0, 1.0, &base_material);
+        let result = apply_texture(&texture, 1.0, 1.0, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#FF0000");
         assert_eq!(result.shininess, 32.0);
 
         // Test with fractional coordinates
         // At (0.7, 0.3): floor(0.7) + floor(0.3) = 0 + 0 = 0, 0 % 2 = 0 -> base_material
-        let result = apply_texture(&texture, 0.7, 0.3, &base_material);
+        let result = apply_texture(&texture, 0.7, 0.3, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#FF0000");
 
         // At (1.2, 0.8): floor(1.2) + floor(0.8) = 1 + 0 = 1, 1 % 2 = 1 -> material_b
-        let result = apply_texture(&texture, 1.2, 0.8, &base_material);
+        let result = apply_texture(&texture, 1.2, 0.8, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#0000FF");
+
+        // Alternate squares can carry their own reflectivity and texture
+        let texture = Texture::Checkerboard {
+            material_b: Box::new(Material {
+                reflectivity: Some(0.4),
+                texture: Some(Texture::Grid {
+                    line_color: "#00FF00".to_string(),
+                    line_width: 0.1,
+                    cell_size: 1.0,
+                    fade_distance: None,
+                }),
+                ..material_b
+            }),
+        };
+        let result = apply_texture(&texture, 1.5, 0.5, (0.0, 0.0), &base_material);
+        assert_eq!(
+            (result.color.as_str(), result.reflectivity),
+            ("#0000FF", Some(0.4))
+        );
+        let result = apply_texture(&texture, 1.01, 0.5, (0.0, 0.0), &base_material);
+        assert_eq!(result.color, "#00FF00");
+        assert_eq!(
+            apply_texture(&texture, 0.5, 0.5, (0.0, 0.0), &base_material).reflectivity,
+            None
+        );
+    }
+
+    #[test]
+    fn test_texture_filtering() {
+        let base_material = Material {
+            color: "#FFFFFF".to_string(),
+            ..Material::default()
+        };
+        let grid = Texture::Grid {
+            line_color: "#000000".to_string(),
+            line_width: 0.1,
+            cell_size: 1.0,
+            fade_distance: None,
+        };
+        // A footprint of many cells averages the lines in; a tiny one keeps them sharp
+        let result = apply_texture(&grid, 0.5, 0.5, (20.0, 20.0), &base_material);
+        let color = hex_to_color(&result.color).unwrap();
+        assert!((color.x - 0.81).abs() < 0.01);
+        let result = apply_texture(&grid, 0.5, 0.5, (0.01, 0.01), &base_material);
+        assert_eq!(result.color, "#FFFFFF");
+
+        // Faded lines are half strength halfway out, and gone past the fade distance
+        let faded = Texture::Grid {
+            line_color: "#000000".to_string(),
+            line_width: 0.1,
+            cell_size: 1.0,
+            fade_distance: Some(10.0),
+        };
+        let result = apply_texture(&faded, 5.0, 0.5, (0.0, 0.0), &base_material);
+        let color = hex_to_color(&result.color).unwrap();
+        assert!((color.x - 0.5).abs() < 0.01);
+        let result = apply_texture(&faded, 12.0, 0.5, (0.0, 0.0), &base_material);
+        assert_eq!(result.color, "#FFFFFF");
+
+        let checker = Texture::Checkerboard {
+            material_b: Box::new(Material {
+                color: "#000000".to_string(),
+                diffuse: 0.2,
+                reflectivity: Some(0.6),
+                ..base_material.clone()
+            }),
+        };
+        // Straddling the edge of two squares gives half of each
+        let result = apply_texture(&checker, 1.0, 0.5, (0.5, 0.0), &base_material);
+        let color = hex_to_color(&result.color).unwrap();
+        assert!((color.x - 0.5).abs() < 0.01);
+        assert!((result.diffuse - (base_material.diffuse + 0.2) / 2.0).abs() < 1e-12);
+        assert!((result.reflectivity.unwrap() - 0.3).abs() < 1e-12);
     }
 
     #[test]
@@ -649,6 +1976,7 @@ mod tests {
             line_color: "#FF0000".to_string(),
             line_width: 0.1,
             cell_size: 1.0,
+            fade_distance: None,
         };
 
         let base_material = Material {
@@ -659,15 +1987,21 @@ mod tests {
             shininess: 10.0,
             reflectivity: None,
             texture: None,
+            bump: None,
+            emission: None,
+            transparency: None,
+            refractive_index: None,
+            shadow_catcher: None,
+            opacity: None,
         };
 
         // Test that grid texture still works
         // At (0.0, 0.0) we should be on a grid line
-        let result = apply_texture(&texture, 0.0, 0.0, &base_material);
+        let result = apply_texture(&texture, 0.0, 0.0, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#FF0000"); // Should be grid line color
 
         // At (0.5, 0.5) we should NOT be on a grid line
-        let result = apply_texture(&texture, 0.5, 0.5, &base_material);
+        let result = apply_texture(&texture, 0.5, 0.5, (0.0, 0.0), &base_material);
         assert_eq!(result.color, "#FFFFFF"); // Should be base material color
     }
 }
@@ -0,0 +1,198 @@
+use nalgebra::Matrix4;
+
+use crate::camera::Camera;
+use crate::mesh::Mesh;
+use crate::scene::{LineWidthUnit, MeshLod, Object, Point};
+
+/// Picks which of a mesh's `lods` to draw from how large the mesh looks on screen, and
+/// sizes lines whose width is given in pixels
+///
+/// Measures each mesh through every camera a world is built for, so a level is only
+/// drawn where it is small in all of them.
+pub struct LodSelector {
+    cameras: Vec<Camera>,
+    width: f64,
+    height: f64,
+    full_detail: bool,
+}
+
+impl LodSelector {
+    /// Selector for images `width` by `height` pixels through `cameras`
+    pub fn new(cameras: &[crate::scene::Camera], width: u32, height: u32) -> Result<Self, String> {
+        let aspect_ratio = width as f64 / height as f64;
+        let cameras = cameras
+            .iter()
+            .map(|camera| Camera::from_config(camera, aspect_ratio))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            cameras,
+            width: width as f64,
+            height: height as f64,
+            full_detail: false,
+        })
+    }
+
+    /// Draw every mesh whole, keeping the selector only for sizing lines
+    pub fn with_full_detail(mut self) -> Self {
+        self.full_detail = true;
+        self
+    }
+
+    /// Longest side in pixels of the screen rectangle around `points`, the largest
+    /// through any camera
+    ///
+    /// None when a point can't be projected: behind a perspective camera, or through a
+    /// fisheye or equirectangular lens.
+    pub fn screen_size(&self, points: &[Point]) -> Option<f64> {
+        let mut size: f64 = 0.0;
+        for camera in &self.cameras {
+            let mut min = [f64::INFINITY; 2];
+            let mut max = [f64::NEG_INFINITY; 2];
+            for point in points {
+                let (u, v) = camera.project(point)?;
+                min = [min[0].min(u), min[1].min(v)];
+                max = [max[0].max(u), max[1].max(v)];
+            }
+            size = size
+                .max((max[0] - min[0]) * self.width)
+                .max((max[1] - min[1]) * self.height);
+        }
+        Some(size)
+    }
+
+    /// World size at `point` of a pixel, the largest through any camera, so lines sized
+    /// from it are at least as wide as asked in every view
+    pub fn pixel_size(&self, point: &Point) -> f64 {
+        (self.cameras.iter())
+            .map(|camera| camera.pixel_size(point, self.width as u32))
+            .fold(0.0, f64::max)
+    }
+
+    /// Whether any of `objects` is a mesh with levels to pick from
+    pub fn needed<'a>(objects: impl IntoIterator<Item = &'a Object>) -> bool {
+        (objects.into_iter())
+            .any(|object| matches!(object, Object::Mesh { lods, .. } if !lods.is_empty()))
+    }
+
+    /// Whether any of `objects` is a line with its width in pixels
+    pub fn needed_for_lines<'a>(objects: impl IntoIterator<Item = &'a Object>) -> bool {
+        objects.into_iter().any(|object| {
+            matches!(
+                object,
+                Object::Line {
+                    width_unit: LineWidthUnit::Pixels,
+                    ..
+                }
+            )
+        })
+    }
+
+    /// The mesh to draw for `mesh`, placed by `transform`: the coarsest loaded level
+    /// whose `max_pixels` the mesh's size on screen is within, or `mesh` itself
+    pub fn pick<'a>(
+        &self,
+        mesh: &'a Mesh,
+        lods: &'a [MeshLod],
+        transform: Option<&Matrix4<f64>>,
+    ) -> &'a Mesh {
+        if self.full_detail {
+            return mesh;
+        }
+        let (min, max) = mesh.bounds();
+        let corners: Vec<Point> = (0..8)
+            .map(|corner| {
+                let pick = |axis: usize| match corner >> axis & 1 {
+                    0 => min[axis],
+                    _ => max[axis],
+                };
+                let point = Point::new(pick(0), pick(1), pick(2));
+                match transform {
+                    Some(matrix) => {
+                        Point::from_homogeneous(matrix * point.to_homogeneous()).unwrap_or(point)
+                    }
+                    None => point,
+                }
+            })
+            .collect();
+        let Some(size) = self.screen_size(&corners) else {
+            return mesh;
+        };
+        lods.iter()
+            .filter(|lod| size <= lod.max_pixels)
+            .filter_map(|lod| Some((lod.max_pixels, lod.mesh_data.as_ref()?)))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(mesh, |(_, level)| level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Vec3;
+
+    fn lod(max_pixels: f64, triangles: usize) -> MeshLod {
+        let mut mesh = Mesh::placeholder_box();
+        mesh.triangles.truncate(triangles);
+        MeshLod {
+            filename: format!("box-{}.stl", triangles),
+            max_pixels,
+            mesh_data: Some(mesh),
+        }
+    }
+
+    #[test]
+    fn test_pick_by_screen_size() {
+        let mut mesh: Object = serde_json::from_str(
+            r##"{"kind": "mesh", "filename": "box.stl", "transform": null,
+                "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1},
+                "lods": [{"filename": "box-lod.stl", "max_pixels": 30}]}"##,
+        )
+        .unwrap();
+        assert!(LodSelector::needed([&mesh]));
+        if let Object::Mesh { lods, .. } = &mut mesh {
+            lods.clear();
+        }
+        assert!(!LodSelector::needed([&mesh]));
+
+        let camera: crate::scene::Camera = serde_json::from_str(
+            r#"{"kind": "perspective", "position": [0, -10, 0], "target": [0, 0, 0], "up": [0, 0, 1],
+                "fov": 90, "width": 1, "height": 1}"#,
+        )
+        .unwrap();
+        let selector = LodSelector::new(&[camera], 200, 200).unwrap();
+
+        // 10 units away the 90 degree view is 20 units across, so a unit is 10 pixels
+        let size = selector.screen_size(&[Point::new(-0.5, 0.0, 0.0), Point::new(0.5, 0.0, 0.0)]);
+        assert!((size.unwrap() - 10.0).abs() < 1e-9);
+        let mesh = Mesh::placeholder_box();
+        let lods = [lod(100.0, 6), lod(30.0, 2), lod(10.0, 1)];
+        assert_eq!(selector.pick(&mesh, &lods, None).triangles.len(), 2);
+
+        // Brought closer or scaled up it gets finer levels, then the full mesh
+        let closer = Matrix4::new_translation(&Vec3::new(0.0, -7.0, 0.0));
+        assert_eq!(
+            selector.pick(&mesh, &lods, Some(&closer)).triangles.len(),
+            6
+        );
+        let scaled = Matrix4::new_scaling(10.0);
+        let full = mesh.triangles.len();
+        assert_eq!(
+            selector.pick(&mesh, &lods, Some(&scaled)).triangles.len(),
+            full
+        );
+
+        // Levels that failed to load are passed over
+        let unloaded = [MeshLod {
+            mesh_data: None,
+            ..lod(30.0, 2)
+        }];
+        assert_eq!(selector.pick(&mesh, &unloaded, None).triangles.len(), full);
+
+        // Behind the camera there's no size to go by, so the mesh is drawn whole
+        let behind = Matrix4::new_translation(&Vec3::new(0.0, -20.0, 0.0));
+        assert_eq!(
+            selector.pick(&mesh, &lods, Some(&behind)).triangles.len(),
+            full
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt;
+
+use crate::scene::{Material, Object, Scene, Texture};
+
+/// Largest shininess worth using; past this highlights shrink below a pixel
+pub const MAX_SHININESS: f64 = 10000.0;
+
+/// A material setting that can blow out to white or shade implausibly
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialWarning {
+    /// Object holding the material, as its name or `objects[index]`, followed by
+    /// `face_materials[index]` for a mesh face material
+    pub object: String,
+    /// What is wrong, in terms of the values the scene gave
+    pub message: String,
+}
+
+impl fmt::Display for MaterialWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "material of {}: {}", self.object, self.message)
+    }
+}
+
+impl Scene {
+    /// Check every object's material, including checkerboard squares and mesh faces
+    ///
+    /// Reflectivity blends between the surface's own shading and what it reflects,
+    /// so only ambient + diffuse + specular count towards the energy budget of 1.
+    pub fn material_warnings(&self) -> Vec<MaterialWarning> {
+        let mut warnings = Vec::new();
+        for (index, object) in self.objects.iter().enumerate() {
+            for (slot, material) in object.materials().into_iter().enumerate() {
+                let label = label(object, index, slot);
+                inspect(&mut material.clone(), false, &label, &mut warnings);
+            }
+        }
+        warnings
+    }
+
+    /// Fix what `material_warnings` reports, returning the warnings for the materials
+    /// that changed
+    ///
+    /// Negative coefficients become 0, ambient, diffuse and specular are scaled down
+    /// together to sum to 1, and reflectivity, transparency, opacity and shininess are
+    /// clamped into range.
+    pub fn normalize_materials(&mut self) -> Vec<MaterialWarning> {
+        let mut warnings = Vec::new();
+        for (index, object) in self.objects.iter_mut().enumerate() {
+            let labels: Vec<String> = (0..object.materials().len())
+                .map(|slot| label(object, index, slot))
+                .collect();
+            for (material, label) in object.materials_mut().into_iter().zip(labels) {
+                inspect(material, true, &label, &mut warnings);
+            }
+        }
+        warnings
+    }
+}
+
+/// Where the `slot`th of `object.materials()` is, for messages
+fn label(object: &Object, index: usize, slot: usize) -> String {
+    let object = match object.name() {
+        Some(name) => format!("'{}'", name),
+        None => format!("objects[{}]", index),
+    };
+    match slot {
+        0 => object,
+        slot => format!("{} face_materials[{}]", object, slot - 1),
+    }
+}
+
+fn inspect(material: &mut Material, fix: bool, object: &str, warnings: &mut Vec<MaterialWarning>) {
+    let mut warn = |message: String| {
+        warnings.push(MaterialWarning {
+            object: object.to_string(),
+            message,
+        })
+    };
+
+    for (name, value) in [
+        ("ambient", &mut material.ambient),
+        ("diffuse", &mut material.diffuse),
+        ("specular", &mut material.specular),
+    ] {
+        if *value < 0.0 {
+            warn(format!("{} is negative ({})", name, value));
+            if fix {
+                *value = 0.0;
+            }
+        }
+    }
+
+    let total = material.ambient.max(0.0) + material.diffuse.max(0.0) + material.specular.max(0.0);
+    if total > 1.0 + 1e-9 {
+        warn(format!(
+            "ambient + diffuse + specular is {:.2}, so fully lit surfaces can blow out to white",
+            total
+        ));
+        if fix {
+            material.ambient /= total;
+            material.diffuse /= total;
+            material.specular /= total;
+        }
+    }
+
+    if let Some(reflectivity) = &mut material.reflectivity {
+        if !(0.0..=1.0).contains(reflectivity) {
+            warn(format!("reflectivity {} is outside 0 to 1", reflectivity));
+            if fix {
+                *reflectivity = reflectivity.clamp(0.0, 1.0);
+            }
+        }
+    }
+
+    if let Some(transparency) = &mut material.transparency {
+        if !(0.0..=1.0).contains(transparency) {
+            warn(format!("transparency {} is outside 0 to 1", transparency));
+            if fix {
+                *transparency = transparency.clamp(0.0, 1.0);
+            }
+        }
+    }
+
+    if let Some(opacity) = &mut material.opacity {
+        if !(0.0..=1.0).contains(opacity) {
+            warn(format!("opacity {} is outside 0 to 1", opacity));
+            if fix {
+                *opacity = opacity.clamp(0.0, 1.0);
+            }
+        }
+    }
+
+    if material.shininess < 1.0 {
+        warn(format!(
+            "shininess {} is below 1, so highlights spread over the whole surface",
+            material.shininess
+        ));
+        if fix {
+            material.shininess = 1.0;
+        }
+    } else if material.shininess > MAX_SHININESS {
+        warn(format!(
+            "shininess {} is above {}, so highlights are too small to see",
+            material.shininess, MAX_SHININESS
+        ));
+        if fix {
+            material.shininess = MAX_SHININESS;
+        }
+    }
+
+    if let Some(Texture::Checkerboard { material_b }) = &mut material.texture {
+        inspect(
+            material_b,
+            fix,
+            &format!("{} (material_b)", object),
+            warnings,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_material_warnings_and_normalization() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+            "objects": [
+                {"kind": "sphere", "center": [0, 0, 0], "radius": 1,
+                    "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.6, "specular": 0.3, "shininess": 32}},
+                {"kind": "sphere", "name": "hot", "center": [2, 0, 0], "radius": 1,
+                    "material": {"color": "#FFFFFF", "ambient": 0.4, "diffuse": 1.0, "specular": 0.6, "shininess": 0.5,
+                        "reflectivity": 1.5}},
+                {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1],
+                    "material": {"color": "#FFFFFF", "ambient": -0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10,
+                        "texture": {"type": "checkerboard", "material_b": {"color": "#000000", "ambient": 0.1,
+                            "diffuse": 0.7, "specular": 0.2, "shininess": 20000}}}}
+            ],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+        }"##,
+        )
+        .unwrap();
+
+        let warnings = scene.material_warnings();
+        let messages: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(warnings.len(), 5, "{:#?}", messages);
+        assert!(messages[0].starts_with("material of 'hot': ambient + diffuse + specular is 2.00"));
+        assert!(messages[1].contains("reflectivity 1.5"));
+        assert!(messages[2].contains("shininess 0.5"));
+        assert_eq!(warnings[3].object, "objects[2]");
+        assert!(messages[3].contains("ambient is negative"));
+        assert_eq!(warnings[4].object, "objects[2] (material_b)");
+        // Checking leaves the scene alone
+        assert_eq!(scene.material_warnings(), warnings);
+
+        assert_eq!(scene.normalize_materials(), warnings);
+        assert!(scene.material_warnings().is_empty());
+        let hot = scene.objects[1].material().unwrap();
+        assert!((hot.ambient - 0.2).abs() < 1e-12);
+        assert!((hot.diffuse - 0.5).abs() < 1e-12);
+        assert!((hot.specular - 0.3).abs() < 1e-12);
+        assert_eq!((hot.reflectivity, hot.shininess), (Some(1.0), 1.0));
+        assert_eq!(scene.objects[2].material().unwrap().ambient, 0.0);
+    }
+}
@@ -0,0 +1,623 @@
+//! Distances and clearances between objects and points, for checking assemblies
+
+use nalgebra::Matrix3;
+
+use crate::object_stats::label;
+use crate::overlap::{segment_hit, Face, Solid};
+use crate::scene::{Point, Scene, Vec3};
+
+/// Segments around spheres and discs when measuring: enough that the polygons stay
+/// within 0.01% of the radius of the true shape
+const MEASURE_SEGMENTS: usize = 256;
+
+/// Faces in each leaf of a `Bvh`
+const LEAF_FACES: usize = 4;
+
+/// Directions clearances are measured along, with their names
+pub const CLEARANCE_DIRECTIONS: [(&str, [f64; 3]); 6] = [
+    ("+x", [1.0, 0.0, 0.0]),
+    ("-x", [-1.0, 0.0, 0.0]),
+    ("+y", [0.0, 1.0, 0.0]),
+    ("-y", [0.0, -1.0, 0.0]),
+    ("+z", [0.0, 0.0, 1.0]),
+    ("-z", [0.0, 0.0, -1.0]),
+];
+
+/// One end of a measurement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// Index into `scene.objects`
+    Object(usize),
+    /// Point in scene coordinates
+    Point(Point),
+}
+
+impl Target {
+    /// A point written as `x,y,z`, or the name of one of the scene's objects
+    pub fn parse(scene: &Scene, text: &str) -> Result<Target, String> {
+        let coordinates: Result<Vec<f64>, _> =
+            text.split(',').map(|value| value.trim().parse()).collect();
+        if let Ok([x, y, z]) = coordinates.as_deref() {
+            return Ok(Target::Point(Point::new(*x, *y, *z)));
+        }
+        match scene
+            .objects
+            .iter()
+            .position(|object| object.name() == Some(text))
+        {
+            Some(index) => Ok(Target::Object(index)),
+            None => {
+                let names: Vec<&str> = scene.objects.iter().filter_map(|o| o.name()).collect();
+                match names.is_empty() {
+                    true => Err(format!(
+                        "Unknown object '{}'; the scene has no named objects, so give a point as x,y,z",
+                        text
+                    )),
+                    false => Err(format!(
+                        "Unknown object '{}'. Valid options are: {}, or a point as x,y,z",
+                        text,
+                        names.join(", ")
+                    )),
+                }
+            }
+        }
+    }
+
+    /// The object's label, or the point's coordinates
+    pub fn describe(&self, scene: &Scene) -> String {
+        match self {
+            Target::Object(index) => label(scene, *index),
+            Target::Point(point) => format_point(point),
+        }
+    }
+
+    fn solid(&self, scene: &Scene) -> Result<Solid, String> {
+        let solid = match self {
+            Target::Point(point) => Solid::from_faces(vec![Face::new([*point; 3])], false),
+            Target::Object(index) => {
+                let object = scene
+                    .objects
+                    .get(*index)
+                    .ok_or_else(|| format!("No object at index {}", index))?;
+                Solid::of(scene, object, MEASURE_SEGMENTS)?
+            }
+        };
+        solid.ok_or_else(|| format!("{} has no finite surface to measure", self.describe(scene)))
+    }
+}
+
+/// How close two targets come, and how far the first can move along each axis before
+/// it meets the second
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub from: Target,
+    pub to: Target,
+    /// Closest approach in scene units; 0 where the targets meet
+    pub distance: f64,
+    /// Points on `from` and `to` that are `distance` apart
+    pub closest: (Point, Point),
+    /// Whether the targets touch, cross, or one lies inside the other
+    pub touching: bool,
+    /// Distance `from` can travel along each of `CLEARANCE_DIRECTIONS` before it meets
+    /// `to`, None if it never does
+    pub clearances: [Option<f64>; 6],
+}
+
+impl Measurement {
+    /// The closest approach on one line, and the clearances on a second unless the
+    /// targets already meet
+    pub fn describe(&self, scene: &Scene) -> String {
+        let (from, to) = (self.from.describe(scene), self.to.describe(scene));
+        if self.touching {
+            return format!(
+                "{} and {} meet at {}",
+                from,
+                to,
+                format_point(&self.closest.0)
+            );
+        }
+        let clearances: Vec<String> = CLEARANCE_DIRECTIONS
+            .iter()
+            .zip(&self.clearances)
+            .map(|((name, _), clearance)| match clearance {
+                Some(distance) => format!("{} {:.3}", name, distance),
+                None => format!("{} clear", name),
+            })
+            .collect();
+        format!(
+            "{} to {}: {:.3} apart, closest at {} and {}\nClearance moving {}: {}",
+            from,
+            to,
+            self.distance,
+            format_point(&self.closest.0),
+            format_point(&self.closest.1),
+            from,
+            clearances.join(", ")
+        )
+    }
+}
+
+/// Measure from one target to another in a loaded `scene`
+///
+/// Objects are measured as the surfaces `find_overlaps` checks, with spheres and discs
+/// as 256-sided polygons: planes, floor grids and volumes can't be measured. Spheres,
+/// cubes, meshes and instances are solids, so a target inside one touches it.
+/// Clearances cast rays along each axis from every vertex of one target at the faces
+/// of the other, and sweep their edges past each other.
+pub fn measure(scene: &Scene, from: &Target, to: &Target) -> Result<Measurement, String> {
+    if from == to {
+        return Err(format!(
+            "Can't measure {} against itself",
+            from.describe(scene)
+        ));
+    }
+    let (a, b) = (from.solid(scene)?, to.solid(scene)?);
+    let (a_tree, b_tree) = (Bvh::new(&a), Bvh::new(&b));
+
+    let (mut distance, mut closest) = closest_approach(&a, &a_tree, &b, &b_tree);
+    if distance > 0.0 {
+        // Without meeting surfaces, one target can still lie wholly inside the other
+        let inside = [(&a, &b), (&b, &a)]
+            .into_iter()
+            .map(|(inner, outer)| (inner.faces[0].vertices[0], outer))
+            .find(|(vertex, outer)| outer.closed && outer.contains(vertex));
+        if let Some((vertex, _)) = inside {
+            (distance, closest) = (0.0, (vertex, vertex));
+        }
+    }
+
+    let touching = distance == 0.0;
+    let clearances = match touching {
+        true => [Some(0.0); 6],
+        false => CLEARANCE_DIRECTIONS
+            .map(|(_, direction)| clearance(&a, &a_tree, &b, &b_tree, &Vec3::from(direction))),
+    };
+    Ok(Measurement {
+        from: *from,
+        to: *to,
+        distance,
+        closest,
+        touching,
+        clearances,
+    })
+}
+
+fn format_point(point: &Point) -> String {
+    // Values that round to zero print as "0.000" rather than "-0.000"
+    let point = point.map(|value| if value.abs() < 5e-4 { 0.0 } else { value });
+    format!("({:.3}, {:.3}, {:.3})", point.x, point.y, point.z)
+}
+
+/// Nearest points of two surfaces and the distance between them
+fn closest_approach(a: &Solid, a_tree: &Bvh, b: &Solid, b_tree: &Bvh) -> (f64, (Point, Point)) {
+    let mut best = (
+        f64::INFINITY,
+        (a.faces[0].vertices[0], b.faces[0].vertices[0]),
+    );
+    let mut stack = vec![(0, 0)];
+    while let Some((i, j)) = stack.pop() {
+        let (m, n) = (&a_tree.nodes[i], &b_tree.nodes[j]);
+        if box_gap(m, n) >= best.0 {
+            continue;
+        }
+        match split(a_tree, i, b_tree, j) {
+            Some(pairs) => {
+                // Nearer pair pushed last, so it is searched first
+                let gaps = pairs.map(|(i, j)| box_gap(&a_tree.nodes[i], &b_tree.nodes[j]));
+                match gaps[0] <= gaps[1] {
+                    true => stack.extend([pairs[1], pairs[0]]),
+                    false => stack.extend(pairs),
+                }
+            }
+            None => {
+                for fa in a_tree.leaf_faces(m) {
+                    for fb in b_tree.leaf_faces(n) {
+                        let (va, vb) = (&a.faces[fa].vertices, &b.faces[fb].vertices);
+                        let (p, q) = triangle_closest(va, vb);
+                        let distance = (q - p).norm();
+                        if distance < best.0 {
+                            best = (distance, (p, q));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+/// How far `a` moves along the unit `direction` before meeting `b`, if it ever does
+fn clearance(a: &Solid, a_tree: &Bvh, b: &Solid, b_tree: &Bvh, direction: &Vec3) -> Option<f64> {
+    let mut best = f64::INFINITY;
+    let mut stack = vec![(0, 0)];
+    while let Some((i, j)) = stack.pop() {
+        let (m, n) = (&a_tree.nodes[i], &b_tree.nodes[j]);
+        if !sweep_start(m, n, direction).is_some_and(|start| start < best) {
+            continue;
+        }
+        match split(a_tree, i, b_tree, j) {
+            Some(pairs) => stack.extend(pairs),
+            None => {
+                for fa in a_tree.leaf_faces(m) {
+                    for fb in b_tree.leaf_faces(n) {
+                        let (va, vb) = (&a.faces[fa].vertices, &b.faces[fb].vertices);
+                        if let Some(t) = triangle_sweep(va, vb, direction) {
+                            best = best.min(t);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    best.is_finite().then_some(best)
+}
+
+/// Bounding volume hierarchy over a solid's faces, so pairs of faces too far apart to
+/// matter are skipped a box at a time
+struct Bvh {
+    nodes: Vec<Node>,
+    faces: Vec<usize>, // Face indices, each node's a contiguous run
+}
+
+struct Node {
+    min: Point,
+    max: Point,
+    faces: (usize, usize),            // Start and end in `Bvh::faces`
+    children: Option<(usize, usize)>, // None for leaves
+}
+
+impl Bvh {
+    fn new(solid: &Solid) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            faces: (0..solid.faces.len()).collect(),
+        };
+        tree.build(solid, 0, solid.faces.len());
+        tree
+    }
+
+    /// Add the node for faces `start..end`, split at the median along its longest side
+    fn build(&mut self, solid: &Solid, start: usize, end: usize) -> usize {
+        let faces = &mut self.faces[start..end];
+        let first = &solid.faces[faces[0]];
+        let (min, max) = faces
+            .iter()
+            .fold((first.min, first.max), |(min, max), &face| {
+                (
+                    min.inf(&solid.faces[face].min),
+                    max.sup(&solid.faces[face].max),
+                )
+            });
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            min,
+            max,
+            faces: (start, end),
+            children: None,
+        });
+        if end - start > LEAF_FACES {
+            let axis = (max - min).imax();
+            let center = |face: &usize| solid.faces[*face].min[axis] + solid.faces[*face].max[axis];
+            let middle = (end - start) / 2;
+            faces.select_nth_unstable_by(middle, |a, b| center(a).total_cmp(&center(b)));
+            let left = self.build(solid, start, start + middle);
+            let right = self.build(solid, start + middle, end);
+            self.nodes[index].children = Some((left, right));
+        }
+        index
+    }
+
+    fn leaf_faces<'a>(&'a self, node: &Node) -> impl Iterator<Item = usize> + 'a {
+        self.faces[node.faces.0..node.faces.1].iter().copied()
+    }
+}
+
+/// Pairs of nodes to visit instead of `a.nodes[i]` and `b.nodes[j]`: the children of
+/// the larger one that has any, each with the other node; None if both are leaves
+fn split(a: &Bvh, i: usize, b: &Bvh, j: usize) -> Option<[(usize, usize); 2]> {
+    let (m, n) = (&a.nodes[i], &b.nodes[j]);
+    let size = |node: &Node| (node.max - node.min).norm_squared();
+    match (m.children, n.children) {
+        (Some((left, right)), None) => Some([(left, j), (right, j)]),
+        (Some((left, right)), Some(_)) if size(m) >= size(n) => Some([(left, j), (right, j)]),
+        (_, Some((left, right))) => Some([(i, left), (i, right)]),
+        (None, None) => None,
+    }
+}
+
+/// Distance between two nodes' boxes, a lower bound on that between their faces
+fn box_gap(m: &Node, n: &Node) -> f64 {
+    let gap = |axis: usize| {
+        (n.min[axis] - m.max[axis])
+            .max(m.min[axis] - n.max[axis])
+            .max(0.0)
+    };
+    Vec3::new(gap(0), gap(1), gap(2)).norm()
+}
+
+/// First distance along `direction` at which box `m`, moving, overlaps box `n`
+fn sweep_start(m: &Node, n: &Node, direction: &Vec3) -> Option<f64> {
+    let (mut start, mut end) = (0.0f64, f64::INFINITY);
+    for axis in 0..3 {
+        // Moved by t along the axis, m overlaps n while low <= t <= high
+        let (low, high) = (n.min[axis] - m.max[axis], n.max[axis] - m.min[axis]);
+        let step = direction[axis];
+        if step == 0.0 {
+            if low > 0.0 || high < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let (t0, t1) = (low / step, high / step);
+        start = start.max(t0.min(t1));
+        end = end.min(t0.max(t1));
+    }
+    (start <= end).then_some(start)
+}
+
+/// Nearest points of two triangles, the same point twice if they cross
+fn triangle_closest(a: &[Point; 3], b: &[Point; 3]) -> (Point, Point) {
+    for (edges, triangle) in [(a, b), (b, a)] {
+        for i in 0..3 {
+            if let Some(point) = segment_hit(&edges[i], &edges[(i + 1) % 3], triangle) {
+                return (point, point);
+            }
+        }
+    }
+    let mut candidates = Vec::with_capacity(15);
+    for vertex in a {
+        candidates.push((*vertex, closest_on_triangle(vertex, b)));
+    }
+    for vertex in b {
+        candidates.push((closest_on_triangle(vertex, a), *vertex));
+    }
+    for i in 0..3 {
+        for j in 0..3 {
+            candidates.push(closest_on_segments(
+                (&a[i], &a[(i + 1) % 3]),
+                (&b[j], &b[(j + 1) % 3]),
+            ));
+        }
+    }
+    candidates
+        .into_iter()
+        .min_by(|(p, q), (r, s)| (q - p).norm_squared().total_cmp(&(s - r).norm_squared()))
+        .expect("triangles have vertices")
+}
+
+/// Point of `triangle` nearest to `point`, by the Voronoi region it falls in
+/// (Ericson, Real-Time Collision Detection, 5.1.5)
+fn closest_on_triangle(point: &Point, triangle: &[Point; 3]) -> Point {
+    let [a, b, c] = triangle;
+    let (ab, ac) = (b - a, c - a);
+    let ap = point - a;
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+    let bp = point - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = point - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let area = va + vb + vc;
+    if area <= 0.0 {
+        // A sliver with no inside: the nearest point is on an edge
+        return [(a, b), (b, c), (c, a)]
+            .map(|edge| closest_on_segments((point, point), edge).1)
+            .into_iter()
+            .min_by(|p, q| (p - point).norm().total_cmp(&(q - point).norm()))
+            .expect("triangles have edges");
+    }
+    a + ab * (vb / area) + ac * (vc / area)
+}
+
+/// Nearest points of two segments, one on each (Ericson, 5.1.9)
+fn closest_on_segments(first: (&Point, &Point), second: (&Point, &Point)) -> (Point, Point) {
+    let (d1, d2) = (first.1 - first.0, second.1 - second.0);
+    let r = first.0 - second.0;
+    let (a, e, f) = (d1.norm_squared(), d2.norm_squared(), d2.dot(&r));
+    let (s, t) = if a <= 0.0 && e <= 0.0 {
+        (0.0, 0.0)
+    } else if a <= 0.0 {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(&r);
+        if e <= 0.0 {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(&d2);
+            let denominator = a * e - b * b;
+            let s = match denominator > 0.0 {
+                true => ((b * f - c * e) / denominator).clamp(0.0, 1.0),
+                false => 0.0,
+            };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                ((-c / a).clamp(0.0, 1.0), 0.0)
+            } else if t > 1.0 {
+                (((b - c) / a).clamp(0.0, 1.0), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (first.0 + d1 * s, second.0 + d2 * t)
+}
+
+/// How far triangle `a` moves along `direction` before meeting triangle `b`: its
+/// vertices run into `b`, `b`'s vertices into it, or an edge of each crosses
+fn triangle_sweep(a: &[Point; 3], b: &[Point; 3], direction: &Vec3) -> Option<f64> {
+    let mut best: Option<f64> = None;
+    let mut add = |t: Option<f64>| {
+        if let Some(t) = t {
+            best = Some(best.map_or(t, |best| best.min(t)));
+        }
+    };
+    for vertex in a {
+        add(ray_hit(vertex, direction, b));
+    }
+    for vertex in b {
+        add(ray_hit(vertex, &-direction, a));
+    }
+    for i in 0..3 {
+        for j in 0..3 {
+            add(edge_sweep(
+                (&a[i], &a[(i + 1) % 3]),
+                (&b[j], &b[(j + 1) % 3]),
+                direction,
+            ));
+        }
+    }
+    best
+}
+
+/// Distance along `direction` from `origin` to `triangle`, if the ray meets it
+fn ray_hit(origin: &Point, direction: &Vec3, triangle: &[Point; 3]) -> Option<f64> {
+    let (edge1, edge2) = (triangle[1] - triangle[0], triangle[2] - triangle[0]);
+    let p = direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() <= 1e-12 * edge1.norm() * edge2.norm() {
+        return None; // Edge-on to the ray
+    }
+    let s = origin - triangle[0];
+    let u = s.dot(&p) / determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let v = direction.dot(&q) / determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(&q) / determinant;
+    (t >= 0.0).then_some(t)
+}
+
+/// Distance along `direction` the first segment moves before crossing the second, if
+/// they aren't parallel to each other or to the motion
+fn edge_sweep(first: (&Point, &Point), second: (&Point, &Point), direction: &Vec3) -> Option<f64> {
+    // first.0 + s·e1 + t·direction = second.0 + u·e2
+    let (e1, e2) = (first.1 - first.0, second.1 - second.0);
+    let inverse = Matrix3::from_columns(&[e1, -e2, *direction]).try_inverse()?;
+    let solution = inverse * (second.0 - first.0);
+    let (s, u, t) = (solution.x, solution.y, solution.z);
+    ((0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&u) && t >= 0.0).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(objects: &str) -> Scene {
+        Scene::from_json_str(&format!(
+            r##"{{
+                "camera": {{"kind": "ortho", "position": [0, -10, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4}},
+                "objects": [{}],
+                "lights": [],
+                "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 1}}}}
+            }}"##,
+            objects
+        ))
+        .unwrap()
+    }
+
+    fn cube(name: &str, center: [f64; 3], size: [f64; 3]) -> String {
+        format!(
+            r##"{{"kind": "cube", "name": "{}", "center": {:?}, "size": {:?},
+                "material": {{"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.8, "specular": 0, "shininess": 1}}}}"##,
+            name, center, size
+        )
+    }
+
+    #[test]
+    fn test_measure_objects() {
+        // b sits 1.5 right of a and 0.5 higher; c crosses a
+        let scene = scene(
+            &[
+                cube("a", [0.0, 0.0, 0.0], [2.0, 2.0, 2.0]),
+                cube("b", [3.5, 0.0, 0.5], [2.0, 2.0, 2.0]),
+                cube("c", [1.0, 1.0, 1.0], [1.0, 1.0, 1.0]),
+            ]
+            .join(","),
+        );
+        let target = |text: &str| Target::parse(&scene, text).unwrap();
+        let measurement = measure(&scene, &target("a"), &target("b")).unwrap();
+        assert!((measurement.distance - 1.5).abs() < 1e-9);
+        assert!((measurement.closest.0.x - 1.0).abs() < 1e-9);
+        assert!((measurement.closest.1.x - 2.5).abs() < 1e-9);
+        assert!(!measurement.touching);
+        // Only moving right brings a into b
+        let clearances = measurement.clearances;
+        assert!((clearances[0].unwrap() - 1.5).abs() < 1e-9);
+        assert_eq!(clearances[1..], [None; 5]);
+        assert!(measurement
+            .describe(&scene)
+            .starts_with("'a' (cube) to 'b' (cube): 1.500 apart"));
+
+        let crossing = measure(&scene, &target("a"), &target("c")).unwrap();
+        assert!(crossing.touching && crossing.distance == 0.0);
+        assert_eq!(crossing.clearances, [Some(0.0); 6]);
+
+        // Points measure to the nearest surface, or touch solids they are inside
+        let point = measure(&scene, &target("3, 4, 0"), &target("a")).unwrap();
+        assert!((point.distance - (2.0f64 * 2.0 + 3.0 * 3.0).sqrt()).abs() < 1e-9);
+        assert!(
+            measure(&scene, &target("0.5,0,0"), &target("a"))
+                .unwrap()
+                .touching
+        );
+
+        assert!(Target::parse(&scene, "d").unwrap_err().contains("a, b, c"));
+        assert!(measure(&scene, &target("a"), &target("a")).is_err());
+    }
+
+    #[test]
+    fn test_measure_spheres_and_edges() {
+        let scene = scene(&format!(
+            r##"{{"kind": "sphere", "name": "ball", "center": [0, 0, 0], "radius": 1,
+                "material": {{"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.8, "specular": 0, "shininess": 1}}}},
+            {},
+            {}"##,
+            // Two bars crossing above one another at right angles, rotated 45° so only
+            // their edges can meet
+            cube("low", [10.0, 0.0, 0.0], [4.0, 0.5, 0.5]).replace(
+                "\"size\"",
+                "\"transform\": [\"rotate(45, 0, 0)\"], \"size\""
+            ),
+            cube("high", [10.0, 0.0, 3.0], [0.5, 4.0, 0.5]).replace(
+                "\"size\"",
+                "\"transform\": [\"rotate(0, 45, 0)\"], \"size\""
+            ),
+        ));
+        let target = |text: &str| Target::parse(&scene, text).unwrap();
+
+        // The sphere's polygon reads within 0.01% of its true radius
+        let measurement = measure(&scene, &target("ball"), &target("0,0,3")).unwrap();
+        assert!((measurement.distance - 2.0).abs() < 1e-4);
+
+        // Dropping the high bar, its lower edge meets the low bar's upper edge
+        let half_diagonal = 0.25 * 2f64.sqrt();
+        let gap = 3.0 - 2.0 * half_diagonal;
+        let bars = measure(&scene, &target("high"), &target("low")).unwrap();
+        assert!((bars.distance - gap).abs() < 1e-9, "{}", bars.distance);
+        assert!((bars.clearances[5].unwrap() - gap).abs() < 1e-9);
+    }
+}
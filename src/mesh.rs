@@ -1,40 +1,7 @@
-use nalgebra::{Point3, Vector3};
-use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
 
-/// 3D point type alias
-pub type Point = Point3<f64>;
-
-/// 3D vector type alias  
-pub type Vec3 = Vector3<f64>;
-
-/// Triangle defined by three vertices and a normal
-#[derive(Debug, Clone)]
-pub struct Triangle {
-    pub vertices: [Point; 3],
-    pub normal: Vec3,
-}
-
-impl Triangle {
-    /// Get the center point of the triangle
-    pub fn center(&self) -> Point {
-        (self.vertices[0] + self.vertices[1].coords + self.vertices[2].coords) / 3.0
-    }
-
-    /// Get the bounding box of the triangle
-    pub fn bounds(&self) -> (Point, Point) {
-        let mut min = self.vertices[0];
-        let mut max = self.vertices[0];
-
-        for vertex in &self.vertices[1..] {
-            min.coords = min.coords.inf(&vertex.coords);
-            max.coords = max.coords.sup(&vertex.coords);
-        }
-
-        (min, max)
-    }
-}
+pub use crate::core::ray::Triangle;
+pub use crate::core::{Point, Vec3};
+use std::f64::consts::PI;
 
 /// K-d tree node for accelerating ray-triangle intersections
 #[derive(Debug, Clone)]
@@ -146,7 +113,8 @@ impl KdTree {
             .map(|&idx| (triangles[idx].center()[axis], idx))
             .collect();
 
-        positions.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        // total_cmp keeps the sort well-defined if a transform produced NaN coordinates
+        positions.sort_by(|a, b| a.0.total_cmp(&b.0));
 
         let median_idx = positions.len() / 2;
         let split_pos = positions[median_idx].0;
@@ -417,8 +385,12 @@ impl KdTree {
                 split_pos,
                 left,
                 right,
-                bounds: _,
+                bounds,
             } => {
+                // Skip whole subtrees the ray misses, as in sparse meshes like glyph fields
+                if !Self::ray_intersects_bounds(ray_origin, ray_direction, bounds) {
+                    return;
+                }
                 let origin_pos = ray_origin[*axis];
                 let dir = ray_direction[*axis];
 
@@ -727,10 +699,33 @@ impl KdTree {
     }
 }
 
+/// Upper bounds enforced while loading STL data, so untrusted input can't exhaust memory
+///
+/// The defaults comfortably fit real-world models (about 1.9 GB of triangles at the
+/// limit); render services accepting uploads should lower them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshLimits {
+    /// Maximum number of triangles in one mesh
+    pub max_triangles: usize,
+    /// Maximum size of the STL data in bytes
+    pub max_bytes: u64,
+}
+
+impl Default for MeshLimits {
+    fn default() -> Self {
+        Self {
+            max_triangles: 20_000_000,
+            max_bytes: 1 << 30,
+        }
+    }
+}
+
 /// Immutable mesh object containing triangles
 #[derive(Debug, Clone)]
 pub struct Mesh {
     pub triangles: Vec<Triangle>,
+    /// Binary STL attribute word of each triangle; empty for ASCII STL and generated meshes
+    pub attributes: Vec<u16>,
     pub bounds_min: Point,
     pub bounds_max: Point,
     pub kdtree: KdTree,
@@ -741,269 +736,62 @@ impl Mesh {
     pub fn new() -> Self {
         Self {
             triangles: Vec::new(),
+            attributes: Vec::new(),
             bounds_min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
             bounds_max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
             kdtree: KdTree::new(&[], 16, 10), // Empty k-d tree
         }
     }
 
-    /// Load mesh from STL file (auto-detects binary vs ASCII)
-    pub fn from_stl_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut file = File::open(&path)?;
-
-        // Try to determine if this is ASCII or binary STL
-        let mut header = [0u8; 80];
-        file.read_exact(&mut header)?;
-
-        let header_str = String::from_utf8_lossy(&header);
-        if header_str.trim_start().starts_with("solid") {
-            // Might be ASCII, but we need to check if it's actually ASCII throughout
-            file.seek(SeekFrom::Start(0))?;
-            if Self::is_ascii_stl(&mut file)? {
-                file.seek(SeekFrom::Start(0))?;
-                return Self::load_ascii_stl(file);
-            }
-        }
-
-        // Binary STL
-        file.seek(SeekFrom::Start(0))?;
-        Self::load_binary_stl(file)
-    }
-
-    /// Load mesh from STL byte buffer (auto-detects binary vs ASCII)
-    pub fn from_stl_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-        if bytes.len() < 80 {
-            return Err("STL data too short".into());
-        }
-
-        let header_str = String::from_utf8_lossy(&bytes[0..80]);
-        if header_str.trim_start().starts_with("solid") && Self::is_ascii_stl_bytes(bytes)? {
-            Self::load_ascii_stl_bytes(bytes)
-        } else {
-            Self::load_binary_stl_bytes(bytes)
-        }
-    }
-
-    /// Check if STL file is ASCII format by looking for ASCII markers
-    fn is_ascii_stl(file: &mut File) -> Result<bool, Box<dyn std::error::Error>> {
-        let reader = BufReader::new(file);
-        let mut line_count = 0;
-
-        for line in reader.lines() {
-            let line = line?;
-            line_count += 1;
-
-            if line_count > 10 {
-                break;
-            }
-
-            let trimmed = line.trim();
-            if trimmed.starts_with("facet normal")
-                || trimmed == "outer loop"
-                || trimmed == "endloop"
-            {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
-    }
-
-    /// Check if STL bytes represent ASCII format
-    fn is_ascii_stl_bytes(bytes: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
-        let content = String::from_utf8_lossy(bytes);
-        let lines: Vec<&str> = content.lines().take(10).collect();
-
-        for line in lines {
-            let trimmed = line.trim();
-            if trimmed.starts_with("facet normal")
-                || trimmed == "outer loop"
-                || trimmed == "endloop"
-            {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
-    }
-
-    /// Load ASCII STL format
-    fn load_ascii_stl(mut file: File) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut content = String::new();
-        file.read_to_string(&mut content)?;
-        Self::load_ascii_stl_bytes(content.as_bytes())
-    }
-
-    /// Load ASCII STL from bytes
-    fn load_ascii_stl_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = String::from_utf8_lossy(bytes);
-        let lines: Vec<&str> = content.lines().collect();
-
-        let mut mesh = Mesh::new();
-        let mut i = 0;
-
-        while i < lines.len() {
-            let line = lines[i].trim();
-
-            if line.starts_with("facet normal") {
-                // Parse normal vector
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() != 5 {
-                    return Err("Invalid facet normal format".into());
-                }
-
-                let nx: f64 = parts[2].parse()?;
-                let ny: f64 = parts[3].parse()?;
-                let nz: f64 = parts[4].parse()?;
-                let normal = Vec3::new(nx, ny, nz);
-
-                i += 1; // Skip "outer loop"
-                if i >= lines.len() || lines[i].trim() != "outer loop" {
-                    return Err("Expected 'outer loop' after facet normal".into());
-                }
-
-                // Parse three vertices
-                let mut vertices = [Point::origin(); 3];
-                #[allow(clippy::needless_range_loop)]
-                for j in 0..3 {
-                    i += 1;
-                    if i >= lines.len() {
-                        return Err("Unexpected end of file while reading vertex".into());
-                    }
-
-                    let vertex_line = lines[i].trim();
-                    if !vertex_line.starts_with("vertex") {
-                        return Err("Expected vertex line".into());
-                    }
-
-                    let parts: Vec<&str> = vertex_line.split_whitespace().collect();
-                    if parts.len() != 4 {
-                        return Err("Invalid vertex format".into());
-                    }
-
-                    let x: f64 = parts[1].parse()?;
-                    let y: f64 = parts[2].parse()?;
-                    let z: f64 = parts[3].parse()?;
-                    vertices[j] = Point::new(x, y, z);
-                }
-
-                i += 1; // Skip "endloop"
-                if i >= lines.len() || lines[i].trim() != "endloop" {
-                    return Err("Expected 'endloop'".into());
-                }
-
-                i += 1; // Skip "endfacet"
-                if i >= lines.len() || lines[i].trim() != "endfacet" {
-                    return Err("Expected 'endfacet'".into());
-                }
-
-                mesh.add_triangle(Triangle { vertices, normal });
-            }
-
-            i += 1;
+    /// Mesh of a flat convex polygon, split into a fan of triangles around the first vertex
+    ///
+    /// No k-d tree is built; a handful of triangles is fastest to test directly.
+    pub fn from_polygon(points: &[Point]) -> Self {
+        let mut mesh = Self::new();
+        for pair in points.windows(2).skip(1) {
+            let vertices = [points[0], pair[0], pair[1]];
+            let normal = (vertices[1] - vertices[0])
+                .cross(&(vertices[2] - vertices[0]))
+                .normalize();
+            mesh.add_triangle(Triangle { vertices, normal });
         }
-
-        mesh.compute_bounds();
-        mesh.build_kdtree();
-        Ok(mesh)
-    }
-
-    /// Load binary STL format
-    fn load_binary_stl(mut file: File) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut bytes = Vec::new();
-        file.read_to_end(&mut bytes)?;
-        Self::load_binary_stl_bytes(&bytes)
+        mesh
     }
 
-    /// Load binary STL from bytes
-    fn load_binary_stl_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-        if bytes.len() < 84 {
-            return Err("Binary STL too short".into());
-        }
-
-        // Skip 80-byte header, read triangle count
-        let triangle_count =
-            u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
-
-        let expected_size = 84 + triangle_count * 50;
-        if bytes.len() < expected_size {
-            return Err(format!(
-                "Binary STL size mismatch: expected {}, got {}",
-                expected_size,
-                bytes.len()
+    /// Unit cube centered on the origin, standing in for a mesh that failed to load
+    pub fn placeholder_box() -> Self {
+        let corner = |i: usize| {
+            Point::new(
+                if i & 1 == 0 { -0.5 } else { 0.5 },
+                if i & 2 == 0 { -0.5 } else { 0.5 },
+                if i & 4 == 0 { -0.5 } else { 0.5 },
             )
-            .into());
-        }
-
-        let mut mesh = Mesh::new();
-        let mut offset = 84;
-
-        for _ in 0..triangle_count {
-            if offset + 50 > bytes.len() {
-                return Err("Unexpected end of binary STL data".into());
-            }
-
-            // Read normal (3 * f32)
-            let nx = f32::from_le_bytes([
-                bytes[offset],
-                bytes[offset + 1],
-                bytes[offset + 2],
-                bytes[offset + 3],
-            ]) as f64;
-            let ny = f32::from_le_bytes([
-                bytes[offset + 4],
-                bytes[offset + 5],
-                bytes[offset + 6],
-                bytes[offset + 7],
-            ]) as f64;
-            let nz = f32::from_le_bytes([
-                bytes[offset + 8],
-                bytes[offset + 9],
-                bytes[offset + 10],
-                bytes[offset + 11],
-            ]) as f64;
-            let normal = Vec3::new(nx, ny, nz);
-            offset += 12;
-
-            // Read three vertices (3 * 3 * f32)
-            let mut vertices = [Point::origin(); 3];
-            #[allow(clippy::needless_range_loop)]
-            for i in 0..3 {
-                let x = f32::from_le_bytes([
-                    bytes[offset],
-                    bytes[offset + 1],
-                    bytes[offset + 2],
-                    bytes[offset + 3],
-                ]) as f64;
-                let y = f32::from_le_bytes([
-                    bytes[offset + 4],
-                    bytes[offset + 5],
-                    bytes[offset + 6],
-                    bytes[offset + 7],
-                ]) as f64;
-                let z = f32::from_le_bytes([
-                    bytes[offset + 8],
-                    bytes[offset + 9],
-                    bytes[offset + 10],
-                    bytes[offset + 11],
-                ]) as f64;
-                vertices[i] = Point::new(x, y, z);
-                offset += 12;
+        };
+        // Faces wound counter-clockwise seen from outside
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let mut mesh = Self::new();
+        for face in faces {
+            let points = face.map(corner);
+            for vertices in [[points[0], points[1], points[2]], [points[0], points[2], points[3]]] {
+                let normal = (vertices[1] - vertices[0])
+                    .cross(&(vertices[2] - vertices[0]))
+                    .normalize();
+                mesh.add_triangle(Triangle { vertices, normal });
             }
-
-            // Skip 2-byte attribute
-            offset += 2;
-
-            mesh.add_triangle(Triangle { vertices, normal });
         }
-
-        mesh.compute_bounds();
         mesh.build_kdtree();
-        Ok(mesh)
+        mesh
     }
 
-    /// Add a triangle to the mesh
-    fn add_triangle(&mut self, triangle: Triangle) {
+    /// Add a triangle to the mesh (call `build_kdtree` once all triangles are added)
+    pub fn add_triangle(&mut self, triangle: Triangle) {
         // Update bounding box
         for vertex in &triangle.vertices {
             self.bounds_min.coords = self.bounds_min.coords.inf(&vertex.coords);
@@ -1034,6 +822,7 @@ impl Mesh {
 
     /// Build k-d tree for accelerating ray intersections
     pub fn build_kdtree(&mut self) {
+        let _span = crate::profile::span("acceleration build");
         // Use reasonable defaults: max depth 16, max 10 triangles per leaf
         self.kdtree = KdTree::new(&self.triangles, 16, 10);
     }
@@ -1047,6 +836,109 @@ impl Mesh {
     pub fn bounds(&self) -> (Point, Point) {
         (self.bounds_min, self.bounds_max)
     }
+
+    /// Outward normal of the largest flat face the mesh can rest on
+    ///
+    /// Coplanar triangles are pooled into faces by their plane, and faces are tried
+    /// from the largest down; the first with no vertex in front of it touches the
+    /// mesh's convex hull, so the mesh is stable lying on it. None if no face qualifies
+    /// among the largest few, or the mesh has no triangles with area.
+    pub fn resting_face_normal(&self) -> Option<Vec3> {
+        // Candidates beyond these only matter for meshes with no large flat faces
+        const CANDIDATES: usize = 64;
+        let scale = (self.bounds_max - self.bounds_min).norm();
+        if self.triangles.is_empty() || scale == 0.0 || !scale.is_finite() {
+            return None;
+        }
+
+        // Planes quantized to about a thousandth of a radian and of the mesh size, so
+        // float noise in exported STLs doesn't split a face
+        let mut faces: std::collections::HashMap<[i64; 4], (Vec3, f64, f64)> =
+            std::collections::HashMap::new();
+        for triangle in &self.triangles {
+            let [a, b, c] = triangle.vertices;
+            let cross = (b - a).cross(&(c - a));
+            let area = cross.norm() / 2.0;
+            if area == 0.0 || !area.is_finite() {
+                continue;
+            }
+            let normal = cross.normalize();
+            let offset = normal.dot(&a.coords);
+            let key = [
+                (normal.x * 1000.0).round() as i64,
+                (normal.y * 1000.0).round() as i64,
+                (normal.z * 1000.0).round() as i64,
+                (offset / scale * 1000.0).round() as i64,
+            ];
+            let face = faces.entry(key).or_insert((Vec3::zeros(), offset, 0.0));
+            face.0 += normal * area;
+            face.2 += area;
+        }
+        let mut faces: Vec<_> = faces.into_values().collect();
+        faces.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        let tolerance = scale * 1e-4;
+        faces
+            .into_iter()
+            .take(CANDIDATES)
+            .map(|(normal, offset, _)| (normal.normalize(), offset))
+            .find(|(normal, offset)| {
+                self.triangles.iter().all(|triangle| {
+                    triangle
+                        .vertices
+                        .iter()
+                        .all(|vertex| normal.dot(&vertex.coords) <= offset + tolerance)
+                })
+            })
+            .map(|(normal, _)| normal)
+    }
+
+    /// Turn the mesh to rest on its largest flat face (see `resting_face_normal`) and
+    /// move it onto the z = 0 ground plane
+    ///
+    /// The mesh turns about the center of its bounds, keeping its x/y center, and its
+    /// lowest point ends up at z = 0. Meshes without a face to rest on only move down.
+    pub fn lay_flat(&mut self) {
+        if self.triangles.is_empty() {
+            return;
+        }
+        let down = -Vec3::z();
+        let rotation = match self.resting_face_normal() {
+            // Facing straight up has no single shortest turn, so flip about x
+            Some(normal) => nalgebra::Rotation3::rotation_between(&normal, &down)
+                .unwrap_or_else(|| nalgebra::Rotation3::from_axis_angle(&Vec3::x_axis(), PI)),
+            None => nalgebra::Rotation3::identity(),
+        };
+        let center = nalgebra::center(&self.bounds_min, &self.bounds_max);
+        for triangle in &mut self.triangles {
+            for vertex in &mut triangle.vertices {
+                *vertex = center + rotation * (*vertex - center);
+            }
+            triangle.normal = rotation * triangle.normal;
+        }
+        self.compute_bounds();
+
+        let lift = Vec3::new(0.0, 0.0, -self.bounds_min.z);
+        for triangle in &mut self.triangles {
+            for vertex in &mut triangle.vertices {
+                *vertex += lift;
+            }
+        }
+        self.compute_bounds();
+        self.build_kdtree();
+    }
+
+    /// Scale every vertex away from the origin by `factor`, e.g. 25.4 for a mesh
+    /// modelled in inches
+    pub fn scale(&mut self, factor: f64) {
+        for triangle in &mut self.triangles {
+            for vertex in &mut triangle.vertices {
+                *vertex = Point::from(vertex.coords * factor);
+            }
+        }
+        self.compute_bounds();
+        self.build_kdtree();
+    }
 }
 
 impl Default for Mesh {
@@ -1085,87 +977,59 @@ mod tests {
         assert_eq!(max, Point::new(1.0, 1.0, -1.0));
     }
 
-    #[test]
-    fn test_ascii_detection() {
-        let ascii_content = b"solid test
-facet normal 0 0 1
-  outer loop
-    vertex -1 -1 0
-    vertex 1 -1 0
-    vertex 0 1 0
-  endloop
-endfacet
-endsolid test";
-
-        assert!(Mesh::is_ascii_stl_bytes(ascii_content).unwrap());
-    }
-
-    #[test]
-    fn test_ascii_stl_parsing() {
-        let ascii_content = b"solid test
-facet normal 0 0 1
-  outer loop
-    vertex -1 -1 0
-    vertex 1 -1 0
-    vertex 0 1 0
-  endloop
-endfacet
-facet normal 0 0 -1
-  outer loop
-    vertex 0 1 0
-    vertex 1 -1 0
-    vertex -1 -1 0
-  endloop
-endfacet
-endsolid test";
-
-        let mesh = Mesh::from_stl_bytes(ascii_content).unwrap();
-        assert_eq!(mesh.triangle_count(), 2);
-
-        // Check first triangle
-        assert_eq!(mesh.triangles[0].vertices[0], Point::new(-1.0, -1.0, 0.0));
-        assert_eq!(mesh.triangles[0].vertices[1], Point::new(1.0, -1.0, 0.0));
-        assert_eq!(mesh.triangles[0].vertices[2], Point::new(0.0, 1.0, 0.0));
-        assert_eq!(mesh.triangles[0].normal, Vec3::new(0.0, 0.0, 1.0));
+    /// 4×2×0.5 plate centered on the origin
+    fn plate() -> Mesh {
+        let mut mesh = Mesh::new();
+        for triangle in Mesh::placeholder_box().triangles {
+            let vertices = triangle
+                .vertices
+                .map(|v| Point::new(v.x * 4.0, v.y * 2.0, v.z * 0.5));
+            mesh.add_triangle(Triangle {
+                vertices,
+                ..triangle
+            });
+        }
+        mesh
     }
 
     #[test]
-    fn test_binary_stl_parsing() {
-        // Create a simple binary STL with one triangle
-        let mut binary_data = vec![0u8; 80]; // header
-        binary_data.extend_from_slice(&1u32.to_le_bytes()); // triangle count
-
-        // Triangle data: normal + 3 vertices + attribute
-        let normal = [0.0f32, 0.0f32, 1.0f32];
-        let vertex1 = [-1.0f32, -1.0f32, 0.0f32];
-        let vertex2 = [1.0f32, -1.0f32, 0.0f32];
-        let vertex3 = [0.0f32, 1.0f32, 0.0f32];
-        let attribute = 0u16;
-
-        // Add normal
-        for &f in &normal {
-            binary_data.extend_from_slice(&f.to_le_bytes());
-        }
-        // Add vertices
-        for &f in &vertex1 {
-            binary_data.extend_from_slice(&f.to_le_bytes());
-        }
-        for &f in &vertex2 {
-            binary_data.extend_from_slice(&f.to_le_bytes());
-        }
-        for &f in &vertex3 {
-            binary_data.extend_from_slice(&f.to_le_bytes());
+    fn test_lay_flat() {
+        // Tipped over and lifted, the plate comes back to lying on a broad side
+        let rotation = nalgebra::Rotation3::from_euler_angles(0.7, -1.1, 0.3);
+        let mut mesh = Mesh::new();
+        for triangle in plate().triangles {
+            let vertices = triangle
+                .vertices
+                .map(|v| rotation * v + Vec3::new(1.0, 2.0, 3.0));
+            mesh.add_triangle(Triangle {
+                vertices,
+                ..triangle
+            });
         }
-        // Add attribute
-        binary_data.extend_from_slice(&attribute.to_le_bytes());
-
-        let mesh = Mesh::from_stl_bytes(&binary_data).unwrap();
-        assert_eq!(mesh.triangle_count(), 1);
-
-        // Check triangle data
-        assert_eq!(mesh.triangles[0].vertices[0], Point::new(-1.0, -1.0, 0.0));
-        assert_eq!(mesh.triangles[0].vertices[1], Point::new(1.0, -1.0, 0.0));
-        assert_eq!(mesh.triangles[0].vertices[2], Point::new(0.0, 1.0, 0.0));
-        assert_eq!(mesh.triangles[0].normal, Vec3::new(0.0, 0.0, 1.0));
+        mesh.lay_flat();
+        let (min, max) = mesh.bounds();
+        assert!(min.z.abs() < 1e-9);
+        assert!((max.z - 0.5).abs() < 1e-9);
+        assert!((nalgebra::center(&min, &max).x - 1.0).abs() < 1e-9);
+
+        // A spike on top keeps the plate from resting on its top face, so the bottom
+        // face, already facing down, is used
+        let mut mesh = plate();
+        let spike = [
+            Point::new(0.0, 0.0, 0.25),
+            Point::new(0.1, 0.0, 0.25),
+            Point::new(0.0, 0.0, 1.0),
+        ];
+        mesh.add_triangle(Triangle {
+            vertices: spike,
+            normal: Vec3::y(),
+        });
+        assert_eq!(mesh.resting_face_normal(), Some(-Vec3::z()));
+        mesh.lay_flat();
+        assert_eq!(
+            mesh.bounds(),
+            (Point::new(-2.0, -1.0, 0.0), Point::new(2.0, 1.0, 1.25))
+        );
+        assert_eq!(Mesh::new().resting_face_normal(), None);
     }
 }
@@ -0,0 +1,64 @@
+//! Pixels whose color came out NaN or infinite, and what they were looking at
+
+use std::sync::{Arc, Mutex};
+
+use crate::scene::Color;
+
+/// Color that replaces NaN and infinite pixels when they are checked for
+pub const NON_FINITE_MARKER: Color = Color::new(1.0, 0.0, 1.0);
+
+/// Pixel whose traced color was NaN or infinite
+#[derive(Debug, Clone, PartialEq)]
+pub struct NonFinitePixel {
+    pub x: u32,
+    pub y: u32,
+    /// The color as traced, before it was replaced by `NON_FINITE_MARKER`
+    pub color: [f64; 3],
+    /// Object seen through the pixel center, labeled like `ObjectStat::label`, or
+    /// None for the background
+    pub object: Option<String>,
+}
+
+impl std::fmt::Display for NonFinitePixel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [r, g, b] = self.color;
+        write!(
+            f,
+            "pixel ({}, {}) is ({}, {}, {}) on ",
+            self.x, self.y, r, g, b
+        )?;
+        match &self.object {
+            Some(object) => write!(f, "{}", object),
+            None => write!(f, "the background"),
+        }
+    }
+}
+
+/// NaN and infinite pixels collected across renders
+///
+/// Renders using it (see `Renderer::with_non_finite_check`) scan their float pixels
+/// once tracing is done, record every pixel that isn't finite, and paint it
+/// `NON_FINITE_MARKER` so it stands out instead of quietly turning black. Clones
+/// share the same list, and pixels add up across renders until `clear`.
+#[derive(Debug, Clone, Default)]
+pub struct NonFinitePixels(Arc<Mutex<Vec<NonFinitePixel>>>);
+
+impl NonFinitePixels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every pixel recorded so far, in the order they were found
+    pub fn pixels(&self) -> Vec<NonFinitePixel> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Forget the pixels recorded so far
+    pub fn clear(&self) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    pub(crate) fn record(&self, pixel: NonFinitePixel) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).push(pixel);
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::ray::{HitRecord, Intersectable, Ray};
+use crate::scene::{Object, Point, Scene};
+use crate::time::{Duration, Instant};
+
+/// Per-object intersection counters, for finding the objects that slow a render down
+///
+/// Clones share the same counters. Renders using them (see `Renderer::with_object_stats`)
+/// count and time every ray tested against each object, which costs a little speed, and
+/// the totals add up across renders until `reset`.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectStats(Arc<Mutex<Vec<Arc<ObjectCounter>>>>);
+
+#[derive(Debug, Default)]
+pub(crate) struct ObjectCounter {
+    tests: AtomicU64,
+    hits: AtomicU64,
+    nanos: AtomicU64,
+}
+
+/// Intersection work spent on one scene object
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectStat {
+    /// Index in `scene.objects`
+    pub index: usize,
+    /// Name or `objects[index]`, with the kind and any mesh triangle count
+    pub label: String,
+    /// Rays tested against the object, of every kind
+    pub tests: u64,
+    /// Tests that hit the object before anything tested earlier
+    pub hits: u64,
+    /// CPU time inside the tests, summed over threads
+    pub time: Duration,
+    /// Share of the time spent on all objects, from 0 to 1
+    pub share: f64,
+}
+
+impl ObjectStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter for the object at `index`, shared by every render
+    pub(crate) fn counter(&self, index: usize) -> Arc<ObjectCounter> {
+        let mut counters = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if counters.len() <= index {
+            counters.resize_with(index + 1, Default::default);
+        }
+        Arc::clone(&counters[index])
+    }
+
+    /// Totals for each object of `scene` that was tested, slowest first
+    pub fn report(&self, scene: &Scene) -> Vec<ObjectStat> {
+        let counters = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let total: u64 = counters
+            .iter()
+            .map(|c| c.nanos.load(Ordering::Relaxed))
+            .sum();
+        let mut stats: Vec<ObjectStat> = counters
+            .iter()
+            .enumerate()
+            .filter(|(_, counter)| counter.tests.load(Ordering::Relaxed) > 0)
+            .map(|(index, counter)| {
+                let nanos = counter.nanos.load(Ordering::Relaxed);
+                ObjectStat {
+                    index,
+                    label: label(scene, index),
+                    tests: counter.tests.load(Ordering::Relaxed),
+                    hits: counter.hits.load(Ordering::Relaxed),
+                    time: Duration::from_nanos(nanos),
+                    share: nanos as f64 / total.max(1) as f64,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| b.time.cmp(&a.time).then(a.index.cmp(&b.index)));
+        stats
+    }
+
+    /// Zero every counter
+    pub fn reset(&self) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// Name or `objects[index]` of an object, with its kind
+pub(crate) fn label(scene: &Scene, index: usize) -> String {
+    // The auto floor is traced after the scene's own objects
+    let floor = match index == scene.objects.len() {
+        true => scene.auto_floor().ok().flatten(),
+        false => None,
+    };
+    let Some(object) = scene.objects.get(index).or(floor.as_ref()) else {
+        return format!("objects[{}]", index);
+    };
+    let name = match object.name() {
+        Some(name) => format!("'{}'", name),
+        None => format!("objects[{}]", index),
+    };
+    let kind = match object {
+        Object::Mesh {
+            mesh_data: Some(mesh),
+            ..
+        } => format!("mesh, {} triangles", mesh.triangles.len()),
+        Object::Instance { reference, .. } => {
+            match scene
+                .meshes
+                .get(reference)
+                .and_then(|prototype| prototype.mesh_data.as_ref())
+            {
+                Some(mesh) => format!(
+                    "instance of '{}', {} triangles",
+                    reference,
+                    mesh.triangles.len()
+                ),
+                None => format!("instance of '{}'", reference),
+            }
+        }
+        object => object.kind().to_string(),
+    };
+    format!("{} ({})", name, kind)
+}
+
+/// World object that counts and times the intersection tests made against it
+pub(crate) struct Counted {
+    inner: Box<dyn Intersectable + Send + Sync>,
+    counter: Arc<ObjectCounter>,
+}
+
+impl Counted {
+    pub(crate) fn new(inner: Box<dyn Intersectable + Send + Sync>, stats: &ObjectStats) -> Self {
+        let counter = stats.counter(inner.material_index());
+        Self { inner, counter }
+    }
+}
+
+impl Intersectable for Counted {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let start = Instant::now();
+        let hit = self.inner.hit(ray, t_min, t_max);
+        let nanos = start.elapsed().as_nanos() as u64;
+        self.counter.nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.counter.tests.fetch_add(1, Ordering::Relaxed);
+        if hit.is_some() {
+            self.counter.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        hit
+    }
+
+    fn material_index(&self) -> usize {
+        self.inner.material_index()
+    }
+
+    fn bounding_box(&self) -> Option<(Point, Point)> {
+        self.inner.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use crate::renderer::Renderer;
+    use crate::scene::{Material, Point};
+
+    #[test]
+    fn test_object_stats() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+            "objects": [
+                {"kind": "sphere", "name": "ball", "center": [0, 0, 0], "radius": 1,
+                    "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}
+            ],
+            "lights": [{"position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+        }"##,
+        )
+        .unwrap();
+        let square = [(-2.0, -2.0), (2.0, -2.0), (2.0, 2.0), (-2.0, 2.0)]
+            .map(|(x, z)| Point::new(x, 1.5, z));
+        let mut wall = Mesh::from_polygon(&square);
+        wall.build_kdtree();
+        scene.objects.push(Object::Mesh {
+            name: None,
+            filename: "wall.stl".to_string(),
+            material: Material::default(),
+            face_materials: Vec::new(),
+            orient: None,
+            unit: None,
+            lods: Vec::new(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+            mesh_data: Some(wall),
+        });
+
+        let stats = ObjectStats::new();
+        let renderer = Renderer::new(8, 8).with_object_stats(stats.clone());
+        renderer.render_rgb(&scene).unwrap();
+        let report = stats.report(&scene);
+        assert_eq!(report.len(), 2);
+        let ball = report.iter().find(|stat| stat.index == 0).unwrap();
+        let wall = report.iter().find(|stat| stat.index == 1).unwrap();
+        assert_eq!(ball.label, "'ball' (sphere)");
+        assert_eq!(wall.label, "objects[1] (mesh, 2 triangles)");
+        // Every primary and shadow ray tests both objects; the wall is hit behind the ball
+        assert!(ball.tests > 64 && ball.tests == wall.tests);
+        assert!(ball.hits > 0 && wall.hits > 0);
+        assert!((report.iter().map(|stat| stat.share).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(report[0].time >= report[1].time);
+
+        // Counts add up across renders until reset
+        renderer.render_rgb(&scene).unwrap();
+        assert_eq!(stats.report(&scene)[0].tests, 2 * report[0].tests);
+        stats.reset();
+        assert!(stats.report(&scene).is_empty());
+    }
+}
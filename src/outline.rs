@@ -1,3 +1,6 @@
+use std::collections::BTreeMap;
+
+use crate::aov::{Aov, AovBuffer};
 use crate::scene::{Color, Vec3};
 
 /// Configuration parameters for outline detection
@@ -15,6 +18,17 @@ pub struct OutlineConfig {
     pub use_8_neighbors: bool,
     /// Line thickness factor (1.0 = no thickening, >1.0 = thicker lines)
     pub line_thickness: f64,
+    /// Weight for neighboring pixels showing different objects (w_o); 0 ignores objects
+    pub object_weight: f64,
+    /// Angle in degrees between neighboring normals above which a crease is always an
+    /// edge, however small the weighted difference
+    pub crease_angle: Option<f64>,
+    /// Edge colors of particular objects by object index, in place of `edge_color`;
+    /// None draws no outlines for the object
+    pub object_edge_colors: BTreeMap<usize, Option<Color>>,
+    /// Samples per pixel along each axis of the outline buffers; edges are found at that
+    /// resolution and averaged down, for anti-aliased lines (1 = one sample per pixel)
+    pub supersample: u32,
 }
 
 impl Default for OutlineConfig {
@@ -26,10 +40,34 @@ impl Default for OutlineConfig {
             edge_color: Color::new(0.0, 0.0, 0.0), // Black edges
             use_8_neighbors: false, // 4-neighbor by default for performance
             line_thickness: 1.0,
+            object_weight: 0.0,
+            crease_angle: None,
+            object_edge_colors: BTreeMap::new(),
+            supersample: 1,
         }
     }
 }
 
+impl OutlineConfig {
+    /// Color of the outlines of the object with index `object`, or None if it has none
+    pub fn edge_color_of(&self, object: Option<usize>) -> Option<Color> {
+        match object.and_then(|object| self.object_edge_colors.get(&object)) {
+            Some(color) => *color,
+            None => Some(self.edge_color),
+        }
+    }
+
+    /// Size of the image drawn over with `buffers`, `supersample` times smaller
+    fn image_size(&self, buffers: &OutlineBuffers) -> (u32, u32) {
+        let scale = self.supersample.max(1);
+        (buffers.width / scale, buffers.height / scale)
+    }
+}
+
+/// 16-bit grayscale image made by `OutlineBuffers::depth_map`
+#[cfg(feature = "image")]
+pub type DepthMap = image::ImageBuffer<image::Luma<u16>, Vec<u16>>;
+
 /// Buffers containing depth and normal data for outline detection
 pub struct OutlineBuffers {
     pub width: u32,
@@ -38,6 +76,9 @@ pub struct OutlineBuffers {
     pub depth_buffer: Vec<Option<f64>>,
     /// World-space normal vectors (None for background pixels)
     pub normal_buffer: Vec<Option<Vec3>>,
+    /// Index of the object seen, for object edges and per-object outline colors (None
+    /// if unknown)
+    pub object_buffer: Vec<Option<usize>>,
 }
 
 impl OutlineBuffers {
@@ -48,6 +89,7 @@ impl OutlineBuffers {
             height,
             depth_buffer: vec![None; size],
             normal_buffer: vec![None; size],
+            object_buffer: vec![None; size],
         }
     }
 
@@ -73,6 +115,39 @@ impl OutlineBuffers {
         self.depth_buffer[index]
     }
 
+    /// Buffers from `depth` and `normal` AOV passes, where background pixels hold zeros
+    pub fn from_aovs(depth: &AovBuffer, normal: &AovBuffer) -> Result<Self, String> {
+        if (depth.aov, normal.aov) != (Aov::Depth, Aov::Normal) {
+            return Err("Outline buffers need a depth and a normal pass".to_string());
+        }
+        if (depth.width, depth.height) != (normal.width, normal.height) {
+            return Err("Depth and normal passes differ in size".to_string());
+        }
+        let mut buffers = Self::new(depth.width, depth.height);
+        let pixels = depth.pixels.iter().zip(&normal.pixels);
+        for (index, (&[d, ..], &[nx, ny, nz])) in pixels.enumerate() {
+            if d > 0.0 {
+                buffers.depth_buffer[index] = Some(d as f64);
+                buffers.normal_buffer[index] = Some(Vec3::new(nx as f64, ny as f64, nz as f64));
+            }
+        }
+        Ok(buffers)
+    }
+
+    /// These buffers with the objects seen taken from an `object_id` AOV pass
+    pub fn with_object_ids(mut self, object_id: &AovBuffer) -> Result<Self, String> {
+        if object_id.aov != Aov::ObjectId {
+            return Err("Outline objects need an object_id pass".to_string());
+        }
+        if (object_id.width, object_id.height) != (self.width, self.height) {
+            return Err("Object ID pass differs in size from the outline buffers".to_string());
+        }
+        self.object_buffer = (object_id.pixels.iter())
+            .map(|&[id, ..]| (id >= 1.0).then(|| id as usize - 1))
+            .collect();
+        Ok(self)
+    }
+
     pub fn get_normal(&self, x: u32, y: u32) -> Option<Vec3> {
         if x >= self.width || y >= self.height {
             return None;
@@ -80,6 +155,52 @@ impl OutlineBuffers {
         let index = self.get_index(x, y);
         self.normal_buffer[index]
     }
+
+    pub fn set_object(&mut self, x: u32, y: u32, object: usize) {
+        let index = self.get_index(x, y);
+        self.object_buffer[index] = Some(object);
+    }
+
+    pub fn get_object(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = self.get_index(x, y);
+        self.object_buffer[index]
+    }
+
+    /// 16-bit depth map: the nearest hit white, the farthest at a tenth of white and
+    /// the background black, as the depth AOV is shown in 8-bit formats
+    #[cfg(feature = "image")]
+    pub fn depth_map(&self) -> DepthMap {
+        let depths = self.depth_buffer.iter().flatten();
+        let near = depths.clone().copied().fold(f64::INFINITY, f64::min);
+        let far = depths.copied().fold(0.0, f64::max);
+        let range = (far - near).max(f64::EPSILON);
+        let data = (self.depth_buffer.iter())
+            .map(|depth| match depth {
+                Some(depth) => ((1.0 - 0.9 * (depth - near) / range) * 65535.0).round() as u16,
+                None => 0,
+            })
+            .collect();
+        image::ImageBuffer::from_raw(self.width, self.height, data)
+            .expect("Depth buffer matches its dimensions")
+    }
+
+    /// Normal map: world-space x, y and z from -1..1 in red, green and blue, and the
+    /// background black
+    #[cfg(feature = "image")]
+    pub fn normal_map(&self) -> image::RgbImage {
+        let data = (self.normal_buffer.iter())
+            .flat_map(|normal| match normal {
+                Some(normal) => [normal.x, normal.y, normal.z]
+                    .map(|value| ((value * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8),
+                None => [0; 3],
+            })
+            .collect();
+        image::RgbImage::from_raw(self.width, self.height, data)
+            .expect("Normal buffer matches its dimensions")
+    }
 }
 
 /// Apply outline detection to a color image using depth and normal buffers
@@ -88,39 +209,152 @@ pub fn apply_outline_detection(
     buffers: &OutlineBuffers,
     config: &OutlineConfig,
 ) {
-    // Create edge mask
+    let final_mask = edges(buffers, config);
+    let (width, _) = config.image_size(buffers);
+
+    // Apply edges to image data
+    for (x, y, color) in image_data.iter_mut() {
+        let index = (*y * width + *x) as usize;
+        let Some(&(edge_strength, object)) = final_mask.get(index) else {
+            continue;
+        };
+        if let (true, Some(edge_color)) = (edge_strength > 0.0, config.edge_color_of(object)) {
+            // Blend edge color based on edge strength
+            *color = blend_colors(*color, edge_color, edge_strength.min(1.0));
+        }
+    }
+}
+
+/// Edge strength of every pixel, row by row, from 0 (no edge) to 1, after line thickening
+///
+/// This is the mask `apply_outline_detection` blends the edge color with; objects
+/// without outlines leave no edges in it.
+pub fn edge_mask(buffers: &OutlineBuffers, config: &OutlineConfig) -> Vec<f64> {
+    (edges(buffers, config).into_iter())
+        .map(|(strength, object)| match config.edge_color_of(object) {
+            Some(_) => strength,
+            None => 0.0,
+        })
+        .collect()
+}
+
+/// Edge strength of every pixel after line thickening, with the object whose outline
+/// the edge is
+///
+/// Supersampled buffers are thickened at their own resolution, lines keeping their
+/// width in image pixels, then averaged down to the image's.
+fn edges(buffers: &OutlineBuffers, config: &OutlineConfig) -> Vec<(f64, Option<usize>)> {
+    let scale = config.supersample.max(1);
     let edge_mask = detect_edges(buffers, config);
-    
-    // Apply line thickness if requested
-    let final_mask = if config.line_thickness > 1.0 {
-        dilate_edges(&edge_mask, buffers.width, buffers.height, config.line_thickness)
+    let (width, height) = (buffers.width, buffers.height);
+    let thickness = config.line_thickness * scale as f64;
+    let edge_mask = if thickness > 1.0 {
+        dilate_edges(&edge_mask, width, height, thickness)
     } else {
         edge_mask
     };
+    if scale > 1 {
+        downsample_edges(&edge_mask, width, scale, config)
+    } else {
+        edge_mask
+    }
+}
 
-    // Apply edges to image data
-    for (x, y, color) in image_data.iter_mut() {
-        let index = (*y * buffers.width + *x) as usize;
-        if index < final_mask.len() && final_mask[index] > 0.0 {
-            // Blend edge color based on edge strength
-            let edge_strength = final_mask[index].min(1.0);
-            *color = blend_colors(*color, config.edge_color, edge_strength);
+/// Average each `scale` by `scale` block of a supersampled edge mask into one pixel
+///
+/// Edges of objects without outlines count as none, and each pixel's edge belongs to
+/// the object with the strongest edge in its block.
+fn downsample_edges(
+    edge_mask: &[(f64, Option<usize>)],
+    width: u32,
+    scale: u32,
+    config: &OutlineConfig,
+) -> Vec<(f64, Option<usize>)> {
+    let (out_width, out_height) = (width / scale, edge_mask.len() as u32 / width / scale);
+    let samples = (scale * scale) as f64;
+    let mut downsampled = Vec::with_capacity((out_width * out_height) as usize);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let mut total = 0.0;
+            let (mut strongest, mut object) = (0.0, None);
+            for (dx, dy) in (0..scale).flat_map(|dy| (0..scale).map(move |dx| (dx, dy))) {
+                let index = ((y * scale + dy) * width + x * scale + dx) as usize;
+                let (strength, edge_object) = edge_mask[index];
+                if config.edge_color_of(edge_object).is_none() {
+                    continue;
+                }
+                let strength = strength.min(1.0);
+                total += strength;
+                if strength > strongest {
+                    (strongest, object) = (strength, edge_object);
+                }
+            }
+            downsampled.push((total / samples, object));
         }
     }
+    downsampled
+}
+
+/// Draw outlines onto an already rendered image, for outlines as a post process
+///
+/// The buffers must match the image's size, times `config.supersample`;
+/// `Renderer::render_outline_buffers` makes them for a scene. Colors blend in the image's 8-bit values rather than the renderer's
+/// float colors, so anti-aliased edges can differ slightly from outlines drawn while rendering.
+#[cfg(feature = "image")]
+pub fn apply_outline_to_image(
+    image: &mut image::RgbImage,
+    buffers: &OutlineBuffers,
+    config: &OutlineConfig,
+) -> Result<(), String> {
+    let scale = config.supersample.max(1);
+    if (image.width() * scale, image.height() * scale) != (buffers.width, buffers.height) {
+        return Err(format!(
+            "Outline buffers are {}x{} but the image is {}x{}, supersampled {}x",
+            buffers.width,
+            buffers.height,
+            image.width(),
+            image.height(),
+            scale
+        ));
+    }
+    let mut image_data: Vec<(u32, u32, Color)> = image
+        .enumerate_pixels()
+        .map(|(x, y, pixel)| (x, y, Color::from(pixel.0.map(f64::from)) / 255.0))
+        .collect();
+    apply_outline_detection(&mut image_data, buffers, config);
+    for (x, y, color) in image_data {
+        let channels = [color.x, color.y, color.z];
+        image.put_pixel(x, y, image::Rgb(channels.map(|c| (c * 255.0) as u8)));
+    }
+    Ok(())
+}
+
+/// The edge mask as a grayscale image, white on the outlines and black elsewhere
+#[cfg(feature = "image")]
+pub fn edge_mask_image(buffers: &OutlineBuffers, config: &OutlineConfig) -> image::GrayImage {
+    let pixels = edge_mask(buffers, config)
+        .into_iter()
+        .map(|strength| (strength.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect();
+    let (width, height) = config.image_size(buffers);
+    image::GrayImage::from_raw(width, height, pixels)
+        .expect("edge mask matches the buffers' dimensions")
 }
 
 /// Detect edges using depth and normal discontinuities
-fn detect_edges(buffers: &OutlineBuffers, config: &OutlineConfig) -> Vec<f64> {
+fn detect_edges(buffers: &OutlineBuffers, config: &OutlineConfig) -> Vec<(f64, Option<usize>)> {
     let size = (buffers.width * buffers.height) as usize;
-    let mut edge_mask = vec![0.0; size];
+    let mut edge_mask = vec![(0.0, None); size];
 
     for y in 0..buffers.height {
         for x in 0..buffers.width {
-            let edge_strength = compute_edge_strength(buffers, x, y, config);
+            let (edge_strength, object) = compute_edge_strength(buffers, x, y, config);
             let index = (y * buffers.width + x) as usize;
             
             if edge_strength > config.threshold {
-                edge_mask[index] = (edge_strength - config.threshold) / (1.0 - config.threshold).max(0.001);
+                let strength =
+                    (edge_strength - config.threshold) / (1.0 - config.threshold).max(0.001);
+                edge_mask[index] = (strength, object);
             }
         }
     }
@@ -128,15 +362,22 @@ fn detect_edges(buffers: &OutlineBuffers, config: &OutlineConfig) -> Vec<f64> {
     edge_mask
 }
 
-/// Compute edge strength for a pixel using neighboring pixels
+/// Compute edge strength for a pixel using neighboring pixels, along with the object
+/// whose outline the strongest edge is
+///
+/// Neighbors showing different objects add `object_weight`, and creases sharper than
+/// `crease_angle` are always edges. An edge belongs to the nearer of the two pixels it
+/// lies between, so an object's silhouette stays its own wherever it is seen against.
+/// Edges of objects without outlines are passed over.
 fn compute_edge_strength(
     buffers: &OutlineBuffers,
     x: u32,
     y: u32,
     config: &OutlineConfig,
-) -> f64 {
+) -> (f64, Option<usize>) {
     let current_depth = buffers.get_depth(x, y);
     let current_normal = buffers.get_normal(x, y);
+    let current_object = buffers.get_object(x, y);
 
     let neighbors = if config.use_8_neighbors {
         get_8_neighbors(x, y)
@@ -145,11 +386,21 @@ fn compute_edge_strength(
     };
 
     let mut max_edge_strength: f64 = 0.0;
+    let mut edge_object = None;
 
     for (nx, ny) in neighbors {
         let neighbor_depth = buffers.get_depth(nx, ny);
         let neighbor_normal = buffers.get_normal(nx, ny);
 
+        let object = match (current_depth, neighbor_depth) {
+            (Some(curr_d), Some(neigh_d)) if neigh_d < curr_d => buffers.get_object(nx, ny),
+            (None, Some(_)) => buffers.get_object(nx, ny),
+            _ => current_object,
+        };
+        if config.edge_color_of(object).is_none() {
+            continue;
+        }
+
         let edge_strength = match (current_depth, current_normal, neighbor_depth, neighbor_normal) {
             (Some(curr_d), Some(curr_n), Some(neigh_d), Some(neigh_n)) => {
                 // Both pixels are foreground - compute gradual differences
@@ -159,7 +410,21 @@ fn compute_edge_strength(
                 let dot_product = curr_n.dot(&neigh_n).clamp(-1.0, 1.0);
                 let normal_diff = 1.0 - dot_product;
                 
-                config.depth_weight * normalized_depth_diff + config.normal_weight * normal_diff
+                let mut strength = config.depth_weight * normalized_depth_diff
+                    + config.normal_weight * normal_diff;
+
+                // Parts touching or overlapping meet here, however alike their surfaces
+                let neighbor_object = buffers.get_object(nx, ny);
+                if matches!((current_object, neighbor_object), (Some(a), Some(b)) if a != b) {
+                    strength += config.object_weight;
+                }
+                // Creases sharper than the crease angle are edges at full strength
+                if let Some(angle) = config.crease_angle {
+                    if dot_product < angle.to_radians().cos() {
+                        strength = strength.max(1.0);
+                    }
+                }
+                strength
             }
             (Some(_), Some(_), None, None) => {
                 // Current pixel is foreground, neighbor is background - strong edge
@@ -175,10 +440,13 @@ fn compute_edge_strength(
             }
         };
 
-        max_edge_strength = max_edge_strength.max(edge_strength);
+        if edge_strength > max_edge_strength {
+            max_edge_strength = edge_strength;
+            edge_object = object;
+        }
     }
 
-    max_edge_strength
+    (max_edge_strength, edge_object)
 }
 
 /// Get 4-connected neighbors (up, down, left, right)
@@ -224,7 +492,12 @@ fn get_8_neighbors(x: u32, y: u32) -> Vec<(u32, u32)> {
 }
 
 /// Dilate edge mask for thicker lines
-fn dilate_edges(edge_mask: &[f64], width: u32, height: u32, thickness: f64) -> Vec<f64> {
+fn dilate_edges(
+    edge_mask: &[(f64, Option<usize>)],
+    width: u32,
+    height: u32,
+    thickness: f64,
+) -> Vec<(f64, Option<usize>)> {
     let mut dilated = edge_mask.to_vec();
     let radius = (thickness - 1.0).ceil() as i32;
     
@@ -237,7 +510,7 @@ fn dilate_edges(edge_mask: &[f64], width: u32, height: u32, thickness: f64) -> V
     for y in 0..height {
         for x in 0..width {
             let index = (y * width + x) as usize;
-            let mut max_strength = original[index];
+            let (mut max_strength, mut object) = original[index];
             
             // Check neighborhood for maximum edge strength
             for dy in -radius..=radius {
@@ -252,15 +525,18 @@ fn dilate_edges(edge_mask: &[f64], width: u32, height: u32, thickness: f64) -> V
                             if distance <= thickness {
                                 // Apply distance-based falloff
                                 let falloff = 1.0 - (distance / thickness);
-                                let strength = original[ni] * falloff;
-                                max_strength = max_strength.max(strength);
+                                let strength = original[ni].0 * falloff;
+                                if strength > max_strength {
+                                    max_strength = strength;
+                                    object = original[ni].1;
+                                }
                             }
                         }
                     }
                 }
             }
             
-            dilated[index] = max_strength;
+            dilated[index] = (max_strength, object);
         }
     }
     
@@ -378,8 +654,96 @@ mod tests {
         let edge_mask = detect_edges(&buffers, &config);
         
         // The middle column should have edges due to depth discontinuity
-        let center_index = (1 * buffers.width + 1) as usize;
-        assert!(edge_mask[center_index] > 0.0, "Center pixel should have an edge");
+        let center_index = (buffers.width + 1) as usize;
+        assert!(
+            edge_mask[center_index].0 > 0.0,
+            "Center pixel should have an edge"
+        );
+    }
+
+    #[test]
+    fn test_object_and_crease_edges() {
+        // Two parts side by side at the same depth, the right one turned 30 degrees
+        let mut buffers = OutlineBuffers::new(4, 3);
+        let turned = Vec3::new(0.5, 0.0, 3.0_f64.sqrt() / 2.0);
+        for y in 0..3 {
+            for x in 0..4 {
+                buffers.set_depth(x, y, 5.0);
+                buffers.set_normal(x, y, if x < 2 { Vec3::z() } else { turned });
+                buffers.set_object(x, y, if x < 2 { 0 } else { 1 });
+            }
+        }
+        // Pixels away from the image's right and bottom borders, across the middle row
+        let middle = |config: &OutlineConfig| {
+            let mask = edge_mask(&buffers, config);
+            [4, 5, 6].map(|index| mask[index] > 0.0)
+        };
+
+        let config = OutlineConfig {
+            normal_weight: 0.5,
+            ..OutlineConfig::default()
+        };
+        assert_eq!(middle(&config), [false; 3]);
+
+        let objects = OutlineConfig {
+            object_weight: 1.0,
+            ..config.clone()
+        };
+        assert_eq!(middle(&objects), [false, true, true]);
+
+        let creases = OutlineConfig {
+            crease_angle: Some(20.0),
+            ..config
+        };
+        assert_eq!(middle(&creases), [false, true, true]);
+        let gentle = OutlineConfig {
+            crease_angle: Some(45.0),
+            ..creases
+        };
+        assert_eq!(middle(&gentle), [false; 3]);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_depth_and_normal_maps() {
+        let mut buffers = OutlineBuffers::new(3, 1);
+        buffers.set_depth(0, 0, 2.0);
+        buffers.set_normal(0, 0, Vec3::new(0.0, 0.0, 1.0));
+        buffers.set_depth(1, 0, 12.0);
+        buffers.set_normal(1, 0, Vec3::new(-1.0, 0.0, 0.0));
+
+        // The nearest hit is white, the farthest a tenth of white, background black
+        let depth = buffers.depth_map();
+        assert_eq!(depth.get_pixel(0, 0).0, [65535]);
+        assert_eq!(depth.get_pixel(1, 0).0, [6553]);
+        assert_eq!(depth.get_pixel(2, 0).0, [0]);
+
+        let normals = buffers.normal_map();
+        assert_eq!(normals.get_pixel(0, 0).0, [128, 128, 255]);
+        assert_eq!(normals.get_pixel(1, 0).0, [0, 128, 128]);
+        assert_eq!(normals.get_pixel(2, 0).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_downsample_edges() {
+        let config = OutlineConfig {
+            object_edge_colors: BTreeMap::from([(2, None)]),
+            ..OutlineConfig::default()
+        };
+        // Two 2x2 blocks: a half-covered edge of objects 0 and 1, and one of object 2,
+        // which has no outlines
+        let mask = [
+            (1.0, Some(0)),
+            (0.0, None),
+            (3.0, Some(2)),
+            (0.0, None),
+            (0.5, Some(1)),
+            (0.5, Some(1)),
+            (0.0, None),
+            (0.0, None),
+        ];
+        let downsampled = downsample_edges(&mask, 4, 2, &config);
+        assert_eq!(downsampled, vec![(0.5, Some(0)), (0.0, None)]);
     }
 
     #[test]
@@ -394,6 +758,10 @@ mod tests {
             edge_color: Color::new(1.0, 0.0, 0.0), // Red edges
             use_8_neighbors: false,
             line_thickness: 1.0,
+            object_weight: 0.0,
+            crease_angle: None,
+            object_edge_colors: BTreeMap::new(),
+            supersample: 1,
         };
         
         // Create test data with depth and normal discontinuities
@@ -427,4 +795,25 @@ mod tests {
         // The center pixel should have some red component from edge detection
         assert!(center_pixel.2.x > 0.5, "Center pixel should have red edge contribution");
     }
+
+    #[test]
+    fn test_buffers_from_aovs() {
+        let mut depth = AovBuffer::new(Aov::Depth, 2, 1);
+        let mut normal = AovBuffer::new(Aov::Normal, 2, 1);
+        depth.pixels[1] = [3.0; 3];
+        normal.pixels[1] = [0.0, 0.0, 1.0];
+        let buffers = OutlineBuffers::from_aovs(&depth, &normal).unwrap();
+        assert_eq!(buffers.get_depth(0, 0), None);
+        assert_eq!(buffers.get_depth(1, 0), Some(3.0));
+        assert_eq!(buffers.get_normal(1, 0), Some(Vec3::new(0.0, 0.0, 1.0)));
+        assert!(OutlineBuffers::from_aovs(&normal, &depth).is_err());
+
+        // The background pixel beside the object is an edge in the mask
+        let mask = edge_mask_image(&buffers, &OutlineConfig::default());
+        assert_eq!(mask.get_pixel(0, 0).0, [255]);
+
+        let mut image = image::RgbImage::new(3, 1);
+        let config = OutlineConfig::default();
+        assert!(apply_outline_to_image(&mut image, &buffers, &config).is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,585 @@
+//! Objects whose geometry intersects, for checking assemblies before rendering
+
+use std::cell::OnceCell;
+
+use nalgebra::Matrix4;
+
+use crate::object_stats::label;
+use crate::scene::{parse_transforms, Color, Object, Point, Scene, Vec3};
+
+/// Overlaps no deeper than this, in scene units, count as touching by default
+pub const DEFAULT_OVERLAP_TOLERANCE: f64 = 0.01;
+
+/// Segments around spheres and discs, which are checked as the polygons they make
+pub(crate) const ROUND_SEGMENTS: usize = 32;
+
+/// Two objects whose geometry overlaps
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlap {
+    /// Index into `scene.objects` of the earlier object
+    pub first: usize,
+    /// Index into `scene.objects` of the later object
+    pub second: usize,
+    /// Box around the shared region: where the surfaces cross, and the vertices of
+    /// each object that lie inside the other
+    pub region: (Point, Point),
+}
+
+impl Overlap {
+    /// How far the objects interpenetrate: the smallest side of `region`
+    pub fn depth(&self) -> f64 {
+        (self.region.1 - self.region.0).min()
+    }
+
+    /// One line naming both objects, the depth and where they meet
+    pub fn describe(&self, scene: &Scene) -> String {
+        let center = nalgebra::center(&self.region.0, &self.region.1);
+        format!(
+            "{} and {} overlap by {:.3} around ({:.3}, {:.3}, {:.3})",
+            label(scene, self.first),
+            label(scene, self.second),
+            self.depth(),
+            center.x,
+            center.y,
+            center.z
+        )
+    }
+}
+
+/// Pairs of objects in a loaded `scene` overlapping by more than `tolerance`, deepest first
+///
+/// Spheres, cubes, meshes and instances are solids, so an object inside one counts
+/// even where no surfaces cross; triangles, quads and discs are surfaces only. Planes,
+/// floor grids and volumes are left out, as are meshes whose data isn't loaded.
+/// Spheres and discs are checked as 32-sided polygons inside the true shape.
+pub fn find_overlaps(scene: &Scene, tolerance: f64) -> Result<Vec<Overlap>, String> {
+    if !(tolerance.is_finite() && tolerance >= 0.0) {
+        return Err(format!(
+            "Overlap tolerance must be non-negative, got {}",
+            tolerance
+        ));
+    }
+    let solids = scene
+        .objects
+        .iter()
+        .map(|object| Solid::of(scene, object, ROUND_SEGMENTS))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut overlaps = Vec::new();
+    for (first, a) in solids.iter().enumerate() {
+        let Some(a) = a else { continue };
+        for (second, b) in solids.iter().enumerate().skip(first + 1) {
+            let Some(b) = b else { continue };
+            if let Some(region) = a.overlap(b, tolerance) {
+                let overlap = Overlap {
+                    first,
+                    second,
+                    region,
+                };
+                if overlap.depth() > tolerance {
+                    overlaps.push(overlap);
+                }
+            }
+        }
+    }
+    overlaps.sort_by(|a, b| b.depth().total_cmp(&a.depth()));
+    Ok(overlaps)
+}
+
+/// World-space triangle with its bounding box
+pub(crate) struct Face {
+    pub(crate) vertices: [Point; 3],
+    pub(crate) min: Point,
+    pub(crate) max: Point,
+}
+
+impl Face {
+    pub(crate) fn new(vertices: [Point; 3]) -> Self {
+        let [a, b, c] = vertices;
+        Self {
+            vertices,
+            min: a.inf(&b).inf(&c),
+            max: a.sup(&b).sup(&c),
+        }
+    }
+
+    fn touches(&self, min: &Point, max: &Point) -> bool {
+        (0..3).all(|axis| self.min[axis] <= max[axis] && self.max[axis] >= min[axis])
+    }
+}
+
+/// An object's surface as triangles in world space
+pub(crate) struct Solid {
+    pub(crate) faces: Vec<Face>,
+    pub(crate) min: Point,
+    pub(crate) max: Point,
+    pub(crate) closed: bool, // Encloses a volume, so points can be inside it
+    grid: OnceCell<YzGrid>,
+}
+
+impl Solid {
+    /// `object`'s surface, with spheres and discs as polygons of `segments` sides, or
+    /// None for objects without a finite surface
+    pub(crate) fn of(
+        scene: &Scene,
+        object: &Object,
+        segments: usize,
+    ) -> Result<Option<Solid>, String> {
+        let matrix = |transform: &Option<Vec<String>>| -> Result<Matrix4<f64>, String> {
+            match transform {
+                Some(strings) => parse_transforms(strings),
+                None => Ok(Matrix4::identity()),
+            }
+        };
+        let (triangles, closed) = match object {
+            Object::Sphere {
+                center,
+                radius,
+                transform,
+                ..
+            } => {
+                let (center, matrix) = (Point::from(*center), matrix(transform)?);
+                let triangles = sphere_triangles(*radius, segments)
+                    .into_iter()
+                    .map(|triangle| triangle.map(|p| matrix.transform_point(&(center + p))))
+                    .collect();
+                (triangles, true)
+            }
+            Object::Cube {
+                center,
+                size,
+                transform,
+                ..
+            } => {
+                // As `Cube::bounds` places its corners
+                let (center, matrix) = (Point::from(*center), matrix(transform)?);
+                let half = Vec3::from(*size) / 2.0;
+                let corner = |signs: [f64; 3]| {
+                    let local = Point::from(half.component_mul(&Vec3::from(signs)));
+                    center + matrix.transform_point(&local).coords
+                };
+                (box_triangles(corner), true)
+            }
+            Object::Mesh {
+                mesh_data: Some(mesh),
+                transform,
+                ..
+            } => (mesh_triangles(mesh, &matrix(transform)?), true),
+            Object::Tube {
+                caps, transform, ..
+            } => match object.tessellated_mesh()? {
+                Some(mesh) => (mesh_triangles(&mesh, &matrix(transform)?), *caps),
+                None => return Ok(None),
+            },
+            Object::Arrow { transform, .. } | Object::Glyphs { transform, .. } => {
+                match object.tessellated_mesh()? {
+                    Some(mesh) => (mesh_triangles(&mesh, &matrix(transform)?), true),
+                    None => return Ok(None),
+                }
+            }
+            Object::Instance {
+                reference,
+                transform,
+                ..
+            } => {
+                let prototype = scene.meshes.get(reference);
+                match prototype.and_then(|prototype| prototype.mesh_data.as_ref()) {
+                    Some(mesh) => (mesh_triangles(mesh, &matrix(transform)?), true),
+                    None => return Ok(None),
+                }
+            }
+            Object::Triangle {
+                vertices,
+                transform,
+                ..
+            } => {
+                let points = crate::scene::transform_points(vertices, transform);
+                (vec![[points[0], points[1], points[2]]], false)
+            }
+            Object::Quad {
+                vertices,
+                transform,
+                ..
+            } => {
+                let points = crate::scene::transform_points(vertices, transform);
+                let triangles = vec![
+                    [points[0], points[1], points[2]],
+                    [points[0], points[2], points[3]],
+                ];
+                (triangles, false)
+            }
+            Object::Disc {
+                center,
+                normal,
+                radius,
+                transform,
+                ..
+            } => {
+                let disc = crate::scene::transform_disc(
+                    center,
+                    normal,
+                    *radius,
+                    transform,
+                    Color::zeros(),
+                    0,
+                );
+                let (u, v) = perpendicular_axes(&disc.normal);
+                let rim = |i: usize| {
+                    let angle = std::f64::consts::TAU * i as f64 / segments as f64;
+                    disc.center + (u * angle.cos() + v * angle.sin()) * disc.radius
+                };
+                let triangles = (0..segments)
+                    .map(|i| [disc.center, rim(i), rim(i + 1)])
+                    .collect();
+                (triangles, false)
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Self::from_faces(
+            triangles.into_iter().map(Face::new).collect(),
+            closed,
+        ))
+    }
+
+    /// Surface made of `faces`, or None if there are none
+    pub(crate) fn from_faces(faces: Vec<Face>, closed: bool) -> Option<Solid> {
+        let first = faces.first()?;
+        let (min, max) = faces
+            .iter()
+            .fold((first.min, first.max), |(min, max), face| {
+                (min.inf(&face.min), max.sup(&face.max))
+            });
+        Some(Solid {
+            faces,
+            min,
+            max,
+            closed,
+            grid: OnceCell::new(),
+        })
+    }
+
+    /// Box around where `self` and `other` overlap, if they do by more than `tolerance`
+    fn overlap(&self, other: &Solid, tolerance: f64) -> Option<(Point, Point)> {
+        // The shared region lies inside both bounding boxes
+        let (min, max) = (self.min.sup(&other.min), self.max.inf(&other.max));
+        if (max - min).min() <= tolerance {
+            return None;
+        }
+        let near = |solid: &Solid| -> Vec<usize> {
+            (0..solid.faces.len())
+                .filter(|&face| solid.faces[face].touches(&min, &max))
+                .collect()
+        };
+        let (ours, theirs) = (near(self), near(other));
+
+        let mut region = Region::default();
+        self.add_crossings(&ours, other, &theirs, &mut region);
+        for (solid, faces, container) in [(self, &ours, other), (other, &theirs, self)] {
+            if !container.closed {
+                continue;
+            }
+            for &face in faces {
+                for vertex in &solid.faces[face].vertices {
+                    let in_box = (0..3).all(|axis| (min[axis]..=max[axis]).contains(&vertex[axis]));
+                    if in_box && container.contains(vertex) {
+                        region.add(vertex);
+                    }
+                }
+            }
+        }
+        region.bounds()
+    }
+
+    /// Add the points where faces `ours` of `self` cross faces `theirs` of `other`
+    fn add_crossings(&self, ours: &[usize], other: &Solid, theirs: &[usize], region: &mut Region) {
+        // Sorted by their lowest x, faces too far right to meet one of ours form a tail
+        let mut theirs = theirs.to_vec();
+        theirs.sort_by(|&a, &b| other.faces[a].min.x.total_cmp(&other.faces[b].min.x));
+        for a in ours.iter().map(|&face| &self.faces[face]) {
+            let end = theirs.partition_point(|&face| other.faces[face].min.x <= a.max.x);
+            for b in theirs[..end].iter().map(|&face| &other.faces[face]) {
+                if !a.touches(&b.min, &b.max) {
+                    continue;
+                }
+                // The segment where two triangles cross starts and ends on an edge of one
+                for (edges, triangle) in [(a, b), (b, a)] {
+                    for i in 0..3 {
+                        let (start, end) = (edges.vertices[i], edges.vertices[(i + 1) % 3]);
+                        if let Some(point) = segment_hit(&start, &end, &triangle.vertices) {
+                            region.add(&point);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether `point` is inside this closed surface, by the parity of the faces a ray
+    /// toward +x crosses
+    pub(crate) fn contains(&self, point: &Point) -> bool {
+        // Nudged off the edges and vertices axis-aligned models tend to line up on
+        let size = (self.max - self.min).max();
+        let (y, z) = (
+            point.y + size * 1.414_213_6e-7,
+            point.z + size * 1.732_050_8e-7,
+        );
+        let grid = self.grid.get_or_init(|| YzGrid::new(self));
+        let crossings = grid
+            .faces_at(y, z)
+            .iter()
+            .filter(|&&face| {
+                let face = &self.faces[face];
+                face.max.x >= point.x && ray_x_at(&face.vertices, y, z).is_some_and(|x| x > point.x)
+            })
+            .count();
+        crossings % 2 == 1
+    }
+}
+
+/// Faces bucketed by where they cover the y-z plane, so rays along x test only a few
+struct YzGrid {
+    min: (f64, f64),
+    cell: (f64, f64),
+    cells: usize, // Along each side
+    faces: Vec<Vec<usize>>,
+}
+
+impl YzGrid {
+    fn new(solid: &Solid) -> Self {
+        let cells = ((solid.faces.len() as f64).sqrt().ceil() as usize).clamp(1, 256);
+        let min = (solid.min.y, solid.min.z);
+        let cell = (
+            ((solid.max.y - min.0) / cells as f64).max(f64::MIN_POSITIVE),
+            ((solid.max.z - min.1) / cells as f64).max(f64::MIN_POSITIVE),
+        );
+        let mut grid = Self {
+            min,
+            cell,
+            cells,
+            faces: vec![Vec::new(); cells * cells],
+        };
+        for (index, face) in solid.faces.iter().enumerate() {
+            let (y0, z0) = grid.cell_of(face.min.y, face.min.z);
+            let (y1, z1) = grid.cell_of(face.max.y, face.max.z);
+            for y in y0..=y1 {
+                for z in z0..=z1 {
+                    grid.faces[y * cells + z].push(index);
+                }
+            }
+        }
+        grid
+    }
+
+    fn cell_of(&self, y: f64, z: f64) -> (usize, usize) {
+        let index = |value: f64, min: f64, cell: f64| {
+            (((value - min) / cell).floor().max(0.0) as usize).min(self.cells - 1)
+        };
+        (
+            index(y, self.min.0, self.cell.0),
+            index(z, self.min.1, self.cell.1),
+        )
+    }
+
+    fn faces_at(&self, y: f64, z: f64) -> &[usize] {
+        let (y, z) = self.cell_of(y, z);
+        &self.faces[y * self.cells + z]
+    }
+}
+
+/// Bounding box of the points added so far
+#[derive(Default)]
+struct Region(Option<(Point, Point)>);
+
+impl Region {
+    fn add(&mut self, point: &Point) {
+        self.0 = Some(match self.0 {
+            Some((min, max)) => (min.inf(point), max.sup(point)),
+            None => (*point, *point),
+        });
+    }
+
+    fn bounds(self) -> Option<(Point, Point)> {
+        self.0
+    }
+}
+
+/// Where the segment from `start` to `end` passes through `triangle`, if it does;
+/// segments lying in the triangle's plane don't count
+pub(crate) fn segment_hit(start: &Point, end: &Point, triangle: &[Point; 3]) -> Option<Point> {
+    // Möller–Trumbore, with the segment as a ray over t in [0, 1]
+    let direction = end - start;
+    let edge1 = triangle[1] - triangle[0];
+    let edge2 = triangle[2] - triangle[0];
+    let p = direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    let scale = direction.norm() * edge1.norm() * edge2.norm();
+    if determinant.abs() <= 1e-12 * scale {
+        return None;
+    }
+    let s = start - triangle[0];
+    let u = s.dot(&p) / determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let v = direction.dot(&q) / determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(&q) / determinant;
+    (0.0..=1.0).contains(&t).then(|| start + direction * t)
+}
+
+/// x where the line through (·, `y`, `z`) along x meets `triangle`, if it does
+fn ray_x_at(triangle: &[Point; 3], y: f64, z: f64) -> Option<f64> {
+    let [a, b, c] = triangle;
+    let area = (b.y - a.y) * (c.z - a.z) - (c.y - a.y) * (b.z - a.z);
+    if area == 0.0 {
+        return None; // Edge-on to the ray
+    }
+    let u = ((y - a.y) * (c.z - a.z) - (c.y - a.y) * (z - a.z)) / area;
+    let v = ((b.y - a.y) * (z - a.z) - (y - a.y) * (b.z - a.z)) / area;
+    if u < 0.0 || v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    Some(a.x + u * (b.x - a.x) + v * (c.x - a.x))
+}
+
+/// Triangles of a sphere of `radius` around the origin, from pole to pole, with
+/// `segments` around its equator
+fn sphere_triangles(radius: f64, segments: usize) -> Vec<[Vec3; 3]> {
+    let rings = segments / 2;
+    let point = |ring: usize, segment: usize| {
+        let polar = std::f64::consts::PI * ring as f64 / rings as f64;
+        let azimuth = std::f64::consts::TAU * segment as f64 / segments as f64;
+        Vec3::new(
+            polar.sin() * azimuth.cos(),
+            polar.sin() * azimuth.sin(),
+            polar.cos(),
+        ) * radius
+    };
+    let mut triangles = Vec::new();
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let (a, b) = (point(ring, segment), point(ring, segment + 1));
+            let (c, d) = (point(ring + 1, segment), point(ring + 1, segment + 1));
+            if ring > 0 {
+                triangles.push([a, c, b]);
+            }
+            if ring + 1 < rings {
+                triangles.push([b, c, d]);
+            }
+        }
+    }
+    triangles
+}
+
+/// The 12 triangles of a box with corners at `corner([±1, ±1, ±1])`
+fn box_triangles(corner: impl Fn([f64; 3]) -> Point) -> Vec<[Point; 3]> {
+    let mut triangles = Vec::new();
+    for axis in 0..3 {
+        for side in [-1.0, 1.0] {
+            // Corners of this face, in order around it
+            let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(s, t)| {
+                let mut signs = [0.0; 3];
+                signs[axis] = side;
+                signs[(axis + 1) % 3] = s;
+                signs[(axis + 2) % 3] = t;
+                corner(signs)
+            });
+            triangles.push([corners[0], corners[1], corners[2]]);
+            triangles.push([corners[0], corners[2], corners[3]]);
+        }
+    }
+    triangles
+}
+
+/// A mesh's triangles moved by `matrix`, as the renderer places them
+fn mesh_triangles(mesh: &crate::mesh::Mesh, matrix: &Matrix4<f64>) -> Vec<[Point; 3]> {
+    mesh.triangles
+        .iter()
+        .map(|triangle| {
+            triangle
+                .vertices
+                .map(|vertex| matrix.transform_point(&vertex))
+        })
+        .collect()
+}
+
+/// Two unit vectors perpendicular to `normal` and to each other
+fn perpendicular_axes(normal: &Vec3) -> (Vec3, Vec3) {
+    let helper = match normal.x.abs() < 0.9 {
+        true => Vec3::x(),
+        false => Vec3::y(),
+    };
+    let u = normal.cross(&helper).normalize();
+    (u, normal.cross(&u).normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(objects: &str) -> Scene {
+        Scene::from_json_str(&format!(
+            r##"{{
+                "camera": {{"kind": "ortho", "position": [0, -10, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4}},
+                "objects": [{}],
+                "lights": [],
+                "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 1}}}}
+            }}"##,
+            objects
+        ))
+        .unwrap()
+    }
+
+    fn cube(name: &str, center: [f64; 3], size: [f64; 3]) -> String {
+        format!(
+            r##"{{"kind": "cube", "name": "{}", "center": {:?}, "size": {:?},
+                "material": {{"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.8, "specular": 0, "shininess": 1}}}}"##,
+            name, center, size
+        )
+    }
+
+    #[test]
+    fn test_find_overlaps() {
+        // b sinks 0.5 into a; c rests on a's top face; d crosses b like a plus sign
+        let objects = [
+            cube("a", [0.0, 0.0, 0.0], [2.0, 2.0, 2.0]),
+            cube("b", [1.5, 0.0, 0.0], [2.0, 2.0, 2.0]),
+            cube("c", [0.0, 0.0, 1.5], [1.0, 1.0, 1.0]),
+            cube("d", [2.0, 0.0, 0.0], [0.4, 4.0, 0.4]),
+        ];
+        let scene = scene(&objects.join(","));
+        let overlaps = find_overlaps(&scene, DEFAULT_OVERLAP_TOLERANCE).unwrap();
+        let pairs: Vec<(usize, usize)> = overlaps.iter().map(|o| (o.first, o.second)).collect();
+        assert_eq!(pairs, [(0, 1), (1, 3)]);
+        assert!((overlaps[0].depth() - 0.5).abs() < 1e-9);
+        assert!((overlaps[1].depth() - 0.4).abs() < 1e-9);
+        assert!(overlaps[0]
+            .describe(&scene)
+            .starts_with("'a' (cube) and 'b' (cube) overlap by 0.500"));
+
+        // A tolerance above a depth lets that pair pass
+        assert_eq!(find_overlaps(&scene, 0.45).unwrap().len(), 1);
+        assert!(find_overlaps(&scene, -1.0).is_err());
+    }
+
+    #[test]
+    fn test_contained_objects() {
+        // No surfaces cross, but the sphere lies inside the box; the quad only touches it
+        let scene = scene(&format!(
+            r##"{},
+            {{"kind": "sphere", "center": [0, 0, 0], "radius": 0.5,
+                "material": {{"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.8, "specular": 0, "shininess": 1}}}},
+            {{"kind": "quad", "vertices": [[-1, -1, 1], [1, -1, 1], [1, 1, 1], [-1, 1, 1]],
+                "material": {{"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.8, "specular": 0, "shininess": 1}}}}"##,
+            cube("box", [0.0, 0.0, 0.0], [2.0, 2.0, 2.0])
+        ));
+        let overlaps = find_overlaps(&scene, DEFAULT_OVERLAP_TOLERANCE).unwrap();
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!((overlaps[0].first, overlaps[0].second), (0, 1));
+        assert!((overlaps[0].depth() - 1.0).abs() < 1e-9);
+    }
+}
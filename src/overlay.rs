@@ -0,0 +1,410 @@
+use crate::camera::Camera;
+use crate::colormap::{sample_stops, ColorScale};
+use crate::scene::{Color, Point, Scene, Vec3};
+
+/// Debug drawing composited over a finished render, for checking transforms and framing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overlay {
+    /// World-space bounding box of every finite object, in yellow
+    Bounds,
+    /// X (red), Y (green) and Z (blue) axes from the origin
+    Axes,
+    /// Legend of the first glyph field colored by a scalar: its color map as a bar down
+    /// the right of the image, labeled with the scalars at either end
+    ColorBar,
+}
+
+impl Overlay {
+    /// Every overlay, in the order they are drawn
+    pub const ALL: [Overlay; 3] = [Overlay::Bounds, Overlay::Axes, Overlay::ColorBar];
+
+    /// Name used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overlay::Bounds => "bounds",
+            Overlay::Axes => "axes",
+            Overlay::ColorBar => "color_bar",
+        }
+    }
+}
+
+impl std::str::FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Overlay::ALL
+            .into_iter()
+            .find(|overlay| overlay.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Overlay::ALL.iter().map(Overlay::name).collect();
+                format!(
+                    "Invalid overlay '{}'. Valid options are: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Draw `overlays` over the image as unshaded lines, on top of everything
+///
+/// Lines are projected through `camera`, so they only appear for orthographic and
+/// perspective cameras. Axes are half as long as the scene's finite bounds are wide.
+pub fn apply_overlays(
+    image_data: &mut [(u32, u32, Color)],
+    width: u32,
+    height: u32,
+    camera: &Camera,
+    scene: &Scene,
+    overlays: &[Overlay],
+) {
+    let mut canvas = Canvas {
+        width,
+        height,
+        // Keep lines visible on large renders
+        thickness: (width.max(height) / 400).max(1),
+        pixels: vec![None; (width * height) as usize],
+        camera,
+    };
+
+    for overlay in Overlay::ALL.iter().filter(|o| overlays.contains(o)) {
+        match overlay {
+            Overlay::Bounds => {
+                let yellow = Color::new(1.0, 0.85, 0.0);
+                for (min, max) in scene.objects.iter().filter_map(|o| scene.object_bounds(o)) {
+                    canvas.draw_box(&min, &max, yellow);
+                }
+            }
+            Overlay::Axes => {
+                let length = scene
+                    .compute_finite_bounds()
+                    .map_or(1.0, |(min, max)| (max - min).max() / 2.0)
+                    .max(f64::EPSILON);
+                let origin = Point::origin();
+                for (axis, color) in [
+                    (Vec3::x(), Color::new(1.0, 0.0, 0.0)),
+                    (Vec3::y(), Color::new(0.0, 1.0, 0.0)),
+                    (Vec3::z(), Color::new(0.0, 0.0, 1.0)),
+                ] {
+                    canvas.draw_line(&origin, &(origin + axis * length), color);
+                }
+            }
+            Overlay::ColorBar => {
+                let scale = (scene.objects.iter())
+                    .find_map(|object| crate::glyphs::glyph_color_scale(object).ok().flatten());
+                if let Some(scale) = scale {
+                    canvas.draw_color_bar(&scale);
+                }
+            }
+        }
+    }
+
+    for (x, y, color) in image_data.iter_mut() {
+        if let Some(line) = canvas.pixels[(*y * width + *x) as usize] {
+            *color = line;
+        }
+    }
+}
+
+/// Line layer the size of the image
+struct Canvas<'a> {
+    width: u32,
+    height: u32,
+    thickness: u32,
+    pixels: Vec<Option<Color>>,
+    camera: &'a Camera,
+}
+
+impl Canvas<'_> {
+    fn draw_box(&mut self, min: &Point, max: &Point, color: Color) {
+        let corner = |i: usize| {
+            Point::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        // Each edge joins two corners that differ in one axis
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.draw_line(&corner(i), &corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Project and rasterize a world-space segment
+    fn draw_line(&mut self, a: &Point, b: &Point, color: Color) {
+        let Some((a, b)) = self.clip_near(*a, *b) else {
+            return;
+        };
+        let (Some(a), Some(b)) = (self.to_pixels(&a), self.to_pixels(&b)) else {
+            return;
+        };
+        let Some((a, b)) = self.clip_to_image(a, b) else {
+            return;
+        };
+
+        let steps = (b.0 - a.0).abs().max((b.1 - a.1).abs()).ceil().max(1.0) as u32;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            self.plot(a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t, color);
+        }
+    }
+
+    /// Cut the part of a segment behind a perspective camera
+    fn clip_near(&self, a: Point, b: Point) -> Option<(Point, Point)> {
+        if !self.camera.is_perspective() {
+            return Some((a, b));
+        }
+        let near = 1e-3;
+        let depth = |p: &Point| (p - self.camera.origin).dot(self.camera.view_direction.as_ref());
+        let (depth_a, depth_b) = (depth(&a), depth(&b));
+        if depth_a < near && depth_b < near {
+            return None;
+        }
+        let cut = |inside: Point, outside: Point, d_in: f64, d_out: f64| {
+            inside + (outside - inside) * ((d_in - near) / (d_in - d_out))
+        };
+        if depth_a < near {
+            Some((cut(b, a, depth_b, depth_a), b))
+        } else if depth_b < near {
+            Some((a, cut(a, b, depth_a, depth_b)))
+        } else {
+            Some((a, b))
+        }
+    }
+
+    /// Pixel coordinates, with y growing downwards as in the image
+    fn to_pixels(&self, point: &Point) -> Option<(f64, f64)> {
+        let (u, v) = self.camera.project(point)?;
+        Some((u * self.width as f64, (1.0 - v) * self.height as f64))
+    }
+
+    /// Liang-Barsky clip of a pixel-space segment to the image rectangle
+    fn clip_to_image(&self, a: (f64, f64), b: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let (mut t0, mut t1) = (0.0f64, 1.0f64);
+        for (p, q) in [
+            (-dx, a.0),
+            (dx, self.width as f64 - a.0),
+            (-dy, a.1),
+            (dy, self.height as f64 - a.1),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+        let start = (a.0 + dx * t0, a.1 + dy * t0);
+        (t0 <= t1).then_some((start, (a.0 + dx * t1, a.1 + dy * t1)))
+    }
+
+    /// Color map legend at the right edge: the high end at the top, each end labeled
+    /// with its value, on a white panel
+    fn draw_color_bar(&mut self, scale: &ColorScale) {
+        let range = scale.range;
+        if !range.iter().all(|value| value.is_finite()) {
+            return;
+        }
+        // Font pixels are `unit` image pixels across
+        let unit = (self.width.max(self.height) / 200).max(1) as i64;
+        let (width, height) = (self.width as i64, self.height as i64);
+        let labels = [format_label(range[1]), format_label(range[0])];
+        let text_width = |text: &str| text.len() as i64 * 4 * unit - unit;
+        let widest = labels
+            .iter()
+            .map(|label| text_width(label))
+            .max()
+            .unwrap_or(0);
+
+        let right = width - 4 * unit;
+        let (top, bottom) = (height / 4, height * 3 / 4);
+        let left = right - 4 * unit;
+        let gap = 2 * unit;
+        let white = Color::new(1.0, 1.0, 1.0);
+        let ink = Color::new(0.1, 0.1, 0.1);
+        self.fill(
+            (left.min(right - widest) - gap, top - 5 * unit - 2 * gap),
+            (right + gap, bottom + 5 * unit + 2 * gap),
+            white,
+        );
+        self.fill((left - 1, top - 1), (right + 1, bottom + 1), ink);
+        for y in top..bottom {
+            let share = 1.0 - (y - top) as f64 / (bottom - top - 1).max(1) as f64;
+            let color = sample_stops(&scale.stops, share);
+            self.fill((left, y), (right, y + 1), color);
+        }
+        let [high, low] = &labels;
+        let high_at = (right - text_width(high), top - 5 * unit - gap);
+        self.draw_text(high, high_at, unit, ink);
+        self.draw_text(low, (right - text_width(low), bottom + gap), unit, ink);
+    }
+
+    /// Text in a 3 by 5 pixel font, `unit` image pixels to a font pixel, from its top left
+    fn draw_text(&mut self, text: &str, origin: (i64, i64), unit: i64, color: Color) {
+        for (index, character) in text.chars().enumerate() {
+            let rows = font_rows(character);
+            let x = origin.0 + index as i64 * 4 * unit;
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        let corner = (x + column * unit, origin.1 + row as i64 * unit);
+                        self.fill(corner, (corner.0 + unit, corner.1 + unit), color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fill the pixels from `min` up to but not including `max`, clipped to the image
+    fn fill(&mut self, min: (i64, i64), max: (i64, i64), color: Color) {
+        for y in min.1.max(0)..max.1.min(self.height as i64) {
+            for x in min.0.max(0)..max.0.min(self.width as i64) {
+                self.pixels[(y as u32 * self.width + x as u32) as usize] = Some(color);
+            }
+        }
+    }
+
+    fn plot(&mut self, x: f64, y: f64, color: Color) {
+        let half = (self.thickness / 2) as i64;
+        let (x, y) = (x.floor() as i64, y.floor() as i64);
+        for py in y - half..y - half + self.thickness as i64 {
+            for px in x - half..x - half + self.thickness as i64 {
+                if (0..self.width as i64).contains(&px) && (0..self.height as i64).contains(&py) {
+                    self.pixels[(py as u32 * self.width + px as u32) as usize] = Some(color);
+                }
+            }
+        }
+    }
+}
+
+/// Color bar label: up to four significant digits, in exponent form when very large or
+/// small
+fn format_label(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude == 0.0 {
+        return "0".to_string();
+    }
+    if !(1e-3..1e5).contains(&magnitude) {
+        return format!("{:.2e}", value);
+    }
+    let decimals = (3 - magnitude.log10().floor() as i64).clamp(0, 6) as usize;
+    let text = format!("{:.*}", decimals, value);
+    match text.contains('.') {
+        true => text.trim_end_matches('0').trim_end_matches('.').to_string(),
+        false => text,
+    }
+}
+
+/// Rows of a character in the color bar's 3 by 5 font, high bit on the left; blank for
+/// characters labels don't use
+fn font_rows(character: char) -> [u8; 5] {
+    match character {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        'e' => [0b000, 0b111, 0b110, 0b100, 0b111],
+        _ => [0; 5],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlays_draw_projected_lines() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0], "width": 4, "height": 4},
+            "objects": [{"kind": "cube", "center": [0, 0, 0], "size": [2, 2, 2],
+                "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+        }"##,
+        )
+        .unwrap();
+        let camera = Camera::from_config(&scene.camera, 1.0).unwrap();
+        let black = Color::new(0.0, 0.0, 0.0);
+        let blank: Vec<(u32, u32, Color)> = (0..40)
+            .flat_map(|y| (0..40).map(move |x| (x, y, black)))
+            .collect();
+        let pixel = |data: &[(u32, u32, Color)], x: u32, y: u32| data[(y * 40 + x) as usize].2;
+
+        // The box's outline runs from pixel 10 to 30 on this 4-unit-wide view
+        let mut data = blank.clone();
+        apply_overlays(&mut data, 40, 40, &camera, &scene, &[Overlay::Bounds]);
+        assert_eq!(pixel(&data, 10, 20), Color::new(1.0, 0.85, 0.0));
+        assert_eq!(pixel(&data, 20, 30), Color::new(1.0, 0.85, 0.0));
+        assert_eq!(pixel(&data, 20, 20), black);
+
+        // X points right and Y up the image from the center, one unit long
+        let mut data = blank.clone();
+        apply_overlays(&mut data, 40, 40, &camera, &scene, &[Overlay::Axes]);
+        assert_eq!(pixel(&data, 25, 20), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(pixel(&data, 20, 15), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(pixel(&data, 35, 20), black);
+
+        assert_eq!("axes".parse::<Overlay>(), Ok(Overlay::Axes));
+        assert!("grid".parse::<Overlay>().is_err());
+    }
+
+    #[test]
+    fn test_color_bar() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0], "width": 4, "height": 4},
+            "objects": [],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+        }"##,
+        )
+        .unwrap();
+        let glyphs = r##"{"kind": "glyphs", "filename": "points.csv", "size": 1, "scalar": "t",
+            "color_map": ["#000000", "#FFFFFF"],
+            "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}"##;
+        let mut object: crate::scene::Object = serde_json::from_str(glyphs).unwrap();
+        let csv = b"x,y,z,t\n0,0,0,-2\n0,0,0,10\n";
+        let data = crate::glyphs::DataTable::from_bytes("points.csv", csv).unwrap();
+        if let crate::scene::Object::Glyphs { table, .. } = &mut object {
+            *table = Some(std::sync::Arc::new(data));
+        }
+        scene.objects.push(object);
+
+        let camera = Camera::from_config(&scene.camera, 1.0).unwrap();
+        let black = Color::new(0.0, 0.0, 0.0);
+        let mut data: Vec<(u32, u32, Color)> = (0..100)
+            .flat_map(|y| (0..100).map(move |x| (x, y, black)))
+            .collect();
+        apply_overlays(&mut data, 100, 100, &camera, &scene, &[Overlay::ColorBar]);
+        let pixel = |x: u32, y: u32| data[(y * 100 + x) as usize].2;
+
+        // The bar runs from white at the top to black at the bottom, on a white panel
+        assert!(pixel(94, 25).x > 0.99 && pixel(94, 74).x < 0.01);
+        assert!((pixel(94, 50).x - 0.5).abs() < 0.02);
+        assert_eq!(pixel(90, 80), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(pixel(50, 50), black);
+
+        assert_eq!(format_label(10.0), "10");
+        assert_eq!(format_label(-0.125), "-0.125");
+        assert_eq!(format_label(2.0 / 3.0), "0.6667");
+        assert_eq!(format_label(123456.0), "1.23e5");
+        assert_eq!(format_label(0.0), "0");
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::scene::Scene;
+
+/// Material and light changes applied to a loaded scene by name
+///
+/// Each patch is a JSON merge patch: listed fields replace the scene's values,
+/// nested objects such as a texture merge field by field, and `null` removes an
+/// optional field. Keeping these in a sidecar file leaves the scene itself untouched.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Overrides {
+    /// Patches keyed by object `name`
+    #[serde(default)]
+    pub objects: BTreeMap<String, ObjectOverride>,
+    /// Patches to every light field, keyed by light `name`
+    #[serde(default)]
+    pub lights: BTreeMap<String, Value>,
+}
+
+/// Changes to one named object
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectOverride {
+    pub material: Option<Value>,
+}
+
+impl Overrides {
+    /// Load overrides from a JSON file
+    pub fn from_json_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Patch every object and light whose name has an entry
+    ///
+    /// Names that match nothing in the scene are an error, so a typo doesn't
+    /// silently leave the scene unchanged.
+    pub fn apply(&self, scene: &mut Scene) -> Result<(), String> {
+        for (name, object_override) in &self.objects {
+            let mut found = false;
+            for object in &mut scene.objects {
+                if object.name() != Some(name.as_str()) {
+                    continue;
+                }
+                found = true;
+                if let (Some(patch), Some(material)) =
+                    (&object_override.material, object.material_mut())
+                {
+                    let loaded = material.clone();
+                    *material = patched(material, patch)
+                        .and_then(|mut material| {
+                            material.reuse_height_maps(loaded)?;
+                            Ok(material)
+                        })
+                        .map_err(|e| format!("Override for object '{}': {}", name, e))?;
+                }
+            }
+            if !found {
+                return Err(format!("Override for unknown object '{}'", name));
+            }
+        }
+
+        for (name, patch) in &self.lights {
+            let mut found = false;
+            for light in &mut scene.lights {
+                if light.name.as_deref() != Some(name.as_str()) {
+                    continue;
+                }
+                found = true;
+                *light = patched(light, patch)
+                    .map_err(|e| format!("Override for light '{}': {}", name, e))?;
+            }
+            if !found {
+                return Err(format!("Override for unknown light '{}'", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `value` with `patch` merged into its JSON form
+fn patched<T: Serialize + DeserializeOwned>(value: &T, patch: &Value) -> Result<T, String> {
+    let mut json = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    merge_patch(&mut json, patch);
+    serde_json::from_value(json).map_err(|e| e.to_string())
+}
+
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Object;
+
+    #[test]
+    fn test_overrides_patch_by_name() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+                "objects": [
+                    {"kind": "sphere", "name": "ball", "center": [0, 0, 0], "radius": 1,
+                     "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10, "reflectivity": 0.5}},
+                    {"kind": "sphere", "center": [2, 0, 0], "radius": 1,
+                     "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}
+                ],
+                "lights": [{"name": "key", "position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1.0}],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+            }"##,
+        )
+        .unwrap();
+
+        let overrides: Overrides = serde_json::from_str(
+            r##"{
+                "objects": {"ball": {"material": {"color": "#FF0000", "reflectivity": null}}},
+                "lights": {"key": {"intensity": 2.5}}
+            }"##,
+        )
+        .unwrap();
+        overrides.apply(&mut scene).unwrap();
+
+        let Object::Sphere { material, .. } = &scene.objects[0] else {
+            panic!("expected a sphere");
+        };
+        assert_eq!(material.color, "#FF0000");
+        assert_eq!(material.reflectivity, None);
+        assert_eq!(material.shininess, 10.0);
+        let Object::Sphere { material, .. } = &scene.objects[1] else {
+            panic!("expected a sphere");
+        };
+        assert_eq!(material.color, "#FFFFFF");
+        assert_eq!(scene.lights[0].intensity, 2.5);
+        assert_eq!(scene.lights[0].color, "#FFFFFF");
+
+        // Unknown names and invalid values are errors
+        let unknown: Overrides =
+            serde_json::from_str(r#"{"objects": {"bal": {"material": {}}}}"#).unwrap();
+        assert!(unknown.apply(&mut scene).is_err());
+        let invalid: Overrides =
+            serde_json::from_str(r#"{"lights": {"key": {"intensity": "bright"}}}"#).unwrap();
+        let error = invalid.apply(&mut scene).unwrap_err();
+        assert!(error.contains("light 'key'"), "{}", error);
+    }
+}
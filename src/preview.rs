@@ -0,0 +1,321 @@
+use image::RgbImage;
+
+use crate::assets::StableHasher;
+use crate::glyphs::DataTable;
+use crate::mesh::Mesh;
+use crate::renderer::{PrimaryHitBuffer, Renderer};
+use crate::scene::{Object, Scene};
+
+/// What a `PreviewSession` had to redo for the latest render
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreviewUpdate {
+    /// Camera, geometry or sampling changed: primary rays were traced from scratch
+    Full,
+    /// Only materials, lights or settings changed: cached primary hits were reshaded
+    ShadingOnly,
+}
+
+/// Repeated renders of an evolving scene for watch/preview workflows
+///
+/// The session remembers the primary ray hits of the previous render. When an edit
+/// leaves the camera and geometry untouched (for example a material color, a light
+/// or fog), the next render skips primary intersection and only recomputes shading.
+/// Shadow and reflection rays are still traced, so the output is identical to a full
+/// render. The hit buffer costs roughly 128 bytes per primary sample.
+pub struct PreviewSession {
+    renderer: Renderer,
+    geometry_key: Option<u64>,
+    primary_hits: Option<PrimaryHitBuffer>,
+}
+
+impl PreviewSession {
+    pub fn new(renderer: Renderer) -> Self {
+        Self {
+            renderer,
+            geometry_key: None,
+            primary_hits: None,
+        }
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    /// Render `scene`, reusing cached primary hits when only shading inputs changed
+    pub fn render(
+        &mut self,
+        scene: &Scene,
+    ) -> Result<(RgbImage, PreviewUpdate), Box<dyn std::error::Error>> {
+        let key = geometry_fingerprint(scene, &self.renderer)?;
+
+        let update = if self.geometry_key == Some(key) && self.primary_hits.is_some() {
+            PreviewUpdate::ShadingOnly
+        } else {
+            self.primary_hits = Some(PrimaryHitBuffer::new(self.renderer.primary_sample_count()));
+            self.geometry_key = Some(key);
+            PreviewUpdate::Full
+        };
+
+        let pixels = self
+            .renderer
+            .render_with_primary_hits(scene, self.primary_hits.as_ref())?;
+        Ok((self.renderer.image_from_rgb(pixels), update))
+    }
+}
+
+/// Hash of everything that determines where primary rays hit
+///
+/// Covers the camera, object geometry and transforms (materials stripped), the auto
+/// floor, loaded mesh triangles and glyph data, clip planes, and the renderer's resolution and
+/// sampling pattern.
+fn geometry_fingerprint(
+    scene: &Scene,
+    renderer: &Renderer,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let mut hasher = StableHasher::new();
+
+    let sampling = format!(
+        "{}x{} samples={} aa={:?} seed={:?}",
+        renderer.width,
+        renderer.height,
+        renderer.samples,
+        renderer.anti_aliasing_mode,
+        renderer.seed
+    );
+    hasher.update(sampling.as_bytes());
+    hasher.update(&serde_json::to_vec(&scene.camera)?);
+    hasher.update(&serde_json::to_vec(&scene.scene_settings.clip_planes)?);
+
+    // The auto floor is traced as one more object after the scene's own
+    let floor = scene.auto_floor()?;
+    for object in scene.objects.iter().chain(&floor) {
+        let mut value = serde_json::to_value(object)?;
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("material");
+        }
+        hasher.update(&serde_json::to_vec(&value)?);
+
+        // The mesh filename alone does not capture edits to the STL file itself
+        if let Object::Mesh {
+            mesh_data, lods, ..
+        } = object
+        {
+            // Each level is hashed, loaded or not, so a level's triangles can't pass
+            // for the next one's
+            let levels = lods.iter().map(|lod| &lod.mesh_data);
+            for mesh in std::iter::once(mesh_data).chain(levels) {
+                hasher.update(&[mesh.is_some() as u8]);
+                if let Some(mesh) = mesh {
+                    hash_triangles(&mut hasher, mesh);
+                }
+            }
+        }
+        // Nor does the glyph field's filename capture edits to its data
+        if let Object::Glyphs {
+            table: Some(table), ..
+        } = object
+        {
+            hash_table(&mut hasher, table);
+        }
+    }
+
+    // Instances are covered by their `ref` above and the shared mesh here
+    for (name, prototype) in &scene.meshes {
+        hasher.update(name.as_bytes());
+        if let Some(mesh) = &prototype.mesh_data {
+            hash_triangles(&mut hasher, mesh);
+        }
+    }
+
+    Ok(hasher.finish())
+}
+
+fn hash_table(hasher: &mut StableHasher, table: &DataTable) {
+    for column in &table.columns {
+        hasher.update(column.as_bytes());
+        hasher.update(&[0]);
+    }
+    for value in table.rows.iter().flatten() {
+        hasher.update(&value.to_le_bytes());
+    }
+}
+
+fn hash_triangles(hasher: &mut StableHasher, mesh: &Mesh) {
+    for triangle in &mesh.triangles {
+        for vertex in &triangle.vertices {
+            for coordinate in vertex.iter() {
+                hasher.update(&coordinate.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{AutoFloor, ClipPlane, Light, Material};
+
+    fn test_scene() -> Scene {
+        let mut scene = Scene::default();
+        scene.camera.position = [0.0, -5.0, 1.0];
+        scene.camera.target = [0.0, 0.0, 0.0];
+        scene.camera.width = 4.0;
+        scene.camera.height = 4.0;
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.lights.push(Light {
+            position: [2.0, -2.0, 3.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+        scene
+    }
+
+    #[test]
+    fn test_shading_only_edit_matches_full_render() {
+        let mut renderer = Renderer::new(16, 16);
+        renderer.anti_aliasing_mode = crate::renderer::AntiAliasingMode::Stochastic;
+        renderer.samples = 2;
+        let mut session = PreviewSession::new(renderer);
+
+        let mut scene = test_scene();
+        let (_, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::Full);
+
+        // Material-only edit reuses primary hits and matches a from-scratch render
+        if let Object::Sphere { material, .. } = &mut scene.objects[0] {
+            material.color = "#FF0000".to_string();
+        }
+        let (image, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::ShadingOnly);
+        assert_eq!(image, session.renderer().render(&scene).unwrap());
+
+        // Moving geometry forces a full re-render
+        if let Object::Sphere { center, .. } = &mut scene.objects[0] {
+            center[0] = 0.5;
+        }
+        let (image, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::Full);
+        assert_eq!(image, session.renderer().render(&scene).unwrap());
+    }
+
+    #[test]
+    fn test_clip_plane_edit_forces_full_render() {
+        let mut session = PreviewSession::new(Renderer::new(12, 9));
+        let mut scene = test_scene();
+        session.render(&scene).unwrap();
+
+        // Cutting the sphere open changes what primary rays hit
+        scene.scene_settings.clip_planes.push(ClipPlane {
+            point: [0.0, 0.0, 0.0],
+            normal: [0.0, -1.0, 0.0],
+        });
+        let (image, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::Full);
+        assert_eq!(image, session.renderer().render(&scene).unwrap());
+
+        scene.scene_settings.clip_planes[0].point[1] = 0.5;
+        let (_, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::Full);
+    }
+
+    #[test]
+    fn test_auto_floor_edit_forces_full_render() {
+        let mut session = PreviewSession::new(Renderer::new(12, 9));
+        let mut scene = test_scene();
+        scene.scene_settings.auto_floor = Some(AutoFloor::default());
+        session.render(&scene).unwrap();
+
+        scene.scene_settings.auto_floor.as_mut().unwrap().offset = 0.5;
+        let (image, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::Full);
+        assert_eq!(image, session.renderer().render(&scene).unwrap());
+
+        scene.scene_settings.auto_floor = None;
+        let (_, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::Full);
+    }
+
+    #[test]
+    fn test_glyph_data_edit_forces_full_render() {
+        let mut session = PreviewSession::new(Renderer::new(12, 9));
+        let mut scene = test_scene();
+        let glyphs = serde_json::from_str(
+            r##"{"kind": "glyphs", "filename": "points.csv", "glyph": "cube", "size": 0.5,
+                "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}"##,
+        )
+        .unwrap();
+        scene.objects.push(glyphs);
+        let mut load = |csv: &str| {
+            let data = DataTable::parse_csv(csv).unwrap();
+            if let Object::Glyphs { table, .. } = &mut scene.objects[1] {
+                *table = Some(std::sync::Arc::new(data));
+            }
+            session.render(&scene).unwrap()
+        };
+        load("x,y,z\n1.5,0,0\n");
+
+        // Same file name, new points
+        let (_, update) = load("x,y,z\n-1.5,0,0\n");
+        assert_eq!(update, PreviewUpdate::Full);
+    }
+
+    #[test]
+    fn test_lod_mesh_edit_changes_fingerprint() {
+        let renderer = Renderer::new(12, 9);
+        let mut scene = test_scene();
+        let mesh = serde_json::from_str(
+            r##"{"kind": "mesh", "filename": "part.stl",
+                "lods": [{"filename": "part-low.stl", "max_pixels": 50}],
+                "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}"##,
+        )
+        .unwrap();
+        scene.objects.push(mesh);
+        let square = |size: f64| {
+            Mesh::from_polygon(
+                &[(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)]
+                    .map(|(x, z)| crate::scene::Point::new(x, 0.0, z)),
+            )
+        };
+        let mut load = |low: f64| {
+            if let Object::Mesh {
+                mesh_data, lods, ..
+            } = &mut scene.objects[1]
+            {
+                *mesh_data = Some(square(1.0));
+                lods[0].mesh_data = Some(square(low));
+            }
+            geometry_fingerprint(&scene, &renderer).unwrap()
+        };
+
+        // Same file names and full-detail mesh, new triangles in the lower level
+        assert_ne!(load(0.5), load(0.25));
+        assert_eq!(load(0.25), load(0.25));
+    }
+
+    #[test]
+    fn test_quincunx_reuse_matches_full_render() {
+        let mut session = PreviewSession::new(Renderer::new(12, 9));
+        let mut scene = test_scene();
+        session.render(&scene).unwrap();
+
+        scene.lights[0].intensity = 0.5;
+        let (image, update) = session.render(&scene).unwrap();
+        assert_eq!(update, PreviewUpdate::ShadingOnly);
+        assert_eq!(image, session.renderer().render(&scene).unwrap());
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use crate::time::Instant;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+static EVENTS: Mutex<Vec<TraceEvent>> = Mutex::new(Vec::new());
+
+static RAY_NANOS: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+static RAY_COUNTS: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Categories of rays whose CPU time is accumulated across threads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayKind {
+    /// Camera rays up to their first hit
+    Primary,
+    /// Light visibility and ambient occlusion rays
+    Shadow,
+    /// Reflected and refracted rays including their shading (inclusive of nested bounces)
+    Reflection,
+}
+
+impl RayKind {
+    const ALL: [RayKind; 3] = [RayKind::Primary, RayKind::Shadow, RayKind::Reflection];
+
+    fn label(self) -> &'static str {
+        match self {
+            RayKind::Primary => "primary rays",
+            RayKind::Shadow => "shadow rays",
+            RayKind::Reflection => "reflections",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TraceEvent {
+    name: String,
+    start_us: f64,
+    duration_us: f64,
+    tid: u32,
+    args: serde_json::Value,
+}
+
+/// Turn profiling on for the rest of the process
+///
+/// Profiling is process-wide and off by default; while off every hook is a single
+/// atomic load. Wall-clock phases (scene parse, mesh load, acceleration build,
+/// render, image encode) become nested spans on the main track. Ray work runs on
+/// many threads at once, so primary, shadow and reflection rays are accumulated as
+/// inclusive CPU time and reported as one span per category on separate tracks.
+pub fn enable() {
+    EPOCH.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn micros_since_epoch(instant: Instant) -> f64 {
+    let epoch = *EPOCH.get_or_init(Instant::now);
+    instant.saturating_duration_since(epoch).as_secs_f64() * 1e6
+}
+
+/// Records a wall-clock span on the main track when dropped
+pub struct Span {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start {
+            push_event(TraceEvent {
+                name: self.name.to_string(),
+                start_us: micros_since_epoch(start),
+                duration_us: start.elapsed().as_secs_f64() * 1e6,
+                tid: 1,
+                args: serde_json::Value::Null,
+            });
+        }
+    }
+}
+
+/// Start a named phase; it ends when the returned guard goes out of scope
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        start: is_enabled().then(Instant::now),
+    }
+}
+
+/// Run `f`, adding its duration to the CPU total for `kind`
+#[inline]
+pub(crate) fn time_rays<T>(kind: RayKind, f: impl FnOnce() -> T) -> T {
+    if !is_enabled() {
+        return f();
+    }
+
+    let start = Instant::now();
+    let result = f();
+    let nanos = start.elapsed().as_nanos() as u64;
+    RAY_NANOS[kind as usize].fetch_add(nanos, Ordering::Relaxed);
+    RAY_COUNTS[kind as usize].fetch_add(1, Ordering::Relaxed);
+    result
+}
+
+/// Emit the accumulated ray totals as spans starting at `render_start`, then reset them
+pub(crate) fn flush_ray_totals(render_start: Instant) {
+    if !is_enabled() {
+        return;
+    }
+
+    for (track, kind) in RayKind::ALL.into_iter().enumerate() {
+        let nanos = RAY_NANOS[kind as usize].swap(0, Ordering::Relaxed);
+        let count = RAY_COUNTS[kind as usize].swap(0, Ordering::Relaxed);
+        if count == 0 {
+            continue;
+        }
+        push_event(TraceEvent {
+            name: format!("{} (cpu total)", kind.label()),
+            start_us: micros_since_epoch(render_start),
+            duration_us: nanos as f64 / 1e3,
+            tid: 2 + track as u32,
+            args: serde_json::json!({ "count": count }),
+        });
+    }
+}
+
+fn push_event(event: TraceEvent) {
+    if let Ok(mut events) = EVENTS.lock() {
+        events.push(event);
+    }
+}
+
+/// Build the Chrome tracing document for everything recorded so far
+pub fn chrome_trace() -> serde_json::Value {
+    let events = EVENTS.lock().map(|e| e.clone()).unwrap_or_default();
+
+    let mut trace_events = vec![serde_json::json!({
+        "name": "thread_name", "ph": "M", "pid": 1, "tid": 1,
+        "args": { "name": "phases" }
+    })];
+    for (track, kind) in RayKind::ALL.into_iter().enumerate() {
+        trace_events.push(serde_json::json!({
+            "name": "thread_name", "ph": "M", "pid": 1, "tid": 2 + track,
+            "args": { "name": kind.label() }
+        }));
+    }
+
+    for event in events {
+        let mut json = serde_json::json!({
+            "name": event.name,
+            "ph": "X",
+            "ts": event.start_us,
+            "dur": event.duration_us,
+            "pid": 1,
+            "tid": event.tid,
+        });
+        if !event.args.is_null() {
+            json["args"] = event.args;
+        }
+        trace_events.push(json);
+    }
+
+    serde_json::json!({ "traceEvents": trace_events, "displayTimeUnit": "ms" })
+}
+
+/// Write the recorded profile to `path` in Chrome tracing format
+/// (viewable in `chrome://tracing` or Perfetto)
+pub fn write_chrome_trace(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string_pretty(&chrome_trace())?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chrome_trace_output() {
+        enable();
+
+        {
+            let _span = span("test phase");
+            let render_start = Instant::now();
+            time_rays(RayKind::Shadow, || std::hint::black_box(1 + 1));
+            flush_ray_totals(render_start);
+        }
+
+        let trace = chrome_trace();
+        let events = trace["traceEvents"].as_array().unwrap();
+
+        let phase = events.iter().find(|e| e["name"] == "test phase").unwrap();
+        assert_eq!(phase["ph"], "X");
+        assert_eq!(phase["tid"], 1);
+        assert!(phase["dur"].as_f64().unwrap() >= 0.0);
+
+        let shadow = events
+            .iter()
+            .find(|e| e["name"] == "shadow rays (cpu total)")
+            .unwrap();
+        assert!(shadow["args"]["count"].as_u64().unwrap() >= 1);
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Stage of a render reported to a `ProgressReporter`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderPhase {
+    /// Building the camera, world and acceleration structures
+    Setup,
+    /// Tracing pixels; reported after every completed tile
+    Tracing,
+    /// Image-space post processing such as outline detection
+    PostProcessing,
+    /// The image is complete; `elapsed` is the total render time
+    Finished,
+}
+
+/// Snapshot of how far a render has come
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub phase: RenderPhase,
+    pub completed_pixels: usize,
+    pub total_pixels: usize,
+    /// Time since the current phase started (since the render started for `Finished`)
+    pub elapsed: Duration,
+    /// Estimated time until tracing completes, once at least one tile has finished
+    pub eta: Option<Duration>,
+}
+
+impl Progress {
+    pub(crate) fn new(
+        phase: RenderPhase,
+        completed_pixels: usize,
+        total_pixels: usize,
+        elapsed: Duration,
+    ) -> Self {
+        let eta = (phase == RenderPhase::Tracing && completed_pixels > 0).then(|| {
+            let remaining = total_pixels.saturating_sub(completed_pixels) as f64;
+            elapsed.mul_f64(remaining / completed_pixels as f64)
+        });
+        Self {
+            phase,
+            completed_pixels,
+            total_pixels,
+            elapsed,
+            eta,
+        }
+    }
+
+    /// Completed fraction of the image in percent (0-100)
+    pub fn percent(&self) -> f64 {
+        if self.total_pixels == 0 {
+            return 100.0;
+        }
+        self.completed_pixels as f64 / self.total_pixels as f64 * 100.0
+    }
+}
+
+/// Receives progress updates from a `Renderer`
+///
+/// Tracing updates arrive from rayon worker threads, one per finished tile, but never
+/// concurrently and always in increasing order. Keep `report` cheap: tiles wait on it.
+/// Closures taking `&Progress` implement this trait.
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, progress: &Progress);
+}
+
+impl<F> ProgressReporter for F
+where
+    F: Fn(&Progress) + Send + Sync,
+{
+    fn report(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// Prints a line to stdout for every 10% of traced pixels and the total render time
+#[derive(Debug, Default)]
+pub struct StdoutProgress {
+    last_decile: AtomicUsize,
+}
+
+impl StdoutProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ProgressReporter for StdoutProgress {
+    fn report(&self, progress: &Progress) {
+        match progress.phase {
+            RenderPhase::Setup => self.last_decile.store(0, Ordering::Relaxed),
+            RenderPhase::Tracing => {
+                let progress_step = (progress.total_pixels / 10).max(1);
+                let decile = progress.completed_pixels / progress_step;
+                if decile <= self.last_decile.fetch_max(decile, Ordering::Relaxed) {
+                    return;
+                }
+
+                if progress.completed_pixels == progress.total_pixels {
+                    println!("Rendering: 100.0%");
+                } else {
+                    let eta = progress.eta.unwrap_or_default().as_secs_f64();
+                    println!(
+                        "Rendering: {:.1}% (ETA: {})",
+                        progress.percent(),
+                        format_duration(eta)
+                    );
+                }
+            }
+            RenderPhase::PostProcessing => {}
+            RenderPhase::Finished => println!(
+                "Total rendering time: {}",
+                format_duration(progress.elapsed.as_secs_f64())
+            ),
+        }
+    }
+}
+
+/// Format duration in seconds to a human-readable string (e.g., "3m45s", "1h23m", "45s")
+pub(crate) fn format_duration(seconds: f64) -> String {
+    if seconds < 0.0 {
+        return "0s".to_string();
+    }
+
+    let total_seconds = seconds.round() as u64;
+
+    if total_seconds == 0 {
+        return "0s".to_string();
+    }
+
+    let hours = total_seconds / 3600;
+    let minutes = (total_seconds % 3600) / 60;
+    let secs = total_seconds % 60;
+
+    if hours > 0 {
+        if minutes > 0 {
+            format!("{}h{}m", hours, minutes)
+        } else {
+            format!("{}h", hours)
+        }
+    } else if minutes > 0 {
+        if secs > 0 {
+            format!("{}m{}s", minutes, secs)
+        } else {
+            format!("{}m", minutes)
+        }
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0.0), "0s");
+        assert_eq!(format_duration(-1.0), "0s");
+        assert_eq!(format_duration(5.0), "5s");
+        assert_eq!(format_duration(59.0), "59s");
+        assert_eq!(format_duration(60.0), "1m");
+        assert_eq!(format_duration(65.0), "1m5s");
+        assert_eq!(format_duration(125.0), "2m5s");
+        assert_eq!(format_duration(3600.0), "1h");
+        assert_eq!(format_duration(3665.0), "1h1m");
+        assert_eq!(format_duration(7200.0), "2h");
+        assert_eq!(format_duration(7325.0), "2h2m");
+    }
+
+    #[test]
+    fn test_progress_eta() {
+        let progress = Progress::new(RenderPhase::Tracing, 25, 100, Duration::from_secs(10));
+        assert_eq!(progress.percent(), 25.0);
+        assert_eq!(progress.eta, Some(Duration::from_secs(30)));
+
+        let setup = Progress::new(RenderPhase::Setup, 0, 100, Duration::ZERO);
+        assert_eq!(setup.eta, None);
+    }
+}
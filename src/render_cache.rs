@@ -0,0 +1,277 @@
+use std::path::{Path, PathBuf};
+
+use crate::assets::{find_asset_dir, is_remote_url, AssetOptions, StableHasher};
+use crate::renderer::Renderer;
+use crate::scene::{Material, Object, Scene};
+
+/// On-disk cache of rendered images keyed by a hash of everything that affects the output
+///
+/// The key covers the scene JSON and its cameras as loaded, any overrides file and jitter seed,
+/// the contents of referenced mesh and backplate files, the renderer settings, whether the
+/// exposure is still to be measured and the rtrace version. Re-rendering an unchanged scene becomes a file copy, which matters for documentation
+/// builds.
+pub struct RenderCache {
+    dir: PathBuf,
+}
+
+impl RenderCache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Compute the cache key for rendering `scene` (loaded from `scene_path` with
+    /// `asset_options`, then patched by the overrides file at `overrides_path`) with
+    /// `renderer`
+    ///
+    /// With `auto_exposure`, the exposure `Renderer::auto_exposure` measures will be added
+    /// to the renderer's before rendering. It follows from the rest of the key, so the
+    /// lookup can skip the measuring pre-pass.
+    pub fn key(
+        scene_path: &str,
+        overrides_path: Option<&str>,
+        scene: &Scene,
+        renderer: &Renderer,
+        auto_exposure: bool,
+        asset_options: &AssetOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let scene_json = std::fs::read(scene_path)?;
+        let search_dirs = scene.asset_search_dirs(Some(scene_path), asset_options);
+
+        let mut hasher = StableHasher::new();
+        let mut add = |bytes: &[u8]| {
+            // Length prefix keeps adjacent fields from running together
+            hasher.update(&(bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        };
+
+        add(env!("CARGO_PKG_VERSION").as_bytes());
+        add(&scene_json);
+        if let Some(path) = overrides_path {
+            add(&std::fs::read(path)?);
+        }
+        add(renderer.settings_fingerprint().as_bytes());
+        add(&[auto_exposure as u8]);
+        // Cameras as loaded, since flags like `--fit` change them in memory only
+        add(serde_json::to_string(&scene.camera)?.as_bytes());
+        add(serde_json::to_string(&scene.cameras)?.as_bytes());
+        if let Some(seed) = asset_options.jitter_seed {
+            add(&seed.to_le_bytes());
+        }
+
+        let meshes = scene.objects.iter().flat_map(|object| match object {
+            Object::Mesh { filename, lods, .. } => std::iter::once(filename)
+                .chain(lods.iter().map(|lod| &lod.filename))
+                .collect(),
+            Object::Volume {
+                density_file: Some(file),
+                ..
+            } => vec![file],
+            Object::Glyphs { filename, .. } => vec![filename],
+            _ => Vec::new(),
+        });
+        let prototypes = scene.meshes.values().map(|prototype| &prototype.filename);
+        let backplate = scene.scene_settings.background.iter().map(|b| &b.image);
+        let bumps = scene
+            .objects
+            .iter()
+            .flat_map(Object::materials)
+            .flat_map(Material::bump_images);
+        for reference in meshes.chain(prototypes).chain(backplate).chain(bumps) {
+            if is_remote_url(reference) {
+                // Remote assets are pinned by the asset cache, so the URL identifies the content
+                add(reference.as_bytes());
+            } else {
+                let dir = find_asset_dir(reference, &search_dirs)?;
+                add(&std::fs::read(dir.join(reference))?);
+            }
+        }
+
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Path of the cached image for `key` in the format of `output_path`, if one exists
+    pub fn lookup(&self, key: &str, output_path: &Path) -> Option<PathBuf> {
+        let path = self.entry_path(key, output_path);
+        path.is_file().then_some(path)
+    }
+
+    /// Copy a freshly rendered image into the cache under `key`
+    pub fn store(&self, key: &str, image_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path(key, image_path);
+
+        // Copy then rename so concurrent builds never observe a partial entry
+        let tmp_path = path.with_extension("part");
+        std::fs::copy(image_path, &tmp_path)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    /// Entries keep the image's extension, so a PNG never satisfies a request for an EXR
+    fn entry_path(&self, key: &str, image_path: &Path) -> PathBuf {
+        let extension = image_path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("png")
+            .to_ascii_lowercase();
+        self.dir.join(format!("{}.{}", key, extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rtrace-render-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_key_tracks_inputs() {
+        let dir = temp_dir("key");
+        let scene_path = dir.join("scene.json");
+        let scene_path = scene_path.to_str().unwrap();
+        let stl_path = dir.join("part.stl");
+
+        let json = r##"{
+            "camera": {"kind": "ortho", "position": [0,0,5], "target": [0,0,0], "up": [0,1,0], "width": 2, "height": 2},
+            "objects": [{"kind": "mesh", "filename": "part.stl", "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}, "background_color": "#000000"}
+        }"##;
+        let stl = "solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        std::fs::write(scene_path, json).unwrap();
+        std::fs::write(&stl_path, stl).unwrap();
+
+        let scene = Scene::from_json_file(scene_path).unwrap();
+        let options = AssetOptions::default();
+        let renderer = Renderer::new(8, 8);
+        let key = RenderCache::key(scene_path, None, &scene, &renderer, false, &options).unwrap();
+
+        // Identical inputs produce an identical key
+        assert_eq!(
+            key,
+            RenderCache::key(scene_path, None, &scene, &renderer, false, &options).unwrap()
+        );
+
+        // Renderer settings are part of the key
+        let mut other = Renderer::new(8, 8);
+        other.samples = 4;
+        assert_ne!(
+            key,
+            RenderCache::key(scene_path, None, &scene, &other, false, &options).unwrap()
+        );
+
+        // As is whether the exposure is measured before rendering
+        assert_ne!(
+            key,
+            RenderCache::key(scene_path, None, &scene, &renderer, true, &options).unwrap()
+        );
+
+        // Thread count does not change the output, so it does not change the key
+        let threaded = Renderer::new_with_threads(8, 8, 2);
+        assert_eq!(
+            key,
+            RenderCache::key(scene_path, None, &scene, &threaded, false, &options).unwrap()
+        );
+
+        // So is the camera, which can change after loading, as `--fit` does
+        let mut fitted = scene.clone();
+        fitted.camera.fit = Some(true);
+        assert_ne!(
+            key,
+            RenderCache::key(scene_path, None, &fitted, &renderer, false, &options).unwrap()
+        );
+
+        // So is the overrides file applied on top of the scene
+        let overrides_path = dir.join("overrides.json");
+        let overrides_path = overrides_path.to_str().unwrap();
+        std::fs::write(overrides_path, r#"{"objects": {}}"#).unwrap();
+        let overrides = Some(overrides_path);
+        let patched =
+            RenderCache::key(scene_path, overrides, &scene, &renderer, false, &options).unwrap();
+        assert_ne!(key, patched);
+        std::fs::write(overrides_path, r#"{"lights": {}}"#).unwrap();
+        assert_ne!(
+            patched,
+            RenderCache::key(scene_path, overrides, &scene, &renderer, false, &options).unwrap()
+        );
+
+        // And a jitter seed given in place of the scene's
+        let reseeded = AssetOptions {
+            jitter_seed: Some(7),
+            ..Default::default()
+        };
+        assert_ne!(
+            key,
+            RenderCache::key(scene_path, None, &scene, &renderer, false, &reseeded).unwrap()
+        );
+
+        // Editing a referenced mesh invalidates the entry
+        std::fs::write(&stl_path, stl.replace("vertex 0 1 0", "vertex 0 2 0")).unwrap();
+        assert_ne!(
+            key,
+            RenderCache::key(scene_path, None, &scene, &renderer, false, &options).unwrap()
+        );
+
+        // As does editing a bump image
+        let mut scene = scene;
+        scene.objects[0].material_mut().unwrap().bump = Some(crate::scene::Bump::Image {
+            image: "bumps.png".to_string(),
+            size: 1.0,
+            depth: 0.1,
+            height_map: None,
+        });
+        std::fs::write(dir.join("bumps.png"), [0u8]).unwrap();
+        let bumped =
+            RenderCache::key(scene_path, None, &scene, &renderer, false, &options).unwrap();
+        std::fs::write(dir.join("bumps.png"), [1u8]).unwrap();
+        assert_ne!(
+            bumped,
+            RenderCache::key(scene_path, None, &scene, &renderer, false, &options).unwrap()
+        );
+
+        // And editing the data file of a glyph field
+        let glyphs: Object = serde_json::from_str(
+            r##"{"kind": "glyphs", "filename": "points.csv", "size": 1,
+                "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}}"##,
+        )
+        .unwrap();
+        scene.objects.push(glyphs);
+        std::fs::write(dir.join("points.csv"), "x,y,z\n0,0,0\n").unwrap();
+        let plotted =
+            RenderCache::key(scene_path, None, &scene, &renderer, false, &options).unwrap();
+        std::fs::write(dir.join("points.csv"), "x,y,z\n0,0,1\n").unwrap();
+        assert_ne!(
+            plotted,
+            RenderCache::key(scene_path, None, &scene, &renderer, false, &options).unwrap()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_store_and_lookup() {
+        let dir = temp_dir("store");
+        let cache = RenderCache::new(dir.join("cache"));
+        let image_path = dir.join("image.png");
+        std::fs::write(&image_path, b"not really a png").unwrap();
+
+        assert!(cache.lookup("abc", &image_path).is_none());
+        cache.store("abc", &image_path).unwrap();
+
+        let cached = cache.lookup("abc", Path::new("other.png")).unwrap();
+        assert_eq!(std::fs::read(cached).unwrap(), b"not really a png");
+
+        // The same render in another format is a separate entry
+        assert!(cache.lookup("abc", Path::new("other.exr")).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
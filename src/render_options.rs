@@ -0,0 +1,193 @@
+//! One set of render options shared by the CLI and the language bindings
+
+use crate::renderer::{AntiAliasingMode, Renderer};
+use crate::scene::{OutlineSettings, Scene};
+
+/// Image size and renderer settings for rendering one scene
+///
+/// Unset fields keep the renderer's defaults, or the scene's own `render_settings` and
+/// `outline` where it has them. The CLI and the bindings build their renderers through
+/// `renderer`, so an option means the same thing everywhere.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Image diagonal in pixels, the aspect ratio following the camera (1000 if unset)
+    pub size: Option<u32>,
+    /// Image width in pixels; alone, the height follows the camera's aspect ratio
+    pub width: Option<u32>,
+    /// Image height in pixels; alone, the width follows the camera's aspect ratio
+    pub height: Option<u32>,
+    /// Worker threads (all cores if unset; needs `parallel`)
+    pub threads: Option<usize>,
+    /// Intersect meshes through their k-d trees (the default) or by brute force
+    pub kdtree: Option<bool>,
+    pub anti_aliasing: Option<AntiAliasingMode>,
+    pub samples: Option<u32>,
+    pub seed: Option<u64>,
+    /// Outline settings in place of the scene's, objects named as in the scene
+    pub outline: Option<OutlineSettings>,
+    pub max_depth: Option<i32>,
+}
+
+impl RenderOptions {
+    /// Image diagonal when neither `size` nor the width and height are set
+    pub const DEFAULT_SIZE: u32 = 1000;
+
+    /// Width and height of the image for a camera with `aspect_ratio` (width / height)
+    ///
+    /// Explicit dimensions win over `size`, which can't be combined with them.
+    pub fn dimensions(&self, aspect_ratio: f64) -> Result<(u32, u32), String> {
+        if self.size.is_some() && (self.width.is_some() || self.height.is_some()) {
+            return Err("Give either a size or a width and height, not both".to_string());
+        }
+        let (width, height) = match (self.width, self.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, (width as f64 / aspect_ratio).round() as u32),
+            (None, Some(height)) => ((height as f64 * aspect_ratio).round() as u32, height),
+            (None, None) => {
+                // Using diagonal D and aspect ratio R = W/H:
+                // H = D / sqrt(R² + 1)
+                // W = R * H
+                let diagonal = self.size.unwrap_or(Self::DEFAULT_SIZE) as f64;
+                let height = diagonal / (aspect_ratio * aspect_ratio + 1.0).sqrt();
+                let width = aspect_ratio * height;
+                (width.round() as u32, height.round() as u32)
+            }
+        };
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "Image must be at least 1×1 pixels, not {}×{}",
+                width, height
+            ));
+        }
+        Ok((width, height))
+    }
+
+    /// Renderer for `scene` with these options over the scene's settings
+    ///
+    /// Fails on a zero sample count, a zero-sized image or invalid outline settings.
+    pub fn renderer(&self, scene: &Scene) -> Result<Renderer, String> {
+        let (width, height) = self.dimensions(scene.camera.width / scene.camera.height)?;
+
+        let mut settings = scene.render_settings.clone().unwrap_or_default();
+        if let Some(mode) = self.anti_aliasing {
+            settings.anti_aliasing = Some(mode);
+        }
+        if let Some(samples) = self.samples {
+            settings.samples = Some(samples);
+        }
+        if settings.samples == Some(0) {
+            return Err("Samples must be greater than 0".to_string());
+        }
+
+        let kdtree = self.kdtree.unwrap_or(true);
+        let mut renderer = Renderer::new_with_options(width, height, kdtree, self.threads)
+            .with_render_settings(&settings);
+        if let Some(seed) = self.seed {
+            renderer.seed = Some(seed);
+        }
+        if let Some(max_depth) = self.max_depth {
+            renderer.max_depth = max_depth;
+        }
+        let outline = match &self.outline {
+            Some(outline) => scene.outline_config(outline)?,
+            None => scene.get_outline_config()?,
+        };
+        if let Some(outline) = outline {
+            renderer = renderer.with_outline_detection(outline);
+        }
+        Ok(renderer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions() {
+        let options = RenderOptions::default();
+        assert_eq!(options.dimensions(1.0), Ok((707, 707)));
+        let options = RenderOptions {
+            size: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(options.dimensions(4.0 / 3.0), Ok((400, 300)));
+
+        // One side follows the aspect ratio from the other
+        let options = RenderOptions {
+            width: Some(320),
+            ..Default::default()
+        };
+        assert_eq!(options.dimensions(2.0), Ok((320, 160)));
+        let options = RenderOptions {
+            height: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(options.dimensions(1.5), Ok((150, 100)));
+        let options = RenderOptions {
+            width: Some(64),
+            height: Some(48),
+            ..Default::default()
+        };
+        assert_eq!(options.dimensions(1.0), Ok((64, 48)));
+
+        let options = RenderOptions {
+            size: Some(500),
+            width: Some(64),
+            ..Default::default()
+        };
+        assert!(options.dimensions(1.0).is_err());
+        let options = RenderOptions {
+            width: Some(0),
+            ..Default::default()
+        };
+        assert!(options.dimensions(1.0).is_err());
+    }
+
+    #[test]
+    fn test_renderer() {
+        let mut scene = Scene::default();
+        scene.render_settings = Some(crate::scene::RenderSettings {
+            anti_aliasing: Some(AntiAliasingMode::Stochastic),
+            samples: Some(4),
+            ..Default::default()
+        });
+
+        // The scene's settings apply where the options leave them unset
+        let renderer = RenderOptions::default().renderer(&scene).unwrap();
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Stochastic);
+        assert_eq!(renderer.samples, 4);
+        assert!(renderer.use_kdtree);
+        assert!(renderer.outline_config.is_none());
+
+        let options = RenderOptions {
+            width: Some(40),
+            height: Some(30),
+            threads: Some(2),
+            kdtree: Some(false),
+            samples: Some(8),
+            seed: Some(9),
+            max_depth: Some(3),
+            outline: Some(OutlineSettings {
+                enabled: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let renderer = options.renderer(&scene).unwrap();
+        assert_eq!((renderer.width, renderer.height), (40, 30));
+        assert_eq!(renderer.thread_count, Some(2));
+        assert!(!renderer.use_kdtree);
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Stochastic);
+        assert_eq!(renderer.samples, 8);
+        assert_eq!(renderer.seed, Some(9));
+        assert_eq!(renderer.max_depth, 3);
+        assert!(renderer.outline_config.is_some());
+
+        let options = RenderOptions {
+            samples: Some(0),
+            ..Default::default()
+        };
+        assert!(options.renderer(&scene).is_err());
+    }
+}
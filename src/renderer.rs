@@ -1,19 +1,60 @@
-use image::{ImageBuffer, Rgb, RgbImage};
+#[cfg(feature = "image")]
+use image::{Rgb, Rgb32FImage, RgbImage};
 use rand::{Rng, SeedableRng};
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
-use std::time::Instant;
-
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::ThreadId;
+
+use crate::aov::{Aov, AovBuffer, FalseColor};
+#[cfg(feature = "image")]
+use crate::light_groups::{self, LightGroupPass};
+use crate::backplate::Backplate;
 use crate::camera::Camera;
-use crate::lighting::{ray_color, ray_color_with_camera};
-use crate::outline::{apply_outline_detection, OutlineBuffers, OutlineConfig};
-use crate::ray::{Cube, MeshObject, Plane, Sphere, World};
-use crate::scene::{hex_to_color, Color, Object, Point, Scene, Vec3};
+use crate::core::tonemap::{self, srgb_to_linear, ToneMapping};
+use crate::emission::emissive_light;
+use crate::framing::Framing;
+use crate::half_res::{HalfResShading, Texel, ALBEDO_EPSILON};
+use crate::lighting::{
+    ambient_occlusion_shade, effective_material, lighting_shade, phong_lighting, preview_shade,
+    ray_past, shade_hit_components, shade_hit_with_data, shading_hit, shadow_factor, shadow_shade,
+    surface_albedo, DEFAULT_SHADOW_SAMPLES,
+};
+use crate::lod::LodSelector;
+use crate::non_finite::{NonFinitePixel, NonFinitePixels, NON_FINITE_MARKER};
+use crate::object_stats::{self, Counted, ObjectStats};
+use crate::outline::{apply_outline_detection, edge_mask, OutlineBuffers, OutlineConfig};
+use crate::overlay::{apply_overlays, Overlay};
+use crate::profile::{self, time_rays, RayKind};
+use crate::progress::{Progress, ProgressReporter, RenderPhase};
+use crate::ray::{
+    Bvh, Cube, Ellipsoid, FaceMaterials, HitRecord, InstanceObject, Intersectable, MeshObject,
+    MovingObject, Ray, Ribbon, Sphere, World,
+};
+use crate::scene::{
+    decode_srgb_hex, hex_to_color, Color, ColorSpace, FaceMaterial, LineWidthUnit, Object, Point,
+    RenderSettings, Scene, Vec3,
+};
+#[cfg(feature = "image")]
+use crate::sequence::{AnimationWriter, SequenceFormat};
+#[cfg(feature = "image")]
+use crate::sink::{FileSink, MemorySink, RenderSink, RenderedImage, SinkPass, TilePixels};
+#[cfg(feature = "image")]
+use crate::stereo::{side_by_side, Eye, Stereo, StereoLayout};
+use crate::temporal::{Surface, TemporalConfig, TemporalHistory};
+use crate::tile_failures::{panic_message, TileFailure, TileFailures};
+use crate::tilt_shift::{apply_tilt_shift, TiltShiftConfig};
+use crate::time::{Duration, Instant};
+use crate::xray::XRayConfig;
+use serde::{Deserialize, Serialize};
 
 /// Anti-aliasing sampling modes
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Serialized (and parsed) as `"quincunx"`, `"stochastic"` or `"no-jitter"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum AntiAliasingMode {
     /// No jittering - deterministic center-pixel sampling
     NoJitter,
@@ -23,72 +64,564 @@ pub enum AntiAliasingMode {
     Stochastic,
 }
 
+impl AntiAliasingMode {
+    /// Name used in scene files, the CLI and the Node binding
+    pub fn name(&self) -> &'static str {
+        match self {
+            AntiAliasingMode::NoJitter => "no-jitter",
+            AntiAliasingMode::Quincunx => "quincunx",
+            AntiAliasingMode::Stochastic => "stochastic",
+        }
+    }
+}
+
+impl std::str::FromStr for AntiAliasingMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "quincunx" => Ok(AntiAliasingMode::Quincunx),
+            "stochastic" => Ok(AntiAliasingMode::Stochastic),
+            "no-jitter" => Ok(AntiAliasingMode::NoJitter),
+            _ => Err(format!(
+                "Invalid anti-aliasing mode '{}'. Valid options are: quincunx, stochastic, no-jitter",
+                name
+            )),
+        }
+    }
+}
+
+/// Shading modes selectable on the renderer
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderMode {
+    /// Full Phong shading with shadows, reflections and fog
+    Shaded,
+    /// Ambient occlusion only - uniform clay look driven by hemisphere occlusion rays
+    AmbientOcclusion,
+    /// Share of the lights' intensity reaching each surface - white when lit, black in shadow
+    Shadows,
+    /// Direct light arriving at matte white surfaces, ignoring materials, reflections and fog
+    Lighting,
+    /// Materials lit with one hard shadow ray per light, without reflections, refraction,
+    /// caustics or fog, for quick previews that keep outlines; see `Renderer::with_preview`
+    Preview,
+}
+
+impl RenderMode {
+    /// Name used on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderMode::Shaded => "shaded",
+            RenderMode::AmbientOcclusion => "ao",
+            RenderMode::Shadows => "shadows",
+            RenderMode::Lighting => "lighting",
+            RenderMode::Preview => "preview",
+        }
+    }
+}
+
+impl std::str::FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "shaded" => Ok(RenderMode::Shaded),
+            "ao" => Ok(RenderMode::AmbientOcclusion),
+            "shadows" => Ok(RenderMode::Shadows),
+            "lighting" => Ok(RenderMode::Lighting),
+            "preview" => Ok(RenderMode::Preview),
+            _ => Err(format!(
+                "Invalid render mode '{}'. Valid options are: shaded, ao, shadows, lighting, preview",
+                name
+            )),
+        }
+    }
+}
+
+/// Primary ray hits for one frame, indexed by sample id
+///
+/// Slots are filled lazily during a render and reused verbatim by later renders
+/// of the same geometry, so only shading has to be recomputed. `PreviewSession` and
+/// light group renders create one.
+#[cfg_attr(not(feature = "image"), allow(dead_code))]
+pub(crate) struct PrimaryHitBuffer {
+    slots: Vec<OnceLock<Option<HitRecord>>>,
+}
+
+impl PrimaryHitBuffer {
+    #[cfg(feature = "image")]
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            slots: (0..len).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    /// Return the cached hit for `sample_id`, tracing and storing it on first use
+    pub(crate) fn get_or_trace<F>(&self, sample_id: usize, trace: F) -> Option<HitRecord>
+    where
+        F: FnOnce() -> Option<HitRecord>,
+    {
+        match self.slots.get(sample_id) {
+            Some(slot) => slot.get_or_init(trace).clone(),
+            None => trace(),
+        }
+    }
+}
+
 /// Context for rendering operations
+#[derive(Clone, Copy)]
 struct RenderContext<'a> {
+    world: &'a World,
+    camera: &'a Camera,
+    lights: &'a [crate::scene::Light],
     ambient: &'a crate::scene::AmbientIllumination,
     fog: &'a Option<crate::scene::Fog>,
     camera_pos: &'a Point,
     background_color: Color,
+    backplate: Option<&'a Backplate>,
+    materials: &'a [crate::scene::Material],
+    primary_hits: Option<&'a PrimaryHitBuffer>,
+    half_res: Option<&'a HalfResShading>, // Shading to upsample instead of shading each hit
+    report_progress: bool,                // Report per-tile progress (off for auxiliary passes)
+    on_tile: Option<&'a TileCallback<'a>>, // Receives each traced tile's colors
+}
+
+/// Callback for each tile of the main image as it is traced, with its colors in the
+/// order of `Tile::pixels`
+type TileCallback<'a> = dyn Fn(&Tile, &[Color]) + Sync + 'a;
+
+/// Camera, world and materials built from a scene, ready to trace
+struct PreparedView {
+    camera: Camera,
+    camera_pos: Point,
+    shared: Arc<SceneWorld>,
+}
+
+/// World, and camera if it replaces the scene's, that renders on one thread use instead
+/// of building their own
+struct Batch {
+    thread: ThreadId,
+    shared: Arc<SceneWorld>,
+    camera: Option<crate::scene::Camera>,
+}
+
+/// A scene with its world built once: objects, mesh k-d trees, materials, lights and
+/// caustic photons, ready to be rendered again and again
+///
+/// Made by `Renderer::prepare` and rendered through any camera by
+/// `Renderer::render_prepared`, so servers and animation loops that only move the camera
+/// don't rebuild the world for every frame. `Renderer::edit_prepared` moves, adds and
+/// removes objects, building only the objects it changes. Renderers with a different
+/// render mode, shadow sample count, seed or k-d tree setting than the one that
+/// prepared it would build a different world, and can't render or edit it.
+///
+/// Prepared scenes are `Send` and `Sync`: behind an `RwLock`, any number of threads can
+/// render one at once while edits wait for the write lock.
+#[cfg(feature = "image")]
+pub struct PreparedScene {
+    scene: Scene,
+    shared: Arc<SceneWorld>,
+    objects: Vec<SharedObject>, // Built parts of each of the scene's objects, for edits
+    world_key: String,          // Settings of the renderer the world was built for
+}
+
+#[cfg(feature = "image")]
+impl PreparedScene {
+    /// The scene the world was built from
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// Indices of the objects called `name`
+    fn named(&self, name: &str) -> Result<Vec<usize>, String> {
+        let indices: Vec<usize> = (0..self.scene.objects.len())
+            .filter(|&index| self.scene.objects[index].name() == Some(name))
+            .collect();
+        if indices.is_empty() {
+            return Err(format!("No object is named '{}'", name));
+        }
+        Ok(indices)
+    }
+}
+
+/// A change to the objects of a `PreparedScene`, made by `Renderer::edit_prepared`
+#[derive(Debug, Clone)]
+pub enum SceneEdit {
+    /// Give every object called `name` this transform in place of its own, moving it
+    Transform {
+        name: String,
+        transform: Option<Vec<String>>,
+    },
+    /// Add an object after the scene's others; a mesh needs its `mesh_data` loaded, and
+    /// groups must be added as their children
+    Add(Box<Object>),
+    /// Remove every object called `name`
+    Remove { name: String },
+}
+
+/// How to take back a `SceneEdit` whose world couldn't be built
+#[cfg(feature = "image")]
+enum Undo {
+    Restore(Vec<(usize, Object, SharedObject)>),
+    Pop,
+    Reinsert(Vec<(usize, Object, SharedObject)>),
+}
+
+/// An object's built parts, kept by a `PreparedScene` so edits build only the objects
+/// they change
+#[cfg(feature = "image")]
+struct SharedObject {
+    parts: Vec<Arc<dyn Intersectable + Send + Sync>>,
+    material: crate::scene::Material,
+    face_materials: Vec<crate::scene::Material>,
+    emitters: Vec<crate::scene::Light>,
+    index: usize,     // Object index the parts were built with
+    face_base: usize, // Material key of the first face material they were built with
+}
+
+#[cfg(feature = "image")]
+impl SharedObject {
+    fn new(built: BuiltObject, index: usize, face_base: usize) -> Self {
+        Self {
+            parts: built.parts.into_iter().map(Arc::from).collect(),
+            material: built.material,
+            face_materials: built.face_materials,
+            emitters: built.emitters,
+            index,
+            face_base,
+        }
+    }
+
+    /// The object as if built with index `index` and face materials keyed from `face_base`
+    fn placed(&self, index: usize, face_base: usize) -> BuiltObject {
+        let face_offset = face_base as isize - self.face_base as isize;
+        let parts = (self.parts.iter())
+            .map(|part| -> Box<dyn Intersectable + Send + Sync> {
+                Box::new(crate::ray::PlacedObject {
+                    part: Arc::clone(part),
+                    material_index: index,
+                    face_offset,
+                })
+            })
+            .collect();
+        let emitters = (self.emitters.iter())
+            .map(|light| {
+                let mut light = light.clone();
+                if let Some(emitter) = &mut light.emitter {
+                    let mut moved = (**emitter).clone();
+                    moved.key = match moved.key == self.index {
+                        true => index,
+                        false => moved.key.wrapping_add_signed(face_offset),
+                    };
+                    *emitter = Arc::new(moved);
+                }
+                light
+            })
+            .collect();
+        BuiltObject {
+            parts,
+            material: self.material.clone(),
+            face_materials: self.face_materials.clone(),
+            emitters,
+        }
+    }
+}
+
+/// Camera to render a `PreparedScene` through: the scene's own, or one of its named
+/// cameras, with any of the fields below replaced
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CameraOverride {
+    /// Key of the scene's `cameras` to start from instead of its main camera
+    pub name: Option<String>,
+    pub position: Option<[f64; 3]>,
+    pub target: Option<[f64; 3]>,
+    pub up: Option<[f64; 3]>,
+    /// Field of view in degrees, for perspective and fisheye cameras
+    pub fov: Option<f64>,
+}
+
+impl CameraOverride {
+    /// Camera from `position` looking at `target`, the scene's otherwise
+    pub fn look_at(position: [f64; 3], target: [f64; 3]) -> Self {
+        Self {
+            position: Some(position),
+            target: Some(target),
+            ..Self::default()
+        }
+    }
+
+    /// The camera of `scene` with these changes, fitted to its bounds if it sets `fit`
+    pub fn apply(&self, scene: &Scene) -> Result<crate::scene::Camera, String> {
+        let mut camera = match &self.name {
+            Some(name) => scene.named_camera(name)?.clone(),
+            None => scene.camera.clone(),
+        };
+        if let Some(position) = self.position {
+            camera.position = position;
+        }
+        if let Some(target) = self.target {
+            camera.target = target;
+        }
+        if let Some(up) = self.up {
+            camera.up = up;
+        }
+        if let Some(fov) = self.fov {
+            camera.fov = Some(fov);
+        }
+        scene.fit_camera(&camera)
+    }
+}
+
+/// One object's share of a `SceneWorld`, before the world is put together
+struct BuiltObject {
+    parts: Vec<Box<dyn Intersectable + Send + Sync>>,
+    material: crate::scene::Material,
+    face_materials: Vec<crate::scene::Material>, // Keyed from the face base built with
+    emitters: Vec<crate::scene::Light>,
+}
+
+/// Everything built from a scene but its camera, shared by all the views of a batch
+struct SceneWorld {
+    world: World,
+    // Indexed by material key: objects' own, then mesh faces'
+    materials: Vec<crate::scene::Material>,
+    lights: Vec<crate::scene::Light>, // Scene lights, with every shadow_samples filled in
+    ambient: crate::scene::AmbientIllumination,
+    fog: Option<crate::scene::Fog>,
+    background_color: Color,
+    backplate: Option<Backplate>, // Linear copy of an sRGB scene's backplate
+    moving: bool,                 // Some object moves, so cameras need a shutter
+}
+
+impl PreparedView {
+    fn context<'a>(
+        &'a self,
+        scene: &'a Scene,
+        primary_hits: Option<&'a PrimaryHitBuffer>,
+    ) -> RenderContext<'a> {
+        let shared = &*self.shared;
+        RenderContext {
+            world: &shared.world,
+            camera: &self.camera,
+            lights: &shared.lights,
+            ambient: &shared.ambient,
+            fog: &shared.fog,
+            camera_pos: &self.camera_pos,
+            background_color: shared.background_color,
+            backplate: shared.backplate.as_ref().or_else(|| {
+                scene
+                    .scene_settings
+                    .background
+                    .as_ref()
+                    .and_then(|background| background.backplate.as_ref())
+            }),
+            materials: &shared.materials,
+            primary_hits,
+            half_res: None,
+            report_progress: true,
+            on_tile: None,
+        }
+    }
 }
 
 /// Type alias for pixel rendering results with outline data
-type PixelRenderResult = (u32, u32, Color, Option<f64>, Option<Vec3>);
+type PixelRenderResult = (u32, u32, Color, Option<f64>, Option<Vec3>, Option<usize>);
+
+/// Type alias for a traced sample: its color, then the camera-space depth, world normal
+/// and object index of its first hit, for outline detection
+type SampleResult = (Color, Option<f64>, Option<Vec3>, Option<usize>);
+
+/// Type alias for linear pixel colors keyed by position
+type PixelColors = Vec<(u32, u32, Color)>;
+
+/// Type alias for a rendered frame: pixel colors plus optional outline buffers
+type FrameData = (PixelColors, Option<OutlineBuffers>);
+
+/// Thread pool returned by `thread_pool()` (a placeholder without the `parallel` feature)
+#[cfg(feature = "parallel")]
+type ThreadPool = rayon::ThreadPool;
+#[cfg(not(feature = "parallel"))]
+type ThreadPool = ();
+
+/// Shared flag for aborting a render from another thread (e.g. a Ctrl-C handler)
+///
+/// Clones share the same flag. Once cancelled, renders using the token stop starting
+/// new tiles and return a "Render cancelled" error; tiles already in flight finish.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of every render using this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clear a previous cancellation so the token can be reused
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Caps on render size and scene complexity, for services rendering untrusted scenes
+///
+/// Unset fields are unlimited. Renders exceeding a limit fail before any tracing
+/// starts. Mesh loading has its own `MeshLimits`, which bound memory while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RendererLimits {
+    /// Maximum width × height of the output image
+    pub max_pixels: Option<u64>,
+    /// Maximum number of scene objects
+    pub max_objects: Option<usize>,
+    /// Maximum number of triangles across all meshes
+    pub max_triangles: Option<usize>,
+    /// Maximum ray recursion depth
+    pub max_depth: Option<i32>,
+}
+
+impl RendererLimits {
+    /// Check the output size and recursion depth of a render
+    pub fn check_render(&self, width: u32, height: u32, max_depth: i32) -> Result<(), String> {
+        let pixels = width as u64 * height as u64;
+        if let Some(max_pixels) = self.max_pixels {
+            if pixels > max_pixels {
+                return Err(format!(
+                    "Image is {}x{} = {} pixels, above the limit of {}",
+                    width, height, pixels, max_pixels
+                ));
+            }
+        }
+        if let Some(limit) = self.max_depth {
+            if max_depth > limit {
+                return Err(format!(
+                    "Max depth {} is above the limit of {}",
+                    max_depth, limit
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the object and triangle counts of a loaded scene
+    pub fn check_scene(&self, scene: &Scene) -> Result<(), String> {
+        if let Some(max_objects) = self.max_objects {
+            if scene.objects.len() > max_objects {
+                return Err(format!(
+                    "Scene has {} objects, above the limit of {}",
+                    scene.objects.len(),
+                    max_objects
+                ));
+            }
+        }
+        if let Some(max_triangles) = self.max_triangles {
+            // Instances share their mesh's triangles, which count once
+            let meshes = scene.objects.iter().map(|object| match object {
+                Object::Mesh {
+                    mesh_data: Some(mesh),
+                    ..
+                } => mesh.triangles.len(),
+                object => object.tessellated_triangle_count(),
+            });
+            let prototypes = scene.meshes.values().map(|prototype| {
+                prototype
+                    .mesh_data
+                    .as_ref()
+                    .map_or(0, |mesh| mesh.triangles.len())
+            });
+            let triangles: usize = meshes.chain(prototypes).sum();
+            if triangles > max_triangles {
+                return Err(format!(
+                    "Scene has {} mesh triangles, above the limit of {}",
+                    triangles, max_triangles
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rectangular block of pixels rendered as one unit of parallel work (x1/y1 exclusive)
+struct Tile {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+impl Tile {
+    fn pixel_count(&self) -> usize {
+        ((self.x1 - self.x0) * (self.y1 - self.y0)) as usize
+    }
+
+    /// Pixel coordinates in row-major order
+    fn pixels(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (self.y0..self.y1).flat_map(move |y| (self.x0..self.x1).map(move |x| (x, y)))
+    }
+}
+
+/// Longest side in pixels of the preview `Renderer::auto_exposure` measures
+pub const AUTO_EXPOSURE_SIZE: u32 = 64;
 
 pub struct Renderer {
     pub width: u32,
     pub height: u32,
     pub max_depth: i32,
     pub use_kdtree: bool, // New field to control k-d tree usage for meshes
-    pub thread_count: Option<usize>, // Number of threads to use (None = use all available cores)
+    pub thread_count: Option<usize>, // Number of threads to use (None = all cores; needs `parallel`)
     pub samples: u32,     // Number of samples per pixel for stochastic subsampling
     pub anti_aliasing_mode: AntiAliasingMode, // Anti-aliasing sampling mode
     pub seed: Option<u64>, // Seed for deterministic randomness (None = use default seed)
     pub outline_config: Option<OutlineConfig>, // Optional outline detection configuration
+    pub tilt_shift: Option<TiltShiftConfig>, // Optional depth blur for a miniature look
+    pub render_mode: RenderMode, // Shading mode (full shading or a diagnostic pass)
+    pub ao_samples: u32,   // Number of occlusion rays per hit in ambient occlusion mode
+    pub ao_radius: f64,    // Maximum occluder distance in ambient occlusion mode
+    pub shadow_samples: u32, // Disk samples per diffuse light without its own shadow_samples
+    pub tile_size: u32,    // Edge length in pixels of the tiles handed to worker threads
+    pub cancellation: Option<CancellationToken>, // When cancelled, remaining tiles are skipped
+    pub progress: Option<Arc<dyn ProgressReporter>>, // Receives progress updates (None = silent)
+    pub tone_mapping: ToneMapping, // Applied when quantizing to 8 bits (not to `render_hdr`)
+    pub aovs: Vec<Aov>, // Extra passes `render_to_file` writes next to the image
+    pub aov_false_color: Option<FalseColor>, // Color map for 8-bit depth, alpha and outline AOVs
+    pub light_groups: bool, // Also write one pass per light group from `render_to_file`
+    pub overlays: Vec<Overlay>, // Debug lines drawn over the finished image
+    pub limits: RendererLimits, // Size and complexity caps checked before rendering
+    pub object_stats: Option<ObjectStats>, // Counts intersection tests per object when set
+    pub tile_failures: Option<TileFailures>, // Records tiles that failed and were retried
+    pub half_res_shading: bool, // Shade at half resolution, upsampled along full-resolution hits
+    pub xray: Option<XRayConfig>, // Blend every surface along primary rays, for ghosted views
+    pub non_finite_pixels: Option<NonFinitePixels>, // Records and marks NaN and infinite pixels
+    pub framing: Framing,    // Crop and margin applied to images from `render_to_sink`
+    pub full_detail: bool,   // Draw meshes at full detail, ignoring their `lods`
+    pub temporal: Option<TemporalConfig>, // Blend each frame of `render_frames` with the last
+    pub strict_determinism: bool, // Trace the quincunx corner grid up front, each corner once
+    batches: Mutex<Vec<Batch>>, // Set while rendering a camera batch or a prepared scene
+    temporal_history: Mutex<Option<TemporalHistory>>, // Set while `render_frames` runs
+    sequence_frame: AtomicU64, // Frame of an accumulated sequence, varying its seeds
+    #[cfg(feature = "image")]
+    pub sequence_format: Option<SequenceFormat>, // How `render_frames` saves (None = by extension)
 }
 
 impl Renderer {
     pub fn new(width: u32, height: u32) -> Self {
-        Self {
-            width,
-            height,
-            max_depth: 10,
-            use_kdtree: true,   // Default to using k-d tree
-            thread_count: None, // Use all available cores by default
-            samples: 1,         // Default to 1 sample (quincunx adds shared corner samples)
-            anti_aliasing_mode: AntiAliasingMode::Quincunx, // Default to quincunx anti-aliasing
-            seed: Some(0),      // Default to deterministic seed for reproducibility
-            outline_config: None, // No outline detection by default
-        }
+        Self::new_with_options(width, height, true, None)
     }
 
     /// Create a renderer with k-d tree disabled (brute force mesh intersection)
     pub fn new_brute_force(width: u32, height: u32) -> Self {
-        Self {
-            width,
-            height,
-            max_depth: 10,
-            use_kdtree: false,                              // Disable k-d tree
-            thread_count: None,                             // Use all available cores by default
-            samples: 1, // Default to 1 sample (quincunx adds shared corner samples)
-            anti_aliasing_mode: AntiAliasingMode::Quincunx, // Default to quincunx anti-aliasing
-            seed: Some(0), // Default to deterministic seed for reproducibility
-            outline_config: None, // No outline detection by default
-        }
+        Self::new_with_options(width, height, false, None)
     }
 
     /// Create a renderer with a specific thread count
     pub fn new_with_threads(width: u32, height: u32, thread_count: usize) -> Self {
-        Self {
-            width,
-            height,
-            max_depth: 10,
-            use_kdtree: true,
-            thread_count: Some(thread_count),
-            samples: 1, // Default to 1 sample (quincunx adds shared corner samples)
-            anti_aliasing_mode: AntiAliasingMode::Quincunx, // Default to quincunx anti-aliasing
-            seed: Some(0), // Default to deterministic seed for reproducibility
-            outline_config: None, // No outline detection by default
-        }
+        Self::new_with_options(width, height, true, Some(thread_count))
     }
 
     /// Create a renderer with specific thread count and k-d tree settings
@@ -108,6 +641,34 @@ impl Renderer {
             anti_aliasing_mode: AntiAliasingMode::Quincunx, // Default to quincunx anti-aliasing
             seed: Some(0), // Default to deterministic seed for reproducibility
             outline_config: None, // No outline detection by default
+            tilt_shift: None,
+            render_mode: RenderMode::Shaded,
+            ao_samples: 16,
+            ao_radius: 1.0,
+            shadow_samples: DEFAULT_SHADOW_SAMPLES,
+            tile_size: 32,
+            cancellation: None,
+            progress: None,
+            tone_mapping: ToneMapping::default(),
+            aovs: Vec::new(),
+            aov_false_color: None,
+            light_groups: false,
+            overlays: Vec::new(),
+            limits: RendererLimits::default(),
+            object_stats: None,
+            tile_failures: None,
+            half_res_shading: false,
+            xray: None,
+            non_finite_pixels: None,
+            framing: Framing::default(),
+            full_detail: false,
+            temporal: None,
+            strict_determinism: false,
+            batches: Mutex::new(Vec::new()),
+            temporal_history: Mutex::new(None),
+            sequence_frame: AtomicU64::new(0),
+            #[cfg(feature = "image")]
+            sequence_format: None,
         }
     }
 
@@ -117,1104 +678,5545 @@ impl Renderer {
         self
     }
 
-    pub fn render(&self, scene: &Scene) -> Result<RgbImage, Box<dyn std::error::Error>> {
-        // Validate samples parameter
-        if self.samples == 0 {
-            return Err("Samples must be greater than 0".into());
-        }
+    /// Blur by depth around a focus band, making scenes look like miniature photographs
+    pub fn with_tilt_shift(mut self, config: TiltShiftConfig) -> Self {
+        self.tilt_shift = Some(config);
+        self
+    }
 
-        let render_start_time = Instant::now();
+    /// Blend each frame of `render_frames` with the frames before it where they saw the
+    /// same surfaces, amortizing sampling noise across the sequence (see `temporal`)
+    pub fn with_temporal_accumulation(mut self, config: TemporalConfig) -> Self {
+        self.temporal = Some(config);
+        self
+    }
 
-        // Create camera
-        let aspect_ratio = self.width as f64 / self.height as f64;
-        let camera = Camera::from_config(&scene.camera, aspect_ratio)?;
-        let camera_pos = Point::new(
-            scene.camera.position[0],
-            scene.camera.position[1],
-            scene.camera.position[2],
-        );
+    /// Abort the render (with a "Render cancelled" error) once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
 
-        // Build world with objects
-        let mut world = World::new();
-        let mut materials = HashMap::new();
+    /// Send progress updates to `reporter` (renders are silent by default)
+    pub fn with_progress(mut self, reporter: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
 
-        for (index, object) in scene.objects.iter().enumerate() {
-            match object {
-                Object::Sphere {
-                    center,
-                    radius,
-                    material,
-                    transform,
-                } => {
-                    let mut center_point = Point::new(center[0], center[1], center[2]);
-                    let mut effective_radius = *radius;
+    /// Tone map and gamma encode 8-bit output (the default is a plain linear clamp)
+    pub fn with_tone_mapping(mut self, tone_mapping: ToneMapping) -> Self {
+        self.tone_mapping = tone_mapping;
+        self
+    }
 
-                    // Apply transforms if present
-                    if let Some(transform_strings) = transform {
-                        if let Ok(transform_matrix) =
-                            crate::scene::parse_transforms(transform_strings)
-                        {
-                            // Transform the center point
-                            let center_homogeneous =
-                                transform_matrix * center_point.to_homogeneous();
-                            center_point = Point::new(
-                                center_homogeneous.x,
-                                center_homogeneous.y,
-                                center_homogeneous.z,
-                            );
-
-                            // For radius, we need to consider scaling - use the maximum scale component
-                            let scale_x = (transform_matrix.column(0).xyz().magnitude()) as f64;
-                            let scale_y = (transform_matrix.column(1).xyz().magnitude()) as f64;
-                            let scale_z = (transform_matrix.column(2).xyz().magnitude()) as f64;
-                            let max_scale = scale_x.max(scale_y).max(scale_z);
-                            effective_radius *= max_scale;
-                        }
-                    }
+    /// Apply anti-aliasing, sample and framing settings stored with a scene (unset
+    /// fields are kept)
+    pub fn with_render_settings(mut self, settings: &RenderSettings) -> Self {
+        if let Some(mode) = settings.anti_aliasing {
+            self.anti_aliasing_mode = mode;
+        }
+        if let Some(samples) = settings.samples {
+            self.samples = samples;
+        }
+        if let Some(crop_to_content) = settings.crop_to_content {
+            self.framing.crop_to_content = crop_to_content;
+        }
+        if let Some(padding_percent) = settings.padding_percent {
+            self.framing.padding_percent = padding_percent;
+        }
+        self
+    }
 
-                    let color = hex_to_color(&material.color)?;
-                    let sphere = Box::new(Sphere {
-                        center: center_point,
-                        radius: effective_radius,
-                        material_color: color,
-                        material_index: index,
-                    });
-                    world.add(sphere);
-                    materials.insert(index, material.clone());
-                }
-                Object::Plane {
-                    point,
-                    normal,
-                    material,
-                    transform,
-                } => {
-                    let mut plane_point = Point::new(point[0], point[1], point[2]);
-                    let mut plane_normal = Vec3::new(normal[0], normal[1], normal[2]);
+    /// Crop and pad images saved by `render_to_sink` and `render_to_file`
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
 
-                    // Apply transforms if present
-                    if let Some(transform_strings) = transform {
-                        if let Ok(transform_matrix) =
-                            crate::scene::parse_transforms(transform_strings)
-                        {
-                            // Transform the point
-                            let point_homogeneous = transform_matrix * plane_point.to_homogeneous();
-                            plane_point = Point::new(
-                                point_homogeneous.x,
-                                point_homogeneous.y,
-                                point_homogeneous.z,
-                            );
-
-                            // Transform the normal (inverse transpose for normals)
-                            if let Some(inverse_matrix) = transform_matrix.try_inverse() {
-                                let inverse_transpose = inverse_matrix.transpose();
-                                let normal_homogeneous =
-                                    inverse_transpose * plane_normal.to_homogeneous();
-                                plane_normal = Vec3::new(
-                                    normal_homogeneous.x,
-                                    normal_homogeneous.y,
-                                    normal_homogeneous.z,
-                                );
-                            }
-                        }
-                    }
+    /// Also write these AOVs from `render_to_file`, e.g. `render.depth.exr`
+    pub fn with_aovs(mut self, aovs: Vec<Aov>) -> Self {
+        self.aovs = aovs;
+        self
+    }
 
-                    let normal_unit = nalgebra::Unit::new_normalize(plane_normal);
-                    let color = hex_to_color(&material.color)?;
-                    let plane = Box::new(Plane {
-                        point: plane_point,
-                        normal: normal_unit,
-                        material_color: color,
-                        material_index: index,
-                    });
-                    world.add(plane);
-                    materials.insert(index, material.clone());
-                }
-                Object::Cube {
-                    center,
-                    size,
-                    material,
-                    transform,
-                } => {
-                    let center_point = Point::new(center[0], center[1], center[2]);
-                    let cube_size = Vec3::new(size[0], size[1], size[2]);
-                    let color = hex_to_color(&material.color)?;
+    /// Show depth, alpha and outline AOVs through a color map when saved as 8-bit images
+    pub fn with_aov_false_color(mut self, false_color: FalseColor) -> Self {
+        self.aov_false_color = Some(false_color);
+        self
+    }
 
-                    // Create cube with transform if present
-                    let cube = if let Some(transform_strings) = transform {
-                        if let Ok(transform_matrix) =
-                            crate::scene::parse_transforms(transform_strings)
-                        {
-                            Box::new(Cube::new_with_transform(
-                                center_point,
-                                cube_size,
-                                transform_matrix,
-                                color,
-                                index,
-                            ))
-                        } else {
-                            Box::new(Cube::new(center_point, cube_size, color, index))
-                        }
-                    } else {
-                        Box::new(Cube::new(center_point, cube_size, color, index))
-                    };
-                    
-                    world.add(cube);
-                    materials.insert(index, material.clone());
-                }
-                Object::Mesh {
-                    mesh_data,
-                    material,
-                    transform,
-                    ..
-                } => {
-                    if let Some(mesh) = mesh_data {
-                        let mut transformed_mesh = mesh.clone();
-
-                        // Apply transforms if present
-                        if let Some(transform_strings) = transform {
-                            if let Ok(transform_matrix) =
-                                crate::scene::parse_transforms(transform_strings)
-                            {
-                                // Transform all vertices in the mesh
-                                for triangle in &mut transformed_mesh.triangles {
-                                    for vertex in &mut triangle.vertices {
-                                        let vertex_homogeneous =
-                                            transform_matrix * vertex.to_homogeneous();
-                                        *vertex = Point::new(
-                                            vertex_homogeneous.x,
-                                            vertex_homogeneous.y,
-                                            vertex_homogeneous.z,
-                                        );
-                                    }
-                                }
+    /// Also write one pass per light group from `render_to_file`, e.g. `render.light_key.exr`
+    pub fn with_light_groups(mut self) -> Self {
+        self.light_groups = true;
+        self
+    }
 
-                                // Update the mesh bounds after transformation
-                                transformed_mesh.compute_bounds();
+    /// Draw bounding boxes or axes over the image (see `Overlay`)
+    pub fn with_overlays(mut self, overlays: Vec<Overlay>) -> Self {
+        self.overlays = overlays;
+        self
+    }
 
-                                // Rebuild the KD-tree with transformed vertices
-                                transformed_mesh.build_kdtree();
-                            }
-                        }
+    /// Refuse renders exceeding `limits` (see `RendererLimits`)
+    pub fn with_limits(mut self, limits: RendererLimits) -> Self {
+        self.limits = limits;
+        self
+    }
 
-                        let color = hex_to_color(&material.color)?;
-                        let mesh_object = if self.use_kdtree {
-                            Box::new(MeshObject::new(transformed_mesh, color, index))
-                        } else {
-                            Box::new(MeshObject::new_brute_force(transformed_mesh, color, index))
-                        };
-                        world.add(mesh_object);
-                        materials.insert(index, material.clone());
-                    }
-                }
-            }
-        }
+    /// Count and time the intersection tests against each object into `stats`
+    pub fn with_object_stats(mut self, stats: ObjectStats) -> Self {
+        self.object_stats = Some(stats);
+        self
+    }
 
-        // Get background color
-        let background_color = if let Some(bg) = &scene.scene_settings.background_color {
-            hex_to_color(bg)?
-        } else {
-            Color::new(0.0, 0.0, 0.0)
-        };
-
-        // Set up thread pool if specific thread count is requested
-        if let Some(thread_count) = self.thread_count {
-            let pool = rayon::ThreadPoolBuilder::new()
-                .num_threads(thread_count)
-                .build()
-                .map_err(|e| format!("Failed to create thread pool: {}", e))?;
-
-            // Use the thread pool for rendering
-            let (image_data, outline_buffers) = pool.install(|| {
-                self.render_parallel(
-                    &world,
-                    &camera,
-                    &scene.lights,
-                    &scene.scene_settings.ambient_illumination,
-                    &scene.scene_settings.fog,
-                    &camera_pos,
-                    background_color,
-                    &materials,
-                )
-            });
+    /// Record tiles that fail to render, and whether retrying them helped, into `failures`
+    pub fn with_tile_failures(mut self, failures: TileFailures) -> Self {
+        self.tile_failures = Some(failures);
+        self
+    }
 
-            let total_time = render_start_time.elapsed();
-            let mut final_image_data = image_data;
-            
-            // Apply outline detection if configured
-            if let (Some(outline_config), Some(buffers)) = (&self.outline_config, outline_buffers) {
-                apply_outline_detection(&mut final_image_data, &buffers, outline_config);
-            }
-            
-            let image = self.create_image_from_data(final_image_data);
-            println!(
-                "Total rendering time: {}",
-                format_duration(total_time.as_secs_f64())
-            );
-            Ok(image)
-        } else {
-            // Use default parallel rendering with all available cores
-            let (image_data, outline_buffers) = self.render_parallel(
-                &world,
-                &camera,
-                &scene.lights,
-                &scene.scene_settings.ambient_illumination,
-                &scene.scene_settings.fog,
-                &camera_pos,
-                background_color,
-                &materials,
-            );
+    /// Paint NaN and infinite pixels `NON_FINITE_MARKER` magenta, recording each with
+    /// the object it shows into `pixels`
+    pub fn with_non_finite_check(mut self, pixels: NonFinitePixels) -> Self {
+        self.non_finite_pixels = Some(pixels);
+        self
+    }
 
-            let total_time = render_start_time.elapsed();
-            let mut final_image_data = image_data;
-            
-            // Apply outline detection if configured
-            if let (Some(outline_config), Some(buffers)) = (&self.outline_config, outline_buffers) {
-                apply_outline_detection(&mut final_image_data, &buffers, outline_config);
-            }
-            
-            let image = self.create_image_from_data(final_image_data);
-            println!(
-                "Total rendering time: {}",
-                format_duration(total_time.as_secs_f64())
-            );
-            Ok(image)
-        }
+    /// Save frame sequences from `render_frames` in `format`, whatever the output's extension
+    #[cfg(feature = "image")]
+    pub fn with_sequence_format(mut self, format: SequenceFormat) -> Self {
+        self.sequence_format = Some(format);
+        self
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn render_parallel(
-        &self,
-        world: &World,
-        camera: &Camera,
-        lights: &[crate::scene::Light],
-        ambient: &crate::scene::AmbientIllumination,
-        fog: &Option<crate::scene::Fog>,
-        camera_pos: &Point,
-        background_color: Color,
-        materials: &HashMap<usize, crate::scene::Material>,
-    ) -> (Vec<(u32, u32, Color)>, Option<OutlineBuffers>) {
-        match self.anti_aliasing_mode {
-            AntiAliasingMode::Quincunx => {
-                let image_data = self.render_quincunx(
-                    world,
-                    camera,
-                    lights,
-                    ambient,
-                    fog,
-                    camera_pos,
-                    background_color,
-                    materials,
-                );
-                
-                // For now, quincunx mode doesn't support outline detection due to shared samples
-                (image_data, None)
-            },
-            _ => {
-                if self.outline_config.is_some() {
-                    let render_context = RenderContext {
-                        ambient,
-                        fog,
-                        camera_pos,
-                        background_color,
-                    };
-                    self.render_standard_with_outline(
-                        world,
-                        camera,
-                        lights,
-                        &render_context,
-                        materials,
-                    )
-                } else {
-                    let image_data = self.render_standard(
-                        world,
-                        camera,
-                        lights,
-                        ambient,
-                        fog,
-                        camera_pos,
-                        background_color,
-                        materials,
-                    );
-                    (image_data, None)
+    /// Switch to ambient occlusion mode with the given sample count and occlusion radius
+    pub fn with_ambient_occlusion(mut self, samples: u32, radius: f64) -> Self {
+        self.render_mode = RenderMode::AmbientOcclusion;
+        self.ao_samples = samples;
+        self.ao_radius = radius;
+        self
+    }
+
+    /// Switch to preview mode, tracing one ray through each pixel center
+    ///
+    /// Surfaces keep their materials but are lit with a single hard shadow ray per light,
+    /// and reflections, refraction, caustics and fog are skipped, so large meshes render
+    /// almost at once with silhouettes where they will be in the final image. Outline
+    /// detection still runs.
+    pub fn with_preview(mut self) -> Self {
+        self.render_mode = RenderMode::Preview;
+        self.samples = 1;
+        self.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        self
+    }
+
+    /// Sample diffuse lights at `samples` points each, unless a light sets its own
+    /// `shadow_samples`; more samples give smoother penumbras at proportional cost
+    pub fn with_shadow_samples(mut self, samples: u32) -> Self {
+        self.shadow_samples = samples;
+        self
+    }
+
+    /// Shade every other pixel in each direction and upsample the rest along the
+    /// full-resolution hits, for a quicker render with softer lighting detail
+    ///
+    /// Mirrors, glass and pixels the upsampling can't match, such as silhouettes, are
+    /// still shaded at full resolution. Progressive renders and light groups ignore it.
+    pub fn with_half_res_shading(mut self) -> Self {
+        self.half_res_shading = true;
+        self
+    }
+
+    /// Draw every mesh from its own file, never one of its lower-detail `lods`
+    ///
+    /// By default a mesh with `lods` is drawn from the coarsest level that still covers
+    /// its size on screen, measured through the camera the world is built for.
+    pub fn with_full_detail(mut self) -> Self {
+        self.full_detail = true;
+        self
+    }
+
+    /// Draw every surface along each primary ray partly see-through, blended front to
+    /// back, so parts inside or behind others show through
+    ///
+    /// Each surface is lit without shadows, so enclosed parts are lit as if the shell
+    /// around them weren't there. Reflections, glass, fog and the render mode are
+    /// ignored for the surfaces it draws.
+    pub fn with_xray(mut self, config: XRayConfig) -> Self {
+        self.xray = Some(config);
+        self
+    }
+
+    /// Trace the whole quincunx corner grid before any pixel, each corner exactly once
+    ///
+    /// By default each tile traces its own corners, so corners on tile borders are
+    /// traced by both neighbors. Their seeds depend only on the corner, so the pixels
+    /// are identical either way, whatever the thread count; this mode also makes the
+    /// work done (object stats, sample ids) independent of the tile layout, at the cost
+    /// of an extra pass and a grid of `(width + 1) * (height + 1)` colors.
+    pub fn with_strict_determinism(mut self) -> Self {
+        self.strict_determinism = true;
+        self
+    }
+
+    /// Describe every setting that affects the rendered pixels
+    ///
+    /// Thread count, tile size, k-d tree usage and strict determinism are excluded since
+    /// they only change speed.
+    /// Used by the render cache to decide whether a previous render can be reused.
+    pub fn settings_fingerprint(&self) -> String {
+        format!(
+            "{}x{} depth={} samples={} aa={:?} seed={:?} outline={:?} tilt_shift={:?} mode={:?} ao={}/{} shadow_samples={} tonemap={:?} aovs={:?} aov_false_color={:?} light_groups={} overlays={:?} half_res={} xray={:?} framing={:?} full_detail={}",
+            self.width,
+            self.height,
+            self.max_depth,
+            self.samples,
+            self.anti_aliasing_mode,
+            self.seed,
+            self.outline_config,
+            self.tilt_shift,
+            self.render_mode,
+            self.ao_samples,
+            self.ao_radius,
+            self.shadow_samples,
+            self.tone_mapping,
+            self.aovs,
+            self.aov_false_color,
+            self.light_groups,
+            self.overlays,
+            self.half_res_shading,
+            self.xray,
+            self.framing,
+            self.full_detail
+        )
+    }
+
+    #[cfg(feature = "image")]
+    pub fn render(&self, scene: &Scene) -> Result<RgbImage, Box<dyn std::error::Error>> {
+        let pixels = self.render_rgb(scene)?;
+        Ok(self.image_from_rgb(pixels))
+    }
+
+    /// Render to packed 8-bit RGB pixels, row by row (3 bytes per pixel)
+    ///
+    /// Same output as `render()`, without depending on the `image` crate.
+    pub fn render_rgb(&self, scene: &Scene) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.render_with_primary_hits(scene, None)
+    }
+
+    /// Exposure in stops that brings the scene's brightest pixels to white
+    ///
+    /// Traces a quick preview at most `AUTO_EXPOSURE_SIZE` pixels across, one ray per
+    /// pixel, and measures it with `tonemap::auto_exposure`. Outlines, tilt-shift and
+    /// overlays are left out; the current exposure is ignored, so add any compensation
+    /// to the result.
+    pub fn auto_exposure(&self, scene: &Scene) -> Result<f64, Box<dyn std::error::Error>> {
+        let scale = (AUTO_EXPOSURE_SIZE as f64 / self.width.max(self.height) as f64).min(1.0);
+        let size = |n: u32| ((n as f64 * scale).round() as u32).max(1);
+        let mut preview = Renderer::new_with_options(
+            size(self.width),
+            size(self.height),
+            self.use_kdtree,
+            self.thread_count,
+        );
+        preview.max_depth = self.max_depth;
+        preview.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        preview.seed = self.seed;
+        preview.render_mode = self.render_mode.clone();
+        preview.ao_samples = self.ao_samples;
+        preview.ao_radius = self.ao_radius;
+        preview.shadow_samples = self.shadow_samples;
+        preview.cancellation = self.cancellation.clone();
+        preview.limits = self.limits;
+
+        let pixels = preview.render_frame(scene, None, None)?;
+        Ok(tonemap::auto_exposure(
+            pixels.into_iter().map(|(_, _, color)| color),
+        ))
+    }
+
+    /// Number of primary rays traced per frame with the current settings
+    ///
+    /// Quincunx traces one center per pixel plus the shared corner grid; the other
+    /// modes trace `samples` rays per pixel.
+    #[cfg(feature = "image")]
+    pub(crate) fn primary_sample_count(&self) -> usize {
+        let (width, height) = (self.width as usize, self.height as usize);
+        match self.anti_aliasing_mode {
+            AntiAliasingMode::Quincunx => width * height + (width + 1) * (height + 1),
+            _ => width * height * self.samples as usize,
+        }
+    }
+
+    /// Render to linear 32-bit float RGB, keeping radiance above 1.0
+    ///
+    /// Unlike `render()`, highlights are not clipped, so the result can be composited
+    /// or exposure-adjusted later. Clamping and quantizing it gives `render()`'s pixels.
+    #[cfg(feature = "image")]
+    pub fn render_hdr(&self, scene: &Scene) -> Result<Rgb32FImage, Box<dyn std::error::Error>> {
+        let frame = self.render_frame(scene, None, None)?;
+        Ok(self.float_image(frame))
+    }
+
+    /// Pack per-pixel colors into a float image
+    #[cfg(feature = "image")]
+    fn float_image(&self, image_data: PixelColors) -> Rgb32FImage {
+        let mut image = Rgb32FImage::new(self.width, self.height);
+        for (x, y, color) in image_data {
+            image.put_pixel(x, y, Rgb([color.x as f32, color.y as f32, color.z as f32]));
+        }
+        image
+    }
+
+    /// Render auxiliary passes for `aovs`, in the same order
+    ///
+    /// Each pass traces one ray through every pixel center, so the values are exact
+    /// per-pixel data rather than anti-aliased averages, and every AOV comes from
+    /// the same hit. Direct and indirect light use the first sample's seed. The outline
+    /// pass runs outline detection over the depth, normal and object ID passes, with the
+    /// default settings if outline detection isn't enabled.
+    pub fn render_aovs(
+        &self,
+        scene: &Scene,
+        aovs: &[Aov],
+    ) -> Result<Vec<AovBuffer>, Box<dyn std::error::Error>> {
+        self.validate()?;
+        let prepared = self.prepare_scene(scene)?;
+        let render_context = prepared.context(scene, None);
+        let pool = self.thread_pool()?;
+
+        // Outlines are found from the depth, normal and object ID passes, traced after
+        // the rest, or from buffers of their own if supersampled
+        let outline = aovs.contains(&Aov::Outline);
+        let config = self.outline_config.clone().unwrap_or_default();
+        let mut traced = aovs.to_vec();
+        if outline && config.supersample <= 1 {
+            traced.extend([Aov::Depth, Aov::Normal, Aov::ObjectId]);
+        }
+
+        let _span = profile::span("aov");
+        let results: Vec<(u32, u32, Vec<[f32; 3]>)> = self.install(pool.as_ref(), || {
+            self.render_tiles_reporting(false, |tile| {
+                tile.pixels()
+                    .map(|(x, y)| (x, y, self.aov_pixel(x, y, &traced, &render_context)))
+                    .collect()
+            })
+        })?;
+
+        let mut buffers: Vec<AovBuffer> = traced
+            .iter()
+            .map(|&aov| AovBuffer::new(aov, self.width, self.height))
+            .collect();
+        for (x, y, values) in results {
+            let index = (y * self.width + x) as usize;
+            for (buffer, value) in buffers.iter_mut().zip(values) {
+                buffer.pixels[index] = value;
+            }
+        }
+
+        if outline {
+            let outline_buffers = if config.supersample > 1 {
+                self.install(pool.as_ref(), || {
+                    self.supersampled_outline_buffers(&render_context, config.supersample)
+                })?
+            } else {
+                let object_id = buffers.pop().expect("object ID pass traced for outlines");
+                let normal = buffers.pop().expect("normal pass traced for outlines");
+                let depth = buffers.pop().expect("depth pass traced for outlines");
+                OutlineBuffers::from_aovs(&depth, &normal)?.with_object_ids(&object_id)?
+            };
+            let mask = edge_mask(&outline_buffers, &config);
+            let pixels: Vec<[f32; 3]> = mask.iter().map(|&strength| [strength as f32; 3]).collect();
+            for buffer in &mut buffers {
+                if buffer.aov == Aov::Outline {
+                    buffer.pixels = pixels.clone();
                 }
+            }
+        }
+        Ok(buffers)
+    }
+
+    /// Depth, normal and object buffers for outline detection as a post process, through
+    /// each pixel center, or each sub-pixel's if the outline config supersamples
+    ///
+    /// Pass them with an image of the scene to `outline::apply_outline_to_image`, or to
+    /// `outline::edge_mask_image` for the outlines alone.
+    pub fn render_outline_buffers(
+        &self,
+        scene: &Scene,
+    ) -> Result<OutlineBuffers, Box<dyn std::error::Error>> {
+        let scale = self
+            .outline_config
+            .as_ref()
+            .map_or(1, |config| config.supersample);
+        self.render_outline_buffers_supersampled(scene, scale)
+    }
+
+    /// `render_outline_buffers` traced through `scale` by `scale` sub-pixels of every
+    /// pixel, for an `OutlineConfig` with that `supersample`
+    pub fn render_outline_buffers_supersampled(
+        &self,
+        scene: &Scene,
+        scale: u32,
+    ) -> Result<OutlineBuffers, Box<dyn std::error::Error>> {
+        if scale > 1 {
+            self.validate()?;
+            let prepared = self.prepare_scene(scene)?;
+            let render_context = prepared.context(scene, None);
+            let pool = self.thread_pool()?;
+            return Ok(self.install(pool.as_ref(), || {
+                self.supersampled_outline_buffers(&render_context, scale)
+            })?);
+        }
+        let passes = self.render_aovs(scene, &[Aov::Depth, Aov::Normal, Aov::ObjectId])?;
+        let buffers = OutlineBuffers::from_aovs(&passes[0], &passes[1])?;
+        Ok(buffers.with_object_ids(&passes[2])?)
+    }
+
+    /// Depth and normal maps of `scene` from its outline buffers, for compositing and
+    /// training data, framed like the images `render_to_file` saves
+    ///
+    /// See `OutlineBuffers::depth_map` and `OutlineBuffers::normal_map`.
+    #[cfg(feature = "image")]
+    pub fn render_depth_normal_maps(
+        &self,
+        scene: &Scene,
+    ) -> Result<(crate::outline::DepthMap, RgbImage), Box<dyn std::error::Error>> {
+        let buffers = self.render_outline_buffers_supersampled(scene, 1)?;
+        let (depth, normals) = (buffers.depth_map(), buffers.normal_map());
+        if self.framing.is_identity() {
+            return Ok((depth, normals));
+        }
+        let window = self.framing_window(scene)?;
+        Ok((
+            window.apply(&depth, image::Luma([0])),
+            window.apply(&normals, Rgb([0; 3])),
+        ))
+    }
+
+    /// Render one pass per light group, plus an ambient pass, with the main image's sampling
+    ///
+    /// Each group pass sees only that group's lights, with ambient light, the background
+    /// and the fog color removed; the ambient pass holds what is left. Primary rays are
+    /// traced once and shared by every pass, and the passes sum to `render_hdr()` before
+    /// outline detection and tilt-shift, which are not applied to them.
+    #[cfg(feature = "image")]
+    pub fn render_light_groups(
+        &self,
+        scene: &Scene,
+    ) -> Result<Vec<LightGroupPass>, Box<dyn std::error::Error>> {
+        self.validate()?;
+        if self.render_mode != RenderMode::Shaded {
+            return Err("Light groups need the shaded render mode".into());
+        }
+        let prepared = self.prepare_scene(scene)?;
+        // Emissive materials light the scene in their own groups
+        let groups = light_groups::group_names(&prepared.shared.lights)?;
+        let primary_hits = PrimaryHitBuffer::new(self.primary_sample_count());
+        let pool = self.thread_pool()?;
+        let _span = profile::span("light groups");
+
+        let ambient_context = RenderContext {
+            lights: &[],
+            report_progress: false,
+            ..prepared.context(scene, Some(&primary_hits))
+        };
+        let mut passes = vec![LightGroupPass {
+            name: light_groups::AMBIENT_PASS.to_string(),
+            image: self.light_group_image(&ambient_context, pool.as_ref())?,
+        }];
+
+        // Group passes add light only: everything the ambient pass covers is black
+        let no_ambient = crate::scene::AmbientIllumination {
+            intensity: 0.0,
+            ..prepared.shared.ambient.clone()
+        };
+        // Volumetric fog glows with the light of each pass's own lights instead
+        let black_fog = prepared.shared.fog.clone().map(|fog| match fog.volumetric {
+            Some(_) => fog,
+            None => crate::scene::Fog {
+                color: "#000000".to_string(),
+                ..fog
             },
+        });
+        let mut gridless_camera = prepared.camera.clone();
+        gridless_camera.grid_color = None;
+
+        for name in groups {
+            let lights: Vec<crate::scene::Light> = prepared
+                .shared
+                .lights
+                .iter()
+                .filter(|light| light_groups::group_of(light) == name)
+                .cloned()
+                .collect();
+            let context = RenderContext {
+                camera: &gridless_camera,
+                lights: &lights,
+                ambient: &no_ambient,
+                fog: &black_fog,
+                background_color: Color::zeros(),
+                backplate: None,
+                ..ambient_context
+            };
+            let image = self.light_group_image(&context, pool.as_ref())?;
+            passes.push(LightGroupPass { name, image });
         }
+        Ok(passes)
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn render_standard(
+    /// Trace one light group pass into a float image
+    #[cfg(feature = "image")]
+    fn light_group_image(
         &self,
-        world: &World,
-        camera: &Camera,
-        lights: &[crate::scene::Light],
-        ambient: &crate::scene::AmbientIllumination,
-        fog: &Option<crate::scene::Fog>,
-        camera_pos: &Point,
-        background_color: Color,
-        materials: &HashMap<usize, crate::scene::Material>,
-    ) -> Vec<(u32, u32, Color)> {
-        // Create a vector of all pixel coordinates
-        let pixels: Vec<(u32, u32)> = (0..self.height)
-            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
-            .collect();
+        render_context: &RenderContext,
+        pool: Option<&ThreadPool>,
+    ) -> Result<Rgb32FImage, String> {
+        let (image_data, _) = self.install(pool, || self.render_parallel(render_context))?;
+        let mut image = Rgb32FImage::new(self.width, self.height);
+        for (x, y, color) in image_data {
+            image.put_pixel(x, y, Rgb([color.x as f32, color.y as f32, color.z as f32]));
+        }
+        Ok(image)
+    }
 
-        // Progress tracking setup
-        let total_pixels = self.width * self.height;
-        let progress_step = (total_pixels / 10).max(1);
-        let completed_pixels = AtomicUsize::new(0);
-        let progress_mutex = Mutex::new(());
-        let start_time = Instant::now();
+    /// Values of each requested AOV for the ray through the center of pixel (x, y)
+    fn aov_pixel(
+        &self,
+        x: u32,
+        y: u32,
+        aovs: &[Aov],
+        render_context: &RenderContext,
+    ) -> Vec<[f32; 3]> {
+        let to_f32 = |c: Vec3| [c.x as f32, c.y as f32, c.z as f32];
+        let u = (x as f64 + 0.5) / self.width as f64;
+        let v = 1.0 - (y as f64 + 0.5) / self.height as f64; // Flip Y coordinate
+        let seed = self.pixel_seed(x, y);
+        let ray = Self::camera_ray(render_context.camera, (u, v), seed, 0, 1);
+        let hit = Self::primary_hit(render_context, &ray, (u, v));
+
+        let needs_light = aovs.iter().any(|aov| matches!(aov, Aov::Direct | Aov::Indirect));
+        let (direct, indirect) = match (needs_light, &self.render_mode) {
+            (false, _) => (Color::zeros(), Color::zeros()),
+            (true, _) if hit.is_none() && render_context.backplate.is_some() => {
+                let (color, ..) = self.trace_sample(&ray, (u, v), render_context, None, seed, 0);
+                (color, Color::zeros())
+            }
+            (true, RenderMode::Shaded) => {
+                let (direct, indirect, _, _) = shade_hit_components(
+                    &ray,
+                    hit.as_ref(),
+                    render_context.world,
+                    render_context.lights,
+                    render_context.ambient,
+                    render_context.fog,
+                    render_context.camera_pos,
+                    render_context.background_color,
+                    render_context.materials,
+                    self.max_depth,
+                    Some(render_context.camera),
+                    seed,
+                );
+                (direct, indirect)
+            }
+            (true, _) => {
+                let (color, ..) = self.trace_sample(&ray, (u, v), render_context, None, seed, 0);
+                (color, Color::zeros())
+            }
+        };
 
-        // Render pixels in parallel
-        let results: Vec<(u32, u32, Color)> = pixels
-            .par_iter()
-            .map(|&(x, y)| {
-                // Calculate base pixel coordinates
-                let pixel_u = x as f64 / (self.width - 1) as f64;
-                let pixel_v = (self.height - 1 - y) as f64 / (self.height - 1) as f64; // Flip Y coordinate
-
-                // Calculate pixel size in UV coordinates
-                let pixel_width = 1.0 / (self.width - 1) as f64;
-                let pixel_height = 1.0 / (self.height - 1) as f64;
-
-                // Collect samples for this pixel
-                let mut total_color = Color::new(0.0, 0.0, 0.0);
-
-                // Create deterministic RNG seeded by pixel coordinates and global seed
-                let pixel_seed = self
-                    .seed
-                    .unwrap_or(0)
-                    .wrapping_mul(0x9E3779B97F4A7C15_u64)
-                    .wrapping_add((x as u64).wrapping_mul(0x85EBCA6B))
-                    .wrapping_add((y as u64).wrapping_mul(0xC2B2AE35));
-                let mut rng = rand::rngs::StdRng::seed_from_u64(pixel_seed);
-
-                for sample in 0..self.samples {
-                    let (sample_u, sample_v) = match self.anti_aliasing_mode {
-                        AntiAliasingMode::NoJitter => {
-                            // No jittering: sample at exact pixel center
-                            (pixel_u, pixel_v)
-                        }
-                        AntiAliasingMode::Stochastic => {
-                            if self.samples == 1 {
-                                // Single sample with random jitter within pixel bounds
-                                let jitter_u = rng.gen::<f64>() - 0.5; // [-0.5, 0.5]
-                                let jitter_v = rng.gen::<f64>() - 0.5; // [-0.5, 0.5]
-                                (
-                                    pixel_u + jitter_u * pixel_width,
-                                    pixel_v + jitter_v * pixel_height,
-                                )
-                            } else {
-                                // Multiple samples: radially symmetric pattern with random phase
-                                let angle = 2.0 * std::f64::consts::PI * sample as f64
-                                    / self.samples as f64;
-                                let random_phase = rng.gen::<f64>() * 2.0 * std::f64::consts::PI;
-                                let rotated_angle = angle + random_phase;
-
-                                // Use a smaller radius to keep samples within pixel bounds
-                                let radius = 0.5 * rng.gen::<f64>(); // Random radius [0, 0.5]
-                                let jitter_u = radius * rotated_angle.cos();
-                                let jitter_v = radius * rotated_angle.sin();
-
-                                (
-                                    pixel_u + jitter_u * pixel_width,
-                                    pixel_v + jitter_v * pixel_height,
-                                )
-                            }
-                        }
-                        AntiAliasingMode::Quincunx => unreachable!(), // Handled separately
-                    };
+        aovs.iter()
+            .map(|aov| match (aov, &hit) {
+                (Aov::Direct, _) => to_f32(direct),
+                (Aov::Indirect, _) => to_f32(indirect),
+                (Aov::Alpha, hit) => {
+                    [self.alpha(&ray, hit.as_ref(), render_context, seed) as f32; 3]
+                }
+                // Filled in once every pixel's depth and normal are known
+                (Aov::Outline, _) => [0.0; 3],
+                (_, None) => [0.0; 3],
+                (Aov::Depth, Some(hit)) => {
+                    [(hit.point - *render_context.camera_pos).magnitude() as f32; 3]
+                }
+                (Aov::Normal, Some(hit)) => to_f32(*hit.normal.as_ref()),
+                (Aov::ObjectId, Some(hit)) => [(hit.material_index + 1) as f32; 3],
+                (Aov::Albedo, Some(hit)) => {
+                    let material = render_context
+                        .materials
+                        .get(hit.material_key())
+                        .cloned()
+                        .unwrap_or_default();
+                    to_f32(surface_albedo(hit, &material))
+                }
+            })
+            .collect()
+    }
 
-                    let ray = camera.get_ray(sample_u, sample_v);
-
-                    // Create sample-specific seed for ray tracing consistency
-                    let sample_seed =
-                        pixel_seed.wrapping_add((sample as u64).wrapping_mul(0x1F845FED));
-
-                    let sample_color = ray_color_with_camera(
-                        &ray,
-                        world,
-                        lights,
-                        ambient,
-                        fog,
-                        camera_pos,
-                        background_color,
-                        materials,
-                        self.max_depth,
-                        Some(camera),
-                        sample_seed,
-                    );
+    /// Opacity of what `ray` shows over the background: 1 for objects, 0 for nothing, and
+    /// for a shadow catcher the strength of its shadow, or for a see-through surface its
+    /// `opacity`, laid over whatever is behind it
+    fn alpha(
+        &self,
+        ray: &Ray,
+        hit: Option<&HitRecord>,
+        render_context: &RenderContext,
+        seed: u64,
+    ) -> f64 {
+        let Some(hit) = hit else {
+            return 0.0;
+        };
+        let opacity = render_context.world.opacity(hit);
+        let catcher = Self::is_shadow_catcher(hit, render_context);
+        if !catcher && opacity >= 1.0 {
+            return 1.0;
+        }
+        let past = ray_past(ray, hit);
+        let behind = render_context.world.hit(&past, 0.001, f64::INFINITY);
+        let behind = self.alpha(&past, behind.as_ref(), render_context, seed);
+        let cover = if catcher {
+            1.0 - shadow_factor(hit, render_context.lights, render_context.world, seed)
+        } else {
+            opacity
+        };
+        behind + (1.0 - behind) * cover
+    }
+
+    fn is_shadow_catcher(hit: &HitRecord, render_context: &RenderContext) -> bool {
+        render_context
+            .materials
+            .get(hit.material_key())
+            .is_some_and(|material| material.is_shadow_catcher())
+    }
+
+    /// Outline buffers `scale` times the image's size along each axis, traced through the
+    /// center of every sub-pixel, for anti-aliased outlines
+    fn supersampled_outline_buffers(
+        &self,
+        render_context: &RenderContext,
+        scale: u32,
+    ) -> Result<OutlineBuffers, String> {
+        let (width, height) = (self.width * scale, self.height * scale);
+        let results = self.render_tiles_reporting(false, |tile| {
+            let sub_pixels = tile.pixels().flat_map(|(x, y)| {
+                (0..scale * scale)
+                    .map(move |i| (x, y, x * scale + i % scale, y * scale + i / scale))
+            });
+            sub_pixels
+                .map(|(x, y, sub_x, sub_y)| {
+                    let u = (sub_x as f64 + 0.5) / width as f64;
+                    let v = 1.0 - (sub_y as f64 + 0.5) / height as f64; // Flip Y coordinate
+                    let seed = self.pixel_seed(x, y);
+                    let ray = Self::camera_ray(render_context.camera, (u, v), seed, 0, 1);
+                    let hit = Self::primary_hit(render_context, &ray, (u, v));
+                    (sub_x, sub_y, hit)
+                })
+                .collect()
+        })?;
+
+        let mut buffers = OutlineBuffers::new(width, height);
+        for (x, y, hit) in results {
+            if let Some(hit) = hit {
+                let depth = (hit.point - *render_context.camera_pos).magnitude();
+                buffers.set_depth(x, y, depth);
+                buffers.set_normal(x, y, *hit.normal.as_ref());
+                buffers.set_object(x, y, hit.material_index);
+            }
+        }
+        Ok(buffers)
+    }
+
+    /// Distance to the first hit through each pixel center, row by row (None for background)
+    fn depth_pass(&self, render_context: &RenderContext) -> Result<Vec<Option<f64>>, String> {
+        let results: Vec<(u32, u32, Option<f64>)> = self.render_tiles_reporting(false, |tile| {
+            tile.pixels()
+                .map(|(x, y)| {
+                    let u = (x as f64 + 0.5) / self.width as f64;
+                    let v = 1.0 - (y as f64 + 0.5) / self.height as f64; // Flip Y coordinate
+                    let seed = self.pixel_seed(x, y);
+                    let ray = Self::camera_ray(render_context.camera, (u, v), seed, 0, 1);
+                    let hit = Self::primary_hit(render_context, &ray, (u, v));
+                    (x, y, hit.map(|hit| hit.t))
+                })
+                .collect()
+        })?;
+
+        let mut depths = vec![None; self.pixel_count()];
+        for (x, y, depth) in results {
+            depths[(y * self.width + x) as usize] = depth;
+        }
+        Ok(depths)
+    }
+
+    /// First surface through every pixel's center, for temporal accumulation
+    fn surface_pass(&self, render_context: &RenderContext) -> Result<Vec<Option<Surface>>, String> {
+        let results: Vec<(u32, u32, Option<Surface>)> =
+            self.render_tiles_reporting(false, |tile| {
+                tile.pixels()
+                    .map(|(x, y)| {
+                        let u = (x as f64 + 0.5) / self.width as f64;
+                        let v = 1.0 - (y as f64 + 0.5) / self.height as f64; // Flip Y coordinate
+                        let seed = self.pixel_seed(x, y);
+                        let ray = Self::camera_ray(render_context.camera, (u, v), seed, 0, 1);
+                        let hit = Self::primary_hit(render_context, &ray, (u, v));
+                        let surface = hit.map(|hit| Surface {
+                            point: hit.point,
+                            normal: *hit.normal,
+                        });
+                        (x, y, surface)
+                    })
+                    .collect()
+            })?;
+
+        let mut surfaces = vec![None; self.pixel_count()];
+        for (x, y, surface) in results {
+            surfaces[(y * self.width + x) as usize] = surface;
+        }
+        Ok(surfaces)
+    }
+
+    /// Shade the center of every other pixel in each direction, for `half_res_shading`
+    fn half_res_pass(&self, render_context: &RenderContext) -> Result<HalfResShading, String> {
+        let (width, height) = (self.width.div_ceil(2), self.height.div_ceil(2));
+        let shade_texel = |x: u32, y: u32| {
+            let u = (x as f64 + 0.5) / width as f64;
+            let v = 1.0 - (y as f64 + 0.5) / height as f64; // Flip Y coordinate
+            let seed = self.pixel_seed(x, y);
+            let ray = Self::camera_ray(render_context.camera, (u, v), seed, 0, 1);
+            let hit = time_rays(RayKind::Primary, || {
+                render_context.world.hit(&ray, 0.001, f64::INFINITY)
+            })?;
+            let albedo = self.half_res_albedo(&hit, render_context)?;
+            let camera = Some(render_context.camera);
+            let (color, _, _) = self.shade_sample(&ray, Some(&hit), render_context, camera, seed);
+            Some(Texel {
+                lighting: color.component_div(&albedo.add_scalar(ALBEDO_EPSILON)),
+                point: hit.point,
+                normal: *hit.normal.as_ref(),
+            })
+        };
+        let results = self.render_tiles_reporting(false, |tile| {
+            tile.pixels()
+                .filter(|(x, y)| x % 2 == 0 && y % 2 == 0)
+                .map(|(x, y)| (x / 2, y / 2, shade_texel(x / 2, y / 2)))
+                .collect()
+        })?;
+
+        let mut texels = vec![None; (width * height) as usize];
+        for (x, y, texel) in results {
+            texels[(y * width + x) as usize] = texel;
+        }
+        Ok(HalfResShading {
+            width,
+            height,
+            texels,
+        })
+    }
+
+    /// Surface color `half_res_shading` divides shading by, or None for surfaces shaded
+    /// at full resolution; white outside the shaded render mode
+    fn half_res_albedo(&self, hit: &HitRecord, render_context: &RenderContext) -> Option<Color> {
+        if self.render_mode != RenderMode::Shaded {
+            return Some(Color::repeat(1.0));
+        }
+        let material = match render_context.materials.get(hit.material_key()) {
+            Some(material) => effective_material(hit, material),
+            None => crate::scene::Material::default(),
+        };
+        // Reflections, refractions and what shows past shadow catchers and see-through
+        // surfaces hold detail the upsampling would blur
+        if material.reflectivity.unwrap_or(0.0) > 0.0
+            || material.transparency.unwrap_or(0.0) > 0.0
+            || material.is_shadow_catcher()
+            || material.opacity() < 1.0
+        {
+            return None;
+        }
+        Some(hex_to_color(&material.color).unwrap_or(Color::repeat(1.0)))
+    }
+
+    /// Render, reusing (or filling) a buffer of primary ray hits
+    pub(crate) fn render_with_primary_hits(
+        &self,
+        scene: &Scene,
+        primary_hits: Option<&PrimaryHitBuffer>,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let frame = self.render_frame(scene, primary_hits, None)?;
+        Ok(self.rgb_from_data(frame))
+    }
+
+    /// Trace and post-process a frame, returning linear per-pixel colors
+    ///
+    /// `on_tile` receives each tile's colors as soon as it is traced.
+    fn render_frame(
+        &self,
+        scene: &Scene,
+        primary_hits: Option<&PrimaryHitBuffer>,
+        on_tile: Option<&TileCallback>,
+    ) -> Result<PixelColors, Box<dyn std::error::Error>> {
+        self.validate()?;
+
+        let render_start_time = Instant::now();
+        self.report_progress(RenderPhase::Setup, 0, Duration::ZERO);
+        let prepared = self.prepare_scene(scene)?;
+        let render_context = prepared.context(scene, primary_hits);
+
+        let pool = self.thread_pool()?;
+        let half_res = if self.half_res_shading {
+            let _span = profile::span("half-res shading");
+            Some(self.install(pool.as_ref(), || self.half_res_pass(&render_context))?)
+        } else {
+            None
+        };
+        let render_context = RenderContext {
+            half_res: half_res.as_ref(),
+            on_tile,
+            ..render_context
+        };
+
+        let trace_start = Instant::now();
+        let trace_span = profile::span("render");
+        let (image_data, outline_buffers) =
+            self.install(pool.as_ref(), || self.render_parallel(&render_context))?;
+        drop(trace_span);
+        profile::flush_ray_totals(trace_start);
+
+        let mut final_image_data = image_data;
+        // Before post-processing spreads them to their neighbors
+        self.mark_non_finite(&mut final_image_data, &render_context, scene, true);
+
+        // Earlier frames are blended in before outlines and overlays are drawn over them
+        if let Some(temporal) = &self.temporal {
+            let mut history = self
+                .temporal_history
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            if let Some(history) = history.as_mut() {
+                let _span = profile::span("temporal accumulation");
+                let surfaces =
+                    self.install(pool.as_ref(), || self.surface_pass(&render_context))?;
+                let mut colors = vec![Color::zeros(); self.pixel_count()];
+                for &(x, y, color) in &final_image_data {
+                    colors[(y * self.width + x) as usize] = color;
+                }
+                let size = (self.width, self.height);
+                history.accumulate(render_context.camera, size, surfaces, &mut colors, temporal);
+                for (x, y, color) in &mut final_image_data {
+                    *color = colors[(*y * self.width + *x) as usize];
+                }
+            }
+        }
+
+        // Apply outline detection if configured
+        if let (Some(outline_config), Some(buffers)) = (&self.outline_config, outline_buffers) {
+            let _span = profile::span("outline detection");
+            let start = Instant::now();
+            self.report_progress(
+                RenderPhase::PostProcessing,
+                self.pixel_count(),
+                Duration::ZERO,
+            );
+            let buffers = match outline_config.supersample {
+                0 | 1 => buffers,
+                scale => self.install(pool.as_ref(), || {
+                    self.supersampled_outline_buffers(&render_context, scale)
+                })?,
+            };
+            apply_outline_detection(&mut final_image_data, &buffers, outline_config);
+            self.report_progress(
+                RenderPhase::PostProcessing,
+                self.pixel_count(),
+                start.elapsed(),
+            );
+        }
+
+        if let Some(tilt_shift) = &self.tilt_shift {
+            let _span = profile::span("tilt-shift blur");
+            let start = Instant::now();
+            self.report_progress(RenderPhase::PostProcessing, self.pixel_count(), Duration::ZERO);
+            let depths = self.install(pool.as_ref(), || self.depth_pass(&render_context))?;
+            apply_tilt_shift(&mut final_image_data, &depths, self.width, self.height, tilt_shift);
+            self.report_progress(RenderPhase::PostProcessing, self.pixel_count(), start.elapsed());
+        }
+
+        if !self.overlays.is_empty() {
+            let camera = render_context.camera;
+            let (width, height) = (self.width, self.height);
+            apply_overlays(&mut final_image_data, width, height, camera, scene, &self.overlays);
+        }
+
+        self.report_progress(
+            RenderPhase::Finished,
+            self.pixel_count(),
+            render_start_time.elapsed(),
+        );
+        Ok(final_image_data)
+    }
+
+    /// Render in passes of one sample per pixel, reporting the running average after each
+    ///
+    /// `samples` sets the number of passes. After pass `n` of `total`, `on_pass(n, total,
+    /// &image)` receives the average of the first `n` samples of every pixel, so a
+    /// preview sharpens as the render proceeds. The last snapshot is identical to
+    /// `render()` and is also returned. Quincunx uses a fixed five-sample pattern, so
+    /// it renders in a single pass.
+    #[cfg(feature = "image")]
+    pub fn render_progressive<F>(
+        &self,
+        scene: &Scene,
+        mut on_pass: F,
+    ) -> Result<RgbImage, Box<dyn std::error::Error>>
+    where
+        F: FnMut(u32, u32, &RgbImage),
+    {
+        if self.anti_aliasing_mode == AntiAliasingMode::Quincunx {
+            let image = self.render(scene)?;
+            on_pass(1, 1, &image);
+            return Ok(image);
+        }
+
+        self.validate()?;
+
+        let render_start_time = Instant::now();
+        self.report_progress(RenderPhase::Setup, 0, Duration::ZERO);
+        let prepared = self.prepare_scene(scene)?;
+        let render_context = prepared.context(scene, None);
+        let pool = self.thread_pool()?;
+
+        let pixel_count = self.pixel_count();
+        let mut totals = vec![Color::new(0.0, 0.0, 0.0); pixel_count];
+        let mut closest_hits: Vec<Option<(f64, Vec3, Option<usize>)>> = vec![None; pixel_count];
+        let mut image = RgbImage::new(self.width, self.height);
+        let depths = match self.tilt_shift {
+            Some(_) => Some(self.install(pool.as_ref(), || self.depth_pass(&render_context))?),
+            None => None,
+        };
+        let supersampled_outlines = match &self.outline_config {
+            Some(config) if config.supersample > 1 => Some(self.install(pool.as_ref(), || {
+                self.supersampled_outline_buffers(&render_context, config.supersample)
+            })?),
+            _ => None,
+        };
 
-                    total_color += sample_color;
+        let trace_start = Instant::now();
+        let trace_span = profile::span("render");
+        for pass in 0..self.samples {
+            let results: Vec<PixelRenderResult> = self.install(pool.as_ref(), || {
+                let render_tile = |tile: &Tile| {
+                    tile.pixels()
+                        .map(|(x, y)| {
+                            // Replay the jitter draws of earlier passes to reach this sample
+                            let mut rng = crate::SeededRng::seed_from_u64(self.pixel_seed(x, y));
+                            if self.anti_aliasing_mode == AntiAliasingMode::Stochastic {
+                                for _ in 0..2 * pass {
+                                    rng.gen::<f64>();
+                                }
+                            }
+                            let (color, depth, normal, object) =
+                                self.render_standard_sample(x, y, pass, &mut rng, &render_context);
+                            (x, y, color, depth, normal, object)
+                        })
+                        .collect()
+                };
+                self.render_tiles(render_tile, |&(x, y, color, ..)| finite_color(x, y, &color))
+            })?;
+
+            for (x, y, color, depth, normal, object) in results {
+                let index = (y * self.width + x) as usize;
+                totals[index] += color;
+                if let (Some(depth), Some(normal)) = (depth, normal) {
+                    if closest_hits[index].is_none_or(|(closest, ..)| depth < closest) {
+                        closest_hits[index] = Some((depth, normal, object));
+                    }
                 }
+            }
 
-                // Average the samples
-                let color = total_color / self.samples as f64;
-
-                // Update progress tracking
-                let current_completed = completed_pixels.fetch_add(1, Ordering::Relaxed) + 1;
-
-                // Print progress periodically with thread-safe output
-                if current_completed % progress_step as usize == 0
-                    || current_completed == total_pixels as usize
-                {
-                    if let Ok(_guard) = progress_mutex.lock() {
-                        let progress = (current_completed as f64 / total_pixels as f64) * 100.0;
-                        let elapsed = start_time.elapsed();
-
-                        if current_completed == total_pixels as usize {
-                            // Final progress update
-                            println!("Rendering: 100.0%");
-                        } else if progress > 0.0 {
-                            // Calculate estimated time remaining
-                            let estimated_total_time = elapsed.as_secs_f64()
-                                / (current_completed as f64 / total_pixels as f64);
-                            let estimated_remaining = estimated_total_time - elapsed.as_secs_f64();
-                            let eta_formatted = format_duration(estimated_remaining);
-                            println!("Rendering: {:.1}% (ETA: {})", progress, eta_formatted);
+            let passes_done = pass + 1;
+            let mut image_data: Vec<(u32, u32, Color)> = (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let index = (y * self.width + x) as usize;
+                    (x, y, totals[index] / passes_done as f64)
+                })
+                .collect();
+            // Recorded once, from the finished average
+            let last_pass = passes_done == self.samples;
+            self.mark_non_finite(&mut image_data, &render_context, scene, last_pass);
+
+            match (&self.outline_config, &supersampled_outlines) {
+                (Some(outline_config), Some(buffers)) => {
+                    apply_outline_detection(&mut image_data, buffers, outline_config);
+                }
+                (Some(outline_config), None) => {
+                    let mut buffers = OutlineBuffers::new(self.width, self.height);
+                    for (index, hit) in closest_hits.iter().enumerate() {
+                        if let Some((depth, normal, object)) = hit {
+                            let (x, y) = (index as u32 % self.width, index as u32 / self.width);
+                            buffers.set_depth(x, y, *depth);
+                            buffers.set_normal(x, y, *normal);
+                            if let Some(object) = object {
+                                buffers.set_object(x, y, *object);
+                            }
                         }
                     }
+                    apply_outline_detection(&mut image_data, &buffers, outline_config);
                 }
+                (None, _) => {}
+            }
+            if let (Some(tilt_shift), Some(depths)) = (&self.tilt_shift, &depths) {
+                apply_tilt_shift(&mut image_data, depths, self.width, self.height, tilt_shift);
+            }
+            if !self.overlays.is_empty() {
+                let camera = render_context.camera;
+                let (width, height) = (self.width, self.height);
+                apply_overlays(&mut image_data, width, height, camera, scene, &self.overlays);
+            }
 
-                (x, y, color)
-            })
-            .collect();
+            image = self.image_from_rgb(self.rgb_from_data(image_data));
+            on_pass(passes_done, self.samples, &image);
+        }
+        drop(trace_span);
+        profile::flush_ray_totals(trace_start);
+
+        self.report_progress(RenderPhase::Finished, pixel_count, render_start_time.elapsed());
+        Ok(image)
+    }
 
-        results
+    fn pixel_count(&self) -> usize {
+        (self.width * self.height) as usize
     }
 
-    fn render_standard_with_outline(
+    /// With `non_finite_pixels` set, replace NaN and infinite colors with
+    /// `NON_FINITE_MARKER`, recording each (if `record`) with the object hit through
+    /// the pixel center
+    fn mark_non_finite(
         &self,
-        world: &World,
-        camera: &Camera,
-        lights: &[crate::scene::Light],
+        image_data: &mut [(u32, u32, Color)],
         render_context: &RenderContext,
-        materials: &HashMap<usize, crate::scene::Material>,
-    ) -> (Vec<(u32, u32, Color)>, Option<OutlineBuffers>) {
-        use crate::lighting::ray_color_with_data;
-        
-        // Create a vector of all pixel coordinates
-        let pixels: Vec<(u32, u32)> = (0..self.height)
-            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
-            .collect();
+        scene: &Scene,
+        record: bool,
+    ) {
+        let Some(pixels) = &self.non_finite_pixels else {
+            return;
+        };
+        for (x, y, color) in image_data.iter_mut() {
+            if finite_color(*x, *y, color).is_ok() {
+                continue;
+            }
+            if record {
+                let u = (*x as f64 + 0.5) / self.width as f64;
+                let v = 1.0 - (*y as f64 + 0.5) / self.height as f64; // Flip Y coordinate
+                let seed = self.pixel_seed(*x, *y);
+                let ray = Self::camera_ray(render_context.camera, (u, v), seed, 0, 1);
+                let hit = render_context.world.hit(&ray, 0.001, f64::INFINITY);
+                pixels.record(NonFinitePixel {
+                    x: *x,
+                    y: *y,
+                    color: [color.x, color.y, color.z],
+                    object: hit.map(|hit| object_stats::label(scene, hit.material_index)),
+                });
+            }
+            *color = NON_FINITE_MARKER;
+        }
+    }
 
-        // Progress tracking setup
-        let total_pixels = self.width * self.height;
-        let progress_step = (total_pixels / 10).max(1);
-        let completed_pixels = AtomicUsize::new(0);
-        let progress_mutex = Mutex::new(());
-        let start_time = Instant::now();
+    fn report_progress(&self, phase: RenderPhase, completed_pixels: usize, elapsed: Duration) {
+        if let Some(reporter) = &self.progress {
+            reporter.report(&Progress::new(
+                phase,
+                completed_pixels,
+                self.pixel_count(),
+                elapsed,
+            ));
+        }
+    }
 
-        // Render pixels in parallel and collect outline data
-        let results: Vec<PixelRenderResult> = pixels
-            .par_iter()
-            .map(|&(x, y)| {
-                // Calculate base pixel coordinates
-                let pixel_u = x as f64 / (self.width - 1) as f64;
-                let pixel_v = (self.height - 1 - y) as f64 / (self.height - 1) as f64; // Flip Y coordinate
-
-                // Calculate pixel size in UV coordinates
-                let pixel_width = 1.0 / (self.width - 1) as f64;
-                let pixel_height = 1.0 / (self.height - 1) as f64;
-
-                // Collect samples for this pixel
-                let mut total_color = Color::new(0.0, 0.0, 0.0);
-                let mut pixel_depth = None;
-                let mut pixel_normal = None;
-
-                // Create deterministic RNG seeded by pixel coordinates and global seed
-                let pixel_seed = self
-                    .seed
-                    .unwrap_or(0)
-                    .wrapping_mul(0x9E3779B97F4A7C15_u64)
-                    .wrapping_add((x as u64).wrapping_mul(0x85EBCA6B))
-                    .wrapping_add((y as u64).wrapping_mul(0xC2B2AE35));
-                let mut rng = rand::rngs::StdRng::seed_from_u64(pixel_seed);
-
-                for sample in 0..self.samples {
-                    let (sample_u, sample_v) = match self.anti_aliasing_mode {
-                        AntiAliasingMode::NoJitter => {
-                            // No jittering: sample at exact pixel center
-                            (pixel_u, pixel_v)
-                        }
-                        AntiAliasingMode::Stochastic => {
-                            if self.samples == 1 {
-                                // Single sample with random jitter within pixel bounds
-                                let jitter_u = rng.gen::<f64>() - 0.5; // [-0.5, 0.5]
-                                let jitter_v = rng.gen::<f64>() - 0.5; // [-0.5, 0.5]
-                                (
-                                    pixel_u + jitter_u * pixel_width,
-                                    pixel_v + jitter_v * pixel_height,
-                                )
-                            } else {
-                                // Multiple samples: radially symmetric pattern with random phase
-                                let angle = 2.0 * std::f64::consts::PI * sample as f64
-                                    / self.samples as f64;
-                                let random_phase = rng.gen::<f64>() * 2.0 * std::f64::consts::PI;
-                                let rotated_angle = angle + random_phase;
-
-                                // Use a smaller radius to keep samples within pixel bounds
-                                let radius = 0.5 * rng.gen::<f64>(); // Random radius [0, 0.5]
-                                let jitter_u = radius * rotated_angle.cos();
-                                let jitter_v = radius * rotated_angle.sin();
-
-                                (
-                                    pixel_u + jitter_u * pixel_width,
-                                    pixel_v + jitter_v * pixel_height,
-                                )
-                            }
-                        }
-                        AntiAliasingMode::Quincunx => unreachable!(), // Handled separately
-                    };
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        // Validate samples parameter
+        if self.samples == 0 {
+            return Err("Samples must be greater than 0".into());
+        }
+        if self.render_mode == RenderMode::AmbientOcclusion
+            && (self.ao_samples == 0 || self.ao_radius <= 0.0)
+        {
+            return Err("Ambient occlusion requires samples > 0 and a positive radius".into());
+        }
+        if self.shadow_samples == 0 {
+            return Err("Shadow samples must be greater than 0".into());
+        }
+        if let Some(tilt_shift) = &self.tilt_shift {
+            tilt_shift.validate()?;
+        }
+        if let Some(temporal) = &self.temporal {
+            temporal.validate()?;
+        }
+        self.framing.validate()?;
+        self.limits
+            .check_render(self.width, self.height, self.max_depth)?;
+        Ok(())
+    }
+
+    /// Flat polygon traced as a small brute-force mesh
+    fn polygon_object(points: &[Point], color: Color, index: usize) -> Box<MeshObject> {
+        let mesh = crate::mesh::Mesh::from_polygon(points);
+        Box::new(MeshObject::new_brute_force(mesh, color, index))
+    }
+
+    /// Linear color of rays that miss everything (black unless the scene sets one)
+    fn background_color(scene: &Scene) -> Result<Color, String> {
+        let background_color = match &scene.scene_settings.background_color {
+            Some(bg) => hex_to_color(bg)?,
+            None => Color::new(0.0, 0.0, 0.0),
+        };
+        if scene.color_space() == ColorSpace::Srgb {
+            return Ok(background_color.map(srgb_to_linear));
+        }
+        Ok(background_color)
+    }
+
+    /// Build the camera, world and material table for `scene`
+    ///
+    /// Inside `render_cameras_to_sinks` and `render_prepared` the world is the batch's,
+    /// built once, and in `render_prepared` so is the camera.
+    fn prepare_scene(&self, scene: &Scene) -> Result<PreparedView, Box<dyn std::error::Error>> {
+        self.limits.check_scene(scene)?;
+        let batch = self.batch();
+
+        // Create camera
+        let aspect_ratio = self.width as f64 / self.height as f64;
+        let camera_config = match batch.as_ref().and_then(|(_, camera)| camera.clone()) {
+            Some(camera) => camera,
+            None => scene.fitted_camera()?,
+        };
+        let mut camera = Camera::from_config(&camera_config, aspect_ratio)?;
+        // Each sample filters textures over its share of the pixel
+        let share = match self.anti_aliasing_mode {
+            AntiAliasingMode::NoJitter => 1.0,
+            AntiAliasingMode::Quincunx => 0.5,
+            AntiAliasingMode::Stochastic => 1.0 / (self.samples.max(1) as f64).sqrt(),
+        };
+        camera.pixel_spread = Some((share / self.width as f64, share / self.height as f64));
+        let camera_pos = Point::from(camera_config.position);
+
+        let shared = match batch {
+            Some((shared, _)) => shared,
+            None => Arc::new(self.prepare_world(scene, || Ok(vec![camera_config.clone()]))?),
+        };
+        if shared.moving {
+            camera.shutter = Some(camera_config.shutter());
+        }
+        if scene.color_space() == ColorSpace::Srgb {
+            camera.grid_color = camera.grid_color.map(|color| color.map(srgb_to_linear));
+        }
+
+        Ok(PreparedView {
+            camera,
+            camera_pos,
+            shared,
+        })
+    }
+
+    /// World shared by the views of the batch this thread is rendering, if any, and the
+    /// camera it renders through if not the scene's
+    fn batch(&self) -> Option<(Arc<SceneWorld>, Option<crate::scene::Camera>)> {
+        let batches = self.batches.lock().unwrap_or_else(|e| e.into_inner());
+        let thread = std::thread::current().id();
+        let batch = batches.iter().rev().find(|batch| batch.thread == thread)?;
+        Some((Arc::clone(&batch.shared), batch.camera.clone()))
+    }
+
+    /// Run `render` with renders on this thread using the world `shared`, and `camera`
+    /// if given, instead of building their own
+    #[cfg(feature = "image")]
+    fn in_batch<T>(
+        &self,
+        shared: Arc<SceneWorld>,
+        camera: Option<crate::scene::Camera>,
+        render: impl FnOnce() -> T,
+    ) -> T {
+        let thread = std::thread::current().id();
+        let batches = || self.batches.lock().unwrap_or_else(|e| e.into_inner());
+        batches().push(Batch {
+            thread,
+            shared,
+            camera,
+        });
+        let result = render();
+        let mut batches = batches();
+        if let Some(index) = batches.iter().rposition(|batch| batch.thread == thread) {
+            batches.remove(index);
+        }
+        result
+    }
+
+    /// Settings of this renderer that the world built by `prepare_world` depends on
+    #[cfg(feature = "image")]
+    fn world_key(&self) -> String {
+        format!(
+            "mode={:?} shadow_samples={} seed={:?} kdtree={} full_detail={}",
+            self.render_mode, self.shadow_samples, self.seed, self.use_kdtree, self.full_detail
+        )
+    }
+
+    /// Build the world of `scene` once, for any number of `render_prepared` calls
+    ///
+    /// The scene's meshes must already be loaded, as for `render`.
+    #[cfg(feature = "image")]
+    pub fn prepare(&self, scene: Scene) -> Result<PreparedScene, Box<dyn std::error::Error>> {
+        self.validate()?;
+        self.limits.check_scene(&scene)?;
+        let lod = self.lod_selector(&scene.objects, || Ok(vec![scene.fitted_camera()?]))?;
+        let mut objects = Vec::with_capacity(scene.objects.len());
+        for (index, object) in scene.objects.iter().enumerate() {
+            objects.push(self.share_object(&scene, object, index, lod.as_ref())?);
+        }
+        let shared = Arc::new(self.shared_world(&scene, &objects, None)?);
+        Ok(PreparedScene {
+            scene,
+            shared,
+            objects,
+            world_key: self.world_key(),
+        })
+    }
+
+    /// Move, add or remove objects of a scene built by `prepare`
+    ///
+    /// Only the objects the edit changes are built again, so moving a part leaves the
+    /// k-d trees of every other mesh alone; the world is then put back together from
+    /// the parts, with the auto floor and caustic photons made anew. A move refits the
+    /// boxes of the world's object tree where it can, and adding or removing objects
+    /// builds the tree again. On error the scene is left as it was.
+    #[cfg(feature = "image")]
+    pub fn edit_prepared(
+        &self,
+        prepared: &mut PreparedScene,
+        edit: SceneEdit,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if prepared.world_key != self.world_key() {
+            return Err("Scene was prepared by a renderer that builds a different world".into());
+        }
+        let edited = match &edit {
+            SceneEdit::Add(object) => Some(&**object),
+            _ => None,
+        };
+        let objects = prepared.scene.objects.iter().chain(edited);
+        let lod = self.lod_selector(objects, || Ok(vec![prepared.scene.fitted_camera()?]))?;
+        let moved = matches!(edit, SceneEdit::Transform { .. });
+        let undo = match edit {
+            SceneEdit::Transform { name, transform } => {
+                let mut changed = Vec::new();
+                for index in prepared.named(&name)? {
+                    let mut object = prepared.scene.objects[index].clone();
+                    *object.transform_mut() = transform.clone();
+                    let shared =
+                        self.share_object(&prepared.scene, &object, index, lod.as_ref())?;
+                    changed.push((index, object, shared));
+                }
+                for (index, object, shared) in &mut changed {
+                    std::mem::swap(&mut prepared.scene.objects[*index], object);
+                    std::mem::swap(&mut prepared.objects[*index], shared);
+                }
+                Undo::Restore(changed)
+            }
+            SceneEdit::Add(object) => {
+                let index = prepared.scene.objects.len();
+                let shared = self.share_object(&prepared.scene, &object, index, lod.as_ref())?;
+                prepared.scene.objects.push(*object);
+                prepared.objects.push(shared);
+                Undo::Pop
+            }
+            SceneEdit::Remove { name } => {
+                let indices = prepared.named(&name)?;
+                let mut removed: Vec<_> = (indices.into_iter().rev())
+                    .map(|index| {
+                        let object = prepared.scene.objects.remove(index);
+                        (index, object, prepared.objects.remove(index))
+                    })
+                    .collect();
+                removed.reverse();
+                Undo::Reinsert(removed)
+            }
+        };
+
+        // Moving objects keeps the world's tree, refit around them
+        let previous = (moved.then(|| prepared.shared.world.bvh())).flatten();
+        match self.shared_world(&prepared.scene, &prepared.objects, previous) {
+            Ok(world) => {
+                prepared.shared = Arc::new(world);
+                Ok(())
+            }
+            Err(error) => {
+                match undo {
+                    Undo::Restore(changed) => {
+                        for (index, object, shared) in changed {
+                            prepared.scene.objects[index] = object;
+                            prepared.objects[index] = shared;
+                        }
+                    }
+                    Undo::Pop => {
+                        prepared.scene.objects.pop();
+                        prepared.objects.pop();
+                    }
+                    Undo::Reinsert(removed) => {
+                        for (index, object, shared) in removed {
+                            prepared.scene.objects.insert(index, object);
+                            prepared.objects.insert(index, shared);
+                        }
+                    }
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Render a scene built by `prepare` through `camera`, without rebuilding its world
+    ///
+    /// Mesh levels of detail and lines with pixel widths stay as `prepare` sized them for
+    /// the scene's own camera. Otherwise this is the image `render` gives for the scene
+    /// with that camera, and always is for the scene's own camera.
+    #[cfg(feature = "image")]
+    pub fn render_prepared(
+        &self,
+        prepared: &PreparedScene,
+        camera: &CameraOverride,
+    ) -> Result<RgbImage, Box<dyn std::error::Error>> {
+        if prepared.world_key != self.world_key() {
+            return Err("Scene was prepared by a renderer that builds a different world".into());
+        }
+        let camera = camera.apply(&prepared.scene)?;
+        let shared = Arc::clone(&prepared.shared);
+        self.in_batch(shared, Some(camera), || self.render(&prepared.scene))
+    }
+
+    /// `build_object` for a `PreparedScene`, keyed as if it came after every object
+    /// already in `scene` so its face materials can't be mistaken for object indices
+    #[cfg(feature = "image")]
+    fn share_object(
+        &self,
+        scene: &Scene,
+        object: &Object,
+        index: usize,
+        lod: Option<&LodSelector>,
+    ) -> Result<SharedObject, Box<dyn std::error::Error>> {
+        let face_base = scene.objects.len() + 2;
+        let built = self.build_object(scene, object, index, face_base, lod)?;
+        Ok(SharedObject::new(built, index, face_base))
+    }
+
+    /// `prepare_world` for a scene whose objects are already built, refitting `previous`,
+    /// the tree of the world before some of them moved, if they still fit it
+    #[cfg(feature = "image")]
+    fn shared_world(
+        &self,
+        scene: &Scene,
+        shared: &[SharedObject],
+        previous: Option<&Bvh>,
+    ) -> Result<SceneWorld, Box<dyn std::error::Error>> {
+        let _span = profile::span("scene setup");
+        let floor = scene.auto_floor()?;
+        let objects: Vec<&Object> = scene.objects.iter().chain(&floor).collect();
+        let mut face_base = objects.len();
+        let mut built = Vec::with_capacity(objects.len());
+        for (index, object) in shared.iter().enumerate() {
+            built.push(object.placed(index, face_base));
+            face_base += object.face_materials.len();
+        }
+        if let Some(floor) = &floor {
+            built.push(self.build_object(scene, floor, shared.len(), face_base, None)?);
+        }
+        self.assemble_world(scene, &objects, built, previous)
+    }
+
+    /// What picks the level of detail of meshes seen through `cameras` and sizes lines
+    /// in pixels, if any of `objects` has levels the renderer draws or is such a line
+    fn lod_selector<'a>(
+        &self,
+        objects: impl IntoIterator<Item = &'a Object>,
+        cameras: impl FnOnce() -> Result<Vec<crate::scene::Camera>, String>,
+    ) -> Result<Option<LodSelector>, String> {
+        let objects: Vec<&Object> = objects.into_iter().collect();
+        let levels = !self.full_detail && LodSelector::needed(objects.iter().copied());
+        if !levels && !LodSelector::needed_for_lines(objects) {
+            return Ok(None);
+        }
+        let selector = LodSelector::new(&cameras()?, self.width, self.height)?;
+        Ok(Some(match self.full_detail {
+            true => selector.with_full_detail(),
+            false => selector,
+        }))
+    }
+
+    /// Build the world, material table and lights for `scene`, everything but its camera,
+    /// with mesh levels of detail picked for `cameras`
+    fn prepare_world(
+        &self,
+        scene: &Scene,
+        cameras: impl FnOnce() -> Result<Vec<crate::scene::Camera>, String>,
+    ) -> Result<SceneWorld, Box<dyn std::error::Error>> {
+        let _span = profile::span("scene setup");
+        let floor = scene.auto_floor()?;
+        let objects: Vec<&Object> = scene.objects.iter().chain(&floor).collect();
+        let lod = self.lod_selector(&scene.objects, cameras)?;
+        // Mesh face materials follow the objects' own in the material table
+        let mut face_base = objects.len();
+        let mut built = Vec::with_capacity(objects.len());
+        for (index, &object) in objects.iter().enumerate() {
+            let object = self.build_object(scene, object, index, face_base, lod.as_ref())?;
+            face_base += object.face_materials.len();
+            built.push(object);
+        }
+        self.assemble_world(scene, &objects, built, None)
+    }
+
+    /// World parts, material and emissive lights of `object`, the object with index
+    /// `index`, with its mesh face materials keyed from `face_base` and its mesh level
+    /// of detail picked by `lod`
+    fn build_object(
+        &self,
+        scene: &Scene,
+        object: &Object,
+        index: usize,
+        face_base: usize,
+        lod: Option<&LodSelector>,
+    ) -> Result<BuiltObject, Box<dyn std::error::Error>> {
+        let mut parts: Vec<Box<dyn Intersectable + Send + Sync>> = Vec::new();
+        let mut own_material = crate::scene::Material::default();
+        let mut face_table = Vec::new();
+        let mut emitters = Vec::new();
+        match object {
+            Object::Sphere {
+                center,
+                radius,
+                material,
+                transform,
+                ..
+            } => {
+                let center_point = Point::new(center[0], center[1], center[2]);
+                let color = hex_to_color(&material.color)?;
+                let transform_matrix = transform
+                    .as_ref()
+                    .and_then(|strings| crate::scene::parse_transforms(strings).ok());
+
+                if let Some(transform_matrix) = transform_matrix {
+                    // Nonuniform scales and shears make an ellipsoid
+                    let Some(ellipsoid) =
+                        Ellipsoid::new(center_point, *radius, transform_matrix, color, index)
+                    else {
+                        return Err("Sphere has a transform that can't be inverted".into());
+                    };
+                    parts.push(Box::new(ellipsoid));
+                } else {
+                    parts.push(Box::new(Sphere {
+                        center: center_point,
+                        radius: *radius,
+                        material_color: color,
+                        material_index: index,
+                    }));
+                }
+                own_material = material.clone();
+                emitters.extend(emissive_light(material, index, Vec::new)?);
+            }
+            Object::Plane {
+                point,
+                normal,
+                width,
+                height,
+                material,
+                transform,
+                ..
+            } => {
+                let color = hex_to_color(&material.color)?;
+                let plane = crate::scene::transform_plane(
+                    point, normal, *width, *height, transform, color, index,
+                )?;
+                parts.push(Box::new(plane));
+                own_material = material.clone();
+                emitters.extend(emissive_light(material, index, Vec::new)?);
+            }
+            Object::FloorGrid {
+                point,
+                normal,
+                pitch,
+                line_width,
+                line_color,
+                extent,
+                fade_distance,
+                material,
+                transform,
+                ..
+            } => {
+                let material = crate::scene::floor_grid_material(
+                    material,
+                    *pitch,
+                    *line_width,
+                    line_color,
+                    *fade_distance,
+                )?;
+                let color = hex_to_color(&material.color)?;
+                let plane = crate::scene::transform_plane(
+                    point, normal, *extent, *extent, transform, color, index,
+                )?;
+                parts.push(Box::new(plane));
+                emitters.extend(emissive_light(&material, index, Vec::new)?);
+                own_material = material;
+            }
+            Object::Cube {
+                center,
+                size,
+                material,
+                transform,
+                ..
+            } => {
+                let center_point = Point::new(center[0], center[1], center[2]);
+                let cube_size = Vec3::new(size[0], size[1], size[2]);
+                let color = hex_to_color(&material.color)?;
+
+                // Create cube with transform if present
+                let cube = if let Some(transform_strings) = transform {
+                    if let Ok(transform_matrix) = crate::scene::parse_transforms(transform_strings)
+                    {
+                        Box::new(Cube::new_with_transform(
+                            center_point,
+                            cube_size,
+                            transform_matrix,
+                            color,
+                            index,
+                        ))
+                    } else {
+                        Box::new(Cube::new(center_point, cube_size, color, index))
+                    }
+                } else {
+                    Box::new(Cube::new(center_point, cube_size, color, index))
+                };
+
+                parts.push(cube);
+                own_material = material.clone();
+                emitters.extend(emissive_light(material, index, Vec::new)?);
+            }
+            Object::Instance {
+                reference,
+                material,
+                transform,
+                ..
+            } => {
+                let prototype = scene
+                    .meshes
+                    .get(reference)
+                    .ok_or_else(|| format!("Instance refers to unknown mesh '{}'", reference))?;
+                // Skipped missing meshes have no data and render nothing
+                if let Some(mesh) = &prototype.mesh_data {
+                    let transform_matrix = transform
+                        .as_ref()
+                        .and_then(|strings| crate::scene::parse_transforms(strings).ok())
+                        .unwrap_or_else(nalgebra::Matrix4::identity);
+                    let color = hex_to_color(&material.color)?;
+                    let instance =
+                        InstanceObject::new(Arc::clone(mesh), transform_matrix, color, index);
+                    let Some(mut instance) = instance else {
+                        return Err(format!(
+                            "Instance of mesh '{}' has a transform that can't be inverted",
+                            reference
+                        )
+                        .into());
+                    };
+                    instance.use_kdtree = self.use_kdtree;
+                    parts.push(Box::new(instance));
+                    own_material = material.clone();
+                    emitters.extend(emissive_light(material, index, || {
+                        mesh.triangles
+                            .iter()
+                            .map(|triangle| {
+                                triangle
+                                    .vertices
+                                    .map(|v| transform_matrix.transform_point(&v))
+                            })
+                            .collect()
+                    })?);
+                }
+            }
+            Object::Triangle {
+                vertices,
+                material,
+                transform,
+                ..
+            } => {
+                let points = crate::scene::transform_points(vertices, transform);
+                let color = hex_to_color(&material.color)?;
+                parts.push(Self::polygon_object(&points, color, index));
+                own_material = material.clone();
+                emitters.extend(emissive_light(material, index, || {
+                    let polygon = crate::mesh::Mesh::from_polygon(&points);
+                    polygon.triangles.iter().map(|t| t.vertices).collect()
+                })?);
+            }
+            Object::Quad {
+                vertices,
+                material,
+                transform,
+                ..
+            } => {
+                let points = crate::scene::transform_points(vertices, transform);
+                let color = hex_to_color(&material.color)?;
+                parts.push(Self::polygon_object(&points, color, index));
+                own_material = material.clone();
+                emitters.extend(emissive_light(material, index, || {
+                    let polygon = crate::mesh::Mesh::from_polygon(&points);
+                    polygon.triangles.iter().map(|t| t.vertices).collect()
+                })?);
+            }
+            Object::Disc {
+                center,
+                normal,
+                radius,
+                material,
+                transform,
+                ..
+            } => {
+                let color = hex_to_color(&material.color)?;
+                let disc =
+                    crate::scene::transform_disc(center, normal, *radius, transform, color, index);
+                parts.push(Box::new(disc));
+                own_material = material.clone();
+                emitters.extend(emissive_light(material, index, Vec::new)?);
+            }
+            Object::Line {
+                points,
+                width,
+                width_unit,
+                material,
+                transform,
+                ..
+            } => {
+                if points.len() < 2 {
+                    return Err("Line needs at least two points".into());
+                }
+                if !(width.is_finite() && *width > 0.0) {
+                    return Err("Line width must be greater than 0".into());
+                }
+                let (points, half_width) = crate::scene::transform_line(points, *width, transform);
+                let half_widths = match width_unit {
+                    LineWidthUnit::World => vec![half_width; points.len()],
+                    LineWidthUnit::Pixels => {
+                        let view = lod.ok_or("Line width in pixels needs a camera to size it")?;
+                        (points.iter())
+                            .map(|point| view.pixel_size(point) * width / 2.0)
+                            .collect()
+                    }
+                };
+                let color = hex_to_color(&material.color)?;
+                parts.push(Box::new(Ribbon::new(points, half_widths, color, index)));
+                own_material = material.clone();
+                emitters.extend(emissive_light(material, index, Vec::new)?);
+            }
+            Object::Tube {
+                name,
+                material,
+                transform,
+                motion,
+                ignore_clip,
+                ..
+            }
+            | Object::Arrow {
+                name,
+                material,
+                transform,
+                motion,
+                ignore_clip,
+                ..
+            } => {
+                // Built as the mesh it is tessellated into
+                let mesh = Object::Mesh {
+                    name: name.clone(),
+                    filename: String::new(),
+                    material: material.clone(),
+                    face_materials: Vec::new(),
+                    orient: None,
+                    unit: None,
+                    lods: Vec::new(),
+                    transform: transform.clone(),
+                    motion: motion.clone(),
+                    ignore_clip: *ignore_clip,
+                    mesh_data: object.tessellated_mesh()?,
+                };
+                return self.build_object(scene, &mesh, index, face_base, lod);
+            }
+            Object::Glyphs {
+                name,
+                material,
+                transform,
+                motion,
+                ignore_clip,
+                ..
+            } => {
+                // One mesh of every glyph, colored through face materials by scalar level
+                let (mesh_data, face_materials) = match crate::glyphs::glyph_field(object)? {
+                    Some((mesh, face_materials)) => (Some(mesh), face_materials),
+                    None => (None, Vec::new()),
+                };
+                let mesh = Object::Mesh {
+                    name: name.clone(),
+                    filename: String::new(),
+                    material: material.clone(),
+                    face_materials,
+                    orient: None,
+                    unit: None,
+                    lods: Vec::new(),
+                    transform: transform.clone(),
+                    motion: motion.clone(),
+                    ignore_clip: *ignore_clip,
+                    mesh_data,
+                };
+                return self.build_object(scene, &mesh, index, face_base, lod);
+            }
+            Object::Mesh {
+                mesh_data,
+                material,
+                face_materials,
+                lods,
+                transform,
+                ..
+            } => {
+                if let Some(mesh) = mesh_data {
+                    let mesh = match lod {
+                        Some(selector) if !lods.is_empty() => {
+                            let transform_matrix = (transform.as_ref())
+                                .and_then(|strings| crate::scene::parse_transforms(strings).ok());
+                            selector.pick(mesh, lods, transform_matrix.as_ref())
+                        }
+                        _ => mesh,
+                    };
+                    let mut transformed_mesh = mesh.clone();
+
+                    // Apply transforms if present
+                    if let Some(transform_strings) = transform {
+                        if let Ok(transform_matrix) =
+                            crate::scene::parse_transforms(transform_strings)
+                        {
+                            // Transform all vertices in the mesh
+                            for triangle in &mut transformed_mesh.triangles {
+                                for vertex in &mut triangle.vertices {
+                                    let vertex_homogeneous =
+                                        transform_matrix * vertex.to_homogeneous();
+                                    *vertex = Point::new(
+                                        vertex_homogeneous.x,
+                                        vertex_homogeneous.y,
+                                        vertex_homogeneous.z,
+                                    );
+                                }
+                            }
+
+                            // Update the mesh bounds after transformation
+                            transformed_mesh.compute_bounds();
+
+                            // Rebuild the KD-tree with transformed vertices
+                            transformed_mesh.build_kdtree();
+                        }
+                    }
+
+                    let faces = if face_materials.is_empty() {
+                        Vec::new()
+                    } else {
+                        FaceMaterial::assign(face_materials, mesh)?
+                    };
+                    // Triangles showing the face material in `slot`, or the mesh's own
+                    let triangles_of = |slot: Option<u16>| {
+                        transformed_mesh
+                            .triangles
+                            .iter()
+                            .enumerate()
+                            .filter(|(face, _)| faces.get(*face).copied().flatten() == slot)
+                            .map(|(_, triangle)| triangle.vertices)
+                            .collect()
+                    };
+                    emitters.extend(emissive_light(material, index, || triangles_of(None))?);
+
+                    let mut palette = Vec::new();
+                    for (slot, face_material) in face_materials.iter().enumerate() {
+                        let material = &face_material.material;
+                        let face_key = face_base + face_table.len();
+                        palette.push((face_key, hex_to_color(&material.color)?));
+                        face_table.push(material.clone());
+                        emitters.extend(emissive_light(material, face_key, || {
+                            triangles_of(Some(slot as u16))
+                        })?);
+                    }
+
+                    let color = hex_to_color(&material.color)?;
+                    let mut mesh_object = if self.use_kdtree {
+                        MeshObject::new(transformed_mesh, color, index)
+                    } else {
+                        MeshObject::new_brute_force(transformed_mesh, color, index)
+                    };
+                    if !face_materials.is_empty() {
+                        mesh_object =
+                            mesh_object.with_face_materials(FaceMaterials { palette, faces });
+                    }
+                    parts.push(Box::new(mesh_object));
+                    own_material = material.clone();
+                }
+            }
+            // Volumes join the world as it is put together
+            Object::Volume { .. } => {}
+            Object::Group { name, .. } => {
+                return Err(format!(
+                    "Group '{}' is not expanded; call Scene::load_mesh_data first",
+                    name.as_deref().unwrap_or("unnamed")
+                )
+                .into());
+            }
+        }
+
+        Ok(BuiltObject {
+            parts,
+            material: own_material,
+            face_materials: face_table,
+            emitters,
+        })
+    }
+
+    /// Put the objects `built` for `objects`, the scene's own and its auto floor, together
+    /// into the world of `scene`, its tree refit from `previous` where they fit it
+    fn assemble_world(
+        &self,
+        scene: &Scene,
+        objects: &[&Object],
+        built: Vec<BuiltObject>,
+        previous: Option<&Bvh>,
+    ) -> Result<SceneWorld, Box<dyn std::error::Error>> {
+        let mut world = World::new();
+        // Materials by object index, then those of individual mesh faces in the order
+        // they are met; a Vec rather than a map keeps every lookup and walk in order
+        let mut materials = Vec::with_capacity(objects.len());
+        let mut face_materials = Vec::new();
+        // Lights cast by emissive materials, added after the scene's own
+        let mut emitters = Vec::new();
+        // Runs of world objects carried along the same motion transforms
+        let mut moving: Vec<(std::ops::Range<usize>, &[String])> = Vec::new();
+
+        for (&object, built) in objects.iter().zip(built) {
+            let first_part = world.objects.len();
+            world.objects.extend(built.parts);
+            materials.push(built.material);
+            face_materials.extend(built.face_materials);
+            emitters.extend(built.emitters);
+            // Volumes are marched through while shading rather than hit
+            world.volumes.extend(object.volume()?);
+
+            if let Some(motion) = object.motion().filter(|motion| !motion.is_empty()) {
+                let parts = first_part..world.objects.len();
+                match moving.last_mut() {
+                    // Neighbors moving alike, like the children of a group, share one
+                    // motion so it's worked out once per ray
+                    Some((previous, previous_motion))
+                        if previous.end == parts.start && *previous_motion == motion =>
+                    {
+                        previous.end = parts.end
+                    }
+                    _ => moving.push((parts, motion)),
+                }
+            }
+        }
+
+        materials.extend(face_materials);
+
+        if let Some(stats) = &self.object_stats {
+            world.objects = std::mem::take(&mut world.objects)
+                .into_iter()
+                .map(|object| -> Box<dyn Intersectable + Send + Sync> {
+                    Box::new(Counted::new(object, stats))
+                })
+                .collect();
+        }
+
+        // Wrapped last, from the back so earlier runs keep their place
+        let moving_objects = !moving.is_empty();
+        for (parts, motion) in moving.into_iter().rev() {
+            let motion = crate::scene::parse_motion(motion)?;
+            let start = parts.start;
+            let parts = world.objects.drain(parts).collect();
+            world
+                .objects
+                .insert(start, Box::new(MovingObject { parts, motion }));
+        }
+        if !previous.is_some_and(|bvh| world.refit_bvh(bvh)) {
+            world.build_bvh();
+        }
+
+        let background_color = Self::background_color(scene)?;
+        if let Some(background) = &scene.scene_settings.background {
+            if background.backplate.is_none() {
+                return Err(format!(
+                    "Background image '{}' is not loaded; call Scene::load_mesh_data first",
+                    background.image
+                )
+                .into());
+            }
+        }
+
+        if let Some(fog) = &scene.scene_settings.fog {
+            fog.validate()?;
+        }
+
+        for plane in &scene.scene_settings.clip_planes {
+            plane.validate()?;
+            let normal = Vec3::new(plane.normal[0], plane.normal[1], plane.normal[2]);
+            let point = Point::new(plane.point[0], plane.point[1], plane.point[2]);
+            world.clip_planes.push((point, normal.normalize()));
+        }
+        world.clip_exempt = objects.iter().map(|object| object.ignores_clip()).collect();
+
+        for material in &materials {
+            material.validate()?;
+        }
+        world.opacities = materials
+            .iter()
+            .map(|material| material.opacity())
+            .collect();
+
+        let mut lights = scene.lights.clone();
+        lights.extend(emitters);
+        for light in &mut lights {
+            light.validate()?;
+            light.shadow_samples.get_or_insert(self.shadow_samples);
+            // Previews cast a single hard shadow ray toward each light
+            if self.render_mode == RenderMode::Preview {
+                light.diameter = None;
+                light.shadow_samples = Some(1);
+            }
+        }
+
+        let mut ambient = scene.scene_settings.ambient_illumination.clone();
+        let mut fog = scene.scene_settings.fog.clone();
+        let mut backplate = None;
+        // Light linear values: decode every color and image of an sRGB scene up front
+        if scene.color_space() == ColorSpace::Srgb {
+            for material in &mut materials {
+                material.decode_srgb()?;
+            }
+            for light in &mut lights {
+                decode_srgb_hex(&mut light.color)?;
+            }
+            decode_srgb_hex(&mut ambient.color)?;
+            if let Some(fog) = &mut fog {
+                decode_srgb_hex(&mut fog.color)?;
+            }
+            for volume in &mut world.volumes {
+                volume.color = volume.color.map(srgb_to_linear);
+            }
+            backplate = scene
+                .scene_settings
+                .background
+                .as_ref()
+                .and_then(|background| background.backplate.as_ref())
+                .map(Backplate::to_linear);
+        }
+
+        // Previews leave caustics out, along with the mirrors and glass that cast them
+        let caustics = (scene.scene_settings.caustics.as_ref())
+            .filter(|_| self.render_mode != RenderMode::Preview);
+        if let Some(caustics) = caustics {
+            caustics.validate()?;
+            let _span = profile::span("caustics");
+            let targets = crate::caustics::caustic_targets(scene);
+            let seed = self.seed.unwrap_or(0);
+            for (index, light) in lights.iter_mut().enumerate() {
+                let photons = crate::caustics::trace_photons(
+                    caustics, light, index, &targets, &world, &materials, seed,
+                );
+                light.caustics = photons.map(Arc::new);
+            }
+        }
+
+        Ok(SceneWorld {
+            world,
+            materials,
+            lights,
+            ambient,
+            fog,
+            background_color,
+            backplate,
+            moving: moving_objects,
+        })
+    }
+
+    /// Dedicated thread pool when a specific thread count is requested
+    #[cfg(feature = "parallel")]
+    fn thread_pool(&self) -> Result<Option<ThreadPool>, String> {
+        self.thread_count
+            .map(|thread_count| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(thread_count)
+                    .build()
+                    .map_err(|e| format!("Failed to create thread pool: {}", e))
+            })
+            .transpose()
+    }
+
+    /// Run `op` inside `pool`, or on the global rayon pool when there is none
+    #[cfg(feature = "parallel")]
+    fn install<T, OP>(&self, pool: Option<&ThreadPool>, op: OP) -> T
+    where
+        T: Send,
+        OP: FnOnce() -> T + Send,
+    {
+        match pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Without the `parallel` feature everything runs on the calling thread
+    #[cfg(not(feature = "parallel"))]
+    fn thread_pool(&self) -> Result<Option<ThreadPool>, String> {
+        Ok(None)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn install<T, OP>(&self, _pool: Option<&ThreadPool>, op: OP) -> T
+    where
+        OP: FnOnce() -> T,
+    {
+        op()
+    }
+
+    fn render_parallel(&self, render_context: &RenderContext) -> Result<FrameData, String> {
+        match self.anti_aliasing_mode {
+            AntiAliasingMode::Quincunx => self.render_quincunx(render_context),
+            _ => self.render_standard(render_context),
+        }
+    }
+
+    /// Trace a single primary ray according to the active render mode
+    ///
+    /// Returns the sample color along with the camera-space depth, world normal and
+    /// object index of the first hit (used by outline detection). `sample_id`
+    /// identifies the primary ray within the frame so its hit can be reused across
+    /// re-renders, and `uv` is its screen position, where a miss shows the backplate.
+    fn trace_sample(
+        &self,
+        ray: &Ray,
+        uv: (f64, f64),
+        render_context: &RenderContext,
+        camera: Option<&Camera>,
+        seed: u64,
+        sample_id: usize,
+    ) -> SampleResult {
+        if self.max_depth <= 0 || !render_context.camera.in_image(uv.0, uv.1) {
+            return (Color::new(0.0, 0.0, 0.0), None, None, None);
+        }
+
+        let trace = || {
+            time_rays(RayKind::Primary, || {
+                render_context.world.hit(ray, 0.001, f64::INFINITY)
+            })
+        };
+        let hit = match render_context.primary_hits {
+            Some(buffer) => buffer.get_or_trace(sample_id, trace),
+            None => trace(),
+        };
+
+        let (color, depth, normal) =
+            self.shade_primary(ray, hit.as_ref(), uv, render_context, camera, seed);
+        let object = depth.and(hit.map(|hit| hit.material_index));
+        (color, depth, normal, object)
+    }
+
+    /// Shade a primary ray's first hit, or miss, as `trace_sample` does
+    fn shade_primary(
+        &self,
+        ray: &Ray,
+        hit: Option<&HitRecord>,
+        uv: (f64, f64),
+        render_context: &RenderContext,
+        camera: Option<&Camera>,
+        seed: u64,
+    ) -> (Color, Option<f64>, Option<Vec3>) {
+        if let (None, Some(backplate)) = (hit, render_context.backplate) {
+            return (backplate.sample(uv.0, uv.1), None, None);
+        }
+        if let (Some(xray), Some(hit)) = (&self.xray, hit) {
+            return Self::xray_sample(xray, ray, hit, uv, render_context, seed);
+        }
+
+        // Shadow catchers in front of the backplate darken it, which shading alone can't
+        // see as it only knows the background color
+        if let (Some(hit), Some(backplate)) = (hit, render_context.backplate) {
+            let world = render_context.world;
+            let behind = || world.hit(&ray_past(ray, hit), 0.001, f64::INFINITY);
+            if self.render_mode == RenderMode::Shaded
+                && Self::is_shadow_catcher(hit, render_context)
+                && behind().is_none()
+            {
+                let lit = shadow_factor(hit, render_context.lights, world, seed);
+                let depth = (hit.point - *render_context.camera_pos).magnitude();
+                let color = backplate.sample(uv.0, uv.1) * lit;
+                return (color, Some(depth), Some(*hit.normal.as_ref()));
+            }
+        }
+
+        if let (Some(half_res), Some(hit)) = (render_context.half_res, hit) {
+            if let Some(albedo) = self.half_res_albedo(hit, render_context) {
+                let depth = (hit.point - *render_context.camera_pos).magnitude();
+                let normal = *hit.normal.as_ref();
+                if let Some(lighting) = half_res.lighting(uv, &hit.point, &normal, depth) {
+                    let color = lighting.component_mul(&albedo.add_scalar(ALBEDO_EPSILON));
+                    return (color, Some(depth), Some(normal));
+                }
+            }
+        }
+
+        self.shade_sample(ray, hit, render_context, camera, seed)
+    }
+
+    /// Blend every surface along `ray` from its first hit on, each lit without shadows,
+    /// over the background
+    fn xray_sample(
+        xray: &XRayConfig,
+        ray: &Ray,
+        first: &HitRecord,
+        uv: (f64, f64),
+        render_context: &RenderContext,
+        seed: u64,
+    ) -> (Color, Option<f64>, Option<Vec3>) {
+        let unoccluded = World::new();
+        let surfaces = std::iter::successors(Some((ray.clone(), first.clone())), |(ray, hit)| {
+            let past = ray_past(ray, hit);
+            let next = render_context.world.hit(&past, 0.001, f64::INFINITY)?;
+            Some((past, next))
+        });
+        let layers = surfaces.map(|(_, hit)| {
+            let material = render_context
+                .materials
+                .get(hit.material_key())
+                .cloned()
+                .unwrap_or_default();
+            phong_lighting(
+                &shading_hit(&hit, &material),
+                &material,
+                render_context.lights,
+                render_context.ambient,
+                render_context.camera_pos,
+                &unoccluded,
+                seed,
+            )
+        });
+        let background = match render_context.backplate {
+            Some(backplate) => backplate.sample(uv.0, uv.1),
+            None => render_context.background_color,
+        };
+        let depth = (first.point - *render_context.camera_pos).magnitude();
+        let color = xray.composite(layers, background);
+        (color, Some(depth), Some(*first.normal.as_ref()))
+    }
+
+    /// Shade an already-traced primary hit (or miss) according to the active render mode
+    fn shade_sample(
+        &self,
+        ray: &Ray,
+        hit: Option<&HitRecord>,
+        render_context: &RenderContext,
+        camera: Option<&Camera>,
+        seed: u64,
+    ) -> (Color, Option<f64>, Option<Vec3>) {
+        match self.render_mode {
+            RenderMode::Shaded => shade_hit_with_data(
+                ray,
+                hit,
+                render_context.world,
+                render_context.lights,
+                render_context.ambient,
+                render_context.fog,
+                render_context.camera_pos,
+                render_context.background_color,
+                render_context.materials,
+                self.max_depth,
+                camera,
+                seed,
+            ),
+            RenderMode::AmbientOcclusion => ambient_occlusion_shade(
+                hit,
+                render_context.world,
+                render_context.camera_pos,
+                render_context.background_color,
+                self.ao_samples,
+                self.ao_radius,
+                seed,
+            ),
+            RenderMode::Shadows => shadow_shade(
+                hit,
+                render_context.world,
+                render_context.lights,
+                render_context.camera_pos,
+                render_context.background_color,
+                seed,
+            ),
+            RenderMode::Lighting => lighting_shade(
+                hit,
+                render_context.world,
+                render_context.lights,
+                render_context.ambient,
+                render_context.camera_pos,
+                render_context.background_color,
+                seed,
+            ),
+            RenderMode::Preview => preview_shade(
+                hit,
+                render_context.world,
+                render_context.lights,
+                render_context.ambient,
+                render_context.camera_pos,
+                render_context.background_color,
+                render_context.materials,
+                seed,
+            ),
+        }
+    }
+
+    /// Split the image into `tile_size`×`tile_size` tiles, in row-major order
+    fn tiles(&self) -> Vec<Tile> {
+        let tile_size = self.tile_size.max(1);
+        let mut tiles = Vec::new();
+        for y0 in (0..self.height).step_by(tile_size as usize) {
+            for x0 in (0..self.width).step_by(tile_size as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    x1: (x0 + tile_size).min(self.width),
+                    y1: (y0 + tile_size).min(self.height),
+                });
+            }
+        }
+        tiles
+    }
+
+    /// Render every tile in parallel and concatenate the per-tile results
+    ///
+    /// Rayon hands out whole tiles, so threads that finish cheap tiles early keep
+    /// stealing work from the expensive ones. Progress is reported per tile, and the
+    /// cancellation token is checked before each tile starts. Tiles fail as in
+    /// `render_tiles_checked`, with results that `check` rejects.
+    #[cfg(feature = "image")]
+    fn render_tiles<T, F, C>(&self, render_tile: F, check: C) -> Result<Vec<T>, String>
+    where
+        T: Send,
+        F: Fn(&Tile) -> Vec<T> + Sync,
+        C: Fn(&T) -> Result<(), String> + Sync,
+    {
+        self.render_tiles_checked(true, render_tile, check)
+    }
+
+    /// `render_tiles`, optionally without progress reports (for auxiliary passes), and
+    /// failing tiles only when they panic
+    fn render_tiles_reporting<T, F>(&self, report: bool, render_tile: F) -> Result<Vec<T>, String>
+    where
+        T: Send,
+        F: Fn(&Tile) -> Vec<T> + Sync,
+    {
+        self.render_tiles_checked(report, render_tile, |_| Ok(()))
+    }
+
+    /// `render_tiles`, optionally without progress reports
+    ///
+    /// A tile fails when it panics or `check` rejects one of its results, and doesn't
+    /// stop the others. Failed tiles are rendered again one at a time on this thread
+    /// once the rest are done, and recorded in `tile_failures`. A tile that fails again
+    /// keeps its second results, or leaves its pixels black if it panicked again.
+    fn render_tiles_checked<T, F, C>(
+        &self,
+        report: bool,
+        render_tile: F,
+        check: C,
+    ) -> Result<Vec<T>, String>
+    where
+        T: Send,
+        F: Fn(&Tile) -> Vec<T> + Sync,
+        C: Fn(&T) -> Result<(), String> + Sync,
+    {
+        let try_tile = |tile: &Tile| -> TileResults<T> {
+            match std::panic::catch_unwind(AssertUnwindSafe(|| render_tile(tile))) {
+                Ok(results) => match results.iter().find_map(|result| check(result).err()) {
+                    Some(error) => Err((error, results)),
+                    None => Ok(results),
+                },
+                Err(payload) => Err((panic_message(payload.as_ref()), Vec::new())),
+            }
+        };
+
+        let tiles = self.tiles();
+
+        // Progress tracking setup; the count is updated under the lock so reports arrive in order
+        let total_pixels = self.pixel_count();
+        let completed_pixels = Mutex::new(0);
+        let start_time = Instant::now();
+
+        #[cfg(feature = "parallel")]
+        let tile_iter = tiles.par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let tile_iter = tiles.iter();
+
+        let results: Vec<Option<TileResults<T>>> = tile_iter
+            .map(|tile| {
+                if self.is_cancelled() {
+                    return None;
+                }
+
+                let tile_results = try_tile(tile);
+
+                if report && self.progress.is_some() {
+                    if let Ok(mut completed) = completed_pixels.lock() {
+                        *completed += tile.pixel_count();
+                        self.report_progress(RenderPhase::Tracing, *completed, start_time.elapsed());
+                    }
+                }
+
+                Some(tile_results)
+            })
+            .collect();
+
+        let mut flattened = Vec::with_capacity(total_pixels);
+        for (tile, tile_results) in tiles.iter().zip(results) {
+            match tile_results {
+                Some(Ok(tile_results)) => flattened.extend(tile_results),
+                Some(Err((error, _))) => {
+                    if self.is_cancelled() {
+                        return Err("Render cancelled".to_string());
+                    }
+                    // Alone this time, in case the failure came from sharing the work
+                    let retried = try_tile(tile);
+                    if let Some(failures) = &self.tile_failures {
+                        failures.record(TileFailure {
+                            x0: tile.x0,
+                            y0: tile.y0,
+                            x1: tile.x1,
+                            y1: tile.y1,
+                            error,
+                            recovered: retried.is_ok(),
+                        });
+                    }
+                    flattened.extend(retried.unwrap_or_else(|(_, results)| results));
+                }
+                None => return Err("Render cancelled".to_string()),
+            }
+        }
+        Ok(flattened)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    fn render_standard(
+        &self,
+        render_context: &RenderContext,
+    ) -> Result<FrameData, String> {
+        let report = render_context.report_progress;
+        let render_tile = |tile: &Tile| {
+            let results: Vec<PixelRenderResult> = tile
+                .pixels()
+                .map(|(x, y)| {
+                    let (color, depth, normal, object) =
+                        self.render_standard_pixel(x, y, render_context);
+                    (x, y, color, depth, normal, object)
+                })
+                .collect();
+            if let Some(on_tile) = render_context.on_tile {
+                let colors: Vec<Color> = results.iter().map(|&(_, _, color, ..)| color).collect();
+                on_tile(tile, &colors);
+            }
+            results
+        };
+        let results: Vec<PixelRenderResult> =
+            self.render_tiles_checked(report, render_tile, |&(x, y, color, ..)| {
+                finite_color(x, y, &color)
+            })?;
+
+        Ok(self.frame_data(results))
+    }
+
+    /// Split traced pixels into their colors and, if outlines are drawn, the buffers of
+    /// depths and normals they are found from
+    fn frame_data(&self, results: Vec<PixelRenderResult>) -> FrameData {
+        if self.outline_config.is_none() {
+            let image_data = results
+                .into_iter()
+                .map(|(x, y, color, ..)| (x, y, color))
+                .collect();
+            return (image_data, None);
+        }
+
+        // Separate color data and outline data
+        let mut image_data = Vec::with_capacity(results.len());
+        let mut outline_buffers = OutlineBuffers::new(self.width, self.height);
+
+        for (x, y, color, depth, normal, object) in results {
+            image_data.push((x, y, color));
+
+            if let Some(depth) = depth {
+                outline_buffers.set_depth(x, y, depth);
+            }
+            if let Some(normal) = normal {
+                outline_buffers.set_normal(x, y, normal);
+            }
+            if let Some(object) = object {
+                outline_buffers.set_object(x, y, object);
+            }
+        }
+
+        (image_data, Some(outline_buffers))
+    }
+
+    /// Render one pixel in no-jitter or stochastic mode
+    ///
+    /// Returns the averaged color plus the closest depth and matching normal and object
+    /// among the samples, for outline detection.
+    fn render_standard_pixel(
+        &self,
+        x: u32,
+        y: u32,
+        render_context: &RenderContext,
+    ) -> SampleResult {
+        // Collect samples for this pixel
+        let mut total_color = Color::new(0.0, 0.0, 0.0);
+        let mut pixel_depth: Option<f64> = None;
+        let mut pixel_normal = None;
+        let mut pixel_object = None;
+
+        let mut rng = crate::SeededRng::seed_from_u64(self.pixel_seed(x, y));
+
+        for sample in 0..self.samples {
+            let (sample_color, sample_depth, sample_normal, sample_object) =
+                self.render_standard_sample(x, y, sample, &mut rng, render_context);
+
+            total_color += sample_color;
+
+            // For outline detection, we want the closest depth and corresponding normal
+            if let (Some(depth), Some(normal)) = (sample_depth, sample_normal) {
+                if pixel_depth.is_none_or(|closest| depth < closest) {
+                    pixel_depth = Some(depth);
+                    pixel_normal = Some(normal);
+                    pixel_object = sample_object;
+                }
+            }
+        }
+
+        // Average the samples
+        (
+            total_color / self.samples as f64,
+            pixel_depth,
+            pixel_normal,
+            pixel_object,
+        )
+    }
+
+    /// The global seed, offset by the frame in temporally accumulated sequences so each
+    /// frame draws samples of its own
+    fn base_seed(&self) -> u64 {
+        let frame = self.sequence_frame.load(Ordering::Relaxed);
+        self.seed.unwrap_or(0).wrapping_add(frame)
+    }
+
+    /// Deterministic per-pixel seed derived from pixel coordinates and the global seed
+    fn pixel_seed(&self, x: u32, y: u32) -> u64 {
+        self.base_seed()
+            .wrapping_mul(0x9E3779B97F4A7C15_u64)
+            .wrapping_add((x as u64).wrapping_mul(0x85EBCA6B))
+            .wrapping_add((y as u64).wrapping_mul(0xC2B2AE35))
+    }
+
+    /// Camera ray through (u, v) at a time within the shutter, in stratum `sample` of
+    /// `samples` equal parts of it, placed within the part by `seed`
+    ///
+    /// Time comes from its own generator, so renders where nothing moves draw exactly
+    /// the same random numbers as without motion blur.
+    fn camera_ray(
+        camera: &Camera,
+        (u, v): (f64, f64),
+        seed: u64,
+        sample: u32,
+        samples: u32,
+    ) -> Ray {
+        let ray = camera.get_ray(u, v);
+        if camera.shutter.is_none() {
+            return ray;
+        }
+        let offset: f64 = crate::SeededRng::seed_from_u64(!seed).gen();
+        let fraction = (sample as f64 + offset) / samples.max(1) as f64;
+        ray.with_time(camera.shutter_time(fraction))
+    }
+
+    /// First hit of the camera ray through (u, v), or None where the camera sees nothing
+    fn primary_hit(
+        render_context: &RenderContext,
+        ray: &Ray,
+        (u, v): (f64, f64),
+    ) -> Option<HitRecord> {
+        if !render_context.camera.in_image(u, v) {
+            return None;
+        }
+        render_context.world.hit(ray, 0.001, f64::INFINITY)
+    }
+
+    /// Trace sample number `sample` of a pixel in no-jitter or stochastic mode
+    ///
+    /// `rng` is the pixel's jitter generator; stochastic samples draw two values from
+    /// it each, so samples must be traced in order for the pattern to be reproducible.
+    fn render_standard_sample(
+        &self,
+        x: u32,
+        y: u32,
+        sample: u32,
+        rng: &mut crate::SeededRng,
+        render_context: &RenderContext,
+    ) -> SampleResult {
+        let camera = render_context.camera;
+
+        // Calculate base pixel coordinates
+        let pixel_u = x as f64 / (self.width - 1) as f64;
+        let pixel_v = (self.height - 1 - y) as f64 / (self.height - 1) as f64; // Flip Y coordinate
+
+        // Calculate pixel size in UV coordinates
+        let pixel_width = 1.0 / (self.width - 1) as f64;
+        let pixel_height = 1.0 / (self.height - 1) as f64;
+
+        let (sample_u, sample_v) = match self.anti_aliasing_mode {
+            AntiAliasingMode::NoJitter => {
+                // No jittering: sample at exact pixel center
+                (pixel_u, pixel_v)
+            }
+            AntiAliasingMode::Stochastic => {
+                if self.samples == 1 {
+                    // Single sample with random jitter within pixel bounds
+                    let jitter_u = rng.gen::<f64>() - 0.5; // [-0.5, 0.5]
+                    let jitter_v = rng.gen::<f64>() - 0.5; // [-0.5, 0.5]
+                    (
+                        pixel_u + jitter_u * pixel_width,
+                        pixel_v + jitter_v * pixel_height,
+                    )
+                } else {
+                    // Multiple samples: radially symmetric pattern with random phase
+                    let angle = 2.0 * std::f64::consts::PI * sample as f64 / self.samples as f64;
+                    let random_phase = rng.gen::<f64>() * 2.0 * std::f64::consts::PI;
+                    let rotated_angle = angle + random_phase;
+
+                    // Use a smaller radius to keep samples within pixel bounds
+                    let radius = 0.5 * rng.gen::<f64>(); // Random radius [0, 0.5]
+                    let jitter_u = radius * rotated_angle.cos();
+                    let jitter_v = radius * rotated_angle.sin();
+
+                    (
+                        pixel_u + jitter_u * pixel_width,
+                        pixel_v + jitter_v * pixel_height,
+                    )
+                }
+            }
+            AntiAliasingMode::Quincunx => unreachable!(), // Handled separately
+        };
+
+        // Create sample-specific seed for ray tracing consistency
+        let sample_seed = self
+            .pixel_seed(x, y)
+            .wrapping_add((sample as u64).wrapping_mul(0x1F845FED));
+
+        let uv = (sample_u, sample_v);
+        let ray = Self::camera_ray(camera, uv, sample_seed, sample, self.samples);
+
+        let sample_id = ((y * self.width + x) * self.samples + sample) as usize;
+        self.trace_sample(
+            &ray,
+            uv,
+            render_context,
+            Some(camera),
+            sample_seed,
+            sample_id,
+        )
+    }
+
+    /// Render in quincunx mode, where outlines are found from each pixel's center sample
+    fn render_quincunx(&self, render_context: &RenderContext) -> Result<FrameData, String> {
+        let camera = render_context.camera;
+
+        // Calculate pixel size in UV coordinates
+        let pixel_width = 1.0 / self.width as f64;
+        let pixel_height = 1.0 / self.height as f64;
+
+        // Trace the corner sample at a pixel grid intersection
+        let corner_sample = |corner_x: u32, corner_y: u32| -> Color {
+            // Calculate corner UV coordinates (corners are at pixel boundaries)
+            let corner_u = (corner_x as f64 * pixel_width).clamp(0.0, 1.0);
+            let corner_v = (1.0 - corner_y as f64 * pixel_height).clamp(0.0, 1.0); // Flip Y coordinate
+
+            // Create deterministic seed for corner based on corner coordinates
+            let corner_seed = self
+                .base_seed()
+                .wrapping_mul(0x9E3779B97F4A7C15_u64)
+                .wrapping_add(corner_x as u64)
+                .wrapping_add((corner_y as u64).wrapping_mul(0x85EBCA6B));
+            let ray = Self::camera_ray(camera, (corner_u, corner_v), corner_seed, 0, 1);
+
+            // Corner ids follow the per-pixel center ids
+            let corner_id =
+                (self.width * self.height + corner_y * (self.width + 1) + corner_x) as usize;
+            let (color, ..) = self.trace_sample(
+                &ray,
+                (corner_u, corner_v),
+                render_context,
+                Some(camera),
+                corner_seed,
+                corner_id,
+            );
+            color
+        };
+
+        // In strict mode every corner is traced once into a grid that tiles only read
+        let grid_wide = (self.width + 1) as usize;
+        let corner_grid = match self.strict_determinism {
+            true => Some(self.corner_grid(&corner_sample)?),
+            false => None,
+        };
+
+        let render_tile = |tile: &Tile| {
+            // Corners are shared between neighboring pixels, so trace each corner of the
+            // tile once. Corners on tile borders are traced by both neighboring tiles;
+            // their seeds are deterministic, so both see the same color.
+            let corners_wide = (tile.x1 - tile.x0 + 1) as usize;
+            let corners: Vec<Color> = match corner_grid {
+                Some(_) => Vec::new(),
+                None => (tile.y0..=tile.y1)
+                    .flat_map(|corner_y| {
+                        (tile.x0..=tile.x1).map(move |corner_x| (corner_x, corner_y))
+                    })
+                    .map(|(corner_x, corner_y)| corner_sample(corner_x, corner_y))
+                    .collect(),
+            };
+            let corner = |x: u32, y: u32| match &corner_grid {
+                Some(grid) => grid[y as usize * grid_wide + x as usize],
+                None => corners[(y - tile.y0) as usize * corners_wide + (x - tile.x0) as usize],
+            };
+
+            let results: Vec<PixelRenderResult> = tile
+                .pixels()
+                .map(|(x, y)| {
+                    // Calculate center sample coordinates
+                    let pixel_center_u = (x as f64 + 0.5) * pixel_width;
+                    let pixel_center_v = 1.0 - (y as f64 + 0.5) * pixel_height; // Flip Y coordinate
+
+                    // Create deterministic seed for center sample based on pixel coordinates
+                    let center_seed = self
+                        .base_seed()
+                        .wrapping_mul(0x9E3779B97F4A7C15_u64)
+                        .wrapping_add((x as u64).wrapping_mul(0x85EBCA6B))
+                        .wrapping_add((y as u64).wrapping_mul(0xC2B2AE35))
+                        .wrapping_add(0x12345678_u64); // Different constant for center vs corners
+
+                    // Center sample
+                    let center_uv = (pixel_center_u, pixel_center_v);
+                    let center_ray = Self::camera_ray(camera, center_uv, center_seed, 0, 1);
+
+                    let pixel_index = (y * self.width + x) as usize;
+                    let (center_color, depth, normal, object) = self.trace_sample(
+                        &center_ray,
+                        (pixel_center_u, pixel_center_v),
+                        render_context,
+                        Some(camera),
+                        center_seed,
+                        pixel_index,
+                    );
+
+                    // Average center + 4 corner samples (true quincunx pattern)
+                    let total_color = center_color
+                        + corner(x, y) // Top-left corner
+                        + corner(x + 1, y) // Top-right corner
+                        + corner(x, y + 1) // Bottom-left corner
+                        + corner(x + 1, y + 1); // Bottom-right corner
+                    (x, y, total_color / 5.0, depth, normal, object)
+                })
+                .collect();
+            if let Some(on_tile) = render_context.on_tile {
+                let colors: Vec<Color> = results.iter().map(|&(_, _, color, ..)| color).collect();
+                on_tile(tile, &colors);
+            }
+            results
+        };
+        let results = self.render_tiles_checked(
+            render_context.report_progress,
+            render_tile,
+            |&(x, y, color, ..)| finite_color(x, y, &color),
+        )?;
+        Ok(self.frame_data(results))
+    }
+
+    /// Trace every quincunx corner once, in parallel over the pixel tiles
+    ///
+    /// Each tile traces the corners at its top-left pixel corners, plus the last
+    /// column and row of the grid when it touches the right or bottom edge. Returns the
+    /// `(width + 1) * (height + 1)` corner colors in row-major order.
+    fn corner_grid<S>(&self, corner_sample: &S) -> Result<Vec<Color>, String>
+    where
+        S: Fn(u32, u32) -> Color + Sync,
+    {
+        let corners = self.render_tiles_reporting(false, |tile: &Tile| {
+            let x_end = tile.x1 + u32::from(tile.x1 == self.width);
+            let y_end = tile.y1 + u32::from(tile.y1 == self.height);
+            (tile.y0..y_end)
+                .flat_map(|y| (tile.x0..x_end).map(move |x| (x, y)))
+                .map(|(x, y)| (x, y, corner_sample(x, y)))
+                .collect()
+        })?;
+        let grid_wide = (self.width + 1) as usize;
+        let mut grid = vec![Color::zeros(); grid_wide * (self.height + 1) as usize];
+        for (x, y, color) in corners {
+            grid[y as usize * grid_wide + x as usize] = color;
+        }
+        Ok(grid)
+    }
+
+    /// Pack per-pixel colors into row-major RGB bytes
+    fn rgb_from_data(&self, image_data: Vec<(u32, u32, Color)>) -> Vec<u8> {
+        let mut pixels = vec![0; self.pixel_count() * 3];
+
+        for (x, y, color) in image_data {
+            // Tone map to 0-1, then convert to RGB values (0-255)
+            let color = self.tone_mapping.apply(color);
+            let index = (y * self.width + x) as usize * 3;
+            pixels[index] = (color.x * 255.0) as u8;
+            pixels[index + 1] = (color.y * 255.0) as u8;
+            pixels[index + 2] = (color.z * 255.0) as u8;
+        }
+
+        pixels
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn image_from_rgb(&self, pixels: Vec<u8>) -> RgbImage {
+        RgbImage::from_raw(self.width, self.height, pixels)
+            .expect("pixel buffer matches the image dimensions")
+    }
+
+    /// Render and save to `output_path`, choosing the format from its extension
+    ///
+    /// `.exr` (OpenEXR) and `.hdr` (Radiance) files get the linear float output of
+    /// `render_hdr`; every other format gets the 8-bit image from `render`. Same as
+    /// `render_to_sink` with a `FileSink`.
+    #[cfg(feature = "image")]
+    pub fn render_to_file(
+        &self,
+        scene: &Scene,
+        output_path: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.render_to_sink(scene, &mut FileSink::new(output_path))
+    }
+
+    /// Render and hand the results to `sink`
+    ///
+    /// The sink gets each tile as it is traced, then the image as `render` or, if it
+    /// asks for float output, `render_hdr` would return it, followed by every AOV and
+    /// light group pass this renderer is set up for.
+    #[cfg(feature = "image")]
+    pub fn render_to_sink(
+        &self,
+        scene: &Scene,
+        sink: &mut dyn RenderSink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let float_output = sink.float_output();
+        let shared_sink = Mutex::new(sink);
+        let on_tile = |tile: &Tile, colors: &[Color]| {
+            let pixels = colors
+                .iter()
+                .flat_map(|&color| {
+                    let color = self.tone_mapping.apply(color);
+                    [color.x, color.y, color.z].map(|channel| (channel * 255.0) as u8)
+                })
+                .collect();
+            let tile = TilePixels {
+                x0: tile.x0,
+                y0: tile.y0,
+                x1: tile.x1,
+                y1: tile.y1,
+                pixels,
+            };
+            shared_sink
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .tile(&tile);
+        };
+        let frame = self.render_frame(scene, None, Some(&on_tile))?;
+        let sink = shared_sink.into_inner().unwrap_or_else(|e| e.into_inner());
+
+        let image = match float_output {
+            true => RenderedImage::Rgb32F(self.float_image(frame)),
+            false => RenderedImage::Rgb8(self.image_from_rgb(self.rgb_from_data(frame))),
+        };
+        // Every pass is framed alike; margins show the background where the image would
+        let window = match self.framing.is_identity() {
+            true => None,
+            false => Some(self.framing_window(scene)?),
+        };
+        let background = Self::background_color(scene)?;
+        let image = self.frame_image(window.as_ref(), image, background);
+        {
+            let _span = profile::span("image encode");
+            sink.image(SinkPass::Image, image)?;
+        }
+
+        if !self.aovs.is_empty() {
+            for buffer in self.render_aovs(scene, &self.aovs)? {
+                let image = match float_output {
+                    true => RenderedImage::Rgb32F(buffer.to_rgb32f()),
+                    false => {
+                        RenderedImage::Rgb8(buffer.to_rgb8_with(self.aov_false_color.as_ref()))
+                    }
+                };
+                let image = self.frame_image(window.as_ref(), image, Color::zeros());
+                let _span = profile::span("image encode");
+                sink.image(SinkPass::Aov(buffer.aov), image)?;
+            }
+        }
+
+        if self.light_groups {
+            for pass in self.render_light_groups(scene)? {
+                // Only the ambient pass holds the background
+                let fill = match pass.name == light_groups::AMBIENT_PASS {
+                    true => background,
+                    false => Color::zeros(),
+                };
+                let image =
+                    self.frame_image(window.as_ref(), RenderedImage::Rgb32F(pass.image), fill);
+                let image = match float_output {
+                    true => image,
+                    false => RenderedImage::Rgb8(image.to_rgb8()),
+                };
+                let _span = profile::span("image encode");
+                sink.image(SinkPass::LightGroup(pass.name), image)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Render both eyes of `stereo` and save them to `output_path`: one side-by-side
+    /// image, or one per eye (`render.left.png` and `render.right.png`), each with its
+    /// own AOV and light group passes
+    #[cfg(feature = "image")]
+    pub fn render_stereo_to_file(
+        &self,
+        scene: &Scene,
+        stereo: &Stereo,
+        output_path: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match stereo.layout {
+            StereoLayout::SideBySide => {
+                self.render_stereo_to_sink(scene, stereo, &mut FileSink::new(output_path))
+            }
+            StereoLayout::Separate => {
+                for eye in Eye::BOTH {
+                    let path = eye.output_path(output_path);
+                    let mut sink = FileSink::new(path.to_string_lossy());
+                    self.render_to_sink(&stereo.eye_scene(scene, eye)?, &mut sink)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Render both eyes of `stereo` and deliver every pass to `sink` side by side,
+    /// twice this renderer's width with the left eye on the left
+    ///
+    /// Each eye is rendered in full before anything reaches `sink`, so it gets no tiles.
+    #[cfg(feature = "image")]
+    pub fn render_stereo_to_sink(
+        &self,
+        scene: &Scene,
+        stereo: &Stereo,
+        sink: &mut dyn RenderSink,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let float_output = sink.float_output();
+        let mut eyes = Eye::BOTH.map(|_| MemorySink {
+            float_output,
+            images: Vec::new(),
+        });
+        for (eye, memory) in Eye::BOTH.into_iter().zip(&mut eyes) {
+            self.render_to_sink(&stereo.eye_scene(scene, eye)?, memory)?;
+        }
+
+        let [left, right] = eyes;
+        for ((pass, left), (_, right)) in left.images.into_iter().zip(right.images) {
+            let image = match (left, right) {
+                (RenderedImage::Rgb8(left), RenderedImage::Rgb8(right)) => {
+                    RenderedImage::Rgb8(side_by_side(&left, &right)?)
+                }
+                (RenderedImage::Rgb32F(left), RenderedImage::Rgb32F(right)) => {
+                    RenderedImage::Rgb32F(side_by_side(&left, &right)?)
+                }
+                _ => unreachable!("both eyes are rendered to the same kind of sink"),
+            };
+            let _span = profile::span("image encode");
+            sink.image(pass, image)?;
+        }
+        Ok(())
+    }
+
+    /// Render each named camera of `scene` to its own file next to `output_path`, which
+    /// gets the camera's name before the extension (`render.front.png`), along with its
+    /// AOV and light group passes
+    ///
+    /// See `render_cameras_to_sinks`.
+    #[cfg(feature = "image")]
+    pub fn render_cameras_to_file(
+        &self,
+        scene: &Scene,
+        names: &[String],
+        output_path: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut sinks: Vec<FileSink> = names
+            .iter()
+            .map(|name| {
+                let path = Self::camera_output_path(output_path, name);
+                FileSink::new(path.to_string_lossy())
+            })
+            .collect();
+        let mut views: Vec<(&str, &mut dyn RenderSink)> = names
+            .iter()
+            .zip(&mut sinks)
+            .map(|(name, sink)| (name.as_str(), sink as &mut dyn RenderSink))
+            .collect();
+        self.render_cameras_to_sinks(scene, &mut views)
+    }
+
+    /// Path `render_cameras_to_file` saves the camera called `name` to
+    #[cfg(feature = "image")]
+    pub fn camera_output_path(output_path: &str, name: &str) -> std::path::PathBuf {
+        crate::aov::pass_output_path(output_path, name)
+    }
+
+    /// Render `scene` through each named camera of `views` (keys of `scene.cameras`) and
+    /// hand the results to that view's sink, as `render_to_sink` would
+    ///
+    /// The world, mesh k-d trees and caustic photons are built once and shared by every
+    /// view; each view is rendered at this renderer's size, its viewport widened or
+    /// heightened to match.
+    #[cfg(feature = "image")]
+    pub fn render_cameras_to_sinks(
+        &self,
+        scene: &Scene,
+        views: &mut [(&str, &mut dyn RenderSink)],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Unknown names fail before anything is built
+        let scenes = views
+            .iter()
+            .map(|(name, _)| scene.with_camera(name))
+            .collect::<Result<Vec<Scene>, String>>()?;
+        self.validate()?;
+        self.limits.check_scene(scene)?;
+        let cameras = || scenes.iter().map(Scene::fitted_camera).collect();
+        let shared = Arc::new(self.prepare_world(scene, cameras)?);
+
+        self.in_batch(shared, None, || {
+            scenes
+                .iter()
+                .zip(views.iter_mut())
+                .try_for_each(|(view, (_, sink))| self.render_to_sink(view, &mut **sink))
+        })
+    }
+
+    /// Where `framing` crops and pads this renderer's images of `scene`, found from which
+    /// pixel centers hit an object
+    #[cfg(feature = "image")]
+    fn framing_window(
+        &self,
+        scene: &Scene,
+    ) -> Result<crate::framing::FramingWindow, Box<dyn std::error::Error>> {
+        let covered = match self.framing.crop_to_content {
+            true => {
+                let alpha = self.render_aovs(scene, &[Aov::Alpha])?.remove(0);
+                alpha.pixels.iter().map(|alpha| alpha[0] > 0.0).collect()
+            }
+            false => Vec::new(),
+        };
+        Ok(self.framing.window(self.width, self.height, &covered))
+    }
+
+    /// Crop and pad `image` to `window`, filling the margin with the linear color `fill`
+    /// (quantized like the rest of the image for 8-bit output)
+    #[cfg(feature = "image")]
+    fn frame_image(
+        &self,
+        window: Option<&crate::framing::FramingWindow>,
+        image: RenderedImage,
+        fill: Color,
+    ) -> RenderedImage {
+        let Some(window) = window else {
+            return image;
+        };
+        match image {
+            RenderedImage::Rgb8(image) => {
+                let fill = self.tone_mapping.apply(fill);
+                let fill = [fill.x, fill.y, fill.z].map(|channel| (channel * 255.0) as u8);
+                RenderedImage::Rgb8(window.apply(&image, image::Rgb(fill)))
+            }
+            RenderedImage::Rgb32F(image) => {
+                let fill = [fill.x, fill.y, fill.z].map(|channel| channel as f32);
+                RenderedImage::Rgb32F(window.apply(&image, image::Rgb(fill)))
+            }
+        }
+    }
+
+    /// Render every frame of the scene's `animation` with `render_frames`, at the
+    /// animation's `fps` (24 unless set) if saving a GIF or APNG
+    #[cfg(feature = "image")]
+    pub fn render_sequence(
+        &self,
+        scene: &Scene,
+        output_path: &str,
+        on_frame: impl FnMut(u32, &str),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let animation = scene.animation.as_ref().ok_or("Scene has no animation")?;
+        let fps = animation.fps.unwrap_or(crate::animation::DEFAULT_FPS);
+        let scene_at = |frame| scene.frame(frame);
+        self.render_frames(animation.frames, scene_at, output_path, fps, on_frame)
+    }
+
+    /// Render `frames` scenes, one per frame, and save them to `output_path`
+    ///
+    /// The `sequence_format`, or else `SequenceFormat::from_path`, decides how. Numbered
+    /// frames each get a file from `render_to_file`, numbered before the extension (see
+    /// `animation::frame_path`). GIF, APNG and ffmpeg output is one file at `fps`
+    /// frames per second, which can't carry AOVs or light group passes. `on_frame` is
+    /// called with each frame number and the file it went to once it is saved.
+    ///
+    /// With `temporal` set, each frame draws fresh samples and is blended with the
+    /// frames before it.
+    #[cfg(feature = "image")]
+    pub fn render_frames(
+        &self,
+        frames: u32,
+        scene_at: impl FnMut(u32) -> Result<Scene, String>,
+        output_path: &str,
+        fps: f64,
+        on_frame: impl FnMut(u32, &str),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let history = || {
+            self.temporal_history
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+        };
+        if self.temporal.is_some() {
+            *history() = Some(TemporalHistory::default());
+        }
+        let result = self.save_frames(frames, scene_at, output_path, fps, on_frame);
+        *history() = None;
+        self.sequence_frame.store(0, Ordering::Relaxed);
+        result
+    }
+
+    #[cfg(feature = "image")]
+    fn save_frames(
+        &self,
+        frames: u32,
+        mut scene_at: impl FnMut(u32) -> Result<Scene, String>,
+        output_path: &str,
+        fps: f64,
+        mut on_frame: impl FnMut(u32, &str),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let format = match &self.sequence_format {
+            Some(format) => format.clone(),
+            None => SequenceFormat::from_path(output_path),
+        };
+        if format == SequenceFormat::Frames {
+            for frame in 0..frames {
+                self.start_frame(frame);
+                let path = crate::animation::frame_path(output_path, frame);
+                self.render_to_file(&scene_at(frame)?, &path)?;
+                on_frame(frame, &path);
+            }
+            return Ok(());
+        }
+
+        if !self.aovs.is_empty() || self.light_groups {
+            return Err("AOVs and light groups can't be saved in a single animation file".into());
+        }
+        let size = (self.width, self.height);
+        let mut writer = AnimationWriter::create(&format, output_path, frames, size, fps)?;
+        for frame in 0..frames {
+            self.start_frame(frame);
+            let image = self.render(&scene_at(frame)?)?;
+            let _span = profile::span("image encode");
+            writer.write(&image)?;
+            on_frame(frame, output_path);
+        }
+        writer.finish()?;
+        println!("Animation saved to: {}", output_path);
+        Ok(())
+    }
+
+    /// Give frame `frame` of an accumulated sequence samples of its own
+    #[cfg(feature = "image")]
+    fn start_frame(&self, frame: u32) {
+        if self.temporal.is_some() {
+            self.sequence_frame.store(frame as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A tile's results, or what went wrong and whatever results there were
+type TileResults<T> = Result<Vec<T>, (String, Vec<T>)>;
+
+/// Error for a pixel whose color came out NaN or infinite, which would otherwise
+/// quietly turn black
+fn finite_color(x: u32, y: u32, color: &Color) -> Result<(), String> {
+    if color.iter().all(|channel| channel.is_finite()) {
+        return Ok(());
+    }
+    Err(format!(
+        "non-finite color ({}, {}, {}) at pixel ({}, {})",
+        color.x, color.y, color.z, x, y
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{Light, Material, MeshLod, Object, Scene};
+
+    #[test]
+    fn test_renderer_creation() {
+        let renderer = Renderer::new(800, 600);
+        assert_eq!(renderer.width, 800);
+        assert_eq!(renderer.height, 600);
+        assert_eq!(renderer.thread_count, None);
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
+        assert_eq!(renderer.samples, 1); // Default for quincunx with shared samples
+
+        // Test with specific thread count
+        let renderer_threaded = Renderer::new_with_threads(800, 600, 4);
+        assert_eq!(renderer_threaded.thread_count, Some(4));
+        assert_eq!(
+            renderer_threaded.anti_aliasing_mode,
+            AntiAliasingMode::Quincunx
+        );
+    }
+
+    #[test]
+    fn test_simple_render() {
+        let mut scene = Scene::default();
+
+        // Add a simple sphere
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // Add a light
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        let renderer = Renderer::new(100, 100);
+        let result = renderer.render(&scene);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_stochastic_sampling() {
+        let mut scene = Scene::default();
+
+        // Add a simple sphere
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // Add a light
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // Test with multiple samples
+        let mut renderer = Renderer::new(50, 50);
+        renderer.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        renderer.samples = 4;
+        let result = renderer.render(&scene);
+        assert!(result.is_ok());
+
+        // Test with single sample
+        renderer.samples = 1;
+        let result = renderer.render(&scene);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_no_jitter_sampling() {
+        let mut scene = Scene::default();
+
+        // Add a simple sphere
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // Add a light
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // Test no-jitter mode with single sample
+        let mut renderer = Renderer::new(50, 50);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        renderer.samples = 1;
+        let result = renderer.render(&scene);
+        assert!(result.is_ok());
+
+        // Test no-jitter mode with multiple samples (should still work)
+        renderer.samples = 4;
+        let result = renderer.render(&scene);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_quincunx_sampling() {
+        let mut scene = Scene::default();
+
+        // Add a simple sphere
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // Add a light
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // Test quincunx mode with default samples
+        let renderer = Renderer::new(50, 50);
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
+        assert_eq!(renderer.samples, 1);
+        let result = renderer.render(&scene);
+        assert!(result.is_ok());
+
+        // Test quincunx mode with custom samples
+        let mut renderer2 = Renderer::new(50, 50);
+        renderer2.samples = 4;
+        let result = renderer2.render(&scene);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_shadow_samples() {
+        let mut scene = Scene::default();
+        scene.camera.position = [0.0, -6.0, 4.0];
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 1.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.objects.push(Object::Plane {
+            name: None,
+            point: [0.0, 0.0, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            width: None,
+            height: None,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.lights.push(Light {
+            position: [1.0, -1.0, 5.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: Some(2.0),
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // A light's own count wins over the renderer's
+        let coarse = Renderer::new(32, 32).with_shadow_samples(4);
+        let fine = Renderer::new(32, 32).with_shadow_samples(64);
+        let coarse_pixels = coarse.render_rgb(&scene).unwrap();
+        assert_ne!(coarse_pixels, fine.render_rgb(&scene).unwrap());
+        scene.lights[0].shadow_samples = Some(64);
+        assert_eq!(
+            coarse.render_rgb(&scene).unwrap(),
+            fine.render_rgb(&scene).unwrap()
+        );
+        assert_ne!(coarse.settings_fingerprint(), fine.settings_fingerprint());
+
+        scene.lights[0].shadow_samples = Some(0);
+        assert!(fine.render_rgb(&scene).is_err());
+        scene.lights[0].shadow_samples = None;
+        assert!(Renderer::new(32, 32)
+            .with_shadow_samples(0)
+            .render_rgb(&scene)
+            .is_err());
+    }
+
+    #[test]
+    fn test_deterministic_rendering() {
+        let mut scene = Scene::default();
+
+        // Add a simple sphere
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // Add a diffuse light for area light sampling
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: Some(0.5), // Area light to trigger stochastic sampling
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // Create renderer with stochastic anti-aliasing and multiple samples
+        let mut renderer = Renderer::new(50, 50);
+        renderer.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        renderer.samples = 4;
+        renderer.seed = Some(42); // Fixed seed
+
+        // Render the same scene multiple times
+        let result1 = renderer.render(&scene).expect("First render failed");
+        let result2 = renderer.render(&scene).expect("Second render failed");
+
+        // Extract pixel data for comparison
+        let pixels1: Vec<_> = result1.pixels().collect();
+        let pixels2: Vec<_> = result2.pixels().collect();
+
+        // Results should be byte-for-byte identical
+        assert_eq!(pixels1.len(), pixels2.len());
+        for (i, (&pixel1, &pixel2)) in pixels1.iter().zip(pixels2.iter()).enumerate() {
+            assert_eq!(
+                pixel1, pixel2,
+                "Pixel {} differs between renders: {:?} vs {:?}",
+                i, pixel1, pixel2
+            );
+        }
+    }
+
+    #[test]
+    fn test_deterministic_rendering_with_threading() {
+        let mut scene = Scene::default();
+
+        // Add a simple sphere
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // Add a diffuse light for area light sampling
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: Some(0.5), // Area light to trigger stochastic sampling
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // Test with different thread counts to ensure thread scheduling doesn't affect results
+        let mut renderer1 = Renderer::new_with_threads(50, 50, 1);
+        renderer1.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        renderer1.samples = 4;
+        renderer1.seed = Some(42);
+
+        let mut renderer4 = Renderer::new_with_threads(50, 50, 4);
+        renderer4.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        renderer4.samples = 4;
+        renderer4.seed = Some(42);
+
+        // Render with different thread counts
+        let result1 = renderer1
+            .render(&scene)
+            .expect("Single-threaded render failed");
+        let result4 = renderer4
+            .render(&scene)
+            .expect("Multi-threaded render failed");
+
+        // Extract pixel data for comparison
+        let pixels1: Vec<_> = result1.pixels().collect();
+        let pixels4: Vec<_> = result4.pixels().collect();
+
+        // Results should be identical regardless of thread count
+        assert_eq!(pixels1.len(), pixels4.len());
+        for (i, (&pixel1, &pixel4)) in pixels1.iter().zip(pixels4.iter()).enumerate() {
+            assert_eq!(
+                pixel1, pixel4,
+                "Pixel {} differs between thread counts: {:?} vs {:?}",
+                i, pixel1, pixel4
+            );
+        }
+    }
+
+    #[test]
+    fn test_quincunx_deterministic() {
+        let mut scene = Scene::default();
+
+        // Add a simple sphere
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        // Add a diffuse light
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: Some(0.5), // Area light to trigger stochastic sampling
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // Test quincunx mode (which should also be deterministic)
+        let mut renderer = Renderer::new(50, 50);
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
+        renderer.seed = Some(123);
+
+        let result1 = renderer
+            .render(&scene)
+            .expect("First quincunx render failed");
+        let result2 = renderer
+            .render(&scene)
+            .expect("Second quincunx render failed");
+
+        // Extract pixel data for comparison
+        let pixels1: Vec<_> = result1.pixels().collect();
+        let pixels2: Vec<_> = result2.pixels().collect();
+
+        // Results should be identical
+        assert_eq!(pixels1.len(), pixels2.len());
+        for (i, (&pixel1, &pixel2)) in pixels1.iter().zip(pixels2.iter()).enumerate() {
+            assert_eq!(
+                pixel1, pixel2,
+                "Quincunx pixel {} differs between renders: {:?} vs {:?}",
+                i, pixel1, pixel2
+            );
+        }
+    }
+
+    #[test]
+    fn test_quincunx_identical_across_threads_and_tiles() {
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.lights.push(Light {
+            position: [2.0, 2.0, 2.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: Some(0.5),
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // Odd sizes leave partial tiles on the right and bottom edges
+        let render = |threads: usize, tile_size: u32, strict: bool| {
+            let mut renderer = Renderer::new_with_threads(37, 29, threads);
+            renderer.seed = Some(7);
+            renderer.tile_size = tile_size;
+            renderer.strict_determinism = strict;
+            renderer.render_hdr(&scene).expect("Render failed").into_raw()
+        };
+        let expected = render(1, 32, false);
+        for (threads, tile_size) in [(1, 5), (4, 7), (8, 32), (3, 64)] {
+            for strict in [false, true] {
+                let pixels = render(threads, tile_size, strict);
+                assert!(
+                    pixels == expected,
+                    "{} threads, {}px tiles, strict={} changed the image",
+                    threads,
+                    tile_size,
+                    strict
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_strict_determinism_traces_each_corner_once() {
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        let tests = |tile_size: u32, strict: bool| {
+            let stats = ObjectStats::new();
+            let mut renderer = Renderer::new_with_threads(20, 20, 4)
+                .with_object_stats(stats.clone());
+            renderer.tile_size = tile_size;
+            renderer.strict_determinism = strict;
+            renderer.render_rgb(&scene).expect("Render failed");
+            stats.report(&scene)[0].tests
+        };
+
+        // Tile borders trace their shared corners twice unless the grid is precomputed
+        assert_ne!(tests(4, false), tests(10, false));
+        assert_eq!(tests(4, true), tests(10, true));
+        assert!(tests(4, true) < tests(4, false));
+    }
+
+    #[test]
+    fn test_ambient_occlusion_mode() {
+        let mut scene = Scene::default();
+
+        // Sphere resting on a floor plane so the contact region is occluded
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 1.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.objects.push(Object::Plane {
+            name: None,
+            point: [0.0, 0.0, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            width: None,
+            height: None,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        let renderer = Renderer::new(40, 40).with_ambient_occlusion(8, 1.0);
+        assert_eq!(renderer.render_mode, RenderMode::AmbientOcclusion);
+        let image = renderer.render(&scene).expect("AO render failed");
+
+        // AO output is grayscale
+        for pixel in image.pixels() {
+            assert_eq!(pixel[0], pixel[1]);
+            assert_eq!(pixel[1], pixel[2]);
+        }
+
+        // Invalid AO settings are rejected
+        let invalid = Renderer::new(10, 10).with_ambient_occlusion(0, 1.0);
+        assert!(invalid.render(&scene).is_err());
+    }
+
+    #[test]
+    fn test_shadow_and_lighting_modes() {
+        let red = Material {
+            color: "#FF0000".to_string(),
+            ..Material::default()
+        };
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 1.0],
+            radius: 1.0,
+            material: red.clone(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.objects.push(Object::Plane {
+            name: None,
+            point: [0.0, 0.0, 0.0],
+            normal: [0.0, 0.0, 1.0],
+            width: None,
+            height: None,
+            material: red,
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.lights.push(crate::scene::Light {
+            position: [0.0, 0.0, 10.0],
+            color: "#FFFFFF".to_string(),
+            intensity: 1.0,
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
+        });
+
+        // A point light leaves each pixel fully lit or fully shadowed
+        let mut renderer = Renderer::new(40, 40);
+        renderer.render_mode = RenderMode::Shadows;
+        let image = renderer.render(&scene).expect("Shadow render failed");
+        assert!(image.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+        assert!(image.pixels().any(|p| p[0] == 0));
+        assert!(image.pixels().any(|p| p[0] == 255));
+
+        // Red materials light up grey, as if they were matte white
+        renderer.render_mode = RenderMode::Lighting;
+        let image = renderer.render(&scene).expect("Lighting render failed");
+        assert!(image.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+        assert!(image.pixels().any(|p| p[0] > 128));
+
+        assert_eq!("shadows".parse::<RenderMode>(), Ok(RenderMode::Shadows));
+        assert!("wireframe".parse::<RenderMode>().is_err());
+    }
+
+    #[test]
+    fn test_preview_mode() {
+        let json = r##"{
+            "camera": {"kind": "ortho", "position": [6, -8, 6], "target": [0, 0, 0.5], "up": [0, 0, 1],
+                "width": 6, "height": 6},
+            "objects": [
+                {"kind": "sphere", "center": [0, 0, 1], "radius": 1,
+                    "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.5, "specular": 0.3,
+                        "shininess": 20, "reflectivity": 0.8}},
+                {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1],
+                    "material": {"color": "#4080C0", "ambient": 0.2, "diffuse": 0.8, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [{"position": [3, -2, 8], "color": "#FFFFFF", "intensity": 1, "diameter": 2}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#FFFFFF"}
+        }"##;
+        let scene = Scene::from_json_str(json).unwrap();
+        let mut preview = Renderer::new(32, 32);
+        preview.samples = 4;
+        let preview = preview.with_preview();
+        assert_eq!(preview.primary_sample_count(), 32 * 32);
+
+        // The same as a single-sample render without the reflection and with a point light
+        let mut plain = scene.clone();
+        if let Object::Sphere { material, .. } = &mut plain.objects[0] {
+            material.reflectivity = None;
+        }
+        plain.lights[0].diameter = None;
+        let mut reference = Renderer::new(32, 32);
+        reference.samples = 1;
+        reference.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        assert_eq!(
+            preview.render(&scene).unwrap(),
+            reference.render(&plain).unwrap()
+        );
+
+        // Outlines are still drawn
+        let outlined = preview.with_outline_detection(OutlineConfig::default());
+        let image = outlined.render(&scene).unwrap();
+        assert!(image.pixels().any(|pixel| pixel.0 == [0, 0, 0]));
+        assert_eq!("preview".parse::<RenderMode>(), Ok(RenderMode::Preview));
+    }
+
+    #[test]
+    fn test_render_prepared() {
+        let json = r##"{
+            "camera": {"kind": "perspective", "position": [0, -8, 3], "target": [0, 0, 1], "up": [0, 0, 1], "fov": 40,
+                "width": 6, "height": 6},
+            "cameras": {"top": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 6, "height": 6}},
+            "objects": [
+                {"kind": "sphere", "center": [0, 0, 1], "radius": 1,
+                    "material": {"color": "#C04040", "ambient": 0.1, "diffuse": 0.7, "specular": 0.3, "shininess": 20}},
+                {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1],
+                    "material": {"color": "#FFFFFF", "ambient": 0.2, "diffuse": 0.8, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [{"position": [3, -2, 8], "color": "#FFFFFF", "intensity": 1}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+        }"##;
+        let scene = Scene::from_json_str(json).unwrap();
+        let renderer = Renderer::new(24, 24);
+        let prepared = renderer.prepare(scene.clone()).unwrap();
+
+        // The same images as rendering the scene with its camera changed
+        let camera = CameraOverride::default();
+        assert_eq!(
+            renderer.render_prepared(&prepared, &camera).unwrap(),
+            renderer.render(&scene).unwrap()
+        );
+        let camera = CameraOverride::look_at([5.0, -5.0, 4.0], [0.0, 0.0, 0.5]);
+        let mut moved = scene.clone();
+        moved.camera.position = [5.0, -5.0, 4.0];
+        moved.camera.target = [0.0, 0.0, 0.5];
+        let image = renderer.render_prepared(&prepared, &camera).unwrap();
+        assert_eq!(image, renderer.render(&moved).unwrap());
+        assert_ne!(image, renderer.render(&scene).unwrap());
+        let camera = CameraOverride {
+            name: Some("top".to_string()),
+            ..CameraOverride::default()
+        };
+        assert_eq!(
+            renderer.render_prepared(&prepared, &camera).unwrap(),
+            renderer.render(&scene.with_camera("top").unwrap()).unwrap()
+        );
+
+        // Unknown cameras and renderers that would build a different world are errors
+        let camera = CameraOverride {
+            name: Some("side".to_string()),
+            ..CameraOverride::default()
+        };
+        assert!(renderer.render_prepared(&prepared, &camera).is_err());
+        let mut clay = Renderer::new(24, 24);
+        clay.render_mode = RenderMode::AmbientOcclusion;
+        assert!(clay
+            .render_prepared(&prepared, &CameraOverride::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_edit_prepared() {
+        let json = r##"{
+            "camera": {"kind": "perspective", "position": [0, -8, 3], "target": [0, 0, 1], "up": [0, 0, 1], "fov": 40,
+                "width": 6, "height": 6},
+            "objects": [
+                {"kind": "sphere", "name": "ball", "center": [0, 0, 1], "radius": 1,
+                    "material": {"color": "#C04040", "ambient": 0.1, "diffuse": 0.7, "specular": 0.3, "shininess": 20}},
+                {"kind": "quad", "name": "panel", "vertices": [[-2, 1, 2], [-1, 1, 2], [-1, 1, 3], [-2, 1, 3]],
+                    "material": {"color": "#FFFFFF", "ambient": 0, "diffuse": 1, "specular": 0, "shininess": 1,
+                        "emission": {"color": "#FF8000"}}},
+                {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1],
+                    "material": {"color": "#FFFFFF", "ambient": 0.2, "diffuse": 0.8, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [{"position": [3, -2, 8], "color": "#FFFFFF", "intensity": 1}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+        }"##;
+        let mut scene = Scene::from_json_str(json).unwrap();
+        let renderer = Renderer::new(24, 24);
+        let mut prepared = renderer.prepare(scene.clone()).unwrap();
+        let camera = CameraOverride::default();
+
+        // Each edit renders the same image as building the edited scene from scratch
+        let transform = Some(vec!["translate(1, 0, 0.5)".to_string()]);
+        let edit = SceneEdit::Transform {
+            name: "ball".to_string(),
+            transform: transform.clone(),
+        };
+        renderer.edit_prepared(&mut prepared, edit).unwrap();
+        *scene.objects[0].transform_mut() = transform;
+        let image = renderer.render_prepared(&prepared, &camera).unwrap();
+        assert_eq!(image, renderer.render(&scene).unwrap());
+
+        let cube: Object = serde_json::from_str(
+            r##"{"kind": "cube", "name": "box", "center": [-1.5, 0, 0.5], "size": [1, 1, 1],
+                "material": {"color": "#40C040", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}"##,
+        )
+        .unwrap();
+        renderer
+            .edit_prepared(&mut prepared, SceneEdit::Add(Box::new(cube.clone())))
+            .unwrap();
+        scene.objects.push(cube);
+        let added = renderer.render_prepared(&prepared, &camera).unwrap();
+        assert_eq!(added, renderer.render(&scene).unwrap());
+        assert_ne!(added, image);
+
+        let edit = SceneEdit::Remove {
+            name: "ball".to_string(),
+        };
+        renderer.edit_prepared(&mut prepared, edit).unwrap();
+        scene.objects.remove(0);
+        assert_eq!(prepared.scene().objects.len(), 3);
+        assert_eq!(
+            renderer.render_prepared(&prepared, &camera).unwrap(),
+            renderer.render(&scene).unwrap()
+        );
+
+        // Unknown names and renderers that would build a different world are errors, and
+        // leave the scene as it was
+        let edit = SceneEdit::Remove {
+            name: "ball".to_string(),
+        };
+        assert!(renderer.edit_prepared(&mut prepared, edit).is_err());
+        let mut clay = Renderer::new(24, 24);
+        clay.render_mode = RenderMode::AmbientOcclusion;
+        let edit = SceneEdit::Remove {
+            name: "box".to_string(),
+        };
+        assert!(clay.edit_prepared(&mut prepared, edit).is_err());
+        assert_eq!(prepared.scene().objects.len(), 3);
+    }
+
+    #[test]
+    fn test_prepared_scene_is_send_and_sync() {
+        fn shareable<T: Send + Sync>() {}
+        shareable::<PreparedScene>();
+    }
+
+    #[test]
+    fn test_grid_reflections() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [6, -8, 6], "target": [0, 0, 0.5], "up": [0, 0, 1],
+                "width": 8, "height": 6, "grid_pitch": 1.0, "grid_color": "#00FF00", "grid_thickness": 0.1},
+            "objects": [{"kind": "sphere", "center": [0, 0, 1], "radius": 1,
+                "material": {"color": "#FFFFFF", "ambient": 0.0, "diffuse": 0.0, "specular": 0.0,
+                    "shininess": 10, "reflectivity": 1.0}}],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}, "background_color": "#000000"}
+        }"##,
+        )
+        .unwrap();
+        let green_pixels = |scene: &Scene| {
+            let image = Renderer::new(80, 60).render(scene).expect("Render failed");
+            image.pixels().filter(|p| p[1] > 128 && p[0] < 64).count()
+        };
+
+        // The default quincunx sampling draws the grid behind the sphere, but only
+        // reflects it when asked to
+        let without = green_pixels(&scene);
+        assert!(without > 0);
+        scene.camera.grid_reflections = Some(true);
+        assert!(green_pixels(&scene) > without);
+    }
+
+    #[test]
+    fn test_half_res_shading() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "perspective", "position": [0, -6, 2.5], "target": [0, 0, 0.8], "up": [0, 0, 1], "width": 8, "height": 6, "fov": 45},
+            "objects": [
+                {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1],
+                    "material": {"color": "#E0E0E0", "ambient": 0.1, "diffuse": 0.8, "specular": 0, "shininess": 1,
+                        "texture": {"type": "checkerboard", "material_b": {"color": "#4060C0", "ambient": 0.1, "diffuse": 0.8, "specular": 0, "shininess": 1}}}},
+                {"kind": "sphere", "center": [-1.2, 0, 1], "radius": 1,
+                    "material": {"color": "#C04040", "ambient": 0.1, "diffuse": 0.7, "specular": 0.3, "shininess": 32}},
+                {"kind": "sphere", "center": [1.2, 0, 1], "radius": 1,
+                    "material": {"color": "#FFFFFF", "ambient": 0, "diffuse": 0.1, "specular": 0, "shininess": 1, "reflectivity": 0.9}}
+            ],
+            "lights": [{"position": [-3, -4, 6], "color": "#FFFFFF", "intensity": 1, "diameter": 1.5}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.2}, "background_color": "#203040"}
+        }"##,
+        )
+        .unwrap();
+        let mut renderer = Renderer::new(64, 48);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let full = renderer.render(&scene).unwrap();
+        let half = renderer.with_half_res_shading().render(&scene).unwrap();
+
+        // Soft shadows and shading come through the upsampling nearly unchanged, with
+        // the checkerboard as sharp as before
+        let differences: Vec<i32> = full
+            .pixels()
+            .zip(half.pixels())
+            .flat_map(|(a, b)| (0..3).map(move |c| (a[c] as i32 - b[c] as i32).abs()))
+            .collect();
+        let mean = differences.iter().sum::<i32>() as f64 / differences.len() as f64;
+        assert!(mean < 2.0, "mean difference {}", mean);
+        assert!(differences.iter().any(|&d| d > 0));
 
-                    let ray = camera.get_ray(sample_u, sample_v);
-
-                    // Create sample-specific seed for ray tracing consistency
-                    let sample_seed =
-                        pixel_seed.wrapping_add((sample as u64).wrapping_mul(0x1F845FED));
-
-                    let (sample_color, sample_depth, sample_normal) = ray_color_with_data(
-                        &ray,
-                        world,
-                        lights,
-                        render_context.ambient,
-                        render_context.fog,
-                        render_context.camera_pos,
-                        render_context.background_color,
-                        materials,
-                        self.max_depth,
-                        Some(camera),
-                        sample_seed,
-                    );
+        // The mirror is shaded at full resolution
+        for (x, y) in [(43, 22), (41, 25), (45, 20)] {
+            assert_eq!(full.get_pixel(x, y), half.get_pixel(x, y));
+        }
+    }
 
-                    total_color += sample_color;
-                    
-                    // For outline detection, we want the closest depth and corresponding normal
-                    if let (Some(depth), Some(normal)) = (sample_depth, sample_normal) {
-                        if pixel_depth.is_none() || depth < pixel_depth.unwrap() {
-                            pixel_depth = Some(depth);
-                            pixel_normal = Some(normal);
-                        }
+    #[test]
+    fn test_failed_tiles_are_retried() {
+        let failures = TileFailures::new();
+        let mut renderer = Renderer::new(40, 40).with_tile_failures(failures.clone());
+        renderer.tile_size = 20;
+        let first_try = AtomicBool::new(true);
+        let results = renderer
+            .render_tiles_checked(
+                false,
+                |tile| {
+                    // The top left tile fails once, the bottom right one every time
+                    let (x, y) = (tile.x0, tile.y0);
+                    if (x, y) == (0, 0) && first_try.swap(false, Ordering::SeqCst) {
+                        panic!("flaky tile");
                     }
-                }
-
-                // Average the samples
-                let color = total_color / self.samples as f64;
-
-                // Update progress tracking
-                let current_completed = completed_pixels.fetch_add(1, Ordering::Relaxed) + 1;
-
-                // Print progress periodically with thread-safe output
-                if current_completed % progress_step as usize == 0
-                    || current_completed == total_pixels as usize
-                {
-                    if let Ok(_guard) = progress_mutex.lock() {
-                        let progress = (current_completed as f64 / total_pixels as f64) * 100.0;
-                        let elapsed = start_time.elapsed();
-
-                        if current_completed == total_pixels as usize {
-                            // Final progress update
-                            println!("Rendering: 100.0%");
-                        } else if progress > 0.0 {
-                            // Calculate estimated time remaining
-                            let estimated_total_time = elapsed.as_secs_f64()
-                                / (current_completed as f64 / total_pixels as f64);
-                            let estimated_remaining = estimated_total_time - elapsed.as_secs_f64();
-                            let eta_formatted = format_duration(estimated_remaining);
-                            println!("Rendering: {:.1}% (ETA: {})", progress, eta_formatted);
-                        }
+                    if (x, y) == (20, 20) {
+                        panic!("broken tile");
                     }
-                }
-
-                (x, y, color, pixel_depth, pixel_normal)
-            })
+                    let value = if (x, y) == (20, 0) { f64::NAN } else { 1.0 };
+                    tile.pixels().map(|(x, y)| (x, y, value)).collect()
+                },
+                |&(x, y, value)| match value.is_nan() {
+                    true => Err(format!("NaN at ({}, {})", x, y)),
+                    false => Ok(()),
+                },
+            )
+            .unwrap();
+
+        // The flaky tile is back, the NaN tile keeps its values and the broken one is missing
+        assert_eq!(results.len(), 3 * 400);
+        assert!(results.iter().any(|&(x, y, _)| (x, y) == (0, 0)));
+        let nan_count = results
+            .iter()
+            .filter(|(_, _, value)| value.is_nan())
+            .count();
+        assert_eq!(nan_count, 400);
+        assert!(!results.iter().any(|&(x, y, _)| x >= 20 && y >= 20));
+
+        let failures = failures.failures();
+        let summary: Vec<_> = failures
+            .iter()
+            .map(|failure| (failure.x0, failure.y0, failure.recovered))
             .collect();
+        assert_eq!(summary, [(0, 0, true), (20, 0, false), (20, 20, false)]);
+        assert_eq!(failures[0].error, "panicked: flaky tile");
+        assert_eq!(failures[1].error, "NaN at (20, 0)");
+    }
 
-        // Separate color data and outline data
-        let mut image_data = Vec::new();
-        let mut outline_buffers = OutlineBuffers::new(self.width, self.height);
-        
-        for (x, y, color, depth, normal) in results {
-            image_data.push((x, y, color));
-            
-            if let Some(depth) = depth {
-                outline_buffers.set_depth(x, y, depth);
-            }
-            if let Some(normal) = normal {
-                outline_buffers.set_normal(x, y, normal);
-            }
-        }
+    #[test]
+    fn test_non_finite_check() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+                "objects": [{"kind": "sphere", "name": "ball", "center": [0, 0, 0], "radius": 1,
+                    "material": {"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+            }"##,
+        )
+        .unwrap();
+        // A shading bug's worth of NaN on every pixel of the ball
+        scene.scene_settings.ambient_illumination.intensity = f64::NAN;
+        let mut renderer = Renderer::new(8, 8);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
 
-        (image_data, Some(outline_buffers))
+        // Unchecked, the ball quietly turns as black as the background
+        let image = renderer.render(&scene).unwrap();
+        assert_eq!(image.get_pixel(4, 4), &Rgb([0, 0, 0]));
+
+        let pixels = NonFinitePixels::new();
+        let renderer = renderer.with_non_finite_check(pixels.clone());
+        let image = renderer.render(&scene).unwrap();
+        assert_eq!(image.get_pixel(4, 4), &Rgb([255, 0, 255]));
+        assert_eq!(image.get_pixel(0, 0), &Rgb([0, 0, 0]));
+
+        let found = pixels.pixels();
+        let magenta = image.pixels().filter(|p| **p == Rgb([255, 0, 255])).count();
+        assert_eq!(found.len(), magenta);
+        assert!(found.iter().any(|pixel| (pixel.x, pixel.y) == (4, 4)));
+        assert!(found[0].color[0].is_nan());
+        assert_eq!(found[0].object.as_deref(), Some("'ball' (sphere)"));
+
+        // Float output is marked too, and pixels add up until cleared
+        let hdr = renderer.render_hdr(&scene).unwrap();
+        assert_eq!(hdr.get_pixel(4, 4), &Rgb([1.0, 0.0, 1.0]));
+        assert_eq!(pixels.pixels().len(), 2 * magenta);
+        pixels.clear();
+        assert!(pixels.pixels().is_empty());
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn render_quincunx(
-        &self,
-        world: &World,
-        camera: &Camera,
-        lights: &[crate::scene::Light],
-        ambient: &crate::scene::AmbientIllumination,
-        fog: &Option<crate::scene::Fog>,
-        camera_pos: &Point,
-        background_color: Color,
-        materials: &HashMap<usize, crate::scene::Material>,
-    ) -> Vec<(u32, u32, Color)> {
-        use std::collections::HashMap as StdHashMap;
-        use std::sync::{Arc, Mutex};
-
-        // Pre-compute corner samples that will be shared between pixels
-        // Each corner is identified by its grid position
-        let corner_cache: Arc<Mutex<StdHashMap<(u32, u32), Color>>> =
-            Arc::new(Mutex::new(StdHashMap::new()));
+    #[test]
+    fn test_motion_blur() {
+        let scene_with = |motion: &str, shutter: &str| {
+            Scene::from_json_str(&format!(
+                r##"{{
+                "camera": {{"kind": "ortho", "position": [0, 0, 8], "target": [0, 0, 0], "up": [0, 1, 0],
+                    "width": 8, "height": 2{shutter}}},
+                "objects": [
+                    {{"kind": "sphere", "center": [-2, 0, 0], "radius": 0.5{motion},
+                        "material": {{"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}}}
+                ],
+                "lights": [],
+                "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 1}}, "background_color": "#000000"}}
+            }}"##
+            ))
+            .unwrap()
+        };
+        let mut renderer = Renderer::new(80, 20);
+        renderer.samples = 32;
+        let render =
+            |motion: &str, shutter: &str| renderer.render(&scene_with(motion, shutter)).unwrap();
+        let still = render("", "");
+        let moving = render(r#", "motion": ["translate(4, 0, 0)"]"#, "");
+
+        // The sphere smears into a streak along its path, dimmest where it spent least time
+        let streak = |image: &RgbImage, x: u32| image.get_pixel(x, 10)[0];
+        assert_eq!(streak(&still, 20), 255);
+        assert_eq!(streak(&still, 40), 0);
+        let (start, middle) = (streak(&moving, 20), streak(&moving, 40));
+        assert!(start < 200, "{}", start);
+        assert!((20..160).contains(&middle), "{}", middle);
+        assert!(streak(&moving, 60) > 0);
+        assert_eq!(streak(&moving, 70), 0);
+
+        // Closing the shutter halfway leaves the second half of the path out
+        let half = render(
+            r#", "motion": ["translate(4, 0, 0)"]"#,
+            r#", "shutter_close": 0.5"#,
+        );
+        assert!(streak(&half, 35) > 0);
+        assert_eq!(streak(&half, 50), 0);
 
-        // Calculate pixel size in UV coordinates
-        let pixel_width = 1.0 / self.width as f64;
-        let pixel_height = 1.0 / self.height as f64;
+        // A motion that goes nowhere renders exactly as no motion
+        assert_eq!(render(r#", "motion": ["translate(0, 0, 0)"]"#, ""), still);
+    }
 
-        // Helper function to get corner sample color (with caching)
-        let get_corner_sample = |corner_x: u32,
-                                 corner_y: u32,
-                                 corner_cache: Arc<Mutex<StdHashMap<(u32, u32), Color>>>,
-                                 world: &World,
-                                 camera: &Camera|
-         -> Color {
-            let key = (corner_x, corner_y);
+    #[test]
+    fn test_grid_background_coverage() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 8], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 6, "height": 6, "grid_pitch": 0.5, "grid_color": "#FFFFFF", "grid_thickness": 0.02},
+            "objects": [],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}, "background_color": "#000000"}
+        }"##,
+        )
+        .unwrap();
+        // At one sample per pixel, the 12 lines a fifth of a pixel wide each tint the
+        // pixels they cross by about a fifth, instead of being hit or missed
+        let mut renderer = Renderer::new(60, 60);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render(&scene).unwrap();
+        let row: Vec<u8> = (0..60).map(|x| image.get_pixel(x, 2)[0]).collect();
+        assert!(row
+            .chunks(5)
+            .all(|cell| cell.iter().any(|&value| value > 0)));
+        assert!(row.iter().all(|&value| value < 64), "{:?}", row);
+    }
 
-            // Check cache first
-            {
-                let cache = corner_cache.lock().unwrap();
-                if let Some(&color) = cache.get(&key) {
-                    return color;
-                }
-            }
+    #[test]
+    fn test_fisheye_corners_are_black() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "fisheye", "position": [0, 0, 0], "target": [0, 1, 0], "up": [0, 0, 1], "width": 1, "height": 1},
+            "objects": [],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}, "background_color": "#FFFFFF"}
+        }"##,
+        )
+        .unwrap();
+        // Rays past the image circle would look further round than the fov and see the
+        // background; the lens forms no image there
+        for mode in [AntiAliasingMode::Quincunx, AntiAliasingMode::NoJitter] {
+            let mut renderer = Renderer::new(32, 32);
+            renderer.anti_aliasing_mode = mode;
+            let image = renderer.render(&scene).unwrap();
+            assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+            assert_eq!(image.get_pixel(31, 31).0, [0, 0, 0]);
+            assert_eq!(image.get_pixel(16, 16).0, [255, 255, 255]);
+            assert_eq!(image.get_pixel(1, 16).0, [255, 255, 255]);
+        }
+    }
 
-            // Calculate corner UV coordinates (corners are at pixel boundaries)
-            let corner_u = (corner_x as f64 * pixel_width).clamp(0.0, 1.0);
-            let corner_v = (1.0 - corner_y as f64 * pixel_height).clamp(0.0, 1.0); // Flip Y coordinate
+    #[test]
+    fn test_floor_grid() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 8], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 6, "height": 6},
+            "objects": [
+                {"kind": "floor_grid", "pitch": 1, "line_width": 0.2, "line_color": "#000000", "extent": 4,
+                    "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.9, "specular": 0, "shininess": 10}}
+            ],
+            "lights": [{"position": [6, 0, 2], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+        }"##,
+        )
+        .unwrap();
+        let mut renderer = Renderer::new(60, 60);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render(&scene).unwrap();
+        // Ten pixels to a cell, with the lines through the origin two pixels wide; the
+        // grid stops 2 units out
+        let cell = image.get_pixel(35, 24)[0];
+        assert!(cell > 100, "{}", cell);
+        assert_eq!(image.get_pixel(30, 24)[0], 0);
+        assert_eq!(image.get_pixel(5, 24)[0], 0);
+
+        // A wall out of view shades the grid like any other surface
+        scene.objects.push(Object::Cube {
+            name: None,
+            center: [3.5, 0.0, 1.0],
+            size: [0.2, 10.0, 2.0],
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        let shaded = renderer.render(&scene).unwrap().get_pixel(35, 24)[0];
+        assert!(shaded < cell - 30, "{} vs {}", shaded, cell);
+    }
 
-            let ray = camera.get_ray(corner_u, corner_v);
+    #[test]
+    fn test_auto_floor() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8},
+            "objects": [
+                {"kind": "sphere", "center": [0, 0, 1], "radius": 1,
+                    "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.9, "specular": 0, "shininess": 10}}
+            ],
+            "lights": [{"position": [10, 0, 10], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#808080",
+                "auto_floor": {"shadow_catcher": true}}
+        }"##,
+        )
+        .unwrap();
+        let floor = scene.auto_floor().unwrap().unwrap();
+        let Object::Plane { point, .. } = &floor else {
+            panic!("expected a plane");
+        };
+        assert_eq!(point[2], 0.0);
 
-            // Create deterministic seed for corner based on corner coordinates
-            let corner_seed = self
-                .seed
-                .unwrap_or(0)
-                .wrapping_mul(0x9E3779B97F4A7C15_u64)
-                .wrapping_add(corner_x as u64)
-                .wrapping_add((corner_y as u64).wrapping_mul(0x85EBCA6B));
+        let mut renderer = Renderer::new(16, 16);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        // The sphere's shadow falls toward -x; the rest of the catcher shows the background
+        let image = renderer.render(&scene).unwrap();
+        let (shadow, open) = ((5, 8), (14, 8));
+        assert_eq!(image.get_pixel(open.0, open.1)[0], 128);
+        assert_eq!(image.get_pixel(shadow.0, shadow.1)[0], 0);
+        let buffers = renderer
+            .render_aovs(&scene, &[Aov::Alpha, Aov::ObjectId])
+            .unwrap();
+        let at = |(x, y): (u32, u32)| (y * 16 + x) as usize;
+        assert_eq!(buffers[0].pixels[at(shadow)], [1.0; 3]);
+        assert_eq!(buffers[0].pixels[at(open)], [0.0; 3]);
+        assert_eq!(buffers[0].pixels[at((8, 8))], [1.0; 3]);
+        assert_eq!(buffers[1].pixels[at(open)], [2.0; 3]);
+
+        // Cropping keeps the sphere and its shadow, not the whole catcher
+        let framed = Renderer::new(16, 16).with_framing(Framing {
+            crop_to_content: true,
+            padding_percent: 0.0,
+        });
+        let window = framed.framing_window(&scene).unwrap();
+        assert!(window.x0 <= 5 && window.x0 + window.width <= 13);
+
+        // An ordinary floor is lit and shaded like any other plane
+        let auto_floor = scene.scene_settings.auto_floor.as_mut().unwrap();
+        auto_floor.shadow_catcher = false;
+        auto_floor.offset = 0.5;
+        let image = renderer.render(&scene).unwrap();
+        assert!(image.get_pixel(open.0, open.1)[0] > 128);
+        let buffers = renderer.render_aovs(&scene, &[Aov::Alpha]).unwrap();
+        assert_eq!(buffers[0].pixels[at(open)], [1.0; 3]);
+    }
 
-            let color = ray_color(
-                &ray,
-                world,
-                lights,
-                ambient,
-                fog,
-                camera_pos,
-                background_color,
-                materials,
-                self.max_depth,
-                corner_seed,
-            );
+    #[test]
+    fn test_shadow_catcher_material() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8},
+            "objects": [
+                {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1],
+                    "material": {"color": "#FFFFFF", "ambient": 0, "diffuse": 1, "specular": 0, "shininess": 1,
+                        "shadow_catcher": true}},
+                {"kind": "sphere", "center": [0, 0, 1.5], "radius": 0.5,
+                    "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.9, "specular": 0, "shininess": 10}},
+                {"kind": "quad", "vertices": [[2, -4, -1], [4, -4, -1], [4, 4, -1], [2, 4, -1]],
+                    "material": {"color": "#0000FF", "ambient": 0.5, "diffuse": 0.5, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [{"position": [0, 0, 6], "color": "#FFFFFF", "intensity": 1, "diameter": 2}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+        }"##,
+        )
+        .unwrap();
+        let mut renderer = Renderer::new(32, 32);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render(&scene).unwrap();
+        let buffers = renderer.render_aovs(&scene, &[Aov::Alpha]).unwrap();
+        let alpha = |x: u32, y: u32| buffers[0].pixels[(y * 32 + x) as usize][0];
+
+        // Unshadowed catcher over the background is fully transparent
+        assert_eq!(alpha(2, 2), 0.0);
+        assert_eq!(image.get_pixel(2, 2)[0], 0);
+        assert_eq!(alpha(16, 16), 1.0);
+
+        // The area light's penumbra fades the shadow's alpha out from the sphere
+        let row: Vec<f32> = (16..24).map(|x| alpha(x, 16)).collect();
+        assert!(row.windows(2).all(|pair| pair[1] <= pair[0]), "{:?}", row);
+        assert!(row.iter().any(|&a| a > 0.0 && a < 1.0), "{:?}", row);
+        assert_eq!(row[7], 0.0);
+
+        // Over another object, the catcher shows that object
+        let behind = image.get_pixel(28, 2);
+        assert!(behind[2] > 0 && behind[0] == 0, "{:?}", behind);
+        assert_eq!(alpha(28, 2), 1.0);
+    }
 
-            // Cache the result
-            {
-                let mut cache = corner_cache.lock().unwrap();
-                cache.insert(key, color);
-            }
+    #[test]
+    fn test_opacity() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8},
+            "objects": [
+                {"kind": "cube", "center": [2, 0, -0.5], "size": [4, 8, 1],
+                    "material": {"color": "#FFFFFF", "ambient": 0, "diffuse": 1, "specular": 0, "shininess": 1}},
+                {"kind": "quad", "vertices": [[-4, -4, 1], [2, -4, 1], [2, 4, 1], [-4, 4, 1]],
+                    "material": {"color": "#0000FF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1,
+                        "opacity": 0.5}}
+            ],
+            "lights": [{"position": [1, 0, 10], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+        }"##,
+        )
+        .unwrap();
+        let mut renderer = Renderer::new(16, 16);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render(&scene).unwrap();
+        let (open, covered, over_background) = ((14, 8), (10, 8), (4, 8));
+        assert!(image.get_pixel(open.0, open.1)[0] > 240);
+
+        // Half the quad shows over the floor, which it lets half the light through to
+        let [r, _, b] = image.get_pixel(covered.0, covered.1).0;
+        assert!((60..=66).contains(&r), "{}", r);
+        assert!((187..=193).contains(&b), "{}", b);
+        assert_eq!(
+            image.get_pixel(over_background.0, over_background.1).0,
+            [0, 0, 127]
+        );
 
-            color
+        let buffers = renderer.render_aovs(&scene, &[Aov::Alpha]).unwrap();
+        let at = |(x, y): (u32, u32)| (y * 16 + x) as usize;
+        assert_eq!(buffers[0].pixels[at(over_background)], [0.5; 3]);
+        assert_eq!(buffers[0].pixels[at(covered)], [1.0; 3]);
+    }
+
+    #[test]
+    fn test_clip_planes() {
+        let scene_json = |clip_planes: &str| {
+            format!(
+                r##"{{
+            "camera": {{"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8}},
+            "objects": [
+                {{"kind": "cube", "center": [0, 0, 0], "size": [4, 4, 4],
+                    "material": {{"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}}},
+                {{"kind": "group", "ignore_clip": true, "objects": [
+                    {{"kind": "sphere", "center": [0, 0, 0], "radius": 1.5,
+                        "material": {{"color": "#FF0000", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}}}
+                ]}},
+                {{"kind": "cube", "center": [-1.25, -1.25, 1.5], "size": [0.6, 0.6, 0.6],
+                    "material": {{"color": "#0000FF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}}}
+            ],
+            "lights": [{{"position": [0, 0, 10], "color": "#FFFFFF", "intensity": 1, "diameter": null}}],
+            "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 1}}, "background_color": "#000000",
+                "clip_planes": {}}}
+        }}"##,
+                clip_planes
+            )
+        };
+        let render = |clip_planes: &str| {
+            let scene = Scene::from_json_str(&scene_json(clip_planes)).unwrap();
+            let mut renderer = Renderer::new(16, 16);
+            renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+            renderer.render(&scene).unwrap()
         };
+        let (center, small_cube, outside) = ((8, 8), (5, 10), (1, 1));
 
-        // Create a vector of all pixel coordinates
-        let pixels: Vec<(u32, u32)> = (0..self.height)
-            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
-            .collect();
+        let image = render("[]");
+        assert_eq!(image.get_pixel(center.0, center.1).0, [255, 255, 255]);
 
-        // Progress tracking setup
-        let total_pixels = self.width * self.height;
-        let progress_step = (total_pixels / 10).max(1);
+        // Cutting off the top opens the box; the sphere inside it is kept whole while
+        // the small cube above the cut goes, showing the box's floor
+        let image = render(r#"[{"point": [0, 0, 1], "normal": [0, 0, 1]}]"#);
+        assert_eq!(image.get_pixel(center.0, center.1).0, [255, 0, 0]);
+        assert_eq!(
+            image.get_pixel(small_cube.0, small_cube.1).0,
+            [255, 255, 255]
+        );
+        assert_eq!(image.get_pixel(outside.0, outside.1).0, [0, 0, 0]);
 
-        // Render pixels in parallel
-        pixels
-            .par_iter()
-            .enumerate()
-            .map(|(pixel_index, &(x, y))| {
-                // Calculate center sample coordinates
-                let pixel_center_u = (x as f64 + 0.5) * pixel_width;
-                let pixel_center_v = 1.0 - (y as f64 + 0.5) * pixel_height; // Flip Y coordinate
-
-                // Center sample
-                let center_ray = camera.get_ray(pixel_center_u, pixel_center_v);
-
-                // Create deterministic seed for center sample based on pixel coordinates
-                let center_seed = self
-                    .seed
-                    .unwrap_or(0)
-                    .wrapping_mul(0x9E3779B97F4A7C15_u64)
-                    .wrapping_add((x as u64).wrapping_mul(0x85EBCA6B))
-                    .wrapping_add((y as u64).wrapping_mul(0xC2B2AE35))
-                    .wrapping_add(0x12345678_u64); // Different constant for center vs corners
-
-                let center_color = ray_color(
-                    &center_ray,
-                    world,
-                    lights,
-                    ambient,
-                    fog,
-                    camera_pos,
-                    background_color,
-                    materials,
-                    self.max_depth,
-                    center_seed,
-                );
+        // Only what lies on the normal's side is cut, here leaving the top of the box
+        let image = render(r#"[{"point": [0, 0, 1], "normal": [0, 0, -1]}]"#);
+        assert_eq!(image.get_pixel(center.0, center.1).0, [255, 255, 255]);
 
-                // Get corner samples (these are shared between neighboring pixels)
-                // Corner positions are at pixel grid intersections
-                let corner_colors = [
-                    get_corner_sample(x, y, corner_cache.clone(), world, camera), // Top-left corner
-                    get_corner_sample(x + 1, y, corner_cache.clone(), world, camera), // Top-right corner
-                    get_corner_sample(x, y + 1, corner_cache.clone(), world, camera), // Bottom-left corner
-                    get_corner_sample(x + 1, y + 1, corner_cache.clone(), world, camera), // Bottom-right corner
-                ];
-
-                // Average center + 4 corner samples (true quincunx pattern)
-                let total_color = center_color
-                    + corner_colors[0]
-                    + corner_colors[1]
-                    + corner_colors[2]
-                    + corner_colors[3];
-                let color = total_color / 5.0;
-
-                // Print progress periodically (note: this might be out of order due to parallelism)
-                if pixel_index % progress_step as usize == 0 {
-                    let progress = (pixel_index as f64 / total_pixels as f64) * 100.0;
-                    println!("Rendering: {:.1}%", progress);
-                }
+        let flat = r#"[{"point": [0, 0, 0], "normal": [0, 0, 0]}]"#;
+        let scene = Scene::from_json_str(&scene_json(flat)).unwrap();
+        assert!(Renderer::new(16, 16).render(&scene).is_err());
+    }
 
-                (x, y, color)
-            })
-            .collect()
+    #[test]
+    fn test_xray() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8},
+            "objects": [
+                {"kind": "cube", "center": [0, 0, 0], "size": [4, 4, 4],
+                    "material": {"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}},
+                {"kind": "sphere", "center": [0, 0, 0], "radius": 1,
+                    "material": {"color": "#FF0000", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [{"position": [0, 0, 10], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+        }"##,
+        )
+        .unwrap();
+        let mut renderer = Renderer::new(16, 16);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render(&scene).unwrap();
+        assert_eq!(image.get_pixel(8, 8).0, [255, 255, 255]);
+
+        // The sphere shows through the ghosted top of the box, and the box's floor
+        // beside it; the background around the box is untouched
+        let xray = "0.25,1".parse().unwrap();
+        let renderer = renderer.with_xray(xray);
+        let image = renderer.render(&scene).unwrap();
+        let [r, g, b] = image.get_pixel(8, 8).0;
+        assert_eq!((r, g), (255, 63));
+        assert_eq!(g, b);
+        assert_eq!(image.get_pixel(5, 5).0, [255, 255, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_transparency() {
+        let scene_json = |material: &str| {
+            format!(
+                r##"{{
+                "camera": {{"kind": "ortho", "position": [0, 0, 8], "target": [0, 0, 0], "up": [0, 1, 0],
+                    "width": 4, "height": 4}},
+                "objects": [{{"kind": "sphere", "center": [0, 0, 0], "radius": 1, "material": {}}}],
+                "lights": [{{"position": [0, 0, 10], "color": "#FFFFFF", "intensity": 1, "diameter": null}}],
+                "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 0}},
+                    "background_color": "#808080"}}
+            }}"##,
+                material
+            )
+        };
+        let mut renderer = Renderer::new(20, 20);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+
+        // Without bending the sphere vanishes into the background, tinted by its color
+        let clear = r##"{"color": "#FFFFFF", "ambient": 0, "diffuse": 1, "specular": 0, "shininess": 10,
+            "transparency": 1, "refractive_index": 1}"##;
+        let scene = Scene::from_json_str(&scene_json(clear)).unwrap();
+        let image = renderer.render(&scene).unwrap();
+        assert_eq!(image.get_pixel(10, 10), image.get_pixel(0, 0));
+        let red = clear.replace("#FFFFFF", "#FF0000");
+        let scene = Scene::from_json_str(&scene_json(&red)).unwrap();
+        let image = renderer.render(&scene).unwrap();
+        assert_eq!(image.get_pixel(10, 10)[1], 0);
+        assert_eq!(image.get_pixel(10, 10)[0], image.get_pixel(0, 0)[0]);
+
+        let invalid = clear.replace("\"refractive_index\": 1", "\"refractive_index\": 0");
+        let scene = Scene::from_json_str(&scene_json(&invalid)).unwrap();
+        assert!(renderer.render(&scene).is_err());
     }
 
-    fn create_image_from_data(&self, image_data: Vec<(u32, u32, Color)>) -> RgbImage {
-        let mut image = ImageBuffer::new(self.width, self.height);
+    #[test]
+    fn test_caustics() {
+        let scene_json = |caustics: &str| {
+            format!(
+                r##"{{
+                "camera": {{"kind": "ortho", "position": [0, 0, 8], "target": [0, 0, 0], "up": [0, 1, 0],
+                    "width": 6, "height": 6}},
+                "objects": [
+                    {{"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1],
+                        "material": {{"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.9, "specular": 0, "shininess": 10}}}},
+                    {{"kind": "sphere", "center": [0, 0, 1.5], "radius": 0.5,
+                        "material": {{"color": "#FFFFFF", "ambient": 0, "diffuse": 0, "specular": 0, "shininess": 10,
+                            "transparency": 1}}}}
+                ],
+                "lights": [{{"position": [-8, 0, 9.5], "color": "#FFFFFF", "intensity": 1, "diameter": null}}],
+                "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 1}}{}}}
+            }}"##,
+                caustics
+            )
+        };
+        let mut renderer = Renderer::new(60, 60);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        // The glass ball's shadow falls about 1.5 units along x, clear of the ball itself
+        let shadow = |caustics: &str| {
+            let scene = Scene::from_json_str(&scene_json(caustics)).unwrap();
+            renderer.render(&scene).unwrap().get_pixel(45, 30)[0]
+        };
+        let dark = shadow("");
+        let lit = shadow(r#", "caustics": {"photons": 100000, "radius": 0.1}"#);
+        assert!(lit > dark + 40, "{} vs {}", lit, dark);
+    }
 
-        for (x, y, color) in image_data {
-            // Convert to RGB values (0-255)
-            let r = (color.x.clamp(0.0, 1.0) * 255.0) as u8;
-            let g = (color.y.clamp(0.0, 1.0) * 255.0) as u8;
-            let b = (color.z.clamp(0.0, 1.0) * 255.0) as u8;
+    #[test]
+    fn test_tile_size_does_not_change_output() {
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 2.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
 
-            image.put_pixel(x, y, Rgb([r, g, b]));
+        for mode in [AntiAliasingMode::Quincunx, AntiAliasingMode::Stochastic] {
+            let mut reference = Renderer::new(37, 23);
+            reference.anti_aliasing_mode = mode;
+            reference.samples = 2;
+            let expected = reference.render(&scene).unwrap();
+
+            // Tiles that do not divide the image evenly, and single-pixel tiles
+            for tile_size in [1, 5, 64] {
+                let mut renderer = Renderer::new(37, 23);
+                renderer.anti_aliasing_mode = mode;
+                renderer.samples = 2;
+                renderer.tile_size = tile_size;
+                assert_eq!(renderer.render(&scene).unwrap(), expected);
+            }
         }
-
-        image
     }
 
-    pub fn render_to_file(
-        &self,
-        scene: &Scene,
-        output_path: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let image = self.render(scene)?;
-        image.save(output_path)?;
-        println!("Image saved to: {}", output_path);
-        Ok(())
+    #[test]
+    fn test_deterministic_output() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "perspective", "position": [4, -6, 3], "target": [0, 0, 0.5], "up": [0, 0, 1],
+                "width": 8, "height": 6, "fov": 45},
+            "objects": [
+                {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1], "material": {"color": "#E0E0E0",
+                    "ambient": 0.2, "diffuse": 0.8, "specular": 0, "shininess": 10, "reflectivity": 0.2,
+                    "texture": {"type": "checkerboard", "material_b": {"color": "#404040", "ambient": 0.2,
+                        "diffuse": 0.8, "specular": 0, "shininess": 10}}}},
+                {"kind": "sphere", "center": [-0.8, 0, 1], "radius": 1, "material": {"color": "#D9534F",
+                    "ambient": 0.1, "diffuse": 0.7, "specular": 0.5, "shininess": 32, "reflectivity": 0.3}},
+                {"kind": "cube", "center": [1.2, 0.5, 0.5], "size": [1, 1, 1], "transform": ["rotate(0, 0, 30)"],
+                    "material": {"color": "#5B8DEF", "ambient": 0.1, "diffuse": 0.8, "specular": 0.2, "shininess": 16}}
+            ],
+            "lights": [{"position": [3, -2, 6], "color": "#FFFFFF", "intensity": 1, "diameter": 1.5, "shadow_samples": 8}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.3}, "background_color": "#101828"}
+        }"##,
+        )
+        .unwrap();
+
+        let render = |thread_count: Option<usize>, tile_size: u32| {
+            let mut renderer = Renderer::new(48, 36);
+            renderer.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+            renderer.samples = 4;
+            renderer.seed = Some(7);
+            renderer.thread_count = thread_count;
+            renderer.tile_size = tile_size;
+            let image = renderer.render(&scene).unwrap();
+            let mut hasher = crate::assets::StableHasher::new();
+            hasher.update(image.as_raw());
+            hasher.finish()
+        };
+
+        // The exact bytes are pinned, so a change of random generator, material lookup or
+        // iteration order shows up here; update the hash only when the output is meant to
+        // change. Thread count and tiling must never change it.
+        let expected = render(Some(1), 64);
+        assert_eq!(expected, 0x8ca43993191f2d1b, "{:016x}", expected);
+        assert_eq!(render(None, 7), expected);
+        assert_eq!(render(Some(3), 16), expected);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::scene::{Light, Material, Object, Scene};
+    #[test]
+    fn test_progressive_matches_render() {
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 2.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        let mut stochastic = Renderer::new(24, 18);
+        stochastic.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        stochastic.samples = 4;
+        let mut outlined = Renderer::new(24, 18).with_outline_detection(OutlineConfig::default());
+        outlined.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        outlined.samples = 2;
+        let mut tilt_shifted = Renderer::new(24, 18).with_tilt_shift(TiltShiftConfig {
+            focus: 0.0,
+            range: 0.0,
+            blur: 0.1,
+        });
+        tilt_shifted.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        tilt_shifted.samples = 2;
+        let mut sharp = Renderer::new(24, 18);
+        sharp.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        sharp.samples = 2;
+        assert_ne!(tilt_shifted.render(&scene).unwrap(), sharp.render(&scene).unwrap());
+
+        for renderer in [stochastic, outlined, tilt_shifted, Renderer::new(24, 18)] {
+            let mut passes = Vec::new();
+            let image = renderer
+                .render_progressive(&scene, |pass, total, snapshot| {
+                    passes.push((pass, total, snapshot.clone()))
+                })
+                .unwrap();
+
+            let expected_passes = match renderer.anti_aliasing_mode {
+                AntiAliasingMode::Quincunx => 1,
+                _ => renderer.samples,
+            };
+            assert_eq!(passes.len() as u32, expected_passes);
+            assert_eq!(passes.last().unwrap().0, expected_passes);
+            assert_eq!(passes.last().unwrap().2, image);
+            assert_eq!(image, renderer.render(&scene).unwrap());
+        }
+    }
 
     #[test]
-    fn test_renderer_creation() {
-        let renderer = Renderer::new(800, 600);
-        assert_eq!(renderer.width, 800);
-        assert_eq!(renderer.height, 600);
-        assert_eq!(renderer.thread_count, None);
-        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
-        assert_eq!(renderer.samples, 1); // Default for quincunx with shared samples
+    fn test_cancellation_token() {
+        let scene = Scene::default();
+        let token = CancellationToken::new();
+        let renderer = Renderer::new(20, 20).with_cancellation(token.clone());
+        token.cancel();
 
-        // Test with specific thread count
-        let renderer_threaded = Renderer::new_with_threads(800, 600, 4);
-        assert_eq!(renderer_threaded.thread_count, Some(4));
-        assert_eq!(
-            renderer_threaded.anti_aliasing_mode,
-            AntiAliasingMode::Quincunx
-        );
+        let result = renderer.render(&scene);
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+        let result = renderer.render_progressive(&scene, |_, _, _| {});
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+
+        token.reset();
+        assert!(renderer.render(&scene).is_ok());
     }
 
     #[test]
-    fn test_simple_render() {
+    fn test_render_rgb_matches_render() {
         let mut scene = Scene::default();
-
-        // Add a simple sphere
         scene.objects.push(Object::Sphere {
+            name: None,
             center: [0.0, 0.0, 0.0],
-            radius: 1.0,
+            radius: 2.0,
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         });
 
-        // Add a light
+        let renderer = Renderer::new(20, 12);
+        let pixels = renderer.render_rgb(&scene).unwrap();
+        assert_eq!(pixels.len(), 20 * 12 * 3);
+        assert_eq!(pixels, renderer.render(&scene).unwrap().into_raw());
+    }
+
+    #[test]
+    fn test_render_hdr() {
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 2.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
         scene.lights.push(Light {
-            position: [2.0, 2.0, 2.0],
+            position: [0.0, -5.0, 5.0],
             color: "#FFFFFF".to_string(),
-            intensity: 1.0,
+            intensity: 4.0,
             diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
         });
 
-        let renderer = Renderer::new(100, 100);
-        let result = renderer.render(&scene);
-        assert!(result.is_ok());
+        let renderer = Renderer::new(16, 16);
+        let hdr = renderer.render_hdr(&scene).unwrap();
+        let ldr = renderer.render(&scene).unwrap();
+
+        // Highlights survive in the float image but clamp to the same 8-bit pixels
+        assert!(hdr.pixels().any(|p| p.0.iter().any(|&c| c > 1.0)));
+        for (h, l) in hdr.pixels().zip(ldr.pixels()) {
+            for channel in 0..3 {
+                let quantized = ((h[channel] as f64).clamp(0.0, 1.0) * 255.0) as u8;
+                assert!(quantized.abs_diff(l[channel]) <= 1);
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("rtrace-hdr-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["out.exr", "out.hdr"] {
+            let path = dir.join(name);
+            renderer.render_to_file(&scene, path.to_str().unwrap()).unwrap();
+            let decoded = image::open(&path).unwrap().into_rgb32f();
+            assert_eq!(decoded.dimensions(), (16, 16));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_stochastic_sampling() {
-        let mut scene = Scene::default();
+    fn test_render_sequence() {
+        let scene = Scene::from_json_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+                "objects": [{"kind": "sphere", "name": "ball", "center": [0, 0, 0], "radius": 1,
+                    "material": {"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"},
+                "animation": {"frames": 3, "objects": {"ball": {"keyframes": [
+                    {"frame": 0, "translate": [-1, 0, 0]}, {"frame": 2, "translate": [1, 0, 0]}
+                ]}}}
+            }"##,
+        )
+        .unwrap();
+        let mut renderer = Renderer::new(8, 8);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
 
-        // Add a simple sphere
+        let dir = std::env::temp_dir().join(format!("rtrace-sequence-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("ball.png");
+        let mut saved = Vec::new();
+        renderer
+            .render_sequence(&scene, output.to_str().unwrap(), |frame, path| {
+                saved.push((frame, path.to_string()))
+            })
+            .unwrap();
+
+        // One numbered file per frame, with the ball moving left to right
+        let frames: Vec<_> = saved
+            .iter()
+            .map(|(_, path)| image::open(path).unwrap().into_luma8())
+            .collect();
+        assert_eq!(
+            saved[2],
+            (2, dir.join("ball.0002.png").to_str().unwrap().to_string())
+        );
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1].get_pixel(1, 4), frames[1].get_pixel(6, 4));
+        assert!(frames[0].get_pixel(1, 4)[0] > 0 && frames[0].get_pixel(6, 4)[0] == 0);
+        assert!(frames[2].get_pixel(1, 4)[0] == 0 && frames[2].get_pixel(6, 4)[0] > 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tone_mapping() {
+        let mut scene = Scene::default();
         scene.objects.push(Object::Sphere {
+            name: None,
             center: [0.0, 0.0, 0.0],
-            radius: 1.0,
+            radius: 2.0,
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         });
-
-        // Add a light
         scene.lights.push(Light {
-            position: [2.0, 2.0, 2.0],
+            position: [0.0, -5.0, 5.0],
             color: "#FFFFFF".to_string(),
-            intensity: 1.0,
+            intensity: 4.0,
             diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
         });
 
-        // Test with multiple samples
-        let mut renderer = Renderer::new(50, 50);
-        renderer.anti_aliasing_mode = AntiAliasingMode::Stochastic;
-        renderer.samples = 4;
-        let result = renderer.render(&scene);
-        assert!(result.is_ok());
+        let linear = Renderer::new(16, 16);
+        let hdr = linear.render_hdr(&scene).unwrap();
+        let aces = Renderer::new(16, 16).with_tone_mapping(ToneMapping {
+            operator: crate::ToneMapOperator::Aces,
+            exposure: 0.0,
+        });
+        let mapped = aces.render(&scene).unwrap();
 
-        // Test with single sample
-        renderer.samples = 1;
-        let result = renderer.render(&scene);
-        assert!(result.is_ok());
+        // Every 8-bit pixel is the tone mapped radiance, and mapping changes the image
+        for (h, m) in hdr.pixels().zip(mapped.pixels()) {
+            let color = Color::new(h[0] as f64, h[1] as f64, h[2] as f64);
+            let expected = aces.tone_mapping.apply(color) * 255.0;
+            assert!((expected.x as u8).abs_diff(m[0]) <= 1);
+        }
+        assert_ne!(linear.render(&scene).unwrap(), mapped);
+        assert_ne!(linear.settings_fingerprint(), aces.settings_fingerprint());
     }
 
     #[test]
-    fn test_no_jitter_sampling() {
-        let mut scene = Scene::default();
+    fn test_auto_exposure() {
+        let scene_with_light = |intensity: f64| {
+            let mut scene = Scene::default();
+            scene.scene_settings.ambient_illumination.intensity = 0.0;
+            scene.objects.push(Object::Sphere {
+                name: None,
+                center: [0.0, 0.0, 0.0],
+                radius: 2.0,
+                material: Material::default(),
+                transform: None,
+                motion: None,
+                ignore_clip: None,
+            });
+            scene.lights.push(Light {
+                position: [0.0, -5.0, 5.0],
+                color: "#FFFFFF".to_string(),
+                intensity,
+                diameter: None,
+                shadow_samples: None,
+                attenuation: None,
+                emitter: None,
+                caustics: None,
+                group: None,
+                name: None,
+            });
+            scene
+        };
 
-        // Add a simple sphere
+        let renderer = Renderer::new(200, 100);
+        let dim = renderer.auto_exposure(&scene_with_light(1.0)).unwrap();
+        let blinding = renderer.auto_exposure(&scene_with_light(1000.0)).unwrap();
+        assert!(
+            (dim - blinding - 1000f64.log2()).abs() < 0.1,
+            "{} {}",
+            dim,
+            blinding
+        );
+
+        // Exposed for its highlights, the blinding scene no longer clips to white
+        let exposed = Renderer::new(32, 16).with_tone_mapping(ToneMapping {
+            exposure: blinding,
+            ..ToneMapping::default()
+        });
+        let pixels = exposed.render_rgb(&scene_with_light(1000.0)).unwrap();
+        let white = pixels
+            .chunks(3)
+            .filter(|p| p.iter().all(|&c| c == 255))
+            .count();
+        assert!(white < pixels.len() / 3 / 10, "{} white pixels", white);
+    }
+
+    #[test]
+    fn test_backplate_background() {
+        let mut scene = Scene::default();
         scene.objects.push(Object::Sphere {
+            name: None,
             center: [0.0, 0.0, 0.0],
-            radius: 1.0,
+            radius: 2.0,
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         });
 
-        // Add a light
-        scene.lights.push(Light {
-            position: [2.0, 2.0, 2.0],
-            color: "#FFFFFF".to_string(),
-            intensity: 1.0,
-            diameter: None,
+        // A gradient at the render's resolution maps one texel to each pixel
+        let (width, height) = (16, 12);
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| Color::new(x as f64 / 15.0, y as f64 / 11.0, 0.5))
+            .collect();
+        scene.scene_settings.background = Some(crate::scene::BackgroundSettings {
+            image: "plate.png".to_string(),
+            backplate: Some(Backplate::new(width, height, pixels).unwrap()),
         });
 
-        // Test no-jitter mode with single sample
-        let mut renderer = Renderer::new(50, 50);
+        let mut renderer = Renderer::new(width, height);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render(&scene).unwrap();
+        assert_eq!(image.get_pixel(0, 0), &Rgb([0, 0, 127]));
+        assert_eq!(image.get_pixel(15, 11), &Rgb([255, 255, 127]));
+        // In between, neighboring texels are blended
+        assert!((68..=85).contains(&image.get_pixel(5, 0)[0]));
+        // The sphere covers the center
+        assert_ne!(image.get_pixel(8, 6)[2], 127);
+
+        // An unloaded backplate is an error rather than a silent fallback
+        scene.scene_settings.background.as_mut().unwrap().backplate = None;
+        assert!(renderer.render(&scene).is_err());
+    }
+
+    #[test]
+    fn test_srgb_color_space() {
+        // Half-strength ambient light on a mid-gray sphere, in front of the same gray
+        let scene = |settings: &str| -> Scene {
+            let json = format!(
+                r##"{{
+                    "camera": {{"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4}},
+                    "objects": [{{"kind": "sphere", "center": [0, 0, 0], "radius": 1,
+                        "material": {{"color": "#808080", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}}}],
+                    "lights": [],
+                    "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 0.5}},
+                        "background_color": "#808080"{}}}
+                }}"##,
+                settings
+            );
+            serde_json::from_str(&json).unwrap()
+        };
+        let render = |scene: &Scene| {
+            let mut renderer = Renderer::new(8, 8);
+            renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+            if let Some(tone_mapping) = scene.get_tone_mapping().unwrap() {
+                renderer = renderer.with_tone_mapping(tone_mapping);
+            }
+            let image = renderer.render(scene).unwrap();
+            (image.get_pixel(4, 4)[0], image.get_pixel(0, 0)[0])
+        };
+
+        // Historically colors are lit as written: half of 128 is 64
+        let (sphere, background) = render(&scene(""));
+        assert_eq!((sphere, background), (64, 128));
+
+        // Decoded, halved in linear light and encoded again, the sphere is brighter
+        let srgb = scene(r#", "color_space": "srgb""#);
+        let (sphere, background) = render(&srgb);
+        assert!((92..=94).contains(&sphere), "{}", sphere);
+        assert!((127..=128).contains(&background), "{}", background);
+
+        // Linear output writes the decoded values unencoded
+        let linear = scene(r#", "color_space": "srgb", "tone_mapping": {"operator": "linear"}"#);
+        let (sphere, background) = render(&linear);
+        assert!((26..=28).contains(&sphere), "{}", sphere);
+        assert!((54..=55).contains(&background), "{}", background);
+    }
+
+    #[test]
+    fn test_render_settings_from_scene() {
+        let scene: Scene = serde_json::from_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+                "objects": [],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}},
+                "render_settings": {"anti_aliasing": "no-jitter", "samples": 3}
+            }"##,
+        )
+        .unwrap();
+        let settings = scene.render_settings.clone().unwrap();
+        assert_eq!(settings.anti_aliasing, Some(AntiAliasingMode::NoJitter));
+
+        let renderer = Renderer::new(8, 8).with_render_settings(&settings);
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::NoJitter);
+        assert_eq!(renderer.samples, 3);
+
+        // Missing fields keep the renderer's defaults
+        let renderer = Renderer::new(8, 8).with_render_settings(&RenderSettings::default());
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
+        assert_eq!(renderer.samples, 1);
+
+        for mode in [
+            AntiAliasingMode::Quincunx,
+            AntiAliasingMode::Stochastic,
+            AntiAliasingMode::NoJitter,
+        ] {
+            assert_eq!(mode.name().parse(), Ok(mode));
+        }
+        assert!("supersample".parse::<AntiAliasingMode>().is_err());
+    }
+
+    #[test]
+    fn test_triangle_quad_and_disc_primitives() {
+        let scene: Scene = serde_json::from_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 6, "height": 2},
+                "objects": [
+                    {"kind": "triangle", "vertices": [[-3, 0, -1], [-1, 0, -1], [-2, 0, 1]], "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}},
+                    {"kind": "quad", "vertices": [[-0.5, 0, -0.5], [0.5, 0, -0.5], [0.5, 0, 0.5], [-0.5, 0, 0.5]], "material": {"color": "#00FF00", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}},
+                    {"kind": "disc", "center": [0, 0, 0], "normal": [0, -1, 0], "radius": 0.5, "material": {"color": "#0000FF", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1},
+                     "transform": ["scale(1.8, 1.8, 1.8)", "translate(2, 0, 0)"]}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+            }"##,
+        )
+        .unwrap();
+
+        let (min, max) = scene.compute_finite_bounds().unwrap();
+        assert!((min.x + 3.0).abs() < 1e-10 && (max.x - 2.9).abs() < 1e-10);
+        assert!((min.z + 1.0).abs() < 1e-10 && (max.z - 1.0).abs() < 1e-10);
+
+        // Pixel columns 2, 6 and 10 of 13 look at x = -2, 0 and 2
+        let mut renderer = Renderer::new(13, 5);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let buffers = renderer.render_aovs(&scene, &[Aov::Albedo]).unwrap();
+        let albedo = |x: usize, y: usize| buffers[0].pixels[y * 13 + x];
+        assert_eq!(albedo(2, 2), [1.0, 0.0, 0.0]);
+        assert_eq!(albedo(6, 2), [0.0, 1.0, 0.0]);
+        assert_eq!(albedo(10, 2), [0.0, 0.0, 1.0]);
+        // Outside the quad and the disc's rim
+        assert_eq!(albedo(6, 0), [0.0; 3]);
+        assert_eq!(albedo(10, 0), [0.0; 3]);
+    }
+
+    #[test]
+    fn test_tube_primitive() {
+        let mut scene: Scene = serde_json::from_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 6, "height": 2},
+                "objects": [
+                    {"kind": "tube", "points": [[-2, 0, 0], [2, 0, 0]], "radius": 0.4, "transform": ["translate(0, 0, 0.5)"],
+                     "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+            }"##,
+        )
+        .unwrap();
+
+        let (min, max) = scene.compute_finite_bounds().unwrap();
+        assert!((min.x + 2.0).abs() < 1e-10 && (max.x - 2.0).abs() < 1e-10);
+        assert!((min.z - 0.1).abs() < 1e-10 && (max.z - 0.9).abs() < 1e-10);
+
+        // Pixel rows 0, 1 and 2 of 5 look above, through and below the tube
+        let mut renderer = Renderer::new(13, 5);
         renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
-        renderer.samples = 1;
-        let result = renderer.render(&scene);
-        assert!(result.is_ok());
+        let buffers = renderer.render_aovs(&scene, &[Aov::Albedo]).unwrap();
+        let albedo = |x: usize, y: usize| buffers[0].pixels[y * 13 + x];
+        assert_eq!(albedo(6, 0), [0.0; 3]);
+        assert_eq!(albedo(6, 1), [1.0, 0.0, 0.0]);
+        assert_eq!(albedo(6, 2), [0.0; 3]);
+        // Past its ends
+        assert_eq!(albedo(0, 1), [0.0; 3]);
+
+        if let Object::Tube { sides, .. } = &mut scene.objects[0] {
+            *sides = 2;
+        }
+        let error = renderer.render_aovs(&scene, &[Aov::Albedo]).unwrap_err();
+        assert!(error.to_string().contains("at least 3"), "{}", error);
+    }
 
-        // Test no-jitter mode with multiple samples (should still work)
-        renderer.samples = 4;
-        let result = renderer.render(&scene);
-        assert!(result.is_ok());
+    #[test]
+    fn test_arrow_primitive() {
+        let scene: Scene = serde_json::from_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 6, "height": 2},
+                "objects": [
+                    {"kind": "arrow", "from": [-2, 0, 0], "to": [2, 0, 0], "shaft_radius": 0.1, "head_size": 2,
+                     "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+            }"##,
+        )
+        .unwrap();
+
+        let (min, max) = scene.compute_finite_bounds().unwrap();
+        assert!((min.x + 2.0).abs() < 1e-10 && (max.x - 2.0).abs() < 1e-10);
+        assert!((min.z + 1.0).abs() < 1e-10 && (max.z - 1.0).abs() < 1e-10);
+
+        // Pixel row 1 of 5 passes over the shaft but through the head; row 2 runs
+        // along the axis to the tip
+        let mut renderer = Renderer::new(13, 5);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let buffers = renderer.render_aovs(&scene, &[Aov::Albedo]).unwrap();
+        let albedo = |x: usize, y: usize| buffers[0].pixels[y * 13 + x];
+        assert_eq!(albedo(4, 1), [0.0; 3]);
+        assert_eq!(albedo(4, 2), [1.0, 0.0, 0.0]);
+        assert_eq!(albedo(7, 1), [1.0, 0.0, 0.0]);
+        assert_eq!(albedo(11, 2), [0.0; 3]);
     }
 
     #[test]
-    fn test_quincunx_sampling() {
-        let mut scene = Scene::default();
+    fn test_glyph_colors() {
+        let mut scene: Scene = serde_json::from_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 6, "height": 2},
+                "objects": [
+                    {"kind": "glyphs", "filename": "points.csv", "glyph": "cube", "size": 1, "scalar": "t",
+                     "color_map": ["#FF0000", "#0000FF"],
+                     "material": {"color": "#00FF00", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+            }"##,
+        )
+        .unwrap();
+        let csv = b"x,y,z,t\n-2,0,0,0\n0,0,0,nan\n2,0,0,10\n";
+        let data = crate::glyphs::DataTable::from_bytes("points.csv", csv).unwrap();
+        if let Object::Glyphs { table, .. } = &mut scene.objects[0] {
+            *table = Some(std::sync::Arc::new(data));
+        }
 
-        // Add a simple sphere
-        scene.objects.push(Object::Sphere {
-            center: [0.0, 0.0, 0.0],
-            radius: 1.0,
-            material: Material::default(),
-            transform: None,
-        });
+        // Each cube takes its color from the ends of the map, or the material without
+        // a scalar
+        let mut renderer = Renderer::new(12, 4);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let buffers = renderer.render_aovs(&scene, &[Aov::Albedo]).unwrap();
+        let albedo = |x: usize, y: usize| buffers[0].pixels[y * 12 + x];
+        assert_eq!(albedo(2, 2), [1.0, 0.0, 0.0]);
+        assert_eq!(albedo(6, 2), [0.0, 1.0, 0.0]);
+        assert_eq!(albedo(10, 2), [0.0, 0.0, 1.0]);
+        assert_eq!(albedo(4, 2), [0.0; 3]);
+    }
 
-        // Add a light
-        scene.lights.push(Light {
-            position: [2.0, 2.0, 2.0],
-            color: "#FFFFFF".to_string(),
-            intensity: 1.0,
-            diameter: None,
-        });
+    #[test]
+    fn test_line_widths() {
+        let mut scene: Scene = serde_json::from_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 6, "height": 2},
+                "objects": [
+                    {"kind": "line", "points": [[-2, 0, 0.4], [2, 0, 0.4]], "width": 0.4,
+                     "material": {"color": "#FF0000", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}},
+                    {"kind": "line", "points": [[-2, 0, -0.6], [2, 0, -0.6]], "width": 2, "width_unit": "pixels",
+                     "material": {"color": "#00FF00", "ambient": 0.1, "diffuse": 0.7, "specular": 0, "shininess": 1}}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+            }"##,
+        )
+        .unwrap();
+        let (min, max) = scene.compute_finite_bounds().unwrap();
+        assert!((min.z + 0.6).abs() < 1e-10 && (max.z - 0.6).abs() < 1e-10);
+
+        let renderer = |full_detail: bool| {
+            let mut renderer = Renderer::new(60, 20);
+            renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+            renderer.full_detail = full_detail;
+            renderer
+        };
+        // Rows of the middle column showing each line
+        let rows = |renderer: &Renderer, scene: &Scene| {
+            let buffers = renderer.render_aovs(scene, &[Aov::Albedo]).unwrap();
+            let column: Vec<_> = (0..20).map(|y| buffers[0].pixels[y * 60 + 30]).collect();
+            let count = |color: [f32; 3]| column.iter().filter(|&&p| p == color).count();
+            (count([1.0, 0.0, 0.0]), count([0.0, 1.0, 0.0]))
+        };
+        assert_eq!(rows(&renderer(false), &scene), (4, 2));
 
-        // Test quincunx mode with default samples
-        let renderer = Renderer::new(50, 50);
-        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
-        assert_eq!(renderer.samples, 1);
-        let result = renderer.render(&scene);
-        assert!(result.is_ok());
+        // Zoomed out, the world-unit line narrows and the pixel one keeps its width
+        scene.camera.width = 12.0;
+        scene.camera.height = 4.0;
+        assert_eq!(rows(&renderer(false), &scene), (2, 2));
+        // Meshes drawn at full detail still leave the camera to size lines by
+        assert_eq!(rows(&renderer(true), &scene), (2, 2));
 
-        // Test quincunx mode with custom samples
-        let mut renderer2 = Renderer::new(50, 50);
-        renderer2.samples = 4;
-        let result = renderer2.render(&scene);
-        assert!(result.is_ok());
+        if let Object::Line { width, .. } = &mut scene.objects[0] {
+            *width = 0.0;
+        }
+        let error = (renderer(false).render_aovs(&scene, &[Aov::Albedo])).unwrap_err();
+        assert!(error.to_string().contains("greater than 0"), "{}", error);
     }
 
     #[test]
-    fn test_deterministic_rendering() {
-        let mut scene = Scene::default();
+    fn test_emissive_materials() {
+        let mut scene: Scene = serde_json::from_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, 0, 5], "target": [0, 0, 0], "up": [0, 1, 0], "width": 6.5, "height": 6.5},
+                "objects": [
+                    {"kind": "plane", "point": [0, 0, 0], "normal": [0, 0, 1], "material": {"color": "#FFFFFF", "ambient": 0, "diffuse": 1, "specular": 0, "shininess": 1}},
+                    {"kind": "quad", "vertices": [[-0.5, -0.5, 1], [0.5, -0.5, 1], [0.5, 0.5, 1], [-0.5, 0.5, 1]],
+                     "material": {"color": "#FFFFFF", "ambient": 0, "diffuse": 1, "specular": 0, "shininess": 1,
+                        "emission": {"color": "#FF8000", "group": "panel"}}},
+                    {"kind": "sphere", "center": [-2.5, -2.5, 0.5], "radius": 0.4,
+                     "material": {"color": "#FFFFFF", "ambient": 0, "diffuse": 1, "specular": 0, "shininess": 1,
+                        "emission": {"color": "#0000FF", "strength": 2}}}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0}}
+            }"##,
+        )
+        .unwrap();
+
+        // Pixels are half a unit across: (6, 6) looks at the origin
+        let mut renderer = Renderer::new(13, 13);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render_hdr(&scene).unwrap();
+        let pixel = |image: &Rgb32FImage, x: u32, y: u32| image.get_pixel(x, y).0.map(f64::from);
+
+        // The panel glows in its emission color, which doesn't depend on its own lighting
+        let panel = pixel(&image, 6, 6);
+        assert!((panel[0] - 1.0).abs() < 1e-6 && (panel[1] - 128.0 / 255.0).abs() < 1e-6);
+        // It lights the floor in its color, less further out as it turns edge-on
+        let (near, far) = (pixel(&image, 8, 6), pixel(&image, 11, 6));
+        assert!(near[0] > 2.0 * far[0] && far[0] > 0.0);
+        assert!((near[0] - 2.0 * near[1]).abs() < 0.01 && near[2] == 0.0);
+        // The sphere isn't made of triangles, so it glows without lighting anything
+        assert_eq!(pixel(&image, 1, 11)[2], 2.0);
+
+        let passes = renderer.render_light_groups(&scene).unwrap();
+        let names: Vec<&str> = passes.iter().map(|pass| pass.name.as_str()).collect();
+        assert_eq!(names, ["ambient", "panel", "default"]);
+
+        if let Object::Quad { material, .. } = &mut scene.objects[1] {
+            material.emission = None;
+        }
+        let image = renderer.render_hdr(&scene).unwrap();
+        assert_eq!(pixel(&image, 8, 6), [0.0; 3]);
+        assert_eq!(pixel(&image, 1, 11)[2], 2.0);
+    }
 
-        // Add a simple sphere
-        scene.objects.push(Object::Sphere {
-            center: [0.0, 0.0, 0.0],
-            radius: 1.0,
+    #[test]
+    fn test_instances_match_meshes() {
+        let block = crate::mesh::Mesh::placeholder_box();
+        let transforms = [
+            vec!["scale(2, 1, 1)", "rotate(10, 20, 30)", "translate(1.3, 0.2, 0.1)"],
+            vec!["rotate(0, 0, 45)", "translate(-2.2, 1.1, -0.3)"],
+        ];
+        let transform = |i: usize| Some(transforms[i].iter().map(|t| t.to_string()).collect());
+
+        let mut meshes = Scene::default();
+        let mut instances = Scene::default();
+        instances.meshes.insert(
+            "block".to_string(),
+            crate::scene::MeshPrototype {
+                filename: "block.stl".to_string(),
+                mesh_data: Some(Arc::new(block.clone())),
+            },
+        );
+        for i in 0..transforms.len() {
+            meshes.objects.push(Object::Mesh {
+                name: None,
+                filename: "block.stl".to_string(),
+                material: Material::default(),
+                face_materials: Vec::new(),
+                orient: None,
+                unit: None,
+                lods: Vec::new(),
+                transform: transform(i),
+                motion: None,
+                ignore_clip: None,
+                mesh_data: Some(block.clone()),
+            });
+            instances.objects.push(Object::Instance {
+                name: None,
+                reference: "block".to_string(),
+                material: Material::default(),
+                transform: transform(i),
+                motion: None,
+                ignore_clip: None,
+            });
+        }
+        for scene in [&mut meshes, &mut instances] {
+            scene.lights.push(Light {
+                position: [3.0, -5.0, 5.0],
+                color: "#FFFFFF".to_string(),
+                intensity: 1.0,
+                diameter: None,
+                shadow_samples: None,
+                attenuation: None,
+                emitter: None,
+                caustics: None,
+                group: None,
+                name: None,
+            });
+        }
+
+        let mut renderer = Renderer::new(24, 24);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let expected = renderer.render_hdr(&meshes).unwrap();
+        let actual = renderer.render_hdr(&instances).unwrap();
+        assert!(expected.pixels().any(|p| p[0] > 0.1));
+        for (a, b) in expected.pixels().zip(actual.pixels()) {
+            for channel in 0..3 {
+                assert!((a[channel] - b[channel]).abs() < 1e-4, "{:?} != {:?}", a, b);
+            }
+        }
+        assert_eq!(
+            instances.compute_finite_bounds().map(|(min, _)| (min.x * 1e6).round()),
+            meshes.compute_finite_bounds().map(|(min, _)| (min.x * 1e6).round())
+        );
+
+        instances.objects.push(Object::Instance {
+            name: None,
+            reference: "missing".to_string(),
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         });
+        let error = renderer.render_hdr(&instances).unwrap_err().to_string();
+        assert_eq!(error, "Instance refers to unknown mesh 'missing'");
+    }
 
-        // Add a diffuse light for area light sampling
-        scene.lights.push(Light {
-            position: [2.0, 2.0, 2.0],
-            color: "#FFFFFF".to_string(),
-            intensity: 1.0,
-            diameter: Some(0.5), // Area light to trigger stochastic sampling
-        });
-
-        // Create renderer with stochastic anti-aliasing and multiple samples
-        let mut renderer = Renderer::new(50, 50);
-        renderer.anti_aliasing_mode = AntiAliasingMode::Stochastic;
-        renderer.samples = 4;
-        renderer.seed = Some(42); // Fixed seed
-
-        // Render the same scene multiple times
-        let result1 = renderer.render(&scene).expect("First render failed");
-        let result2 = renderer.render(&scene).expect("Second render failed");
+    #[test]
+    fn test_outline_post_process() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8},
+            "objects": [
+                {"kind": "cube", "center": [0, 0, 0], "size": [4, 4, 4],
+                    "material": {"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [{"position": [0, 0, 10], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#FFFFFF"}
+        }"##,
+        )
+        .unwrap();
+        let renderer = Renderer::new(16, 16);
+        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
+        let plain = renderer.render(&scene).unwrap();
+        assert!(plain.pixels().all(|pixel| pixel.0 == [255, 255, 255]));
+
+        // Quincunx renders draw outlines too, from the pixel centers
+        let outlined = Renderer::new(16, 16).with_outline_detection(OutlineConfig::default());
+        let image = outlined.render(&scene).unwrap();
+        assert!(image.pixels().any(|pixel| pixel.0 == [0, 0, 0]));
+        assert_eq!(image.get_pixel(8, 8).0, [255, 255, 255]);
+
+        // The same outlines drawn afterwards onto the plain render
+        let buffers = renderer.render_outline_buffers(&scene).unwrap();
+        let config = OutlineConfig::default();
+        let mut post = plain.clone();
+        crate::outline::apply_outline_to_image(&mut post, &buffers, &config).unwrap();
+        assert_eq!(post, image);
+
+        let mask = crate::outline::edge_mask_image(&buffers, &config);
+        let outline = renderer.render_aovs(&scene, &[Aov::Outline]).unwrap();
+        assert_eq!(outline.len(), 1);
+        for (pixel, value) in mask.pixels().zip(&outline[0].pixels) {
+            assert_eq!(pixel.0[0], (value[0] * 255.0).round() as u8);
+        }
+        assert_eq!(mask.get_pixel(8, 8).0, [0]);
+        assert!(mask.pixels().any(|pixel| pixel.0 == [255]));
+    }
 
-        // Extract pixel data for comparison
-        let pixels1: Vec<_> = result1.pixels().collect();
-        let pixels2: Vec<_> = result2.pixels().collect();
+    #[test]
+    fn test_supersampled_outlines() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8},
+            "objects": [
+                {"kind": "sphere", "center": [0, 0, 0], "radius": 2.5,
+                    "material": {"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#FFFFFF",
+                "outline": {"enabled": true, "supersample": 3}}
+        }"##,
+        )
+        .unwrap();
+        let config = scene.get_outline_config().unwrap().unwrap();
+        assert_eq!(config.supersample, 3);
+        let renderer = Renderer::new(16, 16).with_outline_detection(config.clone());
+
+        // Buffers are traced at three times the resolution and the mask averaged back
+        // down, so the circle's edge is partly covered in many pixels
+        let buffers = renderer.render_outline_buffers(&scene).unwrap();
+        assert_eq!((buffers.width, buffers.height), (48, 48));
+        let mask = crate::outline::edge_mask_image(&buffers, &config);
+        assert_eq!(mask.dimensions(), (16, 16));
+        let partial = |mask: &image::GrayImage| {
+            mask.pixels()
+                .filter(|pixel| pixel[0] > 0 && pixel[0] < 255)
+                .count()
+        };
+        let plain = OutlineConfig::default();
+        let aliased = renderer
+            .render_outline_buffers_supersampled(&scene, 1)
+            .unwrap();
+        assert!(partial(&mask) > partial(&crate::outline::edge_mask_image(&aliased, &plain)));
+        let outline = renderer.render_aovs(&scene, &[Aov::Outline]).unwrap();
+        for (pixel, value) in mask.pixels().zip(&outline[0].pixels) {
+            assert_eq!(pixel[0], (value[0].clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
 
-        // Results should be byte-for-byte identical
-        assert_eq!(pixels1.len(), pixels2.len());
-        for (i, (&pixel1, &pixel2)) in pixels1.iter().zip(pixels2.iter()).enumerate() {
-            assert_eq!(
-                pixel1, pixel2,
-                "Pixel {} differs between renders: {:?} vs {:?}",
-                i, pixel1, pixel2
+        // Rendering draws the same outlines as the post process
+        let image = renderer.render(&scene).unwrap();
+        let mut post = Renderer::new(16, 16).render(&scene).unwrap();
+        crate::outline::apply_outline_to_image(&mut post, &buffers, &config).unwrap();
+        for (a, b) in image.pixels().zip(post.pixels()) {
+            assert!(
+                a.0.iter().zip(b.0).all(|(a, b)| a.abs_diff(b) <= 1),
+                "{a:?} != {b:?}"
             );
         }
+        assert!(crate::outline::apply_outline_to_image(&mut post, &aliased, &config).is_err());
     }
 
     #[test]
-    fn test_deterministic_rendering_with_threading() {
-        let mut scene = Scene::default();
+    fn test_object_outlines() {
+        let scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 100], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 16, "height": 8},
+            "objects": [
+                {"kind": "cube", "name": "part", "center": [-4, 0, 0], "size": [4, 4, 4],
+                    "material": {"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}},
+                {"kind": "cube", "name": "grid", "center": [4, 0, 0], "size": [4, 4, 4],
+                    "material": {"color": "#FFFFFF", "ambient": 1, "diffuse": 0, "specular": 0, "shininess": 1}}
+            ],
+            "lights": [{"position": [0, 0, 10], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#FFFFFF",
+                "outline": {"enabled": true, "objects": {"part": {"color": "#FF0000"}, "grid": {"enabled": false}}}}
+        }"##,
+        )
+        .unwrap();
+        let config = scene.get_outline_config().unwrap().unwrap();
+        let renderer = Renderer::new(32, 16).with_outline_detection(config.clone());
+        let image = renderer.render(&scene).unwrap();
+
+        // The part is outlined in red, the grid not at all
+        let left = |x: u32| x < 16;
+        assert!(image
+            .enumerate_pixels()
+            .any(|(x, _, pixel)| left(x) && pixel.0 == [255, 0, 0]));
+        assert!(image
+            .enumerate_pixels()
+            .all(|(x, _, pixel)| pixel.0 == [255, 255, 255] || left(x) && pixel.0 == [255, 0, 0]));
+
+        // As a post process too
+        let buffers = renderer.render_outline_buffers(&scene).unwrap();
+        let mask = crate::outline::edge_mask_image(&buffers, &config);
+        let mut edges = mask
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel.0 != [0]);
+        assert!(edges.next().is_some());
+        assert!(edges.all(|(x, ..)| left(x)));
+    }
 
-        // Add a simple sphere
+    #[test]
+    fn test_render_aovs() {
+        let mut scene = Scene::default();
         scene.objects.push(Object::Sphere {
+            name: None,
             center: [0.0, 0.0, 0.0],
-            radius: 1.0,
-            material: Material::default(),
+            radius: 2.0,
+            material: Material {
+                color: "#FF0000".to_string(),
+                reflectivity: Some(0.5),
+                ..Material::default()
+            },
             transform: None,
+            motion: None,
+            ignore_clip: None,
         });
-
-        // Add a diffuse light for area light sampling
         scene.lights.push(Light {
-            position: [2.0, 2.0, 2.0],
+            position: [0.0, -5.0, 5.0],
             color: "#FFFFFF".to_string(),
             intensity: 1.0,
-            diameter: Some(0.5), // Area light to trigger stochastic sampling
+            diameter: None,
+            shadow_samples: None,
+            attenuation: None,
+            emitter: None,
+            caustics: None,
+            group: None,
+            name: None,
         });
 
-        // Test with different thread counts to ensure thread scheduling doesn't affect results
-        let mut renderer1 = Renderer::new_with_threads(50, 50, 1);
-        renderer1.anti_aliasing_mode = AntiAliasingMode::Stochastic;
-        renderer1.samples = 4;
-        renderer1.seed = Some(42);
-
-        let mut renderer4 = Renderer::new_with_threads(50, 50, 4);
-        renderer4.anti_aliasing_mode = AntiAliasingMode::Stochastic;
-        renderer4.samples = 4;
-        renderer4.seed = Some(42);
-
-        // Render with different thread counts
-        let result1 = renderer1
-            .render(&scene)
-            .expect("Single-threaded render failed");
-        let result4 = renderer4
-            .render(&scene)
-            .expect("Multi-threaded render failed");
-
-        // Extract pixel data for comparison
-        let pixels1: Vec<_> = result1.pixels().collect();
-        let pixels4: Vec<_> = result4.pixels().collect();
-
-        // Results should be identical regardless of thread count
-        assert_eq!(pixels1.len(), pixels4.len());
-        for (i, (&pixel1, &pixel4)) in pixels1.iter().zip(pixels4.iter()).enumerate() {
-            assert_eq!(
-                pixel1, pixel4,
-                "Pixel {} differs between thread counts: {:?} vs {:?}",
-                i, pixel1, pixel4
-            );
+        let mut renderer = Renderer::new(9, 9);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let buffers = renderer.render_aovs(&scene, &Aov::ALL).unwrap();
+        let by_aov = |aov: Aov| &buffers.iter().find(|b| b.aov == aov).unwrap().pixels;
+
+        // The center pixel sees the sphere, the corner sees the background
+        let (center, corner) = (4 * 9 + 4, 0);
+        assert_eq!(by_aov(Aov::ObjectId)[center], [1.0; 3]);
+        assert_eq!(by_aov(Aov::ObjectId)[corner], [0.0; 3]);
+        assert_eq!(by_aov(Aov::Albedo)[center], [1.0, 0.0, 0.0]);
+        assert!((by_aov(Aov::Depth)[center][0] - 3.385).abs() < 0.01);
+        assert_eq!(by_aov(Aov::Depth)[corner], [0.0; 3]);
+        let normal = by_aov(Aov::Normal)[center];
+        assert!((normal[0].powi(2) + normal[1].powi(2) + normal[2].powi(2) - 1.0).abs() < 1e-5);
+
+        // Direct plus indirect reproduces the beauty pass at the pixel center
+        let hdr = renderer.render_hdr(&scene).unwrap();
+        let (direct, indirect) = (by_aov(Aov::Direct)[center], by_aov(Aov::Indirect)[center]);
+        for channel in 0..3 {
+            let sum = direct[channel] + indirect[channel];
+            assert!((sum - hdr.get_pixel(4, 4)[channel]).abs() < 1e-5);
         }
+
+        // render_to_file writes each AOV next to the image
+        let dir = std::env::temp_dir().join(format!("rtrace-aov-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("out.png");
+        let plain = renderer.settings_fingerprint();
+        let renderer = renderer.with_aovs(vec![Aov::Depth, Aov::ObjectId]);
+        renderer.render_to_file(&scene, output.to_str().unwrap()).unwrap();
+        assert!(dir.join("out.depth.png").is_file());
+        assert!(dir.join("out.object_id.png").is_file());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Extra passes and the way they're written are settings too
+        let with_aovs = renderer.settings_fingerprint();
+        assert_ne!(plain, with_aovs);
+        let colored = renderer.with_aov_false_color(FalseColor {
+            map: crate::colormap::ColorMap::Plasma,
+            range: None,
+        });
+        let with_false_color = colored.settings_fingerprint();
+        assert_ne!(with_aovs, with_false_color);
+        let grouped = colored.with_light_groups();
+        assert_ne!(with_false_color, grouped.settings_fingerprint());
     }
 
     #[test]
-    fn test_quincunx_deterministic() {
+    fn test_light_groups_sum_to_image() {
         let mut scene = Scene::default();
-
-        // Add a simple sphere
         scene.objects.push(Object::Sphere {
+            name: None,
             center: [0.0, 0.0, 0.0],
-            radius: 1.0,
+            radius: 2.0,
+            material: Material {
+                reflectivity: Some(0.3),
+                ..Material::default()
+            },
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+        scene.objects.push(Object::Plane {
+            name: None,
+            point: [0.0, 0.0, -2.0],
+            normal: [0.0, 0.0, 1.0],
+            width: None,
+            height: None,
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         });
-
-        // Add a diffuse light
-        scene.lights.push(Light {
-            position: [2.0, 2.0, 2.0],
-            color: "#FFFFFF".to_string(),
-            intensity: 1.0,
-            diameter: Some(0.5), // Area light to trigger stochastic sampling
+        for (position, diameter, group) in [
+            ([3.0, -5.0, 5.0], None, Some("key")),
+            ([-4.0, -2.0, 3.0], Some(0.5), Some("fill")),
+            ([0.0, 5.0, 5.0], None, None),
+        ] {
+            scene.lights.push(Light {
+                position,
+                color: "#FFEECC".to_string(),
+                intensity: 0.7,
+                diameter,
+                shadow_samples: None,
+                attenuation: None,
+                emitter: None,
+                caustics: None,
+                group: group.map(str::to_string),
+                name: None,
+            });
+        }
+        scene.scene_settings.background_color = Some("#336699".to_string());
+        scene.scene_settings.fog = Some(crate::scene::Fog {
+            color: "#808080".to_string(),
+            density: 0.3,
+            start: 2.0,
+            end: 12.0,
+            volumetric: None,
         });
 
-        // Test quincunx mode (which should also be deterministic)
-        let mut renderer = Renderer::new(50, 50);
-        assert_eq!(renderer.anti_aliasing_mode, AntiAliasingMode::Quincunx);
-        renderer.seed = Some(123);
-
-        let result1 = renderer
-            .render(&scene)
-            .expect("First quincunx render failed");
-        let result2 = renderer
-            .render(&scene)
-            .expect("Second quincunx render failed");
+        let mut renderer = Renderer::new(20, 16);
+        renderer.samples = 2;
+        renderer.anti_aliasing_mode = AntiAliasingMode::Stochastic;
+        // Passes add up with flat fog, and with fog lit by each group's lights
+        for volumetric in [None, serde_json::from_str(r#"{"steps": 8}"#).unwrap()] {
+            scene.scene_settings.fog.as_mut().unwrap().volumetric = volumetric;
+            let passes = renderer.render_light_groups(&scene).unwrap();
+            let names: Vec<&str> = passes.iter().map(|pass| pass.name.as_str()).collect();
+            assert_eq!(names, ["ambient", "key", "fill", "default"]);
+
+            let hdr = renderer.render_hdr(&scene).unwrap();
+            for (x, y, pixel) in hdr.enumerate_pixels() {
+                for channel in 0..3 {
+                    let sum: f32 = passes
+                        .iter()
+                        .map(|pass| pass.image.get_pixel(x, y)[channel])
+                        .sum();
+                    assert!((sum - pixel[channel]).abs() < 1e-4, "pixel {},{}", x, y);
+                }
+            }
+        }
 
-        // Extract pixel data for comparison
-        let pixels1: Vec<_> = result1.pixels().collect();
-        let pixels2: Vec<_> = result2.pixels().collect();
+        renderer = renderer.with_ambient_occlusion(4, 1.0);
+        assert!(renderer.render_light_groups(&scene).is_err());
+    }
 
-        // Results should be identical
-        assert_eq!(pixels1.len(), pixels2.len());
-        for (i, (&pixel1, &pixel2)) in pixels1.iter().zip(pixels2.iter()).enumerate() {
-            assert_eq!(
-                pixel1, pixel2,
-                "Quincunx pixel {} differs between renders: {:?} vs {:?}",
-                i, pixel1, pixel2
-            );
-        }
+    #[test]
+    fn test_progress_reporter() {
+        let scene = Scene::default();
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = reports.clone();
+        let mut renderer = Renderer::new(40, 40).with_progress(Arc::new(move |p: &Progress| {
+            sink.lock().unwrap().push(p.clone())
+        }));
+        renderer.tile_size = 16;
+        renderer.render(&scene).unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.first().unwrap().phase, RenderPhase::Setup);
+        assert_eq!(reports.last().unwrap().phase, RenderPhase::Finished);
+        let tracing: Vec<_> = reports
+            .iter()
+            .filter(|p| p.phase == RenderPhase::Tracing)
+            .collect();
+        assert_eq!(tracing.len(), 9);
+        assert!(tracing
+            .windows(2)
+            .all(|w| w[0].completed_pixels < w[1].completed_pixels));
+        assert_eq!(tracing.last().unwrap().completed_pixels, 1600);
+        assert_eq!(tracing.last().unwrap().percent(), 100.0);
     }
 
     #[test]
@@ -1223,10 +6225,13 @@ mod tests {
 
         // Add a simple sphere
         scene.objects.push(Object::Sphere {
+            name: None,
             center: [0.0, 0.0, 0.0],
             radius: 1.0,
             material: Material::default(),
             transform: None,
+            motion: None,
+            ignore_clip: None,
         });
 
         let mut renderer = Renderer::new(10, 10);
@@ -1240,18 +6245,214 @@ mod tests {
     }
 
     #[test]
-    fn test_format_duration() {
-        assert_eq!(format_duration(0.0), "0s");
-        assert_eq!(format_duration(-1.0), "0s");
-        assert_eq!(format_duration(5.0), "5s");
-        assert_eq!(format_duration(59.0), "59s");
-        assert_eq!(format_duration(60.0), "1m");
-        assert_eq!(format_duration(65.0), "1m5s");
-        assert_eq!(format_duration(125.0), "2m5s");
-        assert_eq!(format_duration(3600.0), "1h");
-        assert_eq!(format_duration(3665.0), "1h1m");
-        assert_eq!(format_duration(7200.0), "2h");
-        assert_eq!(format_duration(7325.0), "2h2m");
+    fn test_face_materials() {
+        // Two squares side by side, tagged with attribute words 5 and 9
+        let mut mesh = crate::mesh::Mesh::new();
+        for (x, attribute) in [(-1.0, 5), (1.0, 9)] {
+            let corner = |dx: f64, dy: f64| Point::new(x + dx, dy, 0.0);
+            for vertices in [
+                [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0)],
+                [corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)],
+            ] {
+                mesh.add_triangle(crate::mesh::Triangle {
+                    vertices,
+                    normal: Vec3::new(0.0, 0.0, 1.0),
+                });
+                mesh.attributes.push(attribute);
+            }
+        }
+        mesh.build_kdtree();
+
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 5], "target": [0, 0, 0], "up": [0, 1, 0], "width": 4, "height": 2},
+            "objects": [],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1.0}}
+        }"##,
+        )
+        .unwrap();
+        let flat = |color: &str| Material {
+            color: color.to_string(),
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Material::default()
+        };
+        let face_materials = serde_json::from_value(serde_json::json!([
+            {"attribute": 9, "material": flat("#FF0000")},
+            {"faces": [0, 0], "material": flat("#00FF00")}
+        ]))
+        .unwrap();
+        scene.objects.push(Object::Mesh {
+            name: None,
+            filename: "squares.stl".to_string(),
+            material: flat("#FFFFFF"),
+            face_materials,
+            orient: None,
+            unit: None,
+            lods: Vec::new(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+            mesh_data: Some(mesh),
+        });
+
+        let mut renderer = Renderer::new(40, 20);
+        renderer.anti_aliasing_mode = AntiAliasingMode::NoJitter;
+        let image = renderer.render(&scene).expect("Render failed");
+        let pixel = |x: u32, y: u32| image.get_pixel(x, y).0;
+        // The left square's lower-right triangle is picked by index, its other one
+        // keeps the mesh's material, and the right square is picked by attribute
+        assert_eq!(pixel(15, 15), [0, 255, 0]);
+        assert_eq!(pixel(5, 5), [255, 255, 255]);
+        assert_eq!(pixel(30, 10), [255, 0, 0]);
+
+        // Face materials still belong to the mesh in the object ID pass
+        let aovs = renderer
+            .render_aovs(&scene, &[Aov::ObjectId])
+            .expect("AOV render failed");
+        assert!(aovs[0].pixels.iter().all(|value| *value == [1.0; 3]));
+
+        // Ranges past the end of the mesh are an error
+        if let Object::Mesh { face_materials, .. } = &mut scene.objects[0] {
+            face_materials[1].faces = Some([2, 4]);
+        }
+        assert!(Renderer::new(4, 2).render(&scene).is_err());
+    }
+
+    #[test]
+    fn test_mesh_lods() {
+        let mut scene = Scene::from_json_str(
+            r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 5], "target": [0, 0, 0], "up": [0, 1, 0], "width": 4, "height": 2},
+            "objects": [],
+            "lights": [{"position": [2, 3, 5], "color": "#FFFFFF", "intensity": 1}],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.2}}
+        }"##,
+        )
+        .unwrap();
+        // A smaller box stands in for the level, so it shows which one was drawn
+        let mut level = crate::mesh::Mesh::placeholder_box();
+        level.scale(0.5);
+        let mesh = |mesh_data: crate::mesh::Mesh, lods: Vec<MeshLod>| Object::Mesh {
+            name: None,
+            filename: "box.stl".to_string(),
+            material: Material::default(),
+            face_materials: Vec::new(),
+            orient: None,
+            unit: None,
+            lods,
+            transform: Some(vec!["rotate(30, 20, 0)".to_string()]),
+            motion: None,
+            ignore_clip: None,
+            mesh_data: Some(mesh_data),
+        };
+        let lods = vec![MeshLod {
+            filename: "box-lod.stl".to_string(),
+            max_pixels: 20.0,
+            mesh_data: Some(level.clone()),
+        }];
+        scene.objects = vec![mesh(crate::mesh::Mesh::placeholder_box(), lods)];
+        let mut full = scene.clone();
+        full.objects = vec![mesh(crate::mesh::Mesh::placeholder_box(), Vec::new())];
+        let mut coarse = scene.clone();
+        coarse.objects = vec![mesh(level, Vec::new())];
+
+        // About 13 pixels across at 10 pixels a unit, the level is drawn; at 40 pixels a
+        // unit, or at full detail, the mesh is
+        let renderer = Renderer::new(40, 20);
+        let image = renderer.render(&scene).unwrap();
+        assert_eq!(image, renderer.render(&coarse).unwrap());
+        assert_ne!(image, renderer.render(&full).unwrap());
+        let renderer = Renderer::new(40, 20).with_full_detail();
+        assert_eq!(
+            renderer.render(&scene).unwrap(),
+            renderer.render(&full).unwrap()
+        );
+        let renderer = Renderer::new(160, 80);
+        assert_eq!(
+            renderer.render(&scene).unwrap(),
+            renderer.render(&full).unwrap()
+        );
+
+        // Prepared scenes pick levels for the scene's camera
+        let renderer = Renderer::new(40, 20);
+        let prepared = renderer.prepare(scene).unwrap();
+        let camera = CameraOverride::default();
+        assert_eq!(renderer.render_prepared(&prepared, &camera).unwrap(), image);
+    }
+
+    #[test]
+    fn test_renderer_limits() {
+        let mut mesh = crate::mesh::Mesh::new();
+        for _ in 0..3 {
+            mesh.add_triangle(crate::mesh::Triangle {
+                vertices: [
+                    Point::new(-1.0, -1.0, 0.0),
+                    Point::new(1.0, -1.0, 0.0),
+                    Point::new(0.0, 1.0, 0.0),
+                ],
+                normal: Vec3::new(0.0, 0.0, 1.0),
+            });
+        }
+        mesh.build_kdtree();
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Mesh {
+            name: None,
+            filename: "triangles.stl".to_string(),
+            material: Material::default(),
+            face_materials: Vec::new(),
+            orient: None,
+            unit: None,
+            lods: Vec::new(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+            mesh_data: Some(mesh),
+        });
+        scene.objects.push(Object::Sphere {
+            name: None,
+            center: [0.0, 0.0, 0.0],
+            radius: 1.0,
+            material: Material::default(),
+            transform: None,
+            motion: None,
+            ignore_clip: None,
+        });
+
+        let limits = RendererLimits {
+            max_pixels: Some(100),
+            max_objects: Some(2),
+            max_triangles: Some(3),
+            max_depth: Some(10),
+        };
+        assert!(Renderer::new(10, 10).with_limits(limits).render(&scene).is_ok());
+
+        let error = |renderer: Renderer| renderer.render(&scene).unwrap_err().to_string();
+        assert_eq!(
+            error(Renderer::new(11, 10).with_limits(limits)),
+            "Image is 11x10 = 110 pixels, above the limit of 100"
+        );
+        let mut deep = Renderer::new(10, 10).with_limits(limits);
+        deep.max_depth = 11;
+        assert_eq!(error(deep), "Max depth 11 is above the limit of 10");
+        let few_objects = RendererLimits {
+            max_objects: Some(1),
+            ..limits
+        };
+        assert_eq!(
+            error(Renderer::new(10, 10).with_limits(few_objects)),
+            "Scene has 2 objects, above the limit of 1"
+        );
+        let few_triangles = RendererLimits {
+            max_triangles: Some(2),
+            ..limits
+        };
+        assert_eq!(
+            error(Renderer::new(10, 10).with_limits(few_triangles)),
+            "Scene has 3 mesh triangles, above the limit of 2"
+        );
     }
 
     #[test]
@@ -1343,36 +6544,3 @@ endsolid test";
         );
     }
 }
-
-/// Format duration in seconds to a human-readable string (e.g., "3m45s", "1h23m", "45s")
-fn format_duration(seconds: f64) -> String {
-    if seconds < 0.0 {
-        return "0s".to_string();
-    }
-
-    let total_seconds = seconds.round() as u64;
-
-    if total_seconds == 0 {
-        return "0s".to_string();
-    }
-
-    let hours = total_seconds / 3600;
-    let minutes = (total_seconds % 3600) / 60;
-    let secs = total_seconds % 60;
-
-    if hours > 0 {
-        if minutes > 0 {
-            format!("{}h{}m", hours, minutes)
-        } else {
-            format!("{}h", hours)
-        }
-    } else if minutes > 0 {
-        if secs > 0 {
-            format!("{}m{}s", minutes, secs)
-        } else {
-            format!("{}m", minutes)
-        }
-    } else {
-        format!("{}s", secs)
-    }
-}
@@ -1,48 +1,84 @@
-use nalgebra::{Matrix4, Point3, Vector3};
+use nalgebra::{Matrix4, Vector3};
 use serde::{Deserialize, Serialize};
-use crate::ray::Cube;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::assets::{AssetOptions, MissingAsset, MissingAssetPolicy, PLACEHOLDER_COLOR};
+use crate::colormap::ColorMapSpec;
+use crate::core::volume::{DensityGrid, Volume};
+use crate::ray::{Cube, Disc, Ellipsoid, Plane};
 
-/// Color representation as RGB values (0.0-1.0)
-pub type Color = Vector3<f64>;
-
-/// 3D point
-pub type Point = Point3<f64>;
-
-/// 3D vector
-pub type Vec3 = Vector3<f64>;
+pub use crate::core::{Color, Point, Vec3};
 
 /// Convert hex color string to Color
+///
+/// Accepts `#RRGGBB`, or `#RRRRGGGGBBBB` with 16 bits per channel.
 pub fn hex_to_color(hex: &str) -> Result<Color, String> {
     let hex = hex.trim_start_matches('#');
-    if hex.len() != 6 {
+    // Non-ASCII input could put a char boundary inside one of the byte slices below
+    if (hex.len() != 6 && hex.len() != 12) || !hex.is_ascii() {
         return Err("Invalid hex color format".to_string());
     }
 
-    let r = u8::from_str_radix(&hex[0..2], 16).map_err(|_| "Invalid hex color")?;
-    let g = u8::from_str_radix(&hex[2..4], 16).map_err(|_| "Invalid hex color")?;
-    let b = u8::from_str_radix(&hex[4..6], 16).map_err(|_| "Invalid hex color")?;
+    let digits = hex.len() / 3;
+    let max = ((1u32 << (4 * digits)) - 1) as f64;
+    let channel = |i: usize| {
+        u16::from_str_radix(&hex[i * digits..(i + 1) * digits], 16)
+            .map(|value| value as f64 / max)
+            .map_err(|_| "Invalid hex color".to_string())
+    };
+
+    Ok(Color::new(channel(0)?, channel(1)?, channel(2)?))
+}
 
-    Ok(Color::new(
-        r as f64 / 255.0,
-        g as f64 / 255.0,
-        b as f64 / 255.0,
-    ))
+/// Replace an sRGB-encoded hex color with its linear value
+///
+/// Written back with 16 bits per channel, so dark shades keep their precision.
+pub(crate) fn decode_srgb_hex(hex: &mut String) -> Result<(), String> {
+    let color = hex_to_color(hex)?.map(crate::core::tonemap::srgb_to_linear);
+    let channel = |value: f64| (value * 65535.0).round() as u16;
+    *hex = format!(
+        "#{:04X}{:04X}{:04X}",
+        channel(color.x),
+        channel(color.y),
+        channel(color.z)
+    );
+    Ok(())
 }
 
 /// Camera configuration
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Camera {
-    pub kind: String, // "ortho" or "perspective"
+    pub kind: String, // "ortho", "perspective", "fisheye" or "equirect"
     pub position: [f64; 3],
     pub target: [f64; 3],
     pub up: [f64; 3],
     pub width: f64,
     pub height: f64,
-    pub fov: Option<f64>, // field of view in degrees for perspective cameras
+    pub fov: Option<f64>, // field of view in degrees for perspective and fisheye cameras
     // Grid background options for orthographic cameras
     pub grid_pitch: Option<f64>,     // Distance between grid lines
     pub grid_color: Option<String>,  // Hex color for grid lines
     pub grid_thickness: Option<f64>, // Thickness of grid lines
+    pub grid_reflections: Option<bool>, // Show the grid in reflections (default false)
+    // Motion blur: when objects move, rays are spread over this part of their motion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutter_open: Option<f64>, // default 0, where each motion starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutter_close: Option<f64>, // default 1, where each motion ends
+    // Ortho cameras only: center and size the viewport on the scene's finite bounds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fit: Option<bool>,
+}
+
+impl Camera {
+    /// Times the shutter opens and closes, on the 0 to 1 scale of object motion
+    pub fn shutter(&self) -> (f64, f64) {
+        (
+            self.shutter_open.unwrap_or(0.0),
+            self.shutter_close.unwrap_or(1.0),
+        )
+    }
 }
 
 impl Default for Camera {
@@ -58,6 +94,10 @@ impl Default for Camera {
             grid_pitch: None,
             grid_color: None,
             grid_thickness: None,
+            grid_reflections: None,
+            shutter_open: None,
+            shutter_close: None,
+            fit: None,
         }
     }
 }
@@ -72,6 +112,18 @@ pub struct Material {
     pub shininess: f64,
     pub reflectivity: Option<f64>,
     pub texture: Option<Texture>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bump: Option<Bump>, // height field that perturbs the shading normal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission: Option<Emission>, // light given off by the surface
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transparency: Option<f64>, // share of the light seen through the surface, refracted (glass)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refractive_index: Option<f64>, // bending of light through transparent materials (1.5 if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_catcher: Option<bool>, // show only the shadows cast onto the surface, over what is behind it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f64>, // share of the surface drawn over what is straight behind it (1 if unset)
 }
 
 impl Default for Material {
@@ -84,10 +136,44 @@ impl Default for Material {
             shininess: 32.0,
             reflectivity: None,
             texture: None,
+            bump: None,
+            emission: None,
+            transparency: None,
+            refractive_index: None,
+            shadow_catcher: None,
+            opacity: None,
+        }
+    }
+}
+
+impl Material {
+    /// Whether the surface only catches shadows, see `shadow_catcher`
+    pub fn is_shadow_catcher(&self) -> bool {
+        self.shadow_catcher.unwrap_or(false)
+    }
+
+    /// How much of the surface covers what is behind it, from 0 (unseen) to 1 (solid)
+    pub fn opacity(&self) -> f64 {
+        self.opacity.unwrap_or(1.0).clamp(0.0, 1.0)
+    }
+
+    /// Index of refraction of a transparent material, glass unless set
+    pub fn refractive_index(&self) -> f64 {
+        self.refractive_index.unwrap_or(DEFAULT_REFRACTIVE_INDEX)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let index = self.refractive_index();
+        if index.is_nan() || index <= 0.0 {
+            return Err("Material refractive_index must be positive".to_string());
         }
+        Ok(())
     }
 }
 
+/// Refractive index of transparent materials without one: that of common glass
+pub const DEFAULT_REFRACTIVE_INDEX: f64 = 1.5;
+
 /// Texture configuration
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(tag = "type")]
@@ -97,6 +183,8 @@ pub enum Texture {
         line_color: String, // hex color
         line_width: f64,
         cell_size: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fade_distance: Option<f64>, // lines fade out by this far from the texture origin
     },
     #[serde(rename = "checkerboard")]
     Checkerboard {
@@ -104,63 +192,298 @@ pub enum Texture {
     },
 }
 
+impl Material {
+    /// Decode every color of the material, and of those nested in its texture, from sRGB
+    pub(crate) fn decode_srgb(&mut self) -> Result<(), String> {
+        decode_srgb_hex(&mut self.color)?;
+        match &mut self.texture {
+            Some(Texture::Grid { line_color, .. }) => decode_srgb_hex(line_color)?,
+            Some(Texture::Checkerboard { material_b }) => material_b.decode_srgb()?,
+            None => {}
+        }
+        if let Some(emission) = &mut self.emission {
+            decode_srgb_hex(&mut emission.color)?;
+        }
+        Ok(())
+    }
+
+    /// This material's bump and the bumps of materials nested in its texture
+    fn bumps_mut(&mut self) -> Vec<&mut Bump> {
+        let mut bumps: Vec<&mut Bump> = self.bump.iter_mut().collect();
+        if let Some(Texture::Checkerboard { material_b }) = &mut self.texture {
+            bumps.extend(material_b.bumps_mut());
+        }
+        bumps
+    }
+
+    /// Height images of this material's bump and the bumps nested in its texture
+    pub fn bump_images(&self) -> Vec<&String> {
+        let mut images: Vec<&String> = self
+            .bump
+            .iter()
+            .filter_map(|bump| match bump {
+                Bump::Image { image, .. } => Some(image),
+                Bump::Noise { .. } => None,
+            })
+            .collect();
+        if let Some(Texture::Checkerboard { material_b }) = &self.texture {
+            images.extend(material_b.bump_images());
+        }
+        images
+    }
+
+    /// Give image bumps the height maps `loaded` holds for the same images
+    ///
+    /// Patching a loaded material through JSON drops its height maps; images the
+    /// loaded material doesn't use are an error, as they were never loaded.
+    pub(crate) fn reuse_height_maps(&mut self, mut loaded: Material) -> Result<(), String> {
+        let height_maps: Vec<_> = loaded
+            .bumps_mut()
+            .into_iter()
+            .filter_map(|bump| match bump {
+                Bump::Image {
+                    image, height_map, ..
+                } => Some((image.clone(), height_map.clone())),
+                Bump::Noise { .. } => None,
+            })
+            .collect();
+        for bump in self.bumps_mut() {
+            bump.validate()?;
+            if let Bump::Image {
+                image, height_map, ..
+            } = bump
+            {
+                *height_map = height_maps
+                    .iter()
+                    .find(|(loaded_image, _)| loaded_image == image)
+                    .ok_or_else(|| format!("bump image '{}' is not loaded by the scene", image))?
+                    .1
+                    .clone();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Height field that tilts the shading normal, for surface detail without extra geometry
+///
+/// Heights are measured in world space, so the same pattern continues across every
+/// surface of an object, including meshes, and `depth` is in scene units.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "type")]
+pub enum Bump {
+    #[serde(rename = "noise")]
+    Noise {
+        scale: f64, // size of the bumps in scene units
+        depth: f64, // height from the lowest to the highest point
+    },
+    #[serde(rename = "image")]
+    Image {
+        image: String, // grayscale height map, found like mesh files; white is high
+        size: f64,     // scene units covered by one repeat of the image
+        depth: f64,    // height from black to white
+        #[serde(skip)]
+        height_map: Option<Arc<crate::bump::HeightMap>>, // loaded image
+    },
+}
+
+/// Light given off by a surface, which glows and lights the rest of the scene
+///
+/// Triangles, quads, meshes and instances are sampled as area lights, each becoming
+/// a light with this color, strength, falloff and group. Other objects only glow.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Emission {
+    pub color: String, // hex color
+    #[serde(default = "default_emission_strength")]
+    pub strength: f64, // brightness of the surface, and the intensity of the light it casts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attenuation: Option<Attenuation>, // falloff with distance (none if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>, // light group for per-group output passes
+}
+
+fn default_emission_strength() -> f64 { 1.0 }
+
+/// Material for the triangles of a mesh picked by their STL attribute word or index
+///
+/// Set exactly one of `attribute` and `faces`. A triangle takes the material of the
+/// first entry that picks it, and keeps the mesh's own material if none do.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FaceMaterial {
+    /// Binary STL attribute word to match, such as a color written by the exporter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribute: Option<u16>,
+    /// First and last triangle index, counting from 0 in file order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faces: Option<[usize; 2]>,
+    pub material: Material,
+}
+
+impl FaceMaterial {
+    /// Index into `face_materials` of the entry each of `mesh`'s triangles uses
+    pub fn assign(
+        face_materials: &[FaceMaterial],
+        mesh: &crate::mesh::Mesh,
+    ) -> Result<Vec<Option<u16>>, String> {
+        if face_materials.len() > u16::MAX as usize {
+            return Err(format!(
+                "A mesh can have at most {} face materials",
+                u16::MAX
+            ));
+        }
+        let count = mesh.triangles.len();
+        for entry in face_materials {
+            match (entry.attribute, entry.faces) {
+                (Some(_), None) => {}
+                (None, Some([first, last])) if first <= last && last < count => {}
+                (None, Some([first, last])) => {
+                    return Err(format!(
+                        "Face range [{}, {}] is not within the mesh's {} triangles",
+                        first, last, count
+                    ))
+                }
+                _ => {
+                    return Err(
+                        "Face materials need exactly one of 'attribute' and 'faces'".to_string()
+                    )
+                }
+            }
+        }
+
+        // ASCII STL and placeholder meshes have no attribute words to match
+        let attributes = mesh
+            .attributes
+            .iter()
+            .map(Some)
+            .chain(std::iter::repeat(None));
+        Ok((0..count)
+            .zip(attributes)
+            .map(|(index, attribute)| {
+                let picks = |entry: &FaceMaterial| match (entry.attribute, entry.faces) {
+                    (Some(wanted), _) => attribute == Some(&wanted),
+                    (_, Some([first, last])) => (first..=last).contains(&index),
+                    _ => false,
+                };
+                face_materials
+                    .iter()
+                    .position(picks)
+                    .map(|entry| entry as u16)
+            })
+            .collect())
+    }
+}
+
+/// Lower-detail version of a mesh, drawn in its place when the mesh looks small
+///
+/// The file is loaded with the mesh's `unit` and `orient`, and must line up with the
+/// mesh's own file. Face materials pick from the level's own triangles.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MeshLod {
+    /// Path or URL of the STL file
+    pub filename: String,
+    /// Largest size on screen, in pixels across, the level is drawn at
+    pub max_pixels: f64,
+    #[serde(skip)]
+    pub mesh_data: Option<crate::mesh::Mesh>, // loaded mesh data
+}
+
 /// Transform operation
 #[derive(Debug, Clone)]
 pub enum Transform {
-    Rotate(f64, f64, f64),    // rotation in degrees around x, y, z axes
-    Translate(f64, f64, f64), // translation along x, y, z axes
-    Scale(f64, f64, f64),     // scaling along x, y, z axes
+    Rotate(f64, f64, f64),                // rotation in degrees around x, y, z axes
+    RotateAxis(f64, f64, f64, f64),       // rotation in degrees around the axis (x, y, z)
+    Translate(f64, f64, f64),             // translation along x, y, z axes
+    Scale(f64, f64, f64),                 // scaling along x, y, z axes
+    Matrix(Matrix4<f64>),                 // affine matrix given row by row
+    LookAt([f64; 3], [f64; 3], [f64; 3]), // from, target and up: moves to `from`, +Z toward `target`
 }
 
 impl Transform {
     /// Parse a transform string like "rotate(0, 0, 180)" or "translate(15, 0, 0)"
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Transform, String> {
         let s = s.trim();
-
-        if let Some(params) = s.strip_prefix("rotate(") {
-            let params = params
-                .strip_suffix(")")
-                .ok_or("Missing closing parenthesis in rotate transform")?;
-            let values: Result<Vec<f64>, _> =
-                params.split(',').map(|v| v.trim().parse::<f64>()).collect();
-            match values
-                .map_err(|e| format!("Invalid rotate parameters: {}", e))?
-                .as_slice()
-            {
-                [x, y, z] => Ok(Transform::Rotate(*x, *y, *z)),
-                _ => Err("Rotate transform requires exactly 3 parameters (x, y, z)".to_string()),
-            }
-        } else if let Some(params) = s.strip_prefix("translate(") {
-            let params = params
-                .strip_suffix(")")
-                .ok_or("Missing closing parenthesis in translate transform")?;
-            let values: Result<Vec<f64>, _> =
-                params.split(',').map(|v| v.trim().parse::<f64>()).collect();
-            match values
-                .map_err(|e| format!("Invalid translate parameters: {}", e))?
-                .as_slice()
-            {
-                [x, y, z] => Ok(Transform::Translate(*x, *y, *z)),
-                _ => Err("Translate transform requires exactly 3 parameters (x, y, z)".to_string()),
-            }
-        } else if let Some(params) = s.strip_prefix("scale(") {
-            let params = params
-                .strip_suffix(")")
-                .ok_or("Missing closing parenthesis in scale transform")?;
-            let values: Result<Vec<f64>, _> =
-                params.split(',').map(|v| v.trim().parse::<f64>()).collect();
-            match values
-                .map_err(|e| format!("Invalid scale parameters: {}", e))?
-                .as_slice()
-            {
-                [x, y, z] => Ok(Transform::Scale(*x, *y, *z)),
-                _ => Err("Scale transform requires exactly 3 parameters (x, y, z)".to_string()),
-            }
-        } else {
-            Err(format!(
-                "Unknown transform type. Expected rotate(), translate(), or scale(), got: {}",
+        let unknown = || {
+            format!(
+                "Unknown transform type. Expected rotate(), rotate_axis(), translate(), scale(), \
+                 matrix() or look_at(), got: {}",
                 s
-            ))
+            )
+        };
+
+        let (name, params) = s.split_once('(').ok_or_else(unknown)?;
+        let name = name.trim();
+        if ![
+            "rotate",
+            "rotate_axis",
+            "translate",
+            "scale",
+            "matrix",
+            "look_at",
+        ]
+        .contains(&name)
+        {
+            return Err(unknown());
+        }
+        let params = params
+            .strip_suffix(")")
+            .ok_or_else(|| format!("Missing closing parenthesis in {} transform", name))?;
+        let values: Vec<f64> = params
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid {} parameters: {}", name, e))?;
+
+        match (name, values.as_slice()) {
+            ("rotate", [x, y, z]) => Ok(Transform::Rotate(*x, *y, *z)),
+            ("rotate", _) => {
+                Err("Rotate transform requires exactly 3 parameters (x, y, z)".to_string())
+            }
+            ("rotate_axis", [x, y, z, degrees]) => {
+                if [x, y, z].iter().all(|v| **v == 0.0) {
+                    return Err("rotate_axis transform needs a non-zero axis".to_string());
+                }
+                Ok(Transform::RotateAxis(*x, *y, *z, *degrees))
+            }
+            ("rotate_axis", _) => Err(
+                "rotate_axis transform requires exactly 4 parameters (x, y, z, degrees)"
+                    .to_string(),
+            ),
+            ("translate", [x, y, z]) => Ok(Transform::Translate(*x, *y, *z)),
+            ("translate", _) => {
+                Err("Translate transform requires exactly 3 parameters (x, y, z)".to_string())
+            }
+            ("scale", [x, y, z]) => Ok(Transform::Scale(*x, *y, *z)),
+            ("scale", _) => {
+                Err("Scale transform requires exactly 3 parameters (x, y, z)".to_string())
+            }
+            ("matrix", values) if values.len() == 16 => {
+                if values[12..] != [0.0, 0.0, 0.0, 1.0] {
+                    return Err("matrix transform must end with the row 0, 0, 0, 1".to_string());
+                }
+                Ok(Transform::Matrix(Matrix4::from_row_slice(values)))
+            }
+            ("matrix", _) => {
+                Err("matrix transform requires exactly 16 parameters, row by row".to_string())
+            }
+            ("look_at", [fx, fy, fz, tx, ty, tz, up @ ..]) if up.is_empty() || up.len() == 3 => {
+                let up = match up {
+                    [ux, uy, uz] => [*ux, *uy, *uz],
+                    _ => [0.0, 0.0, 1.0],
+                };
+                if [fx, fy, fz] == [tx, ty, tz] {
+                    return Err(
+                        "look_at transform needs a target away from its position".to_string()
+                    );
+                }
+                Ok(Transform::LookAt([*fx, *fy, *fz], [*tx, *ty, *tz], up))
+            }
+            _ => Err(
+                "look_at transform requires 6 or 9 parameters (from, target and optional up)"
+                    .to_string(),
+            ),
         }
     }
 
@@ -181,12 +504,60 @@ impl Transform {
                 // Apply rotations in order: Z * Y * X (this is the common convention)
                 rz * ry * rx
             }
+            Transform::RotateAxis(x, y, z, degrees) => {
+                let axis = nalgebra::Unit::new_normalize(Vector3::new(*x, *y, *z));
+                Matrix4::from_axis_angle(&axis, degrees.to_radians())
+            }
             Transform::Translate(x, y, z) => Matrix4::new_translation(&Vector3::new(*x, *y, *z)),
             Transform::Scale(x, y, z) => Matrix4::new_nonuniform_scaling(&Vector3::new(*x, *y, *z)),
+            Transform::Matrix(matrix) => *matrix,
+            Transform::LookAt(from, target, up) => {
+                let from = Vector3::from(*from);
+                let forward = (Vector3::from(*target) - from).normalize();
+                // Without a usable up vector, fall back to +Y
+                let mut side = Vector3::from(*up).cross(&forward);
+                if side.norm() < 1e-9 {
+                    side = Vector3::y().cross(&forward);
+                }
+                if side.norm() < 1e-9 {
+                    side = Vector3::x().cross(&forward);
+                }
+                let side = side.normalize();
+                let up = forward.cross(&side);
+
+                let mut matrix = Matrix4::identity();
+                matrix.fixed_view_mut::<3, 1>(0, 0).copy_from(&side);
+                matrix.fixed_view_mut::<3, 1>(0, 1).copy_from(&up);
+                matrix.fixed_view_mut::<3, 1>(0, 2).copy_from(&forward);
+                matrix.fixed_view_mut::<3, 1>(0, 3).copy_from(&from);
+                matrix
+            }
         }
     }
-}
 
+    /// Matrix of this transform applied `amount` of the way from none at all, for motion
+    ///
+    /// Angles and offsets grow in proportion and scales ease from 1; matrices and
+    /// look_at placements are blended with the identity.
+    pub fn partial_matrix(&self, amount: f64) -> Matrix4<f64> {
+        let ease = |scale: f64| 1.0 + (scale - 1.0) * amount;
+        match *self {
+            Transform::Rotate(x, y, z) => {
+                Transform::Rotate(x * amount, y * amount, z * amount).to_matrix()
+            }
+            Transform::RotateAxis(x, y, z, degrees) => {
+                Transform::RotateAxis(x, y, z, degrees * amount).to_matrix()
+            }
+            Transform::Translate(x, y, z) => {
+                Transform::Translate(x * amount, y * amount, z * amount).to_matrix()
+            }
+            Transform::Scale(x, y, z) => Transform::Scale(ease(x), ease(y), ease(z)).to_matrix(),
+            Transform::Matrix(_) | Transform::LookAt(..) => {
+                Matrix4::identity() * (1.0 - amount) + self.to_matrix() * amount
+            }
+        }
+    }
+}
 /// Parse a list of transform strings and return the combined transformation matrix
 pub fn parse_transforms(transform_strings: &[String]) -> Result<Matrix4<f64>, String> {
     let mut combined_matrix = Matrix4::identity();
@@ -202,6 +573,175 @@ pub fn parse_transforms(transform_strings: &[String]) -> Result<Matrix4<f64>, St
     Ok(combined_matrix)
 }
 
+/// Parse an object's `motion` transforms, kept apart to be applied a share at a time
+pub fn parse_motion(transform_strings: &[String]) -> Result<Vec<Transform>, String> {
+    transform_strings
+        .iter()
+        .map(|transform| Transform::from_str(transform))
+        .collect()
+}
+
+/// Combined matrix of `motion` at `time`: each transform applied `time` of the way,
+/// in order, so the whole motion is reached at time 1
+pub fn motion_matrix(motion: &[Transform], time: f64) -> Matrix4<f64> {
+    let mut combined = Matrix4::identity();
+    for transform in motion {
+        combined = transform.partial_matrix(time) * combined;
+    }
+    combined
+}
+
+/// Axis-aligned box enclosing the box `(min, max)` after `matrix` is applied to its corners
+pub(crate) fn transform_bounds(
+    (min, max): (Point, Point),
+    matrix: &Matrix4<f64>,
+) -> (Point, Point) {
+    let mut new_min = Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let mut new_max = Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for corner in 0..8 {
+        let point = Point::new(
+            if corner & 1 == 0 { min.x } else { max.x },
+            if corner & 2 == 0 { min.y } else { max.y },
+            if corner & 4 == 0 { min.z } else { max.z },
+        );
+        let transformed = (matrix * point.to_homogeneous()).xyz();
+        new_min.coords = new_min.coords.inf(&transformed);
+        new_max.coords = new_max.coords.sup(&transformed);
+    }
+    (new_min, new_max)
+}
+
+/// Points of a triangle or quad after the object's transforms
+pub(crate) fn transform_points(points: &[[f64; 3]], transform: &Option<Vec<String>>) -> Vec<Point> {
+    let matrix = transform
+        .as_ref()
+        .and_then(|strings| parse_transforms(strings).ok());
+    points
+        .iter()
+        .map(|p| {
+            let point = Point::new(p[0], p[1], p[2]);
+            match matrix {
+                Some(matrix) => {
+                    Point::from_homogeneous(matrix * point.to_homogeneous()).unwrap_or(point)
+                }
+                None => point,
+            }
+        })
+        .collect()
+}
+
+/// Disc after the object's transforms; like a sphere, the radius grows with the largest scale
+pub(crate) fn transform_disc(
+    center: &[f64; 3],
+    normal: &[f64; 3],
+    radius: f64,
+    transform: &Option<Vec<String>>,
+    material_color: Color,
+    material_index: usize,
+) -> Disc {
+    let mut center = Point::new(center[0], center[1], center[2]);
+    let mut normal = Vec3::new(normal[0], normal[1], normal[2]);
+    let mut radius = radius;
+    if let Some(matrix) = transform
+        .as_ref()
+        .and_then(|strings| parse_transforms(strings).ok())
+    {
+        center = Point::from_homogeneous(matrix * center.to_homogeneous()).unwrap_or(center);
+        // Normals transform by the inverse transpose
+        if let Some(inverse) = matrix.try_inverse() {
+            normal = (inverse.transpose() * normal.to_homogeneous()).xyz();
+        }
+        let max_scale = (0..3)
+            .map(|axis| matrix.column(axis).xyz().magnitude())
+            .fold(0.0, f64::max);
+        radius *= max_scale;
+    }
+    Disc {
+        center,
+        normal: nalgebra::Unit::new_normalize(normal),
+        radius,
+        material_color,
+        material_index,
+    }
+}
+
+/// Points of a line after the object's transforms, with the half width of a line
+/// `width` scene units wide, grown by the largest scale factor as a disc's radius is
+pub(crate) fn transform_line(
+    points: &[[f64; 3]],
+    width: f64,
+    transform: &Option<Vec<String>>,
+) -> (Vec<Point>, f64) {
+    let max_scale = match transform
+        .as_ref()
+        .and_then(|strings| parse_transforms(strings).ok())
+    {
+        Some(matrix) => (0..3)
+            .map(|axis| matrix.column(axis).xyz().magnitude())
+            .fold(0.0, f64::max),
+        None => 1.0,
+    };
+    (transform_points(points, transform), width * max_scale / 2.0)
+}
+
+/// Plane after the object's transforms, limited to `width` x `height` if given
+pub(crate) fn transform_plane(
+    point: &[f64; 3],
+    normal: &[f64; 3],
+    width: Option<f64>,
+    height: Option<f64>,
+    transform: &Option<Vec<String>>,
+    material_color: Color,
+    material_index: usize,
+) -> Result<Plane, String> {
+    if !width.iter().chain(&height).all(|size| *size > 0.0) {
+        return Err("Plane width and height must be positive".to_string());
+    }
+    let point = Point::new(point[0], point[1], point[2]);
+    let normal = Vec3::new(normal[0], normal[1], normal[2]);
+    let plane = match transform
+        .as_ref()
+        .and_then(|strings| parse_transforms(strings).ok())
+    {
+        Some(matrix) => {
+            Plane::new_with_transform(point, normal, &matrix, material_color, material_index)
+                .ok_or("Plane has a transform that can't be inverted")?
+        }
+        None => Plane::new(point, normal, material_color, material_index),
+    };
+    Ok(plane.with_size(width, height))
+}
+
+/// Material of a floor grid: `material` with the grid's lines as its texture
+pub(crate) fn floor_grid_material(
+    material: &Material,
+    pitch: f64,
+    line_width: f64,
+    line_color: &str,
+    fade_distance: Option<f64>,
+) -> Result<Material, String> {
+    if !(pitch.is_finite() && pitch > 0.0) {
+        return Err("Floor grid pitch must be positive".to_string());
+    }
+    if !(line_width.is_finite() && line_width >= 0.0) {
+        return Err("Floor grid line_width must be a finite number of at least 0".to_string());
+    }
+    if fade_distance.is_some_and(|fade| fade.is_nan() || fade <= 0.0) {
+        return Err("Floor grid fade_distance must be positive".to_string());
+    }
+    hex_to_color(line_color)?;
+    Ok(Material {
+        texture: Some(Texture::Grid {
+            line_color: line_color.to_string(),
+            // Grid textures take the line width as a share of the cell
+            line_width: line_width / pitch,
+            cell_size: pitch,
+            fade_distance,
+        }),
+        ..material.clone()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -256,6 +796,43 @@ mod tests {
         assert_eq!(matrix, expected);
     }
 
+    #[test]
+    fn test_axis_matrix_and_look_at_transforms() {
+        let apply = |transform: &str, point: [f64; 3]| {
+            let matrix = parse_transforms(&[transform.to_string()]).unwrap();
+            matrix.transform_point(&Point::from(point))
+        };
+        let close = |a: Point, b: [f64; 3]| (a - Point::from(b)).norm() < 1e-10;
+
+        // Around a coordinate axis, rotate_axis matches rotate
+        let axis = parse_transforms(&["rotate_axis(0, 0, 2, 90)".to_string()]).unwrap();
+        let euler = parse_transforms(&["rotate(0, 0, 90)".to_string()]).unwrap();
+        assert!((axis - euler).norm() < 1e-10);
+        // A third of a turn around the diagonal cycles the axes
+        let diagonal = "rotate_axis(1, 1, 1, 120)";
+        assert!(close(apply(diagonal, [1.0, 0.0, 0.0]), [0.0, 1.0, 0.0]));
+
+        // Matrices are written row by row, translation in the last column
+        let matrix = "matrix(0, -1, 0, 5,  1, 0, 0, 0,  0, 0, 2, 0,  0, 0, 0, 1)";
+        assert!(close(apply(matrix, [1.0, 0.0, 1.0]), [5.0, 1.0, 2.0]));
+
+        // look_at moves the origin to `from` and turns +Z toward the target, +Y toward up
+        let look = "look_at(1, 2, 3, 1, 7, 3)";
+        assert!(close(apply(look, [0.0, 0.0, 0.0]), [1.0, 2.0, 3.0]));
+        assert!(close(apply(look, [0.0, 0.0, 1.0]), [1.0, 3.0, 3.0]));
+        assert!(close(apply(look, [0.0, 1.0, 0.0]), [1.0, 2.0, 4.0]));
+        // Looking straight along the up vector still gives a valid rotation
+        let matrix = parse_transforms(&["look_at(0, 0, 0, 0, 0, -4)".to_string()]).unwrap();
+        assert!((matrix.fixed_view::<3, 3>(0, 0).determinant() - 1.0).abs() < 1e-10);
+
+        assert!(Transform::from_str("rotate_axis(0, 0, 0, 45)").is_err());
+        assert!(Transform::from_str("rotate_axis(0, 0, 1)").is_err());
+        assert!(Transform::from_str("matrix(1, 0, 0, 0)").is_err());
+        assert!(Transform::from_str("matrix(1,0,0,0, 0,1,0,0, 0,0,1,0, 1,0,0,1)").is_err());
+        assert!(Transform::from_str("look_at(1, 1, 1, 1, 1, 1)").is_err());
+        assert!(Transform::from_str("look_at(0, 0, 0, 1, 0, 0, 1)").is_err());
+    }
+
     #[test]
     fn test_transform_error_handling() {
         // Test invalid format
@@ -271,6 +848,13 @@ mod tests {
         assert!(Transform::from_str("rotate(1, 2)").is_err());
     }
 
+    #[test]
+    fn test_hex_to_color_rejects_non_ascii() {
+        // Six bytes, but "é" straddles the first two-byte slice
+        assert!(hex_to_color("#aé0ff").is_err());
+        assert!(hex_to_color("#FF8000").is_ok());
+    }
+
     #[test]
     fn test_complete_transform_scenario() {
         // Test the exact scenario from the issue
@@ -306,6 +890,390 @@ mod tests {
             result_point.z
         );
     }
+
+    #[test]
+    fn test_group_expansion() {
+        let material = r##""material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 10}"##;
+        let scene = Scene::from_json_str(&format!(
+            r##"{{
+                "camera": {{"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4}},
+                "objects": [
+                    {{"kind": "group", "name": "outer", "transform": ["translate(10, 0, 0)"], "objects": [
+                        {{"kind": "sphere", "center": [0, 0, 0], "radius": 1, {m}, "transform": ["scale(2, 2, 2)"]}},
+                        {{"kind": "group", "transform": ["translate(0, 0, 5)"], "objects": [
+                            {{"kind": "sphere", "center": [0, 0, 0], "radius": 1, {m}}}
+                        ]}}
+                    ]}},
+                    {{"kind": "sphere", "center": [0, 0, 0], "radius": 1, {m}}}
+                ],
+                "lights": [],
+                "scene_settings": {{"ambient_illumination": {{"color": "#FFFFFF", "intensity": 0.1}}}}
+            }}"##,
+            m = material
+        ))
+        .unwrap();
+
+        // Child transforms apply first, then each enclosing group's, innermost outward
+        let transforms: Vec<_> = scene
+            .objects
+            .iter()
+            .map(|object| match object {
+                Object::Sphere { transform, .. } => transform.clone(),
+                other => panic!("unexpected object {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            transforms,
+            [
+                Some(vec![
+                    "scale(2, 2, 2)".to_string(),
+                    "translate(10, 0, 0)".to_string()
+                ]),
+                Some(vec![
+                    "translate(0, 0, 5)".to_string(),
+                    "translate(10, 0, 0)".to_string()
+                ]),
+                None,
+            ]
+        );
+        let (min, max) = scene.compute_finite_bounds().unwrap();
+        assert_eq!(min, Point::new(-1.0, -2.0, -2.0));
+        assert_eq!(max, Point::new(12.0, 2.0, 6.0));
+
+        // A group turns cubes about the group origin, not their own centers
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Group {
+            name: None,
+            transform: Some(vec!["rotate(0, 0, 90)".to_string()]),
+            motion: None,
+            ignore_clip: None,
+            objects: vec![Object::Cube {
+                name: None,
+                center: [2.0, 0.0, 0.0],
+                size: [2.0, 2.0, 2.0],
+                material: Material::default(),
+                transform: None,
+                motion: None,
+                ignore_clip: None,
+            }],
+        });
+        scene.expand_groups().unwrap();
+        let (min, max) = scene.compute_finite_bounds().unwrap();
+        assert!((min - Point::new(-1.0, 1.0, -1.0)).norm() < 1e-9, "{}", min);
+        assert!((max - Point::new(1.0, 3.0, 1.0)).norm() < 1e-9, "{}", max);
+
+        let mut scene = Scene::default();
+        scene.objects.push(Object::Group {
+            name: Some("broken".to_string()),
+            transform: Some(vec!["spin(1, 2, 3)".to_string()]),
+            motion: None,
+            ignore_clip: None,
+            objects: Vec::new(),
+        });
+        let error = scene.expand_groups().unwrap_err();
+        assert!(error.starts_with("Group 'broken'"), "{}", error);
+    }
+
+    #[test]
+    fn test_motion() {
+        // Each motion transform is applied its share of the way, in order
+        let motion = parse_motion(&[
+            "rotate(0, 0, 90)".to_string(),
+            "translate(2, 0, 0)".to_string(),
+        ])
+        .unwrap();
+        let point = Point::new(1.0, 0.0, 0.0);
+        assert_eq!(motion_matrix(&motion, 0.0).transform_point(&point), point);
+        let halfway = motion_matrix(&motion, 0.5).transform_point(&point);
+        let expected = Point::new(1.0 + 0.5f64.sqrt(), 0.5f64.sqrt(), 0.0);
+        assert!((halfway - expected).norm() < 1e-12, "{}", halfway);
+        let scale = Transform::from_str("scale(3, 1, 1)").unwrap();
+        let matrix = scale.partial_matrix(0.5);
+        assert!((matrix.transform_point(&point) - Point::new(2.0, 0.0, 0.0)).norm() < 1e-12);
+
+        // A group's motion follows that of each child
+        let scene = Scene::from_json_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+                "objects": [
+                    {"kind": "group", "motion": ["rotate(0, 0, 30)"], "objects": [
+                        {"kind": "sphere", "center": [1, 0, 0], "radius": 0.5, "motion": ["translate(0, 0, 1)"],
+                         "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 16}}
+                    ]}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}}
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(
+            scene.objects[0].motion().unwrap(),
+            ["translate(0, 0, 1)", "rotate(0, 0, 30)"]
+        );
+    }
+
+    #[test]
+    fn test_scene_parameters() {
+        let scene = Scene::from_json_str(
+            r##"{
+                "parameters": {"size": 2, "lift": "=size / 2 + 0.25"},
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": "=size * 4", "height": 4},
+                "objects": [
+                    {"kind": "sphere", "center": [0, 0, "=lift"], "radius": "=size / 2",
+                     "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": "=2^4"}}
+                ],
+                "lights": [],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 0.1}},
+                "render_settings": {"samples": "=size * 2"}
+            }"##,
+        )
+        .unwrap();
+        assert_eq!(scene.camera.width, 8.0);
+        assert_eq!(scene.render_settings.unwrap().samples, Some(4));
+        let Object::Sphere {
+            center,
+            radius,
+            material,
+            ..
+        } = &scene.objects[0]
+        else {
+            panic!("expected a sphere");
+        };
+        assert_eq!(*center, [0.0, 0.0, 1.25]);
+        assert_eq!(*radius, 1.0);
+        assert_eq!(material.shininess, 16.0);
+    }
+
+    #[test]
+    fn test_mesh_units() {
+        let dir = std::env::temp_dir().join(format!("rtrace-unit-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // 2 units across: a 2 inch part, or an implausible 2 mm one
+        let stl = "solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 2 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        std::fs::write(dir.join("part.stl"), stl).unwrap();
+        let scene_path = dir.join("scene.json");
+
+        let mut scene = Scene::default();
+        for unit in [
+            None,
+            Some(MeshUnit::Mm),
+            Some(MeshUnit::In),
+            Some(MeshUnit::Auto),
+        ] {
+            scene.objects.push(Object::Mesh {
+                name: None,
+                filename: "part.stl".to_string(),
+                material: Material::default(),
+                face_materials: Vec::new(),
+                orient: None,
+                unit,
+                lods: Vec::new(),
+                transform: None,
+                motion: None,
+                ignore_clip: None,
+                mesh_data: None,
+            });
+        }
+        scene
+            .load_mesh_data_with_assets(scene_path.to_str(), &AssetOptions::default())
+            .unwrap();
+        let widths: Vec<f64> = scene
+            .objects
+            .iter()
+            .map(|object| match object {
+                Object::Mesh {
+                    mesh_data: Some(mesh),
+                    ..
+                } => mesh.bounds().1.x - mesh.bounds().0.x,
+                _ => panic!("expected a loaded mesh"),
+            })
+            .collect();
+        assert_eq!(widths, [2.0, 2.0, 50.8, 50.8]);
+        assert_eq!(
+            scene.inferred_units,
+            [InferredUnit {
+                filename: "part.stl".to_string(),
+                extent: 2.0,
+                unit: MeshUnit::In,
+            }]
+        );
+        assert_eq!(MeshUnit::infer(120.0), Some(MeshUnit::Mm));
+
+        // Empty and degenerate meshes get no unit rather than a guess from a NaN extent
+        for extent in [f64::NAN, f64::NEG_INFINITY, 0.0] {
+            assert_eq!(MeshUnit::infer(extent), None);
+        }
+        // Binary STL: an 80 byte header and a count of no triangles
+        std::fs::write(dir.join("empty.stl"), [0u8; 84]).unwrap();
+        scene.objects.truncate(1);
+        if let Object::Mesh { filename, unit, .. } = &mut scene.objects[0] {
+            *filename = "empty.stl".to_string();
+            *unit = Some(MeshUnit::Auto);
+        }
+        scene
+            .load_mesh_data_with_assets(scene_path.to_str(), &AssetOptions::default())
+            .unwrap();
+        assert!(scene.inferred_units.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_object_outline_config() {
+        let json = r##"{
+            "camera": {"kind": "ortho", "position": [0, 0, 10], "target": [0, 0, 0], "up": [0, 1, 0],
+                "width": 8, "height": 8},
+            "objects": [
+                {"kind": "sphere", "center": [0, 0, 0], "radius": 1, "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 8}},
+                {"kind": "sphere", "name": "grid", "center": [3, 0, 0], "radius": 1, "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 8}},
+                {"kind": "sphere", "name": "part", "center": [-3, 0, 0], "radius": 1, "material": {"color": "#FFFFFF", "ambient": 0.1, "diffuse": 0.7, "specular": 0.2, "shininess": 8}}
+            ],
+            "lights": [],
+            "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#FFFFFF",
+                "outline": {"enabled": true, "color": "#0000FF",
+                "objects": {"grid": {"enabled": false}, "part": {"color": "#FF0000"}}}}
+        }"##;
+        let mut scene = Scene::from_json_str(json).unwrap();
+        let config = scene.get_outline_config().unwrap().unwrap();
+        let (blue, red) = (Color::new(0.0, 0.0, 1.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(config.edge_color_of(None), Some(blue));
+        assert_eq!(config.edge_color_of(Some(0)), Some(blue));
+        assert_eq!(config.edge_color_of(Some(1)), None);
+        assert_eq!(config.edge_color_of(Some(2)), Some(red));
+
+        let outline = scene.scene_settings.outline.as_mut().unwrap();
+        let grid = outline.objects.remove("grid").unwrap();
+        outline.objects.insert("floor".to_string(), grid);
+        let error = scene.get_outline_config().unwrap_err();
+        assert!(error.contains("'floor'"), "{}", error);
+
+        let outline = scene.scene_settings.outline.as_mut().unwrap();
+        outline.objects.clear();
+        outline.crease_angle = Some(0.0);
+        assert!(scene.get_outline_config().is_err());
+        let outline = scene.scene_settings.outline.as_mut().unwrap();
+        outline.crease_angle = None;
+        outline.supersample = 5;
+        assert!(scene.get_outline_config().is_err());
+    }
+}
+
+/// How a mesh is turned before its `transform` applies
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MeshOrient {
+    /// Rest on the largest flat face, with the lowest point on the z = 0 ground plane
+    Auto,
+}
+
+/// Length unit a mesh file was modelled in; scene units are millimeters
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MeshUnit {
+    Mm,
+    In,
+    /// Inches for meshes under `INCH_MESH_EXTENT` across, which would be tiny parts in
+    /// millimeters, and millimeters otherwise
+    Auto,
+}
+
+/// Largest extent, in file units, of a mesh `unit: "auto"` takes to be in inches
+pub const INCH_MESH_EXTENT: f64 = 10.0;
+
+impl MeshUnit {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MeshUnit::Mm => "mm",
+            MeshUnit::In => "in",
+            MeshUnit::Auto => "auto",
+        }
+    }
+
+    /// Millimeters in one unit (None for `Auto`, which depends on the mesh)
+    pub fn millimeters(&self) -> Option<f64> {
+        match self {
+            MeshUnit::Mm => Some(1.0),
+            MeshUnit::In => Some(25.4),
+            MeshUnit::Auto => None,
+        }
+    }
+
+    /// Most plausible unit for a mesh `extent` file units across at its widest
+    ///
+    /// None for empty or degenerate meshes, whose extent is not a positive size.
+    pub fn infer(extent: f64) -> Option<MeshUnit> {
+        if !(extent.is_finite() && extent > 0.0) {
+            None
+        } else if extent < INCH_MESH_EXTENT {
+            Some(MeshUnit::In)
+        } else {
+            Some(MeshUnit::Mm)
+        }
+    }
+}
+
+/// Unit picked for a mesh with `unit: "auto"`, kept so it can be reported
+#[derive(Debug, Clone, PartialEq)]
+pub struct InferredUnit {
+    /// File name or URL as written in the scene
+    pub filename: String,
+    /// Largest side of the mesh's bounding box, in file units
+    pub extent: f64,
+    pub unit: MeshUnit,
+}
+
+impl std::fmt::Display for InferredUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unit = match self.unit {
+            MeshUnit::In => "inches",
+            _ => "millimeters",
+        };
+        let millimeters = self.unit.millimeters().unwrap_or(1.0);
+        write!(
+            f,
+            "mesh '{}' is {:.2} units across; assuming {} ({:.1} mm), set \"unit\" to choose",
+            self.filename,
+            self.extent,
+            unit,
+            self.extent * millimeters
+        )
+    }
+}
+
+/// How a tube's `points` make its centerline
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TubeCurve {
+    /// Straight runs from point to point
+    #[default]
+    Polyline,
+    /// Chained cubic Bezier segments: an end point, then two control points and the
+    /// next end point for each segment
+    Bezier,
+}
+
+/// What a line's `width` is measured in
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LineWidthUnit {
+    /// Scene units, so the line narrows with distance like any object
+    #[default]
+    World,
+    /// Pixels of the rendered image, the same however far the line is from the camera
+    Pixels,
+}
+
+/// Glyph drawn at each point of a glyph field
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GlyphShape {
+    /// Sphere `size` across
+    #[default]
+    Sphere,
+    /// Axis-aligned cube `size` on a side
+    Cube,
+    /// Arrow from the point along its vector, with a head `size` long and across
+    Arrow,
 }
 
 /// Object types in the scene
@@ -314,67 +1282,969 @@ mod tests {
 pub enum Object {
     #[serde(rename = "sphere")]
     Sphere {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
         center: [f64; 3],
         radius: f64,
         material: Material,
         transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
     },
     #[serde(rename = "plane")]
     Plane {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
         point: [f64; 3],
         normal: [f64; 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<f64>, // finite extent along the plane's first axis; infinite if None
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<f64>, // finite extent along the plane's second axis
         material: Material,
         transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    /// Plane carrying a grid of lines, traced like any other surface
+    #[serde(rename = "floor_grid")]
+    FloorGrid {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        #[serde(default)]
+        point: [f64; 3], // grid origin, where lines cross and fading starts
+        #[serde(default = "default_floor_grid_normal")]
+        normal: [f64; 3],
+        #[serde(default = "default_floor_grid_pitch")]
+        pitch: f64, // distance between lines
+        #[serde(default = "default_floor_grid_line_width")]
+        line_width: f64, // in world units
+        #[serde(default = "default_floor_grid_line_color")]
+        line_color: String, // hex color
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        extent: Option<f64>, // side of the square grid; infinite if None
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fade_distance: Option<f64>, // lines fade out by this far from `point`
+        #[serde(default = "default_floor_grid_material")]
+        material: Material, // floor between the lines
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
     },
     #[serde(rename = "cube")]
     Cube {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
         center: [f64; 3],
         size: [f64; 3], // width, height, depth
         material: Material,
         transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    #[serde(rename = "triangle")]
+    Triangle {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        vertices: [[f64; 3]; 3],
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    #[serde(rename = "quad")]
+    Quad {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        vertices: [[f64; 3]; 4], // corners in order around the edge
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    #[serde(rename = "disc")]
+    Disc {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        center: [f64; 3],
+        normal: [f64; 3],
+        radius: f64,
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    /// Round tube swept along a polyline or Bezier curve, for wires, hoses and pipes.
+    /// Tessellated into a mesh when the world is built.
+    #[serde(rename = "tube")]
+    Tube {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        points: Vec<[f64; 3]>, // centerline points, or Bezier end and control points
+        radius: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        radii: Option<Vec<f64>>, // radius at each point, in place of `radius`
+        #[serde(default)]
+        curve: TubeCurve,
+        #[serde(default = "default_tube_sides")]
+        sides: u32, // facets around the tube
+        #[serde(default = "default_tube_steps")]
+        steps: u32, // straight pieces each Bezier segment is split into
+        #[serde(default = "default_tube_caps")]
+        caps: bool, // close the ends with flat discs
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    /// Arrow from `from` to `to`: a round shaft ending in a cone, for vector and force
+    /// glyphs. Tessellated into a mesh when the world is built.
+    #[serde(rename = "arrow")]
+    Arrow {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        from: [f64; 3], // tail
+        to: [f64; 3],   // tip
+        shaft_radius: f64,
+        head_size: f64, // length of the cone, and its width at the base
+        #[serde(default = "default_arrow_sides")]
+        sides: u32, // facets around the arrow
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    /// Glyph drawn at every row of a CSV or NumPy data file, colored by a scalar column,
+    /// for 3D plots. Tessellated into one mesh when the world is built.
+    #[serde(rename = "glyphs")]
+    Glyphs {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        filename: String, // CSV with a header row, or NumPy .npy array
+        #[serde(default)]
+        glyph: GlyphShape,
+        size: f64, // width of each glyph
+        #[serde(default = "default_glyph_position")]
+        position: [String; 3], // columns of the x, y and z of each point
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        vector: Option<[String; 3]>, // columns of each arrow's vector
+        #[serde(default = "default_glyph_vector_scale")]
+        vector_scale: f64, // arrow length per unit of vector
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scalar: Option<String>, // column picking each glyph's color from `color_map`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        color_map: Option<ColorMapSpec>, // built-in map name or hex colors, low to high (viridis if unset)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scalar_range: Option<[f64; 2]>, // scalars at the ends of the color map (the data's range if unset)
+        #[serde(default = "default_glyph_sides")]
+        sides: u32, // facets around spheres and arrows
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+        #[serde(skip)]
+        table: Option<Arc<crate::glyphs::DataTable>>, // loaded data file
+    },
+    /// Polyline drawn as a strip that always faces the camera, for annotation lines,
+    /// vectors, field lines and fibers
+    #[serde(rename = "line")]
+    Line {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        points: Vec<[f64; 3]>,
+        width: f64,
+        #[serde(default)]
+        width_unit: LineWidthUnit,
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
     },
     #[serde(rename = "mesh")]
     Mesh {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
         filename: String, // path to STL file
         material: Material,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        face_materials: Vec<FaceMaterial>, // materials for triangles picked by attribute or index
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        orient: Option<MeshOrient>, // "auto" lays the mesh flat when it is loaded
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        unit: Option<MeshUnit>, // unit of the file's coordinates, scaled to millimeters on load
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        lods: Vec<MeshLod>, // lower-detail files drawn when the mesh is small on screen
         transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
         #[serde(skip)]
         mesh_data: Option<crate::mesh::Mesh>, // loaded mesh data
     },
+    #[serde(rename = "instance")]
+    Instance {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        #[serde(rename = "ref")]
+        reference: String, // name of a mesh in the scene's `meshes` table
+        material: Material,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+    },
+    /// Box of smoke or steam, seen by ray marching through its density.
+    /// Volumes have no surface, so no material; light is absorbed and scattered inside.
+    #[serde(rename = "volume")]
+    Volume {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>, // label used by overrides files and jitter blocks
+        center: [f64; 3],
+        size: [f64; 3], // width, height, depth
+        #[serde(default = "default_volume_density")]
+        density: f64, // constant density, or the multiplier of the density file's values
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        density_file: Option<String>, // text voxel grid spanning the box
+        #[serde(default)]
+        absorption: f64, // light absorbed per unit density and length
+        #[serde(default = "default_volume_scattering")]
+        scattering: f64, // light scattered per unit density and length
+        #[serde(default = "default_volume_color")]
+        color: String, // hex color; share of scattered light of each channel
+        #[serde(default)]
+        anisotropy: f64, // -1 to 1; above 0 scatters mostly onward
+        #[serde(default = "default_volume_steps")]
+        steps: u32, // points sampled along each ray through the box
+        transform: Option<Vec<String>>,
+        #[serde(skip)]
+        density_grid: Option<Arc<DensityGrid>>, // loaded density file
+    },
+    /// Objects sharing a transform, applied after each child's own transform.
+    /// Groups nest, and are expanded into plain objects when the scene is loaded.
+    #[serde(rename = "group")]
+    Group {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        transform: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        motion: Option<Vec<String>>, // transforms applied over the shutter, for motion blur
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ignore_clip: Option<bool>, // keep the object whole where the scene's clip planes cut
+        objects: Vec<Object>,
+    },
 }
 
-/// Light source
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct Light {
-    pub position: [f64; 3],
-    pub color: String, // hex color
-    pub intensity: f64,
-    pub diameter: Option<f64>, // optional diameter for diffuse light sources
+fn default_floor_grid_normal() -> [f64; 3] { [0.0, 0.0, 1.0] }
+fn default_floor_grid_pitch() -> f64 { 1.0 }
+fn default_floor_grid_line_width() -> f64 { 0.02 }
+fn default_floor_grid_line_color() -> String { "#404040".to_string() }
+fn default_floor_grid_material() -> Material {
+    Material {
+        color: "#C8C8C8".to_string(),
+        ambient: 0.2,
+        diffuse: 0.8,
+        specular: 0.0,
+        shininess: 10.0,
+        ..Material::default()
+    }
 }
+fn default_volume_density() -> f64 { 1.0 }
+fn default_volume_scattering() -> f64 { 1.0 }
+fn default_volume_color() -> String { "#FFFFFF".to_string() }
+fn default_volume_steps() -> u32 { 64 }
+fn default_tube_sides() -> u32 { 24 }
+fn default_tube_steps() -> u32 { 16 }
+fn default_tube_caps() -> bool { true }
+fn default_arrow_sides() -> u32 { 24 }
+fn default_glyph_position() -> [String; 3] { ["x", "y", "z"].map(String::from) }
+fn default_glyph_vector_scale() -> f64 { 1.0 }
+fn default_glyph_sides() -> u32 { 16 }
+
+impl Object {
+    /// Name given to the object in the scene, if any
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Object::Sphere { name, .. }
+            | Object::Plane { name, .. }
+            | Object::FloorGrid { name, .. }
+            | Object::Cube { name, .. }
+            | Object::Triangle { name, .. }
+            | Object::Quad { name, .. }
+            | Object::Disc { name, .. }
+            | Object::Tube { name, .. }
+            | Object::Arrow { name, .. }
+            | Object::Glyphs { name, .. }
+            | Object::Line { name, .. }
+            | Object::Mesh { name, .. }
+            | Object::Instance { name, .. }
+            | Object::Volume { name, .. }
+            | Object::Group { name, .. } => name.as_deref(),
+        }
+    }
 
-/// Ambient illumination settings
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct AmbientIllumination {
-    pub color: String, // hex color
-    pub intensity: f64,
-}
+    /// Kind as written in the scene's `kind` field
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Object::Sphere { .. } => "sphere",
+            Object::Plane { .. } => "plane",
+            Object::FloorGrid { .. } => "floor_grid",
+            Object::Cube { .. } => "cube",
+            Object::Triangle { .. } => "triangle",
+            Object::Quad { .. } => "quad",
+            Object::Disc { .. } => "disc",
+            Object::Tube { .. } => "tube",
+            Object::Arrow { .. } => "arrow",
+            Object::Glyphs { .. } => "glyphs",
+            Object::Line { .. } => "line",
+            Object::Mesh { .. } => "mesh",
+            Object::Instance { .. } => "instance",
+            Object::Volume { .. } => "volume",
+            Object::Group { .. } => "group",
+        }
+    }
 
-impl Default for AmbientIllumination {
-    fn default() -> Self {
-        Self {
-            color: "#FFFFFF".to_string(),
-            intensity: 0.1,
+    /// Material of any object other than a volume or group
+    pub fn material(&self) -> Option<&Material> {
+        match self {
+            Object::Sphere { material, .. }
+            | Object::Plane { material, .. }
+            | Object::FloorGrid { material, .. }
+            | Object::Cube { material, .. }
+            | Object::Triangle { material, .. }
+            | Object::Quad { material, .. }
+            | Object::Disc { material, .. }
+            | Object::Tube { material, .. }
+            | Object::Arrow { material, .. }
+            | Object::Glyphs { material, .. }
+            | Object::Line { material, .. }
+            | Object::Mesh { material, .. }
+            | Object::Instance { material, .. } => Some(material),
+            Object::Volume { .. } | Object::Group { .. } => None,
         }
     }
-}
 
-/// Fog settings
+    /// Every material of the object: its own, then those of individual mesh faces
+    pub fn materials(&self) -> Vec<&Material> {
+        let faces = match self {
+            Object::Mesh { face_materials, .. } => face_materials.as_slice(),
+            _ => &[],
+        };
+        let faces = faces.iter().map(|face| &face.material);
+        self.material().into_iter().chain(faces).collect()
+    }
+
+    /// Mutable version of `materials`
+    pub fn materials_mut(&mut self) -> Vec<&mut Material> {
+        match self {
+            Object::Mesh {
+                material,
+                face_materials,
+                ..
+            } => std::iter::once(material)
+                .chain(face_materials.iter_mut().map(|face| &mut face.material))
+                .collect(),
+            object => object.material_mut().into_iter().collect(),
+        }
+    }
+
+    /// Mutable material of any object other than a volume or group
+    pub fn material_mut(&mut self) -> Option<&mut Material> {
+        match self {
+            Object::Sphere { material, .. }
+            | Object::Plane { material, .. }
+            | Object::FloorGrid { material, .. }
+            | Object::Cube { material, .. }
+            | Object::Triangle { material, .. }
+            | Object::Quad { material, .. }
+            | Object::Disc { material, .. }
+            | Object::Tube { material, .. }
+            | Object::Arrow { material, .. }
+            | Object::Glyphs { material, .. }
+            | Object::Line { material, .. }
+            | Object::Mesh { material, .. }
+            | Object::Instance { material, .. } => Some(material),
+            Object::Volume { .. } | Object::Group { .. } => None,
+        }
+    }
+
+    /// Transform list of any object, including a group
+    pub(crate) fn transform_mut(&mut self) -> &mut Option<Vec<String>> {
+        match self {
+            Object::Sphere { transform, .. }
+            | Object::Plane { transform, .. }
+            | Object::FloorGrid { transform, .. }
+            | Object::Cube { transform, .. }
+            | Object::Triangle { transform, .. }
+            | Object::Quad { transform, .. }
+            | Object::Disc { transform, .. }
+            | Object::Tube { transform, .. }
+            | Object::Arrow { transform, .. }
+            | Object::Glyphs { transform, .. }
+            | Object::Line { transform, .. }
+            | Object::Mesh { transform, .. }
+            | Object::Instance { transform, .. }
+            | Object::Volume { transform, .. }
+            | Object::Group { transform, .. } => transform,
+        }
+    }
+
+    /// Motion transforms of the object, if it moves; volumes never do
+    pub fn motion(&self) -> Option<&[String]> {
+        match self {
+            Object::Sphere { motion, .. }
+            | Object::Plane { motion, .. }
+            | Object::FloorGrid { motion, .. }
+            | Object::Cube { motion, .. }
+            | Object::Triangle { motion, .. }
+            | Object::Quad { motion, .. }
+            | Object::Disc { motion, .. }
+            | Object::Tube { motion, .. }
+            | Object::Arrow { motion, .. }
+            | Object::Glyphs { motion, .. }
+            | Object::Line { motion, .. }
+            | Object::Mesh { motion, .. }
+            | Object::Instance { motion, .. }
+            | Object::Group { motion, .. } => motion.as_deref(),
+            Object::Volume { .. } => None,
+        }
+    }
+
+    pub(crate) fn motion_mut(&mut self) -> Option<&mut Option<Vec<String>>> {
+        match self {
+            Object::Sphere { motion, .. }
+            | Object::Plane { motion, .. }
+            | Object::FloorGrid { motion, .. }
+            | Object::Cube { motion, .. }
+            | Object::Triangle { motion, .. }
+            | Object::Quad { motion, .. }
+            | Object::Disc { motion, .. }
+            | Object::Tube { motion, .. }
+            | Object::Arrow { motion, .. }
+            | Object::Glyphs { motion, .. }
+            | Object::Line { motion, .. }
+            | Object::Mesh { motion, .. }
+            | Object::Instance { motion, .. }
+            | Object::Group { motion, .. } => Some(motion),
+            Object::Volume { .. } => None,
+        }
+    }
+
+    /// Whether the scene's clip planes leave the object whole; volumes are never cut
+    pub fn ignores_clip(&self) -> bool {
+        match self {
+            Object::Sphere { ignore_clip, .. }
+            | Object::Plane { ignore_clip, .. }
+            | Object::FloorGrid { ignore_clip, .. }
+            | Object::Cube { ignore_clip, .. }
+            | Object::Triangle { ignore_clip, .. }
+            | Object::Quad { ignore_clip, .. }
+            | Object::Disc { ignore_clip, .. }
+            | Object::Tube { ignore_clip, .. }
+            | Object::Arrow { ignore_clip, .. }
+            | Object::Glyphs { ignore_clip, .. }
+            | Object::Line { ignore_clip, .. }
+            | Object::Mesh { ignore_clip, .. }
+            | Object::Instance { ignore_clip, .. }
+            | Object::Group { ignore_clip, .. } => ignore_clip.unwrap_or(false),
+            Object::Volume { .. } => true,
+        }
+    }
+
+    fn ignore_clip_mut(&mut self) -> Option<&mut Option<bool>> {
+        match self {
+            Object::Sphere { ignore_clip, .. }
+            | Object::Plane { ignore_clip, .. }
+            | Object::FloorGrid { ignore_clip, .. }
+            | Object::Cube { ignore_clip, .. }
+            | Object::Triangle { ignore_clip, .. }
+            | Object::Quad { ignore_clip, .. }
+            | Object::Disc { ignore_clip, .. }
+            | Object::Tube { ignore_clip, .. }
+            | Object::Arrow { ignore_clip, .. }
+            | Object::Glyphs { ignore_clip, .. }
+            | Object::Line { ignore_clip, .. }
+            | Object::Mesh { ignore_clip, .. }
+            | Object::Instance { ignore_clip, .. }
+            | Object::Group { ignore_clip, .. } => Some(ignore_clip),
+            Object::Volume { .. } => None,
+        }
+    }
+
+    /// Medium of a volume object after its transform; None for other objects
+    ///
+    /// A density file that hasn't been loaded leaves the constant `density`.
+    pub fn volume(&self) -> Result<Option<Volume>, String> {
+        let Object::Volume {
+            center,
+            size,
+            density,
+            absorption,
+            scattering,
+            color,
+            anisotropy,
+            steps,
+            transform,
+            density_grid,
+            ..
+        } = self
+        else {
+            return Ok(None);
+        };
+        if !size.iter().all(|side| *side > 0.0) {
+            return Err("Volume size must be positive".to_string());
+        }
+        for (field, value) in [
+            ("density", density),
+            ("absorption", absorption),
+            ("scattering", scattering),
+        ] {
+            if !(value.is_finite() && *value >= 0.0) {
+                return Err(format!(
+                    "Volume {} must be a finite number of at least 0",
+                    field
+                ));
+            }
+        }
+        if !(*anisotropy > -1.0 && *anisotropy < 1.0) {
+            return Err("Volume anisotropy must be between -1 and 1".to_string());
+        }
+        if *steps == 0 {
+            return Err("Volume steps must be greater than 0".to_string());
+        }
+        let world_to_local = match transform {
+            Some(strings) => parse_transforms(strings)?
+                .try_inverse()
+                .ok_or("Volume has a transform that can't be inverted")?,
+            None => Matrix4::identity(),
+        };
+        Ok(Some(Volume {
+            center: Point::new(center[0], center[1], center[2]),
+            size: Vec3::new(size[0], size[1], size[2]),
+            world_to_local,
+            density: *density,
+            grid: density_grid.clone(),
+            absorption: *absorption,
+            scattering: *scattering,
+            color: hex_to_color(color)?,
+            anisotropy: *anisotropy,
+            steps: *steps,
+        }))
+    }
+
+    /// Tessellated surface of a tube, arrow or glyph field object, before its transform;
+    /// None for other objects, or a glyph field without data
+    pub fn tessellated_mesh(&self) -> Result<Option<crate::mesh::Mesh>, String> {
+        if let Object::Glyphs { .. } = self {
+            return Ok(crate::glyphs::glyph_field(self)?.map(|(mesh, _)| mesh));
+        }
+        if let Object::Arrow {
+            from,
+            to,
+            shaft_radius,
+            head_size,
+            sides,
+            ..
+        } = self
+        {
+            let (from, to) = (Point::from(*from), Point::from(*to));
+            return crate::arrow::arrow_mesh(&from, &to, *shaft_radius, *head_size, *sides)
+                .map(Some);
+        }
+        let Object::Tube {
+            points,
+            radius,
+            radii,
+            curve,
+            sides,
+            steps,
+            caps,
+            ..
+        } = self
+        else {
+            return Ok(None);
+        };
+        let radii = match radii {
+            Some(radii) => radii.clone(),
+            None => vec![*radius; points.len()],
+        };
+        if !radii.iter().all(|r| r.is_finite() && *r >= 0.0) {
+            return Err("Tube radii must be finite numbers of at least 0".to_string());
+        }
+        if *sides < 3 {
+            return Err("Tube sides must be at least 3".to_string());
+        }
+        let centerline = crate::tube::centerline(points, &radii, *curve, *steps)?;
+        Ok(Some(crate::tube::tube_mesh(&centerline, *sides, *caps)))
+    }
+
+    /// Most triangles `tessellated_mesh` makes for the object, counted without
+    /// tessellating it
+    pub(crate) fn tessellated_triangle_count(&self) -> usize {
+        if let Object::Arrow { sides, .. } = self {
+            return crate::arrow::ARROW_TRIANGLES_PER_SIDE * *sides as usize;
+        }
+        if let Object::Glyphs {
+            glyph,
+            sides,
+            table,
+            ..
+        } = self
+        {
+            let rows = table.as_ref().map_or(0, |table| table.rows.len());
+            return rows.saturating_mul(crate::glyphs::glyph_triangle_count(*glyph, *sides));
+        }
+        let Object::Tube {
+            points,
+            curve,
+            sides,
+            steps,
+            caps,
+            ..
+        } = self
+        else {
+            return 0;
+        };
+        let runs = match curve {
+            TubeCurve::Polyline => points.len().saturating_sub(1),
+            TubeCurve::Bezier => {
+                (points.len().saturating_sub(1) / 3).saturating_mul(*steps as usize)
+            }
+        };
+        let ends = if *caps { 2 } else { 0 };
+        (runs.saturating_mul(2) + ends).saturating_mul(*sides as usize)
+    }
+}
+
+/// Replace groups in `objects` with their children, recursively
+fn expand_groups(objects: Vec<Object>) -> Result<Vec<Object>, String> {
+    let mut expanded = Vec::with_capacity(objects.len());
+    for object in objects {
+        let Object::Group {
+            name,
+            transform,
+            motion,
+            ignore_clip,
+            objects,
+        } = object
+        else {
+            expanded.push(object);
+            continue;
+        };
+        let name = name.as_deref().unwrap_or("unnamed");
+        let group_transform = transform.unwrap_or_default();
+        parse_transforms(&group_transform).map_err(|e| format!("Group '{}': {}", name, e))?;
+        let group_motion = motion.unwrap_or_default();
+        parse_motion(&group_motion).map_err(|e| format!("Group '{}': {}", name, e))?;
+        for mut child in expand_groups(objects)? {
+            // Cube and volume transforms turn the box about its own center, so the center
+            // moves into the transform for the group to turn and scale it about the group origin
+            if !group_transform.is_empty() {
+                center_into_transform(&mut child);
+            }
+            child
+                .transform_mut()
+                .get_or_insert_with(Vec::new)
+                .extend(group_transform.iter().cloned());
+            // Motion is in scene coordinates, so the group's simply follows the child's
+            if !group_motion.is_empty() {
+                let Some(motion) = child.motion_mut() else {
+                    return Err(format!("Group '{}': volumes can't move", name));
+                };
+                motion
+                    .get_or_insert_with(Vec::new)
+                    .extend(group_motion.iter().cloned());
+            }
+            // Children left unset follow the group, so a whole assembly is kept in one place
+            if let Some(child_ignore_clip) = child.ignore_clip_mut() {
+                if child_ignore_clip.is_none() {
+                    *child_ignore_clip = ignore_clip;
+                }
+            }
+            expanded.push(child);
+        }
+    }
+    Ok(expanded)
+}
+
+/// Move a cube or volume's center into the front of its transform, so transforms added
+/// after it turn and scale the box about the scene origin rather than its own center
+pub(crate) fn center_into_transform(object: &mut Object) {
+    if let Object::Cube {
+        center, transform, ..
+    }
+    | Object::Volume {
+        center, transform, ..
+    } = object
+    {
+        if *center != [0.0; 3] {
+            let offset = format!("translate({}, {}, {})", center[0], center[1], center[2]);
+            transform.get_or_insert_with(Vec::new).insert(0, offset);
+            *center = [0.0; 3];
+        }
+    }
+}
+
+/// Light source
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Light {
+    pub position: [f64; 3],
+    pub color: String, // hex color
+    pub intensity: f64,
+    pub diameter: Option<f64>, // optional diameter for diffuse light sources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_samples: Option<u32>, // points sampled on a diffuse light's disk (renderer default if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attenuation: Option<Attenuation>, // falloff with distance (none if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>, // light group for per-group output passes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>, // label used by overrides files and jitter blocks
+    #[serde(skip)]
+    pub emitter: Option<Arc<crate::emission::Emitter>>, // emissive surface lit from instead of `position`
+    #[serde(skip)]
+    pub caustics: Option<Arc<crate::caustics::PhotonMap>>, // light it focused through glass and mirrors
+}
+
+impl Light {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.shadow_samples == Some(0) {
+            return Err("Light shadow_samples must be greater than 0".to_string());
+        }
+        if let Some(attenuation) = &self.attenuation {
+            attenuation.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Share of the light's intensity arriving from `distance` away
+    pub fn falloff(&self, distance: f64) -> f64 {
+        self.attenuation
+            .map_or(1.0, |attenuation| attenuation.factor(distance))
+    }
+}
+
+/// How a light dims with distance from its center (or from each point on an area light)
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(tag = "type")]
+pub enum Attenuation {
+    /// Same brightness at every distance, as when `attenuation` is unset
+    #[serde(rename = "none")]
+    None,
+    /// Physical `1 / d²` falloff; `intensity` is the brightness one unit from the light
+    #[serde(rename = "inverse_square")]
+    InverseSquare,
+    /// `1 / (constant + linear·d + quadratic·d²)`, for tuning the falloff by hand
+    #[serde(rename = "quadratic")]
+    Quadratic {
+        #[serde(default = "default_attenuation_constant")]
+        constant: f64,
+        #[serde(default)]
+        linear: f64,
+        #[serde(default)]
+        quadratic: f64,
+    },
+}
+
+fn default_attenuation_constant() -> f64 { 1.0 }
+
+impl Attenuation {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Attenuation::Quadratic {
+            constant,
+            linear,
+            quadratic,
+        } = self
+        {
+            let coefficients = [*constant, *linear, *quadratic];
+            if coefficients.iter().any(|c| !(c.is_finite() && *c >= 0.0))
+                || coefficients.iter().all(|c| *c == 0.0)
+            {
+                return Err(
+                    "Attenuation coefficients must be non-negative with at least one above 0"
+                        .to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Factor applied to the light's intensity at `distance`
+    pub fn factor(&self, distance: f64) -> f64 {
+        match self {
+            Attenuation::None => 1.0,
+            // Kept finite for surfaces touching the light
+            Attenuation::InverseSquare => 1.0 / (distance * distance).max(f64::EPSILON),
+            Attenuation::Quadratic {
+                constant,
+                linear,
+                quadratic,
+            } => 1.0 / (constant + linear * distance + quadratic * distance * distance),
+        }
+    }
+}
+
+/// Ambient illumination settings
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AmbientIllumination {
+    pub color: String, // hex color
+    pub intensity: f64,
+}
+
+impl Default for AmbientIllumination {
+    fn default() -> Self {
+        Self {
+            color: "#FFFFFF".to_string(),
+            intensity: 0.1,
+        }
+    }
+}
+
+/// Fog settings
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Fog {
     pub color: String, // hex color
     pub density: f64,
     pub start: f64,
     pub end: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volumetric: Option<VolumetricFog>, // light the fog from the scene's lights (a flat fade if unset)
+}
+
+/// Single scattering settings for fog that is lit by the scene's lights
+///
+/// The fog's `color` becomes the share of light it scatters, and shadows in the fog
+/// show up as light shafts.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct VolumetricFog {
+    #[serde(default = "default_volumetric_steps")]
+    pub steps: u32, // points sampled along each ray through the fog
+    #[serde(default)]
+    pub anisotropy: f64, // -1 to 1; above 0 scatters mostly onward, brightening the fog towards lights
+}
+
+fn default_volumetric_steps() -> u32 { 32 }
+
+impl Fog {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(volumetric) = &self.volumetric {
+            if self.end <= self.start {
+                return Err("Volumetric fog needs an end beyond its start".to_string());
+            }
+            if volumetric.steps == 0 {
+                return Err("Volumetric fog steps must be greater than 0".to_string());
+            }
+            if !(volumetric.anisotropy > -1.0 && volumetric.anisotropy < 1.0) {
+                return Err("Volumetric fog anisotropy must be between -1 and 1".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Photon mapping settings for caustics: the bright patterns glass and mirrors focus
+/// light into
+///
+/// Photons are shot from each light at the transparent and reflective objects, followed
+/// through them, and left where they land on diffuse surfaces; shading gathers those
+/// within `radius` of each point.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct Caustics {
+    #[serde(default = "default_caustic_photons")]
+    pub photons: u32, // shot from each light
+    #[serde(default = "default_caustic_radius")]
+    pub radius: f64, // distance photons are gathered from; larger is smoother but blurrier
+    #[serde(default = "default_caustic_bounces")]
+    pub max_bounces: u32, // reflections and refractions followed per photon
+}
+
+fn default_caustic_photons() -> u32 { 200_000 }
+fn default_caustic_radius() -> f64 { 0.05 }
+fn default_caustic_bounces() -> u32 { 8 }
+
+impl Caustics {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.photons == 0 {
+            return Err("Caustics photons must be greater than 0".to_string());
+        }
+        if !(self.radius.is_finite() && self.radius > 0.0) {
+            return Err("Caustics radius must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Floor added under the scene's finite objects, so they stand on something and cast
+/// contact shadows without a hand-placed plane
+///
+/// The floor is an infinite plane facing +Z, `offset` below the lowest point of every
+/// object but planes and floor grids. As a shadow catcher it shows only the shadows
+/// falling on it, darkening whatever is behind it; the `alpha` AOV holds their strength
+/// for compositing.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct AutoFloor {
+    #[serde(default)]
+    pub offset: f64, // gap between the lowest object and the floor
+    #[serde(default)]
+    pub shadow_catcher: bool, // show only the shadows on the floor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<Material>, // floor surface (matte white if unset)
+}
+
+impl AutoFloor {
+    /// Name of the floor, as object statistics report it
+    pub const NAME: &'static str = "auto_floor";
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.offset.is_finite() {
+            return Err("Auto floor offset must be finite".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Plane cutting the scene open, as in a cutaway illustration
+///
+/// Surfaces on the side `normal` points to are removed, so rays pass through them to
+/// whatever lies behind; solids are left open rather than capped. Objects marked
+/// `ignore_clip` are kept whole.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ClipPlane {
+    pub point: [f64; 3],
+    pub normal: [f64; 3], // points toward the side cut away
+}
+
+impl ClipPlane {
+    pub fn validate(&self) -> Result<(), String> {
+        let length = Vec3::from(self.normal).norm();
+        if !self.point.iter().all(|x| x.is_finite()) || !length.is_finite() || length == 0.0 {
+            return Err("Clip planes need a finite point and a nonzero normal".to_string());
+        }
+        Ok(())
+    }
 }
 
 /// Outline detection settings
@@ -393,6 +2263,65 @@ pub struct OutlineSettings {
     pub thickness: f64,
     #[serde(default = "default_outline_use_8_neighbors")]
     pub use_8_neighbors: bool,
+    #[serde(default)]
+    pub object_weight: f64, // added where neighboring pixels show different objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crease_angle: Option<f64>, // degrees; sharper creases are always edges
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub objects: BTreeMap<String, ObjectOutline>, // outlines of particular objects, by name
+    #[serde(default = "default_outline_supersample")]
+    pub supersample: u32, // samples per pixel along each axis, for anti-aliased lines
+}
+
+/// Outline settings of the objects with one name, in place of the scene's
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ObjectOutline {
+    #[serde(default = "default_object_outline_enabled")]
+    pub enabled: bool, // false draws no outlines around the objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>, // hex color; the scene's outline color if unset
+}
+
+fn default_object_outline_enabled() -> bool { true }
+
+/// Tone mapping settings applied when writing 8-bit images
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ToneMappingSettings {
+    pub operator: String, // "linear", "srgb", "reinhard" or "aces"
+    #[serde(default)]
+    pub exposure: f64, // stops; +1 doubles the radiance
+}
+
+/// Encoding of the scene's hex colors and images, and of 8-bit output by default
+///
+/// Serialized (and parsed) as `"linear"` or `"srgb"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    /// Colors are lit as written and written out unencoded (the historical pipeline)
+    #[default]
+    Linear,
+    /// Colors and images are decoded from sRGB before lighting; output is encoded to sRGB
+    Srgb,
+}
+
+/// Backplate image shown where camera rays miss every object
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BackgroundSettings {
+    pub image: String, // path (relative to the scene file) or http(s) URL
+    #[serde(skip)]
+    pub backplate: Option<crate::backplate::Backplate>, // loaded image
+}
+
+/// Tilt-shift depth blur settings; depths run from 0 (nearest hit) to 1 (farthest)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TiltShiftSettings {
+    #[serde(default = "default_tilt_shift_focus")]
+    pub focus: f64, // normalized depth kept sharp
+    #[serde(default = "default_tilt_shift_range")]
+    pub range: f64, // width of the sharp band around the focus
+    #[serde(default = "default_tilt_shift_blur")]
+    pub blur: f64, // largest blur radius as a fraction of the image width
 }
 
 fn default_outline_depth_weight() -> f64 { 1.0 }
@@ -401,6 +2330,10 @@ fn default_outline_threshold() -> f64 { 0.1 }
 fn default_outline_color() -> String { "#000000".to_string() }
 fn default_outline_thickness() -> f64 { 1.0 }
 fn default_outline_use_8_neighbors() -> bool { false }
+fn default_outline_supersample() -> u32 { 1 }
+fn default_tilt_shift_focus() -> f64 { 0.5 }
+fn default_tilt_shift_range() -> f64 { 0.2 }
+fn default_tilt_shift_blur() -> f64 { 0.01 }
 
 impl Default for OutlineSettings {
     fn default() -> Self {
@@ -412,6 +2345,10 @@ impl Default for OutlineSettings {
             color: "#000000".to_string(),
             thickness: 1.0,
             use_8_neighbors: false,
+            object_weight: 0.0,
+            crease_angle: None,
+            objects: BTreeMap::new(),
+            supersample: 1,
         }
     }
 }
@@ -422,7 +2359,18 @@ pub struct SceneSettings {
     pub ambient_illumination: AmbientIllumination,
     pub fog: Option<Fog>,
     pub background_color: Option<String>, // hex color
+    pub background: Option<BackgroundSettings>, // backplate for camera rays
     pub outline: Option<OutlineSettings>,
+    pub tone_mapping: Option<ToneMappingSettings>,
+    pub tilt_shift: Option<TiltShiftSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caustics: Option<Caustics>, // light focused onto surfaces by transparent and reflective objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_space: Option<ColorSpace>, // encoding of hex colors and images (linear if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_floor: Option<AutoFloor>, // floor placed just under the finite objects
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub clip_planes: Vec<ClipPlane>, // planes cutting the scene open for cutaway views
 }
 
 impl Default for SceneSettings {
@@ -431,18 +2379,67 @@ impl Default for SceneSettings {
             ambient_illumination: AmbientIllumination::default(),
             fog: None,
             background_color: Some("#000000".to_string()),
+            background: None,
             outline: None,
+            tone_mapping: None,
+            tilt_shift: None,
+            caustics: None,
+            color_space: None,
+            auto_floor: None,
+            clip_planes: Vec::new(),
         }
     }
 }
 
+/// Renderer settings stored with the scene, so every front end renders it the same way
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct RenderSettings {
+    pub anti_aliasing: Option<crate::renderer::AntiAliasingMode>,
+    pub samples: Option<u32>, // samples per pixel (passes for progressive renders)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crop_to_content: Option<bool>, // trim saved images to the rendered geometry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub padding_percent: Option<f64>, // margin around saved images, % of the larger side
+}
+
+/// Mesh loaded once and placed any number of times by `instance` objects
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MeshPrototype {
+    pub filename: String, // path or URL of the STL file
+    #[serde(skip)]
+    pub mesh_data: Option<Arc<crate::mesh::Mesh>>, // loaded mesh data, shared by every instance
+}
+
 /// Complete scene definition
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Scene {
     pub camera: Camera,
+    /// Named cameras, rendered in one batch with `Renderer::render_cameras_to_file`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cameras: BTreeMap<String, Camera>,
     pub objects: Vec<Object>,
     pub lights: Vec<Light>,
     pub scene_settings: SceneSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render_settings: Option<RenderSettings>,
+    /// Extra directories searched for relative mesh and image paths (see `asset_search_dirs`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub asset_paths: Vec<String>,
+    /// Named meshes shared by `instance` objects
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meshes: BTreeMap<String, MeshPrototype>,
+    /// Random changes to named objects, applied (and removed) when the scene is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter: Option<crate::jitter::Jitter>,
+    /// Keyframed camera and object moves, rendered frame by frame with `Renderer::render_sequence`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<crate::animation::Animation>,
+    /// Assets replaced or skipped while loading, under `AssetOptions::missing_asset`
+    #[serde(skip)]
+    pub missing_assets: Vec<MissingAsset>,
+    /// Units guessed for meshes with `unit: "auto"` while loading
+    #[serde(skip)]
+    pub inferred_units: Vec<InferredUnit>,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -450,9 +2447,17 @@ impl Default for Scene {
     fn default() -> Self {
         Self {
             camera: Camera::default(),
+            cameras: BTreeMap::new(),
             objects: Vec::new(),
             lights: Vec::new(),
             scene_settings: SceneSettings::default(),
+            render_settings: None,
+            asset_paths: Vec::new(),
+            meshes: BTreeMap::new(),
+            jitter: None,
+            animation: None,
+            missing_assets: Vec::new(),
+            inferred_units: Vec::new(),
         }
     }
 }
@@ -460,44 +2465,325 @@ impl Default for Scene {
 impl Scene {
     /// Load scene from JSON file
     pub fn from_json_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = std::fs::read_to_string(path)?;
-        let mut scene: Scene = serde_json::from_str(&content)?;
+        Self::from_json_file_with_assets(path, &AssetOptions::default())
+    }
+
+    /// Load scene from JSON file, resolving external assets with the given options
+    pub fn from_json_file_with_assets(
+        path: &str,
+        options: &AssetOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut scene: Scene = {
+            let _span = crate::profile::span("scene parse");
+            let content = std::fs::read_to_string(path)?;
+            Self::parse_json(&content)?
+        };
 
         // Load mesh data for any mesh objects
-        scene.load_mesh_data(Some(path))?;
+        scene.load_mesh_data_with_assets(Some(path), options)?;
 
         Ok(scene)
     }
 
     /// Load scene from JSON string
     pub fn from_json_str(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut scene: Scene = serde_json::from_str(json)?;
+        Self::from_json_str_with_assets(json, &AssetOptions::default())
+    }
+
+    /// Load scene from JSON string, resolving external assets with the given options
+    pub fn from_json_str_with_assets(
+        json: &str,
+        options: &AssetOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut scene = Self::parse_json(json)?;
 
         // Load mesh data for any mesh objects (relative to current directory)
-        scene.load_mesh_data(None)?;
+        scene.load_mesh_data_with_assets(None, options)?;
 
         Ok(scene)
     }
 
+    /// Parse scene JSON, first replacing `"=..."` expressions with their values
+    fn parse_json(json: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        if crate::expr::evaluate_scene_expressions(&mut value)? {
+            Ok(serde_json::from_value(value)?)
+        } else {
+            // Parsing the text itself keeps line numbers in error messages
+            Ok(serde_json::from_str(json)?)
+        }
+    }
+
     /// Load mesh data for all mesh objects in the scene
     pub fn load_mesh_data(
         &mut self,
         scene_file_path: Option<&str>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        self.load_mesh_data_with_assets(scene_file_path, &AssetOptions::default())
+    }
+
+    /// Directories searched, in order, for relative asset paths
+    ///
+    /// The scene file's directory (the current directory without one) comes first, then
+    /// the scene's `asset_paths` relative to it, then `AssetOptions::asset_root`.
+    pub fn asset_search_dirs(
+        &self,
+        scene_file_path: Option<&str>,
+        options: &AssetOptions,
+    ) -> Vec<PathBuf> {
         let base_dir = scene_file_path
-            .and_then(|p| std::path::Path::new(p).parent())
-            .unwrap_or_else(|| std::path::Path::new("."));
+            .and_then(|p| Path::new(p).parent())
+            .unwrap_or_else(|| Path::new("."));
+        std::iter::once(base_dir.to_path_buf())
+            .chain(self.asset_paths.iter().map(|path| base_dir.join(path)))
+            .chain(options.asset_root.clone())
+            .collect()
+    }
+
+    /// Replace `group` objects with their children, each carrying the group transforms
+    ///
+    /// Called by `load_mesh_data`, so loaded scenes never contain groups.
+    pub fn expand_groups(&mut self) -> Result<(), String> {
+        // Group names are lost in expansion, so the animation finds its objects first
+        if let Some(animation) = &mut self.animation {
+            animation.bind(&self.objects)?;
+        }
+        if self
+            .objects
+            .iter()
+            .any(|object| matches!(object, Object::Group { .. }))
+        {
+            self.objects = expand_groups(std::mem::take(&mut self.objects))?;
+        }
+        Ok(())
+    }
+
+    /// This scene as it is at `frame` of its `animation`, without the animation block
+    pub fn frame(&self, frame: u32) -> Result<Scene, String> {
+        let animation = self.animation.as_ref().ok_or("Scene has no animation")?;
+        let mut scene = Scene {
+            animation: None,
+            ..self.clone()
+        };
+        animation.apply(&mut scene, frame)?;
+        Ok(scene)
+    }
+
+    /// Apply the scene's `jitter` block, with `seed` in place of its own if given
+    ///
+    /// Called by `load_mesh_data` before groups are expanded, so loaded scenes never
+    /// contain a jitter block.
+    pub fn apply_jitter(&mut self, seed: Option<u64>) -> Result<(), String> {
+        if let Some(jitter) = self.jitter.take() {
+            jitter.apply(self, seed.unwrap_or(jitter.seed))?;
+        }
+        Ok(())
+    }
+
+    /// Load mesh data for all mesh objects, the backplate and bump images and volume
+    /// density files, fetching http(s) references if the options allow it
+    pub fn load_mesh_data_with_assets(
+        &mut self,
+        scene_file_path: Option<&str>,
+        options: &AssetOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let _span = crate::profile::span("mesh load");
+        self.apply_jitter(options.jitter_seed)?;
+        self.expand_groups()?;
+        let search_dirs = self.asset_search_dirs(scene_file_path, options);
+
+        self.missing_assets.clear();
+        self.inferred_units.clear();
+        let mut missing = |reference: &str, error: Box<dyn std::error::Error>| {
+            if options.missing_asset == MissingAssetPolicy::Error {
+                return Err(error);
+            }
+            self.missing_assets.push(MissingAsset {
+                reference: reference.to_string(),
+                error: error.to_string(),
+            });
+            Ok(())
+        };
 
         for object in &mut self.objects {
             if let Object::Mesh {
                 filename,
+                material,
+                face_materials,
+                orient,
+                unit,
+                lods,
                 mesh_data,
                 ..
             } = object
             {
-                let mesh_path = base_dir.join(filename);
-                let mesh = crate::mesh::Mesh::from_stl_file(&mesh_path)?;
-                *mesh_data = Some(mesh);
+                match crate::assets::load_mesh(filename, &search_dirs, options) {
+                    Ok(mut mesh) => {
+                        let unit = match unit {
+                            Some(MeshUnit::Auto) => {
+                                let (min, max) = mesh.bounds();
+                                let extent = (max - min).max();
+                                // Meshes with no size to go by are left unscaled
+                                let unit = MeshUnit::infer(extent);
+                                if let Some(unit) = unit {
+                                    self.inferred_units.push(InferredUnit {
+                                        filename: filename.clone(),
+                                        extent,
+                                        unit,
+                                    });
+                                }
+                                unit
+                            }
+                            unit => *unit,
+                        };
+                        let millimeters = unit.and_then(|unit| unit.millimeters());
+                        if let Some(millimeters) = millimeters.filter(|&mm| mm != 1.0) {
+                            mesh.scale(millimeters);
+                        }
+                        if *orient == Some(MeshOrient::Auto) {
+                            mesh.lay_flat();
+                        }
+                        *mesh_data = Some(mesh);
+
+                        // A missing level leaves the mesh drawn at full detail
+                        for lod in lods.iter_mut() {
+                            if lod.max_pixels.is_nan() || lod.max_pixels <= 0.0 {
+                                return Err(format!(
+                                    "Mesh LOD max_pixels must be positive, not {}",
+                                    lod.max_pixels
+                                )
+                                .into());
+                            }
+                            lod.mesh_data = None;
+                            match crate::assets::load_mesh(&lod.filename, &search_dirs, options) {
+                                Ok(mut mesh) => {
+                                    if let Some(millimeters) = millimeters.filter(|&mm| mm != 1.0) {
+                                        mesh.scale(millimeters);
+                                    }
+                                    if *orient == Some(MeshOrient::Auto) {
+                                        mesh.lay_flat();
+                                    }
+                                    lod.mesh_data = Some(mesh);
+                                }
+                                Err(e) => missing(&lod.filename, e)?,
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        missing(filename, e)?;
+                        if options.missing_asset == MissingAssetPolicy::Placeholder {
+                            *mesh_data = Some(crate::mesh::Mesh::placeholder_box());
+                            material.color = PLACEHOLDER_COLOR.to_string();
+                            material.texture = None;
+                            face_materials.clear();
+                        } else {
+                            // The renderer leaves out meshes without data
+                            *mesh_data = None;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut placeholders = Vec::new();
+        for (name, prototype) in &mut self.meshes {
+            match crate::assets::load_mesh(&prototype.filename, &search_dirs, options) {
+                Ok(mesh) => prototype.mesh_data = Some(Arc::new(mesh)),
+                Err(e) => {
+                    missing(&prototype.filename, e)?;
+                    if options.missing_asset == MissingAssetPolicy::Placeholder {
+                        prototype.mesh_data = Some(Arc::new(crate::mesh::Mesh::placeholder_box()));
+                        placeholders.push(name.clone());
+                    } else {
+                        prototype.mesh_data = None;
+                    }
+                }
+            }
+        }
+        for object in &mut self.objects {
+            if let Object::Instance {
+                reference,
+                material,
+                ..
+            } = object
+            {
+                if placeholders.contains(reference) {
+                    material.color = PLACEHOLDER_COLOR.to_string();
+                    material.texture = None;
+                }
+            }
+        }
+
+        if let Some(background) = &mut self.scene_settings.background {
+            match crate::assets::load_backplate(&background.image, &search_dirs, options) {
+                Ok(backplate) => background.backplate = Some(backplate),
+                Err(e) => missing(&background.image, e)?,
+            }
+        }
+        // Without its image the background falls back to the background color
+        if options.missing_asset != MissingAssetPolicy::Error {
+            self.scene_settings
+                .background
+                .take_if(|background| background.backplate.is_none());
+        }
+
+        // Each height image is loaded once and shared; a missing one leaves surfaces flat
+        let mut height_maps = BTreeMap::new();
+        for material in self.objects.iter_mut().flat_map(Object::materials_mut) {
+            for bump in material.bumps_mut() {
+                bump.validate()?;
+                let Bump::Image {
+                    image, height_map, ..
+                } = bump
+                else {
+                    continue;
+                };
+                if !height_maps.contains_key(image) {
+                    let loaded = crate::assets::load_height_map(image, &search_dirs, options);
+                    let loaded = match loaded {
+                        Ok(map) => Some(Arc::new(map)),
+                        Err(e) => {
+                            missing(image, e)?;
+                            None
+                        }
+                    };
+                    height_maps.insert(image.clone(), loaded);
+                }
+                *height_map = height_maps[image].clone();
+            }
+        }
+
+        // A missing density file leaves the volume's constant density
+        for object in &mut self.objects {
+            if let Object::Volume {
+                density_file: Some(file),
+                density_grid,
+                ..
+            } = object
+            {
+                match crate::assets::load_density_grid(file, &search_dirs, options) {
+                    Ok(grid) => *density_grid = Some(Arc::new(grid)),
+                    Err(e) => {
+                        missing(file, e)?;
+                        *density_grid = None;
+                    }
+                }
+            }
+        }
+
+        // A missing data file leaves the glyphs out
+        for object in &mut self.objects {
+            if let Object::Glyphs {
+                filename, table, ..
+            } = object
+            {
+                match crate::assets::load_data_table(filename, &search_dirs, options) {
+                    Ok(data) => *table = Some(Arc::new(data)),
+                    Err(e) => {
+                        missing(filename, e)?;
+                        *table = None;
+                    }
+                }
             }
         }
 
@@ -513,152 +2799,225 @@ impl Scene {
 
     /// Get outline configuration from scene settings
     pub fn get_outline_config(&self) -> Result<Option<crate::outline::OutlineConfig>, String> {
-        if let Some(outline_settings) = &self.scene_settings.outline {
-            if outline_settings.enabled {
-                let edge_color = hex_to_color(&outline_settings.color)?;
-                let outline_config = crate::outline::OutlineConfig {
-                    depth_weight: outline_settings.depth_weight,
-                    normal_weight: outline_settings.normal_weight,
-                    threshold: outline_settings.threshold,
-                    edge_color,
-                    use_8_neighbors: outline_settings.use_8_neighbors,
-                    line_thickness: outline_settings.thickness,
-                };
-                Ok(Some(outline_config))
-            } else {
-                Ok(None)
+        match &self.scene_settings.outline {
+            Some(outline_settings) => self.outline_config(outline_settings),
+            None => Ok(None),
+        }
+    }
+
+    /// Outline configuration for `outline_settings` in place of the scene's own, with
+    /// colors in the scene's color space and objects found by name in the scene
+    pub fn outline_config(
+        &self,
+        outline_settings: &OutlineSettings,
+    ) -> Result<Option<crate::outline::OutlineConfig>, String> {
+        if !outline_settings.enabled {
+            return Ok(None);
+        }
+        // Outlines are drawn over the linear image, before it is encoded
+        let linear_color = |hex: &str| -> Result<Color, String> {
+            let color = hex_to_color(hex)?;
+            Ok(match self.color_space() {
+                ColorSpace::Srgb => color.map(crate::core::tonemap::srgb_to_linear),
+                ColorSpace::Linear => color,
+            })
+        };
+        let edge_color = linear_color(&outline_settings.color)?;
+        if let Some(angle) = outline_settings.crease_angle {
+            if !(angle > 0.0 && angle <= 180.0) {
+                return Err(format!(
+                    "Outline crease angle must be between 0 and 180 degrees, not {}",
+                    angle
+                ));
+            }
+        }
+        if !(1..=4).contains(&outline_settings.supersample) {
+            return Err(format!(
+                "Outline supersample must be between 1 and 4, not {}",
+                outline_settings.supersample
+            ));
+        }
+
+        // Objects are found by name, the auto floor after the scene's own
+        let floor = self.auto_floor()?;
+        let objects: Vec<&Object> = self.objects.iter().chain(&floor).collect();
+        let mut object_edge_colors = BTreeMap::new();
+        for (name, outline) in &outline_settings.objects {
+            let color = match (outline.enabled, &outline.color) {
+                (false, _) => None,
+                (true, Some(color)) => Some(linear_color(color)?),
+                (true, None) => Some(edge_color),
+            };
+            let named: Vec<usize> = (0..objects.len())
+                .filter(|&index| objects[index].name() == Some(name.as_str()))
+                .collect();
+            if named.is_empty() {
+                return Err(format!(
+                    "Outline settings name object '{}', which isn't in the scene",
+                    name
+                ));
+            }
+            object_edge_colors.extend(named.into_iter().map(|index| (index, color)));
+        }
+
+        let outline_config = crate::outline::OutlineConfig {
+            depth_weight: outline_settings.depth_weight,
+            normal_weight: outline_settings.normal_weight,
+            threshold: outline_settings.threshold,
+            edge_color,
+            use_8_neighbors: outline_settings.use_8_neighbors,
+            line_thickness: outline_settings.thickness,
+            object_weight: outline_settings.object_weight,
+            crease_angle: outline_settings.crease_angle,
+            object_edge_colors,
+            supersample: outline_settings.supersample,
+        };
+        Ok(Some(outline_config))
+    }
+
+    /// Get tone mapping from scene settings (None keeps the renderer's setting)
+    ///
+    /// Scenes in the sRGB color space are encoded to sRGB unless they pick an operator.
+    pub fn get_tone_mapping(&self) -> Result<Option<crate::core::tonemap::ToneMapping>, String> {
+        match &self.scene_settings.tone_mapping {
+            Some(settings) => Ok(Some(crate::core::tonemap::ToneMapping {
+                operator: settings.operator.parse()?,
+                exposure: settings.exposure,
+            })),
+            None if self.color_space() == ColorSpace::Srgb => {
+                Ok(Some(crate::core::tonemap::ToneMapping {
+                    operator: crate::core::tonemap::ToneMapOperator::Srgb,
+                    exposure: 0.0,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Encoding of the scene's colors and images
+    pub fn color_space(&self) -> ColorSpace {
+        self.scene_settings.color_space.unwrap_or_default()
+    }
+
+    /// Get the tilt-shift blur from scene settings
+    pub fn get_tilt_shift_config(&self) -> Option<crate::tilt_shift::TiltShiftConfig> {
+        self.scene_settings
+            .tilt_shift
+            .as_ref()
+            .map(|settings| crate::tilt_shift::TiltShiftConfig {
+                focus: settings.focus,
+                range: settings.range,
+                blur: settings.blur,
+            })
+    }
+
+    /// The camera, fitted to the finite bounds if it sets `fit` (see `AutoCamera::fit`);
+    /// scenes with no finite objects keep the camera as written
+    pub fn fitted_camera(&self) -> Result<Camera, String> {
+        self.fit_camera(&self.camera)
+    }
+
+    /// `camera` fitted to the scene's finite bounds if it sets `fit`, as `fitted_camera`
+    /// fits the scene's own
+    pub fn fit_camera(&self, camera: &Camera) -> Result<Camera, String> {
+        if camera.fit != Some(true) {
+            return Ok(camera.clone());
+        }
+        match self.compute_finite_bounds() {
+            Some(bounds) => crate::auto_camera::AutoCamera::fit(camera, bounds),
+            None => Ok(camera.clone()),
+        }
+    }
+
+    /// Names of the `cameras` picked by `selection`: a comma-separated list of names, or
+    /// "all" for every camera in name order
+    pub fn camera_names(&self, selection: &str) -> Result<Vec<String>, String> {
+        if selection.trim() == "all" {
+            if self.cameras.is_empty() {
+                return Err("Scene has no named cameras".to_string());
+            }
+            return Ok(self.cameras.keys().cloned().collect());
+        }
+        let mut names: Vec<String> = Vec::new();
+        for name in selection.split(',').map(str::trim) {
+            if !self.cameras.contains_key(name) {
+                return Err(self.unknown_camera(name));
+            }
+            if !names.iter().any(|picked| picked == name) {
+                names.push(name.to_string());
             }
-        } else {
-            Ok(None)
         }
+        Ok(names)
+    }
+
+    /// This scene seen through the camera called `name` in `cameras`
+    pub fn with_camera(&self, name: &str) -> Result<Scene, String> {
+        Ok(Scene {
+            camera: self.named_camera(name)?.clone(),
+            ..self.clone()
+        })
+    }
+
+    /// The camera called `name` in `cameras`
+    pub fn named_camera(&self, name: &str) -> Result<&Camera, String> {
+        self.cameras
+            .get(name)
+            .ok_or_else(|| self.unknown_camera(name))
+    }
+
+    fn unknown_camera(&self, name: &str) -> String {
+        match self.cameras.is_empty() {
+            true => format!("Unknown camera '{}'; the scene has no named cameras", name),
+            false => format!(
+                "Unknown camera '{}'. Valid options are: {}",
+                name,
+                self.cameras.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    /// Plane `scene_settings.auto_floor` puts under the finite objects, if the scene asks
+    /// for one and has any; renderers trace it after the scene's own objects
+    pub fn auto_floor(&self) -> Result<Option<Object>, String> {
+        let Some(floor) = &self.scene_settings.auto_floor else {
+            return Ok(None);
+        };
+        floor.validate()?;
+        let Some((min, max)) = self.compute_finite_bounds() else {
+            return Ok(None);
+        };
+        let mut material = floor.material.clone().unwrap_or_else(|| Material {
+            specular: 0.0,
+            ..Material::default()
+        });
+        if floor.shadow_catcher {
+            material.shadow_catcher = Some(true);
+        }
+        Ok(Some(Object::Plane {
+            name: Some(AutoFloor::NAME.to_string()),
+            point: [
+                (min.x + max.x) / 2.0,
+                (min.y + max.y) / 2.0,
+                min.z - floor.offset,
+            ],
+            normal: [0.0, 0.0, 1.0],
+            width: None,
+            height: None,
+            material,
+            transform: None,
+            motion: None,
+            ignore_clip: Some(true), // staging rather than part of the model
+        }))
     }
 
     /// Compute the bounding box of all finite objects in the scene
-    /// Only includes objects with finite bounds (spheres, cubes, meshes) - excludes planes
+    /// Only includes objects with finite bounds (spheres, cubes, meshes, triangles, quads, discs, volumes) - excludes planes
     pub fn compute_finite_bounds(&self) -> Option<(Point, Point)> {
         let mut min_bound: Option<Point> = None;
         let mut max_bound: Option<Point> = None;
 
         for object in &self.objects {
-            let bounds = match object {
-                Object::Sphere {
-                    center,
-                    radius,
-                    transform,
-                    ..
-                } => {
-                    let mut center_point = Point::new(center[0], center[1], center[2]);
-                    let mut effective_radius = *radius;
-
-                    // Apply transforms if present
-                    if let Some(transform_strings) = transform {
-                        if let Ok(transform_matrix) = parse_transforms(transform_strings) {
-                            // Transform the center point
-                            let center_homogeneous =
-                                transform_matrix * center_point.to_homogeneous();
-                            center_point = Point::new(
-                                center_homogeneous.x,
-                                center_homogeneous.y,
-                                center_homogeneous.z,
-                            );
-
-                            // For radius, we need to consider scaling - use the maximum scale component
-                            let scale_x = (transform_matrix.column(0).xyz().magnitude()) as f64;
-                            let scale_y = (transform_matrix.column(1).xyz().magnitude()) as f64;
-                            let scale_z = (transform_matrix.column(2).xyz().magnitude()) as f64;
-                            let max_scale = scale_x.max(scale_y).max(scale_z);
-                            effective_radius *= max_scale;
-                        }
-                    }
-
-                    let r = Vec3::new(effective_radius, effective_radius, effective_radius);
-                    Some((center_point - r, center_point + r))
-                }
-                Object::Cube {
-                    center,
-                    size,
-                    transform,
-                    ..
-                } => {
-                    let center_point = Point::new(center[0], center[1], center[2]);
-                    let cube_size = Vec3::new(size[0], size[1], size[2]);
-
-                    // Create a temporary cube to get bounds
-                    let temp_cube = if let Some(transform_strings) = transform {
-                        if let Ok(transform_matrix) = parse_transforms(transform_strings) {
-                            Cube::new_with_transform(center_point, cube_size, transform_matrix, Color::new(0.0, 0.0, 0.0), 0)
-                        } else {
-                            Cube::new(center_point, cube_size, Color::new(0.0, 0.0, 0.0), 0)
-                        }
-                    } else {
-                        Cube::new(center_point, cube_size, Color::new(0.0, 0.0, 0.0), 0)
-                    };
-
-                    Some(temp_cube.bounds())
-                }
-                Object::Mesh {
-                    mesh_data,
-                    transform,
-                    ..
-                } => {
-                    if let Some(mesh) = mesh_data {
-                        if let Some(transform_strings) = transform {
-                            if let Ok(transform_matrix) = parse_transforms(transform_strings) {
-                                // For mesh, we need to transform all vertices to compute bounds
-                                // This is a simplified approach - we transform the bounding box corners
-                                let (original_min, original_max) = mesh.bounds();
-
-                                // Get all 8 corners of the bounding box
-                                let corners = [
-                                    Point::new(original_min.x, original_min.y, original_min.z),
-                                    Point::new(original_min.x, original_min.y, original_max.z),
-                                    Point::new(original_min.x, original_max.y, original_min.z),
-                                    Point::new(original_min.x, original_max.y, original_max.z),
-                                    Point::new(original_max.x, original_min.y, original_min.z),
-                                    Point::new(original_max.x, original_min.y, original_max.z),
-                                    Point::new(original_max.x, original_max.y, original_min.z),
-                                    Point::new(original_max.x, original_max.y, original_max.z),
-                                ];
-
-                                // Transform all corners
-                                let transformed_corners: Vec<Point> = corners
-                                    .iter()
-                                    .map(|corner| {
-                                        let transformed =
-                                            transform_matrix * corner.to_homogeneous();
-                                        Point::new(transformed.x, transformed.y, transformed.z)
-                                    })
-                                    .collect();
-
-                                // Find the new min and max
-                                let mut new_min = transformed_corners[0];
-                                let mut new_max = transformed_corners[0];
-
-                                for corner in &transformed_corners[1..] {
-                                    new_min.x = new_min.x.min(corner.x);
-                                    new_min.y = new_min.y.min(corner.y);
-                                    new_min.z = new_min.z.min(corner.z);
-                                    new_max.x = new_max.x.max(corner.x);
-                                    new_max.y = new_max.y.max(corner.y);
-                                    new_max.z = new_max.z.max(corner.z);
-                                }
-
-                                Some((new_min, new_max))
-                            } else {
-                                Some(mesh.bounds())
-                            }
-                        } else {
-                            Some(mesh.bounds())
-                        }
-                    } else {
-                        None
-                    }
-                }
-                Object::Plane { .. } => {
-                    // Planes have infinite bounds, so we exclude them
-                    None
-                }
-            };
+            let bounds = self.object_bounds(object);
 
             if let Some((obj_min, obj_max)) = bounds {
                 match (&min_bound, &max_bound) {
@@ -689,4 +3048,191 @@ impl Scene {
             None
         }
     }
+
+    /// World-space bounding box of one object, or None for infinite planes and groups
+    pub fn object_bounds(&self, object: &Object) -> Option<(Point, Point)> {
+        match object {
+            Object::Sphere {
+                center,
+                radius,
+                transform,
+                ..
+            } => {
+                let center_point = Point::new(center[0], center[1], center[2]);
+                match transform.as_ref().and_then(|t| parse_transforms(t).ok()) {
+                    Some(transform_matrix) => {
+                        let color = Color::new(0.0, 0.0, 0.0);
+                        Ellipsoid::new(center_point, *radius, transform_matrix, color, 0)
+                            .map(|ellipsoid| ellipsoid.bounds())
+                    }
+                    None => {
+                        let r = Vec3::new(*radius, *radius, *radius);
+                        Some((center_point - r, center_point + r))
+                    }
+                }
+            }
+            Object::Cube {
+                center,
+                size,
+                transform,
+                ..
+            } => {
+                let center_point = Point::new(center[0], center[1], center[2]);
+                let cube_size = Vec3::new(size[0], size[1], size[2]);
+
+                // Create a temporary cube to get bounds
+                let temp_cube = if let Some(transform_strings) = transform {
+                    if let Ok(transform_matrix) = parse_transforms(transform_strings) {
+                        Cube::new_with_transform(center_point, cube_size, transform_matrix, Color::new(0.0, 0.0, 0.0), 0)
+                    } else {
+                        Cube::new(center_point, cube_size, Color::new(0.0, 0.0, 0.0), 0)
+                    }
+                } else {
+                    Cube::new(center_point, cube_size, Color::new(0.0, 0.0, 0.0), 0)
+                };
+
+                Some(temp_cube.bounds())
+            }
+            Object::Mesh {
+                mesh_data,
+                transform,
+                ..
+            } => mesh_data.as_ref().map(|mesh| {
+                match transform
+                    .as_ref()
+                    .and_then(|strings| parse_transforms(strings).ok())
+                {
+                    Some(matrix) => transform_bounds(mesh.bounds(), &matrix),
+                    None => mesh.bounds(),
+                }
+            }),
+            Object::Line {
+                points,
+                width,
+                width_unit,
+                transform,
+                ..
+            } => {
+                // Pixel widths depend on the camera, so only the centerline counts
+                let (points, half_width) = transform_line(points, *width, transform);
+                let half_width = match width_unit {
+                    LineWidthUnit::World => half_width,
+                    LineWidthUnit::Pixels => 0.0,
+                };
+                let first = points.first()?;
+                let (min, max) = (points.iter())
+                    .fold((*first, *first), |(min, max), p| (min.inf(p), max.sup(p)));
+                let pad = Vec3::repeat(half_width);
+                Some((min - pad, max + pad))
+            }
+            Object::Tube { transform, .. }
+            | Object::Arrow { transform, .. }
+            | Object::Glyphs { transform, .. } => {
+                let mesh = object.tessellated_mesh().ok().flatten()?;
+                match transform
+                    .as_ref()
+                    .and_then(|strings| parse_transforms(strings).ok())
+                {
+                    Some(matrix) => Some(transform_bounds(mesh.bounds(), &matrix)),
+                    None => Some(mesh.bounds()),
+                }
+            }
+            Object::Instance {
+                reference,
+                transform,
+                ..
+            } => self
+                .meshes
+                .get(reference)
+                .and_then(|prototype| prototype.mesh_data.as_ref())
+                .map(|mesh| {
+                    match transform
+                        .as_ref()
+                        .and_then(|strings| parse_transforms(strings).ok())
+                    {
+                        Some(matrix) => transform_bounds(mesh.bounds(), &matrix),
+                        None => mesh.bounds(),
+                    }
+                }),
+            Object::Triangle {
+                vertices,
+                transform,
+                ..
+            } => {
+                let points = transform_points(vertices, transform);
+                Some(crate::mesh::Mesh::from_polygon(&points).bounds())
+            }
+            Object::Quad {
+                vertices,
+                transform,
+                ..
+            } => {
+                let points = transform_points(vertices, transform);
+                Some(crate::mesh::Mesh::from_polygon(&points).bounds())
+            }
+            Object::Disc {
+                center,
+                normal,
+                radius,
+                transform,
+                ..
+            } => Some(
+                transform_disc(
+                    center,
+                    normal,
+                    *radius,
+                    transform,
+                    Color::new(0.0, 0.0, 0.0),
+                    0,
+                )
+                .bounds(),
+            ),
+            Object::FloorGrid {
+                point,
+                normal,
+                extent,
+                transform,
+                ..
+            } => {
+                let color = Color::new(0.0, 0.0, 0.0);
+                transform_plane(point, normal, *extent, *extent, transform, color, 0)
+                    .ok()
+                    .and_then(|plane| plane.bounds())
+            }
+            Object::Plane {
+                point,
+                normal,
+                width,
+                height,
+                transform,
+                ..
+            } => {
+                // Planes without both a width and a height are infinite, so we exclude them
+                let color = Color::new(0.0, 0.0, 0.0);
+                transform_plane(point, normal, *width, *height, transform, color, 0)
+                    .ok()
+                    .and_then(|plane| plane.bounds())
+            }
+            Object::Volume {
+                center,
+                size,
+                transform,
+                ..
+            } => {
+                // Same box as a cube with the same center, size and transform
+                let center_point = Point::new(center[0], center[1], center[2]);
+                let box_size = Vec3::new(size[0], size[1], size[2]);
+                let color = Color::new(0.0, 0.0, 0.0);
+                let cube = match transform.as_ref().and_then(|t| parse_transforms(t).ok()) {
+                    Some(matrix) => {
+                        Cube::new_with_transform(center_point, box_size, matrix, color, 0)
+                    }
+                    None => Cube::new(center_point, box_size, color, 0),
+                };
+                Some(cube.bounds())
+            }
+            // Groups are expanded by load_mesh_data
+            Object::Group { .. } => None,
+        }
+    }
 }
@@ -0,0 +1,320 @@
+//! Saving rendered frame sequences as numbered images, one looping GIF or APNG
+//! animation, or a video encoded by ffmpeg
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbImage};
+
+/// How `Renderer::render_frames` saves a sequence of frames
+#[derive(Debug, Clone, PartialEq)]
+pub enum SequenceFormat {
+    /// One image per frame, numbered before the extension (see `animation::frame_path`)
+    Frames,
+    /// One looping GIF, with each frame reduced to 256 colors
+    Gif,
+    /// One looping APNG, keeping every color
+    Apng,
+    /// Raw RGB frames piped to an ffmpeg process, which picks the container and codec
+    /// from the output's extension
+    Ffmpeg {
+        /// Executable to run, looked up on `PATH` unless it is a path
+        program: String,
+        /// Output options, placed between the piped input and the output path
+        args: Vec<String>,
+    },
+}
+
+impl SequenceFormat {
+    /// Format for an output path: `.gif` and `.apng` files are animations, video
+    /// extensions (`.mp4`, `.mov`, `.mkv`, `.webm`) go to ffmpeg and anything else
+    /// gets numbered frames
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("gif") => SequenceFormat::Gif,
+            Some("apng") => SequenceFormat::Apng,
+            Some("mp4" | "mov" | "mkv" | "webm") => SequenceFormat::ffmpeg(),
+            _ => SequenceFormat::Frames,
+        }
+    }
+
+    /// ffmpeg from `PATH` with options most players accept: 4:2:0 chroma, with odd
+    /// image sizes padded by a pixel since it needs even ones
+    pub fn ffmpeg() -> Self {
+        let args = [
+            "-pix_fmt",
+            "yuv420p",
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+        ];
+        SequenceFormat::Ffmpeg {
+            program: "ffmpeg".to_string(),
+            args: args.map(String::from).to_vec(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SequenceFormat::Frames => "frames",
+            SequenceFormat::Gif => "gif",
+            SequenceFormat::Apng => "apng",
+            SequenceFormat::Ffmpeg { .. } => "ffmpeg",
+        }
+    }
+}
+
+impl FromStr for SequenceFormat {
+    type Err = String;
+
+    /// Parse a format name; `ffmpeg` gets the options of `SequenceFormat::ffmpeg`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "frames" => Ok(SequenceFormat::Frames),
+            "gif" => Ok(SequenceFormat::Gif),
+            "apng" => Ok(SequenceFormat::Apng),
+            "ffmpeg" => Ok(SequenceFormat::ffmpeg()),
+            _ => Err(format!(
+                "Invalid sequence format '{}'. Valid options are: frames, gif, apng, ffmpeg",
+                name
+            )),
+        }
+    }
+}
+
+/// Single animation or video file written one frame at a time as frames finish rendering
+pub(crate) enum AnimationWriter {
+    Gif(GifEncoder<BufWriter<File>>, Delay),
+    Apng(png::Writer<BufWriter<File>>),
+    Ffmpeg(Child, String),
+}
+
+impl AnimationWriter {
+    /// Start a looping animation, or a video, of `frames` frames at `fps` frames per
+    /// second in `format`, which must not be `SequenceFormat::Frames`
+    pub(crate) fn create(
+        format: &SequenceFormat,
+        path: &str,
+        frames: u32,
+        (width, height): (u32, u32),
+        fps: f64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(format!("Frame rate must be greater than 0, got {}", fps).into());
+        }
+        let (program, args) = match format {
+            SequenceFormat::Frames => {
+                return Err("Numbered frames are saved one file at a time".into())
+            }
+            SequenceFormat::Gif => {
+                let file = BufWriter::new(File::create(path)?);
+                // Speed 10 of 30 trades a little palette quality for much faster encoding
+                let mut encoder = GifEncoder::new_with_speed(file, 10);
+                encoder.set_repeat(Repeat::Infinite)?;
+                let delay = Delay::from_saturating_duration(Duration::from_secs_f64(1.0 / fps));
+                return Ok(AnimationWriter::Gif(encoder, delay));
+            }
+            SequenceFormat::Apng => {
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = png::Encoder::new(file, width, height);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_animated(frames, 0)?;
+                // Delays are fractions of a second, so the rate is kept to the millisecond
+                let delay_ms = (1000.0 / fps).round().clamp(1.0, u16::MAX as f64) as u16;
+                encoder.set_frame_delay(delay_ms, 1000)?;
+                return Ok(AnimationWriter::Apng(encoder.write_header()?));
+            }
+            SequenceFormat::Ffmpeg { program, args } => (program, args),
+        };
+
+        let size = format!("{}x{}", width, height);
+        let child = Command::new(program)
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgb24",
+            ])
+            .args([
+                "-video_size",
+                &size,
+                "-framerate",
+                &fps.to_string(),
+                "-i",
+                "-",
+            ])
+            .args(args)
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Could not run '{}': {}", program, e))?;
+        Ok(AnimationWriter::Ffmpeg(child, program.clone()))
+    }
+
+    pub(crate) fn write(&mut self, image: &RgbImage) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            AnimationWriter::Gif(encoder, delay) => {
+                let rgba = image::DynamicImage::ImageRgb8(image.clone()).into_rgba8();
+                encoder.encode_frame(Frame::from_parts(rgba, 0, 0, *delay))?;
+            }
+            AnimationWriter::Apng(writer) => writer.write_image_data(image.as_raw())?,
+            AnimationWriter::Ffmpeg(child, program) => {
+                let stdin = child.stdin.as_mut().expect("ffmpeg stdin is piped");
+                if let Err(e) = stdin.write_all(image.as_raw()) {
+                    // A closed pipe means it quit early, so its exit status says more
+                    let status = child.wait()?;
+                    return Err(format!(
+                        "'{}' stopped reading frames ({}): {}",
+                        program, status, e
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            // The GIF trailer is written when the encoder is dropped
+            AnimationWriter::Gif(..) => {}
+            AnimationWriter::Apng(writer) => writer.finish()?,
+            AnimationWriter::Ffmpeg(mut child, program) => {
+                // Closing its input tells it the last frame has been sent
+                drop(child.stdin.take());
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(format!("'{}' failed ({})", program, status).into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_animations() {
+        let dir =
+            std::env::temp_dir().join(format!("rtrace-animation-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let frames = [
+            RgbImage::from_pixel(4, 3, image::Rgb([255, 0, 0])),
+            RgbImage::from_pixel(4, 3, image::Rgb([0, 0, 255])),
+        ];
+        for name in ["spin.gif", "spin.apng"] {
+            let path = dir.join(name);
+            let path = path.to_str().unwrap();
+            let format = SequenceFormat::from_path(path);
+            let mut writer = AnimationWriter::create(&format, path, 2, (4, 3), 12.5).unwrap();
+            for frame in &frames {
+                writer.write(frame).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        assert_eq!(
+            SequenceFormat::from_path("spin.png"),
+            SequenceFormat::Frames
+        );
+        assert_eq!(
+            SequenceFormat::from_path("spin.MP4"),
+            SequenceFormat::ffmpeg()
+        );
+        assert!("avi".parse::<SequenceFormat>().is_err());
+
+        let file = File::open(dir.join("spin.gif")).unwrap();
+        let decoder = image::codecs::gif::GifDecoder::new(std::io::BufReader::new(file)).unwrap();
+        let decoded = image::AnimationDecoder::into_frames(decoder)
+            .collect_frames()
+            .unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(
+            decoded[1].buffer().get_pixel(0, 0),
+            &image::Rgba([0, 0, 255, 255])
+        );
+        assert_eq!(decoded[0].delay().numer_denom_ms(), (80, 1));
+
+        let file = File::open(dir.join("spin.apng")).unwrap();
+        let reader = png::Decoder::new(file).read_info().unwrap();
+        let control = reader.info().animation_control().unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (2, 0));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ffmpeg_pipe() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stand-in for ffmpeg that saves its arguments and the frames it is sent
+        let dir = std::env::temp_dir().join(format!("rtrace-ffmpeg-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("fake-ffmpeg");
+        std::fs::write(
+            &program,
+            "#!/bin/sh\nfor last; do :; done\necho \"$@\" > \"$last.args\"\ncat > \"$last\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let format = SequenceFormat::Ffmpeg {
+            program: program.to_str().unwrap().to_string(),
+            args: vec!["-crf".to_string(), "18".to_string()],
+        };
+        let output = dir.join("spin.mp4");
+        let output = output.to_str().unwrap();
+        let mut writer = AnimationWriter::create(&format, output, 2, (4, 3), 25.0).unwrap();
+        writer
+            .write(&RgbImage::from_pixel(4, 3, image::Rgb([1, 2, 3])))
+            .unwrap();
+        writer
+            .write(&RgbImage::from_pixel(4, 3, image::Rgb([4, 5, 6])))
+            .unwrap();
+        writer.finish().unwrap();
+
+        let frames = std::fs::read(output).unwrap();
+        assert_eq!(frames.len(), 2 * 4 * 3 * 3);
+        assert_eq!((frames[0], frames[frames.len() - 1]), (1, 6));
+        let args = std::fs::read_to_string(format!("{}.args", output)).unwrap();
+        assert_eq!(
+            args.trim(),
+            format!(
+                "-y -loglevel error -f rawvideo -pix_fmt rgb24 -video_size 4x3 -framerate 25 -i - -crf 18 {}",
+                output
+            )
+        );
+
+        // A program that fails, or can't be found, is an error rather than a missing file
+        std::fs::write(&program, "#!/bin/sh\nexit 3\n").unwrap();
+        let result =
+            AnimationWriter::create(&format, output, 1, (4, 3), 25.0).and_then(|mut writer| {
+                writer.write(&RgbImage::new(4, 3))?;
+                writer.finish()
+            });
+        assert!(result.is_err());
+        let missing = SequenceFormat::Ffmpeg {
+            program: dir.join("missing").to_str().unwrap().to_string(),
+            args: Vec::new(),
+        };
+        let error = AnimationWriter::create(&missing, output, 1, (4, 3), 25.0)
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("Could not run"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,436 @@
+//! Destinations for finished renders: files, memory, writers and per-tile callbacks
+
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, ImageFormat, Rgb, Rgb32FImage, RgbImage};
+
+use crate::aov::{pass_output_path, Aov};
+
+/// One finished image of a render
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderedImage {
+    /// Tone-mapped 8-bit color
+    Rgb8(RgbImage),
+    /// Linear float radiance, for sinks whose `float_output` is true
+    Rgb32F(Rgb32FImage),
+}
+
+impl RenderedImage {
+    /// The image as 8-bit color, clamping float radiance
+    pub fn to_rgb8(&self) -> RgbImage {
+        match self {
+            RenderedImage::Rgb8(image) => image.clone(),
+            RenderedImage::Rgb32F(image) => DynamicImage::ImageRgb32F(image.clone()).to_rgb8(),
+        }
+    }
+}
+
+/// Which image of a render a sink is receiving
+#[derive(Debug, Clone, PartialEq)]
+pub enum SinkPass {
+    /// The rendered image, always delivered first
+    Image,
+    /// An AOV from `Renderer::aovs`
+    Aov(Aov),
+    /// A light group pass, by group name (see `Renderer::light_groups`)
+    LightGroup(String),
+}
+
+impl SinkPass {
+    /// Path of this pass next to the main output: `render.png` becomes `render.depth.png`
+    /// or `render.light_key.png`
+    pub fn output_path(&self, output_path: &str) -> PathBuf {
+        match self {
+            SinkPass::Image => PathBuf::from(output_path),
+            SinkPass::Aov(aov) => aov.output_path(output_path),
+            SinkPass::LightGroup(name) => pass_output_path(output_path, &format!("light_{}", name)),
+        }
+    }
+}
+
+/// Pixels of one tile, delivered as soon as the tile is traced
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilePixels {
+    /// Pixel bounds of the tile; `x1` and `y1` are exclusive
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+    /// Tone-mapped 8-bit RGB, row by row, before outlines, tilt-shift and overlays
+    pub pixels: Vec<u8>,
+}
+
+/// Receiver of a render's results, for `Renderer::render_to_sink`
+///
+/// Tiles arrive from the worker threads while the render runs, in no particular
+/// order; a tile that fails and is re-rendered arrives twice. The finished image
+/// follows, then each AOV and light group pass the renderer is set up for.
+pub trait RenderSink: Send {
+    /// Whether images should arrive as linear float rather than 8-bit color
+    fn float_output(&self) -> bool {
+        false
+    }
+
+    /// Receive a tile as soon as it is traced; ignored unless overridden
+    fn tile(&mut self, _tile: &TilePixels) {}
+
+    /// Receive one finished image
+    fn image(
+        &mut self,
+        pass: SinkPass,
+        image: RenderedImage,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Sink saving each image to a file, in the format of the path's extension
+///
+/// `.exr` (OpenEXR) and `.hdr` (Radiance) paths get linear float images. AOVs and
+/// light group passes go next to the image, named by `SinkPass::output_path`.
+#[derive(Debug, Clone)]
+pub struct FileSink {
+    pub path: String,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl RenderSink for FileSink {
+    fn float_output(&self) -> bool {
+        let extension = Path::new(&self.path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+        matches!(extension.as_deref(), Some("exr") | Some("hdr"))
+    }
+
+    fn image(
+        &mut self,
+        pass: SinkPass,
+        image: RenderedImage,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = pass.output_path(&self.path);
+        match &image {
+            RenderedImage::Rgb8(image) => image.save(&path)?,
+            RenderedImage::Rgb32F(image) => save_float_image(image, &path)?,
+        }
+        match pass {
+            SinkPass::Image => println!("Image saved to: {}", path.display()),
+            SinkPass::Aov(_) => println!("AOV saved to: {}", path.display()),
+            SinkPass::LightGroup(_) => println!("Light group saved to: {}", path.display()),
+        }
+        Ok(())
+    }
+}
+
+/// Sink keeping every image in memory, in the order they were delivered
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    /// Ask for linear float images instead of 8-bit color
+    pub float_output: bool,
+    pub images: Vec<(SinkPass, RenderedImage)>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep linear float images instead of 8-bit color
+    pub fn with_float_output(mut self) -> Self {
+        self.float_output = true;
+        self
+    }
+
+    /// The rendered image, once delivered
+    pub fn image(&self) -> Option<&RenderedImage> {
+        self.images
+            .iter()
+            .find(|(pass, _)| *pass == SinkPass::Image)
+            .map(|(_, image)| image)
+    }
+}
+
+impl RenderSink for MemorySink {
+    fn float_output(&self) -> bool {
+        self.float_output
+    }
+
+    fn image(
+        &mut self,
+        pass: SinkPass,
+        image: RenderedImage,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.images.push((pass, image));
+        Ok(())
+    }
+}
+
+/// Sink encoding the rendered image to a writer, such as a socket or a byte buffer
+///
+/// A writer holds one image, so renders with AOVs or light groups fail.
+#[derive(Debug)]
+pub struct WriterSink<W> {
+    pub writer: W,
+    pub format: ImageFormat,
+}
+
+impl<W: Write + Send> WriterSink<W> {
+    pub fn new(writer: W, format: ImageFormat) -> Self {
+        Self { writer, format }
+    }
+
+    /// Writer sink for the format a file extension such as `png` or `exr` names
+    pub fn for_extension(writer: W, extension: &str) -> Result<Self, String> {
+        match ImageFormat::from_extension(extension) {
+            Some(format) if format.can_write() => Ok(Self::new(writer, format)),
+            _ => Err(format!("Unsupported image format '{}'", extension)),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Send> RenderSink for WriterSink<W> {
+    fn float_output(&self) -> bool {
+        matches!(self.format, ImageFormat::OpenExr | ImageFormat::Hdr)
+    }
+
+    fn image(
+        &mut self,
+        pass: SinkPass,
+        image: RenderedImage,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if pass != SinkPass::Image {
+            return Err(format!("A writer holds only the image, not the {:?} pass", pass).into());
+        }
+        let image = match image {
+            RenderedImage::Rgb8(image) => DynamicImage::ImageRgb8(image),
+            RenderedImage::Rgb32F(image) => DynamicImage::ImageRgb32F(image),
+        };
+        if self.format == ImageFormat::Hdr {
+            let image = image.into_rgb32f();
+            let pixels: Vec<Rgb<f32>> = image.pixels().copied().collect();
+            let (width, height) = (image.width() as usize, image.height() as usize);
+            image::codecs::hdr::HdrEncoder::new(&mut self.writer).encode(&pixels, width, height)?;
+        } else {
+            // Some encoders seek, so the image is encoded whole before it is written
+            let mut encoded = Cursor::new(Vec::new());
+            image.write_to(&mut encoded, self.format)?;
+            self.writer.write_all(encoded.get_ref())?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Sink calling `on_tile` with each tile as it is traced, and passing finished
+/// images on to another sink
+pub struct CallbackSink<S, F> {
+    pub sink: S,
+    on_tile: F,
+}
+
+impl<S: RenderSink, F: FnMut(&TilePixels) + Send> CallbackSink<S, F> {
+    pub fn new(sink: S, on_tile: F) -> Self {
+        Self { sink, on_tile }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+impl<S: RenderSink, F: FnMut(&TilePixels) + Send> RenderSink for CallbackSink<S, F> {
+    fn float_output(&self) -> bool {
+        self.sink.float_output()
+    }
+
+    fn tile(&mut self, tile: &TilePixels) {
+        (self.on_tile)(tile);
+        self.sink.tile(tile);
+    }
+
+    fn image(
+        &mut self,
+        pass: SinkPass,
+        image: RenderedImage,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.sink.image(pass, image)
+    }
+}
+
+/// Save a float image as OpenEXR, or as Radiance HDR for `.hdr` paths
+pub(crate) fn save_float_image(
+    image: &Rgb32FImage,
+    path: impl AsRef<Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let is_hdr = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("hdr"));
+    if is_hdr {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let pixels: Vec<Rgb<f32>> = image.pixels().copied().collect();
+        image::codecs::hdr::HdrEncoder::new(file).encode(
+            &pixels,
+            image.width() as usize,
+            image.height() as usize,
+        )?;
+    } else {
+        image.save(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framing::Framing;
+    use crate::renderer::Renderer;
+    use crate::scene::Scene;
+
+    fn scene() -> Scene {
+        Scene::from_json_str(
+            r##"{
+                "camera": {"kind": "ortho", "position": [0, -5, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 4, "height": 4},
+                "objects": [{"kind": "sphere", "center": [0, 0, 0], "radius": 1,
+                    "material": {"color": "#FF8000", "ambient": 0.2, "diffuse": 0.8, "specular": 0, "shininess": 1}}],
+                "lights": [{"position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+            }"##,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sinks() {
+        let scene = scene();
+        let mut renderer = Renderer::new(40, 40);
+        renderer.tile_size = 16;
+        let expected = renderer.render(&scene).unwrap();
+
+        // Tiles arrive as they are traced, covering the image once between them
+        let mut tiles = Vec::new();
+        let mut sink = CallbackSink::new(MemorySink::new(), |tile: &TilePixels| {
+            tiles.push(tile.clone())
+        });
+        renderer.render_to_sink(&scene, &mut sink).unwrap();
+        let memory = sink.into_inner();
+        assert_eq!(memory.image(), Some(&RenderedImage::Rgb8(expected.clone())));
+        assert_eq!(tiles.len(), 9);
+        for tile in &tiles {
+            let width = tile.x1 - tile.x0;
+            let (x, y) = (tile.x0 + 1, tile.y0 + 1);
+            let offset = ((y - tile.y0) * width + (x - tile.x0)) as usize * 3;
+            assert_eq!(
+                &tile.pixels[offset..offset + 3],
+                &expected.get_pixel(x, y).0
+            );
+        }
+        let covered: u32 = tiles.iter().map(|t| (t.x1 - t.x0) * (t.y1 - t.y0)).sum();
+        assert_eq!(covered, 40 * 40);
+
+        // Float sinks get render_hdr's image, and every pass in order
+        let renderer = renderer.with_aovs(vec![Aov::Depth]).with_light_groups();
+        let mut memory = MemorySink::new().with_float_output();
+        renderer.render_to_sink(&scene, &mut memory).unwrap();
+        let passes: Vec<_> = memory.images.iter().map(|(pass, _)| pass.clone()).collect();
+        assert_eq!(
+            passes,
+            [
+                SinkPass::Image,
+                SinkPass::Aov(Aov::Depth),
+                SinkPass::LightGroup("ambient".to_string()),
+                SinkPass::LightGroup("default".to_string()),
+            ]
+        );
+        let hdr = renderer.render_hdr(&scene).unwrap();
+        assert_eq!(memory.image(), Some(&RenderedImage::Rgb32F(hdr)));
+
+        // A writer gets the encoded image alone
+        let mut writer = WriterSink::new(Vec::new(), ImageFormat::Png);
+        assert!(renderer.render_to_sink(&scene, &mut writer).is_err());
+        let renderer = Renderer::new(40, 40);
+        let mut writer = WriterSink::for_extension(Vec::new(), "png").unwrap();
+        renderer.render_to_sink(&scene, &mut writer).unwrap();
+        let decoded = image::load_from_memory(&writer.into_inner()).unwrap();
+        assert_eq!(decoded.into_rgb8(), expected);
+        assert!(WriterSink::for_extension(Vec::new(), "txt").is_err());
+    }
+
+    #[test]
+    fn test_framing() {
+        let mut scene = scene();
+        scene.scene_settings.background_color = Some("#204060".to_string());
+        let renderer = Renderer::new(40, 40);
+        let full = renderer.render(&scene).unwrap();
+
+        // The sphere covers pixels 10 to 29 each way, plus a 10% (2 pixel) margin
+        let renderer = renderer
+            .with_framing(Framing {
+                crop_to_content: true,
+                padding_percent: 10.0,
+            })
+            .with_aovs(vec![Aov::ObjectId]);
+        let mut memory = MemorySink::new();
+        renderer.render_to_sink(&scene, &mut memory).unwrap();
+        let RenderedImage::Rgb8(framed) = &memory.images[0].1 else {
+            panic!("expected an 8-bit image");
+        };
+        assert_eq!(framed.dimensions(), (24, 24));
+        assert_eq!(framed.get_pixel(0, 0), full.get_pixel(0, 0));
+        assert_eq!(framed.get_pixel(12, 12), full.get_pixel(20, 20));
+        let RenderedImage::Rgb8(ids) = &memory.images[1].1 else {
+            panic!("expected an 8-bit image");
+        };
+        assert_eq!(ids.dimensions(), (24, 24));
+        assert_eq!(ids.get_pixel(0, 0), &image::Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_camera_batch() {
+        let mut scene = scene();
+        let front = scene.camera.clone();
+        let mut side = front.clone();
+        side.position = [5.0, 0.0, 0.0];
+        side.target = [0.0, 0.0, 1.0];
+        scene.cameras.insert("front".to_string(), front);
+        scene.cameras.insert("side".to_string(), side);
+        assert_eq!(scene.camera_names("all").unwrap(), ["front", "side"]);
+        assert_eq!(
+            scene.camera_names("side, front").unwrap(),
+            ["side", "front"]
+        );
+        assert!(scene.camera_names("front,top").is_err());
+
+        // Every view matches a render of the scene through that camera alone
+        let renderer = Renderer::new(24, 24).with_aovs(vec![Aov::Depth]);
+        let mut memories = [MemorySink::new(), MemorySink::new()];
+        let [front_sink, side_sink] = &mut memories;
+        let mut views: [(&str, &mut dyn RenderSink); 2] =
+            [("side", side_sink), ("front", front_sink)];
+        renderer
+            .render_cameras_to_sinks(&scene, &mut views)
+            .unwrap();
+        for (name, memory) in ["front", "side"].into_iter().zip(&memories) {
+            let expected = renderer.render(&scene.with_camera(name).unwrap()).unwrap();
+            assert_eq!(memory.image(), Some(&RenderedImage::Rgb8(expected)));
+            assert_eq!(memory.images.len(), 2);
+        }
+        assert_ne!(memories[0].image(), memories[1].image());
+
+        // A batch naming a missing camera renders nothing
+        let mut sink = MemorySink::new();
+        let mut views: [(&str, &mut dyn RenderSink); 1] = [("top", &mut sink)];
+        assert!(renderer
+            .render_cameras_to_sinks(&scene, &mut views)
+            .is_err());
+        assert!(sink.images.is_empty());
+    }
+}
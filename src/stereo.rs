@@ -0,0 +1,235 @@
+//! Stereoscopic rendering: left and right eye views of a perspective camera
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::scene::{Camera, Scene, Vec3};
+
+/// One of the two eyes of a stereo pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Eye {
+    pub const BOTH: [Eye; 2] = [Eye::Left, Eye::Right];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Eye::Left => "left",
+            Eye::Right => "right",
+        }
+    }
+
+    /// Path of this eye's image next to the output: `render.png` becomes `render.left.png`
+    pub fn output_path(&self, output_path: &str) -> PathBuf {
+        crate::aov::pass_output_path(output_path, self.name())
+    }
+}
+
+/// How the two eye images of a stereo render are saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StereoLayout {
+    /// One image twice as wide, left eye on the left (full side-by-side)
+    #[default]
+    SideBySide,
+    /// One image per eye, e.g. `render.left.png` and `render.right.png`
+    Separate,
+}
+
+impl StereoLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StereoLayout::SideBySide => "sbs",
+            StereoLayout::Separate => "separate",
+        }
+    }
+}
+
+impl FromStr for StereoLayout {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "sbs" | "side-by-side" => Ok(StereoLayout::SideBySide),
+            "separate" => Ok(StereoLayout::Separate),
+            _ => Err(format!(
+                "Invalid stereo layout '{}'. Valid options are: sbs, separate",
+                name
+            )),
+        }
+    }
+}
+
+/// Stereo pair settings for `Renderer::render_stereo_to_file`
+///
+/// The eyes sit on either side of the scene camera along its right vector, looking
+/// the same way (parallel axes, as headsets expect), so nothing converges in front of
+/// the viewer and distant objects line up in both images.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Stereo {
+    /// Distance between the eyes in scene units; None picks 1/30 of the distance from
+    /// the camera to its target, a comfortable depth for most viewers
+    pub interpupillary_distance: Option<f64>,
+    pub layout: StereoLayout,
+}
+
+impl Stereo {
+    /// Eye separation used for `camera`
+    pub fn eye_separation(&self, camera: &Camera) -> f64 {
+        self.interpupillary_distance.unwrap_or_else(|| {
+            let distance = (Vec3::from(camera.position) - Vec3::from(camera.target)).norm();
+            distance / 30.0
+        })
+    }
+
+    /// `camera` moved half the eye separation toward `eye`
+    pub fn eye_camera(&self, camera: &Camera, eye: Eye) -> Result<Camera, String> {
+        if camera.kind != "perspective" {
+            return Err(format!(
+                "Stereo rendering needs a perspective camera, not '{}'",
+                camera.kind
+            ));
+        }
+        let separation = self.eye_separation(camera);
+        if !(separation.is_finite() && separation >= 0.0) {
+            return Err(format!(
+                "Interpupillary distance must be non-negative, got {}",
+                separation
+            ));
+        }
+
+        // Right vector of the camera, as `camera::Camera::from_config` computes it
+        let position = Vec3::from(camera.position);
+        let back = position - Vec3::from(camera.target);
+        let right = Vec3::from(camera.up).cross(&back);
+        if right.norm() == 0.0 {
+            return Err("Camera up vector must not point along the view direction".to_string());
+        }
+        let offset = match eye {
+            Eye::Left => -separation / 2.0,
+            Eye::Right => separation / 2.0,
+        };
+        let shift = right.normalize() * offset;
+        let mut eye_camera = camera.clone();
+        eye_camera.position = (position + shift).into();
+        eye_camera.target = (Vec3::from(camera.target) + shift).into();
+        Ok(eye_camera)
+    }
+
+    /// `scene` as seen from `eye`
+    pub fn eye_scene(&self, scene: &Scene, eye: Eye) -> Result<Scene, String> {
+        Ok(Scene {
+            camera: self.eye_camera(&scene.camera, eye)?,
+            ..scene.clone()
+        })
+    }
+}
+
+/// Place two images of the same size next to each other, `left` on the left
+#[cfg(feature = "image")]
+pub(crate) fn side_by_side<P>(
+    left: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+    right: &image::ImageBuffer<P, Vec<P::Subpixel>>,
+) -> Result<image::ImageBuffer<P, Vec<P::Subpixel>>, String>
+where
+    P: image::Pixel,
+{
+    if left.dimensions() != right.dimensions() {
+        return Err(format!(
+            "Stereo eye images differ in size ({:?} and {:?}); cropping to content can't be combined side by side",
+            left.dimensions(),
+            right.dimensions()
+        ));
+    }
+    let (width, height) = left.dimensions();
+    let mut combined = image::ImageBuffer::new(width * 2, height);
+    image::imageops::replace(&mut combined, left, 0, 0);
+    image::imageops::replace(&mut combined, right, width as i64, 0);
+    Ok(combined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eye_cameras() {
+        let camera = Camera {
+            kind: "perspective".to_string(),
+            position: [0.0, -30.0, 0.0],
+            target: [0.0, 0.0, 0.0],
+            up: [0.0, 0.0, 1.0],
+            ..Camera::default()
+        };
+        let stereo = Stereo::default();
+        assert_eq!(stereo.eye_separation(&camera), 1.0);
+
+        // Looking along +Y with Z up, the right vector is +X
+        let left = stereo.eye_camera(&camera, Eye::Left).unwrap();
+        let right = stereo.eye_camera(&camera, Eye::Right).unwrap();
+        assert_eq!(left.position, [-0.5, -30.0, 0.0]);
+        assert_eq!(left.target, [-0.5, 0.0, 0.0]);
+        assert_eq!(right.position, [0.5, -30.0, 0.0]);
+
+        let stereo = Stereo {
+            interpupillary_distance: Some(4.0),
+            ..stereo
+        };
+        let right = stereo.eye_camera(&camera, Eye::Right).unwrap();
+        assert_eq!(right.target, [2.0, 0.0, 0.0]);
+        assert!(stereo.eye_camera(&Camera::default(), Eye::Left).is_err());
+
+        assert_eq!(
+            "side-by-side".parse::<StereoLayout>(),
+            Ok(StereoLayout::SideBySide)
+        );
+        assert!("anaglyph".parse::<StereoLayout>().is_err());
+        assert_eq!(
+            Eye::Left.output_path("out/render.png"),
+            PathBuf::from("out/render.left.png")
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_side_by_side_render() {
+        use crate::sink::{MemorySink, RenderedImage};
+
+        let scene = Scene::from_json_str(
+            r##"{
+                "camera": {"kind": "perspective", "position": [0, -6, 0], "target": [0, 0, 0], "up": [0, 0, 1], "width": 1, "height": 1, "fov": 40},
+                "objects": [{"kind": "sphere", "center": [0, 0, 0], "radius": 1,
+                    "material": {"color": "#FF8000", "ambient": 0.2, "diffuse": 0.8, "specular": 0, "shininess": 1}}],
+                "lights": [{"position": [0, -5, 5], "color": "#FFFFFF", "intensity": 1, "diameter": null}],
+                "scene_settings": {"ambient_illumination": {"color": "#FFFFFF", "intensity": 1}, "background_color": "#000000"}
+            }"##,
+        )
+        .unwrap();
+        let renderer = crate::renderer::Renderer::new(24, 24);
+        let stereo = Stereo {
+            interpupillary_distance: Some(1.0),
+            layout: StereoLayout::SideBySide,
+        };
+        let mut memory = MemorySink::new();
+        renderer
+            .render_stereo_to_sink(&scene, &stereo, &mut memory)
+            .unwrap();
+        let Some(RenderedImage::Rgb8(combined)) = memory.image() else {
+            panic!("expected an 8-bit image");
+        };
+        assert_eq!(combined.dimensions(), (48, 24));
+
+        // Each half is that eye's own render; the sphere sits right of center for the left eye
+        let left = renderer
+            .render(&stereo.eye_scene(&scene, Eye::Left).unwrap())
+            .unwrap();
+        assert_eq!(combined.get_pixel(15, 12), left.get_pixel(15, 12));
+        assert_ne!(left.get_pixel(15, 12), left.get_pixel(8, 12));
+        let right = renderer
+            .render(&stereo.eye_scene(&scene, Eye::Right).unwrap())
+            .unwrap();
+        assert_eq!(combined.get_pixel(24 + 8, 12), right.get_pixel(8, 12));
+    }
+}
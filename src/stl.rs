@@ -0,0 +1,509 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::mesh::{Mesh, MeshLimits, Point, Triangle, Vec3};
+
+impl Mesh {
+    /// Load mesh from STL file (auto-detects binary vs ASCII)
+    pub fn from_stl_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_stl_file_with_limits(path, &MeshLimits::default())
+    }
+
+    /// Load mesh from STL file, rejecting files that exceed `limits`
+    pub fn from_stl_file_with_limits<P: AsRef<Path>>(
+        path: P,
+        limits: &MeshLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut file = File::open(&path)?;
+
+        // Devices and pipes (e.g. /dev/zero) never end, so only read regular files
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Err(format!("'{}' is not a regular file", path.as_ref().display()).into());
+        }
+        limits.check_bytes(metadata.len())?;
+
+        // Try to determine if this is ASCII or binary STL
+        let mut header = [0u8; 80];
+        file.read_exact(&mut header)?;
+
+        let header_str = String::from_utf8_lossy(&header);
+        if header_str.trim_start().starts_with("solid") {
+            // Might be ASCII, but we need to check if it's actually ASCII throughout
+            file.seek(SeekFrom::Start(0))?;
+            if Self::is_ascii_stl(&mut file)? {
+                file.seek(SeekFrom::Start(0))?;
+                return Self::load_ascii_stl(file, limits);
+            }
+        }
+
+        // Binary STL
+        file.seek(SeekFrom::Start(0))?;
+        Self::load_binary_stl(file, limits)
+    }
+
+    /// Load mesh from STL byte buffer (auto-detects binary vs ASCII)
+    pub fn from_stl_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_stl_bytes_with_limits(bytes, &MeshLimits::default())
+    }
+
+    /// Load mesh from STL byte buffer, rejecting data that exceeds `limits`
+    pub fn from_stl_bytes_with_limits(
+        bytes: &[u8],
+        limits: &MeshLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if bytes.len() < 80 {
+            return Err("STL data too short".into());
+        }
+        limits.check_bytes(bytes.len() as u64)?;
+
+        let header_str = String::from_utf8_lossy(&bytes[0..80]);
+        if header_str.trim_start().starts_with("solid") && Self::is_ascii_stl_bytes(bytes)? {
+            Self::load_ascii_stl_bytes(bytes, limits)
+        } else {
+            Self::load_binary_stl_bytes(bytes, limits)
+        }
+    }
+
+    /// Check if STL file is ASCII format by looking for ASCII markers
+    fn is_ascii_stl(file: &mut File) -> Result<bool, Box<dyn std::error::Error>> {
+        let reader = BufReader::new(file);
+        let mut line_count = 0;
+
+        for line in reader.lines() {
+            let line = line?;
+            line_count += 1;
+
+            if line_count > 10 {
+                break;
+            }
+
+            let trimmed = line.trim();
+            if trimmed.starts_with("facet normal")
+                || trimmed == "outer loop"
+                || trimmed == "endloop"
+            {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Check if STL bytes represent ASCII format
+    fn is_ascii_stl_bytes(bytes: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
+        let content = String::from_utf8_lossy(bytes);
+        let lines: Vec<&str> = content.lines().take(10).collect();
+
+        for line in lines {
+            let trimmed = line.trim();
+            if trimmed.starts_with("facet normal")
+                || trimmed == "outer loop"
+                || trimmed == "endloop"
+            {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Load ASCII STL format
+    fn load_ascii_stl(
+        mut file: File,
+        limits: &MeshLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        Self::load_ascii_stl_bytes(content.as_bytes(), limits)
+    }
+
+    /// Load ASCII STL from bytes
+    fn load_ascii_stl_bytes(
+        bytes: &[u8],
+        limits: &MeshLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = String::from_utf8_lossy(bytes);
+        let lines: Vec<&str> = content.lines().collect();
+
+        let mut mesh = Mesh::new();
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i].trim();
+
+            if line.starts_with("facet normal") {
+                // Parse normal vector
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() != 5 {
+                    return Err("Invalid facet normal format".into());
+                }
+
+                let nx: f64 = parts[2].parse()?;
+                let ny: f64 = parts[3].parse()?;
+                let nz: f64 = parts[4].parse()?;
+                let normal = Vec3::new(nx, ny, nz);
+
+                i += 1; // Skip "outer loop"
+                if i >= lines.len() || lines[i].trim() != "outer loop" {
+                    return Err("Expected 'outer loop' after facet normal".into());
+                }
+
+                // Parse three vertices
+                let mut vertices = [Point::origin(); 3];
+                #[allow(clippy::needless_range_loop)]
+                for j in 0..3 {
+                    i += 1;
+                    if i >= lines.len() {
+                        return Err("Unexpected end of file while reading vertex".into());
+                    }
+
+                    let vertex_line = lines[i].trim();
+                    if !vertex_line.starts_with("vertex") {
+                        return Err("Expected vertex line".into());
+                    }
+
+                    let parts: Vec<&str> = vertex_line.split_whitespace().collect();
+                    if parts.len() != 4 {
+                        return Err("Invalid vertex format".into());
+                    }
+
+                    let x: f64 = parts[1].parse()?;
+                    let y: f64 = parts[2].parse()?;
+                    let z: f64 = parts[3].parse()?;
+                    vertices[j] = Point::new(x, y, z);
+                }
+                Self::check_finite(&vertices)?;
+                limits.check_triangles(mesh.triangles.len() + 1)?;
+
+                i += 1; // Skip "endloop"
+                if i >= lines.len() || lines[i].trim() != "endloop" {
+                    return Err("Expected 'endloop'".into());
+                }
+
+                i += 1; // Skip "endfacet"
+                if i >= lines.len() || lines[i].trim() != "endfacet" {
+                    return Err("Expected 'endfacet'".into());
+                }
+
+                mesh.add_triangle(Triangle { vertices, normal });
+            }
+
+            i += 1;
+        }
+
+        mesh.compute_bounds();
+        mesh.build_kdtree();
+        Ok(mesh)
+    }
+
+    /// Load binary STL format
+    fn load_binary_stl(
+        mut file: File,
+        limits: &MeshLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Self::load_binary_stl_bytes(&bytes, limits)
+    }
+
+    /// Load binary STL from bytes
+    fn load_binary_stl_bytes(
+        bytes: &[u8],
+        limits: &MeshLimits,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if bytes.len() < 84 {
+            return Err("Binary STL too short".into());
+        }
+
+        // Skip 80-byte header, read triangle count
+        let triangle_count =
+            u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+        limits.check_triangles(triangle_count)?;
+
+        // Checked so a huge declared count can't wrap around and pass the size check
+        let expected_size = triangle_count
+            .checked_mul(50)
+            .and_then(|size| size.checked_add(84))
+            .ok_or("Binary STL triangle count too large")?;
+        if bytes.len() < expected_size {
+            return Err(format!(
+                "Binary STL size mismatch: expected {}, got {}",
+                expected_size,
+                bytes.len()
+            )
+            .into());
+        }
+
+        // The size check above guarantees the data really holds this many triangles
+        let mut mesh = Mesh::new();
+        mesh.triangles.reserve_exact(triangle_count);
+        mesh.attributes.reserve_exact(triangle_count);
+        let mut offset = 84;
+
+        for _ in 0..triangle_count {
+            if offset + 50 > bytes.len() {
+                return Err("Unexpected end of binary STL data".into());
+            }
+
+            // Read normal (3 * f32)
+            let nx = f32::from_le_bytes([
+                bytes[offset],
+                bytes[offset + 1],
+                bytes[offset + 2],
+                bytes[offset + 3],
+            ]) as f64;
+            let ny = f32::from_le_bytes([
+                bytes[offset + 4],
+                bytes[offset + 5],
+                bytes[offset + 6],
+                bytes[offset + 7],
+            ]) as f64;
+            let nz = f32::from_le_bytes([
+                bytes[offset + 8],
+                bytes[offset + 9],
+                bytes[offset + 10],
+                bytes[offset + 11],
+            ]) as f64;
+            let normal = Vec3::new(nx, ny, nz);
+            offset += 12;
+
+            // Read three vertices (3 * 3 * f32)
+            let mut vertices = [Point::origin(); 3];
+            #[allow(clippy::needless_range_loop)]
+            for i in 0..3 {
+                let x = f32::from_le_bytes([
+                    bytes[offset],
+                    bytes[offset + 1],
+                    bytes[offset + 2],
+                    bytes[offset + 3],
+                ]) as f64;
+                let y = f32::from_le_bytes([
+                    bytes[offset + 4],
+                    bytes[offset + 5],
+                    bytes[offset + 6],
+                    bytes[offset + 7],
+                ]) as f64;
+                let z = f32::from_le_bytes([
+                    bytes[offset + 8],
+                    bytes[offset + 9],
+                    bytes[offset + 10],
+                    bytes[offset + 11],
+                ]) as f64;
+                vertices[i] = Point::new(x, y, z);
+                offset += 12;
+            }
+
+            // 2-byte attribute, which some exporters use for per-face colors
+            let attribute = u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+            offset += 2;
+
+            Self::check_finite(&vertices)?;
+            mesh.add_triangle(Triangle { vertices, normal });
+            mesh.attributes.push(attribute);
+        }
+
+        mesh.compute_bounds();
+        mesh.build_kdtree();
+        Ok(mesh)
+    }
+
+    /// Reject NaN or infinite vertices, which would poison bounds and the k-d tree
+    ///
+    /// Stored normals are not checked: some exporters write NaN normals for degenerate
+    /// facets, and shading uses the geometric normal anyway.
+    fn check_finite(vertices: &[Point; 3]) -> Result<(), Box<dyn std::error::Error>> {
+        if vertices.iter().all(|v| v.coords.iter().all(|c| c.is_finite())) {
+            Ok(())
+        } else {
+            Err("STL contains non-finite vertex coordinates".into())
+        }
+    }
+}
+
+impl MeshLimits {
+    pub(crate) fn check_bytes(&self, len: u64) -> Result<(), Box<dyn std::error::Error>> {
+        if len > self.max_bytes {
+            return Err(format!(
+                "STL data is {} bytes, exceeding the limit of {} bytes",
+                len, self.max_bytes
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_triangles(&self, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+        if count > self.max_triangles {
+            return Err(format!(
+                "STL has {} triangles, exceeding the limit of {}",
+                count, self.max_triangles
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_detection() {
+        let ascii_content = b"solid test
+facet normal 0 0 1
+  outer loop
+    vertex -1 -1 0
+    vertex 1 -1 0
+    vertex 0 1 0
+  endloop
+endfacet
+endsolid test";
+
+        assert!(Mesh::is_ascii_stl_bytes(ascii_content).unwrap());
+    }
+
+    #[test]
+    fn test_ascii_stl_parsing() {
+        let ascii_content = b"solid test
+facet normal 0 0 1
+  outer loop
+    vertex -1 -1 0
+    vertex 1 -1 0
+    vertex 0 1 0
+  endloop
+endfacet
+facet normal 0 0 -1
+  outer loop
+    vertex 0 1 0
+    vertex 1 -1 0
+    vertex -1 -1 0
+  endloop
+endfacet
+endsolid test";
+
+        let mesh = Mesh::from_stl_bytes(ascii_content).unwrap();
+        assert_eq!(mesh.triangle_count(), 2);
+
+        // Check first triangle
+        assert_eq!(mesh.triangles[0].vertices[0], Point::new(-1.0, -1.0, 0.0));
+        assert_eq!(mesh.triangles[0].vertices[1], Point::new(1.0, -1.0, 0.0));
+        assert_eq!(mesh.triangles[0].vertices[2], Point::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.triangles[0].normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(mesh.attributes.is_empty());
+    }
+
+    #[test]
+    fn test_binary_stl_parsing() {
+        // Create a simple binary STL with one triangle
+        let mut binary_data = vec![0u8; 80]; // header
+        binary_data.extend_from_slice(&1u32.to_le_bytes()); // triangle count
+
+        // Triangle data: normal + 3 vertices + attribute
+        let normal = [0.0f32, 0.0f32, 1.0f32];
+        let vertex1 = [-1.0f32, -1.0f32, 0.0f32];
+        let vertex2 = [1.0f32, -1.0f32, 0.0f32];
+        let vertex3 = [0.0f32, 1.0f32, 0.0f32];
+        let attribute = 0x7C1Fu16;
+
+        // Add normal
+        for &f in &normal {
+            binary_data.extend_from_slice(&f.to_le_bytes());
+        }
+        // Add vertices
+        for &f in &vertex1 {
+            binary_data.extend_from_slice(&f.to_le_bytes());
+        }
+        for &f in &vertex2 {
+            binary_data.extend_from_slice(&f.to_le_bytes());
+        }
+        for &f in &vertex3 {
+            binary_data.extend_from_slice(&f.to_le_bytes());
+        }
+        // Add attribute
+        binary_data.extend_from_slice(&attribute.to_le_bytes());
+
+        let mesh = Mesh::from_stl_bytes(&binary_data).unwrap();
+        assert_eq!(mesh.triangle_count(), 1);
+
+        // Check triangle data
+        assert_eq!(mesh.triangles[0].vertices[0], Point::new(-1.0, -1.0, 0.0));
+        assert_eq!(mesh.triangles[0].vertices[1], Point::new(1.0, -1.0, 0.0));
+        assert_eq!(mesh.triangles[0].vertices[2], Point::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.triangles[0].normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.attributes, vec![0x7C1F]);
+    }
+
+    #[test]
+    fn test_stl_rejects_malformed_input() {
+        // Declared triangle count far beyond the data must fail before allocating
+        let mut binary_data = vec![0u8; 80];
+        binary_data.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Mesh::from_stl_bytes(&binary_data).is_err());
+
+        // NaN vertices are rejected; a NaN stored normal is tolerated
+        let nan_vertex = b"solid test
+facet normal 0 0 1
+  outer loop
+    vertex nan 0 0
+    vertex 1 0 0
+    vertex 0 1 0
+  endloop
+endfacet
+endsolid test";
+        assert!(Mesh::from_stl_bytes(nan_vertex).is_err());
+
+        let nan_normal = b"solid test
+facet normal nan nan nan
+  outer loop
+    vertex 0 0 0
+    vertex 1 0 0
+    vertex 0 1 0
+  endloop
+endfacet
+endsolid test";
+        assert_eq!(Mesh::from_stl_bytes(nan_normal).unwrap().triangle_count(), 1);
+    }
+
+    #[test]
+    fn test_mesh_limits() {
+        let ascii_content = b"solid test
+facet normal 0 0 1
+  outer loop
+    vertex -1 -1 0
+    vertex 1 -1 0
+    vertex 0 1 0
+  endloop
+endfacet
+facet normal 0 0 -1
+  outer loop
+    vertex 0 1 0
+    vertex 1 -1 0
+    vertex -1 -1 0
+  endloop
+endfacet
+endsolid test";
+
+        let one_triangle = MeshLimits {
+            max_triangles: 1,
+            ..MeshLimits::default()
+        };
+        let error = Mesh::from_stl_bytes_with_limits(ascii_content, &one_triangle).unwrap_err();
+        assert!(error.to_string().contains("exceeding the limit"));
+
+        let tiny = MeshLimits {
+            max_bytes: 100,
+            ..MeshLimits::default()
+        };
+        assert!(Mesh::from_stl_bytes_with_limits(ascii_content, &tiny).is_err());
+
+        // A binary header declaring more triangles than allowed fails before any parsing
+        let mut binary_data = vec![0u8; 80];
+        binary_data.extend_from_slice(&2u32.to_le_bytes());
+        binary_data.resize(84 + 2 * 50, 0);
+        assert!(Mesh::from_stl_bytes_with_limits(&binary_data, &one_triangle).is_err());
+        assert_eq!(Mesh::from_stl_bytes(&binary_data).unwrap().triangle_count(), 2);
+    }
+}
@@ -0,0 +1,269 @@
+//! Temporal accumulation: animation frames reusing the shading of the frames before them
+//!
+//! Each pixel's surface is projected into the previous frame's camera, and the color
+//! found there is blended with the pixel's own samples wherever both frames saw the
+//! same surface: at the same depth from the previous camera, facing the same way.
+//! Noise from area lights, ambient occlusion and other sampled effects then averages
+//! out over the sequence instead of being paid for again every frame. Lighting must
+//! stay still; objects that move fail the depth and normal tests, but the shadows
+//! they cast on still surfaces smear.
+
+use crate::camera::Camera;
+use crate::scene::{Color, Point, Vec3};
+
+/// Share of the bilinear weight left after rejecting earlier pixels, below which a
+/// pixel starts its history over
+const MIN_WEIGHT: f64 = 0.5;
+
+/// Settings for `Renderer::with_temporal_accumulation`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemporalConfig {
+    /// Smallest weight of a frame's own samples, so the history counts for at most
+    /// `1 / blend` frames and stale shading fades
+    pub blend: f64,
+    /// Largest difference between the two frames' depths from the previous camera, as
+    /// a share of the depth
+    pub depth_tolerance: f64,
+    /// Smallest cosine of the angle between the two frames' surface normals
+    pub normal_tolerance: f64,
+}
+
+impl Default for TemporalConfig {
+    fn default() -> Self {
+        Self {
+            blend: 0.1,
+            depth_tolerance: 0.02,
+            normal_tolerance: 0.9,
+        }
+    }
+}
+
+impl TemporalConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.blend > 0.0 && self.blend <= 1.0) {
+            return Err(format!(
+                "Temporal blend must be greater than 0 and at most 1, got {}",
+                self.blend
+            ));
+        }
+        if !(self.depth_tolerance.is_finite() && self.depth_tolerance >= 0.0) {
+            return Err(format!(
+                "Temporal depth tolerance must be 0 or more, got {}",
+                self.depth_tolerance
+            ));
+        }
+        if !(-1.0..=1.0).contains(&self.normal_tolerance) {
+            return Err(format!(
+                "Temporal normal tolerance must be between -1 and 1, got {}",
+                self.normal_tolerance
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// First surface seen through a pixel's center
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Surface {
+    pub point: Point,
+    pub normal: Vec3,
+}
+
+/// A finished frame, kept for the next one to reproject
+struct Frame {
+    camera: Camera,
+    width: u32,
+    height: u32,
+    colors: Vec<Color>,
+    surfaces: Vec<Option<Surface>>,
+    /// Frames blended into each pixel so far
+    counts: Vec<u32>,
+}
+
+/// Shading carried from frame to frame while `Renderer::render_frames` runs
+#[derive(Default)]
+pub(crate) struct TemporalHistory {
+    previous: Option<Frame>,
+}
+
+impl TemporalHistory {
+    /// Blend `colors`, a frame of `width` × `height` pixels row by row, with the
+    /// previous frame's wherever its `surfaces` were seen there too, and keep the result
+    /// for the next frame
+    pub(crate) fn accumulate(
+        &mut self,
+        camera: &Camera,
+        (width, height): (u32, u32),
+        surfaces: Vec<Option<Surface>>,
+        colors: &mut [Color],
+        config: &TemporalConfig,
+    ) {
+        let mut counts = vec![1; colors.len()];
+        if let Some(previous) = &self.previous {
+            for (index, surface) in surfaces.iter().enumerate() {
+                let Some((history, count)) = surface.and_then(|s| previous.reproject(&s, config))
+                else {
+                    continue;
+                };
+                counts[index] = count + 1;
+                let weight = (1.0 / counts[index] as f64).max(config.blend);
+                colors[index] = history.lerp(&colors[index], weight);
+            }
+        }
+        self.previous = Some(Frame {
+            camera: camera.clone(),
+            width,
+            height,
+            colors: colors.to_vec(),
+            surfaces,
+            counts,
+        });
+    }
+}
+
+impl Frame {
+    /// Color and frame count this frame had at `surface`, blended bilinearly from the
+    /// four nearest pixels that saw it too; None where too few did, or the camera
+    /// can't project it
+    fn reproject(&self, surface: &Surface, config: &TemporalConfig) -> Option<(Color, u32)> {
+        let (u, v) = self.camera.project(&surface.point)?;
+        let x = u * self.width as f64 - 0.5;
+        let y = (1.0 - v) * self.height as f64 - 0.5;
+        if !(x > -1.0 && y > -1.0 && x < self.width as f64 && y < self.height as f64) {
+            return None;
+        }
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let depth = |point: &Point| (point - self.camera.origin).dot(&self.camera.view_direction);
+        let surface_depth = depth(&surface.point);
+
+        let mut color = Color::zeros();
+        let mut count = 0.0;
+        let mut total_weight = 0.0;
+        for (dx, dy, bilinear) in [
+            (0.0, 0.0, (1.0 - fx) * (1.0 - fy)),
+            (1.0, 0.0, fx * (1.0 - fy)),
+            (0.0, 1.0, (1.0 - fx) * fy),
+            (1.0, 1.0, fx * fy),
+        ] {
+            let (px, py) = (x0 + dx, y0 + dy);
+            if px < 0.0 || py < 0.0 || px >= self.width as f64 || py >= self.height as f64 {
+                continue;
+            }
+            let index = py as usize * self.width as usize + px as usize;
+            let Some(seen) = self.surfaces[index] else {
+                continue;
+            };
+            let seen_depth = depth(&seen.point);
+            let same_depth = (surface_depth - seen_depth).abs()
+                <= config.depth_tolerance * seen_depth.abs().max(f64::EPSILON);
+            let same_facing = surface.normal.dot(&seen.normal) >= config.normal_tolerance;
+            if !(same_depth && same_facing && self.colors[index].iter().all(|c| c.is_finite())) {
+                continue;
+            }
+            color += self.colors[index] * bilinear;
+            count += self.counts[index] as f64 * bilinear;
+            total_weight += bilinear;
+        }
+
+        (total_weight >= MIN_WEIGHT)
+            .then(|| (color / total_weight, (count / total_weight).round() as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Camera as CameraConfig;
+
+    #[test]
+    fn test_accumulation_follows_surfaces() {
+        // Orthographic views straight down at a floor
+        let camera = |position: [f64; 3]| {
+            let config = CameraConfig {
+                position,
+                target: [position[0], position[1], 0.0],
+                up: [0.0, 1.0, 0.0],
+                width: 4.0,
+                height: 4.0,
+                ..CameraConfig::default()
+            };
+            Camera::from_config(&config, 1.0).unwrap()
+        };
+        let floor = |x: f64, y: f64| {
+            Some(Surface {
+                point: Point::new(x, y, 0.0),
+                normal: Vec3::z(),
+            })
+        };
+        let config = TemporalConfig::default();
+        let mut history = TemporalHistory::default();
+
+        // 2x2 pixels, each 2 units across
+        let first = camera([0.0, 0.0, 5.0]);
+        let surfaces = vec![floor(-1.0, 1.0), floor(1.0, 1.0), floor(-1.0, -1.0), None];
+        let mut colors = vec![Color::repeat(1.0); 4];
+        history.accumulate(&first, (2, 2), surfaces.clone(), &mut colors, &config);
+        assert_eq!(colors, vec![Color::repeat(1.0); 4]);
+
+        // The same view averages each frame in evenly until `blend` takes over
+        let mut colors = vec![Color::repeat(0.0); 4];
+        history.accumulate(&first, (2, 2), surfaces.clone(), &mut colors, &config);
+        assert_eq!(colors[0], Color::repeat(0.5));
+        assert_eq!(colors[1], Color::repeat(0.5));
+        // Nothing was seen through the last pixel, so it has no history
+        assert_eq!(colors[3], Color::zeros());
+        for _ in 0..20 {
+            let mut colors = vec![Color::repeat(0.0); 4];
+            history.accumulate(&first, (2, 2), surfaces.clone(), &mut colors, &config);
+        }
+        let previous = history.previous.as_ref().unwrap();
+        assert_eq!(previous.counts[0], 22);
+        let before = previous.colors[0];
+        let mut colors = vec![Color::repeat(1.0); 4];
+        history.accumulate(&first, (2, 2), surfaces.clone(), &mut colors, &config);
+        let expected = before.lerp(&Color::repeat(1.0), config.blend);
+        assert!((colors[0] - expected).norm() < 1e-12);
+
+        // A surface the last frame saw at another depth, or facing another way, starts over
+        let raised = Some(Surface {
+            point: Point::new(-1.0, 1.0, 1.0),
+            normal: Vec3::z(),
+        });
+        let turned = Some(Surface {
+            point: Point::new(1.0, 1.0, 0.0),
+            normal: Vec3::x(),
+        });
+        let mut colors = vec![Color::repeat(0.25); 4];
+        let changed = vec![raised, turned, None, None];
+        history.accumulate(&first, (2, 2), changed, &mut colors, &config);
+        assert_eq!(colors[0], Color::repeat(0.25));
+        assert_eq!(colors[1], Color::repeat(0.25));
+
+        // A moved camera finds the floor where it was: the pixel at the top left now
+        // sees the point the bottom left saw, which the last frame left alone
+        let mut history = TemporalHistory::default();
+        let mut colors = vec![Color::repeat(1.0); 4];
+        history.accumulate(&first, (2, 2), surfaces, &mut colors, &config);
+        let second = camera([0.0, -2.0, 5.0]);
+        let surfaces = vec![floor(-1.0, -1.0), floor(1.0, -1.0), floor(-1.0, -3.0), None];
+        let mut colors = vec![Color::repeat(0.0); 4];
+        history.accumulate(&second, (2, 2), surfaces, &mut colors, &config);
+        assert_eq!(colors[0], Color::repeat(0.5));
+        // Off the edge of the last frame there's nothing to reuse
+        assert_eq!(colors[2], Color::zeros());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(TemporalConfig::default().validate().is_ok());
+        let config = |blend| TemporalConfig {
+            blend,
+            ..TemporalConfig::default()
+        };
+        assert!(config(0.0).validate().is_err());
+        assert!(config(1.5).validate().is_err());
+        assert!(config(f64::NAN).validate().is_err());
+        assert!(config(1.0).validate().is_ok());
+    }
+}
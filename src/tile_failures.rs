@@ -0,0 +1,81 @@
+//! Tiles that failed during a render and whether re-rendering them helped
+
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+/// Tile that failed to render, by panicking or producing non-finite colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileFailure {
+    /// Pixel bounds of the tile; `x1` and `y1` are exclusive
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+    /// What went wrong the first time
+    pub error: String,
+    /// Whether re-rendering the tile on its own succeeded; if not, its pixels are left black
+    pub recovered: bool,
+}
+
+impl std::fmt::Display for TileFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tile ({}, {})-({}, {}) failed: {}; {}",
+            self.x0,
+            self.y0,
+            self.x1,
+            self.y1,
+            self.error,
+            if self.recovered {
+                "re-rendered"
+            } else {
+                "left black"
+            }
+        )
+    }
+}
+
+/// Failed tiles collected across renders
+///
+/// A tile that fails doesn't stop the render: the other tiles carry on, and failed
+/// ones are rendered again one by one on a single thread at the end. Clones share
+/// the same list, so keep one to read after renders using it (see
+/// `Renderer::with_tile_failures`). Failures add up across renders until `clear`.
+#[derive(Debug, Clone, Default)]
+pub struct TileFailures(Arc<Mutex<Vec<TileFailure>>>);
+
+impl TileFailures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every failure recorded so far, in the order the tiles were retried
+    pub fn failures(&self) -> Vec<TileFailure> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Forget the failures recorded so far
+    pub fn clear(&self) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    pub(crate) fn record(&self, failure: TileFailure) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(failure);
+    }
+}
+
+/// Message of a caught panic
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => format!("panicked: {}", message),
+        (_, Some(message)) => format!("panicked: {}", message),
+        _ => "panicked".to_string(),
+    }
+}
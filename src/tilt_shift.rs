@@ -0,0 +1,227 @@
+use crate::scene::Color;
+
+/// Configuration for the tilt-shift (miniature) depth blur
+///
+/// Depths are normalized per frame so 0.0 is the nearest hit and 1.0 the farthest;
+/// background pixels count as the farthest depth. Pixels inside the focus band stay
+/// sharp and the blur grows linearly to `blur` at the depth farthest from `focus`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TiltShiftConfig {
+    /// Normalized depth kept in focus
+    pub focus: f64,
+    /// Width of the sharp band centered on `focus`, in normalized depth
+    pub range: f64,
+    /// Largest blur radius, as a fraction of the image width
+    pub blur: f64,
+}
+
+impl Default for TiltShiftConfig {
+    fn default() -> Self {
+        Self {
+            focus: 0.5,
+            range: 0.2,
+            blur: 0.01,
+        }
+    }
+}
+
+impl TiltShiftConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.focus) {
+            return Err(format!(
+                "Tilt-shift focus must be between 0 and 1, got {}",
+                self.focus
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.range) {
+            return Err(format!(
+                "Tilt-shift range must be between 0 and 1, got {}",
+                self.range
+            ));
+        }
+        if !(self.blur >= 0.0 && self.blur.is_finite()) {
+            return Err(format!(
+                "Tilt-shift blur must be non-negative, got {}",
+                self.blur
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Blur the image according to each pixel's depth
+///
+/// `depths` holds the first-hit distance of every pixel, row by row (None for
+/// background). The blur is separable, horizontal then vertical, and gathers each
+/// neighbor whose own blur reaches the pixel, so out-of-focus foreground spills over
+/// sharp objects while sharp objects never smear into the blurred background.
+pub fn apply_tilt_shift(
+    image_data: &mut [(u32, u32, Color)],
+    depths: &[Option<f64>],
+    width: u32,
+    height: u32,
+    config: &TiltShiftConfig,
+) {
+    let (width, height) = (width as usize, height as usize);
+    let depths = normalized_depths(depths);
+    let radii = blur_radii(&depths, width, config);
+    let max_radius = radii.iter().fold(0.0f64, |max, &r| max.max(r));
+    if max_radius <= 0.0 {
+        return;
+    }
+
+    let mut colors = vec![Color::new(0.0, 0.0, 0.0); width * height];
+    for (x, y, color) in image_data.iter() {
+        colors[*y as usize * width + *x as usize] = *color;
+    }
+
+    let reach = max_radius.ceil() as usize;
+    let horizontal = blur_pass(&colors, &depths, &radii, width, height, reach, (1, 0));
+    let blurred = blur_pass(&horizontal, &depths, &radii, width, height, reach, (0, 1));
+
+    for (x, y, color) in image_data.iter_mut() {
+        *color = blurred[*y as usize * width + *x as usize];
+    }
+}
+
+/// Rescale depths so the nearest hit is 0.0 and the farthest (and background) is 1.0
+fn normalized_depths(depths: &[Option<f64>]) -> Vec<f64> {
+    let (near, far) = depths
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(near, far), &d| {
+            (near.min(d), far.max(d))
+        });
+    let span = (far - near).max(f64::EPSILON);
+    depths
+        .iter()
+        .map(|depth| depth.map_or(1.0, |d| ((d - near) / span).clamp(0.0, 1.0)))
+        .collect()
+}
+
+/// Blur radius in pixels for each normalized depth
+fn blur_radii(depths: &[f64], width: usize, config: &TiltShiftConfig) -> Vec<f64> {
+    let half_range = config.range / 2.0;
+    let max_radius = config.blur * width as f64;
+    // The farthest normalized depth from the focus gets the full blur
+    let ramp = (config.focus.max(1.0 - config.focus) - half_range).max(f64::EPSILON);
+    depths
+        .iter()
+        .map(|&d| {
+            let defocus = ((d - config.focus).abs() - half_range).max(0.0);
+            max_radius * (defocus / ramp).min(1.0)
+        })
+        .collect()
+}
+
+/// One direction of the separable blur; `step` is (1, 0) for rows or (0, 1) for columns
+fn blur_pass(
+    colors: &[Color],
+    depths: &[f64],
+    radii: &[f64],
+    width: usize,
+    height: usize,
+    reach: usize,
+    step: (usize, usize),
+) -> Vec<Color> {
+    let mut output = Vec::with_capacity(colors.len());
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let (position, length) = if step.0 == 1 { (x, width) } else { (y, height) };
+            let stride = if step.0 == 1 { 1 } else { width };
+
+            let first = position.saturating_sub(reach);
+            let last = (position + reach).min(length - 1);
+            let mut total = Color::new(0.0, 0.0, 0.0);
+            let mut weight_sum = 0.0;
+            for other in first..=last {
+                let neighbor = index + other * stride - position * stride;
+                let distance = position.abs_diff(other) as f64;
+                // A neighbor behind this pixel can only show through as far as this pixel is blurred
+                let radius = if depths[neighbor] > depths[index] {
+                    radii[neighbor].min(radii[index])
+                } else {
+                    radii[neighbor]
+                };
+                let weight = (radius + 1.0 - distance).clamp(0.0, 1.0);
+                if weight > 0.0 {
+                    total += colors[neighbor] * weight;
+                    weight_sum += weight;
+                }
+            }
+            output.push(total / weight_sum);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32, color: impl Fn(u32, u32) -> Color) -> Vec<(u32, u32, Color)> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| (x, y, color(x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn test_tilt_shift_keeps_focus_band_sharp() {
+        // Vertical stripes; the left half is near and in focus, the right half is far
+        let (width, height) = (40, 4);
+        let stripes = |x: u32, _| Color::repeat((x % 2) as f64);
+        let mut image_data = image(width, height, stripes);
+        let depths: Vec<Option<f64>> = (0..height)
+            .flat_map(|_| (0..width).map(|x| Some(if x < 20 { 1.0 } else { 10.0 })))
+            .collect();
+        let config = TiltShiftConfig {
+            focus: 0.0,
+            range: 0.2,
+            blur: 0.1,
+        };
+        assert!(config.validate().is_ok());
+
+        apply_tilt_shift(&mut image_data, &depths, width, height, &config);
+        for (x, y, color) in image_data {
+            if x < 20 {
+                assert_eq!(color, stripes(x, y), "in-focus pixel {} changed", x);
+            } else if x > 24 {
+                // Fully blurred stripes average to gray
+                assert!(
+                    (color.x - 0.5).abs() < 0.15,
+                    "pixel {} not blurred: {}",
+                    x,
+                    color.x
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_tilt_shift_without_blur_is_identity() {
+        let mut image_data = image(8, 8, |x, y| Color::new(x as f64, y as f64, 0.0));
+        let original = image_data.clone();
+        let depths: Vec<Option<f64>> = (0..64).map(|i| (i % 3 != 0).then_some(i as f64)).collect();
+        let config = TiltShiftConfig {
+            blur: 0.0,
+            ..TiltShiftConfig::default()
+        };
+        apply_tilt_shift(&mut image_data, &depths, 8, 8, &config);
+        assert_eq!(image_data, original);
+
+        assert!(TiltShiftConfig {
+            focus: 1.5,
+            ..config.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(TiltShiftConfig {
+            blur: -1.0,
+            ..config
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use nalgebra::{Rotation3, Vector4};
+
+use crate::mesh::{Mesh, Triangle};
+use crate::scene::{Point, TubeCurve, Vec3};
+
+/// Smallest cosine of the half angle a polyline tube is mitered through; sharper bends
+/// are beveled no further so their rings don't reach far past the corner
+const MIN_MITER_COSINE: f64 = 0.25;
+
+/// Points along the centerline of a tube through `points`, each with the tube's radius
+/// there from `radii`, which has one radius per point
+///
+/// Bezier segments are split into `steps` straight pieces, their radii following the
+/// same curve. Repeated points are dropped.
+pub fn centerline(
+    points: &[[f64; 3]],
+    radii: &[f64],
+    curve: TubeCurve,
+    steps: u32,
+) -> Result<Vec<(Point, f64)>, String> {
+    if points.len() < 2 {
+        return Err("Tube needs at least two points".to_string());
+    }
+    if radii.len() != points.len() {
+        return Err(format!(
+            "Tube has {} radii for {} points",
+            radii.len(),
+            points.len()
+        ));
+    }
+    let controls: Vec<Vector4<f64>> = (points.iter().zip(radii))
+        .map(|(p, r)| Vector4::new(p[0], p[1], p[2], *r))
+        .collect();
+    let samples = match curve {
+        TubeCurve::Polyline => controls,
+        TubeCurve::Bezier => {
+            if !(points.len() - 1).is_multiple_of(3) {
+                return Err(format!(
+                    "Bezier tube has {} points; it needs 3 per segment plus 1",
+                    points.len()
+                ));
+            }
+            if steps == 0 {
+                return Err("Bezier tube steps must be at least 1".to_string());
+            }
+            let mut samples = vec![controls[0]];
+            for segment in controls.windows(4).step_by(3) {
+                for step in 1..=steps {
+                    let u = step as f64 / steps as f64;
+                    let v = 1.0 - u;
+                    samples.push(
+                        segment[0] * (v * v * v)
+                            + segment[1] * (3.0 * v * v * u)
+                            + segment[2] * (3.0 * v * u * u)
+                            + segment[3] * (u * u * u),
+                    );
+                }
+            }
+            samples
+        }
+    };
+
+    let mut line: Vec<(Point, f64)> = Vec::with_capacity(samples.len());
+    for sample in samples {
+        let point = Point::new(sample.x, sample.y, sample.z);
+        match line.last() {
+            Some((last, _)) if (point - last).norm() <= 1e-12 => {}
+            _ => line.push((point, sample.w)),
+        }
+    }
+    if line.len() < 2 {
+        return Err("Tube points are all in the same place".to_string());
+    }
+    Ok(line)
+}
+
+/// Mesh of a tube with `sides` facets around it, swept along `centerline`
+///
+/// Each ring of vertices is turned as little as possible from the last, so the facets
+/// don't twist along the tube, and at polyline corners is stretched across the bend to
+/// meet both runs. `caps` closes the ends with flat fans.
+pub fn tube_mesh(centerline: &[(Point, f64)], sides: u32, caps: bool) -> Mesh {
+    let directions: Vec<Vec3> = centerline
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].0).normalize())
+        .collect();
+    let last = centerline.len() - 1;
+    let mut rings: Vec<Vec<Point>> = Vec::with_capacity(centerline.len());
+    let mut tangents: Vec<Vec3> = Vec::with_capacity(centerline.len());
+    let mut normal = perpendicular(&directions[0]);
+    for (i, (center, radius)) in centerline.iter().enumerate() {
+        let incoming = directions[i.saturating_sub(1)];
+        let outgoing = directions[i.min(last - 1)];
+        // A run that doubles back on itself has no bend to split
+        let tangent = (incoming + outgoing)
+            .try_normalize(1e-9)
+            .unwrap_or(outgoing);
+        if let Some(previous) = tangents.last() {
+            if let Some(rotation) = Rotation3::rotation_between(previous, &tangent) {
+                normal = rotation * normal;
+            }
+            normal = (normal - tangent * normal.dot(&tangent))
+                .try_normalize(1e-9)
+                .unwrap_or_else(|| perpendicular(&tangent));
+        }
+        let binormal = tangent.cross(&normal);
+        let stretch = 1.0 / incoming.dot(&tangent).max(MIN_MITER_COSINE) - 1.0;
+        let bend = (outgoing - incoming)
+            .try_normalize(1e-9)
+            .unwrap_or_default();
+
+        let ring = (0..sides)
+            .map(|j| {
+                let angle = std::f64::consts::TAU * j as f64 / sides as f64;
+                let offset = normal * angle.cos() + binormal * angle.sin();
+                let offset = offset + bend * (offset.dot(&bend) * stretch);
+                center + offset * *radius
+            })
+            .collect();
+        rings.push(ring);
+        tangents.push(tangent);
+    }
+
+    let mut mesh = Mesh::new();
+    let sides = sides as usize;
+    for (i, pair) in rings.windows(2).enumerate() {
+        let middle = Point::from((centerline[i].0.coords + centerline[i + 1].0.coords) / 2.0);
+        for j in 0..sides {
+            let next = (j + 1) % sides;
+            let quad = [pair[0][j], pair[1][j], pair[1][next], pair[0][next]];
+            let outward = Point::from((quad[0].coords + quad[2].coords) / 2.0) - middle;
+            add_facing(&mut mesh, [quad[0], quad[1], quad[2]], &outward);
+            add_facing(&mut mesh, [quad[0], quad[2], quad[3]], &outward);
+        }
+    }
+    if caps {
+        for (end, outward) in [(0, -tangents[0]), (last, tangents[last])] {
+            let center = centerline[end].0;
+            let ring = &rings[end];
+            for j in 0..sides {
+                add_facing(
+                    &mut mesh,
+                    [center, ring[j], ring[(j + 1) % sides]],
+                    &outward,
+                );
+            }
+        }
+    }
+    mesh.build_kdtree();
+    mesh
+}
+
+/// Add the triangle through `vertices`, wound so its normal points along `outward`;
+/// triangles with no area, as where a tube narrows to a point, are left out
+pub(crate) fn add_facing(mesh: &mut Mesh, mut vertices: [Point; 3], outward: &Vec3) {
+    let normal = (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0]));
+    let Some(mut normal) = normal.try_normalize(1e-18) else {
+        return;
+    };
+    if normal.dot(outward) < 0.0 {
+        vertices.swap(1, 2);
+        normal = -normal;
+    }
+    mesh.add_triangle(Triangle { vertices, normal });
+}
+
+/// Some unit vector perpendicular to the unit vector `direction`
+pub(crate) fn perpendicular(direction: &Vec3) -> Vec3 {
+    let axis = if direction.x.abs() < 0.9 {
+        Vec3::x()
+    } else {
+        Vec3::y()
+    };
+    direction.cross(&axis).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polyline_tube() {
+        let points = [[0.0, 0.0, 0.0], [10.0, 0.0, 0.0], [10.0, 10.0, 0.0]];
+        let line = centerline(&points, &[1.0; 3], TubeCurve::Polyline, 16).unwrap();
+        assert_eq!(line.len(), 3);
+        let mesh = tube_mesh(&line, 8, true);
+        // Two runs of 8 quads, and a fan of 8 at each end
+        assert_eq!(mesh.triangle_count(), 2 * 8 * 2 + 2 * 8);
+        let (min, max) = mesh.bounds();
+        assert!((min - Point::new(0.0, -1.0, -1.0)).norm() < 1e-9);
+        assert!((max - Point::new(11.0, 10.0, 1.0)).norm() < 1e-9);
+
+        // The side facets face away from the run they lie along
+        for triangle in &mesh.triangles[..16] {
+            let centroid = triangle.vertices.iter().map(|v| v.coords).sum::<Vec3>() / 3.0;
+            let radial = Vec3::new(0.0, centroid.y, centroid.z);
+            assert!(triangle.normal.dot(&radial) > 0.0);
+        }
+        // The corner ring is mitered: out on the diagonal, but still 1 from each run
+        let corner = line[1].0;
+        let reach = (mesh.triangles.iter())
+            .flat_map(|triangle| triangle.vertices)
+            .filter(|v| (v.z).abs() < 1e-9 && (v - corner).norm() < 2.0)
+            .map(|v| (v - corner).norm())
+            .fold(0.0, f64::max);
+        assert!((reach - 2f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bezier_tube() {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [0.0, 0.0, 5.0],
+            [10.0, 0.0, 5.0],
+            [10.0, 0.0, 0.0],
+        ];
+        let line = centerline(&points, &[2.0, 2.0, 1.0, 0.0], TubeCurve::Bezier, 4).unwrap();
+        assert_eq!(line.len(), 5);
+        assert_eq!(line[0], (Point::origin(), 2.0));
+        assert!((line[2].0 - Point::new(5.0, 0.0, 3.75)).norm() < 1e-9);
+        assert!((line[4].1).abs() < 1e-12);
+
+        // Narrowing to a point leaves no cap, nor facets of no area at the tip
+        let mesh = tube_mesh(&line, 6, true);
+        assert_eq!(mesh.triangle_count(), 4 * 6 * 2 - 6 + 6);
+        assert!(mesh
+            .triangles
+            .iter()
+            .all(|t| t.normal.iter().all(|n| n.is_finite())));
+
+        assert!(centerline(&points[..3], &[1.0; 3], TubeCurve::Bezier, 4).is_err());
+        assert!(centerline(&points, &[1.0; 3], TubeCurve::Polyline, 4).is_err());
+        let same = [[1.0, 2.0, 3.0]; 2];
+        assert!(centerline(&same, &[1.0; 2], TubeCurve::Polyline, 4).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! X-ray (ghosted) views: every surface along a ray drawn partly see-through
+
+use std::str::FromStr;
+
+use crate::scene::Color;
+
+/// Settings for the X-ray view, where each primary ray peels the surfaces it passes
+/// through front to back and blends them, so parts hidden inside others show through
+#[derive(Debug, Clone, PartialEq)]
+pub struct XRayConfig {
+    /// Opacity of each surface from the front, 0 to 1; the last repeats for deeper
+    /// ones. A closed shell is two surfaces, where the ray enters and leaves it.
+    pub opacities: Vec<f64>,
+    /// Most surfaces blended per ray; the background shows behind them
+    pub max_layers: usize,
+}
+
+impl Default for XRayConfig {
+    fn default() -> Self {
+        Self {
+            opacities: vec![0.25],
+            max_layers: 16,
+        }
+    }
+}
+
+impl XRayConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.opacities.is_empty() {
+            return Err("X-ray needs at least one layer opacity".to_string());
+        }
+        if let Some(opacity) = (self.opacities.iter()).find(|o| !(0.0..=1.0).contains(*o)) {
+            return Err(format!(
+                "X-ray layer opacities must be between 0 and 1, got {}",
+                opacity
+            ));
+        }
+        if self.max_layers == 0 {
+            return Err("X-ray must blend at least one layer".to_string());
+        }
+        Ok(())
+    }
+
+    /// Opacity of the `layer`th surface along a ray, counting from 0 at the front
+    pub fn opacity(&self, layer: usize) -> f64 {
+        let last = self.opacities.len().saturating_sub(1);
+        self.opacities.get(layer.min(last)).copied().unwrap_or(1.0)
+    }
+
+    /// Blend the colors of the surfaces along a ray, front first, over `background`
+    ///
+    /// Stops early once the layers in front let nothing through.
+    pub fn composite(&self, layers: impl IntoIterator<Item = Color>, background: Color) -> Color {
+        let mut color = Color::zeros();
+        let mut transmittance = 1.0;
+        for (layer, layer_color) in layers.into_iter().take(self.max_layers).enumerate() {
+            let opacity = self.opacity(layer);
+            color += layer_color * (transmittance * opacity);
+            transmittance *= 1.0 - opacity;
+            if transmittance <= 0.0 {
+                return color;
+            }
+        }
+        color + background * transmittance
+    }
+}
+
+impl FromStr for XRayConfig {
+    type Err = String;
+
+    /// Comma-separated layer opacities, e.g. `0.3,1` to ghost the nearest surface and
+    /// draw whatever is behind it solid
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let opacities = text
+            .split(',')
+            .map(|value| {
+                value.trim().parse::<f64>().map_err(|_| {
+                    format!(
+                        "Invalid X-ray opacity '{}'; expected numbers like 0.3,1",
+                        value
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let config = XRayConfig {
+            opacities,
+            ..XRayConfig::default()
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite() {
+        let config: XRayConfig = "0.5, 0.5, 1".parse().unwrap();
+        assert_eq!(config.opacity(0), 0.5);
+        assert_eq!(config.opacity(7), 1.0);
+
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        // Two half-opaque red layers, then an opaque blue one hiding the background
+        let color = config.composite([red, red, blue, red], white);
+        assert!((color - Color::new(0.75, 0.0, 0.25)).norm() < 1e-12);
+        // Without the blue layer, a quarter of the background shows through
+        let color = config.composite([red, red], white);
+        assert!((color - Color::new(1.0, 0.25, 0.25)).norm() < 1e-12);
+
+        let config = XRayConfig {
+            max_layers: 1,
+            ..config
+        };
+        assert!((config.composite([red, blue], white) - Color::new(1.0, 0.5, 0.5)).norm() < 1e-12);
+
+        assert!("0.5,2".parse::<XRayConfig>().is_err());
+        assert!("half".parse::<XRayConfig>().is_err());
+    }
+}